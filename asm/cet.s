@@ -0,0 +1,44 @@
+.intel_syntax noprefix
+.global read_ssp
+.global shstk_return_check
+
+.text
+# ============================================================================
+# CET Shadow-Stack Probes
+# ============================================================================
+#
+# PURPOSE:
+# RDSSPQ lives in the hint-NOP space: with shadow stacks disabled it leaves
+# its destination untouched, so pre-zeroing RAX yields 0. With shadow
+# stacks enabled it returns the shadow-stack pointer, whose top entry is
+# the return address the CPU will check on our RET.
+#
+# Comparing that entry against [RSP] catches a return address rewritten
+# after the CALL (ROP-style hook trampolines, return-address patching
+# instrumentation) before the RET would fault with SEGV_CPERR.
+#
+# ============================================================================
+
+# uint64_t read_ssp()
+# Returns the shadow-stack pointer, or 0 if shadow stacks are inactive.
+read_ssp:
+    xor eax, eax
+    rdsspq rax
+    ret
+
+# uint64_t shstk_return_check()
+# Returns 0 if the shadow-stack top matches our return address, 1 if it
+# does not, 2 if shadow stacks are inactive.
+shstk_return_check:
+    xor eax, eax
+    rdsspq rax
+    test rax, rax
+    jz 1f
+    mov rcx, qword ptr [rax]
+    xor eax, eax
+    cmp rcx, qword ptr [rsp]
+    setne al
+    ret
+1:
+    mov eax, 2
+    ret
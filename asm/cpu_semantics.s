@@ -0,0 +1,185 @@
+.intel_syntax noprefix
+.global cpu_semantics_probe
+.global popcnt_flags_probe
+
+.text
+# ============================================================================
+# CPU Semantics Edge Cases
+# ============================================================================
+#
+# PURPOSE:
+# Each slot records one architectural corner case whose result real
+# silicon fixes exactly. Emulation engines implement the common path of
+# every instruction and get these wrong first. The Rust layer compares the
+# slots against the expected values.
+#
+# SLOTS (uint64_t out[15]):
+#  0  CS selector                      0x33 (Linux __USER_CS)
+#  1  SS selector                      0x2b (Linux __USER_DS)
+#  2  fs:[0] points to itself          1    (x86-64 TLS ABI)
+#  3  ES/CS/SS/DS prefixed loads       1    (bases ignored in 64-bit mode)
+#  4  FS-prefixed LEA                  8    (LEA never adds a segment base)
+#  5  RFLAGS & 0x802a                  0x2  (bit 1 reads 1, 3/5/15 read 0)
+#  6  CF after STC; INC                1    (INC leaves CF alone)
+#  7  SHL by CL=64                     3    (count masked to 0: value and CF kept)
+#  8  BSF with zero source             0x1234 (destination left unchanged)
+#  9  BT reg, 65 on 2                  1    (register bit offset taken mod 64)
+# 10  failed CMPXCHG                   5 | 5 << 32 (RAX loaded, memory kept)
+# 11  MXCSR flags after denormal + 0   0x2  (DE only, result exact)
+# 12  SQRTSS(-1)                       0xffc00000 (x86 default NaN is negative)
+# 13  CVTTSS2SI(NaN)                   0x80000000 (integer indefinite)
+# 14  x87 FSQRT(-1) as double          0xfff8000000000000 (real indefinite)
+#
+# MXCSR and the x87 status word are restored / cleared before returning.
+# Only the red zone is used as scratch memory.
+#
+# ============================================================================
+
+# void cpu_semantics_probe(uint64_t *out)
+cpu_semantics_probe:
+    # 0, 1: selectors
+    xor eax, eax
+    mov ax, cs
+    mov [rdi], rax
+    xor eax, eax
+    mov ax, ss
+    mov [rdi + 8], rax
+
+    # 2: TLS self pointer
+    mov rax, qword ptr fs:0
+    xor ecx, ecx
+    cmp rax, [rax]
+    sete cl
+    mov [rdi + 16], rcx
+
+    # 3: ES / CS / SS / DS overrides (emitted as raw prefixes so the
+    # assembler cannot drop them as redundant)
+    mov r8, 0x5a5aa5a5c3c33c3c
+    mov [rsp - 8], r8
+    mov ecx, 1
+    .byte 0x26
+    mov r9, [rsp - 8]
+    cmp r9, r8
+    jne 1f
+    .byte 0x2e
+    mov r9, [rsp - 8]
+    cmp r9, r8
+    jne 1f
+    .byte 0x36
+    mov r9, [rsp - 8]
+    cmp r9, r8
+    jne 1f
+    .byte 0x3e
+    mov r9, [rsp - 8]
+    cmp r9, r8
+    je 2f
+1:
+    xor ecx, ecx
+2:
+    mov [rdi + 24], rcx
+
+    # 4: FS-prefixed LEA
+    xor edx, edx
+    .byte 0x64
+    lea rax, [rdx + 8]
+    mov [rdi + 32], rax
+
+    # 5: reserved RFLAGS bits
+    pushfq
+    pop rax
+    and eax, 0x802a
+    mov [rdi + 40], rax
+
+    # 6: INC preserves CF
+    xor eax, eax
+    stc
+    inc rax
+    mov ecx, 0
+    setc cl
+    mov [rdi + 48], rcx
+
+    # 7: SHL by a count that masks to zero
+    mov eax, 0x1234
+    mov ecx, 64
+    stc
+    shl rax, cl
+    mov edx, 0
+    setc dl
+    xor r8d, r8d
+    cmp rax, 0x1234
+    sete r8b
+    shl r8, 1
+    or rdx, r8
+    mov [rdi + 56], rdx
+
+    # 8: BSF of zero
+    mov edx, 0x1234
+    xor eax, eax
+    bsf rdx, rax
+    mov [rdi + 64], rdx
+
+    # 9: BT with a register offset past the operand size
+    mov eax, 2
+    mov ecx, 65
+    bt rax, rcx
+    mov edx, 0
+    setc dl
+    mov [rdi + 72], rdx
+
+    # 10: failed CMPXCHG
+    mov qword ptr [rsp - 8], 5
+    mov eax, 1
+    mov ecx, 9
+    lock cmpxchg [rsp - 8], rcx
+    mov rdx, [rsp - 8]
+    shl rdx, 32
+    or rax, rdx
+    mov [rdi + 80], rax
+
+    # 11: denormal-operand flag, with DAZ/FTZ off and all flags clear
+    stmxcsr dword ptr [rsp - 16]
+    mov eax, dword ptr [rsp - 16]
+    and eax, 0xffff7f80
+    mov dword ptr [rsp - 12], eax
+    ldmxcsr dword ptr [rsp - 12]
+    mov eax, 1
+    movd xmm0, eax
+    xorps xmm1, xmm1
+    addss xmm0, xmm1
+    stmxcsr dword ptr [rsp - 12]
+    mov eax, dword ptr [rsp - 12]
+    and eax, 0x3f
+    mov [rdi + 88], rax
+
+    # 12, 13: SSE default NaN and integer indefinite
+    mov eax, 0xbf800000
+    movd xmm0, eax
+    sqrtss xmm0, xmm0
+    movd eax, xmm0
+    mov [rdi + 96], rax
+    cvttss2si eax, xmm0
+    mov [rdi + 104], rax
+    ldmxcsr dword ptr [rsp - 16]
+
+    # 14: x87 real indefinite
+    fld1
+    fchs
+    fsqrt
+    fstp qword ptr [rsp - 8]
+    fnclex
+    mov rax, [rsp - 8]
+    mov [rdi + 112], rax
+    ret
+
+# uint64_t popcnt_flags_probe()
+# Returns the arithmetic flags left by POPCNT of a non-zero value after
+# they were all set. Hardware clears every one of them: 0.
+popcnt_flags_probe:
+    mov rax, -1
+    add rax, 1
+    mov ecx, 3
+    popcnt rax, rcx
+    pushfq
+    pop rax
+    and eax, 0x8d5
+    ret
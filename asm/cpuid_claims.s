@@ -0,0 +1,83 @@
+.intel_syntax noprefix
+.global isa_probe_baseline
+.global isa_probe_avx2
+.global isa_probe_bmi2
+.global isa_probe_rdseed
+.global isa_probe_sha
+.global isa_probe_landing
+
+.text
+# ============================================================================
+# CPUID-Claimed Instruction Probes
+# ============================================================================
+#
+# PURPOSE:
+# Each probe executes one representative instruction of a feature that
+# CPUID advertises and returns a result the Rust layer checks against a
+# reference implementation. All probes are leaf functions that never touch
+# the stack, so the SIGILL handler can resume any of them at
+# `isa_probe_landing`, which simply returns 0.
+#
+# - AVX2    VPBROADCASTD / VPADDD / VEXTRACTI128 (upper 128-bit lane)
+# - BMI2    PDEP
+# - RDSEED  RDSEED with bounded retry
+# - SHA     SHA1MSG1
+#
+# Environments that spoof CPUID but trap-and-emulate these either fault,
+# compute a wrong result, or take thousands of cycles per call.
+#
+# ============================================================================
+
+# uint64_t isa_probe_baseline()
+# Empty call used to subtract call overhead from probe latency.
+isa_probe_baseline:
+    xor eax, eax
+    ret
+
+# uint64_t isa_probe_avx2(uint32_t x)
+# Returns 2 * x as computed in the upper lane of a YMM register.
+isa_probe_avx2:
+    vmovd xmm0, edi
+    vpbroadcastd ymm0, xmm0
+    vpaddd ymm0, ymm0, ymm0
+    vextracti128 xmm1, ymm0, 1
+    vmovd eax, xmm1
+    vzeroupper
+    ret
+
+# uint64_t isa_probe_bmi2(uint64_t src, uint64_t mask)
+isa_probe_bmi2:
+    pdep rax, rdi, rsi
+    ret
+
+# uint64_t isa_probe_rdseed(uint64_t *out)
+# Returns 1 and stores the seed on success, 0 if RDSEED kept underflowing.
+isa_probe_rdseed:
+    mov ecx, 64
+1:
+    rdseed rax
+    jc 2f
+    pause
+    dec ecx
+    jnz 1b
+    xor eax, eax
+    ret
+2:
+    mov qword ptr [rdi], rax
+    mov eax, 1
+    ret
+
+# uint64_t isa_probe_sha(const uint32_t *a, const uint32_t *b, uint32_t *out)
+# out = SHA1MSG1(a, b), 4 dwords each.
+isa_probe_sha:
+    movdqu xmm0, xmmword ptr [rdi]
+    movdqu xmm1, xmmword ptr [rsi]
+    sha1msg1 xmm0, xmm1
+    movdqu xmmword ptr [rdx], xmm0
+    mov eax, 1
+    ret
+
+# Resume point for faulting probes
+isa_probe_landing:
+    xor eax, eax
+    ret
@@ -0,0 +1,50 @@
+.intel_syntax noprefix
+.global dbi_read_return_address
+.global dbi_call_pop_rip
+.global dbi_rip_anchor
+.global dbi_x87_last_ip
+.global dbi_x87_anchor
+
+.text
+# ============================================================================
+# DBI Code-Cache Probes
+# ============================================================================
+#
+# PURPOSE:
+# Pin and DynamoRIO never run our instructions where we linked them: each
+# basic block is translated into a code cache and executed from there.
+# They translate the obvious views back (CALL pushes the original return
+# address, RIP-relative LEA yields the original address), so these probes
+# give each view in turn and let the Rust side compare them with the
+# addresses the linker assigned.
+#
+# The x87 last-instruction pointer is the one view a translator rarely
+# fixes up: FXSAVE64 stores the RIP at which the last x87 instruction
+# actually executed - inside the code cache under DBI.
+#
+# ============================================================================
+
+# uint64_t dbi_read_return_address()
+# Returns the return address the caller's CALL pushed.
+dbi_read_return_address:
+    mov rax, qword ptr [rsp]
+    ret
+
+# uint64_t dbi_call_pop_rip()
+# Returns the address of dbi_rip_anchor as observed through CALL/POP.
+dbi_call_pop_rip:
+    call dbi_rip_anchor
+dbi_rip_anchor:
+    pop rax
+    ret
+
+# uint64_t dbi_x87_last_ip(void *fxsave_area)
+# Executes FLDZ at dbi_x87_anchor, then FXSAVE64 into the 512-byte,
+# 16-byte aligned area at RDI. Returns the saved FPU instruction pointer.
+dbi_x87_last_ip:
+dbi_x87_anchor:
+    fldz
+    fxsave64 [rdi]
+    fstp st(0)
+    mov rax, qword ptr [rdi + 8]
+    ret
@@ -0,0 +1,67 @@
+.intel_syntax noprefix
+.global x87_extended_residual
+.global get_mxcsr
+.global set_mxcsr
+.global x87_control_word_roundtrip
+
+.data
+.align 8
+x87_tiny:
+    .quad 0x3c30000000000000     # 2^-60 as a double
+
+.text
+# ============================================================================
+# x87 / SSE Control Probes
+# ============================================================================
+#
+# PURPOSE:
+# Real x87 hardware computes in 80-bit extended precision (64-bit mantissa)
+# under Linux's default control word. (1 + 2^-60) - 1 is therefore exactly
+# 2^-60. Emulators that model x87 with host doubles (53-bit mantissa, e.g.
+# Valgrind) lose the small term and return 0.
+#
+# x87_control_word_roundtrip exposes the reserved control-word bits: on
+# every 387-class FPU bit 6 reads back as 1 and bits 7 and 13-15 as 0,
+# whatever FLDCW loaded. Emulators that store the word verbatim return it
+# unchanged.
+#
+# The MXCSR accessors let the Rust layer toggle FTZ/DAZ around single
+# operations without relying on the deprecated _mm_getcsr/_mm_setcsr.
+#
+# ============================================================================
+
+# double x87_extended_residual()
+# Returns (1 + 2^-60) - 1 computed entirely on the x87 stack.
+x87_extended_residual:
+    fld1
+    fld qword ptr [rip + x87_tiny]
+    faddp st(1), st              # st0 = 1 + 2^-60 (80-bit)
+    fld1
+    fsubp st(1), st              # st0 = st1 - st0 = 2^-60
+    fstp qword ptr [rsp - 8]
+    movsd xmm0, qword ptr [rsp - 8]
+    ret
+
+# uint32_t get_mxcsr()
+get_mxcsr:
+    stmxcsr dword ptr [rsp - 4]
+    mov eax, dword ptr [rsp - 4]
+    ret
+
+# void set_mxcsr(uint32_t value)
+set_mxcsr:
+    mov dword ptr [rsp - 4], edi
+    ldmxcsr dword ptr [rsp - 4]
+    ret
+
+# uint16_t x87_control_word_roundtrip(uint16_t value)
+# Loads `value` with FLDCW, returns what FNSTCW reads back, and restores the
+# original control word.
+x87_control_word_roundtrip:
+    fnstcw word ptr [rsp - 2]
+    mov word ptr [rsp - 4], di
+    fldcw word ptr [rsp - 4]
+    fnstcw word ptr [rsp - 6]
+    fldcw word ptr [rsp - 2]
+    movzx eax, word ptr [rsp - 6]
+    ret
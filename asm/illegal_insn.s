@@ -0,0 +1,49 @@
+.intel_syntax noprefix
+.global probe_ud2
+.global probe_ud1
+.global probe_lock_nop
+.global probe_overlong
+
+.text
+# ============================================================================
+# Illegal-Instruction Semantics Probes
+# ============================================================================
+#
+# PURPOSE:
+# Each probe executes one instruction that real hardware refuses to run.
+# The Rust SIGILL/SIGSEGV handler records what the kernel reported (signal,
+# si_code, si_addr, saved RIP) and resumes at `<probe> + <length>`.
+#
+# EXPECTED (native Linux x86_64):
+# - ud2        0F 0B       #UD -> SIGILL/ILL_ILLOPN, si_addr = RIP = probe
+# - ud1        0F B9 C0    #UD -> SIGILL/ILL_ILLOPN (ModRM consumed, 3 bytes)
+# - lock nop   F0 90       #UD -> SIGILL/ILL_ILLOPN (LOCK on non-lockable op)
+# - overlong   66 x15 90   #GP -> SIGSEGV/SI_KERNEL, si_addr = 0 (>15 bytes)
+#
+# Emulators commonly ignore LOCK on NOP, accept overlong instructions, or
+# report the fault at the next instruction instead of the faulting one.
+#
+# ============================================================================
+
+# void probe_ud2()
+probe_ud2:
+    ud2
+    ret
+
+# void probe_ud1()
+probe_ud1:
+    .byte 0x0f, 0xb9, 0xc0
+    ret
+
+# void probe_lock_nop()
+probe_lock_nop:
+    .byte 0xf0, 0x90
+    ret
+
+# void probe_overlong()
+probe_overlong:
+    .rept 15
+    .byte 0x66
+    .endr
+    nop
+    ret
@@ -0,0 +1,27 @@
+.intel_syntax noprefix
+.global raw_syscall4
+
+.text
+# ============================================================================
+# Raw SYSCALL Without libc
+# ============================================================================
+#
+# PURPOSE:
+# Issues a system call with the SYSCALL instruction directly, so neither a
+# preloaded `syscall()` nor any other interposed libc symbol sits between
+# us and the kernel. Used to cross-check what libc wrappers return.
+#
+# RETURNS:
+# The kernel's result unchanged: -errno on failure, no errno is set.
+#
+# ============================================================================
+
+# int64_t raw_syscall4(int64_t nr, int64_t a1, int64_t a2, int64_t a3, int64_t a4)
+raw_syscall4:
+    mov rax, rdi
+    mov rdi, rsi
+    mov rsi, rdx
+    mov rdx, rcx
+    mov r10, r8
+    syscall
+    ret
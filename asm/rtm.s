@@ -0,0 +1,81 @@
+.intel_syntax noprefix
+.global rtm_commit_probe
+.global rtm_int3_probe
+.global rtm_xabort_probe
+.global rtm_touch_probe
+
+.text
+# ============================================================================
+# TSX/RTM Transaction Probes
+# ============================================================================
+#
+# PURPOSE:
+# Inside an RTM transaction every exception - #BP from INT3, #DB from a
+# single-step or hardware breakpoint - is suppressed: the transaction
+# aborts, execution resumes at the fallback label and EAX holds the abort
+# status. No signal is ever delivered, so a debugger never sees the event,
+# but the DEBUG status bit (bit 4) tells us one would have fired.
+#
+# Each probe returns 0xFFFFFFFF if the transaction committed, otherwise
+# the abort status:
+#   bit 0  XABORT executed (code in bits 31:24)
+#   bit 1  may succeed on retry
+#   bit 2  memory conflict
+#   bit 3  buffer overflow (capacity)
+#   bit 4  debug breakpoint hit
+#   bit 5  abort in nested transaction
+#
+# Callers must check CPUID.(7,0):EBX.RTM first - XBEGIN is #UD otherwise.
+#
+# ============================================================================
+
+# uint32_t rtm_commit_probe()
+# A trivial transaction that should commit on real hardware.
+rtm_commit_probe:
+    mov eax, 0xffffffff
+    xbegin 1f
+    xor ecx, ecx
+    add ecx, 1
+    add ecx, 1
+    add ecx, 1
+    xend
+1:
+    ret
+
+# uint32_t rtm_int3_probe()
+# INT3 inside a transaction: hardware aborts with status 0, no SIGTRAP.
+rtm_int3_probe:
+    mov eax, 0xffffffff
+    xbegin 1f
+    int3
+    xend
+1:
+    ret
+
+# uint32_t rtm_xabort_probe()
+# Explicit abort: hardware reports bit 0 with code 0x5a in bits 31:24.
+rtm_xabort_probe:
+    mov eax, 0xffffffff
+    xbegin 1f
+    xabort 0x5a
+    xend
+1:
+    ret
+
+# uint32_t rtm_touch_probe(const uint8_t *ptr, size_t len)
+# Reads `len` bytes inside a transaction. A data/execute breakpoint on the
+# range aborts with the DEBUG bit instead of stopping the debugger.
+rtm_touch_probe:
+    mov eax, 0xffffffff
+    xbegin 2f
+    xor ecx, ecx
+1:
+    cmp rcx, rsi
+    jae 3f
+    movzx edx, byte ptr [rdi + rcx]
+    inc rcx
+    jmp 1b
+3:
+    xend
+2:
+    ret
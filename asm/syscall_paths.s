@@ -0,0 +1,40 @@
+.intel_syntax noprefix
+.global syscall64_getppid
+.global int80_getppid
+.global syscall_path_landing
+
+.text
+# ============================================================================
+# SYSCALL vs INT 0x80 Entry Paths
+# ============================================================================
+#
+# PURPOSE:
+# The same cheap, never-vDSO'd syscall (getppid) issued through the 64-bit
+# SYSCALL instruction and through the legacy INT 0x80 gate. Natively the
+# two cost within a small factor of each other. Interposition layers
+# (ptrace, seccomp user-notify, gVisor) often handle one path specially
+# or not at all, which shows up as a skewed ratio or a different result.
+#
+# NOTE: INT 0x80 uses the i386 syscall table (getppid = 64) and returns a
+# 32-bit result. On kernels without IA32 emulation it may raise #GP; the
+# Rust SIGSEGV handler then resumes at `syscall_path_landing`.
+#
+# ============================================================================
+
+# int64_t syscall64_getppid()
+syscall64_getppid:
+    mov eax, 110
+    syscall
+    ret
+
+# int64_t int80_getppid()
+int80_getppid:
+    mov eax, 64
+    int 0x80
+    movsxd rax, eax
+    ret
+
+# Resume point when INT 0x80 faults
+syscall_path_landing:
+    mov rax, -1
+    ret
@@ -0,0 +1,37 @@
+.intel_syntax noprefix
+.global calibrated_work_loop
+
+.text
+# ============================================================================
+# Calibrated Work Loop
+# ============================================================================
+#
+# PURPOSE:
+# A loop with a fixed, known instruction count and a short loop-carried
+# dependency chain, so its cost per iteration is predictable on real
+# hardware regardless of compiler or build profile.
+#
+# COST:
+# - 4 instructions per iteration
+# - Dependency chain rax -> rdx -> rax: ~2 cycles/iteration natively
+# - Emulators/translators: typically 10-100x that in host time
+#
+# ============================================================================
+
+# uint64_t calibrated_work_loop(uint64_t iterations)
+# RDI = iteration count. Returns the accumulator (prevents elimination).
+calibrated_work_loop:
+    mov rcx, rdi
+    xor rax, rax
+    mov rdx, 1
+    test rcx, rcx
+    jz .work_done
+    
+.work_loop:
+    add rax, rdx
+    xor rdx, rax
+    dec rcx
+    jnz .work_loop
+    
+.work_done:
+    ret
@@ -0,0 +1,54 @@
+.intel_syntax noprefix
+.global read_xcr0
+.global ymm_signal_roundtrip
+.global clobber_ymm15
+
+.text
+# ============================================================================
+# Extended State (XSAVE) Probes
+# ============================================================================
+#
+# PURPOSE:
+# Verify that the extended register state the CPU advertises is actually
+# enabled by the OS and faithfully saved/restored across a signal.
+#
+# THEORY:
+# - XCR0 (read via XGETBV) lists state components the OS has enabled
+# - On signal delivery the kernel XSAVEs user state into the signal frame
+#   and XRSTORs it on sigreturn, so registers clobbered by the handler
+#   come back intact
+# - Emulators frequently advertise AVX state they do not save/restore
+#
+# ============================================================================
+
+# uint64_t read_xcr0()
+# Returns XCR0. Caller MUST check CPUID.1:ECX.OSXSAVE first (#UD otherwise).
+read_xcr0:
+    xor ecx, ecx
+    xgetbv
+    shl rdx, 32
+    or rax, rdx
+    ret
+
+# void ymm_signal_roundtrip(const uint64_t pattern[4], uint64_t out[4],
+#                           int tgid, int tid, int sig)
+# Loads YMM15 from `pattern`, sends `sig` to this thread with a raw tgkill
+# (no libc code runs between load and signal), then stores YMM15 to `out`.
+# The signal is delivered on return from the syscall.
+ymm_signal_roundtrip:
+    vmovdqu ymm15, [rdi]
+    mov r9, rsi          # out pointer (syscall preserves r9)
+    mov rdi, rdx         # tgid
+    mov rsi, rcx         # tid
+    mov rdx, r8          # sig
+    mov eax, 234         # __NR_tgkill
+    syscall
+    vmovdqu [r9], ymm15
+    vzeroupper
+    ret
+
+# void clobber_ymm15()
+# Zeroes all of YMM15 (VEX-encoded op clears the upper lane).
+clobber_ymm15:
+    vpxor xmm15, xmm15, xmm15
+    ret
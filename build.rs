@@ -1,17 +1,205 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
 fn main() {
-    cc::Build::new()
-        .file("asm/rdtsc.s")
-        .file("asm/scan_int3.s")
-        .file("asm/trap_flag.s")
-        .file("asm/regs.s")
-        .file("asm/debug_regs.s")
-        .file("asm/micro_timing.s")
-        .compile("antidebug_asm");
+    let salt = build_salt();
+    compile_asm(salt);
     
-    println!("cargo:rerun-if-changed=asm/rdtsc.s");
-    println!("cargo:rerun-if-changed=asm/scan_int3.s");
-    println!("cargo:rerun-if-changed=asm/trap_flag.s");
-    println!("cargo:rerun-if-changed=asm/regs.s");
-    println!("cargo:rerun-if-changed=asm/debug_regs.s");
-    println!("cargo:rerun-if-changed=asm/micro_timing.s");
+    // Keep the bait symbols in .dynsym, where `strip` leaves them visible
+    for symbol in ["check_license", "decrypt_key"] {
+        println!("cargo:rustc-link-arg-bins=-Wl,--export-dynamic-symbol={}", symbol);
+    }
+    
+    write_diag_table(salt);
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-env-changed=ANTIDEBUG_DIAG_TABLE");
+}
+
+const ASM_SOURCES: &[&str] = &[
+    "asm/rdtsc.s",
+    "asm/scan_int3.s",
+    "asm/trap_flag.s",
+    "asm/regs.s",
+    "asm/debug_regs.s",
+    "asm/micro_timing.s",
+    "asm/work_loop.s",
+    "asm/xstate.s",
+    "asm/illegal_insn.s",
+    "asm/fpu.s",
+    "asm/cpuid_claims.s",
+    "asm/rtm.s",
+    "asm/cet.s",
+    "asm/syscall_paths.s",
+    "asm/dbi.s",
+    "asm/cpu_semantics.s",
+    "asm/raw_syscall.s",
+];
+
+/// Most NOP bytes placed in front of each assembly detector
+const MAX_ASM_PADDING: u64 = 256;
+
+/// Same finalizer as `engine::salt::mix64`
+fn mix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// `ANTIDEBUG_BUILD_SALT` (hex) if set, otherwise fresh from the OS.
+/// Written to OUT_DIR as the `BUILD_SALT` constant `engine::salt` includes.
+fn build_salt() -> u64 {
+    println!("cargo:rerun-if-env-changed=ANTIDEBUG_BUILD_SALT");
+    let salt = match std::env::var("ANTIDEBUG_BUILD_SALT") {
+        Ok(hex) => u64::from_str_radix(hex.trim_start_matches("0x"), 16).expect("ANTIDEBUG_BUILD_SALT is hex"),
+        Err(_) => {
+            let mut bytes = [0u8; 8];
+            fs::File::open("/dev/urandom")
+                .and_then(|mut f| std::io::Read::read_exact(&mut f, &mut bytes))
+                .expect("read /dev/urandom");
+            u64::from_le_bytes(bytes)
+        }
+    };
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    fs::write(out_dir.join("build_salt.rs"), format!("/// Salt of this build\npub const BUILD_SALT: u64 = {:#018x};\n", salt))
+        .expect("write build_salt.rs");
+    salt
+}
+
+/// Compile the assembly detectors in a salted order, each behind a salted
+/// run of NOPs, so their offsets in `.text` differ from build to build
+fn compile_asm(salt: u64) {
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    let manifest = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is set by cargo"));
+
+    // Fisher-Yates driven by the salt
+    let mut order: Vec<&str> = ASM_SOURCES.to_vec();
+    let mut state = salt;
+    for i in (1..order.len()).rev() {
+        state = mix64(state);
+        order.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+
+    let mut build = cc::Build::new();
+    for source in order {
+        state = mix64(state);
+        let padding = state % (MAX_ASM_PADDING + 1);
+        let stem = Path::new(source).file_stem().and_then(|s| s.to_str()).expect("asm file name");
+        let wrapper = out_dir.join(format!("salted_{}.s", stem));
+        let mut text = String::from(".text\n");
+        if padding > 0 {
+            text.push_str(&format!(".skip {}, 0x90\n", padding));
+        }
+        text.push_str(&format!(".include \"{}\"\n", manifest.join(source).display()));
+        fs::write(&wrapper, text).expect("write salted asm wrapper");
+        build.file(wrapper);
+        println!("cargo:rerun-if-changed={}", source);
+    }
+    build.compile("antidebug_asm");
+}
+
+/// Same code as `engine::salt::salt_code(engine::log::diag_code(file, line))`
+fn diag_code(salt: u64, file: &str, line: u32) -> u32 {
+    let code = file.bytes().chain(line.to_le_bytes())
+        .fold(0x811c_9dc5u32, |hash, b| (hash ^ b as u32).wrapping_mul(0x0100_0193));
+    mix64(salt ^ code as u64) as u32
+}
+
+fn rust_sources(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            rust_sources(&path, out);
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            out.push(path);
+        }
+    }
+}
+
+/// First string literal at or after `from`, unescaped only as far as the table needs
+fn format_string(text: &str, from: usize) -> Option<String> {
+    let start = from + text[from..].find('"')? + 1;
+    let mut out = String::new();
+    let mut chars = text[start..].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(out),
+            '\\' => match chars.next()? {
+                'n' => out.push_str("\\n"),
+                't' => out.push_str("\\t"),
+                other => out.push(other),
+            },
+            '\n' => out.push(' '),
+            _ => out.push(c),
+        }
+    }
+    None
+}
+
+/// Write the stealth-build code table: `code<TAB>file:line<TAB>format` for
+/// `diag!` (D), `reason!` (R) and `described!` (`#<line>`).
+/// Always goes to OUT_DIR; `ANTIDEBUG_DIAG_TABLE` adds a copy elsewhere.
+fn write_diag_table(salt: u64) {
+    let mut sources = Vec::new();
+    rust_sources(Path::new("src"), &mut sources);
+    sources.sort();
+
+    let mut table = String::new();
+    for path in &sources {
+        let Ok(text) = fs::read_to_string(path) else { continue };
+        // Matches `file!()` in the crate: relative to the manifest directory
+        let file = path.to_string_lossy().replace('\\', "/");
+        let mut offset = 0;
+        for (index, line) in text.split_inclusive('\n').enumerate() {
+            let code_part = line.split("//").next().unwrap_or("");
+            let line_no = index as u32 + 1;
+            for (kind, call) in [('D', "diag!("), ('R', "reason!(")] {
+                if let Some(col) = code_part.find(call) {
+                    if let Some(fmt) = format_string(&text, offset + col) {
+                        table.push_str(&format!("{}{:08x}\t{}:{}\t{}\n", kind, diag_code(salt, &file, line_no), file, line_no, fmt));
+                    }
+                }
+            }
+            if let Some(col) = code_part.find("described!(") {
+                if let Some(description) = format_string(&text, offset + col) {
+                    table.push_str(&format!("#{}\t{}:{}\t{}\n", line_no, file, line_no, description));
+                }
+            }
+            offset += line.len();
+        }
+    }
+    write_builtin_rules(&mut table);
+
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    fs::write(out_dir.join("diag_codes.tsv"), &table).expect("write diag_codes.tsv");
+    if let Ok(extra) = std::env::var("ANTIDEBUG_DIAG_TABLE") {
+        fs::write(&extra, &table).expect("write ANTIDEBUG_DIAG_TABLE");
+    }
+}
+
+/// Stealth copy of `BUILTIN_RULES` (`src/engine/rules.rs`): comments
+/// dropped and each description replaced by `#<line>`, which the code table
+/// lists like a `described!` text
+fn write_builtin_rules(table: &mut String) {
+    const FILE: &str = "src/engine/rules.rs";
+    let text = fs::read_to_string(FILE).expect("read src/engine/rules.rs");
+    let start = text.find("pub const BUILTIN_RULES").expect("BUILTIN_RULES in rules.rs");
+    let first_line = text[..start].lines().count() + 2;
+
+    let mut rules = String::new();
+    for (index, line) in text[start..].lines().skip(1).enumerate() {
+        if line.starts_with("\"#") {
+            break;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some(quote) = line.find(" \"") {
+            let line_no = first_line + index;
+            rules.push_str(&format!("{} \"#{}\"\n", &line[..quote], line_no));
+            table.push_str(&format!("#{}\t{}:{}\t{}\n", line_no, FILE, line_no, line[quote + 1..].trim_matches('"')));
+        }
+    }
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    fs::write(out_dir.join("builtin_rules.txt"), rules).expect("write builtin_rules.txt");
 }
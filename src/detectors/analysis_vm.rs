@@ -0,0 +1,254 @@
+//! Whole-System Analysis Platform Detection (PANDA, DECAF)
+//!
+//! # Overview
+//!
+//! PANDA and DECAF run the whole guest under QEMU's TCG and, in PANDA's
+//! case, record it for later replay. Neither looks like the KVM or Hyper-V
+//! guests production runs on, and three traits together set them apart:
+//!
+//! | Signal           | Production VM                  | Analysis platform              |
+//! |------------------|--------------------------------|--------------------------------|
+//! | Disk latency     | Real device behind virtio      | Emulated device, fixed cost    |
+//! | RTC              | Ticks with system time         | Frozen or from the recording   |
+//! | CPUID branding   | `KVMKVMKVM`, `Microsoft Hv`, … | `TCGTCGTCGTCG`, QEMU CPU brand |
+//!
+//! - **Device jitter**: small writes followed by `fdatasync` reach a
+//!   physical device on any production host, and their latencies spread.
+//!   An emulated device completes them on QEMU's virtual clock, almost
+//!   uniformly
+//! - **RTC drift**: `/sys/class/rtc/rtc0/since_epoch` must advance once per
+//!   second and stay near `CLOCK_REALTIME` (modulo a time zone)
+//! - **Branding**: the hypervisor leaf 0x40000000 and the processor brand
+//!   string
+//!
+//! Each trait is common enough alone; the composite only reports when the
+//! branding is not a production hypervisor's or when several traits agree.
+//! Network latency is not probed - measuring it means sending traffic.
+//!
+//! # Why This Fails
+//!
+//! - The emulated CPU model and the hypervisor leaf are configurable
+//! - Without an RTC device (containers, microVMs) that signal is absent
+//! - A write-back cache that ignores flushes makes real disks uniform too
+//! - Temporary directories on tmpfs are skipped, which can leave no disk probe
+
+use std::ffi::CString;
+use std::fs;
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use core::arch::x86_64::{CpuidResult, __cpuid};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Synced writes timed for the disk-jitter probe
+const DISK_SAMPLES: usize = 16;
+
+/// Coefficient of variation below which device latency is "too uniform"
+const UNIFORM_CV: f64 = 0.02;
+
+/// How long the RTC may take to tick once
+const RTC_TICK_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Distance from a whole quarter hour (time zone offsets) the RTC may drift
+const RTC_SLACK_SECS: u64 = 120;
+
+/// Hypervisor signatures of production hypervisors
+const PRODUCTION_VENDORS: &[&str] = &["KVMKVMKVM", "Microsoft Hv", "VMwareVMware", "XenVMMXenVMM", "VBoxVBoxVBox", "ACRNACRNACRN", "bhyve bhyve", " lrpepyh vr", "QNXQVMBSQG", "EVMMEVMMEVMM"];
+
+/// Signatures and brand-string fragments of QEMU's software CPU
+const EMULATOR_BRANDS: &[&str] = &["TCGTCGTCGTCG", "QEMU Virtual CPU", "QEMU TCG CPU"];
+
+/// Where `since_epoch` of the first RTC lives
+const RTC_PATH: &str = "/sys/class/rtc/rtc0/since_epoch";
+
+/// `TMPFS_MAGIC` / `RAMFS_MAGIC`
+const MEMORY_FS: &[i64] = &[0x0102_1994, 0x8584_58f6];
+
+/// What the CPUID branding says about the hypervisor
+#[derive(Debug, Clone, PartialEq)]
+pub enum Branding {
+    Bare,
+    Production(String),
+    Emulator(String),
+    Unknown(String),
+}
+
+/// Classify a hypervisor signature and processor brand string
+pub fn classify_branding(hv_bit: bool, vendor: &str, brand: &str) -> Branding {
+    if let Some(sig) = EMULATOR_BRANDS.iter().find(|sig| vendor.contains(*sig) || brand.contains(*sig)) {
+        return Branding::Emulator(sig.to_string());
+    }
+    if !hv_bit {
+        return Branding::Bare;
+    }
+    match PRODUCTION_VENDORS.iter().find(|sig| vendor.starts_with(*sig)) {
+        Some(sig) => Branding::Production(sig.trim().to_string()),
+        None => Branding::Unknown(vendor.to_string()),
+    }
+}
+
+/// Coefficient of variation of a set of samples
+pub fn coefficient_of_variation(samples: &[f64]) -> f64 {
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    if mean <= 0.0 {
+        return 0.0;
+    }
+    let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+    variance.sqrt() / mean
+}
+
+/// Whether an RTC reading plausibly shows the same time as the system clock
+pub fn rtc_matches_system(rtc: u64, system: u64) -> bool {
+    let offset = rtc.abs_diff(system) % 900;
+    offset <= RTC_SLACK_SECS || 900 - offset <= RTC_SLACK_SECS
+}
+
+fn registers_text(regs: &[u32]) -> String {
+    let bytes: Vec<u8> = regs.iter().flat_map(|r| r.to_le_bytes()).collect();
+    String::from_utf8_lossy(&bytes).trim_matches(|c: char| c == '\0' || c == ' ').to_string()
+}
+
+#[allow(unused_unsafe)] // `__cpuid` is only safe on newer toolchains
+fn cpuid(leaf: u32) -> CpuidResult {
+    unsafe { __cpuid(leaf) }
+}
+
+fn cpuid_branding() -> Branding {
+    let (leaf1, hv, ext) = (cpuid(1), cpuid(0x4000_0000), cpuid(0x8000_0000));
+    let vendor = registers_text(&[hv.ebx, hv.ecx, hv.edx]);
+    let brand = if ext.eax >= 0x8000_0004 {
+        let leaves: Vec<u32> = (0x8000_0002..=0x8000_0004u32)
+            .flat_map(|leaf| {
+                let r = cpuid(leaf);
+                [r.eax, r.ebx, r.ecx, r.edx]
+            })
+            .collect();
+        registers_text(&leaves)
+    } else {
+        String::new()
+    };
+    classify_branding(leaf1.ecx & (1 << 31) != 0, &vendor, &brand)
+}
+
+/// A disk-backed directory to time synced writes in
+fn disk_directory() -> Option<PathBuf> {
+    [PathBuf::from("/var/tmp"), std::env::temp_dir()].into_iter().find(|dir| {
+        let Ok(path) = CString::new(dir.as_os_str().as_encoded_bytes()) else { return false };
+        // SAFETY: statfs fills a zeroed struct from a NUL-terminated path
+        let mut fs: libc::statfs = unsafe { std::mem::zeroed() };
+        unsafe { libc::statfs(path.as_ptr(), &mut fs) == 0 && !MEMORY_FS.contains(&(fs.f_type as i64)) }
+    })
+}
+
+/// Latencies (ns) of small synced writes, if a disk-backed file is writable
+fn synced_write_latencies() -> Option<Vec<f64>> {
+    let path = disk_directory()?.join(format!(".adf-io-{}", std::process::id()));
+    let mut file = fs::File::create(&path).ok()?;
+    let _ = fs::remove_file(&path);
+    let block = [0x5au8; 512];
+    (0..DISK_SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            file.write_all(&block).ok()?;
+            // SAFETY: fdatasync on a file descriptor we own
+            (unsafe { libc::fdatasync(file.as_raw_fd()) } == 0).then(|| start.elapsed().as_nanos() as f64)
+        })
+        .collect()
+}
+
+fn read_rtc() -> Option<u64> {
+    fs::read_to_string(RTC_PATH).ok()?.trim().parse().ok()
+}
+
+fn system_seconds() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// RTC anomaly, if the RTC exists and either does not tick or shows
+/// another time than the system clock
+fn rtc_anomaly() -> Option<String> {
+    let first = read_rtc()?;
+    let start = Instant::now();
+    while start.elapsed() < RTC_TICK_TIMEOUT {
+        std::thread::sleep(Duration::from_millis(50));
+        let now = read_rtc()?;
+        if now != first {
+            let system = system_seconds();
+            return (!rtc_matches_system(now, system))
+                .then(|| reason!("RTC reads {} while the system clock reads {}", now, system));
+        }
+    }
+    Some(reason!("RTC frozen at {} for {} ms", first, RTC_TICK_TIMEOUT.as_millis()))
+}
+
+/// Main entry point for the analysis-platform check
+pub fn check_analysis_vm(engine: &mut DecisionEngine) {
+    let mut traits: Vec<(String, u32)> = Vec::new();
+
+    let branding = cpuid_branding();
+    match &branding {
+        Branding::Emulator(sig) => traits.push((reason!("CPUID branded as QEMU's software CPU ({})", sig), 30)),
+        Branding::Unknown(vendor) => traits.push((reason!("unrecognised hypervisor signature {:?}", vendor), 10)),
+        Branding::Bare | Branding::Production(_) => {}
+    }
+
+    match synced_write_latencies() {
+        Some(latencies) => {
+            let cv = coefficient_of_variation(&latencies);
+            engine.record_feature("disk_latency_cv", cv);
+            diag!("[ANALYSIS_VM] synced write cv={:.4} over {} samples", cv, latencies.len());
+            if cv < UNIFORM_CV {
+                traits.push((reason!("synced disk writes vary by only {:.2}% (emulated device clock)", cv * 100.0), 20));
+            }
+        }
+        None => engine.record_diagnostic("analysis_vm", &reason!("no disk-backed directory for the latency probe")),
+    }
+
+    let rtc = rtc_anomaly();
+    if let Some(anomaly) = &rtc {
+        traits.push((anomaly.clone(), 25));
+    }
+    diag!("[ANALYSIS_VM] branding={:?} rtc={:?} traits={}", branding, rtc, traits.len());
+
+    // A production hypervisor needs every other trait to agree
+    let needed = if matches!(branding, Branding::Production(_)) { 3 } else { 2 };
+    let emulator = matches!(branding, Branding::Emulator(_));
+    engine.record_feature("analysis_vm_traits", traits.len() as f64);
+    if traits.is_empty() || (traits.len() < needed && !emulator) {
+        return;
+    }
+    let weight = traits.iter().map(|(_, w)| w).sum::<u32>().min(60);
+    let confidence = (0.35 + 0.2 * traits.len() as f64).min(0.95);
+    let details: Vec<&str> = traits.iter().map(|(d, _)| d.as_str()).collect();
+    engine.report_with_confidence(
+        DetectionSource::Emulation,
+        weight,
+        confidence,
+        &reason!("Whole-system analysis platform (PANDA/DECAF-class): {}", details.join("; "))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_branding() {
+        assert_eq!(classify_branding(false, "", "Intel(R) Xeon(R) Platinum"), Branding::Bare);
+        assert_eq!(classify_branding(true, "KVMKVMKVM", "Intel(R) Xeon(R)"), Branding::Production("KVMKVMKVM".to_string()));
+        assert_eq!(classify_branding(true, "TCGTCGTCGTCG", ""), Branding::Emulator("TCGTCGTCGTCG".to_string()));
+        assert_eq!(classify_branding(false, "", "QEMU Virtual CPU version 2.5+"), Branding::Emulator("QEMU Virtual CPU".to_string()));
+        assert_eq!(classify_branding(true, "", ""), Branding::Unknown(String::new()));
+    }
+
+    #[test]
+    fn test_latency_and_rtc_helpers() {
+        assert_eq!(coefficient_of_variation(&[100.0, 100.0, 100.0]), 0.0);
+        assert!(coefficient_of_variation(&[50.0, 150.0]) > 0.4);
+        assert!(rtc_matches_system(1_700_000_001, 1_700_000_000));
+        assert!(rtc_matches_system(1_700_003_600, 1_700_000_000));
+        assert!(!rtc_matches_system(1_700_000_450, 1_700_000_000));
+    }
+}
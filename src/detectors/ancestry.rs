@@ -0,0 +1,197 @@
+//! Ancestor Process-Chain Analysis
+//!
+//! # Overview
+//!
+//! Analysis tools start their target as a child: `gdb ./app`,
+//! `rr record ./app`, `strace -f ./app`, `valgrind ./app`, a Python script
+//! driving us through pwntools. Looking only at the direct parent misses
+//! every tool that goes through a shell or a wrapper script, so we follow
+//! the `PPid` chain up to init and classify each ancestor by its `comm`
+//! and `argv[0]`:
+//!
+//! | Ancestor                                  | Source           | Weight |
+//! |-------------------------------------------|------------------|--------|
+//! | rr                                        | RecordReplay     | 50     |
+//! | Debugger or debug stub                    | Ptrace           | 40     |
+//! | strace / ltrace / uftrace                 | Ptrace           | 35     |
+//! | Valgrind, DynamoRIO, Pin                  | Dbi              | 40     |
+//! | qemu user-mode                            | Emulation        | 40     |
+//! | Frida CLI                                 | Instrumentation  | 40     |
+//! | Python with an analysis module in argv    | Instrumentation  | 25     |
+//! | Plain Python interpreter                  | Instrumentation  | 10     |
+//! | perf                                      | SamplingProfiler | 15     |
+//! | Namespace sandbox (bwrap, nsjail, ...)    | Sandbox          | 10     |
+//! | CI runner                                 | Sandbox          | 5      |
+//!
+//! Weights are for the direct parent; a grandparent counts three quarters
+//! and anything further up half, since a long chain leaves more room for
+//! an innocent explanation (a debugger running a shell running us).
+//!
+//! # Why This Fails
+//!
+//! - `gdb -p` and other tools that attach later are not our ancestors
+//! - Renamed tool binaries and double-forking launchers that reparent us to
+//!   init or a subreaper break the chain
+//! - With `hidepid` mounted, other users' processes cannot be read and the
+//!   walk stops early
+
+use std::fs;
+use crate::detectors::launch_env::is_debugger;
+use crate::detectors::remote_debug::is_debug_stub;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Ancestors followed before giving up (PID namespaces, loops)
+const MAX_DEPTH: usize = 32;
+
+/// Modules and tools a Python harness names on its command line
+const PYTHON_HARNESS: &[&str] = &["pwn", "pwntools", "angr", "frida", "qiling", "unicorn", "triton", "pexpect", "lief"];
+
+/// A tool recognised by its process name
+struct Signature {
+    names: &'static [&'static str],
+    what: &'static str,
+    source: DetectionSource,
+    weight: u32,
+    confidence: f64,
+}
+
+const SIGNATURES: &[Signature] = &[
+    Signature { names: &["rr"], what: described!("rr record/replay"), source: DetectionSource::RecordReplay, weight: 50, confidence: 0.9 },
+    Signature { names: &["strace", "ltrace", "uftrace"], what: "tracer", source: DetectionSource::Ptrace, weight: 35, confidence: 0.8 },
+    // Valgrind execs its tool, whose comm is cut to 15 characters
+    Signature { names: &["valgrind", "memcheck-amd64-", "callgrind-amd64", "drrun", "pin"], what: described!("DBI framework"), source: DetectionSource::Dbi, weight: 40, confidence: 0.85 },
+    Signature { names: &["qemu-x86_64", "qemu-i386", "qemu-x86_64-static"], what: described!("user-mode emulator"), source: DetectionSource::Emulation, weight: 40, confidence: 0.8 },
+    Signature { names: &["frida", "frida-trace"], what: "Frida", source: DetectionSource::Instrumentation, weight: 40, confidence: 0.85 },
+    Signature { names: &["perf"], what: "perf", source: DetectionSource::SamplingProfiler, weight: 15, confidence: 0.5 },
+    Signature { names: &["bwrap", "firejail", "nsjail", "minijail0"], what: described!("namespace sandbox"), source: DetectionSource::Sandbox, weight: 10, confidence: 0.4 },
+    Signature { names: &["Runner.Worker", "Runner.Listener", "gitlab-runner", "buildkite-agent"], what: described!("CI runner"), source: DetectionSource::Sandbox, weight: 5, confidence: 0.3 },
+];
+
+/// One process above us
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ancestor {
+    pub pid: u32,
+    pub depth: usize,
+    pub comm: String,
+    pub argv: Vec<String>,
+}
+
+/// What an ancestor is, as (description, source, weight, confidence) for a
+/// direct parent
+pub fn classify_ancestor(comm: &str, argv: &[String]) -> Option<(String, DetectionSource, u32, f64)> {
+    let comm = comm.trim();
+    let argv0 = argv.first().map_or("", |a| a.rsplit('/').next().unwrap_or(a));
+    let named = |name: &str| comm == name || argv0 == name;
+
+    if is_debugger(comm) || is_debugger(argv0) || is_debug_stub(comm) {
+        return Some((format!("debugger {}", comm), DetectionSource::Ptrace, 40, 0.85));
+    }
+    if let Some(sig) = SIGNATURES.iter().find(|sig| sig.names.iter().any(|n| named(n))) {
+        return Some((format!("{} {}", sig.what, comm), sig.source, sig.weight, sig.confidence));
+    }
+    if comm.starts_with("python") || argv0.starts_with("python") {
+        let harness = argv.iter().skip(1)
+            .flat_map(|a| a.split(|c: char| !c.is_ascii_alphanumeric() && c != '_'))
+            .find(|word| PYTHON_HARNESS.contains(word));
+        return Some(match harness {
+            Some(module) => (reason!("Python harness using {} ({})", module, argv.join(" ")), DetectionSource::Instrumentation, 25, 0.6),
+            None => (reason!("Python interpreter ({})", argv.join(" ")), DetectionSource::Instrumentation, 10, 0.3),
+        });
+    }
+    None
+}
+
+/// Weight of a finding `depth` generations up
+pub fn scale_for_depth(weight: u32, depth: usize) -> u32 {
+    match depth {
+        0 | 1 => weight,
+        2 => weight * 3 / 4,
+        _ => weight / 2,
+    }
+}
+
+fn parent_of(pid: u32) -> Option<u32> {
+    fs::read_to_string(format!("/proc/{}/status", pid)).ok()?
+        .lines().find_map(|l| l.strip_prefix("PPid:"))?.trim().parse().ok()
+}
+
+/// Our ancestors, parent first, up to (not including) init; `complete` is
+/// false if the walk stopped at an unreadable process
+fn ancestors() -> (Vec<Ancestor>, bool) {
+    let mut chain = Vec::new();
+    let mut pid = std::os::unix::process::parent_id();
+    for depth in 1..=MAX_DEPTH {
+        if pid <= 1 {
+            return (chain, true);
+        }
+        let Ok(comm) = fs::read_to_string(format!("/proc/{}/comm", pid)) else { return (chain, false) };
+        let argv = fs::read(format!("/proc/{}/cmdline", pid)).unwrap_or_default()
+            .split(|&b| b == 0)
+            .filter(|a| !a.is_empty())
+            .map(|a| String::from_utf8_lossy(a).into_owned())
+            .collect();
+        chain.push(Ancestor { pid, depth, comm: comm.trim().to_string(), argv });
+        let Some(parent) = parent_of(pid) else { return (chain, false) };
+        pid = parent;
+    }
+    (chain, false)
+}
+
+/// Main entry point for the ancestry walk
+pub fn check_ancestry(engine: &mut DecisionEngine) {
+    let (chain, complete) = ancestors();
+    if !complete {
+        engine.record_diagnostic("ancestry", &reason!("ancestor walk stopped after {} process(es)", chain.len()));
+    }
+    let names: Vec<String> = chain.iter().map(|a| format!("{}({})", a.comm, a.pid)).collect();
+    diag!("[ANCESTRY] {}", names.join(" <- "));
+
+    let mut matches = 0;
+    for ancestor in &chain {
+        let Some((what, source, weight, confidence)) = classify_ancestor(&ancestor.comm, &ancestor.argv) else { continue };
+        matches += 1;
+        let relation = match ancestor.depth {
+            1 => "parent".to_string(),
+            2 => "grandparent".to_string(),
+            n => reason!("ancestor {} levels up", n),
+        };
+        engine.report_with_confidence(
+            source,
+            scale_for_depth(weight, ancestor.depth),
+            confidence,
+            &reason!("Launched under {}: {} (PID {})", what, relation, ancestor.pid)
+        );
+    }
+    engine.record_feature("ancestor_depth", chain.len() as f64);
+    engine.record_feature("ancestor_matches", matches as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_classify_ancestor() {
+        let kind = |comm: &str, args: &[&str]| classify_ancestor(comm, &argv(args)).map(|(_, s, w, _)| (s, w));
+        assert_eq!(kind("rr", &["rr", "record", "./app"]), Some((DetectionSource::RecordReplay, 50)));
+        assert_eq!(kind("gdb", &["gdb", "-q", "./app"]), Some((DetectionSource::Ptrace, 40)));
+        assert_eq!(kind("memcheck-amd64-", &["valgrind", "./app"]), Some((DetectionSource::Dbi, 40)));
+        assert_eq!(kind("python3", &["python3", "exploit.py", "-m", "pwn"]), Some((DetectionSource::Instrumentation, 25)));
+        assert_eq!(kind("python3", &["/usr/bin/python3", "build.py"]), Some((DetectionSource::Instrumentation, 10)));
+        assert_eq!(kind("bash", &["-bash"]), None);
+        assert_eq!(kind("sshd", &["sshd: user@pts/0"]), None);
+        // "rr" as a substring is not rr
+        assert_eq!(kind("sherrif", &["sherrif"]), None);
+    }
+
+    #[test]
+    fn test_scale_for_depth() {
+        assert_eq!(scale_for_depth(40, 1), 40);
+        assert_eq!(scale_for_depth(40, 2), 30);
+        assert_eq!(scale_for_depth(40, 5), 20);
+    }
+}
@@ -0,0 +1,390 @@
+//! Remote Time-Attestation Heartbeat
+//!
+//! # Overview
+//!
+//! Every local clock (TSC, CLOCK_MONOTONIC, wall clock) can be virtualized
+//! consistently: a debugger or hypervisor that pauses the process and
+//! rewinds all of them together leaves nothing to cross-check locally. A
+//! remote server's clock is out of its reach.
+//!
+//! When configured, a background thread exchanges nonces with a time server
+//! over UDP. Each response carries the server's monotonic time and an
+//! HMAC-SHA256 over the nonce and that time, keyed with a secret shared with
+//! the server, so responses cannot be replayed or forged. Between any two
+//! exchanges we compare:
+//!
+//! - Server-measured elapsed time
+//! - Local CLOCK_MONOTONIC elapsed time
+//! - TSC elapsed time (rate learned over the first heartbeat interval)
+//!
+//! Allowing for half of each round trip plus drift. Local clocks that fall
+//! behind the server mean the process lost time it cannot see: paused in a
+//! debugger, VM snapshot/resume, or clock compensation.
+//!
+//! # Offline Tolerance
+//!
+//! Timeouts and unreachable servers produce no evidence, only a count in
+//! the feature vector; a run with fewer than two good exchanges reports
+//! nothing. Responses with a bad MAC are discarded and recorded as a
+//! diagnostic.
+//!
+//! # Protocol
+//!
+//! ```text
+//! request:  "ADTA" | nonce[16]
+//! response: nonce[16] | server_ns (u64 LE) | HMAC-SHA256(key, "ADTA" | nonce | server_ns)
+//! ```
+//!
+//! `--serve-attestation <addr>` runs a matching server.
+//!
+//! # Why This Fails
+//!
+//! - Needs network access and a trusted server holding the key
+//! - The key lives in the protected process and can be extracted to build
+//!   a lying server
+//! - Pauses shorter than the round-trip uncertainty go unnoticed
+
+use std::io;
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use crate::engine::chacha20::KEY_LEN;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::sha256::{constant_time_eq, hmac_sha256, DIGEST_LEN};
+use crate::ffi::get_rdtsc;
+
+/// Environment variable naming the time server (`host:port`)
+pub const SERVER_ENV_VAR: &str = "ANTIDEBUG_ATTEST_SERVER";
+
+/// Environment variable holding the shared key (64 hex characters)
+pub const KEY_ENV_VAR: &str = "ANTIDEBUG_ATTEST_KEY";
+
+const MAGIC: &[u8; 4] = b"ADTA";
+const NONCE_LEN: usize = 16;
+const RESPONSE_LEN: usize = NONCE_LEN + 8 + DIGEST_LEN;
+
+/// Time between heartbeats
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long one exchange waits for its response
+const RESPONSE_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// Fixed slack on top of the round-trip uncertainty
+const SLACK_NS: f64 = 50_000_000.0;
+
+/// Relative clock drift tolerated between client and server
+const DRIFT: f64 = 0.01;
+
+/// One authenticated round trip
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exchange {
+    /// Local CLOCK_MONOTONIC when the request was sent / response received
+    pub sent_ns: u64,
+    pub received_ns: u64,
+    /// TSC when the response was received
+    pub tsc: u64,
+    /// Server's monotonic clock when it answered
+    pub server_ns: u64,
+}
+
+impl Exchange {
+    fn local_mid(&self) -> u64 {
+        self.sent_ns + (self.received_ns - self.sent_ns) / 2
+    }
+
+    fn rtt(&self) -> u64 {
+        self.received_ns - self.sent_ns
+    }
+}
+
+/// Local clocks disagreeing with the server between two exchanges
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dilation {
+    pub server_ns: u64,
+    pub local_ns: u64,
+    /// TSC-derived elapsed time, if the TSC (not monotonic) disagreed
+    pub tsc_ns: Option<u64>,
+}
+
+/// Compare the interval between `a` and `b` as seen by the server and by
+/// local clocks. `tsc_per_ns` enables the TSC comparison.
+pub fn compare(a: &Exchange, b: &Exchange, tsc_per_ns: Option<f64>) -> Option<Dilation> {
+    let server = b.server_ns.saturating_sub(a.server_ns) as f64;
+    let local = b.local_mid().saturating_sub(a.local_mid()) as f64;
+    let tolerance = (a.rtt() + b.rtt()) as f64 / 2.0 + SLACK_NS + DRIFT * server;
+
+    let dilation = |tsc_ns| Dilation { server_ns: server as u64, local_ns: local as u64, tsc_ns };
+    if (server - local).abs() > tolerance {
+        return Some(dilation(None));
+    }
+    let rate = tsc_per_ns.filter(|r| *r > 0.0)?;
+    let tsc = b.tsc.wrapping_sub(a.tsc) as f64 / rate;
+    ((server - tsc).abs() > tolerance).then(|| dilation(Some(tsc as u64)))
+}
+
+/// Message the server authenticates
+fn signed_message(nonce: &[u8], server_ns: u64) -> Vec<u8> {
+    let mut message = MAGIC.to_vec();
+    message.extend_from_slice(nonce);
+    message.extend_from_slice(&server_ns.to_le_bytes());
+    message
+}
+
+/// Build a signed response (server side)
+pub fn sign_response(key: &[u8; KEY_LEN], nonce: &[u8], server_ns: u64) -> Vec<u8> {
+    let mut response = nonce.to_vec();
+    response.extend_from_slice(&server_ns.to_le_bytes());
+    response.extend_from_slice(&hmac_sha256(key, &signed_message(nonce, server_ns)));
+    response
+}
+
+/// Check a response against the nonce we sent; returns the server time
+pub fn verify_response(key: &[u8; KEY_LEN], nonce: &[u8], response: &[u8]) -> Result<u64, String> {
+    if response.len() != RESPONSE_LEN {
+        return Err(reason!("response is {} bytes, expected {}", response.len(), RESPONSE_LEN));
+    }
+    let (echoed, rest) = response.split_at(NONCE_LEN);
+    if echoed != nonce {
+        return Err(reason!("response nonce does not match the request"));
+    }
+    let server_ns = u64::from_le_bytes(rest[..8].try_into().unwrap());
+    let expected = hmac_sha256(key, &signed_message(nonce, server_ns));
+    if !constant_time_eq(&expected, &rest[8..]) {
+        return Err(reason!("bad response signature"));
+    }
+    Ok(server_ns)
+}
+
+fn clock_ns() -> u64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` is a valid out-pointer
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+fn random_nonce() -> [u8; NONCE_LEN] {
+    let mut nonce = [0u8; NONCE_LEN];
+    // SAFETY: getrandom writes at most `len` bytes into our buffer
+    let n = unsafe { libc::getrandom(nonce.as_mut_ptr() as *mut libc::c_void, NONCE_LEN, 0) };
+    if n != NONCE_LEN as isize {
+        // Uniqueness is all the protocol needs
+        nonce[..8].copy_from_slice(&clock_ns().to_le_bytes());
+    }
+    nonce
+}
+
+/// Outcome of one exchange attempt
+enum Attempt {
+    Ok(Exchange),
+    Offline(String),
+    Rejected(String),
+}
+
+fn exchange(socket: &UdpSocket, key: &[u8; KEY_LEN]) -> Attempt {
+    let nonce = random_nonce();
+    let mut request = MAGIC.to_vec();
+    request.extend_from_slice(&nonce);
+
+    let sent_ns = clock_ns();
+    if let Err(e) = socket.send(&request) {
+        return Attempt::Offline(e.to_string());
+    }
+    let mut buf = [0u8; 128];
+    loop {
+        let len = match socket.recv(&mut buf) {
+            Ok(len) => len,
+            Err(e) => return Attempt::Offline(e.to_string()),
+        };
+        let received_ns = clock_ns();
+        // SAFETY: RDTSC has no preconditions
+        let tsc = unsafe { get_rdtsc() };
+        match verify_response(key, &nonce, &buf[..len]) {
+            Ok(server_ns) => return Attempt::Ok(Exchange { sent_ns, received_ns, tsc, server_ns }),
+            // A late answer to an earlier request: keep waiting for ours
+            Err(_) if len == RESPONSE_LEN && buf[..NONCE_LEN] != nonce => continue,
+            Err(e) => return Attempt::Rejected(e),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct HeartbeatState {
+    exchanges: Vec<Exchange>,
+    failures: u32,
+    rejected: Vec<String>,
+}
+
+impl HeartbeatState {
+    fn record(&mut self, attempt: Attempt) {
+        match attempt {
+            Attempt::Ok(exchange) => self.exchanges.push(exchange),
+            Attempt::Offline(e) => {
+                diag!("[ATTEST] Server unreachable: {}", e);
+                self.failures += 1;
+            }
+            Attempt::Rejected(e) => {
+                diag!("[ATTEST] Rejected response: {}", e);
+                self.rejected.push(e);
+            }
+        }
+    }
+}
+
+/// Running heartbeat; stop it with [`Heartbeat::finish`]
+pub struct Heartbeat {
+    socket: Arc<UdpSocket>,
+    key: [u8; KEY_LEN],
+    stop: Arc<AtomicBool>,
+    state: Arc<Mutex<HeartbeatState>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Heartbeat {
+    /// Start heartbeats if [`SERVER_ENV_VAR`] and [`KEY_ENV_VAR`] are set
+    pub fn from_env() -> Option<Self> {
+        let server = std::env::var(SERVER_ENV_VAR).ok()?;
+        let key = match crate::engine::log::parse_key(&std::env::var(KEY_ENV_VAR).unwrap_or_default()) {
+            Ok(key) => key,
+            Err(e) => {
+                diag!("[ATTEST] {} set but {} is unusable ({}), heartbeat disabled", SERVER_ENV_VAR, KEY_ENV_VAR, e);
+                return None;
+            }
+        };
+        match Self::start(&server, key) {
+            Ok(heartbeat) => Some(heartbeat),
+            Err(e) => {
+                diag!("[ATTEST] Cannot reach {}: {}, heartbeat disabled", server, e);
+                None
+            }
+        }
+    }
+
+    /// Start exchanging heartbeats with `server`
+    pub fn start(server: &str, key: [u8; KEY_LEN]) -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.connect(server)?;
+        socket.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+        let socket = Arc::new(socket);
+        let stop = Arc::new(AtomicBool::new(false));
+        let state = Arc::new(Mutex::new(HeartbeatState::default()));
+
+        let (thread_socket, thread_stop, thread_state) = (socket.clone(), stop.clone(), state.clone());
+        let thread = crate::engine::threads::spawn_masked("attestation", move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                let attempt = exchange(&thread_socket, &key);
+                thread_state.lock().unwrap_or_else(|e| e.into_inner()).record(attempt);
+                std::thread::park_timeout(HEARTBEAT_INTERVAL);
+            }
+        })?;
+        diag!("[ATTEST] Heartbeat to {} every {} ms", server, HEARTBEAT_INTERVAL.as_millis());
+        Ok(Self { socket, key, stop, state, thread: Some(thread) })
+    }
+
+    /// Take a final exchange, stop the thread and report
+    pub fn finish(mut self, engine: &mut DecisionEngine) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.record(exchange(&self.socket, &self.key));
+
+        engine.record_feature("attest_exchanges", state.exchanges.len() as f64);
+        engine.record_feature("attest_failures", state.failures as f64);
+        for reason in &state.rejected {
+            engine.record_diagnostic("attestation", reason);
+        }
+
+        let exchanges = &state.exchanges;
+        if exchanges.len() < 2 {
+            diag!("[ATTEST] {} good exchange(s), {} failures: offline, nothing to compare",
+                  exchanges.len(), state.failures);
+            return;
+        }
+
+        // TSC rate over the first interval, measured against the server
+        let (first, second) = (&exchanges[0], &exchanges[1]);
+        let server_span = second.server_ns.saturating_sub(first.server_ns);
+        let tsc_per_ns = (server_span > 0).then(|| second.tsc.wrapping_sub(first.tsc) as f64 / server_span as f64);
+
+        let mut max_skew_ns: f64 = 0.0;
+        for pair in exchanges.windows(2) {
+            let server = pair[1].server_ns.saturating_sub(pair[0].server_ns) as f64;
+            let local = pair[1].local_mid().saturating_sub(pair[0].local_mid()) as f64;
+            max_skew_ns = max_skew_ns.max((server - local).abs());
+
+            if let Some(d) = compare(&pair[0], &pair[1], tsc_per_ns) {
+                let details = match d.tsc_ns {
+                    None => reason!("Time server measured {} ms between heartbeats, local monotonic clock {} ms: process time dilated",
+                                    d.server_ns / 1_000_000, d.local_ns / 1_000_000),
+                    Some(tsc) => reason!("Time server measured {} ms between heartbeats, TSC {} ms (monotonic {} ms): TSC compensated",
+                                         d.server_ns / 1_000_000, tsc / 1_000_000, d.local_ns / 1_000_000),
+                };
+                engine.report_with_confidence(DetectionSource::RemoteTime, 40, 0.9, &details);
+            }
+        }
+        engine.record_feature("attest_max_skew_ms", max_skew_ns / 1e6);
+        diag!("[ATTEST] {} exchanges, {} failures, max skew {:.1} ms",
+              exchanges.len(), state.failures, max_skew_ns / 1e6);
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Answer heartbeat requests on `bind` forever (`--serve-attestation`)
+pub fn serve(bind: &str, key: &[u8; KEY_LEN]) -> io::Result<()> {
+    let socket = UdpSocket::bind(bind)?;
+    diag!("[ATTEST] Serving time attestation on {}", socket.local_addr()?);
+    let mut buf = [0u8; 64];
+    loop {
+        let (len, peer) = socket.recv_from(&mut buf)?;
+        if len != MAGIC.len() + NONCE_LEN || &buf[..MAGIC.len()] != MAGIC {
+            continue;
+        }
+        let response = sign_response(key, &buf[MAGIC.len()..len], clock_ns());
+        let _ = socket.send_to(&response, peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: u64 = 1_000_000;
+
+    fn at(local_ms: u64, server_ms: u64) -> Exchange {
+        // 2 ms round trip, 3 GHz TSC
+        Exchange { sent_ns: local_ms * MS - MS, received_ns: local_ms * MS + MS, tsc: local_ms * MS * 3, server_ns: server_ms * MS }
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(compare(&at(1000, 5000), &at(1500, 5500), Some(3.0)), None);
+        // Paused for 2 s with local clocks rewound
+        let d = compare(&at(1000, 5000), &at(1500, 7500), Some(3.0)).unwrap();
+        assert_eq!((d.server_ns, d.local_ns, d.tsc_ns), (2500 * MS, 500 * MS, None));
+        // Monotonic honest, TSC held back
+        let mut b = at(3500, 7500);
+        b.tsc = at(1500, 0).tsc;
+        assert_eq!(compare(&at(1000, 5000), &b, Some(3.0)).unwrap().tsc_ns, Some(500 * MS));
+    }
+
+    #[test]
+    fn test_signed_roundtrip() {
+        let key = [7u8; KEY_LEN];
+        let nonce = [1u8; NONCE_LEN];
+        let mut response = sign_response(&key, &nonce, 123_456);
+        assert_eq!(verify_response(&key, &nonce, &response), Ok(123_456));
+        assert!(verify_response(&[8u8; KEY_LEN], &nonce, &response).is_err());
+        assert!(verify_response(&key, &[2u8; NONCE_LEN], &response).is_err());
+        response[NONCE_LEN] ^= 1;
+        assert!(verify_response(&key, &nonce, &response).is_err());
+    }
+}
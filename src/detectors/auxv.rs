@@ -0,0 +1,300 @@
+//! Auxiliary Vector Consistency Check
+//!
+//! # Overview
+//!
+//! The kernel hands every new process an auxiliary vector (auxv) describing
+//! the execution environment: the program entry point, the interpreter base,
+//! CPU capability bits, whether the process is running set-id, and so on.
+//! We can observe it in two places:
+//! 1. `getauxval()`, which reads libc's copy on the initial stack
+//! 2. `/proc/self/auxv`, which the kernel serves from `mm->saved_auxv`
+//!
+//! Each value also has an independent ground truth we can check it against:
+//!
+//! | Entry      | Cross-check                                              |
+//! |------------|----------------------------------------------------------|
+//! | AT_SECURE  | Must be 1 whenever real and effective uid/gid differ     |
+//! | AT_HWCAP   | On x86_64 the kernel copies CPUID.1:EDX verbatim         |
+//! | AT_HWCAP2  | FSGSBASE bit implies CPUID.7.0:EBX[0]                    |
+//! | AT_BASE    | Start of a file-backed mapping at offset 0 (ld.so)       |
+//! | AT_ENTRY   | Inside an executable mapping of /proc/self/exe           |
+//!
+//! Emulators (qemu-user, Unicorn-based sandboxes) and custom loaders build
+//! the auxv themselves and routinely get at least one of these relationships
+//! subtly wrong.
+//!
+//! # Why This Fails
+//!
+//! - A full-system emulator runs a real kernel, which builds a correct auxv
+//! - A careful loader can fabricate values that satisfy every check here
+//! - `clearcpuid=` on the kernel command line legitimately masks HWCAP bits
+
+use std::collections::HashMap;
+use core::arch::x86_64::CpuidResult;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// AT_HWCAP2 bit: kernel allows user-space FSGSBASE instructions
+const HWCAP2_FSGSBASE: u64 = 1 << 1;
+
+/// Entries we compare between getauxval() and /proc/self/auxv.
+/// AT_HWCAP/AT_HWCAP2 are excluded: glibc on x86_64 replaces them with its own
+/// platform bits, so they only get checked against CPUID.
+const CHECKED_ENTRIES: &[(u64, &str)] = &[
+    (libc::AT_SECURE, "AT_SECURE"),
+    (libc::AT_BASE, "AT_BASE"),
+    (libc::AT_ENTRY, "AT_ENTRY"),
+    (libc::AT_PHDR, "AT_PHDR"),
+];
+
+/// A single line of /proc/self/maps, reduced to the fields we need
+#[derive(Debug, Clone)]
+struct Mapping {
+    start: u64,
+    end: u64,
+    executable: bool,
+    offset: u64,
+    path: String,
+}
+
+/// Parse the raw bytes of /proc/self/auxv into (type, value) pairs.
+/// Stops at AT_NULL or at the first truncated entry.
+pub fn parse_auxv(raw: &[u8]) -> Vec<(u64, u64)> {
+    let mut entries = Vec::new();
+    for chunk in raw.chunks_exact(16) {
+        let key = u64::from_ne_bytes(chunk[..8].try_into().unwrap());
+        let value = u64::from_ne_bytes(chunk[8..].try_into().unwrap());
+        if key == libc::AT_NULL {
+            break;
+        }
+        entries.push((key, value));
+    }
+    entries
+}
+
+fn read_proc_auxv() -> Option<HashMap<u64, u64>> {
+    let raw = std::fs::read("/proc/self/auxv").ok()?;
+    Some(parse_auxv(&raw).into_iter().collect())
+}
+
+fn read_maps() -> Vec<Mapping> {
+    let content = match std::fs::read_to_string("/proc/self/maps") {
+        Ok(c) => c,
+        Err(_) => return Vec::new(),
+    };
+
+    content.lines().filter_map(|line| {
+        let mut parts = line.split_whitespace();
+        let (start, end) = parts.next()?.split_once('-')?;
+        let perms = parts.next()?;
+        let offset = parts.next()?;
+        let path = parts.nth(2).unwrap_or("").to_string();
+        Some(Mapping {
+            start: u64::from_str_radix(start, 16).ok()?,
+            end: u64::from_str_radix(end, 16).ok()?,
+            executable: perms.contains('x'),
+            offset: u64::from_str_radix(offset, 16).ok()?,
+            path,
+        })
+    }).collect()
+}
+
+/// AT_SECURE must be set whenever the process runs with differing real and
+/// effective credentials. The converse is not required (file capabilities
+/// also set it), so only a missing flag is evidence.
+pub fn secure_flag_consistent(at_secure: u64, uid: u32, euid: u32, gid: u32, egid: u32) -> bool {
+    let setid = uid != euid || gid != egid;
+    !setid || at_secure != 0
+}
+
+/// 1. getauxval() vs /proc/self/auxv
+fn check_views(engine: &mut DecisionEngine, kernel: &HashMap<u64, u64>) {
+    let mut diverged = Vec::new();
+    for &(key, name) in CHECKED_ENTRIES {
+        let Some(&kernel_value) = kernel.get(&key) else { continue };
+        let live_value = unsafe { libc::getauxval(key) };
+        if live_value != kernel_value {
+            diverged.push(reason!("{}: live={:#x} kernel={:#x}", name, live_value, kernel_value));
+        }
+    }
+
+    engine.record_feature("auxv_view_divergent", diverged.len() as f64);
+    if !diverged.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::CrossView,
+            60,
+            0.9,
+            &reason!("getauxval() diverges from /proc/self/auxv: {}", diverged.join(", "))
+        );
+    }
+}
+
+/// 2. AT_SECURE vs our actual credentials
+fn check_secure(engine: &mut DecisionEngine, kernel: &HashMap<u64, u64>) {
+    let at_secure = *kernel.get(&libc::AT_SECURE).unwrap_or(&0);
+    let (uid, euid, gid, egid) = unsafe {
+        (libc::getuid(), libc::geteuid(), libc::getgid(), libc::getegid())
+    };
+
+    if !secure_flag_consistent(at_secure, uid, euid, gid, egid) {
+        engine.report_with_confidence(
+            DetectionSource::CrossView,
+            50,
+            0.85,
+            &reason!("AT_SECURE=0 but credentials differ (uid={} euid={} gid={} egid={})", uid, euid, gid, egid)
+        );
+    }
+}
+
+/// 3. AT_HWCAP / AT_HWCAP2 vs CPUID
+#[allow(unused_unsafe)] // `__cpuid` is only safe on newer toolchains
+fn check_hwcap(engine: &mut DecisionEngine, kernel: &HashMap<u64, u64>) {
+    let leaf1: CpuidResult = unsafe { core::arch::x86_64::__cpuid(1) };
+    let cpuid_edx = leaf1.edx as u64;
+
+    if let Some(&hwcap) = kernel.get(&libc::AT_HWCAP) {
+        let fabricated = hwcap & !cpuid_edx;
+        let masked = cpuid_edx & !hwcap;
+
+        diag!("[AUXV] AT_HWCAP={:#010x} CPUID.1:EDX={:#010x}", hwcap, cpuid_edx);
+        engine.record_feature("hwcap_fabricated_bits", fabricated.count_ones() as f64);
+
+        if fabricated != 0 {
+            // Capability the CPU does not report - the kernel never does this
+            engine.report_with_confidence(
+                DetectionSource::CrossView,
+                50,
+                0.8,
+                &reason!("AT_HWCAP claims features CPUID lacks: bits {:#x}", fabricated)
+            );
+        } else if masked != 0 {
+            // clearcpuid= and similar can legitimately hide bits
+            engine.report_with_confidence(
+                DetectionSource::CrossView,
+                10,
+                0.3,
+                &reason!("AT_HWCAP masks CPUID features: bits {:#x}", masked)
+            );
+        }
+    }
+
+    if let Some(&hwcap2) = kernel.get(&libc::AT_HWCAP2) {
+        let leaf7: CpuidResult = unsafe { core::arch::x86_64::__cpuid_count(7, 0) };
+        if hwcap2 & HWCAP2_FSGSBASE != 0 && leaf7.ebx & 1 == 0 {
+            engine.report_with_confidence(
+                DetectionSource::CrossView,
+                40,
+                0.8,
+                &reason!("AT_HWCAP2 advertises FSGSBASE but CPUID.7.0:EBX[0] is clear")
+            );
+        }
+    }
+}
+
+/// 4. AT_BASE / AT_ENTRY vs our actual mappings
+fn check_mappings(engine: &mut DecisionEngine, kernel: &HashMap<u64, u64>) {
+    let maps = read_maps();
+    if maps.is_empty() {
+        diag!("[AUXV] /proc/self/maps unreadable, skipping mapping checks");
+        return;
+    }
+
+    // AT_BASE is 0 for static binaries; otherwise it is where ld.so was mapped
+    if let Some(&base) = kernel.get(&libc::AT_BASE) {
+        if base != 0 {
+            let is_image_start = maps.iter()
+                .any(|m| m.start == base && m.offset == 0 && !m.path.is_empty());
+            if !is_image_start {
+                engine.report_with_confidence(
+                    DetectionSource::CrossView,
+                    40,
+                    0.7,
+                    &reason!("AT_BASE {:#x} is not the start of a file-backed mapping", base)
+                );
+            }
+        }
+    }
+
+    let exe = std::fs::read_link("/proc/self/exe")
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    if let Some(&entry) = kernel.get(&libc::AT_ENTRY) {
+        let owner = maps.iter().find(|m| entry >= m.start && entry < m.end);
+        engine.record_flag(
+            "auxv_entry_outside_exe",
+            !matches!(owner, Some(m) if m.executable && m.path == exe),
+        );
+        match owner {
+            Some(m) if m.executable && m.path == exe => {}
+            Some(m) => {
+                engine.report_with_confidence(
+                    DetectionSource::CrossView,
+                    40,
+                    0.7,
+                    &reason!("AT_ENTRY {:#x} lies in {} (exec={}), not our executable {}",
+                        entry, if m.path.is_empty() { "[anon]" } else { &m.path }, m.executable, exe)
+                );
+            }
+            None => {
+                engine.report_with_confidence(
+                    DetectionSource::CrossView,
+                    45,
+                    0.8,
+                    &reason!("AT_ENTRY {:#x} is not mapped", entry)
+                );
+            }
+        }
+    }
+}
+
+/// Main entry point for the auxv consistency check
+pub fn check_auxv_consistency(engine: &mut DecisionEngine) {
+    let kernel = match read_proc_auxv() {
+        Some(k) if !k.is_empty() => k,
+        _ => {
+            diag!("[AUXV] /proc/self/auxv unreadable, skipping");
+            return;
+        }
+    };
+
+    diag!("[AUXV] {} entries in /proc/self/auxv", kernel.len());
+
+    check_views(engine, &kernel);
+    check_secure(engine, &kernel);
+    check_hwcap(engine, &kernel);
+    check_mappings(engine, &kernel);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(pairs: &[(u64, u64)]) -> Vec<u8> {
+        pairs.iter()
+            .flat_map(|&(k, v)| k.to_ne_bytes().into_iter().chain(v.to_ne_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_auxv_stops_at_null() {
+        let raw = encode(&[(libc::AT_ENTRY, 0x1000), (libc::AT_NULL, 0), (libc::AT_BASE, 0x2000)]);
+        assert_eq!(parse_auxv(&raw), vec![(libc::AT_ENTRY, 0x1000)]);
+    }
+
+    #[test]
+    fn test_secure_flag_consistent() {
+        assert!(secure_flag_consistent(0, 1000, 1000, 1000, 1000));
+        assert!(secure_flag_consistent(1, 1000, 0, 1000, 1000));
+        assert!(!secure_flag_consistent(0, 1000, 0, 1000, 1000));
+    }
+
+    #[test]
+    fn test_live_auxv_matches_kernel() {
+        let kernel = read_proc_auxv().unwrap_or_default();
+        for &(key, _) in CHECKED_ENTRIES {
+            if let Some(&value) = kernel.get(&key) {
+                assert_eq!(unsafe { libc::getauxval(key) }, value);
+            }
+        }
+    }
+}
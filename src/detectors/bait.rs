@@ -0,0 +1,221 @@
+//! Bait Symbols and Honey Breakpoints
+//!
+//! # Overview
+//!
+//! Analysts open a binary, search the symbol table for `license`, `key` or
+//! `decrypt`, and set a breakpoint there. We export functions with exactly
+//! those names (kept in `.dynsym` so `strip` does not remove them) next to
+//! matching error strings. They do plausible-looking work but protect
+//! nothing, and we watch them:
+//!
+//! - **Bytes**: each bait function's first [`BAIT_SCAN_LEN`] bytes in memory
+//!   are compared with the same bytes in the executable on disk. A byte that
+//!   reads `0xCC` in memory but not on disk is a software breakpoint.
+//! - **Call latency**: each bait is called repeatedly and its fastest call
+//!   compared with an identical, unexported control function. A hardware
+//!   execution breakpoint (or a software one that is hit and continued)
+//!   traps to the debugger on every call, adding tens of thousands of
+//!   cycles that the control does not pay.
+//!
+//! Nobody has a reason to break on these functions except someone hunting
+//! for the protection, so a hit is high-confidence evidence.
+//!
+//! # Why This Fails
+//!
+//! - An analyst who reads the code first sees the functions are unused bait
+//! - Breakpoints on the real logic are not covered (see `int3.rs`)
+//! - A debugger that hides its breakpoint bytes from memory reads (a
+//!   hypervisor-level one) leaves only the latency check
+
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::hint::black_box;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::salt::jitter;
+use crate::ffi::get_rdtsc;
+
+/// Bytes of each bait function compared against the executable on disk
+pub const BAIT_SCAN_LEN: usize = 64;
+
+/// Calls per function for the latency comparison (fastest one counts)
+const CALLS: usize = 20;
+
+/// Extra cycles on the fastest bait call over the control that mean a trap
+/// (salted +-10%)
+const TRAP_CYCLES: u64 = jitter(20_000, 10, "bait.trap_cycles");
+
+const LICENSE_OK: &str = "License key accepted";
+const LICENSE_FAILED: &str = "License check failed: invalid or expired key";
+const KEY_BANNER: &str = "Decrypting payload with master key";
+
+/// Bait: looks like a license validator
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn check_license(key: *const u8, len: usize) -> i32 {
+    license_body(key, len)
+}
+
+/// Bait: looks like key unwrapping
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn decrypt_key(buf: *mut u8, len: usize) -> i32 {
+    black_box(KEY_BANNER);
+    let mut state: u8 = 0x5a;
+    for i in 0..len {
+        // SAFETY: callers pass a buffer of at least `len` bytes
+        unsafe {
+            let b = buf.add(i);
+            state = state.rotate_left(3) ^ (i as u8);
+            *b ^= state;
+        }
+    }
+    black_box(state) as i32
+}
+
+/// Unexported twin of [`check_license`], the latency reference
+#[inline(never)]
+fn control(key: *const u8, len: usize) -> i32 {
+    license_body(key, len)
+}
+
+#[inline(always)]
+fn license_body(key: *const u8, len: usize) -> i32 {
+    let mut sum: u32 = 0x811c_9dc5;
+    for i in 0..len {
+        // SAFETY: callers pass a buffer of at least `len` bytes
+        sum = (sum ^ unsafe { *key.add(i) } as u32).wrapping_mul(0x0100_0193);
+    }
+    if black_box(sum) == 0x1234_5678 {
+        black_box(LICENSE_OK);
+        1
+    } else {
+        black_box(LICENSE_FAILED);
+        0
+    }
+}
+
+/// Offsets where `memory` holds 0xCC but `disk` does not
+pub fn inserted_breakpoints(memory: &[u8], disk: &[u8]) -> Vec<usize> {
+    memory.iter().zip(disk).enumerate()
+        .filter(|(_, (m, d))| **m == 0xCC && **d != 0xCC)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Bytes of the executable on disk backing `addr`, via its /proc/self/maps entry
+fn disk_bytes(addr: usize, len: usize) -> Option<Vec<u8>> {
+    let maps = fs::read_to_string("/proc/self/maps").ok()?;
+    for line in maps.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 6 || !fields[5].starts_with('/') {
+            continue;
+        }
+        let (start, end) = fields[0].split_once('-')?;
+        let start = usize::from_str_radix(start, 16).ok()?;
+        let end = usize::from_str_radix(end, 16).ok()?;
+        if addr < start || addr + len > end {
+            continue;
+        }
+        let offset = u64::from_str_radix(fields[2], 16).ok()? + (addr - start) as u64;
+        let mut file = File::open(fields[5]).ok()?;
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let mut bytes = vec![0u8; len];
+        file.read_exact(&mut bytes).ok()?;
+        return Some(bytes);
+    }
+    None
+}
+
+/// Fastest of [`CALLS`] calls to `f`, in TSC cycles
+fn fastest_call(f: impl Fn() -> i32) -> u64 {
+    (0..CALLS)
+        .map(|_| {
+            // SAFETY: RDTSC has no preconditions
+            let start = unsafe { get_rdtsc() };
+            black_box(f());
+            unsafe { get_rdtsc() }.saturating_sub(start)
+        })
+        .min()
+        .unwrap_or(0)
+}
+
+/// Main entry point for the bait-function check
+pub fn check_bait_functions(engine: &mut DecisionEngine) {
+    let baits: [(&str, usize); 2] = [
+        ("check_license", check_license as *const () as usize),
+        ("decrypt_key", decrypt_key as *const () as usize),
+    ];
+
+    let mut breakpoints = 0usize;
+    let mut armed = Vec::new();
+    for (name, addr) in baits {
+        // SAFETY: the function's own code, mapped r-x for the life of the process
+        let memory = unsafe { std::slice::from_raw_parts(addr as *const u8, BAIT_SCAN_LEN) };
+        let Some(disk) = disk_bytes(addr, BAIT_SCAN_LEN) else {
+            diag!("[BAIT] {}: no file backing at {:#x}, skipping byte check", name, addr);
+            continue;
+        };
+        let inserted = inserted_breakpoints(memory, &disk);
+        if inserted.is_empty() {
+            continue;
+        }
+        breakpoints += inserted.len();
+        armed.push(name);
+        engine.report_with_confidence(
+            DetectionSource::Int3,
+            50,
+            0.95,
+            &reason!("Software breakpoint on bait function {} (+{:?}): analyst broke on an attractive symbol", name, inserted)
+        );
+    }
+    engine.record_feature("bait_breakpoints", breakpoints as f64);
+
+    // Calling into an inserted INT3 without a debugger would kill us
+    if !armed.is_empty() {
+        diag!("[BAIT] Skipping latency check, breakpoints present in {:?}", armed);
+        return;
+    }
+
+    let key = *b"AAAA-BBBB-CCCC-DDDD";
+    let reference = fastest_call(|| control(key.as_ptr(), key.len()));
+    let license = fastest_call(|| check_license(key.as_ptr(), key.len()));
+    let decrypt = fastest_call(|| {
+        let mut buf = key;
+        decrypt_key(buf.as_mut_ptr(), buf.len())
+    });
+    diag!("[BAIT] Fastest call cycles: control={} check_license={} decrypt_key={}", reference, license, decrypt);
+    engine.record_feature("bait_call_excess_cycles", license.max(decrypt).saturating_sub(reference) as f64);
+
+    for (name, cycles) in [("check_license", license), ("decrypt_key", decrypt)] {
+        if cycles > reference + TRAP_CYCLES {
+            engine.report_with_confidence(
+                DetectionSource::HardwareBreakpoint,
+                40,
+                0.8,
+                &reason!("Every call to bait function {} trapped ({} cycles vs {} for an identical control): execution breakpoint",
+                         name, cycles, reference)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inserted_breakpoints() {
+        let disk = [0x55, 0x48, 0x89, 0xe5, 0xcc, 0x90];
+        let memory = [0xcc, 0x48, 0x89, 0xcc, 0xcc, 0x90];
+        assert_eq!(inserted_breakpoints(&memory, &disk), vec![0, 3]);
+    }
+
+    #[test]
+    fn test_bait_bytes_match_disk() {
+        let addr = check_license as *const () as usize;
+        let memory = unsafe { std::slice::from_raw_parts(addr as *const u8, BAIT_SCAN_LEN) };
+        if let Some(disk) = disk_bytes(addr, BAIT_SCAN_LEN) {
+            assert!(inserted_breakpoints(memory, &disk).is_empty());
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! CET Shadow-Stack State Inspection
+//!
+//! # Overview
+//!
+//! With Control-flow Enforcement Technology user shadow stacks enabled,
+//! every CALL also pushes its return address onto a separate, write-
+//! protected stack and every RET faults (SIGSEGV/SEGV_CPERR) if the two
+//! disagree. Four views of that state should line up:
+//!
+//! 1. **CPUID**: CET_SS (leaf 7 ECX bit 7) and CET_IBT (leaf 7 EDX bit 20)
+//! 2. **arch_prctl(ARCH_SHSTK_STATUS)**: features enabled for this thread
+//! 3. **/proc/self/status**: `x86_Thread_features` on supporting kernels
+//! 4. **RDSSP**: a NOP (returns 0) unless shadow stacks are really active
+//!
+//! When shadow stacks are active we also compare the shadow-stack top with
+//! our own return address. A hook that rewrites return addresses to
+//! splice itself into a detector shows up as a mismatch here, before the
+//! RET would kill the process.
+//!
+//! # Why This Fails
+//!
+//! - Shadow stacks need kernel 6.6+, glibc opt-in and an SHSTK-marked
+//!   binary; most runs have CET inactive and only the state is recorded
+//! - Linux does not support user-mode IBT, so only its CPUID bit is read
+//! - Inline hooks that preserve CALL/RET pairing are invisible to CET
+
+use std::fs;
+use core::arch::x86_64::CpuidResult;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+extern "C" {
+    fn read_ssp() -> u64;
+    fn shstk_return_check() -> u64;
+}
+
+/// `arch_prctl` code returning the enabled shadow-stack features
+const ARCH_SHSTK_STATUS: libc::c_int = 0x5005;
+const ARCH_SHSTK_SHSTK: u64 = 1 << 0;
+
+/// Times the return-address check is repeated
+const RETURN_CHECKS: usize = 8;
+
+/// Every view of the process's CET state
+#[derive(Debug, Clone, Default)]
+pub struct CetState {
+    pub cpu_shstk: bool,
+    pub cpu_ibt: bool,
+    /// `None` if the kernel does not implement ARCH_SHSTK_STATUS
+    pub prctl_features: Option<u64>,
+    /// `None` if /proc/self/status has no `x86_Thread_features` line
+    pub proc_features: Option<Vec<String>>,
+    pub ssp: u64,
+}
+
+impl CetState {
+    pub fn shstk_enabled(&self) -> bool {
+        self.prctl_features.is_some_and(|f| f & ARCH_SHSTK_SHSTK != 0)
+    }
+}
+
+/// Parse the `x86_Thread_features` line of /proc/self/status
+pub fn parse_thread_features(status: &str) -> Option<Vec<String>> {
+    status.lines()
+        .find_map(|line| line.strip_prefix("x86_Thread_features:"))
+        .map(|rest| rest.split_whitespace().map(str::to_string).collect())
+}
+
+/// Contradictions between the views. Returns descriptions.
+pub fn cet_inconsistencies(state: &CetState) -> Vec<String> {
+    let mut out = Vec::new();
+    let enabled = state.shstk_enabled();
+
+    if let Some(proc_features) = &state.proc_features {
+        let proc_shstk = proc_features.iter().any(|f| f == "shstk");
+        if state.prctl_features.is_some() && proc_shstk != enabled {
+            out.push(reason!("arch_prctl shstk={} but /proc x86_Thread_features shstk={}", enabled, proc_shstk));
+        }
+    }
+    if enabled && !state.cpu_shstk {
+        out.push(reason!("shadow stack enabled but CPUID lacks CET_SS"));
+    }
+    if enabled && state.ssp == 0 {
+        out.push(reason!("shadow stack enabled but RDSSP executed as a NOP"));
+    }
+    if !enabled && state.ssp != 0 {
+        out.push(reason!("shadow stack reported off but RDSSP returned {:#x}", state.ssp));
+    }
+
+    out
+}
+
+#[allow(unused_unsafe)] // `__cpuid` is only safe on newer toolchains
+fn cpuid_cet() -> (bool, bool) {
+    if unsafe { core::arch::x86_64::__cpuid(0) }.eax < 7 {
+        return (false, false);
+    }
+    let leaf7: CpuidResult = unsafe { core::arch::x86_64::__cpuid_count(7, 0) };
+    (leaf7.ecx & (1 << 7) != 0, leaf7.edx & (1 << 20) != 0)
+}
+
+fn prctl_features() -> Option<u64> {
+    let mut features: u64 = 0;
+    // SAFETY: ARCH_SHSTK_STATUS writes a single u64 to the pointer
+    let ret = unsafe { libc::syscall(libc::SYS_arch_prctl, ARCH_SHSTK_STATUS, &mut features as *mut u64) };
+    if ret == 0 { Some(features) } else { None }
+}
+
+fn collect_state() -> CetState {
+    let (cpu_shstk, cpu_ibt) = cpuid_cet();
+    CetState {
+        cpu_shstk,
+        cpu_ibt,
+        prctl_features: prctl_features(),
+        proc_features: fs::read_to_string("/proc/self/status").ok().and_then(|s| parse_thread_features(&s)),
+        // SAFETY: RDSSP is a NOP when shadow stacks are inactive
+        ssp: unsafe { read_ssp() },
+    }
+}
+
+/// Main entry point for the CET state inspection
+pub fn check_cet_state(engine: &mut DecisionEngine) {
+    let state = collect_state();
+    diag!("[CET] CPUID shstk={} ibt={} arch_prctl={:?} proc={:?} ssp={:#x}",
+          state.cpu_shstk, state.cpu_ibt, state.prctl_features, state.proc_features, state.ssp);
+    engine.record_flag("cet_shstk_enabled", state.shstk_enabled());
+
+    let inconsistencies = cet_inconsistencies(&state);
+    engine.record_feature("cet_inconsistencies", inconsistencies.len() as f64);
+    if !inconsistencies.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::CrossView,
+            35,
+            0.7,
+            &reason!("CET state inconsistent: {}", inconsistencies.join("; "))
+        );
+    }
+
+    if !state.shstk_enabled() || state.ssp == 0 {
+        return;
+    }
+
+    // SAFETY: shadow stacks are active, so RDSSP returns a readable pointer
+    let mismatches = (0..RETURN_CHECKS)
+        .filter(|_| unsafe { shstk_return_check() } == 1)
+        .count();
+    if mismatches > 0 {
+        engine.report_with_confidence(
+            DetectionSource::Integrity,
+            60,
+            0.9,
+            &reason!("Shadow-stack top disagreed with the return address in {}/{} calls", mismatches, RETURN_CHECKS)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_thread_features() {
+        let status = "Name:\tx\nx86_Thread_features:\tshstk wrss\nx86_Thread_features_locked:\tshstk\n";
+        assert_eq!(parse_thread_features(status), Some(vec!["shstk".to_string(), "wrss".to_string()]));
+        assert_eq!(parse_thread_features("Name:\tx\n"), None);
+    }
+
+    #[test]
+    fn test_inconsistencies() {
+        let off = CetState { prctl_features: Some(0), proc_features: Some(vec![]), ..Default::default() };
+        assert!(cet_inconsistencies(&off).is_empty());
+
+        let fake = CetState { prctl_features: Some(ARCH_SHSTK_SHSTK), proc_features: Some(vec![]), ..Default::default() };
+        assert_eq!(cet_inconsistencies(&fake).len(), 3);
+    }
+
+    #[test]
+    fn test_rdssp_matches_kernel_state() {
+        let state = collect_state();
+        assert_eq!(state.ssp != 0, state.shstk_enabled());
+    }
+}
@@ -0,0 +1,258 @@
+//! Wall-Clock Jump Monitor
+//!
+//! # Overview
+//!
+//! `CLOCK_MONOTONIC` only ever runs forward at the clocksource's rate;
+//! `CLOCK_REALTIME` is the same clock plus an offset the kernel changes
+//! when time is set, and `CLOCK_BOOTTIME` adds the time spent suspended.
+//! A background thread samples all three for the whole scan and watches the
+//! two offsets:
+//!
+//! | Observation                                   | Typical cause                                | Weight |
+//! |-----------------------------------------------|----------------------------------------------|--------|
+//! | Realtime offset steps, during a long gap      | Paused at a breakpoint (or VM paused), time  | 45     |
+//! |                                               | resynced on resume                           |        |
+//! | Realtime offset steps                         | Clock set mid-run (snapshot restore, tools)  | 35     |
+//! | Boottime offset steps                         | Machine or VM suspended while we ran         | 30     |
+//! | Realtime drifts faster than NTP may slew      | Accelerated or rewritten wall clock          | 20     |
+//!
+//! Evidence goes to the `ExecutionGap` source. Long gaps on their own
+//! (a stop without a clock step) are the timer watchdog's (`watchdog.rs`).
+//!
+//! # Why This Fails
+//!
+//! - A pause that is not followed by a clock resync leaves both offsets
+//!   unchanged
+//! - NTP stepping the clock on its own during the scan looks the same
+//!   (rare outside boot)
+//! - Steps smaller than the jump threshold are ignored
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Sampling period
+pub const INTERVAL: Duration = Duration::from_millis(50);
+
+/// Offset change that counts as a step
+const JUMP_NS: i64 = 50_000_000;
+
+/// Gap between samples that counts as a pause
+const GAP_NS: u64 = 500_000_000;
+
+/// Largest rate difference NTP may slew the wall clock by (500 ppm), with
+/// margin
+const MAX_DRIFT_PPM: f64 = 2_000.0;
+
+/// Shortest run the drift is judged over
+const MIN_DRIFT_WINDOW_NS: u64 = 200_000_000;
+
+/// The three clocks, read together
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClockSample {
+    pub realtime_ns: i64,
+    pub monotonic_ns: i64,
+    pub boottime_ns: i64,
+}
+
+impl ClockSample {
+    fn take() -> Self {
+        Self {
+            monotonic_ns: clock_ns(libc::CLOCK_MONOTONIC),
+            realtime_ns: clock_ns(libc::CLOCK_REALTIME),
+            boottime_ns: clock_ns(libc::CLOCK_BOOTTIME),
+        }
+    }
+
+    fn realtime_offset(&self) -> i64 {
+        self.realtime_ns - self.monotonic_ns
+    }
+
+    fn boottime_offset(&self) -> i64 {
+        self.boottime_ns - self.monotonic_ns
+    }
+}
+
+/// One discontinuity between consecutive samples
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClockEvent {
+    /// Realtime moved by `step_ns` against monotonic across a `gap_ns` interval
+    RealtimeStep { step_ns: i64, gap_ns: u64 },
+    /// Boottime gained `suspended_ns` against monotonic
+    Suspended { suspended_ns: i64 },
+}
+
+/// Discontinuities between two consecutive samples
+pub fn classify_step(prev: &ClockSample, cur: &ClockSample) -> Vec<ClockEvent> {
+    let mut events = Vec::new();
+    let step_ns = cur.realtime_offset() - prev.realtime_offset();
+    if step_ns.abs() >= JUMP_NS {
+        events.push(ClockEvent::RealtimeStep { step_ns, gap_ns: (cur.monotonic_ns - prev.monotonic_ns).max(0) as u64 });
+    }
+    let suspended_ns = cur.boottime_offset() - prev.boottime_offset();
+    if suspended_ns >= JUMP_NS {
+        events.push(ClockEvent::Suspended { suspended_ns });
+    }
+    events
+}
+
+/// Wall-clock rate relative to monotonic over the run in ppm, with the
+/// steps already reported taken out; `None` if the run is too short
+pub fn drift_ppm(first: &ClockSample, last: &ClockSample, stepped_ns: i64) -> Option<f64> {
+    let window = last.monotonic_ns - first.monotonic_ns;
+    if window < MIN_DRIFT_WINDOW_NS as i64 {
+        return None;
+    }
+    let drift = last.realtime_offset() - first.realtime_offset() - stepped_ns;
+    Some(drift as f64 / window as f64 * 1e6)
+}
+
+fn clock_ns(clock: libc::clockid_t) -> i64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` is a valid out-pointer
+    unsafe { libc::clock_gettime(clock, &mut ts) };
+    ts.tv_sec * 1_000_000_000 + ts.tv_nsec
+}
+
+#[derive(Debug, Default)]
+struct MonitorState {
+    first: Option<ClockSample>,
+    last: Option<ClockSample>,
+    samples: u64,
+    max_gap_ns: u64,
+    events: Vec<ClockEvent>,
+}
+
+impl MonitorState {
+    fn record(&mut self, sample: ClockSample) {
+        if let Some(prev) = self.last {
+            self.max_gap_ns = self.max_gap_ns.max((sample.monotonic_ns - prev.monotonic_ns).max(0) as u64);
+            for event in classify_step(&prev, &sample) {
+                diag!("[CLOCK_JUMP] {:?}", event);
+                self.events.push(event);
+            }
+        }
+        self.first.get_or_insert(sample);
+        self.last = Some(sample);
+        self.samples += 1;
+    }
+}
+
+/// Running monitor; report with [`ClockJumpMonitor::finish`]
+pub struct ClockJumpMonitor {
+    stop: Arc<AtomicBool>,
+    state: Arc<Mutex<MonitorState>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ClockJumpMonitor {
+    /// Start sampling every `interval`. `None` if the thread cannot start.
+    pub fn start(interval: Duration) -> Option<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let state = Arc::new(Mutex::new(MonitorState::default()));
+        state.lock().unwrap_or_else(|e| e.into_inner()).record(ClockSample::take());
+        let (thread_stop, thread_state) = (stop.clone(), state.clone());
+        let thread = crate::engine::threads::spawn_masked("clock-jump", move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                std::thread::sleep(interval);
+                thread_state.lock().unwrap_or_else(|e| e.into_inner()).record(ClockSample::take());
+            }
+        })
+        .ok()?;
+        diag!("[CLOCK_JUMP] Sampling realtime/monotonic/boottime every {} ms", interval.as_millis());
+        Some(Self { stop, state, thread: Some(thread) })
+    }
+
+    /// Stop sampling and report every discontinuity seen during the scan
+    pub fn finish(mut self, engine: &mut DecisionEngine) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.record(ClockSample::take());
+
+        let mut stepped_ns = 0;
+        for event in &state.events {
+            match *event {
+                ClockEvent::RealtimeStep { step_ns, gap_ns } => {
+                    stepped_ns += step_ns;
+                    let paused = gap_ns >= GAP_NS;
+                    engine.report_with_confidence(
+                        DetectionSource::ExecutionGap,
+                        if paused { 45 } else { 35 },
+                        if paused { 0.85 } else { 0.75 },
+                        &reason!("Wall clock stepped {:+} ms against monotonic time{}",
+                                 step_ns / 1_000_000,
+                                 if paused { reason!(" after a {} ms gap: paused and resynced", gap_ns / 1_000_000) } else { String::new() })
+                    );
+                }
+                ClockEvent::Suspended { suspended_ns } => engine.report_with_confidence(
+                    DetectionSource::ExecutionGap,
+                    30,
+                    0.7,
+                    &reason!("System suspended for {} ms while the scan ran", suspended_ns / 1_000_000)
+                ),
+            }
+        }
+        let drift = match (state.first, state.last) {
+            (Some(first), Some(last)) => drift_ppm(&first, &last, stepped_ns),
+            _ => None,
+        };
+        diag!("[CLOCK_JUMP] {} samples, longest gap {:.1} ms, {} events, drift {:?} ppm",
+              state.samples, state.max_gap_ns as f64 / 1e6, state.events.len(), drift);
+        engine.record_feature("clock_jump_events", state.events.len() as f64);
+        engine.record_feature("clock_drift_ppm", drift.unwrap_or(0.0));
+        if let Some(ppm) = drift.filter(|p| p.abs() > MAX_DRIFT_PPM) {
+            engine.report_with_confidence(
+                DetectionSource::ExecutionGap,
+                20,
+                0.6,
+                &reason!("Wall clock ran {:+.0} ppm against monotonic time, beyond what NTP may slew", ppm)
+            );
+        }
+    }
+}
+
+impl Drop for ClockJumpMonitor {
+    fn drop(&mut self) {
+        // Unreported monitors just stop; the thread exits after its next sample
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(mono_ms: i64, real_offset_ms: i64, boot_offset_ms: i64) -> ClockSample {
+        let ms = 1_000_000;
+        ClockSample {
+            monotonic_ns: mono_ms * ms,
+            realtime_ns: (1_700_000_000_000 + mono_ms + real_offset_ms) * ms,
+            boottime_ns: (mono_ms + boot_offset_ms) * ms,
+        }
+    }
+
+    #[test]
+    fn test_classify_step() {
+        let ms = 1_000_000;
+        assert!(classify_step(&sample(0, 0, 0), &sample(50, 0, 0)).is_empty());
+        // Paused 10 s, then the wall clock was resynced 3 s ahead
+        assert_eq!(classify_step(&sample(0, 0, 0), &sample(10_000, 3_000, 0)),
+                   vec![ClockEvent::RealtimeStep { step_ns: 3_000 * ms, gap_ns: 10_000_000_000 }]);
+        assert_eq!(classify_step(&sample(0, 0, 0), &sample(50, 0, 2_000)),
+                   vec![ClockEvent::Suspended { suspended_ns: 2_000 * ms }]);
+    }
+
+    #[test]
+    fn test_drift_ppm() {
+        // 1 ms gained over 1 s
+        assert_eq!(drift_ppm(&sample(0, 0, 0), &sample(1_000, 1, 0), 0), Some(1_000.0));
+        // A reported 5 s step does not count as drift
+        assert_eq!(drift_ppm(&sample(0, 0, 0), &sample(1_000, 5_000, 0), 5_000_000_000), Some(0.0));
+        assert_eq!(drift_ppm(&sample(0, 0, 0), &sample(100, 0, 0), 0), None);
+    }
+}
@@ -0,0 +1,309 @@
+//! Multi-Clock Cross-Correlation
+//!
+//! # Overview
+//!
+//! Every clock the process can read has its own path to the hardware, and
+//! each way of tampering with time bends a different subset of them. We
+//! take snapshots of five clocks as close together as possible, spread over
+//! a busy window, and fit each clock's rate against `CLOCK_MONOTONIC_RAW`:
+//!
+//! | Clock                      | Path                                   |
+//! |----------------------------|----------------------------------------|
+//! | TSC                        | `RDTSC`, no kernel involvement         |
+//! | `CLOCK_MONOTONIC_RAW`      | vDSO, unslewed clocksource             |
+//! | `CLOCK_BOOTTIME`           | vDSO, monotonic plus suspend time      |
+//! | `CLOCK_PROCESS_CPUTIME_ID` | syscall, scheduler accounting          |
+//! | `times()`                  | syscall, jiffies                       |
+//!
+//! The divergence pattern names the cause:
+//!
+//! | Pattern                                         | Cause                          | Source          | Weight |
+//! |-------------------------------------------------|--------------------------------|-----------------|--------|
+//! | Wall clocks agree, CPU time lags                | Stopped (breakpoint, SIGSTOP)  | CpuAccounting   | 30     |
+//! | `clock_gettime` disagrees with `times()`/TSC    | Time-dilating sandbox, hooks   | Sandbox         | 40     |
+//! | TSC rate off its CPUID nominal, kernel agrees   | TSC scaled or rewritten        | Timing          | 30     |
+//! | Every snapshot takes tens of microseconds       | Clock reads trapped (rr)       | RecordReplay    | 30     |
+//!
+//! # Why This Fails
+//!
+//! - `times()` ticks are 10 ms; a dilation within that tolerance over the
+//!   window is invisible
+//! - A sandbox that rewrites every clock consistently (including the TSC
+//!   through the hypervisor) leaves ratios of 1
+//! - The TSC is only checked where CPUID reports its nominal frequency
+//!   (leaf 0x15 or the hypervisor's 0x40000010)
+//! - Heavy contention lowers the CPU-time ratio like a stop does; the
+//!   threshold is far below what scheduling alone produces
+
+use core::arch::x86_64::{CpuidResult, __cpuid};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::salt::jitter;
+use crate::ffi::get_rdtsc;
+
+/// Snapshots taken; the window is `SNAPSHOTS - 1` steps long
+const SNAPSHOTS: usize = 16;
+
+/// Busy time between snapshots
+const STEP_NS: u64 = 10_000_000;
+
+/// Median snapshot duration above which clock reads are trapped (salted
+/// +-20%; natively a few microseconds, even where the clocksource forces
+/// `clock_gettime` into a syscall)
+const SLOW_SNAPSHOT_NS: u64 = jitter(20_000, 20, "clock_xcheck.slow_snapshot_ns");
+
+/// CPU time per wall second below which the process was stopped
+const STOPPED_RATIO: f64 = 0.5;
+
+/// Allowed disagreement between vDSO clocks (NTP slew is <= 500 ppm)
+const KERNEL_TOLERANCE: f64 = 0.01;
+
+/// Allowed disagreement between the TSC and its nominal frequency
+const TSC_TOLERANCE: f64 = 0.03;
+
+/// All clocks, read back to back
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Snapshot {
+    pub tsc: u64,
+    pub raw_ns: u64,
+    pub boot_ns: u64,
+    pub cpu_ns: u64,
+    pub ticks: u64,
+    /// `CLOCK_MONOTONIC_RAW` time the snapshot itself took
+    pub read_ns: u64,
+}
+
+/// Rates of the other clocks against `CLOCK_MONOTONIC_RAW` (1.0 = agree)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockRatios {
+    pub boot: f64,
+    pub cpu: f64,
+    pub ticks: f64,
+    /// TSC rate over its nominal frequency, if CPUID reports one
+    pub tsc: Option<f64>,
+    /// Median snapshot duration
+    pub read_ns: u64,
+}
+
+/// One divergence pattern
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClockFinding {
+    Stopped { cpu_ratio: f64 },
+    Rewritten { detail: String },
+    TscScaled { ratio: f64 },
+    SlowReads { read_ns: u64 },
+}
+
+/// Least-squares slope of `ys` against `xs`
+pub fn fit_slope(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let n = xs.len().min(ys.len());
+    if n < 2 {
+        return None;
+    }
+    let (mx, my) = (xs[..n].iter().sum::<f64>() / n as f64, ys[..n].iter().sum::<f64>() / n as f64);
+    let sxx: f64 = xs[..n].iter().map(|x| (x - mx).powi(2)).sum();
+    let sxy: f64 = xs[..n].iter().zip(&ys[..n]).map(|(x, y)| (x - mx) * (y - my)).sum();
+    (sxx > 0.0).then(|| sxy / sxx)
+}
+
+/// Fit every clock against `CLOCK_MONOTONIC_RAW`; ticks are `tick_ns` long
+/// and the TSC nominally runs at `tsc_hz`
+pub fn ratios(snapshots: &[Snapshot], tick_ns: f64, tsc_hz: Option<f64>) -> Option<ClockRatios> {
+    let first = snapshots.first()?;
+    let rel = |f: fn(&Snapshot) -> u64| -> Vec<f64> { snapshots.iter().map(|s| f(s).wrapping_sub(f(first)) as f64).collect() };
+    let raw = rel(|s| s.raw_ns);
+    let mut reads: Vec<u64> = snapshots.iter().map(|s| s.read_ns).collect();
+    reads.sort_unstable();
+    Some(ClockRatios {
+        boot: fit_slope(&raw, &rel(|s| s.boot_ns))?,
+        cpu: fit_slope(&raw, &rel(|s| s.cpu_ns))?,
+        ticks: fit_slope(&raw, &rel(|s| s.ticks))? * tick_ns,
+        tsc: tsc_hz.and_then(|hz| Some(fit_slope(&raw, &rel(|s| s.tsc))? * 1e9 / hz)),
+        read_ns: reads[reads.len() / 2],
+    })
+}
+
+/// Name the divergence pattern; `tick_tolerance` is the `times()` ratio
+/// error its granularity allows over the window
+pub fn classify(r: &ClockRatios, tick_tolerance: f64) -> Vec<ClockFinding> {
+    let mut findings = Vec::new();
+    let ticks_agree = (r.ticks - 1.0).abs() <= tick_tolerance;
+    let boot_agrees = (r.boot - 1.0).abs() <= KERNEL_TOLERANCE;
+    let tsc_agrees = r.tsc.map(|t| (t - 1.0).abs() <= TSC_TOLERANCE);
+
+    if !ticks_agree || !boot_agrees {
+        let mut detail = Vec::new();
+        if !ticks_agree {
+            detail.push(reason!("times() runs at {:.2}x CLOCK_MONOTONIC_RAW", r.ticks));
+        }
+        if !boot_agrees {
+            detail.push(reason!("CLOCK_BOOTTIME runs at {:.3}x CLOCK_MONOTONIC_RAW", r.boot));
+        }
+        if let Some(tsc) = r.tsc {
+            detail.push(reason!("TSC at {:.2}x nominal", tsc));
+        }
+        findings.push(ClockFinding::Rewritten { detail: detail.join(", ") });
+    } else if tsc_agrees == Some(false) {
+        findings.push(ClockFinding::TscScaled { ratio: r.tsc.unwrap_or_default() });
+    }
+    // A stop only means something if the wall clocks are trustworthy
+    if findings.is_empty() && r.cpu < STOPPED_RATIO {
+        findings.push(ClockFinding::Stopped { cpu_ratio: r.cpu });
+    }
+    if r.read_ns > SLOW_SNAPSHOT_NS {
+        findings.push(ClockFinding::SlowReads { read_ns: r.read_ns });
+    }
+    findings
+}
+
+fn clock_ns(clock: libc::clockid_t) -> u64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` is a valid out-pointer
+    unsafe { libc::clock_gettime(clock, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+fn snapshot() -> Snapshot {
+    let start = clock_ns(libc::CLOCK_MONOTONIC_RAW);
+    // SAFETY: RDTSC has no preconditions
+    let tsc = unsafe { get_rdtsc() };
+    let boot_ns = clock_ns(libc::CLOCK_BOOTTIME);
+    let cpu_ns = clock_ns(libc::CLOCK_PROCESS_CPUTIME_ID);
+    // SAFETY: `tms` is plain old data and a valid out-pointer
+    let ticks = unsafe {
+        let mut tms: libc::tms = std::mem::zeroed();
+        libc::times(&mut tms)
+    } as u64;
+    let end = clock_ns(libc::CLOCK_MONOTONIC_RAW);
+    // The raw reading is the midpoint, the others were taken around it
+    Snapshot { tsc, raw_ns: start + (end - start) / 2, boot_ns, cpu_ns, ticks, read_ns: end - start }
+}
+
+#[allow(unused_unsafe)] // `__cpuid` is only safe on newer toolchains
+fn cpuid(leaf: u32) -> CpuidResult {
+    unsafe { __cpuid(leaf) }
+}
+
+/// Nominal TSC frequency from CPUID leaf 0x15, or the hypervisor timing
+/// leaf 0x40000010 (kHz)
+fn nominal_tsc_hz() -> Option<f64> {
+    if cpuid(0).eax >= 0x15 {
+        let leaf = cpuid(0x15);
+        if leaf.eax != 0 && leaf.ebx != 0 && leaf.ecx != 0 {
+            return Some(leaf.ecx as f64 * leaf.ebx as f64 / leaf.eax as f64);
+        }
+    }
+    let hypervisor = cpuid(1).ecx & (1 << 31) != 0;
+    (hypervisor && cpuid(0x4000_0000).eax >= 0x4000_0010)
+        .then(|| cpuid(0x4000_0010).eax as f64 * 1e3)
+        .filter(|hz| *hz > 0.0)
+}
+
+/// Main entry point for the multi-clock cross-check
+pub fn check_clock_xcheck(engine: &mut DecisionEngine) {
+    // SAFETY: sysconf has no preconditions
+    let hz = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if hz <= 0 {
+        engine.record_diagnostic("clock_xcheck", &reason!("sysconf(_SC_CLK_TCK) failed"));
+        return;
+    }
+    let tick_ns = 1e9 / hz as f64;
+
+    let mut snapshots = Vec::with_capacity(SNAPSHOTS);
+    let mut acc: u64 = 0;
+    for _ in 0..SNAPSHOTS {
+        let taken = snapshot();
+        snapshots.push(taken);
+        while clock_ns(libc::CLOCK_MONOTONIC_RAW).saturating_sub(taken.raw_ns) < STEP_NS {
+            for i in 0..1000u64 {
+                acc = std::hint::black_box(acc.wrapping_add(i));
+            }
+        }
+    }
+    std::hint::black_box(acc);
+
+    let tsc_hz = nominal_tsc_hz();
+    let Some(r) = ratios(&snapshots, tick_ns, tsc_hz) else {
+        engine.record_diagnostic("clock_xcheck", &reason!("CLOCK_MONOTONIC_RAW did not advance"));
+        return;
+    };
+    let window_ns = snapshots[SNAPSHOTS - 1].raw_ns.saturating_sub(snapshots[0].raw_ns) as f64;
+    let tick_tolerance = 2.0 * tick_ns / window_ns.max(1.0) + 0.02;
+    let findings = classify(&r, tick_tolerance);
+    diag!("[CLOCK_XCHECK] boot={:.4} cpu={:.3} ticks={:.3} (+-{:.3}) tsc={:?} (nominal {:?} Hz) read={} ns: {:?}",
+          r.boot, r.cpu, r.ticks, tick_tolerance, r.tsc, tsc_hz, r.read_ns, findings);
+
+    engine.record_feature("clock_cpu_ratio", r.cpu);
+    engine.record_feature("clock_tick_ratio", r.ticks);
+    engine.record_feature("clock_snapshot_ns", r.read_ns as f64);
+    for finding in findings {
+        match finding {
+            ClockFinding::Stopped { cpu_ratio } => engine.report_with_confidence(
+                DetectionSource::CpuAccounting,
+                30,
+                0.7,
+                &reason!("Clocks agree but the process accrued {:.0}% CPU time in a busy window: it was stopped", cpu_ratio * 100.0)
+            ),
+            ClockFinding::Rewritten { detail } => engine.report_with_confidence(
+                DetectionSource::Sandbox,
+                40,
+                0.8,
+                &reason!("clock_gettime disagrees with independent clocks (time dilation or clock hooks): {}", detail)
+            ),
+            ClockFinding::TscScaled { ratio } => engine.report_with_confidence(
+                DetectionSource::Timing,
+                30,
+                0.7,
+                &reason!("TSC runs at {:.3}x its CPUID nominal frequency while kernel clocks agree: TSC scaled or rewritten", ratio)
+            ),
+            ClockFinding::SlowReads { read_ns } => engine.report_with_confidence(
+                DetectionSource::RecordReplay,
+                30,
+                0.7,
+                &reason!("Reading five clocks takes {} us: clock reads are trapped and emulated", read_ns / 1000)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(raw_step: u64, f: impl Fn(u64) -> Snapshot) -> Vec<Snapshot> {
+        (0..10).map(|i| Snapshot { raw_ns: i * raw_step, read_ns: 500, ..f(i * raw_step) }).collect()
+    }
+
+    #[test]
+    fn test_fit_slope() {
+        assert_eq!(fit_slope(&[0.0, 1.0, 2.0], &[1.0, 3.0, 5.0]), Some(2.0));
+        assert_eq!(fit_slope(&[1.0, 1.0], &[0.0, 5.0]), None);
+        assert_eq!(fit_slope(&[1.0], &[1.0]), None);
+    }
+
+    #[test]
+    fn test_classify_patterns() {
+        let ms = 1_000_000;
+        // Native: everything at 1x, 3 GHz TSC, 100 Hz ticks
+        let native = series(10 * ms, |t| Snapshot { tsc: t * 3, boot_ns: t + 7, cpu_ns: t, ticks: t / (10 * ms), ..Snapshot::default() });
+        let r = ratios(&native, 1e7, Some(3e9)).unwrap();
+        assert!(classify(&r, 0.2).is_empty(), "{:?}", r);
+
+        // Stopped: CPU time barely moves
+        let stopped = series(10 * ms, |t| Snapshot { tsc: t * 3, boot_ns: t, cpu_ns: t / 10, ticks: t / (10 * ms), ..Snapshot::default() });
+        let r = ratios(&stopped, 1e7, Some(3e9)).unwrap();
+        assert!(matches!(classify(&r, 0.2)[..], [ClockFinding::Stopped { .. }]));
+
+        // clock_gettime sped up 4x (times() and TSC untouched)
+        let dilated = series(40 * ms, |t| Snapshot { tsc: t * 3 / 4, boot_ns: t, cpu_ns: t, ticks: t / (40 * ms), ..Snapshot::default() });
+        let r = ratios(&dilated, 1e7, Some(3e9)).unwrap();
+        assert!(matches!(classify(&r, 0.2)[..], [ClockFinding::Rewritten { .. }]));
+
+        // TSC at half speed, kernel clocks fine
+        let scaled = series(10 * ms, |t| Snapshot { tsc: t * 3 / 2, boot_ns: t, cpu_ns: t, ticks: t / (10 * ms), ..Snapshot::default() });
+        let r = ratios(&scaled, 1e7, Some(3e9)).unwrap();
+        assert!(matches!(classify(&r, 0.2)[..], [ClockFinding::TscScaled { .. }]));
+        // Without a nominal frequency the TSC is not judged
+        let r = ratios(&scaled, 1e7, None).unwrap();
+        assert!(classify(&r, 0.2).is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+//! Command-Line Cross-View Check (argv vs /proc/self/cmdline)
+//!
+//! # Overview
+//!
+//! The kernel remembers how a process was started: `/proc/self/cmdline` and
+//! `/proc/self/environ` are read from the `arg_start..arg_end` and
+//! `env_start..env_end` ranges it set up at `execve`, and
+//! `/proc/self/exe` names the file it executed. Inside the process, `argv`
+//! and `envp` are whatever the loader handed to `main`. Wrappers and
+//! exec-loaders that rewrite the process identity make the two disagree:
+//!
+//! | Check                                            | Typical cause                            | Weight |
+//! |--------------------------------------------------|------------------------------------------|--------|
+//! | cmdline has extra leading arguments              | Started through `ld.so` or a loader      | 25     |
+//! | cmdline and argv otherwise differ                | Arguments rewritten, cmdline repointed   | 35     |
+//! | `/proc/self/exe` is the dynamic loader           | `ld-linux-x86-64.so.2 ./app`             | 30     |
+//! | Our code is not mapped from `/proc/self/exe`     | Userland exec, reflective loading        | 35-40  |
+//! | `argv[0]` / `envp[0]` outside the kernel ranges  | `PR_SET_MM_*` repointing, forged vectors | 30     |
+//! | `argv[0]` and `AT_EXECFN` name different files   | `exec -a`, launcher renaming us          | 10     |
+//!
+//! The `argv[0]`/`envp[0]` addresses come from the pre-main constructor
+//! (`premain::initial_vectors`); the contents of the environment are
+//! compared by `environ.rs`.
+//!
+//! # Why This Fails
+//!
+//! - Tools that rewrite the strings in place (`setproctitle`-style) keep
+//!   both views identical
+//! - Shells and launchers legitimately set `argv[0]` (login shells,
+//!   `exec -a`, busybox-style multi-call binaries), which is why that
+//!   check weighs little
+//! - A loader that maps us from a real file and execs nothing leaves no
+//!   mismatch to find
+
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use crate::detectors::maps_anomaly::parse_regions;
+use crate::detectors::premain;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// How the kernel's argument list relates to ours
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgvView {
+    Same,
+    /// The kernel view has these arguments in front of ours
+    Prefixed(Vec<String>),
+    Different,
+}
+
+/// Split a NUL-separated /proc file into its strings
+pub fn split_nul(raw: &[u8]) -> Vec<String> {
+    let Some(last) = raw.iter().rposition(|&b| b != 0) else { return Vec::new() };
+    raw[..=last].split(|&b| b == 0).map(|s| String::from_utf8_lossy(s).into_owned()).collect()
+}
+
+/// Compare /proc/self/cmdline with our argv
+pub fn compare_argv(kernel: &[String], live: &[String]) -> ArgvView {
+    if kernel == live {
+        ArgvView::Same
+    } else if !live.is_empty() && kernel.len() > live.len() && kernel.ends_with(live) {
+        ArgvView::Prefixed(kernel[..kernel.len() - live.len()].to_vec())
+    } else {
+        ArgvView::Different
+    }
+}
+
+/// Whether `path` is the dynamic loader itself
+pub fn is_dynamic_loader(path: &str) -> bool {
+    let file = path.rsplit('/').next().unwrap_or(path).trim_end_matches(" (deleted)");
+    file.starts_with("ld-linux") || file.starts_with("ld.so") || file.starts_with("ld64.so")
+}
+
+/// (arg_start..arg_end, env_start..env_end) from /proc/self/stat
+pub fn vector_ranges(stat: &str) -> Option<(std::ops::Range<usize>, std::ops::Range<usize>)> {
+    // Fields 48-51 of /proc/self/stat; the fields after "(comm)" start at 3
+    let fields: Vec<usize> = stat.rsplit_once(')')?.1.split_whitespace()
+        .skip(45)
+        .take(4)
+        .map(|f| f.parse().ok())
+        .collect::<Option<_>>()?;
+    (fields.len() == 4).then(|| (fields[0]..fields[1], fields[2]..fields[3]))
+}
+
+fn basename(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// The string `AT_EXECFN` points at: the path given to `execve`
+fn execfn() -> Option<String> {
+    // SAFETY: getauxval has no preconditions
+    let at = unsafe { libc::getauxval(libc::AT_EXECFN) };
+    // SAFETY: the kernel puts a NUL-terminated string there
+    (at != 0).then(|| unsafe { std::ffi::CStr::from_ptr(at as *const libc::c_char) }.to_string_lossy().into_owned())
+}
+
+/// Mismatch between /proc/self/exe and the file our code is mapped from
+fn exe_mismatch() -> Option<(String, u32, f64)> {
+    let exe = fs::metadata("/proc/self/exe").ok()?;
+    let maps = fs::read_to_string("/proc/self/maps").ok()?;
+    let here = check_cmdline_view as fn(&mut DecisionEngine) as usize as u64;
+    let region = parse_regions(&maps).into_iter().find(|r| (r.start..r.end).contains(&here))?;
+    if region.inode == 0 {
+        Some((reason!("our code runs from anonymous memory {:#x}-{:#x}, not from /proc/self/exe", region.start, region.end), 40, 0.85))
+    } else if region.inode != exe.ino() {
+        Some((reason!("our code is mapped from {}, but /proc/self/exe is another file", region.path), 35, 0.8))
+    } else {
+        None
+    }
+}
+
+/// Main entry point for the command-line cross-view check
+pub fn check_cmdline_view(engine: &mut DecisionEngine) {
+    let Ok(cmdline) = fs::read("/proc/self/cmdline") else {
+        engine.record_diagnostic("cmdline_view", &reason!("/proc/self/cmdline unreadable"));
+        return;
+    };
+    let kernel = split_nul(&cmdline);
+    let live: Vec<String> = std::env::args_os().map(|a| a.to_string_lossy().into_owned()).collect();
+    let exe = fs::read_link("/proc/self/exe").map(|p| p.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut findings: Vec<(String, u32, f64)> = Vec::new();
+
+    match compare_argv(&kernel, &live) {
+        ArgvView::Same => {}
+        ArgvView::Prefixed(prefix) => findings.push((reason!("cmdline starts with {:?} before our argv", prefix), 25, 0.7)),
+        ArgvView::Different => findings.push((reason!("cmdline {:?} differs from argv {:?}", kernel, live), 35, 0.8)),
+    }
+    if is_dynamic_loader(&exe) {
+        findings.push((reason!("/proc/self/exe is the dynamic loader {}", exe), 30, 0.75));
+    }
+    findings.extend(exe_mismatch());
+
+    let (argv0, envp0) = premain::initial_vectors();
+    let ranges = fs::read_to_string("/proc/self/stat").ok().and_then(|s| vector_ranges(&s));
+    if let Some((args, env)) = &ranges {
+        if argv0 != 0 && !args.contains(&argv0) {
+            findings.push((reason!("argv[0] at {:#x} lies outside the kernel's argument block {:#x}-{:#x}", argv0, args.start, args.end), 30, 0.75));
+        }
+        if envp0 != 0 && !env.contains(&envp0) {
+            findings.push((reason!("envp[0] at {:#x} lies outside the kernel's environment block {:#x}-{:#x}", envp0, env.start, env.end), 30, 0.75));
+        }
+    }
+    let execfn = execfn();
+    if let (Some(execfn), Some(argv0)) = (&execfn, live.first()) {
+        if basename(execfn) != basename(argv0) {
+            findings.push((reason!("argv[0] {:?} names a different file than execve's {:?}", argv0, execfn), 10, 0.4));
+        }
+    }
+    diag!("[CMDLINE] cmdline={:?} argv={:?} exe={} execfn={:?} ranges={:x?} findings={}", kernel, live, exe, execfn, ranges, findings.len());
+
+    engine.record_feature("cmdline_view_mismatches", findings.len() as f64);
+    if findings.is_empty() {
+        return;
+    }
+    let weight = findings.iter().map(|(_, w, _)| w).sum::<u32>().min(60);
+    let confidence = findings.iter().map(|(_, _, c)| *c).fold(0.0, f64::max);
+    let details: Vec<&str> = findings.iter().map(|(d, _, _)| d.as_str()).collect();
+    engine.report_with_confidence(
+        DetectionSource::CrossView,
+        weight,
+        confidence,
+        &reason!("Process identity rewritten by a wrapper or loader: {}", details.join("; "))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_compare_argv() {
+        assert_eq!(split_nul(b"./app\0-v\0\0"), strings(&["./app", "-v"]));
+        let live = strings(&["./app", "-v"]);
+        assert_eq!(compare_argv(&live, &live), ArgvView::Same);
+        let kernel = strings(&["/lib64/ld-linux-x86-64.so.2", "./app", "-v"]);
+        assert_eq!(compare_argv(&kernel, &live), ArgvView::Prefixed(strings(&["/lib64/ld-linux-x86-64.so.2"])));
+        assert_eq!(compare_argv(&strings(&["./app", "-q"]), &live), ArgvView::Different);
+        assert!(is_dynamic_loader("/usr/lib/x86_64-linux-gnu/ld-linux-x86-64.so.2"));
+        assert!(!is_dynamic_loader("/usr/bin/ldd"));
+    }
+
+    #[test]
+    fn test_vector_ranges() {
+        let stat = "4242 (my app) S 1 4242 4242 0 -1 4194560 100 0 0 0 0 0 0 0 20 0 1 0 500 1000 200 \
+                    18446744073709551615 1 1 0 0 0 0 0 4096 0 0 0 0 17 3 0 0 0 0 0 100 200 300 \
+                    140720000000000 140720000000100 140720000000100 140720000004000 0\n";
+        assert_eq!(vector_ranges(stat), Some((140720000000000..140720000000100, 140720000000100..140720000004000)));
+        assert_eq!(vector_ranges("4242 (app) S 1"), None);
+    }
+
+    #[test]
+    fn test_own_vectors_in_kernel_ranges() {
+        let (args, env) = vector_ranges(&fs::read_to_string("/proc/self/stat").unwrap()).unwrap();
+        let (argv0, envp0) = premain::initial_vectors();
+        assert!(args.contains(&argv0));
+        assert!(envp0 == 0 || env.contains(&envp0));
+    }
+}
@@ -0,0 +1,87 @@
+//! Core-Dump / Forensic-Capture Configuration
+//!
+//! # Overview
+//!
+//! Where a crash of ours would end up is part of the environment model
+//! ([`CoreDumpConfig`], recorded by `environment.rs`). Distributions pipe
+//! `kernel.core_pattern` to their own collector (`systemd-coredump`,
+//! `apport`, `abrt`); analysis sandboxes and forensic setups pipe it to a
+//! helper of their own so every crash of a sample is kept for later. We
+//! look at the helper's path and arguments:
+//!
+//! | Helper                                           | Weight |
+//! |--------------------------------------------------|--------|
+//! | Named like an analysis or forensic tool          | 15     |
+//! | Unknown program outside the system directories   | 5      |
+//!
+//! Both are low confidence: a core-dump helper describes the machine, not
+//! an observer of this run.
+//!
+//! # Why This Fails
+//!
+//! - Inside a container `core_pattern` is the host's, and its helper path
+//!   refers to the host's filesystem
+//! - A sandbox that installs its collector under a distribution name, or
+//!   captures crashes through ptrace instead, leaves nothing here
+
+use crate::engine::environment::CoreDumpConfig;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Distribution crash collectors
+const SYSTEM_COLLECTORS: &[&str] = &["systemd-coredump", "apport", "abrt-hook-ccpp", "abrt-hook", "crash_reporter", "kdumpst", "corekeeper", "false"];
+
+/// Fragments of analysis and forensic helper names
+const ANALYSIS_FRAGMENTS: &[&str] = &["cuckoo", "cape", "sandbox", "forensic", "analysis", "analyzer", "procdump", "triage", "malware", "capture"];
+
+/// Directories system collectors are installed in
+const SYSTEM_DIRS: &[&str] = &["/usr/", "/lib/", "/lib64/", "/sbin/", "/bin/"];
+
+/// Finding for a `core_pattern` pipe helper, as (description, weight, confidence)
+pub fn classify_helper(helper: &str) -> Option<(String, u32, f64)> {
+    let program = helper.split_whitespace().next().unwrap_or("");
+    let name = program.rsplit('/').next().unwrap_or(program);
+    if SYSTEM_COLLECTORS.contains(&name) {
+        return None;
+    }
+    let lower = helper.to_ascii_lowercase();
+    if let Some(fragment) = ANALYSIS_FRAGMENTS.iter().find(|f| lower.contains(*f)) {
+        return Some((reason!("core dumps are piped to {:?}, named like an analysis tool ({})", helper, fragment), 15, 0.4));
+    }
+    if !SYSTEM_DIRS.iter().any(|dir| program.starts_with(dir)) {
+        return Some((reason!("core dumps are piped to {:?}, outside the system directories", helper), 5, 0.3));
+    }
+    None
+}
+
+/// Main entry point for the core-dump configuration check
+pub fn check_core_dump(engine: &mut DecisionEngine) {
+    let config = CoreDumpConfig::detect();
+    let finding = config.pipe_helper().and_then(classify_helper);
+    diag!("[CORE_DUMP] pattern={:?} limit={:?} dumpable={:?} finding={:?}", config.core_pattern, config.core_limit, config.dumpable, finding);
+    if config.core_pattern.is_none() {
+        engine.record_diagnostic("core_dump", &reason!("kernel.core_pattern unreadable"));
+    }
+    engine.record_flag("core_dump_helper_suspicious", finding.is_some());
+    if let Some((details, weight, confidence)) = finding {
+        engine.report_with_confidence(
+            DetectionSource::Sandbox,
+            weight,
+            confidence,
+            &reason!("Forensic capture environment? {}", details)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_helper() {
+        assert_eq!(classify_helper("/usr/lib/systemd/systemd-coredump %P %u %g %s %t 9223372036854775808 %h"), None);
+        assert_eq!(classify_helper("/usr/share/apport/apport -p%p -s%s -c%c"), None);
+        assert_eq!(classify_helper("/opt/cuckoo/bin/dump_core %p").map(|(_, w, _)| w), Some(15));
+        assert_eq!(classify_helper("/home/ops/collect.sh %p").map(|(_, w, _)| w), Some(5));
+        assert_eq!(classify_helper("/usr/sbin/custom-collector %p"), None);
+    }
+}
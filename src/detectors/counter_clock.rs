@@ -0,0 +1,259 @@
+//! Counter-Thread Software Clock
+//!
+//! # Overview
+//!
+//! Every other timing check trusts some hardware or kernel clock. This one
+//! builds its own: a thread pinned to a second CPU increments a shared
+//! counter in a tight loop, and the counter's value is a clock nobody can
+//! virtualize without changing how threads are scheduled.
+//!
+//! We first learn the counter's free-running rate while the measuring
+//! thread sleeps (so the counter has its CPU to itself even if threads are
+//! serialized), then time code blocks of several sizes against both the
+//! counter and `RDTSC`. Natively, the counter advances during a block as
+//! fast as it did in calibration:
+//!
+//! | Counter rate during blocks vs calibration | Meaning                                    | Weight |
+//! |-------------------------------------------|--------------------------------------------|--------|
+//! | ~1                                        | Native                                     | -      |
+//! | Far below 1 (counter starved)             | Threads serialized (rr), or RDTSC trapped  | 35     |
+//! | Far above 1                               | RDTSC runs slow (scaled or virtualized)    | 30     |
+//!
+//! rr records a multi-threaded process by running one thread at a time, so
+//! the counter stops whenever we run; RDTSC, which rr lets through (or
+//! emulates faithfully), does not notice.
+//!
+//! # Why This Fails
+//!
+//! - Needs a second CPU we may run on; with one the counter only moves
+//!   when we are descheduled
+//! - An SMT sibling or a busy second CPU slows the counter; the thresholds
+//!   leave a wide margin and the median of many blocks is used
+//! - A hypervisor stealing the counter's vCPU looks like starvation
+
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use crate::engine::placement::parse_cpu_list;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::ffi::{calibrated_work_loop, get_rdtsc};
+
+/// Work-loop sizes timed against both clocks
+const BLOCK_SIZES: [u64; 3] = [20_000, 200_000, 2_000_000];
+
+/// Blocks per size
+const REPEATS: usize = 8;
+
+/// Sleep the counter's free-running rate is learned over
+const CALIBRATION: Duration = Duration::from_millis(20);
+
+/// Median relative rate below which the counter was starved
+const STARVED_RATE: f64 = 0.25;
+
+/// Median relative rate above which RDTSC runs slow
+const TSC_SLOW_RATE: f64 = 4.0;
+
+/// How long to wait for the counter thread to come up
+const STARTUP_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Counter rate during a block relative to its calibrated rate, from
+/// the block's TSC and counter deltas
+pub fn relative_rate(tsc_ticks: u64, counts: u64, tsc_per_count: f64) -> Option<f64> {
+    (tsc_ticks > 0 && tsc_per_count > 0.0).then(|| counts as f64 * tsc_per_count / tsc_ticks as f64)
+}
+
+/// What the counter says about RDTSC
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CounterFinding {
+    /// The counter barely moved while we ran
+    Starved(f64),
+    /// The counter moved far more than RDTSC said time passed
+    TscSlow(f64),
+}
+
+/// Judge the median relative rate over all blocks
+pub fn assess(rates: &mut [f64]) -> Option<CounterFinding> {
+    if rates.is_empty() {
+        return None;
+    }
+    rates.sort_by(f64::total_cmp);
+    let median = rates[rates.len() / 2];
+    if median < STARVED_RATE {
+        Some(CounterFinding::Starved(median))
+    } else if median > TSC_SLOW_RATE {
+        Some(CounterFinding::TscSlow(median))
+    } else {
+        None
+    }
+}
+
+/// Counter thread pinned to another CPU; stops when dropped
+struct CounterClock {
+    count: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl CounterClock {
+    /// Start on the first of `cpus` the thread can pin to, returning that
+    /// CPU. `None` if none works.
+    fn start(cpus: Vec<usize>) -> Option<(Self, usize)> {
+        let count = Arc::new(AtomicU64::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        // -1 while starting, -2 on failure, else the CPU
+        let placed = Arc::new(AtomicI64::new(-1));
+        let (thread_count, thread_stop, thread_placed) = (count.clone(), stop.clone(), placed.clone());
+        let thread = crate::engine::threads::spawn_masked("counter-clock", move || {
+            let Some(cpu) = cpus.into_iter().find(|&cpu| pin(cpu)) else {
+                thread_placed.store(-2, Ordering::SeqCst);
+                return;
+            };
+            thread_placed.store(cpu as i64, Ordering::SeqCst);
+            let mut n: u64 = 0;
+            while !thread_stop.load(Ordering::Relaxed) {
+                for _ in 0..1024 {
+                    n += 1;
+                    thread_count.store(n, Ordering::Relaxed);
+                }
+            }
+        })
+        .ok()?;
+        let clock = Self { count, stop, thread: Some(thread) };
+        let started = Instant::now();
+        loop {
+            let cpu = placed.load(Ordering::SeqCst);
+            if cpu >= 0 && clock.read() > 0 {
+                return Some((clock, cpu as usize));
+            }
+            if cpu == -2 || started.elapsed() >= STARTUP_TIMEOUT {
+                return None;
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    fn read(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for CounterClock {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn pin(cpu: usize) -> bool {
+    // SAFETY: plain libc call on a stack-owned cpu_set_t
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    }
+}
+
+/// Online CPUs other than the one we are on
+fn other_cpus() -> Vec<usize> {
+    // SAFETY: sched_getcpu has no preconditions
+    let here = unsafe { libc::sched_getcpu() };
+    std::fs::read_to_string("/sys/devices/system/cpu/online")
+        .map(|s| parse_cpu_list(&s))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|&cpu| cpu as i32 != here)
+        .collect()
+}
+
+/// (TSC ticks, counter increments) over one block
+fn time_block(clock: &CounterClock, iterations: u64) -> (u64, u64) {
+    let count_start = clock.read();
+    // SAFETY: RDTSC has no preconditions
+    let tsc_start = unsafe { get_rdtsc() };
+    // SAFETY: fixed-cost assembly loop
+    std::hint::black_box(unsafe { calibrated_work_loop(iterations) });
+    // SAFETY: RDTSC has no preconditions
+    let tsc = unsafe { get_rdtsc() }.wrapping_sub(tsc_start);
+    (tsc, clock.read().wrapping_sub(count_start))
+}
+
+/// Main entry point for the counter-thread clock check
+pub fn check_counter_clock(engine: &mut DecisionEngine) {
+    let candidates = other_cpus();
+    if candidates.is_empty() {
+        engine.record_diagnostic("counter_clock", &reason!("needs a second online CPU"));
+        return;
+    }
+    let Some((clock, cpu)) = CounterClock::start(candidates) else {
+        engine.record_diagnostic("counter_clock", &reason!("counter thread could not be placed on another CPU"));
+        return;
+    };
+
+    // Free-running rate, while we leave the CPU(s) to the counter
+    let count_start = clock.read();
+    // SAFETY: RDTSC has no preconditions
+    let tsc_start = unsafe { get_rdtsc() };
+    std::thread::sleep(CALIBRATION);
+    // SAFETY: as above
+    let tsc_ticks = unsafe { get_rdtsc() }.wrapping_sub(tsc_start);
+    let counts = clock.read().wrapping_sub(count_start);
+    if counts == 0 {
+        engine.record_diagnostic("counter_clock", &reason!("counter did not advance during calibration"));
+        return;
+    }
+    let tsc_per_count = tsc_ticks as f64 / counts as f64;
+
+    let mut rates: Vec<f64> = BLOCK_SIZES.iter()
+        .flat_map(|&size| std::iter::repeat_n(size, REPEATS))
+        .filter_map(|size| {
+            let (tsc, counts) = time_block(&clock, size);
+            relative_rate(tsc, counts, tsc_per_count)
+        })
+        .collect();
+    drop(clock);
+    let finding = assess(&mut rates);
+    let median = rates.get(rates.len() / 2).copied().unwrap_or(0.0);
+    diag!("[COUNTER_CLOCK] counter on CPU {}: {:.2} TSC ticks/count, median block rate {:.2} (min {:.2}, max {:.2}): {:?}",
+          cpu, tsc_per_count, median, rates.first().unwrap_or(&0.0), rates.last().unwrap_or(&0.0), finding);
+    engine.record_feature("counter_clock_rate", median);
+
+    match finding {
+        Some(CounterFinding::Starved(rate)) => engine.report_with_confidence(
+            DetectionSource::RecordReplay,
+            35,
+            0.75,
+            &reason!("Counter thread on CPU {} ran at {:.0}% of its own rate while we executed: threads are serialized (rr) or RDTSC is trapped", cpu, rate * 100.0)
+        ),
+        Some(CounterFinding::TscSlow(rate)) => engine.report_with_confidence(
+            DetectionSource::Timing,
+            30,
+            0.7,
+            &reason!("Counter thread advanced {:.1}x more than RDTSC accounts for: RDTSC is scaled or virtualized", rate)
+        ),
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_rate() {
+        // 3 TSC ticks per count, block of 3000 ticks with 1000 counts
+        assert_eq!(relative_rate(3000, 1000, 3.0), Some(1.0));
+        assert_eq!(relative_rate(3000, 100, 3.0), Some(0.1));
+        assert_eq!(relative_rate(0, 100, 3.0), None);
+    }
+
+    #[test]
+    fn test_assess() {
+        assert_eq!(assess(&mut [0.9, 1.1, 1.0, 0.95]), None);
+        assert_eq!(assess(&mut [0.01, 0.02, 0.0, 1.0, 0.03]), Some(CounterFinding::Starved(0.02)));
+        assert_eq!(assess(&mut [10.0, 9.0, 11.0]), Some(CounterFinding::TscSlow(10.0)));
+        assert_eq!(assess(&mut []), None);
+    }
+}
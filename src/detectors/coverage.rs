@@ -0,0 +1,206 @@
+//! Coverage-Instrumented Build Detection
+//!
+//! # Overview
+//!
+//! Fuzzing and coverage-guided analysis start by rebuilding (or patching)
+//! the target with coverage instrumentation. Our release build never has
+//! any, so finding it in our own image means someone else produced it:
+//!
+//! - **Sections**: `__llvm_prf_cnts` / `__llvm_prf_data` (clang
+//!   `-fprofile-instr-generate`), `__llvm_covmap`, and the
+//!   `__sancov_*` counter / guard / PC tables of `-fsanitize-coverage`
+//! - **Symbols** in `.symtab` / `.dynsym`: `__llvm_profile_*`, `__gcov_*`
+//!   (gcc `--coverage`), `__sanitizer_cov_*` hooks and AFL's `__afl_*`
+//! - **Runtime**: the same hooks resolvable through `dlsym`, which also
+//!   catches a stripped binary that links or preloads a coverage runtime
+//!
+//! The image is read from `/proc/self/exe` and parsed by hand (section
+//! headers and symbol tables only).
+//!
+//! # Why This Fails
+//!
+//! - Hardware tracing (Intel PT) and DBI-based coverage leave the binary
+//!   untouched
+//! - Section and symbol names can be renamed after instrumentation
+//! - Our own `cargo test` / `-C instrument-coverage` builds trip it too
+
+use std::ffi::CString;
+use std::fs;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Section names emitted by coverage instrumentation
+const COVERAGE_SECTIONS: &[&str] = &["__llvm_prf_cnts", "__llvm_prf_data", "__llvm_prf_names", "__llvm_covmap", "__llvm_covfun", "__sancov_cntrs", "__sancov_guards", "__sancov_pcs"];
+
+/// Symbol prefixes of coverage runtimes and hooks
+const COVERAGE_SYMBOLS: &[&str] = &["__llvm_profile_", "__gcov_", "__sanitizer_cov_", "__afl_"];
+
+/// Hooks looked up at runtime
+const RUNTIME_HOOKS: &[&str] = &["__sanitizer_cov_trace_pc", "__sanitizer_cov_trace_pc_guard", "__llvm_profile_write_file", "__gcov_dump", "__afl_area_ptr"];
+
+/// `SHT_SYMTAB` / `SHT_DYNSYM`
+const SHT_SYMTAB: u32 = 2;
+const SHT_DYNSYM: u32 = 11;
+
+/// `Elf64_Sym` size
+const SYM_SIZE: usize = 24;
+
+/// One ELF64 section header, reduced to the fields we need
+#[derive(Debug, Clone, Copy)]
+pub struct Section {
+    pub name: u32,
+    pub kind: u32,
+    pub addr: usize,
+    pub offset: usize,
+    pub size: usize,
+    pub link: u32,
+}
+
+fn u16_at(elf: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(elf.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(elf: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(elf.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn u64_at(elf: &[u8], at: usize) -> Option<usize> {
+    Some(u64::from_le_bytes(elf.get(at..at + 8)?.try_into().ok()?) as usize)
+}
+
+/// Section headers of an ELF64 image
+pub fn sections(elf: &[u8]) -> Option<Vec<Section>> {
+    if !elf.starts_with(b"\x7fELF") {
+        return None;
+    }
+    let (shoff, shentsize, shnum) = (u64_at(elf, 0x28)?, u16_at(elf, 0x3a)? as usize, u16_at(elf, 0x3c)? as usize);
+    (0..shnum)
+        .map(|i| {
+            let at = shoff + i * shentsize;
+            Some(Section {
+                name: u32_at(elf, at)?,
+                kind: u32_at(elf, at + 4)?,
+                addr: u64_at(elf, at + 0x10)?,
+                offset: u64_at(elf, at + 0x18)?,
+                size: u64_at(elf, at + 0x20)?,
+                link: u32_at(elf, at + 0x28)?,
+            })
+        })
+        .collect()
+}
+
+/// NUL-terminated string at `index` in a string table section
+fn string_at<'a>(elf: &'a [u8], table: &Section, index: u32) -> &'a str {
+    elf.get(table.offset + index as usize..table.offset + table.size)
+        .and_then(|s| s.split(|&b| b == 0).next())
+        .and_then(|s| std::str::from_utf8(s).ok())
+        .unwrap_or("")
+}
+
+/// Header of the section called `name`
+pub fn section_named(elf: &[u8], name: &str) -> Option<Section> {
+    let sections = sections(elf)?;
+    let shstrtab = sections.get(u16_at(elf, 0x3e)? as usize)?;
+    sections.iter().copied().find(|s| string_at(elf, shstrtab, s.name) == name)
+}
+
+/// Section names and symbol names (`.symtab` and `.dynsym`) of an ELF64 image
+pub fn elf_names(elf: &[u8]) -> (Vec<String>, Vec<String>) {
+    let Some(sections) = sections(elf) else { return (Vec::new(), Vec::new()) };
+    let section_names = u16_at(elf, 0x3e)
+        .and_then(|i| sections.get(i as usize))
+        .map(|shstrtab| sections.iter().map(|s| string_at(elf, shstrtab, s.name).to_string()).collect())
+        .unwrap_or_default();
+
+    let symbol_names = sections.iter()
+        .filter(|s| s.kind == SHT_SYMTAB || s.kind == SHT_DYNSYM)
+        .filter_map(|symtab| Some((symtab, sections.get(symtab.link as usize)?)))
+        .flat_map(|(symtab, strtab)| {
+            (0..symtab.size / SYM_SIZE)
+                .filter_map(move |i| u32_at(elf, symtab.offset + i * SYM_SIZE))
+                .map(move |name| string_at(elf, strtab, name).to_string())
+        })
+        .filter(|name| !name.is_empty())
+        .collect();
+    (section_names, symbol_names)
+}
+
+/// Coverage sections and distinct coverage symbol families found
+pub fn coverage_markers(sections: &[String], symbols: &[String]) -> Vec<String> {
+    let mut found: Vec<String> = sections.iter()
+        .filter(|name| COVERAGE_SECTIONS.contains(&name.as_str()))
+        .map(|name| format!("section {}", name))
+        .collect();
+    for prefix in COVERAGE_SYMBOLS {
+        let count = symbols.iter().filter(|s| s.starts_with(prefix)).count();
+        if count > 0 {
+            found.push(reason!("{} {}* symbol(s)", count, prefix));
+        }
+    }
+    found
+}
+
+/// Coverage hooks resolvable in the running process
+fn runtime_hooks() -> Vec<&'static str> {
+    RUNTIME_HOOKS.iter()
+        .copied()
+        .filter(|name| {
+            let Ok(symbol) = CString::new(*name) else { return false };
+            // SAFETY: dlsym with a NUL-terminated name only looks the symbol up
+            !unsafe { libc::dlsym(libc::RTLD_DEFAULT, symbol.as_ptr()) }.is_null()
+        })
+        .collect()
+}
+
+/// Main entry point for the coverage-build check
+pub fn check_coverage_build(engine: &mut DecisionEngine) {
+    let mut markers = match fs::read("/proc/self/exe") {
+        Ok(image) => {
+            let (sections, symbols) = elf_names(&image);
+            diag!("[COVERAGE] {} sections, {} symbols", sections.len(), symbols.len());
+            coverage_markers(&sections, &symbols)
+        }
+        Err(e) => {
+            engine.record_diagnostic("coverage", &reason!("/proc/self/exe unreadable: {}", e));
+            Vec::new()
+        }
+    };
+    let hooks = runtime_hooks();
+    if !hooks.is_empty() {
+        markers.push(reason!("runtime resolves {}", hooks.join(", ")));
+    }
+
+    engine.record_feature("coverage_markers", markers.len() as f64);
+    if markers.is_empty() {
+        return;
+    }
+    let (weight, confidence) = if markers.len() == 1 { (30, 0.6) } else { (50, 0.9) };
+    engine.report_with_confidence(
+        DetectionSource::Integrity,
+        weight,
+        confidence,
+        &reason!("Binary carries coverage instrumentation (rebuilt for fuzzing/analysis?): {}", markers.join("; "))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(feature = "stealth", ignore = "evidence text is coded in stealth builds")]
+    fn test_coverage_markers() {
+        let sections = vec![".text".to_string(), "__sancov_guards".to_string()];
+        let symbols = vec!["main".to_string(), "__sanitizer_cov_trace_pc_guard".to_string(), "__sanitizer_cov_trace_cmp4".to_string()];
+        assert_eq!(coverage_markers(&sections, &symbols), vec!["section __sancov_guards", "2 __sanitizer_cov_* symbol(s)"]);
+        assert!(coverage_markers(&[".text".to_string()], &["main".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn test_own_image_parses() {
+        let image = fs::read("/proc/self/exe").unwrap();
+        let (sections, symbols) = elf_names(&image);
+        assert!(sections.iter().any(|s| s == ".text"));
+        assert!(!symbols.is_empty());
+        assert!(elf_names(b"not an elf").0.is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+//! CPU-Time vs Wall-Time Beacon
+//!
+//! # Overview
+//!
+//! A process frozen at a breakpoint (or by SIGSTOP) keeps aging in wall time
+//! but accrues no CPU time. Around every detector slice the scheduler calls
+//! [`on_slice`], which reads `CLOCK_MONOTONIC` and
+//! `CLOCK_PROCESS_CPUTIME_ID` - two clock reads, no thread, no timer. A
+//! slice whose wall time runs far ahead of the CPU time it accrued was
+//! frozen in the middle of it, which is where a debugger stops us: at a
+//! breakpoint on one of our own checks.
+//!
+//! | Slice                                        | Meaning                         | Weight |
+//! |----------------------------------------------|---------------------------------|--------|
+//! | Wall time ~ CPU time                         | Ran                             | -      |
+//! | Wall time ahead by up to ~0.1 s              | Slept (sleep-based checks)      | -      |
+//! | Wall time ahead by ~0.5 s or more            | Frozen (breakpoint, SIGSTOP)    | 45     |
+//!
+//! Evidence goes to `ExecutionGap`. The whole scan's CPU/wall ratio is
+//! recorded as a feature.
+//!
+//! # Why This Fails
+//!
+//! - Only time spent inside slices is judged; in `--interleaved` mode the
+//!   host application's own work between slices may block legitimately
+//! - A paused VM stops both clocks' guest view or neither; a resynced
+//!   monotonic clock after VM resume looks like a freeze
+//! - A detector blocking on I/O for that long (the syscall-filter probe
+//!   waiting for a hung child) looks the same
+
+use std::sync::Mutex;
+use crate::engine::interleave::SliceEdge;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::salt::jitter;
+
+/// Wall time a slice spent without running that counts as a freeze
+/// (salted +-20%; the sleep-based checks idle ~0.1 s at most)
+const GAP_NS: u64 = jitter(500_000_000, 20, "cpu_beacon.gap_ns");
+
+/// Frozen slices named in the report
+const MAX_LISTED: usize = 5;
+
+/// Both clocks at one beacon
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Reading {
+    pub wall_ns: u64,
+    pub cpu_ns: u64,
+}
+
+impl Reading {
+    fn take() -> Self {
+        Self { wall_ns: clock_ns(libc::CLOCK_MONOTONIC), cpu_ns: clock_ns(libc::CLOCK_PROCESS_CPUTIME_ID) }
+    }
+}
+
+fn clock_ns(clock: libc::clockid_t) -> u64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` is a valid out-pointer
+    unsafe { libc::clock_gettime(clock, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+/// Whether a slice of `wall_ns` that accrued `cpu_ns` was frozen
+pub fn frozen(wall_ns: u64, cpu_ns: u64) -> bool {
+    wall_ns.saturating_sub(cpu_ns) >= GAP_NS
+}
+
+#[derive(Debug)]
+struct Beacons {
+    first: Option<Reading>,
+    last: Option<Reading>,
+    /// Slice currently running and its start reading
+    open: Option<(&'static str, Reading)>,
+    slices: u64,
+    /// (slice, wall ns, cpu ns) of each frozen slice
+    frozen: Vec<(&'static str, u64, u64)>,
+}
+
+static BEACONS: Mutex<Beacons> = Mutex::new(Beacons { first: None, last: None, open: None, slices: 0, frozen: Vec::new() });
+
+/// [`crate::engine::interleave::SliceHook`]: take a beacon at each edge
+pub fn on_slice(name: &'static str, edge: SliceEdge) {
+    let reading = Reading::take();
+    let mut beacons = BEACONS.lock().unwrap_or_else(|e| e.into_inner());
+    beacons.first.get_or_insert(reading);
+    beacons.last = Some(reading);
+    match edge {
+        SliceEdge::Start => beacons.open = Some((name, reading)),
+        SliceEdge::End => {
+            let Some((open, start)) = beacons.open.take().filter(|(open, _)| *open == name) else { return };
+            beacons.slices += 1;
+            let wall = reading.wall_ns.saturating_sub(start.wall_ns);
+            let cpu = reading.cpu_ns.saturating_sub(start.cpu_ns);
+            if frozen(wall, cpu) {
+                diag!("[CPU_BEACON] {} took {:.1} ms wall, {:.1} ms CPU", open, wall as f64 / 1e6, cpu as f64 / 1e6);
+                beacons.frozen.push((open, wall, cpu));
+            }
+        }
+    }
+}
+
+/// Main entry point for the CPU/wall beacon check (reports every slice so far)
+pub fn check_cpu_beacon(engine: &mut DecisionEngine) {
+    let beacons = BEACONS.lock().unwrap_or_else(|e| e.into_inner());
+    let (Some(first), Some(last)) = (beacons.first, beacons.last) else {
+        engine.record_diagnostic("cpu_beacon", &reason!("no slice beacons recorded (scheduler hook not installed)"));
+        return;
+    };
+    let wall = last.wall_ns.saturating_sub(first.wall_ns);
+    let cpu = last.cpu_ns.saturating_sub(first.cpu_ns);
+    diag!("[CPU_BEACON] {} slices: {:.1} ms wall, {:.1} ms CPU, {} frozen",
+          beacons.slices, wall as f64 / 1e6, cpu as f64 / 1e6, beacons.frozen.len());
+    if wall > 0 {
+        engine.record_feature("beacon_cpu_wall_ratio", cpu as f64 / wall as f64);
+    }
+    engine.record_feature("beacon_frozen_slices", beacons.frozen.len() as f64);
+
+    if beacons.frozen.is_empty() {
+        return;
+    }
+    let listed: Vec<String> = beacons.frozen.iter()
+        .take(MAX_LISTED)
+        .map(|(name, wall, cpu)| reason!("{} ({} ms wall, {} ms CPU)", name, wall / 1_000_000, cpu / 1_000_000))
+        .collect();
+    engine.report_with_confidence(
+        DetectionSource::ExecutionGap,
+        45,
+        0.9,
+        &reason!("Process was frozen while {} detector(s) ran: {}", beacons.frozen.len(), listed.join(", "))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frozen() {
+        let ms = 1_000_000;
+        // Busy slice
+        assert!(!frozen(2_000 * ms, 1_950 * ms));
+        // Short sleep
+        assert!(!frozen(100 * ms, 0));
+        // Stopped for 3 s at a breakpoint
+        assert!(frozen(3_000 * ms, 5 * ms));
+        // Stopped for 1 s in the middle of a busy check
+        assert!(frozen(1_150 * ms, 145 * ms));
+        // Worker threads on other CPUs may accrue more CPU than wall time
+        assert!(!frozen(100 * ms, 300 * ms));
+    }
+}
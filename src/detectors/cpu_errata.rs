@@ -0,0 +1,205 @@
+//! CPU Errata and Stable Undocumented Behavior (Bochs / QEMU TCG)
+//!
+//! # Overview
+//!
+//! A full-system emulator builds its CPU from the manuals, and the manuals
+//! leave gaps that real silicon fills the same way every time. Where the
+//! timing detectors are ambiguous (a fast TCG host, a slow real machine)
+//! these are not:
+//!
+//! - **x87 control word**: bit 6 reads back as 1 and bits 7 and 13-15 as 0
+//!   whatever FLDCW loaded (`0xffff` -> `0x1f7f`, `0x0000` -> `0x0040`)
+//! - **XSAVE size**: CPUID.(0xD,0).EBX equals the end of the furthest
+//!   component enabled in XCR0, as laid out by the sub-leaves (never less
+//!   than the 576-byte legacy area plus header)
+//! - **Out-of-range leaves**: on Intel, a basic leaf above the maximum
+//!   returns the highest basic leaf's data
+//! - **Topology**: the initial APIC ID in CPUID.1:EBX[31:24] is the low
+//!   byte of the x2APIC ID in CPUID.0xB:EDX
+//! - **Hypervisor leaves**: every hypervisor that sets the CPUID
+//!   hypervisor bit also answers leaf 0x40000000 with its signature; an
+//!   emulator that only copies the bit from a CPU model does not
+//!
+//! # Why This Fails
+//!
+//! - Hardware virtualization executes FLDCW and CPUID leaves from the host
+//!   natively - only emulators are caught
+//! - Recent QEMU and Bochs releases fix these one by one
+//! - A hypervisor can hide its leaves deliberately (`kvm=off`), which the
+//!   last check reads as emulation
+
+use core::arch::x86_64::{CpuidResult, __cpuid_count};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::ffi::{read_xcr0, x87_control_word_roundtrip};
+
+/// Control words loaded by the round-trip probe
+const CONTROL_WORDS: &[u16] = &[0xffff, 0x0000, 0x037f];
+
+/// Legacy FXSAVE region plus XSAVE header
+const XSAVE_LEGACY_SIZE: u32 = 576;
+
+/// Tries for the APIC ID comparison (the thread may migrate between leaves)
+const APIC_TRIES: usize = 3;
+
+#[allow(unused_unsafe)] // `__cpuid_count` is only safe on newer toolchains
+fn cpuid(leaf: u32, sub_leaf: u32) -> CpuidResult {
+    unsafe { __cpuid_count(leaf, sub_leaf) }
+}
+
+fn regs(r: CpuidResult) -> [u32; 4] {
+    [r.eax, r.ebx, r.ecx, r.edx]
+}
+
+/// What a 387-class FPU reads back after FLDCW `loaded`
+pub fn expected_control_word(loaded: u16) -> u16 {
+    (loaded | 0x0040) & 0x1f7f
+}
+
+/// XSAVE area size for `xcr0` given each component's (bit, size, offset)
+pub fn xsave_size(xcr0: u64, components: &[(u32, u32, u32)]) -> u32 {
+    components.iter()
+        .filter(|(bit, _, offset)| xcr0 & (1 << bit) != 0 && *offset != 0)
+        .map(|(_, size, offset)| offset + size)
+        .fold(XSAVE_LEGACY_SIZE, u32::max)
+}
+
+/// Whether a leaf 0x40000000 answer looks like a hypervisor's
+pub fn is_hypervisor_leaf(r: CpuidResult) -> bool {
+    let signature: Vec<u8> = [r.ebx, r.ecx, r.edx].iter().flat_map(|v| v.to_le_bytes()).collect();
+    (0x4000_0000..=0x4000_00ff).contains(&r.eax)
+        && signature.iter().any(|b| *b != 0)
+        && signature.iter().all(|b| *b == 0 || b.is_ascii_graphic() || *b == b' ')
+}
+
+fn control_word_deviations() -> Vec<String> {
+    CONTROL_WORDS.iter()
+        .filter_map(|&cw| {
+            // SAFETY: the probe restores the original control word
+            let read = unsafe { x87_control_word_roundtrip(cw) };
+            (read != expected_control_word(cw))
+                .then(|| reason!("FLDCW {:#06x} reads back {:#06x} (expected {:#06x})", cw, read, expected_control_word(cw)))
+        })
+        .collect()
+}
+
+/// (CPUID-reported, computed) XSAVE size, if XSAVE is enabled
+fn xsave_sizes() -> Option<(u32, u32)> {
+    if cpuid(1, 0).ecx & (1 << 27) == 0 {
+        return None; // no OSXSAVE
+    }
+    // SAFETY: OSXSAVE is set, so XGETBV is enabled
+    let xcr0 = unsafe { read_xcr0() };
+    let components: Vec<(u32, u32, u32)> = (2..63)
+        .filter(|bit| xcr0 & (1 << bit) != 0)
+        .map(|bit| {
+            let r = cpuid(0xD, bit);
+            (bit, r.eax, r.ebx)
+        })
+        .collect();
+    Some((cpuid(0xD, 0).ebx, xsave_size(xcr0, &components)))
+}
+
+/// Main entry point for the errata battery
+pub fn check_cpu_errata(engine: &mut DecisionEngine) {
+    let leaf0 = cpuid(0, 0);
+    let max_basic = leaf0.eax;
+    let intel = (leaf0.ebx, leaf0.edx, leaf0.ecx) == (0x756e_6547, 0x4965_6e69, 0x6c65_746e);
+    let mut deviations = 0usize;
+
+    // 1. x87 control word reserved bits
+    let cw = control_word_deviations();
+    if !cw.is_empty() {
+        deviations += 1;
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            40,
+            0.8,
+            &reason!("x87 control word keeps reserved bits: {}", cw.join("; "))
+        );
+    }
+
+    // 2. XSAVE size vs component layout
+    let sizes = xsave_sizes();
+    if let Some((reported, computed)) = sizes.filter(|(r, c)| r != c) {
+        deviations += 1;
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            30,
+            0.7,
+            &reason!("CPUID.(0xD,0).EBX = {} but enabled components end at {}", reported, computed)
+        );
+    }
+
+    // 3. Intel out-of-range basic leaf
+    let (top, beyond) = (regs(cpuid(max_basic, 0)), regs(cpuid(max_basic + 1, 0)));
+    if intel && top != beyond {
+        deviations += 1;
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            25,
+            0.6,
+            &reason!("GenuineIntel leaf {:#x} returns {:x?}, not the highest basic leaf {:x?}", max_basic + 1, beyond, top)
+        );
+    }
+
+    // 4. Initial APIC ID vs x2APIC ID
+    if max_basic >= 0xB && cpuid(0xB, 0).ebx != 0 {
+        let pairs: Vec<(u32, u32)> = (0..APIC_TRIES)
+            .map(|_| (cpuid(1, 0).ebx >> 24, cpuid(0xB, 0).edx & 0xff))
+            .collect();
+        if let Some(&(initial, x2)) = pairs.iter().all(|(i, x)| i != x).then(|| pairs.last()).flatten() {
+            deviations += 1;
+            engine.report_with_confidence(
+                DetectionSource::Emulation,
+                20,
+                0.5,
+                &reason!("Initial APIC ID {} does not match x2APIC ID low byte {}", initial, x2)
+            );
+        }
+    }
+
+    // 5. Hypervisor bit without hypervisor leaves
+    let hv_bit = cpuid(1, 0).ecx & (1 << 31) != 0;
+    if hv_bit && !is_hypervisor_leaf(cpuid(0x4000_0000, 0)) {
+        deviations += 1;
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            30,
+            0.6,
+            &reason!("CPUID hypervisor bit set but leaf 0x40000000 has no hypervisor signature (full-system emulator?)")
+        );
+    }
+
+    diag!("[ERRATA] intel={} max_basic={:#x} xsave={:?} control_word={:?} deviations={}", intel, max_basic, sizes, cw, deviations);
+    engine.record_feature("cpu_errata_deviations", deviations as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expected_control_word() {
+        assert_eq!(expected_control_word(0xffff), 0x1f7f);
+        assert_eq!(expected_control_word(0x0000), 0x0040);
+        assert_eq!(expected_control_word(0x037f), 0x037f);
+        assert!(control_word_deviations().is_empty());
+    }
+
+    #[test]
+    fn test_xsave_size() {
+        // x87 | SSE | AVX | AVX-512
+        let components = [(2, 256, 576), (5, 64, 1088), (6, 512, 1152), (7, 1024, 1664)];
+        assert_eq!(xsave_size(0x7, &components), 832);
+        assert_eq!(xsave_size(0xe7, &components), 2688);
+        assert_eq!(xsave_size(0x3, &[]), XSAVE_LEGACY_SIZE);
+    }
+
+    #[test]
+    fn test_is_hypervisor_leaf() {
+        let kvm = CpuidResult { eax: 0x4000_0001, ebx: 0x4b4d_564b, ecx: 0x564b_4d56, edx: 0x0000_004d };
+        assert!(is_hypervisor_leaf(kvm));
+        let copied_basic = CpuidResult { eax: 0xd, ebx: 0x2b00, ecx: 0x2b00, edx: 0 };
+        assert!(!is_hypervisor_leaf(copied_basic));
+    }
+}
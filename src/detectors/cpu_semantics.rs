@@ -0,0 +1,131 @@
+//! CPU Semantics Probes (Unicorn / Qiling and other emulation engines)
+//!
+//! # Overview
+//!
+//! Emulation engines such as Unicorn, and the frameworks built on it
+//! (Qiling), run our code instruction by instruction in software. They get
+//! the common path of each instruction right; the corners are where they
+//! diverge from silicon. `asm/cpu_semantics.s` runs a battery of them:
+//!
+//! | Area             | Probe                                     | Hardware                  |
+//! |------------------|-------------------------------------------|---------------------------|
+//! | Segments         | CS / SS selectors                         | 0x33 / 0x2b on Linux      |
+//! |                  | `fs:[0]` holds the thread pointer         | points to itself          |
+//! |                  | ES/CS/SS/DS override on a load            | base ignored (64-bit mode)|
+//! |                  | FS override on LEA                        | no base added             |
+//! | Flags            | RFLAGS reserved bits                      | bit 1 set, 3/5/15 clear   |
+//! |                  | INC after STC                             | CF kept                   |
+//! |                  | SHL by CL = 64                            | count 0: value, CF kept   |
+//! |                  | BSF with zero source                      | destination unchanged     |
+//! |                  | BT with register offset 65                | offset taken mod 64       |
+//! |                  | Failed CMPXCHG                            | RAX loaded, memory kept   |
+//! |                  | POPCNT                                    | CF/OF/SF/AF/PF cleared    |
+//! | x87 / SSE        | denormal + 0                              | MXCSR.DE set, nothing else|
+//! |                  | SQRTSS(-1)                                | negative default NaN      |
+//! |                  | CVTTSS2SI(NaN)                            | 0x80000000                |
+//! |                  | x87 FSQRT(-1)                             | negative real indefinite  |
+//!
+//! Precision and FTZ/DAZ are covered by `fpu.rs`; this module only adds the
+//! exception-flag and indefinite-value corners.
+//!
+//! # Why This Fails
+//!
+//! - Hardware virtualization executes these natively - only emulators are caught
+//! - QEMU TCG, and the Unicorn engine derived from it, gets most of the
+//!   instruction corners right; the selector and TLS checks depend on how
+//!   well the framework models the Linux process environment
+//! - Xen PV guests run user code on Xen's selectors (0xe033 / 0xe02b),
+//!   which are accepted as well
+
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::ffi::{cpu_semantics_probe, popcnt_flags_probe};
+
+/// Number of result slots filled by `cpu_semantics_probe`
+pub const SEM_SLOTS: usize = 15;
+
+/// Accepted hardware values for each slot, in `asm/cpu_semantics.s` order
+pub const EXPECTED: [(&str, &[u64]); SEM_SLOTS] = [
+    (described!("CS selector"), &[0x33, 0xe033]),
+    (described!("SS selector"), &[0x2b, 0xe02b]),
+    (described!("fs:[0] self pointer"), &[1]),
+    (described!("ES/CS/SS/DS override on load"), &[1]),
+    (described!("FS override on LEA"), &[8]),
+    (described!("RFLAGS reserved bits"), &[0x2]),
+    (described!("CF after STC; INC"), &[1]),
+    (described!("SHL by CL=64"), &[3]),
+    (described!("BSF with zero source"), &[0x1234]),
+    (described!("BT offset mod 64"), &[1]),
+    (described!("failed CMPXCHG"), &[5 | 5 << 32]),
+    (described!("MXCSR flags after denormal + 0"), &[0x2]),
+    (described!("SQRTSS(-1) default NaN"), &[0xffc0_0000]),
+    (described!("CVTTSS2SI(NaN) integer indefinite"), &[0x8000_0000]),
+    (described!("x87 FSQRT(-1) real indefinite"), &[0xfff8_0000_0000_0000]),
+];
+
+/// Descriptions of the slots whose value real silicon never produces
+pub fn mismatches(raw: &[u64; SEM_SLOTS]) -> Vec<String> {
+    EXPECTED.iter()
+        .zip(raw)
+        .filter(|((_, accepted), value)| !accepted.contains(value))
+        .map(|((name, accepted), value)| reason!("{} = {:#x} (expected {:#x})", name, value, accepted[0]))
+        .collect()
+}
+
+/// Run every probe and return the mismatches
+fn run_probes() -> Vec<String> {
+    let mut raw = [0u64; SEM_SLOTS];
+    // SAFETY: `raw` has the SEM_SLOTS entries the probe writes
+    unsafe { cpu_semantics_probe(raw.as_mut_ptr()) };
+    diag!("[SEMANTICS] Raw slots: {:x?}", raw);
+    let mut out = mismatches(&raw);
+
+    if std::arch::is_x86_feature_detected!("popcnt") {
+        // SAFETY: CPUID advertises POPCNT
+        let flags = unsafe { popcnt_flags_probe() };
+        if flags != 0 {
+            out.push(reason!("POPCNT left arithmetic flags {:#x} set", flags));
+        }
+    }
+    out
+}
+
+/// Main entry point for the CPU semantics battery
+pub fn check_cpu_semantics(engine: &mut DecisionEngine) {
+    let found = run_probes();
+    engine.record_feature("cpu_semantics_mismatches", found.len() as f64);
+    if found.is_empty() {
+        return;
+    }
+    let (weight, confidence) = match found.len() {
+        1 => (20, 0.5),
+        2 => (35, 0.7),
+        _ => (50, 0.9),
+    };
+    engine.report_with_confidence(
+        DetectionSource::Emulation,
+        weight,
+        confidence,
+        &reason!("CPU semantics differ from real silicon: {}", found.join("; "))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(feature = "stealth", ignore = "evidence text is coded in stealth builds")]
+    fn test_mismatches() {
+        let mut raw: [u64; SEM_SLOTS] = EXPECTED.map(|(_, accepted)| accepted[0]);
+        assert!(mismatches(&raw).is_empty());
+        raw[0] = 0xe033;
+        assert!(mismatches(&raw).is_empty());
+        raw[12] = 0x7fc0_0000;
+        assert_eq!(mismatches(&raw), vec!["SQRTSS(-1) default NaN = 0x7fc00000 (expected 0xffc00000)"]);
+    }
+
+    #[test]
+    fn test_hardware_matches() {
+        assert!(run_probes().is_empty(), "{:?}", run_probes());
+    }
+}
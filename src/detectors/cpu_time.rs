@@ -0,0 +1,364 @@
+//! Kernel CPU-Time Accounting Checks
+//!
+//! # Overview
+//!
+//! The kernel charges CPU time to a thread only while it is actually running
+//! on a CPU. An uncontended busy loop therefore accrues almost exactly one
+//! second of `CLOCK_THREAD_CPUTIME_ID` per second of wall clock.
+//!
+//! Under single-stepping or frequent ptrace-stops the thread spends most of
+//! its wall time stopped in TASK_TRACED while the debugger runs, so it
+//! accrues far less CPU time per wall second than the loop should.
+//!
+//! # Why This Is Useful
+//!
+//! - Both clocks come from kernel accounting (`sched_clock`), not RDTSC,
+//!   so RDTSC virtualization and TSC offsetting do not affect it
+//! - No signals, no exceptions - safe to run under GDB
+//!
+//! # Why This Fails
+//!
+//! - Heavy CPU contention also lowers the ratio (we take the best window)
+//! - A hypervisor that deschedules the whole VM steals time invisibly to the guest
+//! - Continuing at full speed (no stepping) leaves the ratio untouched
+//!
+//! # getrusage Single-Step Signature
+//!
+//! Every ptrace-stop puts the thread to sleep in TASK_TRACED, which the
+//! kernel counts as a context switch in `getrusage(RUSAGE_THREAD)`. Sampling
+//! the counters around the jitter detector's amplification loop gives a
+//! stops-per-iteration ratio: natively ~0, under single-step one or more per
+//! iteration. Linux never fills `ru_nsignals`, but we include it so a kernel
+//! that does is counted.
+//!
+//! Because this signal comes from the scheduler and the jitter signal comes
+//! from RDTSC, agreement between them is high-confidence evidence, and
+//! disagreement (stops seen, timing clean) means RDTSC is being virtualized.
+//!
+//! # Process CPU-Time Drift (Emulation)
+//!
+//! An emulator or binary translator spends many host cycles per guest
+//! instruction. It can compensate the guest's wall-clock and TSC sources to
+//! hide that, but the host kernel still charges the real cost to
+//! `CLOCK_PROCESS_CPUTIME_ID` (for user-mode emulators such as qemu-user).
+//! We run a fixed-cost assembly loop and compare:
+//! - CPU ns per iteration against the native upper bound (~2 cycles/iter)
+//! - CPU time against wall time and TSC-derived time for the same loop
+//!
+//! This is reported as emulation-class evidence, separate from debugger-class.
+
+use std::time::Duration;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::ffi::{calibrated_work_loop, get_rdtsc};
+
+/// Number of busy-loop windows measured; the best one is used
+const WINDOWS: usize = 5;
+
+/// Wall-clock length of each busy-loop window
+const WINDOW_NS: u64 = 10_000_000;
+
+/// Context-switch and signal counters for the calling thread
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadUsage {
+    pub voluntary_switches: i64,
+    pub involuntary_switches: i64,
+    pub signals: i64,
+}
+
+impl ThreadUsage {
+    /// Sample `getrusage(RUSAGE_THREAD)`; all-zero if the call fails
+    pub fn sample() -> Self {
+        let mut ru: libc::rusage = unsafe { std::mem::zeroed() };
+        // SAFETY: `ru` is a valid out-pointer for the duration of the call
+        if unsafe { libc::getrusage(libc::RUSAGE_THREAD, &mut ru) } != 0 {
+            return Self::default();
+        }
+        Self {
+            voluntary_switches: ru.ru_nvcsw,
+            involuntary_switches: ru.ru_nivcsw,
+            signals: ru.ru_nsignals,
+        }
+    }
+
+    /// Total stop-like events since `earlier`
+    pub fn events_since(&self, earlier: &ThreadUsage) -> i64 {
+        (self.voluntary_switches - earlier.voluntary_switches)
+            + (self.involuntary_switches - earlier.involuntary_switches)
+            + (self.signals - earlier.signals)
+    }
+}
+
+/// Stops per iteration at or above which the loop was almost certainly stepped
+const SINGLE_STEP_STOPS_PER_ITER: f64 = 1.0;
+
+/// Iterations of the calibrated work loop (~10-20ms natively)
+const WORK_ITERATIONS: u64 = 10_000_000;
+
+/// CPU ns per work-loop iteration above which execution is implausibly slow
+/// for real hardware (~2 cycles/iteration even at 0.8 GHz is 2.5ns)
+const EMULATION_NS_PER_ITER: f64 = 20.0;
+const SLOW_NS_PER_ITER: f64 = 8.0;
+
+/// Read a POSIX clock in nanoseconds
+fn clock_ns(clock: libc::clockid_t) -> u64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` is a valid out-pointer; clock ids used here are always supported on Linux
+    unsafe { libc::clock_gettime(clock, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+/// Spin until `WINDOW_NS` of wall time has elapsed.
+/// Returns (thread CPU ns, wall ns) accrued over the window.
+fn busy_window() -> (u64, u64) {
+    let wall_start = clock_ns(libc::CLOCK_MONOTONIC);
+    let cpu_start = clock_ns(libc::CLOCK_THREAD_CPUTIME_ID);
+
+    let mut acc: u64 = 0;
+    loop {
+        for i in 0..1000u64 {
+            acc = std::hint::black_box(acc.wrapping_add(i));
+        }
+        if clock_ns(libc::CLOCK_MONOTONIC).saturating_sub(wall_start) >= WINDOW_NS {
+            break;
+        }
+    }
+    std::hint::black_box(acc);
+
+    let cpu = clock_ns(libc::CLOCK_THREAD_CPUTIME_ID).saturating_sub(cpu_start);
+    let wall = clock_ns(libc::CLOCK_MONOTONIC).saturating_sub(wall_start);
+    (cpu, wall)
+}
+
+/// Thread CPU time accrued per wall-clock second, as a ratio (1.0 = fully running)
+pub fn cpu_wall_ratio(cpu_ns: u64, wall_ns: u64) -> f64 {
+    if wall_ns == 0 {
+        return 0.0;
+    }
+    cpu_ns as f64 / wall_ns as f64
+}
+
+/// Main entry point for the thread-CPU-time vs wall-clock check
+pub fn check_thread_cpu_time(engine: &mut DecisionEngine) {
+    // Best (highest) ratio across windows - contention only ever lowers it,
+    // so a single clean window is enough to rule out a loaded system
+    let best = (0..WINDOWS)
+        .map(|_| {
+            let (cpu, wall) = busy_window();
+            cpu_wall_ratio(cpu, wall)
+        })
+        .fold(0.0_f64, f64::max);
+
+    engine.record_feature("thread_cpu_wall_ratio", best);
+    diag!("[CPU_TIME] Thread CPU/wall ratio (best of {}): {:.3}", WINDOWS, best);
+
+    // Native, uncontended: ~0.95-1.0
+    // Single-stepped: each step costs a ptrace-stop round trip, ratio << 0.1
+    if best < 0.2 {
+        engine.report_with_confidence(
+            DetectionSource::CpuAccounting,
+            50,
+            0.8,
+            &reason!("Busy loop accrued only {:.0}% CPU time per wall second (single-stepping/ptrace-stops?)", best * 100.0)
+        );
+    } else if best < 0.5 {
+        engine.report_with_confidence(
+            DetectionSource::CpuAccounting,
+            15,
+            0.5,
+            &reason!("Busy loop CPU/wall ratio low: {:.2} (ptrace-stops or heavy contention)", best)
+        );
+    }
+}
+
+/// Assess the rusage signature of the amplification loop and cross-check it
+/// against the jitter detector's timing verdict.
+///
+/// `timing_single_step` is true when the RDTSC-based amplification timing
+/// already indicated single-stepping or heavy instrumentation.
+pub fn assess_single_step_rusage(
+    engine: &mut DecisionEngine,
+    before: &ThreadUsage,
+    after: &ThreadUsage,
+    iterations: usize,
+    timing_single_step: bool,
+) {
+    let events = after.events_since(before);
+    let per_iter = if iterations > 0 { events as f64 / iterations as f64 } else { 0.0 };
+    let rusage_single_step = per_iter >= SINGLE_STEP_STOPS_PER_ITER;
+
+    engine.record_feature("amp_stops_per_iter", per_iter);
+    diag!("[CPU_TIME] Amplification loop: {} context switches/signals over {} iterations ({:.3}/iter)",
+          events, iterations, per_iter);
+
+    match (rusage_single_step, timing_single_step) {
+        (true, true) => {
+            // Scheduler and RDTSC independently agree
+            engine.report_with_confidence(
+                DetectionSource::CpuAccounting,
+                80,
+                0.95,
+                &reason!("Single-step confirmed by scheduler and RDTSC: {:.1} stops/iteration", per_iter)
+            );
+        }
+        (true, false) => {
+            engine.report_with_confidence(
+                DetectionSource::CpuAccounting,
+                50,
+                0.8,
+                &reason!("Amplification loop stopped {:.1} times/iteration (ptrace single-step?)", per_iter)
+            );
+            engine.record_contradiction(
+                DetectionSource::CpuAccounting,
+                DetectionSource::Jitter,
+                &reason!("Kernel accounted ptrace-stops during amplification loop but RDTSC timing is clean - TSC virtualized?")
+            );
+        }
+        (false, true) => {
+            // Slow but never stopped: DBI/VM rather than a stepping debugger
+            diag!("[CPU_TIME] Amplification timing anomalous without stops - DBI/VM rather than single-step");
+        }
+        (false, false) => {}
+    }
+}
+
+/// Timings of one calibrated work-loop run from three independent clocks
+#[derive(Debug, Clone, Copy)]
+pub struct WorkLoopTiming {
+    pub cpu_ns: u64,
+    pub wall_ns: u64,
+    pub tsc_ns: f64,
+}
+
+impl WorkLoopTiming {
+    pub fn cpu_ns_per_iter(&self, iterations: u64) -> f64 {
+        self.cpu_ns as f64 / iterations.max(1) as f64
+    }
+}
+
+/// TSC ticks per wall-clock ns, calibrated over a short sleep
+fn calibrate_tsc_per_ns() -> f64 {
+    let wall_start = clock_ns(libc::CLOCK_MONOTONIC);
+    let tsc_start = unsafe { get_rdtsc() };
+    std::thread::sleep(Duration::from_millis(20));
+    let tsc_delta = unsafe { get_rdtsc() }.saturating_sub(tsc_start);
+    let wall_delta = clock_ns(libc::CLOCK_MONOTONIC).saturating_sub(wall_start);
+    tsc_delta as f64 / wall_delta.max(1) as f64
+}
+
+fn time_work_loop(tsc_per_ns: f64) -> WorkLoopTiming {
+    let wall_start = clock_ns(libc::CLOCK_MONOTONIC);
+    let cpu_start = clock_ns(libc::CLOCK_PROCESS_CPUTIME_ID);
+    let tsc_start = unsafe { get_rdtsc() };
+
+    std::hint::black_box(unsafe { calibrated_work_loop(WORK_ITERATIONS) });
+
+    let tsc_delta = unsafe { get_rdtsc() }.saturating_sub(tsc_start);
+    let cpu_ns = clock_ns(libc::CLOCK_PROCESS_CPUTIME_ID).saturating_sub(cpu_start);
+    let wall_ns = clock_ns(libc::CLOCK_MONOTONIC).saturating_sub(wall_start);
+
+    WorkLoopTiming {
+        cpu_ns,
+        wall_ns,
+        tsc_ns: if tsc_per_ns > 0.0 { tsc_delta as f64 / tsc_per_ns } else { 0.0 },
+    }
+}
+
+/// Process CPU-time drift check for emulation / binary translation
+pub fn check_process_cpu_drift(engine: &mut DecisionEngine) {
+    let tsc_per_ns = calibrate_tsc_per_ns();
+
+    // Best (fastest) of three runs to shed scheduler noise
+    let usage_before = ThreadUsage::sample();
+    let timing = (0..3)
+        .map(|_| time_work_loop(tsc_per_ns))
+        .min_by_key(|t| t.cpu_ns)
+        .unwrap();
+    let stops = ThreadUsage::sample().events_since(&usage_before);
+
+    let ns_per_iter = timing.cpu_ns_per_iter(WORK_ITERATIONS);
+    let cpu_wall = cpu_wall_ratio(timing.cpu_ns, timing.wall_ns);
+    let tsc_cpu = if timing.cpu_ns > 0 { timing.tsc_ns / timing.cpu_ns as f64 } else { 0.0 };
+
+    engine.record_feature("work_cpu_ns_per_iter", ns_per_iter);
+    engine.record_feature("work_cpu_wall_ratio", cpu_wall);
+    engine.record_feature("work_tsc_cpu_ratio", tsc_cpu);
+
+    diag!("[CPU_TIME] Work loop: {:.2} CPU ns/iter, cpu/wall={:.3}, tsc/cpu={:.3}, stops={}",
+          ns_per_iter, cpu_wall, tsc_cpu, stops);
+
+    // Stepped loops are slow for a different reason - that is debugger-class
+    // evidence and already covered by the rusage signature
+    if stops as u64 > WORK_ITERATIONS / 1000 {
+        diag!("[CPU_TIME] Work loop was stopped {} times, skipping emulation assessment", stops);
+        return;
+    }
+
+    // 1. Absolute cost per guest instruction
+    if ns_per_iter > EMULATION_NS_PER_ITER {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            45,
+            0.75,
+            &reason!("Fixed-cost loop costs {:.1} CPU ns/iteration (native <= ~2.5ns) - emulation/translation?", ns_per_iter)
+        );
+    } else if ns_per_iter > SLOW_NS_PER_ITER {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            15,
+            0.4,
+            &reason!("Fixed-cost loop slow: {:.1} CPU ns/iteration", ns_per_iter)
+        );
+    }
+
+    // 2. Compensated wall clock: a single thread cannot accrue more CPU than wall time
+    if cpu_wall > 1.5 {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            40,
+            0.8,
+            &reason!("Process CPU time exceeds wall time {:.2}x for a single-threaded loop - wall clock compensated?", cpu_wall)
+        );
+    }
+
+    // 3. Compensated TSC: TSC says the loop was much shorter than CPU accounting does
+    if tsc_cpu > 0.0 && tsc_cpu < 0.5 {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            35,
+            0.7,
+            &reason!("TSC-derived loop duration is {:.0}% of CPU time - TSC compensated?", tsc_cpu * 100.0)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu_wall_ratio() {
+        assert_eq!(cpu_wall_ratio(0, 0), 0.0);
+        assert!((cpu_wall_ratio(5, 10) - 0.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_usage_events_since() {
+        let before = ThreadUsage { voluntary_switches: 3, involuntary_switches: 1, signals: 0 };
+        let after = ThreadUsage { voluntary_switches: 1003, involuntary_switches: 2, signals: 0 };
+        assert_eq!(after.events_since(&before), 1001);
+    }
+
+    #[test]
+    fn test_work_loop_native_cost() {
+        let timing = time_work_loop(calibrate_tsc_per_ns());
+        // Generous bound: the loop must not look emulated on the test host
+        assert!(timing.cpu_ns_per_iter(WORK_ITERATIONS) < EMULATION_NS_PER_ITER);
+    }
+
+    #[test]
+    fn test_busy_window_accrues_cpu_time() {
+        let (cpu, wall) = busy_window();
+        assert!(wall >= WINDOW_NS);
+        assert!(cpu > 0);
+    }
+}
@@ -0,0 +1,336 @@
+//! CPUID Claims vs Instruction Behavior
+//!
+//! # Overview
+//!
+//! CPUID is just a table the environment hands us. A sandbox that wants to
+//! look like a modern native CPU can advertise any feature it likes, but
+//! then has to actually run the instructions. For each claimed feature we
+//! execute one representative instruction under a SIGILL guard and check:
+//!
+//! | Feature | Instruction            | Checked                                |
+//! |---------|------------------------|----------------------------------------|
+//! | AVX2    | VPBROADCASTD/VPADDD    | Upper YMM lane result, latency         |
+//! | BMI2    | PDEP                   | Result vs software reference, latency  |
+//! | RDSEED  | RDSEED                 | Succeeds, draws distinct and non-trivial |
+//! | SHA     | SHA1MSG1               | Result vs software reference, latency  |
+//!
+//! A claimed feature that raises #UD means CPUID is spoofed. A wrong result
+//! or a per-call cost in the thousands of cycles means the instruction is
+//! being trapped and emulated in software.
+//!
+//! # Why This Fails
+//!
+//! - Hardware virtualization executes these natively - only emulators are caught
+//! - Features the environment honestly hides are never probed
+//! - RDSEED latency varies too widely across vendors to be timed
+//! - A debugger intercepts SIGILL, so the probe is skipped when traced
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use core::arch::x86_64::CpuidResult;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::signal_compat;
+use crate::ffi::{get_rdtsc, read_xcr0};
+
+extern "C" {
+    fn isa_probe_baseline() -> u64;
+    fn isa_probe_avx2(x: u32) -> u64;
+    fn isa_probe_bmi2(src: u64, mask: u64) -> u64;
+    fn isa_probe_rdseed(out: *mut u64) -> u64;
+    fn isa_probe_sha(a: *const u32, b: *const u32, out: *mut u32) -> u64;
+    fn isa_probe_landing();
+}
+
+/// Calls per timing batch
+const TIMING_CALLS: u64 = 200;
+
+/// Timing batches; the fastest one is kept to shed interrupts and migrations
+const TIMING_BATCHES: usize = 5;
+
+/// RDSEED draws taken for the entropy sanity check
+const RDSEED_DRAWS: usize = 4;
+
+const AVX2_INPUT: u32 = 0x1357_9bdf;
+const BMI2_SRC: u64 = 0x0123_4567_89ab_cdef;
+const BMI2_MASK: u64 = 0xf0f0_ff00_0f0f_aa55;
+const SHA_A: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+const SHA_B: [u32; 4] = [0xc3d2_e1f0, 0x5a82_7999, 0x6ed9_eba1, 0x8f1b_bcdc];
+
+/// A CPUID-advertised feature we can execute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsaFeature {
+    Avx2,
+    Bmi2,
+    Rdseed,
+    Sha,
+}
+
+impl IsaFeature {
+    pub const ALL: [IsaFeature; 4] = [IsaFeature::Avx2, IsaFeature::Bmi2, IsaFeature::Rdseed, IsaFeature::Sha];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IsaFeature::Avx2 => "AVX2",
+            IsaFeature::Bmi2 => "BMI2",
+            IsaFeature::Rdseed => "RDSEED",
+            IsaFeature::Sha => "SHA",
+        }
+    }
+
+    /// Bit in CPUID.(7,0):EBX
+    fn leaf7_ebx_bit(self) -> u32 {
+        match self {
+            IsaFeature::Avx2 => 5,
+            IsaFeature::Bmi2 => 8,
+            IsaFeature::Rdseed => 18,
+            IsaFeature::Sha => 29,
+        }
+    }
+
+    /// Ceiling on cycles per call above the empty-call baseline. Natively
+    /// these cost a handful of cycles; a trap-and-emulate round-trip costs
+    /// thousands. `None` where native cost is too variable to judge.
+    pub fn max_cycles(self) -> Option<u64> {
+        match self {
+            IsaFeature::Rdseed => None,
+            _ => Some(1000),
+        }
+    }
+}
+
+/// What happened when a claimed feature's instruction was executed
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClaimProbe {
+    pub faulted: bool,
+    pub correct: bool,
+    /// Cycles per call above baseline, if timed
+    pub cycles: Option<u64>,
+}
+
+/// Software PDEP: scatter the low bits of `src` into the set bits of `mask`
+pub fn pdep_reference(src: u64, mask: u64) -> u64 {
+    let mut result = 0;
+    let mut remaining = mask;
+    let mut bit = 0;
+    while remaining != 0 {
+        let lowest = remaining & remaining.wrapping_neg();
+        if src & (1 << bit) != 0 {
+            result |= lowest;
+        }
+        remaining &= remaining - 1;
+        bit += 1;
+    }
+    result
+}
+
+/// Software SHA1MSG1 with dwords in memory order (index 0 = bits 31:0)
+pub fn sha1msg1_reference(a: [u32; 4], b: [u32; 4]) -> [u32; 4] {
+    [b[2] ^ a[0], b[3] ^ a[1], a[0] ^ a[2], a[1] ^ a[3]]
+}
+
+/// Compare a probe against hardware behavior. Returns descriptions of deviations.
+pub fn claim_deviations(feature: IsaFeature, probe: &ClaimProbe) -> Vec<String> {
+    let mut out = Vec::new();
+
+    if probe.faulted {
+        out.push(reason!("{} advertised by CPUID but raised #UD", feature.name()));
+        return out;
+    }
+    if !probe.correct {
+        out.push(reason!("{} produced an incorrect result", feature.name()));
+    }
+    if let (Some(cycles), Some(max)) = (probe.cycles, feature.max_cycles()) {
+        if cycles > max {
+            out.push(reason!("{} costs {} cycles per call (native < {})", feature.name(), cycles, max));
+        }
+    }
+
+    out
+}
+
+static FAULTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn sigill_handler(_signum: libc::c_int, _info: *mut libc::siginfo_t, ctx: *mut libc::c_void) {
+    FAULTED.store(true, Ordering::SeqCst);
+    // SAFETY: the kernel passes a valid ucontext_t. Every probe is a leaf
+    // function that leaves RSP untouched, so jumping to the landing pad
+    // returns straight to the caller.
+    unsafe {
+        let ucontext = ctx as *mut libc::ucontext_t;
+        (*ucontext).uc_mcontext.gregs[libc::REG_RIP as usize] = isa_probe_landing as *const () as usize as i64;
+    }
+}
+
+/// Execute the feature's instruction once. Returns whether the result was correct.
+fn execute(feature: IsaFeature) -> bool {
+    // SAFETY: a #UD inside any probe is caught by `sigill_handler`
+    unsafe {
+        match feature {
+            IsaFeature::Avx2 => isa_probe_avx2(AVX2_INPUT) == AVX2_INPUT.wrapping_mul(2) as u64,
+            IsaFeature::Bmi2 => isa_probe_bmi2(BMI2_SRC, BMI2_MASK) == pdep_reference(BMI2_SRC, BMI2_MASK),
+            IsaFeature::Sha => {
+                let mut out = [0u32; 4];
+                isa_probe_sha(SHA_A.as_ptr(), SHA_B.as_ptr(), out.as_mut_ptr());
+                out == sha1msg1_reference(SHA_A, SHA_B)
+            }
+            IsaFeature::Rdseed => {
+                let mut seeds = Vec::with_capacity(RDSEED_DRAWS);
+                for _ in 0..RDSEED_DRAWS {
+                    let mut seed = 0u64;
+                    if isa_probe_rdseed(&mut seed) == 1 {
+                        seeds.push(seed);
+                    }
+                }
+                let trivial = seeds.iter().any(|&s| s == 0 || s == u64::MAX);
+                let repeated = seeds.windows(2).any(|w| w[0] == w[1]);
+                seeds.len() >= 2 && !trivial && !repeated
+            }
+        }
+    }
+}
+
+/// Fastest per-call cost of `f` over several batches, in TSC cycles
+fn min_cycles_per_call(mut f: impl FnMut()) -> u64 {
+    let mut best = u64::MAX;
+    for _ in 0..TIMING_BATCHES {
+        let start = unsafe { get_rdtsc() };
+        for _ in 0..TIMING_CALLS {
+            f();
+        }
+        let end = unsafe { get_rdtsc() };
+        best = best.min(end.wrapping_sub(start) / TIMING_CALLS);
+    }
+    best
+}
+
+fn time_feature(feature: IsaFeature, baseline: u64) -> u64 {
+    let mut sha_out = [0u32; 4];
+    let cycles = min_cycles_per_call(|| unsafe {
+        // SAFETY: only called after `execute` ran without faulting
+        match feature {
+            IsaFeature::Avx2 => { std::hint::black_box(isa_probe_avx2(AVX2_INPUT)); }
+            IsaFeature::Bmi2 => { std::hint::black_box(isa_probe_bmi2(BMI2_SRC, BMI2_MASK)); }
+            IsaFeature::Sha => { isa_probe_sha(SHA_A.as_ptr(), SHA_B.as_ptr(), sha_out.as_mut_ptr()); }
+            IsaFeature::Rdseed => {}
+        }
+    });
+    cycles.saturating_sub(baseline)
+}
+
+/// Features CPUID advertises that the OS also lets us use
+#[allow(unused_unsafe)] // `__cpuid` is only safe on newer toolchains
+fn claimed_features() -> Vec<IsaFeature> {
+    if unsafe { core::arch::x86_64::__cpuid(0) }.eax < 7 {
+        return Vec::new();
+    }
+    let leaf1: CpuidResult = unsafe { core::arch::x86_64::__cpuid(1) };
+    let leaf7: CpuidResult = unsafe { core::arch::x86_64::__cpuid_count(7, 0) };
+
+    // AVX2 is only architecturally usable once the OS enables YMM state
+    let osxsave = leaf1.ecx & (1 << 27) != 0;
+    // SAFETY: OSXSAVE is set, so XGETBV is available
+    let ymm_enabled = osxsave && unsafe { read_xcr0() } & 0b110 == 0b110;
+
+    IsaFeature::ALL.iter().copied()
+        .filter(|f| leaf7.ebx & (1 << f.leaf7_ebx_bit()) != 0)
+        .filter(|f| *f != IsaFeature::Avx2 || ymm_enabled)
+        .collect()
+}
+
+/// Main entry point for the CPUID claims vs behavior check
+pub fn check_cpuid_claims(engine: &mut DecisionEngine) {
+    let tracer_pid = signal_compat::get_tracer_pid();
+    if tracer_pid > 0 {
+        diag!("[CPUID] Tracer detected (PID {}), skipping SIGILL-guarded probes to avoid conflict", tracer_pid);
+        return;
+    }
+
+    let claimed = claimed_features();
+    if claimed.is_empty() {
+        diag!("[CPUID] No probed features advertised");
+        return;
+    }
+
+    let mut old_ill: libc::sigaction = unsafe { std::mem::zeroed() };
+    unsafe {
+        let mut sa: libc::sigaction = std::mem::zeroed();
+        sa.sa_sigaction = sigill_handler as *const () as usize;
+        libc::sigemptyset(&mut sa.sa_mask);
+        sa.sa_flags = libc::SA_SIGINFO;
+        if libc::sigaction(libc::SIGILL, &sa, &mut old_ill) != 0 {
+            diag!("[CPUID] Failed to install SIGILL handler");
+            return;
+        }
+    }
+
+    let baseline = min_cycles_per_call(|| { std::hint::black_box(unsafe { isa_probe_baseline() }); });
+
+    let mut all_deviations = Vec::new();
+    let mut hard_failure = false;
+    let mut slowest = 0;
+    for feature in claimed {
+        FAULTED.store(false, Ordering::SeqCst);
+        let correct = execute(feature);
+        let faulted = FAULTED.load(Ordering::SeqCst);
+        let cycles = match feature.max_cycles() {
+            Some(_) if !faulted => Some(time_feature(feature, baseline)),
+            _ => None,
+        };
+        slowest = slowest.max(cycles.unwrap_or(0));
+
+        let probe = ClaimProbe { faulted, correct, cycles };
+        diag!("[CPUID] {}: faulted={} correct={} cycles={:?}", feature.name(), faulted, correct, cycles);
+        hard_failure |= faulted || !correct;
+        all_deviations.extend(claim_deviations(feature, &probe));
+    }
+
+    unsafe {
+        libc::sigaction(libc::SIGILL, &old_ill, std::ptr::null_mut());
+    }
+
+    engine.record_feature("isa_claim_deviations", all_deviations.len() as f64);
+    engine.record_feature("isa_probe_max_cycles", slowest as f64);
+
+    if !all_deviations.is_empty() {
+        // Faults and wrong answers are unambiguous; latency alone may be a noisy host
+        let (weight, confidence) = if hard_failure { (50, 0.85) } else { (30, 0.6) };
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            weight,
+            confidence,
+            &reason!("CPUID-advertised instructions misbehave: {}", all_deviations.join("; "))
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pdep_reference() {
+        assert_eq!(pdep_reference(0b101, 0b1110_0000), 0b1010_0000);
+        assert_eq!(pdep_reference(u64::MAX, 0xf0f0), 0xf0f0);
+    }
+
+    #[test]
+    fn test_deviations() {
+        let fast = ClaimProbe { faulted: false, correct: true, cycles: Some(5) };
+        assert!(claim_deviations(IsaFeature::Bmi2, &fast).is_empty());
+
+        let trapped = ClaimProbe { faulted: false, correct: true, cycles: Some(20_000) };
+        assert_eq!(claim_deviations(IsaFeature::Bmi2, &trapped).len(), 1);
+
+        let spoofed = ClaimProbe { faulted: true, ..Default::default() };
+        assert_eq!(claim_deviations(IsaFeature::Sha, &spoofed).len(), 1);
+    }
+
+    #[test]
+    fn test_hardware_matches_references() {
+        let claimed = claimed_features();
+        for feature in [IsaFeature::Bmi2, IsaFeature::Sha] {
+            if claimed.contains(&feature) {
+                assert!(execute(feature), "{} disagrees with reference", feature.name());
+            }
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! Pin / DynamoRIO Code-Cache Detection
+//!
+//! # Overview
+//!
+//! Dynamic binary instrumentation frameworks such as Intel Pin and
+//! DynamoRIO run every basic block from a translated copy in their code
+//! cache. That leaves traces a process can see for itself:
+//!
+//! - **Code caches**: large anonymous regions mapped read-write-execute.
+//!   Nothing in this binary maps RWX memory.
+//! - **Framework files**: `pinbin`, `pinvm.so` and friends among the
+//!   file-backed mappings
+//! - **DynamoRIO signatures**: `dynamorio` / `drmemory` components in the
+//!   maps, `DYNAMORIO_*` / `DR__*` variables in the environment, and a
+//!   second copy of libc loaded by DynamoRIO's private loader for its
+//!   clients (the system loader maps each library once)
+//! - **Instruction-pointer views** (`asm/dbi.s`): the return address a
+//!   `CALL` pushed, a `CALL`/`POP` read of RIP and the x87 last-instruction
+//!   pointer from `FXSAVE64`, each compared with the addresses the linker
+//!   assigned. A translator that forgets to map one of them back exposes a
+//!   code-cache address.
+//!
+//! # Why This Fails
+//!
+//! - Pin and DynamoRIO translate `CALL` return addresses and RIP-relative
+//!   reads by design; those two views only catch sloppy tools
+//! - AMD CPUs only save the x87 pointers with an exception pending, so the
+//!   `FXSAVE` view is empty there
+//! - A framework can hide its own mappings from /proc/self/maps or map the
+//!   code cache W^X
+//! - JIT runtimes embedded in the host process also create RWX regions
+//! - `dlmopen` into a new namespace legitimately loads a second libc
+
+use std::fs;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+extern "C" {
+    fn dbi_read_return_address() -> u64;
+    fn dbi_call_pop_rip() -> u64;
+    fn dbi_rip_anchor();
+    fn dbi_x87_last_ip(area: *mut u8) -> u64;
+    fn dbi_x87_anchor();
+}
+
+/// Path fragments of Pin components
+const DBI_PATHS: &[&str] = &["pinbin", "pinvm", "libpindwarf"];
+
+/// Path fragments of DynamoRIO and Dr. Memory components
+const DYNAMORIO_PATHS: &[&str] = &["dynamorio", "drpreload", "drmemory"];
+
+/// Environment prefixes used by DynamoRIO's launcher and injector
+const DYNAMORIO_ENV_PREFIXES: &[&str] = &["DYNAMORIO_", "DR__"];
+
+/// Anonymous RWX bytes that look like a code cache rather than a stray page
+const CODE_CACHE_BYTES: usize = 1 << 20;
+
+/// 512-byte, 16-byte aligned `FXSAVE64` area
+#[repr(C, align(16))]
+struct FxsaveArea([u8; 512]);
+
+/// Anonymous RWX mappings in a maps file: (region count, total bytes)
+pub fn rwx_anonymous(maps: &str) -> (usize, usize) {
+    maps.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 5 || fields[1] != "rwxp" {
+                return None;
+            }
+            let path = fields.get(5).copied().unwrap_or("");
+            if !path.is_empty() && !path.starts_with("[anon") {
+                return None;
+            }
+            let (start, end) = fields[0].split_once('-')?;
+            Some(usize::from_str_radix(end, 16).ok()? - usize::from_str_radix(start, 16).ok()?)
+        })
+        .fold((0, 0), |(count, bytes), size| (count + 1, bytes + size))
+}
+
+/// Distinct mapped files whose name contains one of `signatures`
+fn mapped_files(maps: &str, signatures: &[&str]) -> Vec<String> {
+    let mut paths: Vec<String> = maps.lines()
+        .filter_map(|line| line.split_whitespace().nth(5))
+        .filter(|path| {
+            let file = path.rsplit('/').next().unwrap_or(path).to_ascii_lowercase();
+            signatures.iter().any(|sig| file.contains(sig))
+        })
+        .map(str::to_string)
+        .collect();
+    paths.dedup();
+    paths
+}
+
+/// Distinct mapped files belonging to Pin
+pub fn dbi_paths(maps: &str) -> Vec<String> {
+    mapped_files(maps, DBI_PATHS)
+}
+
+/// Distinct mapped files belonging to DynamoRIO or Dr. Memory
+pub fn dynamorio_paths(maps: &str) -> Vec<String> {
+    mapped_files(maps, DYNAMORIO_PATHS)
+}
+
+/// How many separate loads of libc a maps file shows (offset-0 mappings
+/// that do not continue the previous one)
+pub fn libc_loads(maps: &str) -> usize {
+    let mut loads = 0;
+    let mut previous_end = String::new();
+    for line in maps.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let Some((start, end)) = fields.first().and_then(|range| range.split_once('-')) else { continue };
+        let file = fields.get(5).and_then(|path| path.rsplit('/').next()).unwrap_or("");
+        let is_libc = file.starts_with("libc.so") || (file.starts_with("libc-") && file.ends_with(".so"));
+        if is_libc && fields.get(2).is_some_and(|offset| offset.trim_start_matches('0').is_empty()) && start != previous_end {
+            loads += 1;
+        }
+        previous_end = end.to_string();
+    }
+    loads
+}
+
+/// DynamoRIO indicators: mapped components, environment, private libc
+fn dynamorio_indicators(maps: &str) -> Vec<String> {
+    let mut indicators = Vec::new();
+    let paths = dynamorio_paths(maps);
+    if !paths.is_empty() {
+        indicators.push(reason!("components mapped: {}", paths.join(", ")));
+    }
+    let vars: Vec<String> = std::env::vars_os()
+        .filter_map(|(key, _)| key.into_string().ok())
+        .filter(|key| DYNAMORIO_ENV_PREFIXES.iter().any(|prefix| key.starts_with(prefix)))
+        .collect();
+    if !vars.is_empty() {
+        indicators.push(reason!("environment sets {}", vars.join(", ")));
+    }
+    let loads = libc_loads(maps);
+    if loads > 1 {
+        indicators.push(reason!("libc loaded {} times (private loader)", loads));
+    }
+    indicators
+}
+
+/// Executable ranges of our own binary
+fn exe_text_ranges(maps: &str) -> Vec<(u64, u64)> {
+    let Ok(exe) = fs::read_link("/proc/self/exe") else { return Vec::new() };
+    let exe = exe.to_string_lossy();
+    maps.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 6 || !fields[1].contains('x') || fields[5] != exe {
+                return None;
+            }
+            let (start, end) = fields[0].split_once('-')?;
+            Some((u64::from_str_radix(start, 16).ok()?, u64::from_str_radix(end, 16).ok()?))
+        })
+        .collect()
+}
+
+/// Return address as seen from inside a callee
+#[inline(never)]
+fn observed_return_address() -> u64 {
+    // SAFETY: reads [RSP] on entry, no side effects
+    std::hint::black_box(unsafe { dbi_read_return_address() })
+}
+
+/// Instruction-pointer views that disagree with link-time addresses
+fn rip_mismatches(text: &[(u64, u64)]) -> Vec<String> {
+    let mut out = Vec::new();
+
+    let ret = observed_return_address();
+    if !text.is_empty() && !text.iter().any(|(start, end)| (*start..*end).contains(&ret)) {
+        out.push(reason!("return address {:#x} lies outside our executable's text", ret));
+    }
+
+    // SAFETY: CALL/POP into RAX, stack balanced
+    let rip = unsafe { dbi_call_pop_rip() };
+    let anchor = dbi_rip_anchor as *const () as u64;
+    if rip != anchor {
+        out.push(reason!("CALL/POP read RIP {:#x}, linked at {:#x}", rip, anchor));
+    }
+
+    let mut area = FxsaveArea([0; 512]);
+    // SAFETY: the area is 512 bytes and 16-byte aligned as FXSAVE64 requires;
+    // the x87 stack is left as found
+    let fip = unsafe { dbi_x87_last_ip(area.0.as_mut_ptr()) };
+    let anchor = dbi_x87_anchor as *const () as u64;
+    if fip != 0 && fip != anchor {
+        out.push(reason!("x87 last instruction pointer {:#x}, FLDZ linked at {:#x}", fip, anchor));
+    }
+    diag!("[DBI] ret={:#x} rip={:#x} fip={:#x}", ret, rip, fip);
+
+    out
+}
+
+/// Main entry point for the Pin / DynamoRIO check
+pub fn check_dbi_code_cache(engine: &mut DecisionEngine) {
+    let Ok(maps) = fs::read_to_string("/proc/self/maps") else {
+        engine.record_diagnostic("dbi", &reason!("/proc/self/maps unreadable"));
+        return;
+    };
+
+    for path in dbi_paths(&maps) {
+        engine.report_with_confidence(
+            DetectionSource::Dbi,
+            60,
+            0.95,
+            &reason!("DBI framework component mapped: {}", path)
+        );
+    }
+
+    let dynamorio = dynamorio_indicators(&maps);
+    engine.record_feature("dynamorio_indicators", dynamorio.len() as f64);
+    if !dynamorio.is_empty() {
+        let (weight, confidence) = match dynamorio.len() {
+            1 => (40, 0.8),
+            _ => (60, 0.95),
+        };
+        engine.report_with_confidence(
+            DetectionSource::Dbi,
+            weight,
+            confidence,
+            &reason!("Running under DynamoRIO: {}", dynamorio.join("; "))
+        );
+    }
+
+    let (regions, bytes) = rwx_anonymous(&maps);
+    engine.record_feature("dbi_rwx_anon_bytes", bytes as f64);
+    if regions > 0 {
+        let (weight, confidence) = if bytes >= CODE_CACHE_BYTES { (40, 0.8) } else { (15, 0.5) };
+        engine.report_with_confidence(
+            DetectionSource::Dbi,
+            weight,
+            confidence,
+            &reason!("{} anonymous RWX region(s), {} KiB: JIT code cache", regions, bytes / 1024)
+        );
+    }
+
+    let mismatches = rip_mismatches(&exe_text_ranges(&maps));
+    engine.record_feature("dbi_rip_mismatches", mismatches.len() as f64);
+    for mismatch in mismatches {
+        engine.report_with_confidence(
+            DetectionSource::Dbi,
+            50,
+            0.9,
+            &reason!("Code runs from a translated copy: {}", mismatch)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAPS: &str = "\
+55d0c0a00000-55d0c0a10000 r-xp 00001000 08:01 42 /usr/bin/app
+7f0000000000-7f0000200000 rwxp 00000000 00:00 0
+7f0000200000-7f0000201000 rwxp 00000000 00:00 0 [anon:cache]
+7f1000000000-7f1000100000 r-xp 00000000 08:01 7 /opt/pin/intel64/bin/pinbin
+7f2000000000-7f2000010000 r-xp 00000000 08:01 8 /opt/dr/lib64/release/libdynamorio.so
+7f3000000000-7f3000001000 rwxp 00000000 08:01 9 /usr/lib/libjit.so
+";
+
+    #[test]
+    fn test_maps_signatures() {
+        assert_eq!(rwx_anonymous(MAPS), (2, 0x201000));
+        assert_eq!(dbi_paths(MAPS), vec!["/opt/pin/intel64/bin/pinbin"]);
+        assert_eq!(dynamorio_paths(MAPS), vec!["/opt/dr/lib64/release/libdynamorio.so"]);
+        assert_eq!(rwx_anonymous(""), (0, 0));
+    }
+
+    #[test]
+    fn test_libc_loads() {
+        let native = "\
+7f1000000000-7f1000028000 r--p 00000000 08:01 5 /usr/lib/x86_64-linux-gnu/libc.so.6
+7f1000028000-7f10001bd000 r-xp 00028000 08:01 5 /usr/lib/x86_64-linux-gnu/libc.so.6
+";
+        assert_eq!(libc_loads(native), 1);
+        let private = format!("{}\
+7f4000000000-7f4000028000 r--p 00000000 08:01 5 /usr/lib/x86_64-linux-gnu/libc.so.6
+", native);
+        assert_eq!(libc_loads(&private), 2);
+        let maps = fs::read_to_string("/proc/self/maps").unwrap_or_default();
+        assert!(libc_loads(&maps) <= 1);
+    }
+
+    #[test]
+    fn test_native_rip_views_agree() {
+        let maps = fs::read_to_string("/proc/self/maps").unwrap_or_default();
+        assert!(rip_mismatches(&exe_text_ranges(&maps)).is_empty());
+    }
+}
@@ -123,6 +123,7 @@ fn simulate_external_observation() -> (Duration, usize) {
 }
 
 /// Compare internal vs simulated external observations
+#[allow(clippy::manual_range_contains)] // A NaN ratio is not out of range
 fn compare_observations() -> ObserverComparison {
     // Internal measurement (RDTSC-based)
     let (internal_total_cycles, syscall_times) = measure_syscalls_internally();
@@ -150,7 +151,7 @@ fn compare_observations() -> ObserverComparison {
     // Discrepancy 1: Count mismatch
     if internal_count != external_count {
         discrepancy = true;
-        notes.push_str(&format!(
+        notes.push_str(&reason!(
             "Syscall count mismatch: internal={}, external={}. ",
             internal_count, external_count
         ));
@@ -163,7 +164,7 @@ fn compare_observations() -> ObserverComparison {
     
     if timing_ratio > 10.0 || timing_ratio < 0.1 {
         discrepancy = true;
-        notes.push_str(&format!(
+        notes.push_str(&reason!(
             "Timing discrepancy: internal/external ratio={:.2}. ",
             timing_ratio
         ));
@@ -171,7 +172,7 @@ fn compare_observations() -> ObserverComparison {
     
     // Analysis
     if notes.is_empty() {
-        notes = "Observations consistent within tolerance.".to_string();
+        notes = reason!("Observations consistent within tolerance.");
     }
     
     ObserverComparison {
@@ -185,7 +186,7 @@ fn compare_observations() -> ObserverComparison {
 
 /// Main entry point for eBPF comparison
 pub fn check_ebpf_comparison(engine: &mut DecisionEngine) {
-    eprintln!("[EBPF] Running observer comparison (simulated mode)...");
+    diag!("[EBPF] Running observer comparison (simulated mode)...");
     
     // Run comparison multiple times for statistical confidence
     let mut discrepancy_count = 0;
@@ -194,17 +195,19 @@ pub fn check_ebpf_comparison(engine: &mut DecisionEngine) {
     for trial in 0..TRIALS {
         let comparison = compare_observations();
         
-        eprintln!("[EBPF] Trial {}: internal={}, external={:?}, discrepancy={}",
-                  trial + 1, 
-                  comparison.internal_syscall_count,
-                  comparison.external_syscall_count,
-                  comparison.discrepancy_detected);
+        diag!("[EBPF] Trial {}: internal={}, external={:?}, discrepancy={}",
+              trial + 1, 
+              comparison.internal_syscall_count,
+              comparison.external_syscall_count,
+              comparison.discrepancy_detected);
         
         if comparison.discrepancy_detected {
             discrepancy_count += 1;
         }
     }
     
+    engine.record_feature("observer_discrepancy_trials", discrepancy_count as f64);
+    
     if discrepancy_count > 0 {
         let confidence = discrepancy_count as f64 / TRIALS as f64;
         
@@ -212,16 +215,16 @@ pub fn check_ebpf_comparison(engine: &mut DecisionEngine) {
             DetectionSource::EbpfComparison,
             30,
             confidence,
-            &format!("Observer discrepancy in {}/{} trials (timing virtualization?)", 
+            &reason!("Observer discrepancy in {}/{} trials (timing virtualization?)", 
                      discrepancy_count, TRIALS)
         );
     }
     
     // Report on the fundamental limitation
-    eprintln!("[EBPF] NOTE: This is simulated comparison. True eBPF requires root + kernel support.");
-    eprintln!("[EBPF] Research conclusion: Neither observer is fully trustworthy.");
-    eprintln!("[EBPF]   - Internal: Can be lied to (virtualized RDTSC)");
-    eprintln!("[EBPF]   - External: Has overhead, can be kernel-level manipulated");
+    diag!("[EBPF] NOTE: This is simulated comparison. True eBPF requires root + kernel support.");
+    diag!("[EBPF] Research conclusion: Neither observer is fully trustworthy.");
+    diag!("[EBPF]   - Internal: Can be lied to (virtualized RDTSC)");
+    diag!("[EBPF]   - External: Has overhead, can be kernel-level manipulated");
 }
 
 /// Check if real eBPF is available (for documentation)
@@ -248,10 +251,10 @@ pub fn check_ebpf_availability() -> bool {
         false
     };
     
-    eprintln!("[EBPF] Availability check:");
-    eprintln!("[EBPF]   BTF support: {}", btf_available);
-    eprintln!("[EBPF]   Root privileges: {}", is_root);
-    eprintln!("[EBPF]   Kernel >= 4.18: {}", kernel_ok);
+    diag!("[EBPF] Availability check:");
+    diag!("[EBPF]   BTF support: {}", btf_available);
+    diag!("[EBPF]   Root privileges: {}", is_root);
+    diag!("[EBPF]   Kernel >= 4.18: {}", kernel_ok);
     
     btf_available && is_root && kernel_ok
 }
@@ -0,0 +1,170 @@
+//! Analysis-Oriented Environment Variables
+//!
+//! # Overview
+//!
+//! Analysis tools configure themselves - and their runtime in our process -
+//! through environment variables. Every variable of the initial
+//! environment block (`/proc/self/environ`, which a later `unsetenv` does
+//! not change) and of the live environment is matched against a table of
+//! indicators, each classified on its own:
+//!
+//! | Variable                                 | Indicates                          | Source          | Weight |
+//! |------------------------------------------|------------------------------------|-----------------|--------|
+//! | `RUNNING_UNDER_RR`, `_RR_TRACE_DIR`      | rr recording                       | RecordReplay    | 45-50  |
+//! | `RR_*`                                   | rr options                         | RecordReplay    | 30     |
+//! | `PIN_*`, `DYNAMORIO_*`, `DR__*`          | Pin / DynamoRIO launcher           | Dbi             | 40     |
+//! | `VALGRIND_*`                             | Valgrind                           | Dbi             | 35     |
+//! | `QEMU_*`                                 | qemu-user                          | Emulation       | 35     |
+//! | `FRIDA_*`                                | Frida                              | Instrumentation | 35     |
+//! | `LD_AUDIT`                               | rtld-audit library                 | Instrumentation | 30     |
+//! | `LD_PRELOAD`                             | Preloaded library (by its value)   | by value        | 20-45  |
+//! | `AFL_*`, `ASAN_OPTIONS` and friends      | Fuzzing / sanitizer harness        | Instrumentation | 10-20  |
+//! | `GCOV_PREFIX`, `LLVM_PROFILE_FILE`       | Coverage collection                | Instrumentation | 15     |
+//! | `MALLOC_CHECK_`, `MALLOC_PERTURB_`       | Heap debugging                     | Instrumentation | 10     |
+//! | `LD_DEBUG`                               | Loader tracing                     | Instrumentation | 10     |
+//!
+//! `LINES`, `COLUMNS` and `_`, which tell how a debugger launched us, are
+//! `launch_env.rs`'s; variables scrubbed after startup are `environ.rs`'s.
+//!
+//! # Why This Fails
+//!
+//! - Every tool here can run without its variables (or with them renamed)
+//! - Developers set sanitizer and heap-debugging options globally
+//! - An injector can overwrite the initial environment block in place
+
+use std::collections::BTreeMap;
+use std::fs;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// How one variable (or family of variables) is classified
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Indicator {
+    pub source: DetectionSource,
+    pub weight: u32,
+    pub confidence: f64,
+    pub meaning: &'static str,
+}
+
+const fn indicator(source: DetectionSource, weight: u32, confidence: f64, meaning: &'static str) -> Indicator {
+    Indicator { source, weight, confidence, meaning }
+}
+
+/// Variable names (a trailing `*` matches a prefix) and what they indicate.
+/// The first match wins.
+const INDICATORS: &[(&str, Indicator)] = &[
+    ("RUNNING_UNDER_RR", indicator(DetectionSource::RecordReplay, 50, 0.9, described!("set by rr for its tracees"))),
+    ("_RR_TRACE_DIR", indicator(DetectionSource::RecordReplay, 45, 0.85, described!("rr trace directory"))),
+    ("RR_*", indicator(DetectionSource::RecordReplay, 30, 0.6, described!("rr option"))),
+    ("PIN_*", indicator(DetectionSource::Dbi, 40, 0.75, described!("Intel Pin launcher"))),
+    ("DYNAMORIO_*", indicator(DetectionSource::Dbi, 40, 0.8, described!("DynamoRIO launcher"))),
+    ("DR__*", indicator(DetectionSource::Dbi, 40, 0.8, described!("DynamoRIO injector"))),
+    ("VALGRIND_*", indicator(DetectionSource::Dbi, 35, 0.75, "Valgrind")),
+    ("QEMU_*", indicator(DetectionSource::Emulation, 35, 0.7, described!("qemu-user emulation"))),
+    ("FRIDA_*", indicator(DetectionSource::Instrumentation, 35, 0.7, "Frida")),
+    ("LD_AUDIT", indicator(DetectionSource::Instrumentation, 30, 0.7, described!("rtld-audit library"))),
+    ("AFL_*", indicator(DetectionSource::Instrumentation, 20, 0.6, described!("AFL fuzzing harness"))),
+    ("ASAN_OPTIONS", indicator(DetectionSource::Instrumentation, 10, 0.4, described!("AddressSanitizer options"))),
+    ("MSAN_OPTIONS", indicator(DetectionSource::Instrumentation, 10, 0.4, described!("MemorySanitizer options"))),
+    ("UBSAN_OPTIONS", indicator(DetectionSource::Instrumentation, 10, 0.4, described!("UndefinedBehaviorSanitizer options"))),
+    ("TSAN_OPTIONS", indicator(DetectionSource::Instrumentation, 10, 0.4, described!("ThreadSanitizer options"))),
+    ("GCOV_PREFIX*", indicator(DetectionSource::Instrumentation, 15, 0.5, described!("gcov coverage output"))),
+    ("LLVM_PROFILE_FILE", indicator(DetectionSource::Instrumentation, 15, 0.5, described!("LLVM coverage output"))),
+    ("MALLOC_CHECK_", indicator(DetectionSource::Instrumentation, 10, 0.4, described!("glibc heap checking"))),
+    ("MALLOC_PERTURB_", indicator(DetectionSource::Instrumentation, 10, 0.4, described!("glibc heap poisoning"))),
+    ("LD_DEBUG", indicator(DetectionSource::Instrumentation, 10, 0.4, described!("loader tracing"))),
+];
+
+/// `LD_PRELOAD` entries (by substring) and what they preload
+const PRELOADS: &[(&str, Indicator)] = &[
+    ("librrpreload", indicator(DetectionSource::RecordReplay, 45, 0.9, described!("rr syscall buffer"))),
+    ("frida", indicator(DetectionSource::Instrumentation, 45, 0.9, described!("Frida agent"))),
+    ("gum", indicator(DetectionSource::Instrumentation, 35, 0.7, described!("Gum-based agent"))),
+    ("vgpreload", indicator(DetectionSource::Dbi, 40, 0.8, described!("Valgrind preload"))),
+    ("libdynamorio", indicator(DetectionSource::Dbi, 40, 0.8, "DynamoRIO")),
+    ("asan", indicator(DetectionSource::Instrumentation, 10, 0.4, described!("sanitizer runtime"))),
+];
+
+/// Any other non-empty `LD_PRELOAD`
+const PRELOAD: Indicator = indicator(DetectionSource::Instrumentation, 20, 0.5, described!("preloaded library"));
+
+/// Classify one variable, if it is an analysis indicator
+pub fn classify_var(name: &str, value: &str) -> Option<Indicator> {
+    if name == "LD_PRELOAD" {
+        if value.trim().is_empty() {
+            return None;
+        }
+        let lower = value.to_ascii_lowercase();
+        return Some(PRELOADS.iter().find(|(part, _)| lower.contains(part)).map_or(PRELOAD, |(_, i)| *i));
+    }
+    INDICATORS.iter()
+        .find(|(pattern, _)| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == *pattern,
+        })
+        .map(|(_, i)| *i)
+}
+
+/// (name, value) pairs of a NUL-separated environment block
+pub fn parse_block(block: &[u8]) -> Vec<(String, String)> {
+    block.split(|&b| b == 0)
+        .filter_map(|entry| {
+            let at = entry.iter().position(|&b| b == b'=')?;
+            Some((String::from_utf8_lossy(&entry[..at]).into_owned(), String::from_utf8_lossy(&entry[at + 1..]).into_owned()))
+        })
+        .collect()
+}
+
+/// Main entry point for the environment scan
+pub fn check_env_scan(engine: &mut DecisionEngine) {
+    // Initial block first, so its value wins where the live one was rewritten
+    let mut vars: BTreeMap<String, String> = BTreeMap::new();
+    match fs::read("/proc/self/environ") {
+        Ok(block) => vars.extend(parse_block(&block)),
+        Err(e) => engine.record_diagnostic("env_scan", &reason!("/proc/self/environ unreadable: {}", e)),
+    }
+    for (name, value) in std::env::vars_os() {
+        vars.entry(name.to_string_lossy().into_owned()).or_insert_with(|| value.to_string_lossy().into_owned());
+    }
+
+    let found: Vec<(&String, &String, Indicator)> = vars.iter()
+        .filter_map(|(name, value)| Some((name, value, classify_var(name, value)?)))
+        .collect();
+    diag!("[ENV_SCAN] {} variables, {} indicators: {:?}", vars.len(), found.len(),
+          found.iter().map(|(name, ..)| name.as_str()).collect::<Vec<_>>());
+    engine.record_feature("env_indicators", found.len() as f64);
+
+    for (name, value, indicator) in found {
+        engine.report_with_confidence(
+            indicator.source,
+            indicator.weight,
+            indicator.confidence,
+            &reason!("{}={} in the environment ({})", name, value, indicator.meaning)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_var() {
+        assert_eq!(classify_var("RUNNING_UNDER_RR", "1").map(|i| i.source), Some(DetectionSource::RecordReplay));
+        assert_eq!(classify_var("PIN_ROOT", "/opt/pin").map(|i| i.source), Some(DetectionSource::Dbi));
+        assert_eq!(classify_var("GCOV_PREFIX_STRIP", "3").map(|i| i.weight), Some(15));
+        assert_eq!(classify_var("LD_PRELOAD", "/usr/lib/rr/librrpreload.so").map(|i| i.source), Some(DetectionSource::RecordReplay));
+        assert_eq!(classify_var("LD_PRELOAD", "/tmp/x.so"), Some(PRELOAD));
+        assert_eq!(classify_var("LD_PRELOAD", ""), None);
+        assert_eq!(classify_var("HOME", "/root"), None);
+        // Exact names do not match as prefixes
+        assert_eq!(classify_var("LD_AUDITOR", "1"), None);
+    }
+
+    #[test]
+    fn test_parse_block() {
+        assert_eq!(parse_block(b"A=1\0B=x=y\0junk\0\0"), vec![
+            ("A".to_string(), "1".to_string()),
+            ("B".to_string(), "x=y".to_string()),
+        ]);
+    }
+}
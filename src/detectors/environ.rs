@@ -0,0 +1,206 @@
+//! Environment Cross-View Check (environ vs /proc/self/environ)
+//!
+//! # Overview
+//!
+//! A process has two views of its own environment:
+//! 1. The live `environ` array that libc's `getenv`/`setenv`/`unsetenv` operate on
+//! 2. `/proc/self/environ`, which the kernel serves from the original
+//!    `env_start..env_end` block on the initial stack
+//!
+//! Injection frameworks that rely on `LD_PRELOAD` commonly call
+//! `unsetenv("LD_PRELOAD")` from their constructor so that our `getenv`
+//! calls (and any children we spawn) never see it. That only edits the live
+//! array - the kernel view still holds the original block. A variable that
+//! exists in the kernel view but has vanished from the live view is therefore
+//! near-definitive evidence of environment manipulation.
+//!
+//! # Why This Fails
+//!
+//! - An injector can overwrite the original stack block in place as well
+//! - `prctl(PR_SET_MM_ENV_START/END)` (CAP_SYS_RESOURCE) can repoint the kernel view
+//! - Legitimate programs call `setenv` at runtime, so additions alone are weak
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+extern "C" {
+    static environ: *const *const libc::c_char;
+}
+
+/// Variables that injection frameworks scrub after loading.
+/// A removal or rewrite of one of these is treated as high-confidence evidence.
+const SENSITIVE_VARS: &[&str] = &[
+    "LD_PRELOAD",
+    "LD_AUDIT",
+    "LD_LIBRARY_PATH",
+    "LD_DEBUG",
+    "LD_BIND_NOW",
+];
+
+/// Differences between the kernel view and the live view, keyed by variable name
+#[derive(Debug, Default)]
+pub struct EnvironDiff {
+    /// Present in /proc/self/environ but missing from the live array
+    pub removed: Vec<String>,
+    /// Present in both views with different values
+    pub modified: Vec<String>,
+    /// Present only in the live array (setenv after startup)
+    pub added: Vec<String>,
+}
+
+/// Read the live `environ` array
+fn read_live_environ() -> Vec<Vec<u8>> {
+    let mut entries = Vec::new();
+    // SAFETY: `environ` is a NULL-terminated array of NUL-terminated strings
+    // maintained by libc. We only read it and nothing else in this process
+    // mutates the environment concurrently.
+    unsafe {
+        let mut cursor = environ;
+        if cursor.is_null() {
+            return entries;
+        }
+        while !(*cursor).is_null() {
+            entries.push(CStr::from_ptr(*cursor).to_bytes().to_vec());
+            cursor = cursor.add(1);
+        }
+    }
+    entries
+}
+
+/// Read the kernel's view of our initial environment
+fn read_proc_environ() -> Option<Vec<Vec<u8>>> {
+    let raw = std::fs::read("/proc/self/environ").ok()?;
+    Some(
+        raw.split(|&b| b == 0)
+            .filter(|e| !e.is_empty())
+            .map(|e| e.to_vec())
+            .collect(),
+    )
+}
+
+fn split_entry(entry: &[u8]) -> (String, &[u8]) {
+    match entry.iter().position(|&b| b == b'=') {
+        Some(idx) => (String::from_utf8_lossy(&entry[..idx]).into_owned(), &entry[idx + 1..]),
+        None => (String::from_utf8_lossy(entry).into_owned(), &[]),
+    }
+}
+
+/// Compare the two views by variable name
+pub fn diff_environ(live: &[Vec<u8>], kernel: &[Vec<u8>]) -> EnvironDiff {
+    let live_map: HashMap<String, &[u8]> = live.iter().map(|e| split_entry(e)).collect();
+    let kernel_map: HashMap<String, &[u8]> = kernel.iter().map(|e| split_entry(e)).collect();
+
+    let mut diff = EnvironDiff::default();
+
+    for (name, value) in &kernel_map {
+        match live_map.get(name) {
+            None => diff.removed.push(name.clone()),
+            Some(live_value) if live_value != value => diff.modified.push(name.clone()),
+            Some(_) => {}
+        }
+    }
+    for name in live_map.keys() {
+        if !kernel_map.contains_key(name) {
+            diff.added.push(name.clone());
+        }
+    }
+
+    diff.removed.sort();
+    diff.modified.sort();
+    diff.added.sort();
+    diff
+}
+
+/// Main entry point for the environ cross-view check
+pub fn check_environ_divergence(engine: &mut DecisionEngine) {
+    let kernel = match read_proc_environ() {
+        Some(k) => k,
+        None => {
+            diag!("[ENVIRON] /proc/self/environ unreadable, skipping");
+            return;
+        }
+    };
+    let live = read_live_environ();
+
+    let diff = diff_environ(&live, &kernel);
+
+    diag!(
+        "[ENVIRON] live={} kernel={} removed={} modified={} added={}",
+        live.len(), kernel.len(), diff.removed.len(), diff.modified.len(), diff.added.len()
+    );
+
+    engine.record_feature("environ_removed", diff.removed.len() as f64);
+    engine.record_feature("environ_modified", diff.modified.len() as f64);
+    engine.record_feature("environ_added", diff.added.len() as f64);
+
+    let is_sensitive = |name: &String| SENSITIVE_VARS.contains(&name.as_str());
+
+    // 1. Loader variables scrubbed from the live view - the classic injector cleanup
+    let scrubbed: Vec<&String> = diff.removed.iter()
+        .chain(diff.modified.iter())
+        .filter(|n| is_sensitive(n))
+        .collect();
+    engine.record_flag("environ_loader_scrubbed", !scrubbed.is_empty());
+    if !scrubbed.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::CrossView,
+            70,
+            0.95,
+            &reason!("Loader variables scrubbed from live environ but present in /proc/self/environ: {:?}", scrubbed)
+        );
+    }
+
+    // 2. Other removals/rewrites - still unusual, nothing in a normal startup unsets variables
+    let other: Vec<&String> = diff.removed.iter()
+        .chain(diff.modified.iter())
+        .filter(|n| !is_sensitive(n))
+        .collect();
+    if !other.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::CrossView,
+            40,
+            0.7,
+            &reason!("environ diverges from /proc/self/environ ({} removed/modified): {:?}", other.len(), other)
+        );
+    }
+
+    // 3. Additions only - setenv() at runtime is common, informational
+    if !diff.added.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::CrossView,
+            5,
+            0.3,
+            &reason!("{} variables added to environ after startup: {:?}", diff.added.len(), diff.added)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(list: &[&str]) -> Vec<Vec<u8>> {
+        list.iter().map(|s| s.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_diff_environ() {
+        let kernel = entries(&["PATH=/bin", "LD_PRELOAD=/tmp/hook.so", "HOME=/root"]);
+        let live = entries(&["PATH=/bin", "HOME=/home/x", "EXTRA=1"]);
+
+        let diff = diff_environ(&live, &kernel);
+        assert_eq!(diff.removed, vec!["LD_PRELOAD".to_string()]);
+        assert_eq!(diff.modified, vec!["HOME".to_string()]);
+        assert_eq!(diff.added, vec!["EXTRA".to_string()]);
+    }
+
+    #[test]
+    fn test_live_view_matches_kernel() {
+        // Nothing in the test harness scrubs loader variables
+        let live = read_live_environ();
+        let kernel = read_proc_environ().unwrap_or_default();
+        let diff = diff_environ(&live, &kernel);
+        assert!(!diff.removed.iter().any(|n| SENSITIVE_VARS.contains(&n.as_str())));
+    }
+}
@@ -0,0 +1,160 @@
+//! Inherited File-Descriptor Inspection
+//!
+//! # Overview
+//!
+//! A process starts with whatever descriptors its parent did not close or
+//! mark close-on-exec. Shells hand over stdio and nothing else; debuggers,
+//! supervisors and injectors routinely leak more - the socket of a remote
+//! debugging session, the control pipe of a ptrace supervisor, the memfd
+//! an agent was staged in. The pre-main constructor records which
+//! descriptors above stdio were open before any of our code ran
+//! (`premain::inherited_fds`); for the ones still open we look at:
+//!
+//! | Descriptor | What counts                                              | Weight |
+//! |------------|----------------------------------------------------------|--------|
+//! | Socket     | TCP to a debug-server port, or a debugger as unix peer   | 40     |
+//! | Socket     | Any other inherited socket                               | 15     |
+//! | Pipe       | Other end held by a debugger or our tracer               | 35     |
+//! | Pipe       | Other end held by another process                        | 15     |
+//! | memfd      | Anonymous in-memory file (injector staging area)         | 25     |
+//! | eventfd    | Notification channel shared with another process         | 20     |
+//!
+//! Stdio is left to `output_capture.rs`; inherited regular files and
+//! terminals are ignored.
+//!
+//! # Why This Fails
+//!
+//! - Careful launchers close everything above stdio (`close_range`)
+//! - Service managers pass sockets on purpose (systemd socket
+//!   activation), and job servers share pipes with their children (`make`)
+//! - Descriptors opened after `main()` by an injected agent are not in
+//!   the snapshot; the thread and memory-map detectors look for those agents
+
+use std::fs;
+use crate::detectors::launch_env::is_debugger;
+use crate::detectors::output_capture::{parse_fd_target, processes_holding, read_peer, socket_peer, StdTarget};
+use crate::detectors::premain;
+use crate::detectors::remote_debug::{is_debug_stub, parse_proc_net_tcp, TcpSocket};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::signal_compat;
+
+/// Ports of debug servers and instrumentation daemons
+const DEBUG_PORTS: &[(u16, &str)] = &[
+    (1234, "gdbserver"),
+    (2345, "gdbserver"),
+    (23946, described!("IDA remote debugger")),
+    (27042, "frida-server"),
+    (18001, described!("Ghidra JDWP")),
+];
+
+/// What an inherited descriptor is
+#[derive(Debug, Clone, PartialEq)]
+pub enum FdKind {
+    Socket(u64),
+    Pipe(String),
+    Memfd(String),
+    EventFd,
+    /// Regular files, terminals and other anonymous inodes
+    Ignored,
+}
+
+/// Classify a `/proc/self/fd/N` link target
+pub fn classify_fd(link: &str) -> FdKind {
+    match parse_fd_target(link) {
+        StdTarget::Socket(inode) => FdKind::Socket(inode),
+        StdTarget::Pipe(_) => FdKind::Pipe(link.to_string()),
+        _ if link.starts_with("/memfd:") => FdKind::Memfd(link.trim_start_matches("/memfd:").trim_end_matches(" (deleted)").to_string()),
+        _ if link == "anon_inode:[eventfd]" => FdKind::EventFd,
+        _ => FdKind::Ignored,
+    }
+}
+
+/// Debug service on either end of a TCP connection, if any
+pub fn debug_port(socket: &TcpSocket) -> Option<(u16, &'static str)> {
+    DEBUG_PORTS.iter().copied().find(|(port, _)| *port == socket.local_port || *port == socket.remote_port)
+}
+
+fn tcp_sockets() -> Vec<TcpSocket> {
+    ["/proc/net/tcp", "/proc/net/tcp6"].iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|text| parse_proc_net_tcp(&text))
+        .collect()
+}
+
+/// Whether a process is a debugger or a debug stub
+fn debugger_like(comm: &str) -> bool {
+    is_debugger(comm) || is_debug_stub(comm)
+}
+
+/// Finding for one inherited descriptor, as (source, description, weight, confidence)
+fn inspect(fd: i32, link: &str, tcp: &[TcpSocket], tracer: u32) -> Option<(DetectionSource, String, u32, f64)> {
+    match classify_fd(link) {
+        FdKind::Socket(inode) => {
+            if let Some((port, service)) = tcp.iter().filter(|s| s.inode == inode).find_map(debug_port) {
+                return Some((DetectionSource::RemoteDebug, reason!("fd {} is a TCP connection on port {} ({})", fd, port, service), 40, 0.85));
+            }
+            let peer = socket_peer(fd).map(read_peer);
+            match peer {
+                Some(peer) if debugger_like(&peer.comm) => Some((DetectionSource::RemoteDebug, reason!("fd {} is a socket to {} (PID {})", fd, peer.comm, peer.pid), 40, 0.85)),
+                Some(peer) => Some((DetectionSource::Instrumentation, reason!("fd {} is a socket to {} (PID {})", fd, peer.comm, peer.pid), 15, 0.5)),
+                None => Some((DetectionSource::Instrumentation, reason!("fd {} is an inherited socket ({})", fd, link), 15, 0.5)),
+            }
+        }
+        FdKind::Pipe(link) => {
+            let holders: Vec<_> = processes_holding(|_, _, l| l == link).into_iter().map(read_peer).collect();
+            let names: Vec<String> = holders.iter().map(|p| reason!("{} (PID {})", p.comm, p.pid)).collect();
+            if holders.iter().any(|p| debugger_like(&p.comm) || p.pid == tracer) {
+                Some((DetectionSource::Ptrace, reason!("fd {} is a pipe to {}", fd, names.join(", ")), 35, 0.8))
+            } else if !holders.is_empty() {
+                Some((DetectionSource::Instrumentation, reason!("fd {} is a pipe to {}", fd, names.join(", ")), 15, 0.5))
+            } else {
+                None
+            }
+        }
+        FdKind::Memfd(name) => Some((DetectionSource::Instrumentation, reason!("fd {} is a memfd {:?}", fd, name), 25, 0.7)),
+        FdKind::EventFd => Some((DetectionSource::Instrumentation, reason!("fd {} is an inherited eventfd", fd), 20, 0.6)),
+        FdKind::Ignored => None,
+    }
+}
+
+/// Main entry point for the fd-table check
+pub fn check_fd_table(engine: &mut DecisionEngine) {
+    if !premain::ran() {
+        engine.record_diagnostic("fd_table", &reason!("no pre-main descriptor snapshot"));
+        return;
+    }
+    let open: Vec<(i32, String)> = premain::inherited_fds().into_iter()
+        .filter_map(|fd| Some((fd, fs::read_link(format!("/proc/self/fd/{}", fd)).ok()?.to_string_lossy().into_owned())))
+        .collect();
+    let tcp = tcp_sockets();
+    let tracer = signal_compat::get_tracer_pid();
+
+    let findings: Vec<_> = open.iter().filter_map(|(fd, link)| inspect(*fd, link, &tcp, tracer)).collect();
+    diag!("[FD_TABLE] inherited and still open: {:?}, findings={}", open, findings.len());
+    engine.record_feature("inherited_fds", open.len() as f64);
+    for (source, details, weight, confidence) in findings {
+        engine.report_with_confidence(source, weight, confidence, &reason!("Leaked descriptor: {}", details));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_fd() {
+        assert_eq!(classify_fd("socket:[4242]"), FdKind::Socket(4242));
+        assert_eq!(classify_fd("pipe:[77]"), FdKind::Pipe("pipe:[77]".to_string()));
+        assert_eq!(classify_fd("/memfd:frida-agent-64.so (deleted)"), FdKind::Memfd("frida-agent-64.so".to_string()));
+        assert_eq!(classify_fd("anon_inode:[eventfd]"), FdKind::EventFd);
+        assert_eq!(classify_fd("/dev/pts/3"), FdKind::Ignored);
+        assert_eq!(classify_fd("/home/user/notes.txt"), FdKind::Ignored);
+    }
+
+    #[test]
+    fn test_debug_port() {
+        let socket = TcpSocket { local_port: 40000, remote_port: 27042, state: 1, inode: 9 };
+        assert_eq!(debug_port(&socket), Some((27042, "frida-server")));
+        assert_eq!(debug_port(&TcpSocket { local_port: 40000, remote_port: 443, state: 1, inode: 9 }), None);
+    }
+}
@@ -0,0 +1,225 @@
+//! x87 / SSE Numeric Behavior Fingerprint
+//!
+//! # Overview
+//!
+//! Floating-point edge cases are defined by the silicon, not by timing, so
+//! they give an emulation signal that RDTSC virtualization cannot hide.
+//! Software FPUs rarely reproduce them bit-exactly:
+//!
+//! | Probe                 | Hardware                                   | Typical emulator            |
+//! |-----------------------|--------------------------------------------|-----------------------------|
+//! | x87 (1 + 2^-60) - 1   | 2^-60 (80-bit extended precision)          | 0 (x87 modeled as double)   |
+//! | Denormal x 1.0        | Denormal preserved (FTZ/DAZ off)           | Flushed or slow-path errors |
+//! | FTZ / DAZ in MXCSR    | Underflow / denormal input flushed to 0    | Bits ignored                |
+//! | RCPSS / RSQRTSS       | 12-bit table approximation, vendor-specific| Exactly rounded division    |
+//!
+//! On Intel the approximations are truncated to 12 mantissa bits (low 11
+//! bits zero) and `rcpss(1.0) = rsqrtss(1.0) = 0x3f7ff000`. Other vendors
+//! are only checked against the architectural error bound (1.5 * 2^-12).
+//!
+//! # Why This Fails
+//!
+//! - Hardware virtualization executes FP natively - only emulators are caught
+//! - QEMU's softfloat implements 80-bit x87 and FTZ/DAZ correctly
+//! - An emulator can reproduce the Intel RCP table if it cares enough
+
+use core::arch::x86_64::{CpuidResult, _mm_cvtss_f32, _mm_mul_ss, _mm_rcp_ss, _mm_rsqrt_ss, _mm_set_ss};
+use std::hint::black_box;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::ffi::{get_mxcsr, set_mxcsr, x87_extended_residual};
+
+const MXCSR_DAZ: u32 = 1 << 6;
+const MXCSR_FTZ: u32 = 1 << 15;
+
+/// Architectural maximum relative error of RCPSS/RSQRTSS
+const APPROX_MAX_REL_ERROR: f32 = 1.5 / 4096.0;
+
+/// Intel's RCPSS(1.0) and RSQRTSS(1.0) result (1 - 2^-12)
+const INTEL_APPROX_ONE: u32 = 0x3f7f_f000;
+
+/// Inputs for the reciprocal approximation probes
+const APPROX_INPUTS: &[f32] = &[1.0, 2.0, 3.0, 5.0, 7.0, 10.0, 1.5, 0.3];
+
+fn mul_ss(a: f32, b: f32) -> f32 {
+    // SAFETY: SSE is part of the x86_64 baseline
+    unsafe { _mm_cvtss_f32(_mm_mul_ss(_mm_set_ss(black_box(a)), _mm_set_ss(black_box(b)))) }
+}
+
+fn rcp_ss(x: f32) -> f32 {
+    unsafe { _mm_cvtss_f32(_mm_rcp_ss(_mm_set_ss(black_box(x)))) }
+}
+
+fn rsqrt_ss(x: f32) -> f32 {
+    unsafe { _mm_cvtss_f32(_mm_rsqrt_ss(_mm_set_ss(black_box(x)))) }
+}
+
+/// Run `f` with extra MXCSR bits set, restoring the original afterwards
+fn with_mxcsr<R>(bits: u32, f: impl FnOnce() -> R) -> R {
+    unsafe {
+        let saved = get_mxcsr();
+        set_mxcsr(saved | bits);
+        let result = f();
+        set_mxcsr(saved);
+        result
+    }
+}
+
+#[allow(unused_unsafe)] // `__cpuid` is only safe on newer toolchains
+fn cpu_vendor() -> String {
+    let leaf0: CpuidResult = unsafe { core::arch::x86_64::__cpuid(0) };
+    let mut bytes = Vec::with_capacity(12);
+    for reg in [leaf0.ebx, leaf0.edx, leaf0.ecx] {
+        bytes.extend_from_slice(&reg.to_le_bytes());
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Denormal / FTZ / DAZ behavior. Returns descriptions of deviations.
+fn denormal_deviations() -> Vec<String> {
+    let mut out = Vec::new();
+    let tiny = f32::from_bits(4); // subnormal
+    let half_min_normal = f32::MIN_POSITIVE;
+
+    let plain = mul_ss(tiny, 1.0);
+    if plain.to_bits() != 4 {
+        out.push(reason!("denormal x 1.0 = {:#x} with FTZ/DAZ off", plain.to_bits()));
+    }
+
+    let underflow = mul_ss(half_min_normal, 0.5);
+    if !underflow.is_subnormal() {
+        out.push(reason!("gradual underflow produced {:#x}", underflow.to_bits()));
+    }
+
+    let ftz = with_mxcsr(MXCSR_FTZ, || mul_ss(half_min_normal, 0.5));
+    if ftz != 0.0 {
+        out.push(reason!("FTZ ignored: underflow = {:#x}", ftz.to_bits()));
+    }
+
+    let daz = with_mxcsr(MXCSR_DAZ, || mul_ss(tiny, 1.0));
+    if daz != 0.0 {
+        out.push(reason!("DAZ ignored: denormal input = {:#x}", daz.to_bits()));
+    }
+
+    out
+}
+
+/// Result of the RCPSS/RSQRTSS probes
+#[derive(Debug, Default)]
+pub struct ApproxReport {
+    /// Results outside the architectural error bound
+    pub out_of_bound: usize,
+    /// Results identical to the exactly rounded value
+    pub exact: usize,
+    /// Results with any of the low 11 mantissa bits set (Intel truncates them)
+    pub untruncated: usize,
+    pub total: usize,
+}
+
+/// Classify approximate results against their exact values
+pub fn classify_approx(pairs: &[(f32, f32)]) -> ApproxReport {
+    let mut report = ApproxReport { total: pairs.len(), ..Default::default() };
+    for &(approx, exact) in pairs {
+        if ((approx - exact) / exact).abs() > APPROX_MAX_REL_ERROR {
+            report.out_of_bound += 1;
+        }
+        if approx.to_bits() == exact.to_bits() {
+            report.exact += 1;
+        }
+        if approx.to_bits() & 0x7ff != 0 {
+            report.untruncated += 1;
+        }
+    }
+    report
+}
+
+/// Main entry point for the numeric behavior fingerprint
+pub fn check_fpu_fingerprint(engine: &mut DecisionEngine) {
+    let vendor = cpu_vendor();
+
+    // 1. x87 extended precision
+    let residual = unsafe { x87_extended_residual() };
+    let x87_ok = residual == 2f64.powi(-60);
+    engine.record_flag("x87_extended_ok", x87_ok);
+    if !x87_ok {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            45,
+            0.85,
+            &reason!("x87 lacks 80-bit precision: (1 + 2^-60) - 1 = {:e} (x87 modeled as double?)", residual)
+        );
+    }
+
+    // 2. Denormals, FTZ, DAZ
+    let denormal = denormal_deviations();
+    engine.record_feature("fp_denormal_deviations", denormal.len() as f64);
+    if !denormal.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            30,
+            0.6,
+            &reason!("SSE denormal handling differs from hardware: {}", denormal.join("; "))
+        );
+    }
+
+    // 3. RCPSS / RSQRTSS approximation tables
+    let mut pairs = Vec::with_capacity(APPROX_INPUTS.len() * 2);
+    for &x in APPROX_INPUTS {
+        pairs.push((rcp_ss(x), 1.0 / x));
+        pairs.push((rsqrt_ss(x), 1.0 / x.sqrt()));
+    }
+    let approx = classify_approx(&pairs);
+    engine.record_feature("rcp_exact_fraction", approx.exact as f64 / approx.total as f64);
+
+    diag!("[FPU] vendor={} x87_residual={:e} denormal_deviations={} rcp: exact={}/{} out_of_bound={} untruncated={}",
+          vendor, residual, denormal.len(), approx.exact, approx.total, approx.out_of_bound, approx.untruncated);
+
+    if approx.out_of_bound > 0 {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            40,
+            0.8,
+            &reason!("{} RCPSS/RSQRTSS results exceed the architectural error bound", approx.out_of_bound)
+        );
+    } else if approx.exact == approx.total {
+        // Hardware never returns the exactly rounded value for every input
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            40,
+            0.8,
+            &reason!("RCPSS/RSQRTSS return exactly rounded results (software division, not a hardware table)")
+        );
+    } else if vendor == "GenuineIntel"
+        && (approx.untruncated > 0 || rcp_ss(1.0).to_bits() != INTEL_APPROX_ONE || rsqrt_ss(1.0).to_bits() != INTEL_APPROX_ONE)
+    {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            25,
+            0.6,
+            &reason!("CPUID claims GenuineIntel but RCPSS/RSQRTSS do not match Intel's table ({} untruncated)", approx.untruncated)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_exact_division() {
+        let pairs: Vec<(f32, f32)> = [2.0f32, 3.0].iter().map(|x| (1.0 / x, 1.0 / x)).collect();
+        let report = classify_approx(&pairs);
+        assert_eq!(report.exact, 2);
+        assert_eq!(report.out_of_bound, 0);
+    }
+
+    #[test]
+    fn test_hardware_rcp_within_bound() {
+        let pairs: Vec<(f32, f32)> = APPROX_INPUTS.iter().map(|&x| (rcp_ss(x), 1.0 / x)).collect();
+        assert_eq!(classify_approx(&pairs).out_of_bound, 0);
+    }
+
+    #[test]
+    fn test_x87_extended_precision() {
+        assert_eq!(unsafe { x87_extended_residual() }, 2f64.powi(-60));
+    }
+}
@@ -0,0 +1,235 @@
+//! Frida / Gum Detection (Loaded Objects vs /proc/self/maps)
+//!
+//! # Overview
+//!
+//! Frida injects its agent (or is linked in as `frida-gadget.so`) as an
+//! ordinary shared object, built on the Gum instrumentation library. There
+//! are two independent places to look for it:
+//!
+//! 1. **/proc/self/maps** ([`check_frida_maps`]): file-backed mappings whose
+//!    path names Frida. This is the kernel's view, and the one bypass
+//!    scripts sanitise (renamed files, memfd names, hooked `open`/`read`
+//!    serving an edited copy).
+//! 2. **The loader's list** ([`check_frida_loaded_objects`]):
+//!    `dl_iterate_phdr` walks the dynamic loader's `link_map` without
+//!    touching /proc. Each object is matched by name and by its dynamic
+//!    string table, which still holds Gum/Frida export names however the
+//!    file was renamed.
+//!
+//! The two checks report separately. The second also records how many of
+//! its hits have no matching file mapping in maps; the correlation engine
+//! treats "loader sees Frida, maps does not" as a contradiction.
+//!
+//! # Why This Fails
+//!
+//! - Injectors that map the agent manually (no `dlopen`) never enter the
+//!   loader's list
+//! - A patched agent can strip or rename its exports, or unlink itself from
+//!   `link_map` after loading
+//! - `frida-server` attaching via ptrace leaves the agent in the list only
+//!   while it is injected
+
+use std::collections::HashSet;
+use std::ffi::CStr;
+use std::fs;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Object-name fragments used by Frida builds (lowercase)
+const NAME_SIGNATURES: &[&str] = &["frida", "gum-js", "gadget"];
+
+/// Dynamic-string-table prefixes exported by Gum and the Frida agent
+const EXPORT_SIGNATURES: &[&str] = &["gum_", "frida_", "_frida_", "gumjs_"];
+
+/// Gum/Frida dynamic strings needed before an object counts as Gum-based
+/// (a single `gum_` string could be coincidence)
+const MIN_GUM_EXPORTS: usize = 3;
+
+/// `DT_NULL`, `DT_STRTAB`, `DT_STRSZ` from <elf.h>
+const DT_NULL: i64 = 0;
+const DT_STRTAB: i64 = 5;
+const DT_STRSZ: i64 = 10;
+
+/// `Elf64_Dyn`
+#[repr(C)]
+struct Dyn {
+    d_tag: i64,
+    d_val: u64,
+}
+
+/// One object from the loader's list
+#[derive(Debug, Clone)]
+pub struct LoadedObject {
+    pub name: String,
+    /// Start of the object's first `PT_LOAD` segment
+    pub base: usize,
+    /// Gum/Frida names in the object's dynamic string table
+    pub gum_exports: usize,
+}
+
+impl LoadedObject {
+    pub fn is_frida(&self) -> bool {
+        name_matches(&self.name) || self.gum_exports >= MIN_GUM_EXPORTS
+    }
+}
+
+/// Whether the file name part of `path` looks like a Frida component
+pub fn name_matches(path: &str) -> bool {
+    let file = path.rsplit('/').next().unwrap_or(path).to_ascii_lowercase();
+    NAME_SIGNATURES.iter().any(|sig| file.contains(sig))
+}
+
+/// Count Gum/Frida names in a dynamic string table
+pub fn count_gum_exports(strtab: &[u8]) -> usize {
+    strtab.split(|&b| b == 0)
+        .filter(|s| EXPORT_SIGNATURES.iter().any(|sig| s.starts_with(sig.as_bytes())))
+        .count()
+}
+
+/// Dynamic string table of the object described by `info`
+///
+/// # Safety
+/// `info` must come from `dl_iterate_phdr`, which keeps the object mapped
+/// for the duration of the callback
+unsafe fn dynamic_strtab<'a>(info: &libc::dl_phdr_info) -> Option<&'a [u8]> {
+    let phdrs = std::slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum as usize);
+    let dynamic = phdrs.iter().find(|p| p.p_type == libc::PT_DYNAMIC)?;
+    let mut entry = (info.dlpi_addr as usize + dynamic.p_vaddr as usize) as *const Dyn;
+    let (mut strtab, mut strsz) = (0usize, 0usize);
+    while (*entry).d_tag != DT_NULL {
+        match (*entry).d_tag {
+            DT_STRTAB => strtab = (*entry).d_val as usize,
+            DT_STRSZ => strsz = (*entry).d_val as usize,
+            _ => {}
+        }
+        entry = entry.add(1);
+    }
+    if strtab == 0 || strsz == 0 {
+        return None;
+    }
+    // glibc relocates DT_STRTAB in place; the vDSO's entry stays unrelocated
+    if strtab < info.dlpi_addr as usize {
+        strtab += info.dlpi_addr as usize;
+    }
+    Some(std::slice::from_raw_parts(strtab as *const u8, strsz))
+}
+
+unsafe extern "C" fn collect(info: *mut libc::dl_phdr_info, _size: libc::size_t, data: *mut libc::c_void) -> libc::c_int {
+    let objects = &mut *(data as *mut Vec<LoadedObject>);
+    let info = &*info;
+    let name = if info.dlpi_name.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(info.dlpi_name).to_string_lossy().into_owned()
+    };
+    let phdrs = std::slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum as usize);
+    let base = phdrs.iter()
+        .find(|p| p.p_type == libc::PT_LOAD)
+        .map_or(info.dlpi_addr as usize, |p| info.dlpi_addr as usize + p.p_vaddr as usize);
+    let gum_exports = dynamic_strtab(info).map_or(0, count_gum_exports);
+    objects.push(LoadedObject { name, base, gum_exports });
+    0
+}
+
+/// Every object in the dynamic loader's list
+pub fn loaded_objects() -> Vec<LoadedObject> {
+    let mut objects: Vec<LoadedObject> = Vec::new();
+    // SAFETY: `collect` only dereferences what the loader hands it, and
+    // `objects` outlives the call
+    unsafe {
+        libc::dl_iterate_phdr(Some(collect), &mut objects as *mut Vec<LoadedObject> as *mut libc::c_void);
+    }
+    objects
+}
+
+/// File-backed mappings as (start, end, path)
+fn file_mappings() -> Vec<(usize, usize, String)> {
+    let Ok(maps) = fs::read_to_string("/proc/self/maps") else { return Vec::new() };
+    maps.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.splitn(6, ' ').collect();
+            let path = fields.get(5)?.trim();
+            if path.is_empty() || path.starts_with('[') {
+                return None;
+            }
+            let (start, end) = fields[0].split_once('-')?;
+            Some((usize::from_str_radix(start, 16).ok()?, usize::from_str_radix(end, 16).ok()?, path.to_string()))
+        })
+        .collect()
+}
+
+/// Maps-based check: Frida names among the file-backed mappings
+pub fn check_frida_maps(engine: &mut DecisionEngine) {
+    let hits: HashSet<String> = file_mappings().into_iter()
+        .filter(|(_, _, path)| name_matches(path))
+        .map(|(_, _, path)| path)
+        .collect();
+    engine.record_feature("frida_maps_objects", hits.len() as f64);
+
+    for path in hits {
+        engine.report_with_confidence(
+            DetectionSource::Instrumentation,
+            50,
+            0.85,
+            &reason!("Frida component mapped into the process: {}", path)
+        );
+    }
+}
+
+/// Loader-list check: Frida objects by name or Gum exports via `dl_iterate_phdr`
+pub fn check_frida_loaded_objects(engine: &mut DecisionEngine) {
+    let objects = loaded_objects();
+    let mappings = file_mappings();
+    diag!("[FRIDA] {} loaded objects, {} file mappings", objects.len(), mappings.len());
+
+    let mut found = 0usize;
+    let mut unmapped = 0usize;
+    for object in objects.iter().filter(|o| o.is_frida()) {
+        found += 1;
+        let label = if object.name.is_empty() { format!("{:#x}", object.base) } else { object.name.clone() };
+        engine.report_with_confidence(
+            DetectionSource::Instrumentation,
+            50,
+            0.9,
+            &reason!("Loaded object {} matches Frida/Gum ({} Gum exports)", label, object.gum_exports)
+        );
+
+        // Maps should show the same file mapped at the object's base
+        let backing = mappings.iter().find(|(start, end, _)| (*start..*end).contains(&object.base));
+        let hidden = match backing {
+            None => true,
+            Some((_, _, path)) => !object.name.is_empty() && !path.starts_with(&object.name),
+        };
+        if hidden {
+            unmapped += 1;
+            diag!("[FRIDA] {} at {:#x} has no matching /proc/self/maps entry ({:?})", label, object.base, backing.map(|b| &b.2));
+        }
+    }
+    engine.record_feature("frida_loaded_objects", found as f64);
+    engine.record_feature("frida_unmapped_objects", unmapped as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signatures() {
+        assert!(name_matches("/data/local/tmp/frida-agent-64.so"));
+        assert!(name_matches("/usr/lib/libGadget.so"));
+        assert!(!name_matches("/usr/lib/x86_64-linux-gnu/libc.so.6"));
+        assert!(!name_matches("/opt/frida/libc.so.6"));
+
+        let strtab = b"\0libc.so.6\0gum_init_embedded\0gum_interceptor_attach\0frida_agent_main\0malloc\0";
+        assert_eq!(count_gum_exports(strtab), 3);
+    }
+
+    #[test]
+    fn test_loaded_objects_walk() {
+        let objects = loaded_objects();
+        assert!(objects.len() >= 2, "{:?}", objects);
+        // libc is always loaded and exports no Gum names
+        let libc = objects.iter().find(|o| o.name.contains("libc.so")).expect("libc in loader list");
+        assert_eq!(libc.gum_exports, 0);
+        assert!(!objects.iter().any(|o| o.is_frida()));
+    }
+}
@@ -16,8 +16,10 @@
 //! 1. **Signal-based exception**: Attempt DRx read, catch SIGSEGV
 //!    - If no SIGSEGV: hypervisor is intercepting (detection evidence)
 //!    
-//! 2. **TracerPid + ptrace PEEKUSR**: If we have a tracer, read its DR values
-//!    - Requires cooperation of the tracer (unlikely if hostile)
+//! 2. **Guard PTRACE_PEEKUSER**: With `ANTIDEBUG_GUARD=1`, a cooperative
+//!    guard process reads the real DR0-DR7 (see `engine/guard.rs`)
+//!    - Replaces inference with register contents when no debugger holds
+//!      the ptrace slot
 //!    
 //! 3. **Timing-based inference**: Hardware BP hits add overhead
 //!    - Measure NOP loop timing, detect anomalies
@@ -35,6 +37,7 @@
 
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::ptr;
+use crate::engine::guard;
 use crate::engine::policy::{DecisionEngine, DetectionSource};
 
 extern "C" {
@@ -72,13 +75,13 @@ fn check_via_signal_exception(engine: &mut DecisionEngine) {
     let tracer_pid = crate::engine::signal_compat::get_tracer_pid();
     
     if tracer_pid > 0 {
-        eprintln!("[HW_BP] Tracer detected (PID {}), skipping signal-based DR7 check to avoid conflict", tracer_pid);
+        diag!("[HW_BP] Tracer detected (PID {}), skipping signal-based DR7 check to avoid conflict", tracer_pid);
         // We already know we're being traced, so report that
         engine.report_with_confidence(
             DetectionSource::HardwareBreakpoint,
             20,  // Lower weight since we're inferring
             0.7, // Moderate confidence
-            &format!("DR7 signal check skipped due to tracer (PID {})", tracer_pid)
+            &reason!("DR7 signal check skipped due to tracer (PID {})", tracer_pid)
         );
         return;
     }
@@ -92,7 +95,7 @@ fn check_via_signal_exception(engine: &mut DecisionEngine) {
         
         let mut old_sa: libc::sigaction = std::mem::zeroed();
         if libc::sigaction(libc::SIGSEGV, &sa, &mut old_sa) != 0 {
-            eprintln!("[HW_BP] Failed to install SIGSEGV handler");
+            diag!("[HW_BP] Failed to install SIGSEGV handler");
             return;
         }
         
@@ -106,12 +109,14 @@ fn check_via_signal_exception(engine: &mut DecisionEngine) {
         libc::sigaction(libc::SIGSEGV, &old_sa, ptr::null_mut());
     }
     
-    if !DR_ACCESS_FAULTED.load(Ordering::SeqCst) {
+    let faulted = DR_ACCESS_FAULTED.load(Ordering::SeqCst);
+    engine.record_flag("dr7_access_faulted", faulted);
+    if !faulted {
         // No fault means a hypervisor intercepted the access
         engine.report(
             DetectionSource::HardwareBreakpoint,
             30,
-            "DR7 access did not fault - hypervisor virtualization detected"
+            &reason!("DR7 access did not fault - hypervisor virtualization detected")
         );
     }
     // If it DID fault, that's expected and normal - no evidence either way
@@ -135,22 +140,25 @@ fn check_via_timing(engine: &mut DecisionEngine) {
     let mean = timings.iter().sum::<u64>() as f64 / ITERATIONS as f64;
     let min = *timings.iter().min().unwrap_or(&0);
     let max = *timings.iter().max().unwrap_or(&0);
+    engine.record_feature("dr_nop_loop_mean", mean);
     
     // Thresholds (empirical):
     // Native (no HW BP): ~500-2000 cycles for 1000 NOPs
     // With HW BP on code: Could be 10000+ cycles if hitting frequently
+    // A calibrated baseline replaces the elevated level
+    let elevated = engine.baseline_limit("dr_nop_loop_mean", 8.0).unwrap_or(10000.0);
     
     if mean > 50000.0 {
         engine.report(
             DetectionSource::HardwareBreakpoint,
             50,
-            &format!("NOP timing suggests hardware BP activity: mean={:.0} cycles", mean)
+            &reason!("NOP timing suggests hardware BP activity: mean={:.0} cycles", mean)
         );
-    } else if mean > 10000.0 {
+    } else if mean > elevated {
         engine.report(
             DetectionSource::HardwareBreakpoint,
             20,
-            &format!("NOP timing elevated (possible HW BP): mean={:.0} cycles", mean)
+            &reason!("NOP timing elevated (possible HW BP): mean={:.0} cycles", mean)
         );
     }
     
@@ -159,17 +167,18 @@ fn check_via_timing(engine: &mut DecisionEngine) {
         engine.report(
             DetectionSource::HardwareBreakpoint,
             15,
-            &format!("NOP timing variance suggests intermittent HW BP: min={}, max={}", min, max)
+            &reason!("NOP timing variance suggests intermittent HW BP: min={}, max={}", min, max)
         );
     }
     
-    eprintln!("[HW_BP] NOP loop timing: mean={:.1}, min={}, max={}", mean, min, max);
+    diag!("[HW_BP] NOP loop timing: mean={:.1}, min={}, max={}", mean, min, max);
 }
 
 /// Method 3: Check /proc/self/status for hardware debug hints
 /// 
 /// Limited utility - the kernel doesn't expose DRx contents here,
 /// but we can check for related indicators.
+#[allow(clippy::lines_filter_map_ok)] // Unreadable lines are skipped
 fn check_via_proc_status(engine: &mut DecisionEngine) {
     use std::fs::File;
     use std::io::{BufRead, BufReader};
@@ -184,7 +193,7 @@ fn check_via_proc_status(engine: &mut DecisionEngine) {
                 engine.report(
                     DetectionSource::HardwareBreakpoint,
                     40,
-                    &format!("Unexpected debug register info in /proc: {}", line)
+                    &reason!("Unexpected debug register info in /proc: {}", line)
                 );
             }
         }
@@ -196,6 +205,7 @@ fn check_via_proc_status(engine: &mut DecisionEngine) {
 /// If a debugger has set a data breakpoint (read/write) on a specific
 /// address, accessing it will generate a debug exception.
 /// We can detect this via timing or exception delivery.
+#[allow(clippy::implicit_saturating_sub)] // Explicit wrap-around guard
 fn check_via_data_access_pattern(engine: &mut DecisionEngine) {
     // Allocate a test buffer
     let mut test_data: [u64; 16] = [0; 16];
@@ -211,6 +221,7 @@ fn check_via_data_access_pattern(engine: &mut DecisionEngine) {
     
     let end = unsafe { crate::ffi::get_rdtsc() };
     let delta = if end > start { end - start } else { 0 };
+    engine.record_feature("data_access_cycles", delta as f64);
     
     // 1000 simple memory operations: ~500-2000 cycles normally
     // With data breakpoint: Could be 500,000+ cycles
@@ -219,20 +230,43 @@ fn check_via_data_access_pattern(engine: &mut DecisionEngine) {
         engine.report(
             DetectionSource::HardwareBreakpoint,
             40,
-            &format!("Data access pattern timing anomaly (data BP?): {} cycles", delta)
+            &reason!("Data access pattern timing anomaly (data BP?): {} cycles", delta)
         );
     } else if delta > 50_000 {
         engine.report_with_confidence(
             DetectionSource::HardwareBreakpoint,
             10,  // Reduced from 15
             0.4, // Lower confidence - could be cache/frequency effects
-            &format!("Data access slightly slow (possible data BP): {} cycles", delta)
+            &reason!("Data access slightly slow (possible data BP): {} cycles", delta)
+        );
+    }
+}
+
+/// Method 5: Real DR0-DR7 contents read by the cooperative guard process
+fn check_via_guard(engine: &mut DecisionEngine) {
+    let regs = match guard::read_debug_registers() {
+        Ok(regs) => regs,
+        Err(e) => {
+            diag!("[HW_BP] Guard readback unavailable: {}", e);
+            return;
+        }
+    };
+    let armed = regs.armed_slots();
+    diag!("[HW_BP] Guard read DR0-3={:x?} DR6={:#x} DR7={:#x}", &regs.dr[..4], regs.dr[6], regs.dr[7]);
+    engine.record_feature("dr7_enabled_slots", armed.len() as f64);
+    if !armed.is_empty() {
+        let slots: Vec<String> = armed.iter().map(|&i| format!("DR{}={:#x}", i, regs.address(i))).collect();
+        engine.report(
+            DetectionSource::HardwareBreakpoint,
+            60,
+            &reason!("Hardware breakpoints armed in DR7 ({:#x}): {}", regs.dr[7], slots.join(", "))
         );
     }
 }
 
 /// Main entry point for hardware breakpoint detection
 pub fn check_hardware_breakpoints(engine: &mut DecisionEngine) {
+    return_probe!();
     // Method 1: Signal-based detection (hypervisor presence)
     check_via_signal_exception(engine);
     
@@ -244,6 +278,11 @@ pub fn check_hardware_breakpoints(engine: &mut DecisionEngine) {
     
     // Method 4: Data access pattern (data breakpoints)
     check_via_data_access_pattern(engine);
+    
+    // Method 5: Actual register contents, when the guard is enabled
+    if guard::enabled() {
+        check_via_guard(engine);
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,133 @@
+//! Host-Wide Analysis Daemon Scan (frida-server, gdbserver, rr, strace)
+//!
+//! # Overview
+//!
+//! The other process detectors only care about tools aimed at us: our
+//! tracer, our ancestors, or a command line naming our PID. An analyst's
+//! machine usually has the tooling already running before it points it at
+//! us - `frida-server` waiting for a connection, a `gdbserver --multi`, an
+//! `rr record` of a neighbouring process. This opt-in scan walks every
+//! `/proc/<pid>/comm` and `cmdline` on the host for such daemons, plus a
+//! listener on frida-server's default port 27042.
+//!
+//! Nothing here is attached to us, so each hit is low-confidence
+//! environmental evidence routed to the source the tool would feed once
+//! it attaches. Enable with `--host-scan` or `ANTIDEBUG_HOST_SCAN=1`.
+//!
+//! # Why This Fails
+//!
+//! - Renamed binaries (`frida-server` is often shipped as `fs64`) are only
+//!   caught by the port, and only while it is the default
+//! - PID namespaces (containers) hide the host's processes
+//! - `hidepid=2` on /proc hides other users' processes
+//! - Developers' workstations run these tools legitimately
+
+use std::collections::BTreeSet;
+use std::fs;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::detectors::remote_debug::parse_proc_net_tcp;
+
+/// Daemon name prefixes (matched against comm and argv[0]) and the source
+/// their evidence goes to
+const DAEMONS: &[(&str, DetectionSource)] = &[
+    ("frida-server", DetectionSource::Instrumentation),
+    ("frida-portal", DetectionSource::Instrumentation),
+    ("frida-helper", DetectionSource::Instrumentation),
+    ("gdbserver", DetectionSource::RemoteDebug),
+    ("lldb-server", DetectionSource::RemoteDebug),
+    ("rr", DetectionSource::RecordReplay),
+    ("strace", DetectionSource::Ptrace),
+    ("ltrace", DetectionSource::Ptrace),
+];
+
+/// frida-server's default listening port
+const FRIDA_DEFAULT_PORT: u16 = 27042;
+
+/// `TCP_LISTEN` state in /proc/net/tcp
+const TCP_LISTEN: u8 = 0x0A;
+
+/// Weight and confidence of each environmental hit
+const DAEMON_WEIGHT: u32 = 8;
+const DAEMON_CONFIDENCE: f64 = 0.3;
+
+/// Daemon matching a process's comm or argv[0], if any
+pub fn daemon_match(comm: &str, cmdline: &[u8]) -> Option<(&'static str, DetectionSource)> {
+    let argv0 = cmdline.split(|&b| b == 0).next().unwrap_or(&[]);
+    let argv0 = String::from_utf8_lossy(argv0);
+    let argv0 = argv0.rsplit('/').next().unwrap_or("");
+    let names = [comm.trim(), argv0];
+    DAEMONS.iter()
+        .find(|(daemon, _)| {
+            names.iter().any(|name| {
+                // Exact for short names ("rr"), prefix for versioned builds
+                // ("frida-server-16.1.4-linux-x86_64")
+                *name == *daemon || (daemon.len() > 3 && name.starts_with(daemon))
+            })
+        })
+        .map(|(daemon, source)| (*daemon, *source))
+}
+
+/// Every daemon process on the host other than us, as (pid, daemon, source)
+fn daemon_processes() -> Vec<(u32, &'static str, DetectionSource)> {
+    let me = std::process::id();
+    let Ok(entries) = fs::read_dir("/proc") else { return Vec::new() };
+    entries.flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|pid| *pid != me)
+        .filter_map(|pid| {
+            let comm = fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+            let cmdline = fs::read(format!("/proc/{}/cmdline", pid)).unwrap_or_default();
+            let (daemon, source) = daemon_match(&comm, &cmdline)?;
+            Some((pid, daemon, source))
+        })
+        .collect()
+}
+
+fn frida_port_listening() -> bool {
+    ["/proc/net/tcp", "/proc/net/tcp6"].iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|text| parse_proc_net_tcp(&text))
+        .any(|s| s.state == TCP_LISTEN && s.local_port == FRIDA_DEFAULT_PORT)
+}
+
+/// Main entry point for the host-wide daemon scan
+pub fn check_host_daemons(engine: &mut DecisionEngine) {
+    let daemons = daemon_processes();
+    let kinds: BTreeSet<&str> = daemons.iter().map(|(_, daemon, _)| *daemon).collect();
+    for daemon in &kinds {
+        let pids: Vec<String> = daemons.iter().filter(|(_, d, _)| d == daemon).map(|(pid, _, _)| pid.to_string()).collect();
+        let source = daemons.iter().find(|(_, d, _)| d == daemon).map(|(_, _, s)| *s).unwrap_or(DetectionSource::Instrumentation);
+        engine.report_with_confidence(
+            source,
+            DAEMON_WEIGHT,
+            DAEMON_CONFIDENCE,
+            &reason!("{} running on this host (pid {}), not attached to us", daemon, pids.join(", "))
+        );
+    }
+
+    let frida_port = frida_port_listening();
+    if frida_port {
+        engine.report_with_confidence(
+            DetectionSource::Instrumentation,
+            DAEMON_WEIGHT,
+            DAEMON_CONFIDENCE,
+            &reason!("A process is listening on frida-server's default port {}", FRIDA_DEFAULT_PORT)
+        );
+    }
+    diag!("[HOST_DAEMONS] daemons={:?} frida_port={}", kinds, frida_port);
+    engine.record_feature("host_analysis_daemons", (kinds.len() + frida_port as usize) as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daemon_match() {
+        assert_eq!(daemon_match("frida-server-16\n", b"/data/local/tmp/frida-server-16.1.4-linux-x86_64\0"), Some(("frida-server", DetectionSource::Instrumentation)));
+        assert_eq!(daemon_match("rr\n", b"rr\0record\0./app\0"), Some(("rr", DetectionSource::RecordReplay)));
+        assert_eq!(daemon_match("python3\n", b"/usr/bin/gdbserver\0:1234\0"), Some(("gdbserver", DetectionSource::RemoteDebug)));
+        assert_eq!(daemon_match("rrdtool\n", b"rrdtool\0"), None);
+        assert_eq!(daemon_match("bash\n", b"/bin/bash\0"), None);
+    }
+}
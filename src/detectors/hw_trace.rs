@@ -0,0 +1,175 @@
+//! Hardware Branch-Tracer Probing (Intel PT / BTS / LBR)
+//!
+//! # Overview
+//!
+//! Intel PT, BTS and the LBR branch stack record every branch we take
+//! without touching our code or memory, so none of the software checks see
+//! them. They are scarce, though: the kernel lets only one PT or BTS event
+//! exist per context and, on CPUs where they cannot coexist, keeps PT, BTS
+//! and LBR users mutually exclusive system-wide. A tracer holding one shows
+//! up as `EBUSY` when we try to open the same kind of event on ourselves:
+//!
+//! | Probe (on ourselves, never enabled)              | PMU                | Busy means                         |
+//! |--------------------------------------------------|--------------------|------------------------------------|
+//! | `intel_pt` event                                 | `intel_pt/type`    | PT traces us, or BTS/LBR is held   |
+//! | `intel_bts` event                                | `intel_bts/type`   | BTS traces us, or PT/LBR is held   |
+//! | Cycles sampling with `PERF_SAMPLE_BRANCH_STACK`  | core PMU with LBR  | PT or BTS is held elsewhere        |
+//!
+//! Which of these PMUs exist is read into the environment
+//! ([`HwTracing`]); any busy probe is evidence (weight 30).
+//!
+//! # Why This Fails
+//!
+//! - Inside VMs these PMUs are almost never exposed, and on CPUs where PT
+//!   and LBR coexist a PT tracer on another process does not block us
+//! - A tracer on another CPU's context (`perf record -a -e intel_pt//`)
+//!   only conflicts once scheduled, which opening alone does not do
+//! - `perf_event_paranoid` 3 forbids opening anything
+
+use crate::detectors::perf_observer::{
+    PerfEventAttr, ATTR_DISABLED, ATTR_EXCLUDE_HV, ATTR_EXCLUDE_KERNEL, PERF_TYPE_HARDWARE,
+};
+use crate::engine::environment::HwTracing;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// `PERF_COUNT_HW_CPU_CYCLES`
+const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+
+/// `PERF_SAMPLE_BRANCH_STACK`
+const PERF_SAMPLE_BRANCH_STACK: u64 = 1 << 11;
+
+/// `PERF_SAMPLE_BRANCH_USER | PERF_SAMPLE_BRANCH_ANY`
+const BRANCH_USER_ANY: u64 = (1 << 0) | (1 << 3);
+
+/// Sampling period of the branch-stack probe (never enabled)
+const LBR_PERIOD: u64 = 100_000;
+
+/// `perf_event_attr` up to `branch_sample_type` (PERF_ATTR_SIZE_VER2, 80 bytes)
+#[repr(C)]
+#[derive(Default)]
+struct BranchAttr {
+    base: PerfEventAttr,
+    config2: u64,
+    branch_sample_type: u64,
+}
+
+/// What opening one tracing event on ourselves returned
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProbeOutcome {
+    /// Opened (and closed again)
+    Free,
+    /// `EBUSY`: the resource is claimed
+    Busy,
+    /// Refused for another reason (errno)
+    Refused(i32),
+}
+
+impl ProbeOutcome {
+    pub fn from_errno(errno: Option<i32>) -> Self {
+        match errno {
+            None => ProbeOutcome::Free,
+            Some(libc::EBUSY) => ProbeOutcome::Busy,
+            Some(errno) => ProbeOutcome::Refused(errno),
+        }
+    }
+}
+
+/// Names of the probes that found their resource claimed
+pub fn busy_units(outcomes: &[(&'static str, ProbeOutcome)]) -> Vec<&'static str> {
+    outcomes.iter().filter(|(_, o)| *o == ProbeOutcome::Busy).map(|(name, _)| *name).collect()
+}
+
+/// Open `attr` on ourselves and close it straight away
+fn probe(attr: &BranchAttr) -> ProbeOutcome {
+    // SAFETY: `attr` is a valid attribute of the size it declares; pid 0 / cpu -1 = this thread, any CPU
+    let fd = unsafe { libc::syscall(libc::SYS_perf_event_open, attr as *const BranchAttr, 0, -1, -1, 0) } as libc::c_int;
+    if fd < 0 {
+        return ProbeOutcome::from_errno(std::io::Error::last_os_error().raw_os_error());
+    }
+    // SAFETY: we own the fd
+    unsafe { libc::close(fd) };
+    ProbeOutcome::Free
+}
+
+/// A disabled, user-only event on the given PMU
+fn pmu_event(type_: u32) -> BranchAttr {
+    BranchAttr {
+        base: PerfEventAttr {
+            type_,
+            size: std::mem::size_of::<BranchAttr>() as u32,
+            flags: ATTR_DISABLED | ATTR_EXCLUDE_KERNEL | ATTR_EXCLUDE_HV,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// Cycles sampling with a user branch stack (LBR)
+fn branch_stack_event() -> BranchAttr {
+    let mut attr = pmu_event(PERF_TYPE_HARDWARE);
+    attr.base.config = PERF_COUNT_HW_CPU_CYCLES;
+    attr.base.sample_period = LBR_PERIOD;
+    attr.base.sample_type = PERF_SAMPLE_BRANCH_STACK;
+    attr.branch_sample_type = BRANCH_USER_ANY;
+    attr
+}
+
+/// Main entry point for the hardware branch-tracer probe
+pub fn check_hw_trace(engine: &mut DecisionEngine) {
+    let tracing = HwTracing::detect();
+    if !tracing.any() {
+        engine.record_diagnostic("hw_trace", &reason!("no Intel PT, BTS or LBR exposed by the kernel"));
+        return;
+    }
+    if !engine.kernel_posture().unprivileged_perf() {
+        engine.record_diagnostic("hw_trace", &reason!("perf_event_paranoid forbids perf for unprivileged users; tracing hardware not probed"));
+        return;
+    }
+
+    let mut outcomes = Vec::new();
+    if let Some(type_) = tracing.intel_pt_type {
+        outcomes.push(("intel_pt", probe(&pmu_event(type_))));
+    }
+    if let Some(type_) = tracing.intel_bts_type {
+        outcomes.push(("intel_bts", probe(&pmu_event(type_))));
+    }
+    if tracing.lbr_entries.is_some() {
+        outcomes.push(("lbr", probe(&branch_stack_event())));
+    }
+    diag!("[HW_TRACE] {:?} -> {:?}", tracing, outcomes);
+    for (name, outcome) in &outcomes {
+        if let ProbeOutcome::Refused(errno) = outcome {
+            engine.record_diagnostic("hw_trace", &reason!("{} probe refused (errno {})", name, errno));
+        }
+    }
+
+    let busy = busy_units(&outcomes);
+    engine.record_feature("hw_trace_busy", busy.len() as f64);
+    if !busy.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::SamplingProfiler,
+            30,
+            0.7,
+            &reason!("Branch-tracing hardware already claimed by another session (EBUSY on {}): our branches may be recorded", busy.join(", "))
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attr_layout() {
+        assert_eq!(std::mem::size_of::<BranchAttr>(), 80);
+    }
+
+    #[test]
+    fn test_busy_units() {
+        assert_eq!(ProbeOutcome::from_errno(None), ProbeOutcome::Free);
+        assert_eq!(ProbeOutcome::from_errno(Some(libc::EBUSY)), ProbeOutcome::Busy);
+        assert_eq!(ProbeOutcome::from_errno(Some(libc::EACCES)), ProbeOutcome::Refused(libc::EACCES));
+        let outcomes = [("intel_pt", ProbeOutcome::Busy), ("intel_bts", ProbeOutcome::Refused(libc::ENOENT)), ("lbr", ProbeOutcome::Free)];
+        assert_eq!(busy_units(&outcomes), vec!["intel_pt"]);
+    }
+}
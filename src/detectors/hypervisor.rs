@@ -0,0 +1,275 @@
+//! Hypervisor Identification and Production/Analysis Classification
+//!
+//! # Overview
+//!
+//! The CPUID hypervisor bit (leaf 1, ECX[31]) is set by every VM, so on its
+//! own it says little: most production software runs under KVM or Hyper-V
+//! in a cloud. What matters is which hypervisor, and whether it behaves
+//! like the one it claims to be. We enumerate the hypervisor leaves
+//! 0x40000000-0x400000FF and classify:
+//!
+//! - **Production**: KVM, Hyper-V, VMware and Xen signatures whose feature
+//!   leaves are consistent with the signature
+//! - **Analysis**: desktop and emulated VMs (VirtualBox, QEMU TCG), and any
+//!   hypervisor that hides or fakes its identity:
+//!   - a signature in leaf 0x40000000 with the hypervisor bit cleared
+//!     (the bit was masked to look like bare metal)
+//!   - the hypervisor bit without a readable signature
+//!   - advertised paravirtual MSRs that do not read back (via
+//!     `/dev/cpu/0/msr`, when we are allowed to open it)
+//!   - an advertised paravirtual clock the kernel did not pick up
+//!
+//! Evidence goes to the `Hypervisor` source, and the class is handed to
+//! the engine ([`VmClass`]) which weighs production VMs down.
+//!
+//! # Why This Fails
+//!
+//! - A hypervisor that passes its leaves through unchanged and is not
+//!   branded (or branded as a production one) classifies as production
+//! - `/dev/cpu/*/msr` needs root and the `msr` module; without it only the
+//!   kernel's clocksource corroborates the signature
+//! - Guest kernels built without paravirt clock support look like a faked
+//!   signature
+
+use std::fs;
+use std::os::unix::fs::FileExt;
+use core::arch::x86_64::{CpuidResult, __cpuid};
+use crate::detectors::cpu_errata::is_hypervisor_leaf;
+use crate::engine::policy::{DecisionEngine, DetectionSource, VmClass};
+
+/// First and last leaf of the hypervisor range
+const HV_BASE: u32 = 0x4000_0000;
+const HV_LAST: u32 = 0x4000_00ff;
+
+/// Known signatures: (leaf 0x40000000 signature, name, class)
+const SIGNATURES: &[(&str, &str, VmClass)] = &[
+    ("KVMKVMKVM", "KVM", VmClass::Production),
+    ("Microsoft Hv", "Hyper-V", VmClass::Production),
+    ("VMwareVMware", "VMware", VmClass::Production),
+    ("XenVMMXenVMM", "Xen", VmClass::Production),
+    ("VBoxVBoxVBox", "VirtualBox", VmClass::Analysis),
+    ("TCGTCGTCGTCG", described!("QEMU TCG"), VmClass::Analysis),
+];
+
+/// KVM feature bits (leaf 0x40000001 EAX) and the MSR each advertises
+const KVM_FEATURE_MSRS: &[(u32, u32, &str)] = &[
+    (3, 0x4b56_4d01, "MSR_KVM_SYSTEM_TIME_NEW"),
+    (4, 0x4b56_4d02, "MSR_KVM_ASYNC_PF_EN"),
+    (5, 0x4b56_4d03, "MSR_KVM_STEAL_TIME"),
+    (6, 0x4b56_4d04, "MSR_KVM_PV_EOI_EN"),
+];
+
+/// Hyper-V partition privileges (leaf 0x40000003 EAX) and their MSRs
+const HYPERV_FEATURE_MSRS: &[(u32, u32, &str)] = &[
+    (0, 0x4000_0010, "HV_X64_MSR_VP_RUNTIME"),
+    (1, 0x4000_0020, "HV_X64_MSR_TIME_REF_COUNT"),
+    (5, 0x4000_0000, "HV_X64_MSR_GUEST_OS_ID"),
+];
+
+/// `KVM_FEATURE_CLOCKSOURCE` / `KVM_FEATURE_CLOCKSOURCE2`
+const KVM_CLOCK_BITS: u32 = (1 << 0) | (1 << 3);
+
+/// "Hv#1", the Hyper-V interface signature in leaf 0x40000001 EAX
+const HYPERV_INTERFACE: u32 = 0x3123_7648;
+
+const CLOCKSOURCES: &str = "/sys/devices/system/clocksource/clocksource0/available_clocksource";
+
+#[allow(unused_unsafe)] // `__cpuid` is only safe on newer toolchains
+fn cpuid(leaf: u32) -> CpuidResult {
+    unsafe { __cpuid(leaf) }
+}
+
+/// Signature text of a 0x40000000 leaf
+pub fn signature(r: CpuidResult) -> String {
+    let bytes: Vec<u8> = [r.ebx, r.ecx, r.edx].iter().flat_map(|v| v.to_le_bytes()).collect();
+    String::from_utf8_lossy(&bytes).trim_matches(|c: char| c == '\0' || c == ' ').to_string()
+}
+
+/// Name and class of a known signature
+pub fn identify(signature: &str) -> Option<(&'static str, VmClass)> {
+    SIGNATURES.iter()
+        .find(|(sig, _, _)| signature.starts_with(sig))
+        .map(|(_, name, class)| (*name, *class))
+}
+
+/// The hypervisor leaves up to the advertised maximum (capped at 0x400000FF)
+fn hypervisor_leaves() -> Vec<(u32, CpuidResult)> {
+    let base = cpuid(HV_BASE);
+    if !is_hypervisor_leaf(base) {
+        return Vec::new();
+    }
+    (HV_BASE..=base.eax.clamp(HV_BASE, HV_LAST)).map(|leaf| (leaf, cpuid(leaf))).collect()
+}
+
+fn leaf(leaves: &[(u32, CpuidResult)], n: u32) -> Option<CpuidResult> {
+    leaves.iter().find(|(l, _)| *l == n).map(|(_, r)| *r)
+}
+
+/// MSRs the feature leaves of `vendor` advertise
+pub fn advertised_msrs(vendor: &str, leaves: &[(u32, CpuidResult)]) -> Vec<(u32, &'static str)> {
+    let (features, table) = match vendor {
+        "KVM" => (leaf(leaves, HV_BASE + 1).map(|r| r.eax), KVM_FEATURE_MSRS),
+        "Hyper-V" => (leaf(leaves, HV_BASE + 3).map(|r| r.eax), HYPERV_FEATURE_MSRS),
+        _ => (None, &[][..]),
+    };
+    let features = features.unwrap_or(0);
+    table.iter().filter(|(bit, _, _)| features & (1 << bit) != 0).map(|(_, msr, name)| (*msr, *name)).collect()
+}
+
+/// Leaf-layout inconsistencies for a known vendor
+pub fn leaf_inconsistencies(vendor: &str, leaves: &[(u32, CpuidResult)]) -> Vec<String> {
+    let mut found = Vec::new();
+    let max = leaves.first().map(|(_, r)| r.eax).unwrap_or(0);
+    match vendor {
+        "KVM" if max < HV_BASE + 1 => found.push(reason!("KVM signature without the 0x40000001 feature leaf")),
+        "Hyper-V" => {
+            let interface = leaf(leaves, HV_BASE + 1).map(|r| r.eax).unwrap_or(0);
+            if max < HV_BASE + 5 || interface != HYPERV_INTERFACE {
+                found.push(reason!("Hyper-V signature with interface {:#x} and max leaf {:#x} (\"Hv#1\" and >= 0x40000005 expected)", interface, max));
+            }
+        }
+        "VMware" if leaf(leaves, HV_BASE + 0x10).is_none_or(|r| r.eax == 0) => {
+            found.push(reason!("VMware signature without the 0x40000010 TSC frequency leaf"));
+        }
+        _ => {}
+    }
+    found
+}
+
+/// Clocksource the kernel should offer under `vendor`, if the leaves
+/// advertise one
+pub fn expected_clocksource(vendor: &str, leaves: &[(u32, CpuidResult)]) -> Option<&'static str> {
+    match vendor {
+        "KVM" => leaf(leaves, HV_BASE + 1).filter(|r| r.eax & KVM_CLOCK_BITS != 0).map(|_| "kvm-clock"),
+        "Hyper-V" => leaf(leaves, HV_BASE + 3).filter(|r| r.eax & (1 << 1) != 0).map(|_| "hyperv_clocksource"),
+        "Xen" => Some("xen"),
+        _ => None,
+    }
+}
+
+/// Advertised MSRs that do not read back, or `None` if the MSR device
+/// cannot be opened
+fn unreadable_msrs(msrs: &[(u32, &'static str)]) -> Option<Vec<&'static str>> {
+    let device = fs::File::open("/dev/cpu/0/msr").ok()?;
+    Some(msrs.iter()
+        .filter(|(msr, _)| {
+            let mut value = [0u8; 8];
+            device.read_at(&mut value, *msr as u64).is_err()
+        })
+        .map(|(_, name)| *name)
+        .collect())
+}
+
+/// Classify the VM from the hypervisor bit, the leaves and what the
+/// system around them says; returns the class, the vendor and the reasons
+/// for an analysis classification
+fn classify(hv_bit: bool, leaves: &[(u32, CpuidResult)]) -> (VmClass, String, Vec<(String, u32)>) {
+    let mut anomalies: Vec<(String, u32)> = Vec::new();
+    let Some((_, base)) = leaves.first() else {
+        if hv_bit {
+            anomalies.push((reason!("hypervisor bit set without a signature in leaf 0x40000000"), 15));
+            return (VmClass::Analysis, "unknown".to_string(), anomalies);
+        }
+        return (VmClass::None, String::new(), anomalies);
+    };
+    let sig = signature(*base);
+    let (vendor, class) = identify(&sig).unwrap_or(("unknown", VmClass::Analysis));
+    match class {
+        VmClass::Analysis if vendor == "unknown" => anomalies.push((reason!("unrecognised hypervisor signature {:?}", sig), 10)),
+        VmClass::Analysis => anomalies.push((reason!("{} is a desktop/emulated hypervisor", vendor), 20)),
+        _ => {}
+    }
+    if !hv_bit {
+        anomalies.push((reason!("{} signature present but the hypervisor bit is masked", vendor), 30));
+    }
+    anomalies.extend(leaf_inconsistencies(vendor, leaves).into_iter().map(|d| (d, 20)));
+
+    let msrs = advertised_msrs(vendor, leaves);
+    match unreadable_msrs(&msrs) {
+        Some(missing) if !missing.is_empty() => anomalies.push((reason!("advertised MSRs do not read back: {}", missing.join(", ")), 30)),
+        Some(_) => {}
+        None => diag!("[HYPERVISOR] /dev/cpu/0/msr unavailable, {} advertised MSR(s) unchecked", msrs.len()),
+    }
+    if let Some(clock) = expected_clocksource(vendor, leaves) {
+        let available = fs::read_to_string(CLOCKSOURCES).unwrap_or_default();
+        if !available.is_empty() && !available.split_whitespace().any(|c| c.starts_with(clock)) {
+            anomalies.push((reason!("{} advertises a paravirtual clock but the kernel offers only {:?}", vendor, available.trim()), 10));
+        }
+    }
+
+    let class = if anomalies.is_empty() { VmClass::Production } else { VmClass::Analysis };
+    (class, vendor.to_string(), anomalies)
+}
+
+/// Main entry point for the hypervisor check
+pub fn check_hypervisor(engine: &mut DecisionEngine) {
+    let hv_bit = cpuid(1).ecx & (1 << 31) != 0;
+    engine.record_flag("hv_bit", hv_bit);
+    let leaves = hypervisor_leaves();
+    engine.record_feature("hypervisor_leaf_count", leaves.len() as f64);
+
+    let (class, vendor, anomalies) = classify(hv_bit, &leaves);
+    diag!("[HYPERVISOR] hv_bit={} leaves={} vendor={} class={:?} anomalies={:?}", hv_bit, leaves.len(), vendor, class, anomalies);
+    engine.record_feature("hypervisor_anomalies", anomalies.len() as f64);
+    engine.set_vm_class(class);
+
+    match class {
+        VmClass::None => {}
+        VmClass::Production => engine.report_with_confidence(
+            DetectionSource::Hypervisor,
+            15,
+            0.6,
+            &reason!("Production hypervisor: {} ({} leaves, consistent)", vendor, leaves.len())
+        ),
+        VmClass::Analysis => {
+            let weight = (15 + anomalies.iter().map(|(_, w)| w).sum::<u32>()).min(60);
+            let details: Vec<&str> = anomalies.iter().map(|(d, _)| d.as_str()).collect();
+            engine.report_with_confidence(
+                DetectionSource::Hypervisor,
+                weight,
+                (0.4 + 0.15 * anomalies.len() as f64).min(0.9),
+                &reason!("Analysis VM ({}): {}", vendor, details.join("; "))
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kvm_leaves(features: u32) -> Vec<(u32, CpuidResult)> {
+        vec![
+            (HV_BASE, CpuidResult { eax: HV_BASE + 1, ebx: 0x4b4d_564b, ecx: 0x564b_4d56, edx: 0x0000_004d }),
+            (HV_BASE + 1, CpuidResult { eax: features, ebx: 0, ecx: 0, edx: 0 }),
+        ]
+    }
+
+    #[test]
+    fn test_identify() {
+        let leaves = kvm_leaves(0);
+        assert_eq!(signature(leaves[0].1), "KVMKVMKVM");
+        assert_eq!(identify("KVMKVMKVM"), Some(("KVM", VmClass::Production)));
+        assert_eq!(identify("VBoxVBoxVBox"), Some(("VirtualBox", VmClass::Analysis)));
+        assert_eq!(identify("SomethingNew"), None);
+    }
+
+    #[test]
+    fn test_feature_leaves() {
+        let leaves = kvm_leaves(0x0100_7efb);
+        let msrs: Vec<&str> = advertised_msrs("KVM", &leaves).iter().map(|(_, n)| *n).collect();
+        assert_eq!(msrs, vec!["MSR_KVM_SYSTEM_TIME_NEW", "MSR_KVM_ASYNC_PF_EN", "MSR_KVM_STEAL_TIME", "MSR_KVM_PV_EOI_EN"]);
+        assert_eq!(expected_clocksource("KVM", &leaves), Some("kvm-clock"));
+        assert_eq!(expected_clocksource("KVM", &kvm_leaves(0)), None);
+        assert!(leaf_inconsistencies("KVM", &leaves).is_empty());
+        assert_eq!(leaf_inconsistencies("Hyper-V", &leaves).len(), 1);
+    }
+
+    #[test]
+    fn test_vm_class_scales_evidence() {
+        let mut engine = DecisionEngine::new();
+        engine.set_vm_class(VmClass::Production);
+        engine.report(DetectionSource::Hypervisor, 40, "KVM");
+        assert_eq!(engine.get_score(), 10);
+    }
+}
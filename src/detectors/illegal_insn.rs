@@ -0,0 +1,208 @@
+//! Illegal-Instruction Semantics Probe
+//!
+//! # Overview
+//!
+//! Real hardware faults on invalid opcodes with very precise semantics:
+//! the exception is a *fault* (saved RIP points at the offending
+//! instruction, not past it), the kernel maps #UD to SIGILL/ILL_ILLOPN with
+//! `si_addr` = RIP, and #GP from an over-long instruction to
+//! SIGSEGV/SI_KERNEL with a zero `si_addr`.
+//!
+//! Lightweight emulators and QEMU TCG diverge in the corner cases:
+//! - LOCK-prefixed NOP executed as a plain NOP instead of #UD
+//! - Instructions longer than 15 bytes decoded and executed
+//! - Fault reported at the next instruction (trap semantics)
+//! - Wrong signal or `si_code` for the exception class
+//!
+//! Each probe in `asm/illegal_insn.s` executes one such instruction; our
+//! handler records what the kernel reported and resumes past it.
+//!
+//! # Why This Fails
+//!
+//! - Hardware virtualization executes these natively - only emulators are caught
+//! - Modern QEMU fixes individual corner cases release by release
+//! - A debugger intercepts SIGILL/SIGSEGV, so the probe is skipped when traced
+
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::signal_compat;
+
+extern "C" {
+    fn probe_ud2();
+    fn probe_ud1();
+    fn probe_lock_nop();
+    fn probe_overlong();
+}
+
+/// `si_code` for an illegal operand/opcode (not exported by the libc crate)
+const ILL_ILLOPN: i32 = 2;
+
+/// What a probe should produce on real hardware
+#[derive(Debug, Clone, Copy)]
+pub struct ProbeSpec {
+    pub name: &'static str,
+    /// Instruction length in bytes (resume point = probe + length)
+    pub length: u64,
+    pub signal: i32,
+    pub si_code: i32,
+    /// Whether `si_addr` must equal the faulting instruction (else must be 0)
+    pub si_addr_is_rip: bool,
+}
+
+/// What the kernel actually reported (signal 0 = no fault at all)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProbeOutcome {
+    pub signal: i32,
+    pub si_code: i32,
+    pub si_addr: u64,
+    pub rip: u64,
+}
+
+static FAULT_SIGNAL: AtomicI32 = AtomicI32::new(0);
+static FAULT_CODE: AtomicI32 = AtomicI32::new(0);
+static FAULT_ADDR: AtomicU64 = AtomicU64::new(0);
+static FAULT_RIP: AtomicU64 = AtomicU64::new(0);
+static RESUME_AT: AtomicU64 = AtomicU64::new(0);
+
+extern "C" fn fault_handler(signum: libc::c_int, info: *mut libc::siginfo_t, ctx: *mut libc::c_void) {
+    // SAFETY: the kernel passes valid siginfo and ucontext pointers for SA_SIGINFO handlers
+    unsafe {
+        let ucontext = ctx as *mut libc::ucontext_t;
+        FAULT_SIGNAL.store(signum, Ordering::SeqCst);
+        FAULT_CODE.store((*info).si_code, Ordering::SeqCst);
+        FAULT_ADDR.store((*info).si_addr() as u64, Ordering::SeqCst);
+        FAULT_RIP.store((*ucontext).uc_mcontext.gregs[libc::REG_RIP as usize] as u64, Ordering::SeqCst);
+
+        // Resume at the known end of the probe instruction, regardless of
+        // where the environment claims the fault happened
+        (*ucontext).uc_mcontext.gregs[libc::REG_RIP as usize] = RESUME_AT.load(Ordering::SeqCst) as i64;
+    }
+}
+
+fn probes() -> [(ProbeSpec, unsafe extern "C" fn()); 4] {
+    [
+        (ProbeSpec { name: "ud2", length: 2, signal: libc::SIGILL, si_code: ILL_ILLOPN, si_addr_is_rip: true }, probe_ud2),
+        (ProbeSpec { name: "ud1", length: 3, signal: libc::SIGILL, si_code: ILL_ILLOPN, si_addr_is_rip: true }, probe_ud1),
+        (ProbeSpec { name: described!("lock nop"), length: 2, signal: libc::SIGILL, si_code: ILL_ILLOPN, si_addr_is_rip: true }, probe_lock_nop),
+        (ProbeSpec { name: "overlong", length: 16, signal: libc::SIGSEGV, si_code: libc::SI_KERNEL, si_addr_is_rip: false }, probe_overlong),
+    ]
+}
+
+/// Compare an outcome against hardware semantics for a probe at `site`
+pub fn deviations(spec: &ProbeSpec, outcome: &ProbeOutcome, site: u64) -> Vec<String> {
+    let mut out = Vec::new();
+
+    if outcome.signal == 0 {
+        out.push(reason!("{}: executed without faulting", spec.name));
+        return out;
+    }
+    if outcome.signal != spec.signal {
+        out.push(reason!("{}: signal {} (expected {})", spec.name, outcome.signal, spec.signal));
+    }
+    if outcome.si_code != spec.si_code {
+        out.push(reason!("{}: si_code {} (expected {})", spec.name, outcome.si_code, spec.si_code));
+    }
+    if outcome.rip != site {
+        out.push(reason!("{}: saved RIP {:#x} is {:+} from the faulting instruction",
+                         spec.name, outcome.rip, outcome.rip as i64 - site as i64));
+    }
+    let expected_addr = if spec.si_addr_is_rip { site } else { 0 };
+    if outcome.si_addr != expected_addr {
+        out.push(reason!("{}: si_addr {:#x} (expected {:#x})", spec.name, outcome.si_addr, expected_addr));
+    }
+
+    out
+}
+
+fn run_probe(probe: unsafe extern "C" fn(), length: u64) -> ProbeOutcome {
+    FAULT_SIGNAL.store(0, Ordering::SeqCst);
+    FAULT_CODE.store(0, Ordering::SeqCst);
+    FAULT_ADDR.store(0, Ordering::SeqCst);
+    FAULT_RIP.store(0, Ordering::SeqCst);
+    RESUME_AT.store(probe as usize as u64 + length, Ordering::SeqCst);
+
+    // SAFETY: the probe either faults into `fault_handler`, which resumes at
+    // its trailing `ret`, or executes the instruction and returns normally
+    unsafe { probe() };
+
+    ProbeOutcome {
+        signal: FAULT_SIGNAL.load(Ordering::SeqCst),
+        si_code: FAULT_CODE.load(Ordering::SeqCst),
+        si_addr: FAULT_ADDR.load(Ordering::SeqCst),
+        rip: FAULT_RIP.load(Ordering::SeqCst),
+    }
+}
+
+/// Main entry point for the illegal-instruction semantics probe
+pub fn check_illegal_instruction_semantics(engine: &mut DecisionEngine) {
+    let tracer_pid = signal_compat::get_tracer_pid();
+    if tracer_pid > 0 {
+        diag!("[ILLEGAL] Tracer detected (PID {}), skipping SIGILL probes to avoid conflict", tracer_pid);
+        return;
+    }
+
+    let mut old_ill: libc::sigaction = unsafe { std::mem::zeroed() };
+    let mut old_segv: libc::sigaction = unsafe { std::mem::zeroed() };
+    unsafe {
+        let mut sa: libc::sigaction = std::mem::zeroed();
+        sa.sa_sigaction = fault_handler as *const () as usize;
+        libc::sigemptyset(&mut sa.sa_mask);
+        sa.sa_flags = libc::SA_SIGINFO;
+
+        if libc::sigaction(libc::SIGILL, &sa, &mut old_ill) != 0
+            || libc::sigaction(libc::SIGSEGV, &sa, &mut old_segv) != 0
+        {
+            diag!("[ILLEGAL] Failed to install fault handlers");
+            libc::sigaction(libc::SIGILL, &old_ill, std::ptr::null_mut());
+            return;
+        }
+    }
+
+    let mut all_deviations = Vec::new();
+    for (spec, probe) in probes() {
+        let outcome = run_probe(probe, spec.length);
+        let site = probe as usize as u64;
+        diag!("[ILLEGAL] {}: signal={} si_code={} si_addr={:#x} rip_offset={:+}",
+              spec.name, outcome.signal, outcome.si_code, outcome.si_addr,
+              outcome.rip as i64 - site as i64);
+        all_deviations.extend(deviations(&spec, &outcome, site));
+    }
+
+    unsafe {
+        libc::sigaction(libc::SIGILL, &old_ill, std::ptr::null_mut());
+        libc::sigaction(libc::SIGSEGV, &old_segv, std::ptr::null_mut());
+    }
+
+    engine.record_feature("illegal_insn_deviations", all_deviations.len() as f64);
+
+    if !all_deviations.is_empty() {
+        // A single deviation may be an unusual kernel; several is an emulator
+        let (weight, confidence) = if all_deviations.len() >= 2 { (50, 0.85) } else { (30, 0.6) };
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            weight,
+            confidence,
+            &reason!("Illegal-instruction semantics differ from hardware: {}", all_deviations.join("; "))
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UD2: ProbeSpec = ProbeSpec { name: "ud2", length: 2, signal: libc::SIGILL, si_code: ILL_ILLOPN, si_addr_is_rip: true };
+
+    #[test]
+    fn test_hardware_outcome_has_no_deviations() {
+        let outcome = ProbeOutcome { signal: libc::SIGILL, si_code: ILL_ILLOPN, si_addr: 0x1000, rip: 0x1000 };
+        assert!(deviations(&UD2, &outcome, 0x1000).is_empty());
+    }
+
+    #[test]
+    fn test_trap_semantics_and_no_fault_flagged() {
+        let trap = ProbeOutcome { signal: libc::SIGILL, si_code: ILL_ILLOPN, si_addr: 0x1002, rip: 0x1002 };
+        assert_eq!(deviations(&UD2, &trap, 0x1000).len(), 2);
+        assert_eq!(deviations(&UD2, &ProbeOutcome::default(), 0x1000).len(), 1);
+    }
+}
@@ -0,0 +1,206 @@
+//! Inline-Hook Detection (Prologue Trampolines in libc)
+//!
+//! # Overview
+//!
+//! `libc_hooks.rs` catches hooks that rebind a symbol to another object.
+//! Frida's `Interceptor.attach` and hand-written "PLT bypass" hooks do not
+//! rebind anything: they overwrite the first bytes of the function inside
+//! libc with a jump to their own code, so every caller - including one
+//! that resolved the symbol itself - lands in the hook. We read the first
+//! 16 bytes of security-relevant libc functions and look for:
+//!
+//! - **Trampolines**: `jmp rel32`, `jmp [rip+disp32]`, `mov reg, imm64;
+//!   jmp reg` and `push imm32; ret`, at the entry or right after `endbr64`
+//! - **Disk mismatch**: the same 16 bytes read from the libc file at the
+//!   offset the mapping says they came from. Any difference is a patch,
+//!   whatever it decodes to
+//!
+//! The functions are looked up in libc itself (`dlopen("libc.so.6",
+//! RTLD_NOLOAD)`), so a preloaded replacement does not hide libc's copy.
+//!
+//! # Why This Fails
+//!
+//! - Hooks placed deeper than 16 bytes into the function (mid-function
+//!   hooks, or hooks on the syscall stub libc calls internally)
+//! - A hook that restores the original bytes while we read them (hooking
+//!   `read`/`pread` or watching the page) would have to hide from both
+//!   reads consistently
+//! - If libc was replaced on disk after we started, the mapping points at a
+//!   deleted file and only the trampoline patterns are checked
+
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::fs::FileExt;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// libc functions anti-debugging relies on
+const FUNCTIONS: &[&str] = &["ptrace", "fopen", "read", "kill", "sigaction"];
+
+/// Bytes of each prologue we check
+const PROLOGUE_LEN: usize = 16;
+
+/// `endbr64`, which CET-enabled libcs start every function with
+const ENDBR64: [u8; 4] = [0xf3, 0x0f, 0x1e, 0xfa];
+
+/// Trampoline at the start of `code`, if any
+pub fn trampoline(code: &[u8]) -> Option<&'static str> {
+    match code {
+        [0xe9, ..] => Some(described!("jmp rel32")),
+        [0xeb, ..] => Some(described!("jmp rel8")),
+        [0xff, 0x25, ..] => Some(described!("jmp [rip+disp32]")),
+        [0x68, _, _, _, _, 0xc3, ..] => Some(described!("push imm32; ret")),
+        // REX.W(B) B8+r imm64, then jmp r (FF E0+r, REX.B 41 for r8-r15)
+        [0x48 | 0x49, 0xb8..=0xbf, _, _, _, _, _, _, _, _, rest @ ..] => match rest {
+            [0xff, 0xe0..=0xe7, ..] | [0x41, 0xff, 0xe0..=0xe7, ..] => Some(described!("mov reg, imm64; jmp reg")),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Trampoline at the entry of a prologue or right after its `endbr64`
+pub fn prologue_trampoline(prologue: &[u8]) -> Option<&'static str> {
+    trampoline(prologue).or_else(|| trampoline(prologue.strip_prefix(&ENDBR64[..])?))
+}
+
+/// (path, file offset) the byte at `addr` was mapped from
+pub fn file_offset(maps: &str, addr: u64) -> Option<(String, u64)> {
+    maps.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let (start, end) = fields.next()?.split_once('-')?;
+        let (start, end) = (u64::from_str_radix(start, 16).ok()?, u64::from_str_radix(end, 16).ok()?);
+        if !(start..end).contains(&addr) {
+            return None;
+        }
+        let offset = u64::from_str_radix(fields.nth(1)?, 16).ok()?;
+        let path = fields.nth(2)?;
+        path.starts_with('/').then(|| (path.to_string(), addr - start + offset))
+    })
+}
+
+/// Address of `name` in libc itself, bypassing the global lookup order
+fn libc_symbol(name: &str) -> Option<u64> {
+    let symbol = CString::new(name).ok()?;
+    // SAFETY: RTLD_NOLOAD only returns a handle to the already-loaded libc;
+    // dlsym only looks the symbol up
+    unsafe {
+        let handle = libc::dlopen(c"libc.so.6".as_ptr(), libc::RTLD_LAZY | libc::RTLD_NOLOAD);
+        if handle.is_null() {
+            return None;
+        }
+        let addr = libc::dlsym(handle, symbol.as_ptr());
+        libc::dlclose(handle);
+        (!addr.is_null()).then_some(addr as u64)
+    }
+}
+
+/// What was found at one function's entry
+struct Prologue {
+    name: &'static str,
+    memory: [u8; PROLOGUE_LEN],
+    disk: Option<[u8; PROLOGUE_LEN]>,
+}
+
+fn read_prologue(name: &'static str, maps: &str) -> Option<Prologue> {
+    let addr = libc_symbol(name)?;
+    let mut memory = [0u8; PROLOGUE_LEN];
+    // SAFETY: `addr` is the entry of a libc function in an executable
+    // mapping, and functions are longer than 16 bytes
+    unsafe { std::ptr::copy_nonoverlapping(addr as *const u8, memory.as_mut_ptr(), PROLOGUE_LEN) };
+    let disk = file_offset(maps, addr).and_then(|(path, offset)| {
+        let mut bytes = [0u8; PROLOGUE_LEN];
+        fs::File::open(path).ok()?.read_exact_at(&mut bytes, offset).ok()?;
+        Some(bytes)
+    });
+    Some(Prologue { name, memory, disk })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")
+}
+
+/// Main entry point for the inline-hook check
+pub fn check_inline_hooks(engine: &mut DecisionEngine) {
+    return_probe!();
+    let maps = match fs::read_to_string("/proc/self/maps") {
+        Ok(maps) => maps,
+        Err(e) => {
+            engine.record_diagnostic("inline_hooks", &reason!("/proc/self/maps unreadable: {}", e));
+            return;
+        }
+    };
+    let prologues: Vec<Prologue> = FUNCTIONS.iter().filter_map(|name| read_prologue(name, &maps)).collect();
+    if prologues.is_empty() {
+        engine.record_diagnostic("inline_hooks", &reason!("libc symbols not resolvable through dlopen(RTLD_NOLOAD)"));
+        return;
+    }
+
+    let mut findings: Vec<(String, u32, f64)> = Vec::new();
+    for p in &prologues {
+        let pattern = prologue_trampoline(&p.memory);
+        let patched = p.disk.is_some_and(|disk| disk != p.memory);
+        diag!("[INLINE_HOOKS] {}: {} (disk {}) trampoline={:?}", p.name, hex(&p.memory),
+              p.disk.map_or("unreadable".to_string(), |d| hex(&d)), pattern);
+        let finding = match (pattern, patched, p.disk.is_some()) {
+            (Some(pattern), true, _) => Some((reason!("{} starts with {} ({}), not the on-disk bytes", p.name, pattern, hex(&p.memory)), 60, 0.95)),
+            (None, true, _) => Some((reason!("{} entry differs from the libc file ({})", p.name, hex(&p.memory)), 40, 0.8)),
+            // libc's own thunks match the file; only unverifiable patterns count
+            (Some(pattern), false, false) => Some((reason!("{} starts with {} (libc file unreadable)", p.name, pattern), 35, 0.6)),
+            _ => None,
+        };
+        findings.extend(finding);
+    }
+
+    engine.record_feature("inline_hooked_functions", findings.len() as f64);
+    if findings.is_empty() {
+        return;
+    }
+    let weight = findings.iter().map(|(_, w, _)| w).sum::<u32>().min(80);
+    let confidence = findings.iter().map(|(_, _, c)| *c).fold(0.0, f64::max);
+    let details: Vec<&str> = findings.iter().map(|(d, _, _)| d.as_str()).collect();
+    engine.report_with_confidence(
+        DetectionSource::Integrity,
+        weight,
+        confidence,
+        &reason!("libc functions are inline-hooked: {}", details.join("; "))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(feature = "stealth", ignore = "evidence text is coded in stealth builds")]
+    fn test_trampoline_patterns() {
+        assert_eq!(prologue_trampoline(&[0xe9, 0x10, 0x20, 0x30, 0x40, 0x90]), Some("jmp rel32"));
+        assert_eq!(prologue_trampoline(&[0xf3, 0x0f, 0x1e, 0xfa, 0xff, 0x25, 0, 0, 0, 0]), Some("jmp [rip+disp32]"));
+        let movabs = [0x49, 0xbb, 1, 2, 3, 4, 5, 6, 7, 8, 0x41, 0xff, 0xe3];
+        assert_eq!(prologue_trampoline(&movabs), Some("mov reg, imm64; jmp reg"));
+        assert_eq!(prologue_trampoline(&[0x68, 1, 2, 3, 4, 0xc3]), Some("push imm32; ret"));
+        // endbr64; push rbp; mov rbp, rsp
+        assert_eq!(prologue_trampoline(&[0xf3, 0x0f, 0x1e, 0xfa, 0x55, 0x48, 0x89, 0xe5]), None);
+        // mov rax, imm64 without a jump is ordinary code
+        assert_eq!(prologue_trampoline(&[0x48, 0xb8, 1, 2, 3, 4, 5, 6, 7, 8, 0xc3]), None);
+    }
+
+    #[test]
+    fn test_file_offset() {
+        let maps = "\
+7f0000000000-7f0000028000 r--p 00000000 fe:00 7 /usr/lib/x86_64-linux-gnu/libc.so.6
+7f0000028000-7f00001bd000 r-xp 00028000 fe:00 7 /usr/lib/x86_64-linux-gnu/libc.so.6
+7f00001bd000-7f00001be000 rwxp 00000000 00:00 0
+";
+        assert_eq!(file_offset(maps, 0x7f0000028010), Some(("/usr/lib/x86_64-linux-gnu/libc.so.6".to_string(), 0x28010)));
+        assert_eq!(file_offset(maps, 0x7f00001bd010), None);
+    }
+
+    #[test]
+    fn test_native_libc_matches_disk() {
+        let maps = fs::read_to_string("/proc/self/maps").unwrap();
+        for name in FUNCTIONS {
+            let p = read_prologue(name, &maps).unwrap();
+            assert_eq!(p.disk, Some(p.memory), "{}", name);
+        }
+    }
+}
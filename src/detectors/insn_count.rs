@@ -0,0 +1,231 @@
+//! Retired-Instruction Counter Cross-Check (PMU)
+//!
+//! # Overview
+//!
+//! Timing checks measure how long code takes; the PMU can say exactly how
+//! many instructions it took. We open a `PERF_COUNT_HW_INSTRUCTIONS`
+//! counter on ourselves (user mode only) and run `calibrated_work_loop`,
+//! which retires exactly 4 instructions per iteration, at two sizes. The
+//! difference between the two counts cancels the fixed cost of enabling
+//! and disabling the counter, leaving instructions per iteration:
+//!
+//! | Observation                                       | Source       | Weight |
+//! |---------------------------------------------------|--------------|--------|
+//! | Well over 4 per iteration                         | Dbi          | 40     |
+//! | Well under 4, or the counter never advances       | Emulation    | 25     |
+//! | `perf_event_open` returns `EBUSY`                 | RecordReplay | 30     |
+//! | No PMU on bare metal (perf allowed)               | Emulation    | 15     |
+//!
+//! DBI frameworks run a translated copy of our code: the counter sees the
+//! translation's instructions, including any inserted instrumentation.
+//! rr drives its replay from the PMU's retired-branch counter and keeps
+//! the counters to itself. Emulators either have no PMU at all or expose
+//! one that counts something other than guest instructions.
+//!
+//! # Why This Fails
+//!
+//! - Most VMs expose no PMU; under a hypervisor a missing counter is only
+//!   a diagnostic
+//! - `perf_event_paranoid` of 3 (Debian, Android) forbids even
+//!   self-monitoring
+//! - A DBI that runs the loop untranslated-equivalent (no instrumentation
+//!   in it, no extra instructions) counts the same as native
+
+use crate::detectors::perf_observer::{
+    PerfEventAttr, ATTR_DISABLED, ATTR_EXCLUDE_HV, ATTR_EXCLUDE_KERNEL, PERF_EVENT_IOC_DISABLE,
+    PERF_EVENT_IOC_ENABLE, PERF_TYPE_HARDWARE, READ_FORMAT_TIMES,
+};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::ffi::calibrated_work_loop;
+
+/// `PERF_COUNT_HW_INSTRUCTIONS`
+const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+
+/// `PERF_EVENT_IOC_RESET`
+const PERF_EVENT_IOC_RESET: libc::c_ulong = 0x2403;
+
+/// Instructions `calibrated_work_loop` retires per iteration
+pub const INSNS_PER_ITERATION: f64 = 4.0;
+
+/// Loop sizes whose counts are differenced
+const SMALL: u64 = 100_000;
+const LARGE: u64 = 1_100_000;
+
+/// Runs per size (median taken)
+const REPEATS: usize = 5;
+
+/// Relative error beyond which the count is wrong
+const TOLERANCE: f64 = 0.1;
+
+/// What the instruction counter said about the loop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InsnFinding {
+    /// More instructions than the loop has: we run translated
+    Overcount(f64),
+    /// Fewer instructions than the loop has: the counter is not real
+    Undercount(f64),
+}
+
+/// Instructions per iteration from the median counts at two loop sizes
+pub fn per_iteration(small: (u64, u64), large: (u64, u64)) -> Option<f64> {
+    let (small_n, small_count) = small;
+    let (large_n, large_count) = large;
+    (large_n > small_n).then(|| (large_count as f64 - small_count as f64) / (large_n - small_n) as f64)
+}
+
+/// Judge a per-iteration count against the loop's 4 instructions
+pub fn assess(per_iteration: f64) -> Option<InsnFinding> {
+    if per_iteration > INSNS_PER_ITERATION * (1.0 + TOLERANCE) {
+        Some(InsnFinding::Overcount(per_iteration))
+    } else if per_iteration < INSNS_PER_ITERATION * (1.0 - TOLERANCE) {
+        Some(InsnFinding::Undercount(per_iteration))
+    } else {
+        None
+    }
+}
+
+/// Our own instruction counter
+struct InsnCounter(libc::c_int);
+
+impl InsnCounter {
+    /// Open the counter, or return `errno`
+    fn open() -> Result<Self, i32> {
+        let attr = PerfEventAttr {
+            type_: PERF_TYPE_HARDWARE,
+            size: std::mem::size_of::<PerfEventAttr>() as u32,
+            config: PERF_COUNT_HW_INSTRUCTIONS,
+            read_format: READ_FORMAT_TIMES,
+            flags: ATTR_DISABLED | ATTR_EXCLUDE_KERNEL | ATTR_EXCLUDE_HV,
+            ..Default::default()
+        };
+        // SAFETY: `attr` is a valid VER0 attribute; pid 0 / cpu -1 = this thread, any CPU
+        let fd = unsafe { libc::syscall(libc::SYS_perf_event_open, &attr as *const PerfEventAttr, 0, -1, -1, 0) } as libc::c_int;
+        if fd < 0 {
+            Err(std::io::Error::last_os_error().raw_os_error().unwrap_or(0))
+        } else {
+            Ok(Self(fd))
+        }
+    }
+
+    /// Instructions retired by `iterations` of the work loop, or `None` if
+    /// the counter was multiplexed off the PMU for part of the run
+    fn count_loop(&self, iterations: u64) -> Option<u64> {
+        let mut values = [0u64; 3];
+        // SAFETY: `self.0` is our perf event; the read buffer holds value/enabled/running
+        let n = unsafe {
+            libc::ioctl(self.0, PERF_EVENT_IOC_RESET, 0);
+            libc::ioctl(self.0, PERF_EVENT_IOC_ENABLE, 0);
+            std::hint::black_box(calibrated_work_loop(iterations));
+            libc::ioctl(self.0, PERF_EVENT_IOC_DISABLE, 0);
+            libc::read(self.0, values.as_mut_ptr() as *mut libc::c_void, std::mem::size_of_val(&values))
+        };
+        (n > 0 && values[1] > 0 && values[1] == values[2]).then_some(values[0])
+    }
+
+    /// Median count over [`REPEATS`] runs
+    fn median(&self, iterations: u64) -> Option<u64> {
+        let mut counts: Vec<u64> = (0..REPEATS).filter_map(|_| self.count_loop(iterations)).collect();
+        counts.sort_unstable();
+        counts.get(counts.len() / 2).copied()
+    }
+}
+
+impl Drop for InsnCounter {
+    fn drop(&mut self) {
+        // SAFETY: we own the fd
+        unsafe { libc::close(self.0) };
+    }
+}
+
+#[allow(unused_unsafe)] // `__cpuid` is only safe on newer toolchains
+fn hypervisor_bit() -> bool {
+    unsafe { core::arch::x86_64::__cpuid(1) }.ecx & (1 << 31) != 0
+}
+
+/// Main entry point for the retired-instruction check
+pub fn check_insn_count(engine: &mut DecisionEngine) {
+    let counter = match InsnCounter::open() {
+        Ok(counter) => counter,
+        Err(libc::EBUSY) => {
+            engine.record_flag("insn_counter", false);
+            engine.report_with_confidence(
+                DetectionSource::RecordReplay,
+                30,
+                0.7,
+                &reason!("Instruction counter on ourselves refused with EBUSY: the PMU is held exclusively (rr?)")
+            );
+            return;
+        }
+        Err(errno) => {
+            engine.record_flag("insn_counter", false);
+            let no_pmu = errno == libc::ENOENT || errno == libc::EOPNOTSUPP;
+            if !no_pmu || !engine.kernel_posture().unprivileged_perf() {
+                engine.record_diagnostic("insn_count", &reason!("perf_event_open failed (errno {})", errno));
+            } else if hypervisor_bit() {
+                engine.record_diagnostic("insn_count", &reason!("no instruction counter (no PMU in this VM)"));
+            } else {
+                engine.report_with_confidence(
+                    DetectionSource::Emulation,
+                    15,
+                    0.5,
+                    &reason!("No hardware instruction counter (errno {}) on a CPU that claims no hypervisor", errno)
+                );
+            }
+            return;
+        }
+    };
+    engine.record_flag("insn_counter", true);
+
+    let (Some(small), Some(large)) = (counter.median(SMALL), counter.median(LARGE)) else {
+        engine.record_diagnostic("insn_count", &reason!("instruction counter was never scheduled for a whole run"));
+        return;
+    };
+    diag!("[INSN_COUNT] {} iterations: {} instructions, {} iterations: {}", SMALL, small, LARGE, large);
+    if large == 0 {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            25,
+            0.6,
+            &reason!("Instruction counter opened but counted nothing over {} loop iterations", LARGE)
+        );
+        return;
+    }
+    let Some(per_iteration) = per_iteration((SMALL, small), (LARGE, large)) else { return };
+    engine.record_feature("insn_per_iteration", per_iteration);
+
+    match assess(per_iteration) {
+        Some(InsnFinding::Overcount(n)) => engine.report_with_confidence(
+            DetectionSource::Dbi,
+            40,
+            0.8,
+            &reason!("Work loop retires {:.2} instructions per iteration (it has {}): code runs translated or instrumented", n, INSNS_PER_ITERATION)
+        ),
+        Some(InsnFinding::Undercount(n)) => engine.report_with_confidence(
+            DetectionSource::Emulation,
+            25,
+            0.6,
+            &reason!("Work loop counted as {:.2} instructions per iteration (it has {}): the PMU is emulated", n, INSNS_PER_ITERATION)
+        ),
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_iteration() {
+        // 40 instructions of fixed overhead at both sizes
+        assert_eq!(per_iteration((SMALL, 400_040), (LARGE, 4_400_040)), Some(4.0));
+        assert_eq!(per_iteration((LARGE, 1), (SMALL, 2)), None);
+    }
+
+    #[test]
+    fn test_assess() {
+        assert_eq!(assess(4.0), None);
+        assert_eq!(assess(4.2), None);
+        assert_eq!(assess(11.0), Some(InsnFinding::Overcount(11.0)));
+        assert_eq!(assess(0.5), Some(InsnFinding::Undercount(0.5)));
+    }
+}
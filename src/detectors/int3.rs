@@ -77,6 +77,11 @@ fn analyze_int3_pattern(ptr: *const u8, len: usize) -> (usize, usize, bool) {
 /// - **Alignment**: Large clusters (16+ consecutive bytes) → weight 0-1
 /// - **Ambiguous**: Many scattered bytes (20-1000) → weight 2-5
 /// - **Breakpoints**: Few scattered bytes (<20) → weight 20-30
+///
+/// `text_diff.rs` compares the same code against the file on disk, which
+/// tells patched bytes from padding without guessing; `lib_int3.rs` does
+/// the same for shared libraries.
+#[allow(clippy::manual_flatten)] // Unreadable lines are skipped
 pub fn check_int3_scanning(engine: &mut DecisionEngine) {
     let self_exe = match std::env::current_exe() {
         Ok(p) => p,
@@ -91,63 +96,79 @@ pub fn check_int3_scanning(engine: &mut DecisionEngine) {
     
     let reader = BufReader::new(file);
     
+    // Totals across all of our executable mappings, for the feature vector
+    let mut total_count = 0usize;
+    let mut max_cluster = 0usize;
+    let mut scattered_count = 0usize;
+    
     for line in reader.lines() {
         if let Ok(l) = line {
             // We only care about executable regions (r-xp) of our own binary.
-            // Libraries have their own alignment padding which we want to ignore to reduce noise.
+            // Libraries have their own alignment padding which we want to ignore to reduce noise
+            // (lib_int3.rs diffs them against their files instead).
             if l.contains(" r-xp ") && l.contains(&*self_exe_str) {
-                
+            
                 let parts: Vec<&str> = l.split_whitespace().collect();
                 if parts.is_empty() { continue; }
-                
+            
                 let range_parts: Vec<&str> = parts[0].split('-').collect();
                 if range_parts.len() != 2 { continue; }
-                
+            
                 let start = usize::from_str_radix(range_parts[0], 16).unwrap_or(0);
                 let end = usize::from_str_radix(range_parts[1], 16).unwrap_or(0);
-                
+            
                 if start == 0 || end <= start { continue; }
-                
+            
                 let len = end - start;
                 let ptr = start as *const u8;
-                
+            
                 // SAFETY: We are reading our own process memory which is mapped and valid.
                 let count = unsafe { scan_for_int3(ptr, len) };
-                
+            
                 if count == 0 {
                     continue;
                 }
-                
+            
                 // Analyze INT3 pattern for better classification
                 let (total, largest_cluster, is_alignment) = analyze_int3_pattern(ptr, len);
-                
-                eprintln!("[INT3] Found {} bytes, largest cluster: {}, likely alignment: {}", 
+            
+                diag!("[INT3] Found {} bytes, largest cluster: {}, likely alignment: {}", 
                          total, largest_cluster, is_alignment);
-                
+            
+                total_count += total;
+                max_cluster = max_cluster.max(largest_cluster);
+                if !is_alignment {
+                    scattered_count += total;
+                }
+            
                 // Determine weight based on analysis
                 let (weight, confidence, reason) = if total > INT3_ALIGNMENT_THRESHOLD && is_alignment {
                     // Very high count + clustered = almost certainly alignment padding
                     // Report with near-zero weight (informational only)
-                    (1, 0.1, "Compiler alignment padding (dense clusters, high count)")
+                    (1, 0.1, described!("Compiler alignment padding (dense clusters, high count)"))
                 } else if is_alignment && total > 100 {
                     // Alignment patterns detected, moderate count
-                    (2, 0.3, "Likely compiler alignment (clustered pattern)")
+                    (2, 0.3, described!("Likely compiler alignment (clustered pattern)"))
                 } else if total > INT3_BREAKPOINT_THRESHOLD {
                     // Moderate count, not clearly alignment
                     // Could be many breakpoints or mixed content
-                    (5, 0.5, "Ambiguous INT3 pattern (possible breakpoints or alignment)")
+                    (5, 0.5, described!("Ambiguous INT3 pattern (possible breakpoints or alignment)"))
                 } else {
                     // Low count, scattered = likely breakpoints
-                    (25, 0.8, "Likely debugger breakpoints (few, scattered)")
+                    (25, 0.8, described!("Likely debugger breakpoints (few, scattered)"))
                 };
-                
+            
                 engine.report_with_confidence(
                     DetectionSource::Int3, 
                     weight, 
                     confidence,
-                    &format!("{} - {} INT3 bytes in {:x}-{:x}", reason, count, start, end)
+                    &reason!("{} - {} INT3 bytes in {:x}-{:x}", reason, count, start, end)
                 );
             }
         }
     }
+    
+    engine.record_feature("int3_total_count", total_count as f64);
+    engine.record_feature("int3_largest_cluster", max_cluster as f64);
+    engine.record_feature("int3_scattered_count", scattered_count as f64);
 }
@@ -0,0 +1,169 @@
+//! Kernel-Module Scan (Guest Additions, Tracing and Hooking Modules)
+//!
+//! # Overview
+//!
+//! Analysis setups load kernel modules a production host rarely has. We
+//! read the loaded modules from `/proc/modules` (or, where that is hidden,
+//! the names under `/sys/module`, which also lists built-in ones) and
+//! classify them by vendor:
+//!
+//! | Modules                                  | Vendor / tool                 | Source               | Weight |
+//! |------------------------------------------|-------------------------------|----------------------|--------|
+//! | `vboxguest`, `vboxsf`, `vboxvideo`       | VirtualBox guest additions    | Hypervisor           | 25     |
+//! | `prl_tg`, `prl_fs`                       | Parallels tools               | Hypervisor           | 25     |
+//! | `vmw_vmci`, `vmw_balloon`, `vmwgfx`      | VMware tools                  | Hypervisor           | 15     |
+//! | `stap_*`                                 | SystemTap probe module        | KernelProbe          | 35     |
+//! | `sysdig_probe`, `scap`, `falco`          | sysdig / Falco capture        | KernelProbe          | 20     |
+//! | `lttng_*`                                | LTTng kernel tracer           | KernelProbe          | 20     |
+//! | `kgdboc`, `kgdbts`                       | Kernel debugger (kgdb)        | KernelProbe          | 25     |
+//! | `lime`                                   | LiME memory acquisition       | Sandbox              | 30     |
+//! | `diamorphine`, `reptile`, `suterusu`,    | Syscall-hooking rootkit       | SyscallInterposition | 40     |
+//! | `adore`, `kovid`, `khook`                |                               |                      |        |
+//!
+//! Desktop hypervisors' guest additions weigh more than VMware's, which
+//! production fleets run too. Out-of-tree modules (taint `O`) are counted
+//! as a feature only.
+//!
+//! # Why This Fails
+//!
+//! - `/proc/modules` is often unreadable in containers; `/sys/module` only
+//!   lists modules that have parameters or sysfs entries
+//! - Rootkits hide themselves from both lists first thing after loading
+//! - Guest additions can be uninstalled or built into a custom kernel
+//!   under other names
+
+use std::collections::BTreeMap;
+use std::fs;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Module names (a trailing `*` matches a prefix), the vendor or tool they
+/// belong to, and how much they weigh
+const MODULES: &[(&str, &str, DetectionSource, u32, f64)] = &[
+    ("vboxguest", described!("VirtualBox guest additions"), DetectionSource::Hypervisor, 25, 0.8),
+    ("vboxsf", described!("VirtualBox guest additions"), DetectionSource::Hypervisor, 25, 0.8),
+    ("vboxvideo", described!("VirtualBox guest additions"), DetectionSource::Hypervisor, 25, 0.8),
+    ("prl_tg", described!("Parallels tools"), DetectionSource::Hypervisor, 25, 0.8),
+    ("prl_fs", described!("Parallels tools"), DetectionSource::Hypervisor, 25, 0.8),
+    ("vmw_vmci", described!("VMware tools"), DetectionSource::Hypervisor, 15, 0.6),
+    ("vmw_balloon", described!("VMware tools"), DetectionSource::Hypervisor, 15, 0.6),
+    ("vmwgfx", described!("VMware tools"), DetectionSource::Hypervisor, 15, 0.6),
+    ("stap_*", described!("SystemTap probe module"), DetectionSource::KernelProbe, 35, 0.8),
+    ("sysdig_probe", described!("sysdig / Falco syscall capture"), DetectionSource::KernelProbe, 20, 0.6),
+    ("scap", described!("sysdig / Falco syscall capture"), DetectionSource::KernelProbe, 20, 0.6),
+    ("falco", described!("sysdig / Falco syscall capture"), DetectionSource::KernelProbe, 20, 0.6),
+    ("lttng_*", described!("LTTng kernel tracer"), DetectionSource::KernelProbe, 20, 0.6),
+    ("kgdboc", described!("kernel debugger (kgdb)"), DetectionSource::KernelProbe, 25, 0.6),
+    ("kgdbts", described!("kernel debugger (kgdb)"), DetectionSource::KernelProbe, 25, 0.6),
+    ("lime", described!("LiME memory acquisition"), DetectionSource::Sandbox, 30, 0.7),
+    ("diamorphine", described!("syscall-hooking rootkit"), DetectionSource::SyscallInterposition, 40, 0.8),
+    ("reptile*", described!("syscall-hooking rootkit"), DetectionSource::SyscallInterposition, 40, 0.8),
+    ("suterusu", described!("syscall-hooking rootkit"), DetectionSource::SyscallInterposition, 40, 0.8),
+    ("adore*", described!("syscall-hooking rootkit"), DetectionSource::SyscallInterposition, 40, 0.8),
+    ("kovid", described!("syscall-hooking rootkit"), DetectionSource::SyscallInterposition, 40, 0.8),
+    ("khook*", described!("syscall-hooking rootkit"), DetectionSource::SyscallInterposition, 40, 0.8),
+];
+
+/// One loaded module
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub name: String,
+    /// Taint flags, e.g. `OE` for an unsigned out-of-tree module
+    pub taint: String,
+}
+
+/// Parse `/proc/modules`: `name size refcount deps state address [(taint)]`
+pub fn parse_modules(text: &str) -> Vec<Module> {
+    text.lines()
+        .filter_map(|line| {
+            let name = line.split_whitespace().next()?.to_string();
+            let taint = line.rsplit_once('(')
+                .and_then(|(_, rest)| rest.strip_suffix(')'))
+                .unwrap_or("")
+                .to_string();
+            Some(Module { name, taint })
+        })
+        .collect()
+}
+
+/// Vendor or tool, source, weight and confidence of a module name
+pub fn classify_module(name: &str) -> Option<(&'static str, DetectionSource, u32, f64)> {
+    let name = name.replace('-', "_");
+    MODULES.iter()
+        .find(|(pattern, ..)| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == *pattern,
+        })
+        .map(|&(_, vendor, source, weight, confidence)| (vendor, source, weight, confidence))
+}
+
+/// Loaded modules and where they were read from
+fn loaded_modules() -> Option<(Vec<Module>, &'static str)> {
+    if let Ok(text) = fs::read_to_string("/proc/modules") {
+        return Some((parse_modules(&text), "/proc/modules"));
+    }
+    let entries = fs::read_dir("/sys/module").ok()?;
+    let modules = entries.flatten()
+        .map(|e| Module { name: e.file_name().to_string_lossy().into_owned(), taint: String::new() })
+        .collect();
+    Some((modules, "/sys/module"))
+}
+
+/// Main entry point for the kernel-module scan
+pub fn check_kmod_scan(engine: &mut DecisionEngine) {
+    let Some((modules, origin)) = loaded_modules() else {
+        engine.record_diagnostic("kmod_scan", &reason!("neither /proc/modules nor /sys/module is readable"));
+        return;
+    };
+    let out_of_tree = modules.iter().filter(|m| m.taint.contains('O')).count();
+
+    // Group matches by vendor so each tool reports once
+    let mut vendors: BTreeMap<&'static str, (DetectionSource, u32, f64, Vec<&str>)> = BTreeMap::new();
+    for module in &modules {
+        if let Some((vendor, source, weight, confidence)) = classify_module(&module.name) {
+            vendors.entry(vendor).or_insert((source, weight, confidence, Vec::new())).3.push(&module.name);
+        }
+    }
+    diag!("[KMOD_SCAN] {} modules from {}, {} out-of-tree, matches {:?}", modules.len(), origin, out_of_tree,
+          vendors.iter().map(|(v, (.., names))| (*v, names.len())).collect::<Vec<_>>());
+    engine.record_feature("kmod_analysis_modules", vendors.values().map(|(.., names)| names.len()).sum::<usize>() as f64);
+    engine.record_feature("kmod_out_of_tree", out_of_tree as f64);
+
+    for (vendor, (source, weight, confidence, names)) in vendors {
+        engine.report_with_confidence(
+            source,
+            weight,
+            confidence,
+            &reason!("Kernel modules of {} loaded: {} (from {})", vendor, names.join(", "), origin)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_modules() {
+        let text = "\
+vboxsf 94208 1 - Live 0xffffffffc0a3e000 (OE)
+vboxguest 425984 3 vboxsf, Live 0xffffffffc09b0000 (OE)
+ext4 1024000 2 - Live 0xffffffffc0800000
+";
+        let modules = parse_modules(text);
+        assert_eq!(modules.len(), 3);
+        assert_eq!(modules[0], Module { name: "vboxsf".to_string(), taint: "OE".to_string() });
+        assert_eq!(modules[2].taint, "");
+    }
+
+    #[test]
+    #[cfg_attr(feature = "stealth", ignore = "evidence text is coded in stealth builds")]
+    fn test_classify_module() {
+        assert_eq!(classify_module("vboxguest").map(|c| c.0), Some("VirtualBox guest additions"));
+        assert_eq!(classify_module("stap_1a2b3c_4567").map(|c| c.1), Some(DetectionSource::KernelProbe));
+        assert_eq!(classify_module("reptile-module").map(|c| c.1), Some(DetectionSource::SyscallInterposition));
+        assert_eq!(classify_module("vmw_vsock_virtio_transport"), None);
+        assert_eq!(classify_module("ext4"), None);
+        // Exact names do not match as prefixes
+        assert_eq!(classify_module("limes"), None);
+    }
+}
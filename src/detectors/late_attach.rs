@@ -0,0 +1,171 @@
+//! Mid-Run Tracer Attach (PTRACE_ATTACH / PTRACE_SEIZE)
+//!
+//! # Overview
+//!
+//! Every other ptrace check looks once, during the scan. An analyst who
+//! lets the process start clean and runs `gdb -p` (or `strace -p`) a
+//! moment later is invisible to them. `TracerPid` is cheap to re-read, so
+//! we compare it against the value `signal_compat::init()` saw at startup:
+//!
+//! | Transition                                | Meaning                         | Weight |
+//! |-------------------------------------------|---------------------------------|--------|
+//! | No tracer at startup, one now             | Debugger attached mid-run       | 60     |
+//! | Tracer replaced by another                | Second tool took over           | 60     |
+//! | Tracer gone                               | Debugger detached to hide       | 20     |
+//!
+//! [`check_late_attach`] can be called any number of times (it runs as a
+//! scan phase and again just before the payload, where the engine takes its
+//! findings as late evidence). [`TracerMonitor`] re-reads `TracerPid` on a
+//! background thread between those calls, so an attach-inspect-detach that
+//! finishes before the next call is still caught. Our own `PTRACE_TRACEME`
+//! probe makes the parent our tracer; that is not an attach (the parent is
+//! registered before the call, so a poll in between does not misread it).
+//!
+//! # Why This Fails
+//!
+//! - A tracer that attaches and detaches between two polls is missed
+//! - Hooking the `/proc` read (or a kernel module hiding `TracerPid`)
+//!   hides every transition
+//! - Debuggers that do not use ptrace (hardware probes, hypervisor
+//!   introspection) never show up in `TracerPid`
+
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::signal_compat;
+
+/// Monitor poll period
+pub const INTERVAL: Duration = Duration::from_millis(50);
+
+/// Change in `TracerPid` we have not accounted for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TracerChange {
+    /// A tracer that was not there at startup (or replaced the one that was)
+    Attached(u32),
+    /// A tracer went away
+    Detached(u32),
+}
+
+/// Classify a `TracerPid` transition; `startup` and `self_traced` (our own
+/// `PTRACE_TRACEME` parent) are known tracers
+pub fn classify_change(previous: u32, current: u32, startup: u32, self_traced: u32) -> Option<TracerChange> {
+    if current == previous {
+        None
+    } else if current != 0 && current != startup && current != self_traced {
+        Some(TracerChange::Attached(current))
+    } else if previous != 0 && previous != self_traced {
+        Some(TracerChange::Detached(previous))
+    } else {
+        None
+    }
+}
+
+/// Changes seen (by the monitor or a check) and not yet reported, with
+/// when they were seen
+static PENDING: Mutex<Vec<(TracerChange, Instant)>> = Mutex::new(Vec::new());
+
+/// Re-read `TracerPid` and queue any unaccounted change
+fn observe() {
+    let Some((previous, current)) = signal_compat::tracer_transition() else { return };
+    let change = classify_change(previous, current, signal_compat::startup_tracer_pid(), signal_compat::self_tracer_pid());
+    diag!("[LATE_ATTACH] TracerPid {} -> {}: {:?}", previous, current, change);
+    if let Some(change) = change {
+        PENDING.lock().unwrap_or_else(|e| e.into_inner()).push((change, Instant::now()));
+    }
+}
+
+fn comm(pid: u32) -> String {
+    fs::read_to_string(format!("/proc/{}/comm", pid)).map_or_else(|_| "?".to_string(), |c| c.trim().to_string())
+}
+
+/// Main entry point for the late-attach check; safe to call repeatedly
+pub fn check_late_attach(engine: &mut DecisionEngine) {
+    observe();
+    let changes: Vec<_> = PENDING.lock().unwrap_or_else(|e| e.into_inner()).drain(..).collect();
+    engine.record_feature("tracer_changes", changes.len() as f64);
+    for (change, seen) in changes {
+        let ago = seen.elapsed().as_millis();
+        match change {
+            TracerChange::Attached(pid) => engine.report_with_confidence(
+                DetectionSource::Ptrace,
+                60,
+                0.95,
+                &reason!("Tracer {} (PID {}) attached after startup ({} ms ago)", comm(pid), pid, ago)
+            ),
+            TracerChange::Detached(pid) => engine.report_with_confidence(
+                DetectionSource::Ptrace,
+                20,
+                0.6,
+                &reason!("Tracer (PID {}) detached mid-run ({} ms ago)", pid, ago)
+            ),
+        }
+    }
+}
+
+/// Background `TracerPid` poller; findings are reported by the next
+/// [`check_late_attach`]
+pub struct TracerMonitor {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TracerMonitor {
+    /// Start polling every `interval`. `None` if the thread cannot start.
+    pub fn start(interval: Duration) -> Option<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = crate::engine::threads::spawn_masked("tracer-monitor", move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                std::thread::sleep(interval);
+                observe();
+            }
+        })
+        .ok()?;
+        diag!("[LATE_ATTACH] Monitoring TracerPid every {} ms (startup: {})", interval.as_millis(), signal_compat::startup_tracer_pid());
+        Some(Self { stop, thread: Some(thread) })
+    }
+}
+
+impl Drop for TracerMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_change() {
+        // gdb -p after a clean start
+        assert_eq!(classify_change(0, 4242, 0, 0), Some(TracerChange::Attached(4242)));
+        // Our own PTRACE_TRACEME
+        assert_eq!(classify_change(0, 100, 0, 100), None);
+        // Started under strace, which then detached
+        assert_eq!(classify_change(300, 0, 300, 0), Some(TracerChange::Detached(300)));
+        // Started under strace, gdb took over
+        assert_eq!(classify_change(300, 4242, 300, 0), Some(TracerChange::Attached(4242)));
+        // A late tracer came back to the startup one
+        assert_eq!(classify_change(4242, 300, 300, 0), Some(TracerChange::Detached(4242)));
+        assert_eq!(classify_change(0, 0, 0, 0), None);
+    }
+
+    #[test]
+    fn test_parent_registered_before_traceme() {
+        // The monitor may poll between PTRACE_TRACEME and its result
+        signal_compat::expect_traceme();
+        let parent = std::os::unix::process::parent_id();
+        assert_eq!(signal_compat::self_tracer_pid(), parent);
+        assert_eq!(classify_change(0, parent, 0, signal_compat::self_tracer_pid()), None);
+        assert_eq!(classify_change(parent, 0, 0, signal_compat::self_tracer_pid()), None);
+        signal_compat::cancel_traceme();
+        assert_eq!(signal_compat::self_tracer_pid(), 0);
+    }
+}
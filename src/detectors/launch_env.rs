@@ -0,0 +1,169 @@
+//! Debugger Launch Fingerprinting (gdb / lldb Environment and Terminal)
+//!
+//! # Overview
+//!
+//! TracerPid covers a debugger that is attached now. A process started by
+//! `gdb ./app` or `lldb ./app` also carries traces of how it was launched,
+//! which stay even if the debugger detaches or hides from TracerPid:
+//!
+//! - **`LINES` / `COLUMNS`**: gdb and lldb export the terminal size into
+//!   the inferior's environment. Shells keep both as unexported variables,
+//!   so a program started from a prompt does not see them
+//! - **`_`**: the shell sets `_` to the program it executed. Behind gdb's
+//!   `/bin/sh -c exec ./app` (dash does not update it) it still names gdb
+//! - **Terminal owner**: the nearest non-shell ancestor reads our
+//!   terminal with line editing - its fd 0 is our pty and it has readline
+//!   or libedit mapped - the way an interactive debugger does
+//! - **Signal state at exec**: ignored and blocked signals survive `execve`.
+//!   A launcher that starts us with signals blocked, or with job-control
+//!   signals ignored, is not an interactive shell (SIGPIPE is ignored by
+//!   the Rust runtime itself and is masked out)
+//!
+//! Evidence goes to the `Ptrace` source: it is about a debugger having
+//! started us.
+//!
+//! # Why This Fails
+//!
+//! - `unset LINES COLUMNS` in gdb or `set startup-with-shell` with bash
+//!   removes the environment artifacts
+//! - Terminal multiplexers and IDE run configurations can export `LINES` /
+//!   `COLUMNS` and block signals for their own reasons
+//! - `gdb -p` attaching later leaves none of these traces
+
+use std::fs;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Debugger executables, matched against `_` and process names
+const DEBUGGERS: &[&str] = &["gdb", "lldb", "gdb-multiarch", "gdbserver", "lldb-server", "pwndbg", "gef", "edb", "radare2", "r2"];
+
+/// Shells skipped when looking for the process that owns our terminal
+const SHELLS: &[&str] = &["sh", "bash", "dash", "zsh", "fish", "ksh", "tcsh", "busybox"];
+
+/// Line-editing libraries interactive debuggers link
+const LINE_EDITORS: &[&str] = &["libreadline", "libedit"];
+
+/// Ancestors searched for the terminal owner
+const MAX_ANCESTORS: usize = 6;
+
+/// SIGPIPE, ignored by the Rust runtime before `main`
+const SIGPIPE_BIT: u64 = 1 << (libc::SIGPIPE - 1);
+
+/// Job-control signals an interactive shell never leaves ignored
+const JOB_CONTROL_BITS: u64 = (1 << (libc::SIGINT - 1)) | (1 << (libc::SIGQUIT - 1)) | (1 << (libc::SIGTSTP - 1)) | (1 << (libc::SIGTTIN - 1)) | (1 << (libc::SIGTTOU - 1));
+
+/// Whether a path or process name is a debugger
+pub fn is_debugger(name: &str) -> bool {
+    let file = name.trim().rsplit('/').next().unwrap_or("");
+    DEBUGGERS.contains(&file)
+}
+
+/// Value of `key` in a NUL-separated environment block
+pub fn env_value<'a>(environ: &'a [u8], key: &str) -> Option<&'a [u8]> {
+    environ.split(|&b| b == 0).find_map(|entry| entry.strip_prefix(key.as_bytes())?.strip_prefix(b"="))
+}
+
+/// (ignored, blocked) signal masks from /proc/<pid>/status
+pub fn signal_masks(status: &str) -> Option<(u64, u64)> {
+    let field = |name: &str| status.lines().find_map(|l| l.strip_prefix(name)).and_then(|v| u64::from_str_radix(v.trim(), 16).ok());
+    Some((field("SigIgn:")?, field("SigBlk:")?))
+}
+
+fn status_of(pid: u32) -> Option<String> {
+    fs::read_to_string(format!("/proc/{}/status", pid)).ok()
+}
+
+fn parent_of(pid: u32) -> Option<u32> {
+    status_of(pid)?.lines().find_map(|l| l.strip_prefix("PPid:"))?.trim().parse().ok()
+}
+
+fn comm_of(pid: u32) -> String {
+    fs::read_to_string(format!("/proc/{}/comm", pid)).unwrap_or_default().trim().to_string()
+}
+
+/// Nearest non-shell ancestor reading our terminal with line editing:
+/// (pid, comm, line editor)
+fn terminal_owner() -> Option<(u32, String, &'static str)> {
+    let tty = fs::read_link("/proc/self/fd/0").ok()?;
+    let tty = tty.to_string_lossy();
+    if !(tty.starts_with("/dev/pts/") || tty.starts_with("/dev/tty")) {
+        return None;
+    }
+    let mut pid = std::os::unix::process::parent_id();
+    for _ in 0..MAX_ANCESTORS {
+        if pid <= 1 {
+            return None;
+        }
+        let comm = comm_of(pid);
+        if !SHELLS.contains(&comm.as_str()) {
+            let same_tty = fs::read_link(format!("/proc/{}/fd/0", pid)).is_ok_and(|t| t.to_string_lossy() == tty);
+            let maps = fs::read_to_string(format!("/proc/{}/maps", pid)).unwrap_or_default();
+            let editor = LINE_EDITORS.iter().copied().find(|lib| maps.contains(lib));
+            return match editor {
+                Some(editor) if same_tty => Some((pid, comm, editor)),
+                _ => None,
+            };
+        }
+        pid = parent_of(pid)?;
+    }
+    None
+}
+
+/// Main entry point for the debugger-launch check
+pub fn check_launch_environment(engine: &mut DecisionEngine) {
+    let mut findings: Vec<(String, u32, f64)> = Vec::new();
+
+    // The initial block: a later unsetenv does not change it
+    let environ = fs::read("/proc/self/environ").unwrap_or_default();
+    let (lines, columns) = (env_value(&environ, "LINES"), env_value(&environ, "COLUMNS"));
+    if lines.is_some() && columns.is_some() {
+        findings.push((reason!("LINES and COLUMNS exported into our environment (set by gdb/lldb for the inferior)"), 15, 0.5));
+    }
+    let underscore = env_value(&environ, "_").map(|v| String::from_utf8_lossy(v).into_owned());
+    if let Some(launcher) = underscore.as_deref().filter(|v| is_debugger(v)) {
+        findings.push((reason!("$_ names {} instead of our executable", launcher), 30, 0.7));
+    }
+
+    let owner = terminal_owner();
+    if let Some((pid, comm, editor)) = &owner {
+        let (weight, confidence) = if is_debugger(comm) { (40, 0.85) } else { (20, 0.5) };
+        findings.push((reason!("{} (PID {}) launched us and reads our terminal with {}", comm, pid, editor), weight, confidence));
+    }
+
+    let masks = fs::read_to_string("/proc/self/status").ok().and_then(|s| signal_masks(&s));
+    if let Some((ignored, blocked)) = masks {
+        let ignored = ignored & !SIGPIPE_BIT;
+        if blocked != 0 || ignored & JOB_CONTROL_BITS != 0 {
+            findings.push((reason!("inherited signal state: ignored={:#x} blocked={:#x}", ignored, blocked), 10, 0.4));
+        }
+    }
+    diag!("[LAUNCH] lines={} columns={} _={:?} owner={:?} masks={:x?}", lines.is_some(), columns.is_some(), underscore, owner, masks);
+
+    engine.record_feature("debugger_launch_artifacts", findings.len() as f64);
+    for (details, weight, confidence) in findings {
+        engine.report_with_confidence(DetectionSource::Ptrace, weight, confidence, &reason!("Started under a debugger? {}", details));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launch_artifacts() {
+        let environ = b"_=/usr/bin/gdb\0LINES=50\0COLUMNS=200\0LINESX=1\0";
+        assert_eq!(env_value(environ, "LINES"), Some(&b"50"[..]));
+        assert_eq!(env_value(environ, "_"), Some(&b"/usr/bin/gdb"[..]));
+        assert_eq!(env_value(environ, "HOME"), None);
+        assert!(is_debugger("/usr/bin/gdb"));
+        assert!(is_debugger("lldb\n"));
+        assert!(!is_debugger("/usr/bin/gdbus"));
+    }
+
+    #[test]
+    fn test_signal_masks() {
+        let status = "Name:\tapp\nSigBlk:\t0000000000010000\nSigIgn:\t0000000000001000\n";
+        assert_eq!(signal_masks(status), Some((0x1000, 0x10000)));
+        assert_eq!(SIGPIPE_BIT, 0x1000);
+        assert_eq!(signal_masks("Name:\tapp\n"), None);
+    }
+}
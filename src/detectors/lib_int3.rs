@@ -0,0 +1,187 @@
+//! Breakpoints in Shared Libraries (On-Disk Comparison)
+//!
+//! # Overview
+//!
+//! `int3.rs` and `text_diff.rs` only look at our own code, but analysts
+//! usually break on the libc functions we call (`ptrace`, `open`, `read`)
+//! rather than in our binary. Counting `0xCC` bytes in a library is useless
+//! (its padding is full of them), so every executable mapping of every
+//! loaded library - libc, ld-linux and the rest - is compared with the
+//! bytes at the same offset of its file. Padding is on disk too; an
+//! inserted breakpoint is not:
+//!
+//! | Difference from the file                | Meaning                    | Source    | Weight |
+//! |-----------------------------------------|----------------------------|-----------|--------|
+//! | Patched byte(s) now containing `0xCC`   | Software breakpoint        | Int3      | 45     |
+//! | Other patched bytes                     | Inline patch or hook       | Integrity | 35     |
+//!
+//! Each site is named by the nearest symbol (`dladdr`), so the report says
+//! which function the analyst broke on.
+//!
+//! # Why This Fails
+//!
+//! - Libraries with text relocations differ from their files legitimately
+//!   (rare; the loader refuses them under most hardening settings)
+//! - Libraries loaded from memory (`memfd`) or deleted from disk have no
+//!   file to compare against and are skipped
+//! - A debugger that removes its breakpoints while we run, or hides them
+//!   from data reads (see `mem_crossview.rs`), leaves an identical image
+
+use std::ffi::CStr;
+use std::fs;
+use std::os::unix::fs::FileExt;
+use crate::detectors::inline_hooks::file_offset;
+use crate::detectors::maps_anomaly::parse_regions;
+use crate::detectors::mem_crossview::diverging_ranges;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Sites listed per report; the rest are counted
+const MAX_LISTED: usize = 8;
+
+/// One range where a library's memory differs from its file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Patch {
+    pub addr: u64,
+    pub len: usize,
+    /// The patched bytes include `0xCC`
+    pub breakpoint: bool,
+}
+
+/// Patched ranges of the code at `base`, `memory` against `disk`
+pub fn patches(memory: &[u8], disk: &[u8], base: u64) -> Vec<Patch> {
+    diverging_ranges(memory, disk, base).into_iter()
+        .map(|(addr, len)| {
+            let at = (addr - base) as usize;
+            Patch { addr, len, breakpoint: memory[at..at + len].contains(&0xcc) }
+        })
+        .collect()
+}
+
+/// `library!symbol+offset` for an address, or the bare address
+fn symbolize(addr: u64) -> String {
+    // SAFETY: dladdr only looks the address up; the strings it returns point
+    // into the loader's link map and symbol tables, which outlive this call
+    unsafe {
+        let mut info: libc::Dl_info = std::mem::zeroed();
+        if libc::dladdr(addr as *const libc::c_void, &mut info) == 0 || info.dli_fname.is_null() {
+            return format!("{:#x}", addr);
+        }
+        let file = CStr::from_ptr(info.dli_fname).to_string_lossy();
+        let file = file.rsplit('/').next().unwrap_or_default().to_string();
+        if info.dli_sname.is_null() {
+            return format!("{}+{:#x}", file, addr - info.dli_fbase as u64);
+        }
+        format!("{}!{}+{:#x}", file, CStr::from_ptr(info.dli_sname).to_string_lossy(), addr - info.dli_saddr as u64)
+    }
+}
+
+/// Patches in one executable mapping `[start, end)`; `Err` if its file
+/// cannot be read
+fn compare_mapping(maps: &str, start: u64, end: u64) -> Result<Vec<Patch>, String> {
+    let (path, offset) = file_offset(maps, start).ok_or("not file-backed")?;
+    let file = fs::File::open(&path).map_err(|e| format!("{}: {}", path, e))?;
+    let file_len = file.metadata().map_err(|e| format!("{}: {}", path, e))?.len();
+    // The last page of a mapping may extend past the end of the file
+    let len = (end - start).min(file_len.saturating_sub(offset)) as usize;
+    let mut disk = vec![0u8; len];
+    file.read_exact_at(&mut disk, offset).map_err(|e| format!("{}: {}", path, e))?;
+    // SAFETY: [start, start + len) lies in a readable executable mapping of
+    // a loaded library, which stays mapped while we hold no dlclose
+    let memory = unsafe { std::slice::from_raw_parts(start as *const u8, len) };
+    Ok(patches(memory, &disk, start))
+}
+
+/// Main entry point for the shared-library breakpoint scan
+pub fn check_lib_int3(engine: &mut DecisionEngine) {
+    let maps = match fs::read_to_string("/proc/self/maps") {
+        Ok(maps) => maps,
+        Err(e) => {
+            engine.record_diagnostic("lib_int3", &reason!("/proc/self/maps unreadable: {}", e));
+            return;
+        }
+    };
+    // Our own binary is text_diff.rs's job
+    let exe = std::env::current_exe().map(|p| p.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut found: Vec<Patch> = Vec::new();
+    let mut compared = 0;
+    for region in parse_regions(&maps) {
+        if !region.perms.starts_with("r-x") || !region.path.starts_with('/') || region.path == exe {
+            continue;
+        }
+        if region.path.ends_with(" (deleted)") {
+            engine.record_diagnostic("lib_int3", &reason!("{} is deleted on disk; not compared", region.path));
+            continue;
+        }
+        match compare_mapping(&maps, region.start, region.end) {
+            Ok(patches) => {
+                compared += 1;
+                diag!("[LIB_INT3] {} ({:#x}-{:#x}): {} patched range(s)", region.path, region.start, region.end, patches.len());
+                found.extend(patches);
+            }
+            Err(e) => engine.record_diagnostic("lib_int3", &format!("{:#x}: {}", region.start, e)),
+        }
+    }
+
+    let (breakpoints, others): (Vec<Patch>, Vec<Patch>) = found.into_iter().partition(|p| p.breakpoint);
+    engine.record_feature("lib_int3_breakpoints", breakpoints.len() as f64);
+    engine.record_feature("lib_patched_ranges", others.len() as f64);
+    if compared == 0 {
+        engine.record_diagnostic("lib_int3", &reason!("no library code mapping could be compared with its file"));
+    }
+
+    let list = |patches: &[Patch]| -> String {
+        let listed: Vec<String> = patches.iter().take(MAX_LISTED)
+            .map(|p| reason!("{} ({} byte(s))", symbolize(p.addr), p.len))
+            .collect();
+        let more = patches.len().saturating_sub(MAX_LISTED);
+        format!("{}{}", listed.join(", "), if more > 0 { reason!(" and {} more", more) } else { String::new() })
+    };
+    if !breakpoints.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::Int3,
+            45,
+            0.9,
+            &reason!("Software breakpoints in shared libraries (absent from their files): {}", list(&breakpoints))
+        );
+    }
+    if !others.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::Integrity,
+            35,
+            0.8,
+            &reason!("Shared library code differs from its file: {}", list(&others))
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patches() {
+        let disk = [0x55, 0x48, 0x89, 0xe5, 0xcc, 0xcc, 0xcc, 0xcc, 0x90];
+        let mut memory = disk;
+        assert!(patches(&memory, &disk, 0x1000).is_empty());
+        // Breakpoint on the entry; padding that was 0xCC on disk is not reported
+        memory[0] = 0xcc;
+        // A two-byte patch elsewhere
+        memory[7] = 0xeb;
+        memory[8] = 0xfe;
+        assert_eq!(patches(&memory, &disk, 0x1000), vec![
+            Patch { addr: 0x1000, len: 1, breakpoint: true },
+            Patch { addr: 0x1007, len: 2, breakpoint: false },
+        ]);
+    }
+
+    #[test]
+    fn test_loaded_libraries_match_disk() {
+        let maps = fs::read_to_string("/proc/self/maps").unwrap();
+        let exe = std::env::current_exe().unwrap().to_string_lossy().into_owned();
+        for region in parse_regions(&maps) {
+            if region.perms.starts_with("r-x") && region.path.starts_with('/') && region.path != exe {
+                assert_eq!(compare_mapping(&maps, region.start, region.end), Ok(Vec::new()), "{}", region.path);
+            }
+        }
+    }
+}
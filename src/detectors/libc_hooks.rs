@@ -0,0 +1,231 @@
+//! LD_PRELOAD Hook Detection (Syscall vs libc Divergence)
+//!
+//! # Overview
+//!
+//! Several detectors call `ptrace`, `getpid` and `clock_gettime` through
+//! libc. A preloaded library that interposes on those wrappers can lie to
+//! all of them at once (the classic "`ptrace` always returns 0" bypass)
+//! without touching our code. We ask the kernel directly and compare:
+//!
+//! - **Results**: `ptrace(PTRACE_CONT)` on a process we do not trace must
+//!   fail with `ESRCH` both ways; `getpid` must agree; a raw
+//!   `clock_gettime` must fall between two libc readings. The raw side
+//!   issues `SYSCALL` itself, so an interposed `syscall()` cannot answer
+//!   for the kernel
+//! - **Latency**: the fastest libc `getpid` against the fastest raw one -
+//!   a wrapper that logs or forwards is several times slower
+//! - **Binding**: `dlsym(RTLD_DEFAULT, ...)` for each wrapper must resolve
+//!   into libc itself, not into another object
+//! - **Preload lists**: entries of `/etc/ld.so.preload` and `LD_PRELOAD`
+//!   that are mapped into our process
+//!
+//! # Why This Fails
+//!
+//! - Hooks that forward faithfully and only observe agree with the kernel;
+//!   only binding and the preload lists catch them
+//! - A preload that scrubs `/etc/ld.so.preload` reads and unsets
+//!   `LD_PRELOAD` hides the list (not the binding)
+//! - Hooks patched into libc's own code (inline hooks) resolve "into
+//!   libc" and are left to `inline_hooks.rs`
+
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::hint::black_box;
+use std::time::Instant;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::ffi::raw_syscall4;
+
+/// Wrappers our detectors rely on
+const WRAPPERS: &[&str] = &["ptrace", "getpid", "clock_gettime", "syscall", "sigaction"];
+
+/// Timed calls per variant for the latency comparison
+const LATENCY_SAMPLES: usize = 200;
+
+/// libc / raw `getpid` ratio (fastest of each) that indicates a hook
+const LATENCY_RATIO: f64 = 4.0;
+
+/// Libraries named by a preload list (`/etc/ld.so.preload` or `LD_PRELOAD`)
+pub fn preload_entries(list: &str) -> Vec<String> {
+    list.lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| line.split(|c: char| c == ':' || c.is_whitespace()))
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Preload entries present among the mapped files
+pub fn mapped_preloads(entries: &[String], maps: &str) -> Vec<String> {
+    let mut mapped: Vec<String> = entries.iter()
+        .filter(|entry| {
+            let file = entry.rsplit('/').next().unwrap_or(entry);
+            maps.lines().filter_map(|line| line.split_whitespace().nth(5)).any(|path| path == entry.as_str() || path.rsplit('/').next() == Some(file))
+        })
+        .cloned()
+        .collect();
+    mapped.dedup();
+    mapped
+}
+
+/// Whether a shared object path is the C library
+pub fn is_libc(path: &str) -> bool {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    file.starts_with("libc.so") || file.starts_with("libc-")
+}
+
+/// Object `name` resolves into, if any
+fn binding(name: &str) -> Option<String> {
+    let symbol = CString::new(name).ok()?;
+    // SAFETY: dlsym/dladdr only look the symbol up; dli_fname points into
+    // the loader's link map, which outlives this call
+    unsafe {
+        let addr = libc::dlsym(libc::RTLD_DEFAULT, symbol.as_ptr());
+        if addr.is_null() {
+            return None;
+        }
+        let mut info: libc::Dl_info = std::mem::zeroed();
+        if libc::dladdr(addr, &mut info) == 0 || info.dli_fname.is_null() {
+            return None;
+        }
+        Some(CStr::from_ptr(info.dli_fname).to_string_lossy().into_owned())
+    }
+}
+
+/// `-errno` from libc's (-1, errno) convention, so results compare with
+/// the kernel's
+fn kernel_style(result: i64) -> i64 {
+    if result == -1 {
+        -(std::io::Error::last_os_error().raw_os_error().unwrap_or(0) as i64)
+    } else {
+        result
+    }
+}
+
+/// (libc result, raw result) of `PTRACE_CONT` on our parent, which we do
+/// not trace
+fn ptrace_results() -> (i64, i64) {
+    // SAFETY: PTRACE_CONT on a non-tracee fails without side effects
+    unsafe {
+        let parent = raw_syscall4(libc::SYS_getppid, 0, 0, 0, 0);
+        let via_libc = kernel_style(libc::ptrace(libc::PTRACE_CONT, parent as libc::pid_t, 0, 0) as i64);
+        let raw = raw_syscall4(libc::SYS_ptrace, libc::PTRACE_CONT as i64, parent, 0, 0);
+        (via_libc, raw)
+    }
+}
+
+/// Raw CLOCK_MONOTONIC reading bracketed by two libc readings
+fn clock_bracket() -> (libc::timespec, libc::timespec, libc::timespec) {
+    let mut ts = [libc::timespec { tv_sec: 0, tv_nsec: 0 }; 3];
+    // SAFETY: each call writes one timespec we own
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts[0]);
+        raw_syscall4(libc::SYS_clock_gettime, libc::CLOCK_MONOTONIC as i64, &mut ts[1] as *mut libc::timespec as i64, 0, 0);
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts[2]);
+    }
+    (ts[0], ts[1], ts[2])
+}
+
+fn nanos(ts: libc::timespec) -> i128 {
+    ts.tv_sec as i128 * 1_000_000_000 + ts.tv_nsec as i128
+}
+
+/// Fastest of [`LATENCY_SAMPLES`] calls, in nanoseconds
+fn fastest(call: impl Fn() -> i64) -> u128 {
+    (0..LATENCY_SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(call());
+            start.elapsed().as_nanos()
+        })
+        .min()
+        .unwrap_or(0)
+        .max(1)
+}
+
+/// Main entry point for the libc-hook check
+pub fn check_libc_hooks(engine: &mut DecisionEngine) {
+    let mut findings: Vec<(String, u32)> = Vec::new();
+
+    // 1. Results: libc wrappers vs the kernel
+    let (via_libc, raw) = ptrace_results();
+    if via_libc != raw {
+        findings.push((reason!("ptrace(PTRACE_CONT, parent) returns {} through libc but {} from the kernel", via_libc, raw), 50));
+    }
+    // SAFETY: getpid has no side effects
+    let (pid_libc, pid_raw) = unsafe { (libc::getpid() as i64, raw_syscall4(libc::SYS_getpid, 0, 0, 0, 0)) };
+    if pid_libc != pid_raw {
+        findings.push((reason!("getpid returns {} through libc but {} from the kernel", pid_libc, pid_raw), 50));
+    }
+    let (before, kernel, after) = clock_bracket();
+    if !(nanos(before)..=nanos(after)).contains(&nanos(kernel)) {
+        findings.push((reason!("raw CLOCK_MONOTONIC {} ns is outside the libc readings [{}, {}]", nanos(kernel), nanos(before), nanos(after)), 40));
+    }
+
+    // 2. Latency of the getpid wrapper
+    // SAFETY: getpid has no side effects
+    let libc_ns = fastest(|| unsafe { libc::getpid() as i64 });
+    let raw_ns = fastest(|| unsafe { raw_syscall4(libc::SYS_getpid, 0, 0, 0, 0) });
+    let ratio = libc_ns as f64 / raw_ns as f64;
+    engine.record_feature("getpid_wrapper_ratio", ratio);
+    if ratio > LATENCY_RATIO {
+        findings.push((reason!("libc getpid takes {} ns against {} ns for the raw syscall", libc_ns, raw_ns), 15));
+    }
+
+    // 3. Symbol binding
+    let foreign: Vec<String> = WRAPPERS.iter()
+        .filter_map(|name| Some((name, binding(name)?)))
+        .filter(|(_, object)| !is_libc(object))
+        .map(|(name, object)| format!("{} -> {}", name, object))
+        .collect();
+    if !foreign.is_empty() {
+        findings.push((reason!("wrappers bound outside libc: {}", foreign.join(", ")), 45));
+    }
+
+    // 4. Preload lists
+    let mut entries = preload_entries(&fs::read_to_string("/etc/ld.so.preload").unwrap_or_default());
+    entries.extend(preload_entries(&std::env::var("LD_PRELOAD").unwrap_or_default()));
+    let maps = fs::read_to_string("/proc/self/maps").unwrap_or_default();
+    let mapped = mapped_preloads(&entries, &maps);
+    if !mapped.is_empty() {
+        findings.push((reason!("preloaded into this process: {}", mapped.join(", ")), 15));
+    }
+    diag!("[LIBC_HOOKS] ptrace={}/{} getpid {}ns/{}ns foreign={:?} preloads={:?}", via_libc, raw, libc_ns, raw_ns, foreign, mapped);
+
+    engine.record_feature("libc_hook_findings", findings.len() as f64);
+    if findings.is_empty() {
+        return;
+    }
+    let weight = findings.iter().map(|(_, w)| w).sum::<u32>().min(80);
+    let confidence = if findings.iter().any(|(_, w)| *w >= 40) { 0.9 } else { 0.5 };
+    let details: Vec<&str> = findings.iter().map(|(d, _)| d.as_str()).collect();
+    engine.report_with_confidence(
+        DetectionSource::Integrity,
+        weight,
+        confidence,
+        &reason!("libc wrappers are hooked in userspace: {}", details.join("; "))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preload_lists() {
+        let entries = preload_entries("# comment\n/usr/lib/libhook.so\n/opt/a.so /opt/b.so\n");
+        assert_eq!(entries, vec!["/usr/lib/libhook.so", "/opt/a.so", "/opt/b.so"]);
+        assert_eq!(preload_entries("libx.so:liby.so"), vec!["libx.so", "liby.so"]);
+        let maps = "7f0000000000-7f0000001000 r-xp 00000000 08:01 42 /usr/lib/libhook.so\n";
+        assert_eq!(mapped_preloads(&entries, maps), vec!["/usr/lib/libhook.so"]);
+        assert!(is_libc("/lib/x86_64-linux-gnu/libc.so.6"));
+        assert!(!is_libc("/usr/lib/libhook.so"));
+    }
+
+    #[test]
+    fn test_native_wrappers_agree() {
+        let (via_libc, raw) = ptrace_results();
+        assert_eq!(via_libc, -(libc::ESRCH as i64));
+        assert_eq!(via_libc, raw);
+        assert!(WRAPPERS.iter().filter_map(|name| binding(name)).all(|object| is_libc(&object)));
+    }
+}
@@ -0,0 +1,193 @@
+//! Memory-Map Anomaly Scanner
+//!
+//! # Overview
+//!
+//! Whatever an injector does, its code has to live somewhere in our
+//! address space. A plain Rust or C program only maps its own executable,
+//! system libraries, and the kernel's `[vdso]`; the other detectors look
+//! for specific tools, this one parses /proc/self/maps once and flags the
+//! shapes injected code leaves behind, whoever wrote it:
+//!
+//! | Pattern                    | Typical origin                              | Weight |
+//! |----------------------------|---------------------------------------------|--------|
+//! | Anonymous RWX, >= 1 MiB    | DBI / emulator code cache                   | 25     |
+//! | Anonymous RWX, small       | Hook trampolines, shellcode stubs           | 15     |
+//! | File-backed RWX            | A library patched in place                  | 20     |
+//! | Anonymous executable       | Manually mapped payload, JIT                | 15     |
+//! | Executable and deleted     | Agent loaded from memfd / `/tmp`, unlinked  | 20-35  |
+//! | Unexpected shared object   | Library outside the system library paths    | 20     |
+//!
+//! Each finding is reported on its own, with a confidence that depends on
+//! how specific the pattern is (a deleted memfd is more telling than an
+//! anonymous executable page).
+//!
+//! # Why This Fails
+//!
+//! - JIT runtimes (a scripting engine embedded in the host program)
+//!   legitimately create anonymous executable and RWX memory
+//! - A library replaced by a package upgrade while we run shows as deleted
+//! - Injectors that write into an existing mapping (a code cave in our own
+//!   text) create no new region
+
+use std::fs;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Anonymous RWX bytes that look like a code cache rather than a stub
+const CODE_CACHE_BYTES: u64 = 1 << 20;
+
+/// Directories shared objects are expected to load from
+const LIBRARY_DIRS: &[&str] = &["/lib/", "/lib64/", "/usr/lib/", "/usr/lib64/", "/usr/local/lib/", "/usr/libexec/", "/opt/"];
+
+/// Directories an injector stages a payload in before unlinking it
+const STAGING_DIRS: &[&str] = &["/memfd:", "/tmp/", "/dev/shm/", "/var/tmp/", "/run/user/"];
+
+/// Findings reported individually; the rest are counted
+const MAX_FINDINGS: usize = 8;
+
+/// One line of /proc/self/maps
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub start: u64,
+    pub end: u64,
+    pub perms: String,
+    pub inode: u64,
+    pub path: String,
+}
+
+impl Region {
+    fn size(&self) -> u64 {
+        self.end - self.start
+    }
+
+    fn executable(&self) -> bool {
+        self.perms.as_bytes().get(2) == Some(&b'x')
+    }
+
+    fn writable(&self) -> bool {
+        self.perms.as_bytes().get(1) == Some(&b'w')
+    }
+
+    /// No backing file (named anonymous regions included)
+    fn anonymous(&self) -> bool {
+        self.inode == 0 && (self.path.is_empty() || self.path.starts_with("[anon"))
+    }
+
+    fn deleted(&self) -> bool {
+        self.path.ends_with(" (deleted)")
+    }
+}
+
+/// Parse /proc/self/maps (paths may contain spaces, e.g. " (deleted)")
+pub fn parse_regions(maps: &str) -> Vec<Region> {
+    maps.lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(6, ' ');
+            let (start, end) = fields.next()?.split_once('-')?;
+            let perms = fields.next()?.to_string();
+            let inode = fields.nth(2)?.parse().ok()?;
+            let path = fields.next().unwrap_or("").trim_start().to_string();
+            Some(Region {
+                start: u64::from_str_radix(start, 16).ok()?,
+                end: u64::from_str_radix(end, 16).ok()?,
+                perms,
+                inode,
+                path,
+            })
+        })
+        .collect()
+}
+
+/// Whether `path` is a shared object outside the system library paths
+pub fn unexpected_library(path: &str) -> bool {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    path.starts_with('/')
+        && (file.ends_with(".so") || file.contains(".so."))
+        && !LIBRARY_DIRS.iter().any(|dir| path.starts_with(dir))
+}
+
+/// Anomaly of one region, as (description, weight, confidence)
+pub fn classify_region(region: &Region) -> Option<(String, u32, f64)> {
+    let at = format!("{:#x}-{:#x} {}", region.start, region.end, region.perms);
+    if region.executable() && region.writable() {
+        return Some(if !region.anonymous() {
+            (reason!("file-backed RWX mapping {} {}", at, region.path), 20, 0.7)
+        } else if region.size() >= CODE_CACHE_BYTES {
+            (reason!("anonymous RWX region {} of {} KiB (code cache)", at, region.size() >> 10), 25, 0.8)
+        } else {
+            (reason!("anonymous RWX region {} of {} KiB", at, region.size() >> 10), 15, 0.5)
+        });
+    }
+    if region.executable() && region.anonymous() {
+        return Some((reason!("anonymous executable region {} {}", at, region.path).trim_end().to_string(), 15, 0.4));
+    }
+    if region.executable() && region.deleted() {
+        let staged = STAGING_DIRS.iter().any(|dir| region.path.starts_with(dir));
+        let (weight, confidence) = if staged { (35, 0.85) } else { (20, 0.5) };
+        return Some((reason!("executable mapping of a deleted file {}", region.path), weight, confidence));
+    }
+    None
+}
+
+/// Main entry point for the memory-map anomaly scan
+pub fn check_maps_anomalies(engine: &mut DecisionEngine) {
+    let maps = match fs::read_to_string("/proc/self/maps") {
+        Ok(maps) => maps,
+        Err(e) => {
+            engine.record_diagnostic("maps_anomaly", &reason!("/proc/self/maps unreadable: {}", e));
+            return;
+        }
+    };
+    let regions = parse_regions(&maps);
+
+    let mut findings: Vec<(String, u32, f64)> = regions.iter().filter_map(classify_region).collect();
+    let mut libraries: Vec<&str> = regions.iter()
+        .map(|r| r.path.as_str())
+        .filter(|path| unexpected_library(path))
+        .collect();
+    libraries.dedup();
+    findings.extend(libraries.iter().map(|path| (reason!("shared object outside the system library paths: {}", path), 20, 0.6)));
+    diag!("[MAPS] {} regions, {} anomalies", regions.len(), findings.len());
+
+    engine.record_feature("maps_anomalies", findings.len() as f64);
+    let extra = findings.len().saturating_sub(MAX_FINDINGS);
+    for (details, weight, confidence) in findings.into_iter().take(MAX_FINDINGS) {
+        engine.report_with_confidence(DetectionSource::Instrumentation, weight, confidence, &reason!("Memory-map anomaly: {}", details));
+    }
+    if extra > 0 {
+        diag!("[MAPS] {} further anomalies not reported individually", extra);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAPS: &str = "\
+55d000000000-55d000010000 r-xp 00001000 fe:00 11 /usr/bin/app
+7f0000000000-7f0000200000 rwxp 00000000 00:00 0
+7f0000200000-7f0000201000 r-xp 00000000 00:00 0 [anon:stub]
+7f0000300000-7f0000310000 r-xp 00000000 00:01 2050 /memfd:frida-agent-64.so (deleted)
+7f0000400000-7f0000410000 r-xp 00001000 fe:00 12 /usr/lib/x86_64-linux-gnu/libc.so.6
+7f0000500000-7f0000510000 r-xp 00001000 fe:00 13 /home/user/hook.so
+7ffd00000000-7ffd00002000 r-xp 00000000 00:00 0 [vdso]
+";
+
+    #[test]
+    fn test_parse_regions() {
+        let regions = parse_regions(MAPS);
+        assert_eq!(regions.len(), 7);
+        assert_eq!(regions[3].path, "/memfd:frida-agent-64.so (deleted)");
+        assert_eq!(regions[3].inode, 2050);
+        assert!(regions[2].anonymous());
+    }
+
+    #[test]
+    fn test_classify_region() {
+        let regions = parse_regions(MAPS);
+        let kinds: Vec<Option<(u32, f64)>> = regions.iter().map(|r| classify_region(r).map(|(_, w, c)| (w, c))).collect();
+        assert_eq!(kinds, vec![None, Some((25, 0.8)), Some((15, 0.4)), Some((35, 0.85)), None, None, None]);
+        assert!(unexpected_library("/home/user/hook.so"));
+        assert!(!unexpected_library("/usr/lib/x86_64-linux-gnu/libc.so.6"));
+        assert!(!unexpected_library("/usr/bin/app"));
+    }
+}
@@ -0,0 +1,151 @@
+//! Text Cross-View (Direct Read vs /proc/self/mem)
+//!
+//! # Overview
+//!
+//! Our own code can be read two ways: by dereferencing a pointer into the
+//! text mapping, which goes through the page tables the CPU executes from,
+//! and by `pread` on `/proc/self/mem`, which goes through the kernel's
+//! `access_process_vm`. Normally both return the same bytes. Stealth
+//! breakpoint tools tend to sanitize only one of them:
+//!
+//! - A `pread`/`read` hook (or a ptrace supervisor rewriting the result)
+//!   hides `int3` bytes from the /proc view while the page still holds them
+//! - Split-view page tricks (EPT hooks, a page swapped under us with
+//!   `userfaultfd`) show the original bytes to data reads done by the CPU
+//!   but not to the kernel's walk, or the other way round
+//!
+//! We read the executable mappings of our binary and of libc both ways and
+//! compare them byte for byte; any divergence is reported with the
+//! addresses where it starts, and whether the direct view holds `0xCC`
+//! there.
+//!
+//! # Why This Fails
+//!
+//! - A tool that sanitizes both views consistently (a hypervisor hiding its
+//!   breakpoints from every data read) leaves nothing to compare; the
+//!   timing and hardware-breakpoint detectors look at such tools
+//! - Pages unreadable through /proc/self/mem (restricted `ptrace_scope`
+//!   setups deny it) are skipped and noted as a diagnostic
+
+use std::fs;
+use std::os::unix::fs::FileExt;
+use crate::detectors::maps_anomaly::{parse_regions, Region};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Bytes compared per read
+const CHUNK: usize = 64 << 10;
+
+/// Divergent ranges listed per report; the rest are counted
+const MAX_LISTED: usize = 8;
+
+/// Ranges `(address, length)` where the two views of the bytes at `base`
+/// disagree
+pub fn diverging_ranges(direct: &[u8], via_mem: &[u8], base: u64) -> Vec<(u64, usize)> {
+    let mut ranges: Vec<(u64, usize)> = Vec::new();
+    for (i, _) in direct.iter().zip(via_mem).enumerate().filter(|(_, (a, b))| a != b) {
+        let addr = base + i as u64;
+        match ranges.last_mut() {
+            Some((start, len)) if *start + *len as u64 == addr => *len += 1,
+            _ => ranges.push((addr, 1)),
+        }
+    }
+    ranges
+}
+
+/// Executable mappings of our binary and libc
+fn text_regions(regions: &[Region]) -> Vec<&Region> {
+    let exe = std::env::current_exe().map(|p| p.to_string_lossy().into_owned()).unwrap_or_default();
+    regions.iter()
+        .filter(|r| r.perms.starts_with("r-x"))
+        .filter(|r| r.path == exe || r.path.rsplit('/').next().is_some_and(|f| f.starts_with("libc.so")))
+        .collect()
+}
+
+/// Divergent ranges of one region, with the direct-view byte at each start;
+/// `Err` if /proc/self/mem would not return the region
+fn compare_region(mem: &fs::File, region: &Region) -> Result<Vec<(u64, usize, u8)>, String> {
+    let mut found = Vec::new();
+    let mut via_mem = vec![0u8; CHUNK];
+    let mut addr = region.start;
+    while addr < region.end {
+        let len = ((region.end - addr) as usize).min(CHUNK);
+        mem.read_exact_at(&mut via_mem[..len], addr).map_err(|e| format!("{:#x}: {}", addr, e))?;
+        // SAFETY: [addr, addr + len) lies in a readable mapping of our own
+        // binary or libc, which stays mapped for the life of the process
+        let direct = unsafe { std::slice::from_raw_parts(addr as *const u8, len) };
+        found.extend(diverging_ranges(direct, &via_mem[..len], addr).into_iter()
+            .map(|(at, n)| (at, n, direct[(at - addr) as usize])));
+        addr += len as u64;
+    }
+    Ok(found)
+}
+
+/// Main entry point for the text cross-view check
+pub fn check_mem_crossview(engine: &mut DecisionEngine) {
+    let maps = fs::read_to_string("/proc/self/maps").unwrap_or_default();
+    let regions = parse_regions(&maps);
+    let mem = match fs::File::open("/proc/self/mem") {
+        Ok(mem) => mem,
+        Err(e) => {
+            engine.record_diagnostic("mem_crossview", &reason!("/proc/self/mem unreadable: {}", e));
+            return;
+        }
+    };
+
+    let mut total = 0;
+    for region in text_regions(&regions) {
+        let ranges = match compare_region(&mem, region) {
+            Ok(ranges) => ranges,
+            Err(e) => {
+                engine.record_diagnostic("mem_crossview", &reason!("{} not readable through /proc/self/mem at {}", region.path, e));
+                continue;
+            }
+        };
+        diag!("[MEM_CROSSVIEW] {:#x}-{:#x} {}: {} divergent range(s)", region.start, region.end, region.path, ranges.len());
+        if ranges.is_empty() {
+            continue;
+        }
+        total += ranges.len();
+        let hidden_int3 = ranges.iter().any(|(_, _, byte)| *byte == 0xcc);
+        let listed: Vec<String> = ranges.iter().take(MAX_LISTED)
+            .map(|(at, n, byte)| reason!("{:#x} (+{:#x}, {} byte(s), direct {:02x})", at, at - region.start, n, byte))
+            .collect();
+        let more = ranges.len().saturating_sub(MAX_LISTED);
+        engine.report_with_confidence(
+            DetectionSource::CrossView,
+            if hidden_int3 { 60 } else { 50 },
+            if hidden_int3 { 0.95 } else { 0.9 },
+            &reason!("Text of {} reads differently directly and through /proc/self/mem{}: {}{}",
+                region.path,
+                if hidden_int3 { described!(" (int3 hidden from one view)") } else { "" },
+                listed.join(", "),
+                if more > 0 { reason!(" and {} more", more) } else { String::new() })
+        );
+    }
+    engine.record_feature("mem_view_divergences", total as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diverging_ranges() {
+        let direct = [0x55, 0xcc, 0xcc, 0x89, 0xe5, 0xcc, 0xc3];
+        let via_mem = [0x55, 0x48, 0x89, 0x89, 0xe5, 0x90, 0xc3];
+        assert_eq!(diverging_ranges(&direct, &via_mem, 0x1000), vec![(0x1001, 2), (0x1005, 1)]);
+        assert!(diverging_ranges(&direct, &direct, 0x1000).is_empty());
+    }
+
+    #[test]
+    fn test_native_views_agree() {
+        let maps = fs::read_to_string("/proc/self/maps").unwrap();
+        let regions = parse_regions(&maps);
+        let mem = fs::File::open("/proc/self/mem").unwrap();
+        let text = text_regions(&regions);
+        assert!(!text.is_empty());
+        for region in text {
+            assert_eq!(compare_region(&mem, region), Ok(Vec::new()), "{}", region.path);
+        }
+    }
+}
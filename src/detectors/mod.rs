@@ -1,3 +1,5 @@
+#[macro_use]
+pub mod ret_probe;
 pub mod timing;
 pub mod int3;
 pub mod trap_flag;
@@ -6,3 +8,65 @@ pub mod hardware_bp;
 pub mod jitter;
 pub mod record_replay;
 pub mod ebpf_compare;
+pub mod environ;
+pub mod auxv;
+pub mod cpu_time;
+pub mod xstate;
+pub mod illegal_insn;
+pub mod fpu;
+pub mod cpuid_claims;
+pub mod rtm;
+pub mod cet;
+pub mod syscall_paths;
+pub mod output_capture;
+pub mod profiler;
+pub mod watchdog;
+pub mod attestation;
+pub mod bait;
+pub mod premain;
+pub mod frida;
+pub mod dbi;
+pub mod translation;
+pub mod remote_debug;
+pub mod tracer_kind;
+pub mod tracefs;
+pub mod perf_observer;
+pub mod cpu_semantics;
+pub mod cpu_errata;
+pub mod qbdi;
+pub mod coverage;
+pub mod symbolic;
+pub mod translator;
+pub mod analysis_vm;
+pub mod sandbox;
+pub mod libc_hooks;
+pub mod rtld_audit;
+pub mod host_daemons;
+pub mod launch_env;
+pub mod syscall_filter;
+pub mod hypervisor;
+pub mod inline_hooks;
+pub mod maps_anomaly;
+pub mod thread_inject;
+pub mod fd_table;
+pub mod mem_crossview;
+pub mod text_diff;
+pub mod cmdline_view;
+pub mod ancestry;
+pub mod core_dump;
+pub mod late_attach;
+pub mod tsc_sync;
+pub mod clock_xcheck;
+pub mod counter_clock;
+pub mod sleep_skip;
+pub mod clock_jump;
+pub mod insn_count;
+pub mod hw_trace;
+pub mod vdso_clock;
+pub mod stop_history;
+pub mod cpu_beacon;
+pub mod task_wchan;
+pub mod lib_int3;
+pub mod module_xview;
+pub mod env_scan;
+pub mod kmod_scan;
@@ -0,0 +1,250 @@
+//! Loaded-Module Cross-View (dl_iterate_phdr vs /proc/self/maps)
+//!
+//! # Overview
+//!
+//! The dynamic loader's list of objects (`dl_iterate_phdr`, walking the
+//! `link_map`) and the kernel's list of mappings (`/proc/self/maps`) are
+//! built independently. Normally every executable file mapping belongs to
+//! a listed object and every listed object is mapped from the file it is
+//! named after. Injected code hides by breaking one side:
+//!
+//! | Observation                                      | Meaning                               | Weight |
+//! |--------------------------------------------------|---------------------------------------|--------|
+//! | Executable file mapping no object's `PT_LOAD`    | Manually mapped library (or one       | 40     |
+//! | segment covers                                   | unlinked from the `link_map`)         |        |
+//! | Anonymous mapping starting with an ELF header    | Reflectively loaded image             | 45     |
+//! | that no object covers                            |                                       |        |
+//! | Listed object's code not mapped at all           | maps filtered by a hook               | 45     |
+//! | Listed object's code mapped from another file    | `link_map` name rewritten             | 35     |
+//!
+//! Everything is compared by address, so renaming a file or an entry does
+//! not help. Evidence goes to `CrossView`.
+//!
+//! # Why This Fails
+//!
+//! - An injector that maps its code anonymously, without ELF headers, and
+//!   never touches the `link_map` is indistinguishable from a JIT region
+//! - rtld-audit libraries live in a second namespace; `rtld_audit.rs`
+//!   reports them, and they may show up here as unlisted mappings too
+//! - A hook that filters both `dl_iterate_phdr` and reads of maps
+//!   consistently leaves nothing to compare
+
+use std::ffi::CStr;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use crate::detectors::maps_anomaly::{parse_regions, Region};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// `PF_X`
+const PF_X: u32 = 1;
+
+/// Findings listed per report; the rest are counted
+const MAX_LISTED: usize = 8;
+
+/// One object in the loader's list
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoaderObject {
+    /// `dlpi_name`; empty for the main program
+    pub name: String,
+    /// Runtime ranges of its `PT_LOAD` segments
+    pub segments: Vec<Range<u64>>,
+    /// Start of its first executable segment
+    pub code: Option<u64>,
+}
+
+unsafe extern "C" fn collect(info: *mut libc::dl_phdr_info, _size: libc::size_t, data: *mut libc::c_void) -> libc::c_int {
+    let objects = &mut *(data as *mut Vec<LoaderObject>);
+    let info = &*info;
+    let name = if info.dlpi_name.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(info.dlpi_name).to_string_lossy().into_owned()
+    };
+    let phdrs = std::slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum as usize);
+    let loads = phdrs.iter().filter(|p| p.p_type == libc::PT_LOAD);
+    let segments = loads.clone()
+        .map(|p| info.dlpi_addr + p.p_vaddr..info.dlpi_addr + p.p_vaddr + p.p_memsz)
+        .collect();
+    let code = loads.clone().find(|p| p.p_flags & PF_X != 0).map(|p| info.dlpi_addr + p.p_vaddr);
+    objects.push(LoaderObject { name, segments, code });
+    0
+}
+
+/// Every object in the dynamic loader's list
+fn loader_objects() -> Vec<LoaderObject> {
+    let mut objects: Vec<LoaderObject> = Vec::new();
+    // SAFETY: `collect` only dereferences what the loader hands it, and
+    // `objects` outlives the call
+    unsafe {
+        libc::dl_iterate_phdr(Some(collect), &mut objects as *mut Vec<LoaderObject> as *mut libc::c_void);
+    }
+    objects
+}
+
+fn covered(objects: &[LoaderObject], region: &Region) -> bool {
+    objects.iter().flat_map(|o| &o.segments).any(|s| s.start < region.end && region.start < s.end)
+}
+
+/// Executable file mappings that no listed object's segments cover
+pub fn unlisted_mappings<'a>(regions: &'a [Region], objects: &[LoaderObject]) -> Vec<&'a Region> {
+    regions.iter()
+        .filter(|r| r.perms.contains('x') && r.path.starts_with('/'))
+        .filter(|r| !covered(objects, r))
+        .collect()
+}
+
+/// Readable anonymous mappings that no listed object covers and for which
+/// `starts_with_elf` holds
+pub fn anonymous_images<'a>(regions: &'a [Region], objects: &[LoaderObject], starts_with_elf: impl Fn(&Region) -> bool) -> Vec<&'a Region> {
+    regions.iter()
+        .filter(|r| r.path.is_empty() && r.perms.starts_with('r'))
+        .filter(|r| !covered(objects, r))
+        .filter(|r| starts_with_elf(r))
+        .collect()
+}
+
+/// Listed objects whose code is not mapped from the file they are named
+/// after, as (name, mapped path or `None` if not mapped at all). `exe` is
+/// the main program's path, which the loader lists without a name.
+pub fn misnamed_objects(objects: &[LoaderObject], regions: &[Region], exe: &str) -> Vec<(String, Option<String>)> {
+    objects.iter()
+        .filter_map(|o| {
+            let code = o.code?;
+            let name = if o.name.is_empty() { exe.to_string() } else { o.name.clone() };
+            let Some(region) = regions.iter().find(|r| (r.start..r.end).contains(&code)) else {
+                return Some((name, None));
+            };
+            // The vDSO and other objects without a path have nothing to compare
+            if !name.starts_with('/') {
+                return None;
+            }
+            let mapped = region.path.strip_suffix(" (deleted)").unwrap_or(&region.path);
+            let canonical = fs::canonicalize(Path::new(&name)).map(|p| p.to_string_lossy().into_owned()).unwrap_or_else(|_| name.clone());
+            (mapped != name && mapped != canonical).then(|| (name, Some(mapped.to_string())))
+        })
+        .collect()
+}
+
+fn has_elf_magic(region: &Region) -> bool {
+    // SAFETY: the region is a readable mapping of at least one page, and we
+    // read its first four bytes
+    let magic = unsafe { std::slice::from_raw_parts(region.start as *const u8, 4) };
+    magic == b"\x7fELF"
+}
+
+fn listing<T>(items: &[T], describe: impl Fn(&T) -> String) -> String {
+    let listed: Vec<String> = items.iter().take(MAX_LISTED).map(describe).collect();
+    let more = items.len().saturating_sub(MAX_LISTED);
+    format!("{}{}", listed.join(", "), if more > 0 { reason!(" and {} more", more) } else { String::new() })
+}
+
+/// Main entry point for the loader / maps cross-view check
+pub fn check_module_xview(engine: &mut DecisionEngine) {
+    let objects = loader_objects();
+    let regions = match fs::read_to_string("/proc/self/maps") {
+        Ok(maps) => parse_regions(&maps),
+        Err(e) => {
+            engine.record_diagnostic("module_xview", &reason!("/proc/self/maps unreadable: {}", e));
+            return;
+        }
+    };
+    if objects.is_empty() {
+        engine.record_diagnostic("module_xview", &reason!("dl_iterate_phdr returned no objects"));
+        return;
+    }
+    let exe = std::env::current_exe().map(|p| p.to_string_lossy().into_owned()).unwrap_or_default();
+
+    let unlisted = unlisted_mappings(&regions, &objects);
+    let images = anonymous_images(&regions, &objects, has_elf_magic);
+    let misnamed = misnamed_objects(&objects, &regions, &exe);
+    let (unmapped, renamed): (Vec<_>, Vec<_>) = misnamed.into_iter().partition(|(_, mapped)| mapped.is_none());
+    diag!("[MODULE_XVIEW] {} loader objects, {} mappings: {} unlisted, {} anonymous images, {} unmapped, {} renamed",
+          objects.len(), regions.len(), unlisted.len(), images.len(), unmapped.len(), renamed.len());
+    engine.record_feature("module_xview_mismatches", (unlisted.len() + images.len() + unmapped.len() + renamed.len()) as f64);
+
+    let region = |r: &&Region| format!("{:#x}-{:#x} {}", r.start, r.end, r.path);
+    if !unlisted.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::CrossView,
+            40,
+            0.8,
+            &reason!("Executable file mappings unknown to the dynamic loader (manually mapped): {}", listing(&unlisted, region))
+        );
+    }
+    if !images.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::CrossView,
+            45,
+            0.85,
+            &reason!("Anonymous mappings holding ELF images unknown to the dynamic loader (reflectively loaded): {}",
+                     listing(&images, |r| format!("{:#x}-{:#x} {}", r.start, r.end, r.perms)))
+        );
+    }
+    if !unmapped.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::CrossView,
+            45,
+            0.85,
+            &reason!("Loaded objects whose code is missing from /proc/self/maps: {}", listing(&unmapped, |(name, _)| name.clone()))
+        );
+    }
+    if !renamed.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::CrossView,
+            35,
+            0.75,
+            &reason!("Loaded objects mapped from a different file than their name: {}",
+                     listing(&renamed, |(name, mapped)| format!("{} is {}", name, mapped.as_deref().unwrap_or("?"))))
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(start: u64, end: u64, perms: &str, path: &str) -> Region {
+        Region { start, end, perms: perms.to_string(), inode: 0, path: path.to_string() }
+    }
+
+    /// Object with a single, executable segment
+    fn object(name: &str, start: u64, end: u64) -> LoaderObject {
+        LoaderObject { name: name.to_string(), segments: std::iter::once(start..end).collect(), code: Some(start) }
+    }
+
+    #[test]
+    fn test_cross_view() {
+        let objects = vec![
+            LoaderObject { name: String::new(), segments: vec![0x1000..0x1800, 0x2000..0x2400], code: Some(0x2000) },
+            object("/lib/libc.so.6", 0x10000, 0x12000),
+            object("/lib/libfake.so", 0x30000, 0x31000),
+        ];
+        let regions = vec![
+            region(0x1000, 0x2000, "r--p", "/bin/app"),
+            region(0x2000, 0x3000, "r-xp", "/bin/app"),
+            region(0x10000, 0x12000, "r-xp", "/lib/libc.so.6"),
+            region(0x20000, 0x21000, "r-xp", "/tmp/agent.so"),
+            region(0x40000, 0x41000, "rw-p", ""),
+            region(0x50000, 0x52000, "r-xp", ""),
+        ];
+        let unlisted: Vec<u64> = unlisted_mappings(&regions, &objects).iter().map(|r| r.start).collect();
+        assert_eq!(unlisted, vec![0x20000]);
+        let images: Vec<u64> = anonymous_images(&regions, &objects, |r| r.start == 0x50000).iter().map(|r| r.start).collect();
+        assert_eq!(images, vec![0x50000]);
+        assert_eq!(misnamed_objects(&objects, &regions, "/bin/app"), vec![("/lib/libfake.so".to_string(), None)]);
+
+        let renamed = vec![object("/lib/libc.so.6", 0x20000, 0x21000)];
+        assert_eq!(misnamed_objects(&renamed, &regions, "/bin/app"),
+                   vec![("/lib/libc.so.6".to_string(), Some("/tmp/agent.so".to_string()))]);
+    }
+
+    #[test]
+    fn test_own_process_consistent() {
+        let objects = loader_objects();
+        let regions = parse_regions(&fs::read_to_string("/proc/self/maps").unwrap());
+        let exe = std::env::current_exe().unwrap().to_string_lossy().into_owned();
+        assert!(unlisted_mappings(&regions, &objects).is_empty());
+        assert!(misnamed_objects(&objects, &regions, &exe).is_empty());
+    }
+}
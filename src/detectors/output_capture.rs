@@ -0,0 +1,245 @@
+//! Analyst-Owned Output Capture Detection
+//!
+//! # Overview
+//!
+//! Our diagnostics narrate every check. When stdout/stderr are being
+//! recorded for later study, that narration is a walkthrough for the
+//! analyst. We resolve where fds 1 and 2 actually lead and who holds the
+//! other end:
+//!
+//! | fd target        | Peer resolution                                    |
+//! |------------------|----------------------------------------------------|
+//! | `pipe:[ino]`     | Other processes holding the same pipe inode        |
+//! | `socket:[ino]`   | `SO_PEERCRED` on the socket                        |
+//! | `/dev/pts/N`     | Processes holding `/dev/ptmx` with `tty-index: N`  |
+//! | Regular file     | Path only                                          |
+//!
+//! Peers are matched against known capture tools (`script`, `tee`,
+//! `asciinema`, `strace`/`ltrace`, pytest and CI runners). When capture is
+//! detected `main` switches to silent output for the rest of the run.
+//!
+//! # Why This Fails
+//!
+//! - Processes of other users (or outside our PID namespace) are invisible
+//! - A renamed capture tool, or a terminal emulator logging scrollback,
+//!   looks like an ordinary terminal
+//! - CI harnesses also run legitimate test suites, so evidence weight is low
+
+use std::fs;
+use std::os::unix::io::RawFd;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// (pattern, tool) - matched against a peer's comm, or its argv[0..] basename
+const CAPTURE_TOOLS: &[(&str, &str)] = &[
+    ("script", "script"),
+    ("tee", "tee"),
+    ("asciinema", "asciinema"),
+    ("ttyrec", "ttyrec"),
+    ("expect", "expect"),
+    ("unbuffer", "unbuffer"),
+    ("strace", "strace"),
+    ("ltrace", "ltrace"),
+    ("pytest", "pytest"),
+    ("py.test", "pytest"),
+    ("gitlab-runner", described!("GitLab runner")),
+    ("buildkite-agent", described!("Buildkite agent")),
+    ("Runner.Worker", described!("GitHub Actions runner")),
+];
+
+/// What a standard descriptor points at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StdTarget {
+    Pipe(u64),
+    Socket(u64),
+    /// `/dev/pts/<index>`
+    Pty(u32),
+    File(String),
+    Other(String),
+}
+
+/// A process on the other end of one of our descriptors
+#[derive(Debug, Clone)]
+pub struct Peer {
+    pub pid: u32,
+    pub comm: String,
+    pub cmdline: Vec<String>,
+}
+
+/// Classify a `/proc/self/fd/N` link target
+pub fn parse_fd_target(link: &str) -> StdTarget {
+    let inode = |prefix: &str| {
+        link.strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix(']'))
+            .and_then(|n| n.parse().ok())
+    };
+    if let Some(ino) = inode("pipe:[") {
+        StdTarget::Pipe(ino)
+    } else if let Some(ino) = inode("socket:[") {
+        StdTarget::Socket(ino)
+    } else if let Some(idx) = link.strip_prefix("/dev/pts/").and_then(|n| n.parse().ok()) {
+        StdTarget::Pty(idx)
+    } else if link.starts_with('/') {
+        StdTarget::File(link.to_string())
+    } else {
+        StdTarget::Other(link.to_string())
+    }
+}
+
+/// `tty-index` from a `/proc/<pid>/fdinfo/<fd>` of a ptmx descriptor
+pub fn parse_tty_index(fdinfo: &str) -> Option<u32> {
+    fdinfo.lines()
+        .find_map(|line| line.strip_prefix("tty-index:"))
+        .and_then(|v| v.trim().parse().ok())
+}
+
+/// Name of the capture tool `peer` is, if any
+pub fn capture_tool(peer: &Peer) -> Option<&'static str> {
+    let basename = |s: &str| s.rsplit('/').next().unwrap_or(s).to_string();
+    // Interpreted tools (pytest, asciinema) show up as python in comm
+    let names: Vec<String> = std::iter::once(peer.comm.clone())
+        .chain(peer.cmdline.iter().take(3).map(|a| basename(a)))
+        .collect();
+    CAPTURE_TOOLS.iter()
+        .find(|(pattern, _)| names.iter().any(|n| n == pattern || n.starts_with(&format!("{}-", pattern))))
+        .map(|(_, tool)| *tool)
+}
+
+/// comm and command line of `pid`
+pub fn read_peer(pid: u32) -> Peer {
+    let comm = fs::read_to_string(format!("/proc/{}/comm", pid)).unwrap_or_default().trim().to_string();
+    let cmdline = fs::read(format!("/proc/{}/cmdline", pid)).unwrap_or_default()
+        .split(|&b| b == 0)
+        .filter(|a| !a.is_empty())
+        .map(|a| String::from_utf8_lossy(a).into_owned())
+        .collect();
+    Peer { pid, comm, cmdline }
+}
+
+/// Every other process with an fd matching `pred(pid, fd, link)`
+pub fn processes_holding(pred: impl Fn(u32, &str, &str) -> bool) -> Vec<u32> {
+    let own = std::process::id();
+    let mut pids = Vec::new();
+    let Ok(proc_dir) = fs::read_dir("/proc") else { return pids };
+    for entry in proc_dir.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) else { continue };
+        if pid == own {
+            continue;
+        }
+        let Ok(fds) = fs::read_dir(format!("/proc/{}/fd", pid)) else { continue };
+        let held = fds.flatten().any(|fd| {
+            let name = fd.file_name().to_string_lossy().into_owned();
+            fs::read_link(fd.path())
+                .map(|link| pred(pid, &name, &link.to_string_lossy()))
+                .unwrap_or(false)
+        });
+        if held {
+            pids.push(pid);
+        }
+    }
+    pids
+}
+
+/// PID on the other end of a connected socket (`SO_PEERCRED`)
+pub fn socket_peer(fd: RawFd) -> Option<u32> {
+    let mut cred: libc::ucred = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: SO_PEERCRED fills a ucred of the length we pass
+    let ret = unsafe {
+        libc::getsockopt(fd, libc::SOL_SOCKET, libc::SO_PEERCRED,
+                         &mut cred as *mut _ as *mut libc::c_void, &mut len)
+    };
+    if ret == 0 && cred.pid > 0 { Some(cred.pid as u32) } else { None }
+}
+
+/// Processes on the other end of `fd`
+fn peers_of(fd: RawFd, target: &StdTarget, link: &str) -> Vec<u32> {
+    match target {
+        StdTarget::Pipe(_) => processes_holding(|_, _, l| l == link),
+        StdTarget::Socket(_) => socket_peer(fd).into_iter().collect(),
+        StdTarget::Pty(index) => processes_holding(|pid, fd_name, l| {
+            (l == "/dev/ptmx" || l == "/dev/pts/ptmx")
+                && fs::read_to_string(format!("/proc/{}/fdinfo/{}", pid, fd_name))
+                    .ok()
+                    .and_then(|info| parse_tty_index(&info))
+                    == Some(*index)
+        }),
+        StdTarget::File(_) | StdTarget::Other(_) => Vec::new(),
+    }
+}
+
+/// Capture tools found on stdout/stderr, as descriptions
+pub fn detect_capture() -> Vec<String> {
+    let mut findings = Vec::new();
+    let mut seen = Vec::new();
+
+    for (fd, name) in [(libc::STDOUT_FILENO, "stdout"), (libc::STDERR_FILENO, "stderr")] {
+        let Ok(link) = fs::read_link(format!("/proc/self/fd/{}", fd)) else { continue };
+        let link = link.to_string_lossy().into_owned();
+        let target = parse_fd_target(&link);
+        diag!("[CAPTURE] {} -> {}", name, link);
+
+        for pid in peers_of(fd, &target, &link) {
+            if seen.contains(&pid) {
+                continue;
+            }
+            seen.push(pid);
+            let peer = read_peer(pid);
+            if let Some(tool) = capture_tool(&peer) {
+                findings.push(reason!("{} captured by {} (PID {}: {})", name, tool, peer.pid, peer.cmdline.join(" ")));
+            }
+        }
+    }
+
+    findings
+}
+
+/// Main entry point for output-capture detection.
+/// Returns `true` if our output is being recorded, so the caller can go quiet.
+pub fn check_output_capture(engine: &mut DecisionEngine) -> bool {
+    let findings = detect_capture();
+    engine.record_flag("output_captured", !findings.is_empty());
+    if findings.is_empty() {
+        return false;
+    }
+
+    engine.report_with_confidence(
+        DetectionSource::OutputCapture,
+        15,
+        0.5,
+        &reason!("Diagnostic output is being recorded: {}", findings.join("; "))
+    );
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fd_target() {
+        assert_eq!(parse_fd_target("pipe:[12345]"), StdTarget::Pipe(12345));
+        assert_eq!(parse_fd_target("socket:[7]"), StdTarget::Socket(7));
+        assert_eq!(parse_fd_target("/dev/pts/3"), StdTarget::Pty(3));
+        assert_eq!(parse_fd_target("/tmp/out.log"), StdTarget::File("/tmp/out.log".to_string()));
+        assert_eq!(parse_fd_target("anon_inode:[eventfd]"), StdTarget::Other("anon_inode:[eventfd]".to_string()));
+    }
+
+    #[test]
+    fn test_parse_tty_index() {
+        assert_eq!(parse_tty_index("pos:\t0\nflags:\t0100002\nmnt_id:\t25\ntty-index:\t4\n"), Some(4));
+        assert_eq!(parse_tty_index("pos:\t0\n"), None);
+    }
+
+    #[test]
+    fn test_capture_tool() {
+        let peer = |comm: &str, cmd: &[&str]| Peer {
+            pid: 1,
+            comm: comm.to_string(),
+            cmdline: cmd.iter().map(|s| s.to_string()).collect(),
+        };
+        assert_eq!(capture_tool(&peer("tee", &["tee", "/tmp/analysis/run.log"])), Some("tee"));
+        assert_eq!(capture_tool(&peer("python3", &["/usr/bin/python3", "/usr/bin/pytest", "-q"])), Some("pytest"));
+        assert_eq!(capture_tool(&peer("bash", &["-bash"])), None);
+        assert_eq!(capture_tool(&peer("sshd", &["sshd: user@pts/0"])), None);
+    }
+}
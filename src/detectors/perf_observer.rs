@@ -0,0 +1,340 @@
+//! perf / bpftrace Observer Detection
+//!
+//! # Overview
+//!
+//! `profiler.rs` catches sampling through its interrupts. This module looks
+//! for the observers themselves:
+//!
+//! - **PMU contention**: we open a pinned CPU-cycles counter on ourselves
+//!   with `perf_event_open`. `EBUSY` means another session holds the PMU
+//!   exclusively; a pinned counter that reads back nothing went into error
+//!   state because other events took every counter; `time_running` short
+//!   of `time_enabled` means the PMU is being multiplexed with someone.
+//! - **perf_event_paranoid**: every distribution ships 2 or higher.
+//!   Profiling other users' processes or the whole system needs 0 or -1,
+//!   so a lowered value says someone set this machine up for it.
+//! - **Observer processes**: `perf`, `bpftrace`, `trace-cmd` and BCC tools
+//!   that are our parent or carry our PID in their command line
+//!   (`perf record -p`, `bpftrace -p`, `/pid == N/` filters).
+//! - **perf_event holders** ([`check_perf_fd_holders`]): every process
+//!   whose fd table (`/proc/<pid>/fd`, where permitted) holds
+//!   `anon_inode:[perf_event]` descriptors, whatever its name. The kernel
+//!   does not put an event's target in `fdinfo`, so a holder is tied to
+//!   us when it is our tracer or an ancestor (rr, `perf stat ./app`) or
+//!   names our PID; any other holder may be counting every CPU,
+//!   including ours.
+//!
+//! # Why This Fails
+//!
+//! - Inside most VMs there is no PMU, and `perf_event_open` fails with
+//!   ENOENT before contention can show
+//! - A system-wide session (`perf record -a`) never names our PID
+//! - Process names and command lines are trivially changed; a process in
+//!   another PID namespace is not visible at all
+//! - The paranoid sysctl says nothing about whether anyone is profiling now
+
+use std::fs;
+use std::time::{Duration, Instant};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// `PERF_TYPE_HARDWARE` / `PERF_COUNT_HW_CPU_CYCLES`
+pub const PERF_TYPE_HARDWARE: u32 = 0;
+const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+
+/// `PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_TOTAL_TIME_RUNNING`
+pub const READ_FORMAT_TIMES: u64 = 0b11;
+
+/// `perf_event_attr` flag bits: disabled, pinned, exclude_kernel, exclude_hv
+pub const ATTR_DISABLED: u64 = 1 << 0;
+pub const ATTR_PINNED: u64 = 1 << 2;
+pub const ATTR_EXCLUDE_KERNEL: u64 = 1 << 5;
+pub const ATTR_EXCLUDE_HV: u64 = 1 << 6;
+
+/// `PERF_EVENT_IOC_ENABLE` / `_DISABLE`
+pub const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+pub const PERF_EVENT_IOC_DISABLE: libc::c_ulong = 0x2401;
+
+/// Time the counter runs
+const COUNT_WINDOW: Duration = Duration::from_millis(20);
+
+/// Fraction of enabled time a lone counter must run to count as unshared
+const MIN_RUNNING_RATIO: f64 = 0.95;
+
+/// Ancestors checked for perf_event descriptors
+const MAX_ANCESTORS: usize = 6;
+
+/// Observer tools, and the source their evidence goes to
+const OBSERVERS: &[(&str, DetectionSource)] = &[
+    ("perf", DetectionSource::SamplingProfiler),
+    ("bpftrace", DetectionSource::KernelProbe),
+    ("trace-cmd", DetectionSource::KernelProbe),
+];
+
+/// `perf_event_attr` up to `config1` (PERF_ATTR_SIZE_VER0, 64 bytes)
+#[repr(C)]
+#[derive(Default)]
+pub struct PerfEventAttr {
+    pub type_: u32,
+    pub size: u32,
+    pub config: u64,
+    pub sample_period: u64,
+    pub sample_type: u64,
+    pub read_format: u64,
+    pub flags: u64,
+    pub wakeup_events: u32,
+    pub bp_type: u32,
+    pub config1: u64,
+}
+
+/// What the self-counter probe saw
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PmuProbe {
+    /// No PMU or not permitted (errno)
+    Unavailable(i32),
+    /// Another session holds the PMU exclusively
+    Busy,
+    /// Pinned counter could not be scheduled (read returned no data)
+    ErrorState,
+    /// Counter ran for `running / enabled` of the window
+    Counted { enabled: u64, running: u64 },
+}
+
+/// Open, run and read a pinned cycles counter on ourselves
+fn probe_pmu() -> PmuProbe {
+    let attr = PerfEventAttr {
+        type_: PERF_TYPE_HARDWARE,
+        size: std::mem::size_of::<PerfEventAttr>() as u32,
+        config: PERF_COUNT_HW_CPU_CYCLES,
+        read_format: READ_FORMAT_TIMES,
+        flags: ATTR_DISABLED | ATTR_PINNED | ATTR_EXCLUDE_KERNEL | ATTR_EXCLUDE_HV,
+        ..Default::default()
+    };
+    // SAFETY: `attr` is a valid VER0 attribute; pid 0 / cpu -1 = this thread, any CPU
+    let fd = unsafe { libc::syscall(libc::SYS_perf_event_open, &attr as *const PerfEventAttr, 0, -1, -1, 0) } as libc::c_int;
+    if fd < 0 {
+        let errno = std::io::Error::last_os_error().raw_os_error().unwrap_or(0);
+        return if errno == libc::EBUSY { PmuProbe::Busy } else { PmuProbe::Unavailable(errno) };
+    }
+
+    let mut values = [0u64; 3];
+    // SAFETY: `fd` is our perf event; the read buffer holds value/enabled/running
+    let n = unsafe {
+        libc::ioctl(fd, PERF_EVENT_IOC_ENABLE, 0);
+        let start = Instant::now();
+        while start.elapsed() < COUNT_WINDOW {
+            std::hint::black_box(start.elapsed());
+        }
+        libc::ioctl(fd, PERF_EVENT_IOC_DISABLE, 0);
+        let n = libc::read(fd, values.as_mut_ptr() as *mut libc::c_void, std::mem::size_of_val(&values));
+        libc::close(fd);
+        n
+    };
+    if n <= 0 {
+        PmuProbe::ErrorState
+    } else {
+        PmuProbe::Counted { enabled: values[1], running: values[2] }
+    }
+}
+
+/// Whether `cmdline` (NUL-separated) targets `pid`: a whole-word PID
+/// argument or a `pid == N` style filter
+pub fn cmdline_targets(cmdline: &[u8], pid: u32) -> bool {
+    let pid = pid.to_string();
+    String::from_utf8_lossy(cmdline)
+        .split(|c: char| !c.is_ascii_digit())
+        .any(|token| token == pid)
+}
+
+/// Observer entry for a process name
+fn observer(comm: &str) -> Option<DetectionSource> {
+    let comm = comm.trim();
+    // BCC tools are installed as e.g. `funccount-bpfcc`
+    if comm.ends_with("-bpfcc") {
+        return Some(DetectionSource::KernelProbe);
+    }
+    OBSERVERS.iter().find(|(name, _)| *name == comm).map(|(_, source)| *source)
+}
+
+/// Observer processes aimed at us: (pid, comm, source, is_parent)
+fn observer_processes() -> Vec<(u32, String, DetectionSource, bool)> {
+    let me = std::process::id();
+    let parent = std::os::unix::process::parent_id();
+    let Ok(entries) = fs::read_dir("/proc") else { return Vec::new() };
+    entries.flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|pid| *pid != me)
+        .filter_map(|pid| {
+            let comm = fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+            let source = observer(&comm)?;
+            let is_parent = pid == parent;
+            let cmdline = fs::read(format!("/proc/{}/cmdline", pid)).unwrap_or_default();
+            (is_parent || cmdline_targets(&cmdline, me)).then(|| (pid, comm.trim().to_string(), source, is_parent))
+        })
+        .collect()
+}
+
+/// Number of `perf_event` descriptors in a process's fd table, or None
+/// if it cannot be read
+fn perf_event_fds(pid: u32) -> Option<usize> {
+    let entries = fs::read_dir(format!("/proc/{}/fd", pid)).ok()?;
+    Some(entries.flatten()
+        .filter(|entry| fs::read_link(entry.path()).is_ok_and(|target| is_perf_event_link(&target.to_string_lossy())))
+        .count())
+}
+
+/// Whether an fd symlink target is a perf event
+pub fn is_perf_event_link(target: &str) -> bool {
+    target == "anon_inode:[perf_event]"
+}
+
+/// Our ancestors, parent first
+fn ancestors() -> Vec<u32> {
+    let parent_of = |pid: u32| -> Option<u32> {
+        let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+        status.lines().find_map(|l| l.strip_prefix("PPid:"))?.trim().parse().ok()
+    };
+    let mut chain = Vec::new();
+    let mut pid = std::os::unix::process::parent_id();
+    while pid > 1 && chain.len() < MAX_ANCESTORS {
+        chain.push(pid);
+        pid = parent_of(pid).unwrap_or(0);
+    }
+    chain
+}
+
+/// Another process holding perf_event descriptors
+#[derive(Debug)]
+struct PerfHolder {
+    pid: u32,
+    comm: String,
+    fds: usize,
+    /// How it is tied to us, if it is
+    relation: Option<&'static str>,
+}
+
+/// perf_event holders and how many fd tables could not be read
+fn perf_fd_holders() -> (Vec<PerfHolder>, usize) {
+    let me = std::process::id();
+    let tracer = crate::engine::signal_compat::get_tracer_pid();
+    let chain = ancestors();
+    let Ok(entries) = fs::read_dir("/proc") else { return (Vec::new(), 0) };
+    let mut denied = 0;
+    let holders = entries.flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|pid| *pid != me)
+        .filter_map(|pid| {
+            let Some(fds) = perf_event_fds(pid) else {
+                denied += 1;
+                return None;
+            };
+            if fds == 0 {
+                return None;
+            }
+            let comm = fs::read_to_string(format!("/proc/{}/comm", pid)).unwrap_or_default().trim().to_string();
+            let cmdline = fs::read(format!("/proc/{}/cmdline", pid)).unwrap_or_default();
+            let relation = if pid == tracer {
+                Some(described!("is our tracer"))
+            } else if chain.contains(&pid) {
+                Some(described!("is our ancestor"))
+            } else if cmdline_targets(&cmdline, me) {
+                Some(described!("names our PID"))
+            } else {
+                None
+            };
+            Some(PerfHolder { pid, comm, fds, relation })
+        })
+        .collect();
+    (holders, denied)
+}
+
+/// Processes holding perf_event descriptors, tied to us where possible
+pub fn check_perf_fd_holders(engine: &mut DecisionEngine) {
+    let (holders, denied) = perf_fd_holders();
+    diag!("[PERF] perf_event holders={:?} unreadable_fd_tables={}", holders, denied);
+    engine.record_feature("perf_event_holders", holders.len() as f64);
+    for PerfHolder { pid, comm, fds, relation } in holders {
+        let source = if comm == "rr" { DetectionSource::RecordReplay } else { DetectionSource::SamplingProfiler };
+        match relation {
+            Some(how) => engine.report_with_confidence(source, 40, 0.85,
+                &reason!("{} (PID {}) {} and holds {} perf_event fd(s): counting or sampling this process", comm, pid, how, fds)),
+            None => engine.report_with_confidence(source, 5, 0.3,
+                &reason!("{} (PID {}) holds {} perf_event fd(s), possibly counting every CPU", comm, pid, fds)),
+        }
+    }
+}
+
+/// Main entry point for the perf / bpftrace observer check
+pub fn check_perf_observers(engine: &mut DecisionEngine) {
+    let probe = probe_pmu();
+    diag!("[PERF] Self-counter probe: {:?}", probe);
+    if matches!(probe, PmuProbe::Unavailable(_)) && !engine.kernel_posture().unprivileged_perf() {
+        engine.record_diagnostic("perf_observer", &reason!("perf_event_paranoid forbids perf for unprivileged users; PMU contention not checked"));
+    }
+    match probe {
+        PmuProbe::Busy => engine.report_with_confidence(DetectionSource::SamplingProfiler, 25, 0.6,
+            &reason!("perf_event_open on ourselves returned EBUSY: another session holds the PMU exclusively")),
+        PmuProbe::ErrorState => engine.report_with_confidence(DetectionSource::SamplingProfiler, 20, 0.5,
+            &reason!("Pinned cycles counter could not be scheduled: every PMU counter is taken")),
+        PmuProbe::Counted { enabled, running } if enabled > 0 && (running as f64) < enabled as f64 * MIN_RUNNING_RATIO => {
+            engine.record_feature("pmu_running_ratio", running as f64 / enabled as f64);
+            engine.report_with_confidence(DetectionSource::SamplingProfiler, 15, 0.4,
+                &reason!("Lone cycles counter ran {:.0}% of the time: PMU multiplexed with other sessions",
+                         running as f64 * 100.0 / enabled as f64));
+        }
+        PmuProbe::Counted { enabled, running } if enabled > 0 => {
+            engine.record_feature("pmu_running_ratio", running as f64 / enabled as f64);
+        }
+        _ => {}
+    }
+
+    if let Some(paranoid) = engine.kernel_posture().perf_event_paranoid {
+        engine.record_feature("perf_event_paranoid", paranoid as f64);
+        if paranoid <= 0 {
+            engine.report_with_confidence(DetectionSource::SamplingProfiler, 5, 0.3,
+                &reason!("perf_event_paranoid lowered to {} (distributions ship 2 or higher)", paranoid));
+        }
+    }
+
+    let observers = observer_processes();
+    engine.record_feature("perf_observer_processes", observers.len() as f64);
+    for (pid, comm, source, is_parent) in observers {
+        let how = if is_parent {
+            described!("launched us")
+        } else {
+            described!("names our PID")
+        };
+        engine.report_with_confidence(source, 40, 0.9, &reason!("{} (PID {}) {}: observing this process", comm, pid, how));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attr_layout() {
+        assert_eq!(std::mem::size_of::<PerfEventAttr>(), 64);
+    }
+
+    #[test]
+    fn test_cmdline_targets() {
+        assert!(cmdline_targets(b"perf\0record\0-p\x001234\0", 1234));
+        assert!(cmdline_targets(b"bpftrace\0-e\0uprobe:/bin/x:f /pid == 1234/ { @[ustack] = count(); }\0", 1234));
+        assert!(!cmdline_targets(b"perf\0record\0-p\x0012345\0", 1234));
+        assert!(!cmdline_targets(b"perf\0top\0", 1234));
+    }
+
+    #[test]
+    fn test_perf_event_link() {
+        assert!(is_perf_event_link("anon_inode:[perf_event]"));
+        assert!(!is_perf_event_link("anon_inode:[eventfd]"));
+        assert_eq!(perf_event_fds(std::process::id()), Some(0));
+    }
+
+    #[test]
+    fn test_observer_names() {
+        assert_eq!(observer("perf\n"), Some(DetectionSource::SamplingProfiler));
+        assert_eq!(observer("funccount-bpfcc"), Some(DetectionSource::KernelProbe));
+        assert_eq!(observer("bash"), None);
+    }
+}
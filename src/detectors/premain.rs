@@ -0,0 +1,246 @@
+//! Pre-Main Detection (ELF Constructor)
+//!
+//! # Overview
+//!
+//! Everything else in the framework runs from `main()`. Workflows that act
+//! at process entry - gdb `starti` / `break _start`, `LD_PRELOAD` and
+//! `LD_AUDIT` injectors - are done with their setup by then, and the
+//! injector has had its own constructor to scrub the evidence.
+//!
+//! A function pointer in `.init_array` is called by the dynamic loader
+//! right after the preloaded libraries' constructors and before `main()`
+//! (and before the Rust runtime is set up). From there we take a minimal
+//! snapshot:
+//!
+//! - **TracerPid** from /proc/self/status
+//! - **`LD_PRELOAD` / `LD_AUDIT`** in the `envp` the loader hands the
+//!   constructor (the initial block, not what a later `unsetenv` leaves)
+//! - **ASLR disabled**: the `ADDR_NO_RANDOMIZE` personality bit, which gdb
+//!   sets for every inferior it starts by default, and the
+//!   `kernel.randomize_va_space` sysctl
+//! - **Open descriptors** above stdio: nothing of ours is open yet, so
+//!   these were inherited or opened by a preloaded library
+//!   (`fd_table.rs` inspects them)
+//! - **Where `argv` and `envp` point**: the addresses of the first
+//!   argument and environment strings, before anything can move them
+//!   (`cmdline_view.rs` checks them against the kernel's view)
+//!
+//! The constructor only makes raw libc calls and stores the results in
+//! atomics; [`ingest_premain`] turns them into evidence once the engine
+//! exists. A tracer present before `main()` but gone by the time of ingest
+//! means a debugger detached - or started hiding - in between.
+//!
+//! # Why This Fails
+//!
+//! - A preloaded library's constructor runs before ours and can unset the
+//!   variables in place or fake /proc/self/status with a `read` hook
+//! - `set disable-randomization off` in gdb clears the personality bit
+//! - A debugger that attaches after `main()` is not seen here (the ptrace
+//!   detectors cover it)
+//! - `LD_PRELOAD` is legitimately used by memory allocators and profilers
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::threads::parse_task_status;
+
+/// `ADDR_NO_RANDOMIZE` from <linux/personality.h>
+const ADDR_NO_RANDOMIZE: libc::c_int = 0x0040000;
+
+/// Snapshot flags
+const FLAG_PRELOAD: u32 = 1 << 0;
+const FLAG_AUDIT: u32 = 1 << 1;
+const FLAG_NO_RANDOMIZE: u32 = 1 << 2;
+const FLAG_ASLR_SYSCTL_OFF: u32 = 1 << 3;
+
+/// Descriptors covered by the pre-main fd snapshot
+const SNAPSHOT_FDS: usize = 256;
+
+/// Constructor-to-ingest gap above which someone probably stopped in between
+const MAX_GAP_NS: u64 = 1_000_000_000;
+
+static RAN: AtomicBool = AtomicBool::new(false);
+static TRACER_PID: AtomicU32 = AtomicU32::new(0);
+static FLAGS: AtomicU32 = AtomicU32::new(0);
+static RAN_AT_NS: AtomicU64 = AtomicU64::new(0);
+static ARGV0_AT: AtomicUsize = AtomicUsize::new(0);
+static ENVP0_AT: AtomicUsize = AtomicUsize::new(0);
+static OPEN_FDS: [AtomicU64; SNAPSHOT_FDS / 64] = [const { AtomicU64::new(0) }; SNAPSHOT_FDS / 64];
+
+#[used]
+#[link_section = ".init_array"]
+static PREMAIN_CONSTRUCTOR: extern "C" fn(libc::c_int, *const *const libc::c_char, *const *const libc::c_char) = premain;
+
+fn monotonic_ns() -> u64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` is a valid out-pointer
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+/// Read up to `buf.len()` bytes of a NUL-terminated `path` with raw libc calls
+fn read_raw(path: &[u8], buf: &mut [u8]) -> usize {
+    // SAFETY: `path` is NUL-terminated and `buf` is writable for its length
+    unsafe {
+        let fd = libc::open(path.as_ptr() as *const libc::c_char, libc::O_RDONLY | libc::O_CLOEXEC);
+        if fd < 0 {
+            return 0;
+        }
+        let n = libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len());
+        libc::close(fd);
+        n.max(0) as usize
+    }
+}
+
+/// Loader-relevant flags for one `NAME=value` environment entry
+pub fn env_flags(entry: &[u8]) -> u32 {
+    let non_empty = |prefix: &[u8]| entry.len() > prefix.len() && entry.starts_with(prefix);
+    if non_empty(b"LD_PRELOAD=") {
+        FLAG_PRELOAD
+    } else if non_empty(b"LD_AUDIT=") {
+        FLAG_AUDIT
+    } else {
+        0
+    }
+}
+
+/// The `.init_array` entry. glibc passes argc/argv/envp to constructors.
+extern "C" fn premain(argc: libc::c_int, argv: *const *const libc::c_char, envp: *const *const libc::c_char) {
+    let mut flags = 0;
+
+    if argc > 0 && !argv.is_null() {
+        // SAFETY: the loader passes `argc` valid entries
+        ARGV0_AT.store(unsafe { *argv } as usize, Ordering::Relaxed);
+    }
+    if !envp.is_null() {
+        // SAFETY: the array is NULL-terminated, so its first entry is readable
+        ENVP0_AT.store(unsafe { *envp } as usize, Ordering::Relaxed);
+    }
+
+    if !envp.is_null() {
+        // SAFETY: the loader passes a NULL-terminated array of C strings
+        unsafe {
+            let mut cursor = envp;
+            while !(*cursor).is_null() {
+                flags |= env_flags(std::ffi::CStr::from_ptr(*cursor).to_bytes());
+                cursor = cursor.add(1);
+            }
+        }
+    }
+
+    // SAFETY: querying the personality (0xffffffff) changes nothing
+    let persona = unsafe { libc::personality(0xffff_ffff) };
+    if persona != -1 && persona & ADDR_NO_RANDOMIZE != 0 {
+        flags |= FLAG_NO_RANDOMIZE;
+    }
+
+    let mut buf = [0u8; 4096];
+    let n = read_raw(b"/proc/sys/kernel/randomize_va_space\0", &mut buf);
+    if n > 0 && buf[0] == b'0' {
+        flags |= FLAG_ASLR_SYSCTL_OFF;
+    }
+
+    let n = read_raw(b"/proc/self/status\0", &mut buf);
+    let tracer = std::str::from_utf8(&buf[..n]).ok()
+        .and_then(parse_task_status)
+        .map_or(0, |s| s.tracer_pid);
+
+    for fd in 3..SNAPSHOT_FDS {
+        // SAFETY: F_GETFD only queries the descriptor flags
+        if unsafe { libc::fcntl(fd as libc::c_int, libc::F_GETFD) } != -1 {
+            OPEN_FDS[fd / 64].fetch_or(1 << (fd % 64), Ordering::Relaxed);
+        }
+    }
+
+    TRACER_PID.store(tracer, Ordering::Relaxed);
+    FLAGS.store(flags, Ordering::Relaxed);
+    RAN_AT_NS.store(monotonic_ns(), Ordering::Relaxed);
+    RAN.store(true, Ordering::Release);
+}
+
+/// Whether the constructor ran (it does not under loaders that skip `.init_array`)
+pub fn ran() -> bool {
+    RAN.load(Ordering::Acquire)
+}
+
+/// Descriptors above stdio that were open before `main()`
+pub fn inherited_fds() -> Vec<i32> {
+    (3..SNAPSHOT_FDS)
+        .filter(|fd| OPEN_FDS[fd / 64].load(Ordering::Relaxed) & (1 << (fd % 64)) != 0)
+        .map(|fd| fd as i32)
+        .collect()
+}
+
+/// Addresses of the `argv[0]` and `envp[0]` strings handed to the
+/// constructor (0 if absent)
+pub fn initial_vectors() -> (usize, usize) {
+    (ARGV0_AT.load(Ordering::Relaxed), ENVP0_AT.load(Ordering::Relaxed))
+}
+
+/// Report the pre-main snapshot. Called once, right after the engine exists.
+pub fn ingest_premain(engine: &mut DecisionEngine) {
+    if !ran() {
+        engine.record_diagnostic("premain", &reason!("constructor did not run; no pre-main snapshot"));
+        return;
+    }
+    let tracer = TRACER_PID.load(Ordering::Relaxed);
+    let flags = FLAGS.load(Ordering::Relaxed);
+    let gap_ns = monotonic_ns().saturating_sub(RAN_AT_NS.load(Ordering::Relaxed));
+    diag!("[PREMAIN] TracerPid={} flags={:#x} constructor->ingest {} ms", tracer, flags, gap_ns / 1_000_000);
+
+    engine.record_flag("premain_traced", tracer != 0);
+    engine.record_flag("premain_preload", flags & (FLAG_PRELOAD | FLAG_AUDIT) != 0);
+    engine.record_flag("premain_aslr_disabled", flags & FLAG_NO_RANDOMIZE != 0);
+
+    if tracer != 0 {
+        let now = std::fs::read_to_string("/proc/self/status").ok().as_deref()
+            .and_then(parse_task_status)
+            .map_or(0, |s| s.tracer_pid);
+        let detail = if now == 0 {
+            reason!("Traced by PID {} before main(), no tracer now: debugger detached or is hiding", tracer)
+        } else {
+            reason!("Traced by PID {} before main(): debugger present from process entry", tracer)
+        };
+        engine.report_with_confidence(DetectionSource::Ptrace, 60, 0.95, &detail);
+    }
+
+    if flags & FLAG_AUDIT != 0 {
+        engine.report_with_confidence(DetectionSource::Integrity, 30, 0.8,
+            &reason!("LD_AUDIT set at process entry: audit library can observe and redirect every symbol binding"));
+    }
+    if flags & FLAG_PRELOAD != 0 {
+        engine.report_with_confidence(DetectionSource::Integrity, 15, 0.5,
+            &reason!("LD_PRELOAD set at process entry: a library was injected before our constructor"));
+    }
+
+    if flags & FLAG_NO_RANDOMIZE != 0 {
+        engine.report_with_confidence(DetectionSource::Ptrace, 25, 0.7,
+            &reason!("ADDR_NO_RANDOMIZE personality at entry: gdb disables ASLR for inferiors it launches"));
+    } else if flags & FLAG_ASLR_SYSCTL_OFF != 0 {
+        diag!("[PREMAIN] ASLR disabled system-wide (randomize_va_space=0)");
+    }
+
+    if gap_ns > MAX_GAP_NS {
+        engine.report_with_confidence(DetectionSource::Timing, 20, 0.5,
+            &reason!("{} ms between the pre-main constructor and main(): stopped during startup", gap_ns / 1_000_000));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_flags() {
+        assert_eq!(env_flags(b"LD_PRELOAD=/tmp/hook.so"), FLAG_PRELOAD);
+        assert_eq!(env_flags(b"LD_AUDIT=/tmp/audit.so"), FLAG_AUDIT);
+        assert_eq!(env_flags(b"LD_PRELOAD="), 0);
+        assert_eq!(env_flags(b"LD_PRELOADX=1"), 0);
+        assert_eq!(env_flags(b"PATH=/bin"), 0);
+    }
+
+    #[test]
+    fn test_constructor_ran() {
+        // The test harness is an ordinary executable with our .init_array entry
+        assert!(ran());
+    }
+}
@@ -13,17 +13,24 @@ use crate::engine::policy::{DecisionEngine, DetectionSource};
 ///   Subsequent signals (like from Trap Flag check) will cause the process to stop and wait for the parent.
 ///   This can cause the application to hang if the parent isn't expecting to be a debugger.
 pub fn check_ptrace(engine: &mut DecisionEngine) {
+    return_probe!();
+    // The late-attach monitor must not mistake our parent for an attaching
+    // debugger, even if it polls between the call and its result
+    crate::engine::signal_compat::expect_traceme();
     let res = unsafe {
         libc::ptrace(libc::PTRACE_TRACEME, 0, 0, 0)
     };
     
+    engine.record_flag("ptrace_traceme_failed", res == -1);
+    
     if res == -1 {
         // failed, likely someone else is tracing us
         let err = std::io::Error::last_os_error();
+        crate::engine::signal_compat::cancel_traceme();
         engine.report(
             DetectionSource::Ptrace, 
             80, 
-            &format!("ptrace(PTRACE_TRACEME) failed: {} (Debugger attached)", err)
+            &reason!("ptrace(PTRACE_TRACEME) failed: {} (Debugger attached)", err)
         );
     } else {
         // succeeded. We are now traced by our parent.
@@ -35,6 +42,7 @@ pub fn check_ptrace(engine: &mut DecisionEngine) {
 }
 
 /// A safer check using /proc/self/status
+#[allow(clippy::manual_flatten)] // Unreadable lines are skipped
 pub fn check_tracer_pid(engine: &mut DecisionEngine) {
     use std::fs::File;
     use std::io::{BufRead, BufReader};
@@ -47,11 +55,15 @@ pub fn check_tracer_pid(engine: &mut DecisionEngine) {
                     let parts: Vec<&str> = l.split_whitespace().collect();
                     if parts.len() > 1 {
                         let pid: i32 = parts[1].parse().unwrap_or(0);
+                        engine.record_flag("tracerpid_nonzero", pid != 0);
                         if pid != 0 {
+                            // Under Yama scope 1+ the tracer had to be an ancestor or privileged
+                            let scope = engine.kernel_posture().ptrace_scope.filter(|_| engine.kernel_posture().ptrace_ancestors_only());
                             engine.report(
                                 DetectionSource::Ptrace, 
                                 70, 
-                                &format!("TracerPid is non-zero: {} (Debugger attached)", pid)
+                                &reason!("TracerPid is non-zero: {} (Debugger attached){}", pid,
+                                         scope.map_or(String::new(), |s| reason!(", ptrace_scope={}: tracer is an ancestor or privileged", s)))
                             );
                         }
                     }
@@ -0,0 +1,217 @@
+//! QBDI Instrumentation Detection
+//!
+//! # Overview
+//!
+//! QBDI runs instrumented code inside its own virtual machine: each basic
+//! block is copied into an *ExecBlock* - a code page of translated
+//! instructions next to a data page holding the guest context - and the
+//! guest runs on a virtual stack QBDI allocated. Unlike Pin and DynamoRIO
+//! it does not translate the machine context the kernel hands a signal
+//! handler. We look for:
+//!
+//! - **Library**: `libQBDI.so` / `QBDIPreload` among the mappings
+//! - **Code copies**: anonymous executable pages directly followed by an
+//!   anonymous read-write page of the same size
+//! - **Shadow stack**: the main thread's stack pointer outside `[stack]`
+//! - **Instruction pointer**: the RIP in a signal's machine context must be
+//!   inside a file-backed executable mapping (or the vDSO); a code copy
+//!   leaves it in anonymous memory
+//!
+//! # Why This Fails
+//!
+//! - QBDI can be linked statically into a loader with an unremarkable name
+//! - JIT runtimes in the host process also create anonymous code pages
+//! - Only instrumented code is exposed: if our detectors run natively
+//!   (QBDI instruments a range that excludes them) the stack and RIP
+//!   checks see nothing
+//! - A debugger intercepts the signal, so that probe is skipped when traced
+//! - The RIP probe installs a SIGUSR1 handler and signals itself, which
+//!   makes the check intrusive: `stealthy` does not run it
+
+use std::fs;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::signal_compat;
+
+/// Path fragments of QBDI components
+const QBDI_PATHS: &[&str] = &["libqbdi", "qbdipreload"];
+
+static HANDLER_RAN: AtomicBool = AtomicBool::new(false);
+static SIGNAL_RIP: AtomicU64 = AtomicU64::new(0);
+
+/// A single line of /proc/self/maps, reduced to the fields we need
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    pub start: u64,
+    pub end: u64,
+    pub perms: String,
+    pub path: String,
+}
+
+impl Mapping {
+    fn anonymous(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    fn contains(&self, addr: u64) -> bool {
+        (self.start..self.end).contains(&addr)
+    }
+}
+
+/// Parse /proc/self/maps
+pub fn parse_maps(maps: &str) -> Vec<Mapping> {
+    maps.lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let (start, end) = parts.next()?.split_once('-')?;
+            let perms = parts.next()?.to_string();
+            let path = parts.nth(3).unwrap_or("").to_string();
+            Some(Mapping {
+                start: u64::from_str_radix(start, 16).ok()?,
+                end: u64::from_str_radix(end, 16).ok()?,
+                perms,
+                path,
+            })
+        })
+        .collect()
+}
+
+/// Anonymous executable regions directly followed by an anonymous
+/// read-write region of the same size (ExecBlock code + data pages)
+pub fn exec_block_pairs(maps: &[Mapping]) -> usize {
+    maps.windows(2)
+        .filter(|pair| {
+            let (code, data) = (&pair[0], &pair[1]);
+            code.anonymous() && code.perms.starts_with("r-x")
+                && data.anonymous() && data.perms.starts_with("rw-")
+                && code.end == data.start
+                && code.end - code.start == data.end - data.start
+        })
+        .count()
+}
+
+/// Whether `rip` lies in code the loader or the kernel mapped
+pub fn rip_in_mapped_code(maps: &[Mapping], rip: u64) -> bool {
+    maps.iter().any(|m| {
+        m.contains(rip) && m.perms.contains('x') && (m.path.starts_with('/') || m.path == "[vdso]")
+    })
+}
+
+extern "C" fn context_handler(_signum: libc::c_int, _info: *mut libc::siginfo_t, ctx: *mut libc::c_void) {
+    // SAFETY: the kernel passes a valid ucontext pointer for SA_SIGINFO handlers
+    unsafe {
+        let ucontext = ctx as *const libc::ucontext_t;
+        SIGNAL_RIP.store((*ucontext).uc_mcontext.gregs[libc::REG_RIP as usize] as u64, Ordering::SeqCst);
+    }
+    HANDLER_RAN.store(true, Ordering::SeqCst);
+}
+
+/// RIP the kernel saved when delivering a signal we sent ourselves
+fn signal_context_rip() -> Option<u64> {
+    HANDLER_RAN.store(false, Ordering::SeqCst);
+    // SAFETY: SIGUSR1 is routed to `context_handler` for the duration of one
+    // tgkill and the previous disposition is restored afterwards
+    unsafe {
+        let mut sa: libc::sigaction = std::mem::zeroed();
+        sa.sa_sigaction = context_handler as *const () as usize;
+        libc::sigemptyset(&mut sa.sa_mask);
+        sa.sa_flags = libc::SA_SIGINFO;
+        let mut old_sa: libc::sigaction = std::mem::zeroed();
+        if libc::sigaction(libc::SIGUSR1, &sa, &mut old_sa) != 0 {
+            return None;
+        }
+        libc::syscall(libc::SYS_tgkill, libc::getpid(), libc::gettid(), libc::SIGUSR1);
+        libc::sigaction(libc::SIGUSR1, &old_sa, std::ptr::null_mut());
+    }
+    HANDLER_RAN.load(Ordering::SeqCst).then(|| SIGNAL_RIP.load(Ordering::SeqCst))
+}
+
+/// Main entry point for the QBDI check
+pub fn check_qbdi(engine: &mut DecisionEngine) {
+    let Ok(raw) = fs::read_to_string("/proc/self/maps") else {
+        engine.record_diagnostic("qbdi", &reason!("/proc/self/maps unreadable"));
+        return;
+    };
+    let maps = parse_maps(&raw);
+    let mut indicators = Vec::new();
+
+    let mut libraries: Vec<&str> = maps.iter()
+        .map(|m| m.path.as_str())
+        .filter(|path| {
+            let file = path.rsplit('/').next().unwrap_or(path).to_ascii_lowercase();
+            QBDI_PATHS.iter().any(|sig| file.contains(sig))
+        })
+        .collect();
+    libraries.dedup();
+    if !libraries.is_empty() {
+        indicators.push(reason!("QBDI mapped: {}", libraries.join(", ")));
+    }
+
+    let pairs = exec_block_pairs(&maps);
+    if pairs > 0 {
+        indicators.push(reason!("{} anonymous code/data page pair(s) shaped like ExecBlocks", pairs));
+    }
+
+    // SAFETY: gettid/getpid cannot fail
+    let main_thread = unsafe { libc::gettid() == libc::getpid() };
+    let sp = &maps as *const _ as u64;
+    if main_thread && maps.iter().any(|m| m.path == "[stack]") && !maps.iter().any(|m| m.path == "[stack]" && m.contains(sp)) {
+        indicators.push(reason!("main thread runs on a stack at {:#x} outside [stack]", sp));
+    }
+
+    let tracer_pid = signal_compat::get_tracer_pid();
+    let rip = if tracer_pid == 0 { signal_context_rip() } else { None };
+    if let Some(rip) = rip.filter(|rip| !rip_in_mapped_code(&maps, *rip)) {
+        indicators.push(reason!("signal context RIP {:#x} is not in any mapped code", rip));
+    }
+    diag!("[QBDI] exec_block_pairs={} sp={:#x} signal_rip={:x?} indicators={}", pairs, sp, rip, indicators.len());
+
+    engine.record_feature("qbdi_indicators", indicators.len() as f64);
+    if indicators.is_empty() {
+        return;
+    }
+    let (weight, confidence) = match indicators.len() {
+        1 => (25, 0.6),
+        2 => (45, 0.85),
+        _ => (60, 0.95),
+    };
+    engine.report_with_confidence(
+        DetectionSource::Dbi,
+        weight,
+        confidence,
+        &reason!("QBDI instrumentation: {}", indicators.join("; "))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAPS: &str = "\
+55d0c0a00000-55d0c0a10000 r-xp 00001000 08:01 42 /usr/bin/app
+7f0000000000-7f0000001000 r-xp 00000000 00:00 0
+7f0000001000-7f0000002000 rw-p 00000000 00:00 0
+7f0000010000-7f0000012000 r-xp 00000000 00:00 0
+7f0000012000-7f0000013000 rw-p 00000000 00:00 0
+7ffd00000000-7ffd00021000 rw-p 00000000 00:00 0 [stack]
+7ffd00100000-7ffd00102000 r-xp 00000000 00:00 0 [vdso]
+";
+
+    #[test]
+    fn test_maps_shapes() {
+        let maps = parse_maps(MAPS);
+        assert_eq!(maps[5].path, "[stack]");
+        assert_eq!(exec_block_pairs(&maps), 1);
+        assert!(rip_in_mapped_code(&maps, 0x55d0c0a00100));
+        assert!(rip_in_mapped_code(&maps, 0x7ffd00100010));
+        assert!(!rip_in_mapped_code(&maps, 0x7f0000000010));
+    }
+
+    #[test]
+    fn test_native_signal_rip_is_mapped() {
+        let maps = parse_maps(&fs::read_to_string("/proc/self/maps").unwrap_or_default());
+        if let Some(rip) = signal_context_rip() {
+            assert!(rip_in_mapped_code(&maps, rip), "{:#x}", rip);
+        }
+    }
+}
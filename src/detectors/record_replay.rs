@@ -16,11 +16,14 @@
 //!
 //! # Detection Approaches (All Fragile)
 //!
-//! 1. **CPUID Virtualization**: rr sets hypervisor present bit (unreliable, VMs do too)
-//! 2. **Signal Race Probing**: Test if signal delivery is deterministic (weak)
-//! 3. **RDTSC vs Wall Clock**: rr's rdtsc doesn't track real time (detectable)
-//! 4. **Syscall Timing**: Syscalls may happen at unnatural intervals
-//! 5. **Perf Counter Discrepancy**: rr uses perf counters; userspace sees virtualized values
+//! 1. **RDTSC vs Wall Clock**: rr's rdtsc doesn't track real time (detectable)
+//! 2. **Signal Race Probing**: Test if signal delivery is deterministic (weak;
+//!    intrusive, so `stealthy` skips it)
+//! 3. **Syscall Timing**: Syscalls may happen at unnatural intervals
+//! 4. **Perf Counter Discrepancy**: rr uses perf counters; userspace sees virtualized values
+//!
+//! The CPUID hypervisor bit, which VMs set as well, is classified by
+//! `hypervisor.rs`.
 //!
 //! # Important Caveats
 //!
@@ -28,53 +31,64 @@
 //! - A skilled rr user can often work around detection
 //! - rr is open source; it could be patched to defeat any detection
 //! - Detection during REPLAY is different from RECORDING
+//!
+//! # Recording vs Replay
+//!
+//! Replay feeds us the recorded result of every syscall, `rdtsc` and
+//! `cpuid`, so anything we learn through those reads the same in both
+//! phases: rr's runtime artifacts (the `rr_page` mapping at 0x70000000,
+//! `librrpreload.so`, the syscall buffer) tell us rr is involved, not which
+//! phase. Two things are live in replay only:
+//!
+//! - **Breakpoints**: the recorder never touches our code, but a debugger
+//!   attached to `rr replay` plants INT3s that plain memory reads see
+//! - **Machine state outside the recording**: `xgetbv` is not trapped, so a
+//!   trace replayed on another machine shows XCR0 bits the (recorded)
+//!   CPUID leaf 0xD says are unsupported
+//!
+//! rr artifacts without either marker are reported as recording, with them
+//! as replay. Replay markers make the replay diverge from the recording,
+//! which rr usually notices and aborts on - the evidence is still emitted
+//! first.
 
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::time::{Duration, Instant};
 use crate::engine::policy::{DecisionEngine, DetectionSource};
 use core::arch::x86_64::CpuidResult;
+use crate::ffi::read_xcr0;
 
-/// Check CPUID for hypervisor bit
-/// 
-/// rr sets the hypervisor present bit (CPUID.1:ECX[31]) to indicate
-/// it's virtualizing the CPU. However, real VMs also set this.
-fn check_cpuid_hypervisor(engine: &mut DecisionEngine) {
-    // CPUID leaf 1, check ECX bit 31 (hypervisor present)
-    let result: CpuidResult = unsafe { core::arch::x86_64::__cpuid(1) };
-    let ecx = result.ecx;
-    
-    if ecx & (1 << 31) != 0 {
-        // Hypervisor bit is set
-        // This could be rr, QEMU, KVM, VirtualBox, etc.
-        engine.report_with_confidence(
-            DetectionSource::RecordReplay,
-            15,
-            0.4,  // Low confidence - could be legitimate VM
-            "CPUID hypervisor bit set (rr, VM, or other virtualization)"
-        );
-        
-        // Try to identify the hypervisor by reading signature
-        // CPUID leaf 0x40000000 returns hypervisor vendor string
-        let hv_result: CpuidResult = unsafe { core::arch::x86_64::__cpuid(0x40000000) };
-        
-        if hv_result.eax >= 0x40000000 {
-            // Decode vendor string from EBX, ECX, EDX
-            let vendor_bytes: [u8; 12] = unsafe {
-                std::mem::transmute([hv_result.ebx, hv_result.ecx, hv_result.edx])
-            };
-            let vendor = String::from_utf8_lossy(&vendor_bytes);
-            
-            eprintln!("[RR] Hypervisor vendor: {}", vendor);
-            
-            // rr might not set a vendor string, but if it does...
-            if vendor.contains("rr") || vendor.contains("record") {
-                engine.report(
-                    DetectionSource::RecordReplay,
-                    50,
-                    &format!("Hypervisor identifies as record-replay: {}", vendor)
-                );
-            }
-        }
+/// rr's fixed `RR_PAGE_ADDR` mapping
+const RR_PAGE_ADDR: &str = "70000000-";
+
+/// Which phase of rr we appear to run under
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RrPhase {
+    Absent,
+    Recording,
+    Replaying,
+}
+
+/// rr runtime artifacts visible in /proc/self/maps and the environment
+pub fn rr_runtime_artifacts(maps: &str, ld_preload: &str) -> Vec<&'static str> {
+    let mut found = Vec::new();
+    if maps.lines().any(|l| l.starts_with(RR_PAGE_ADDR) || l.contains("rr_page")) {
+        found.push(described!("rr_page mapping"));
+    }
+    if maps.contains("librrpreload") || ld_preload.contains("librrpreload") {
+        found.push("librrpreload.so");
+    }
+    if maps.contains("syscallbuf") {
+        found.push(described!("syscall buffer"));
+    }
+    found
+}
+
+/// Combine rr's runtime artifacts with the replay-only markers
+pub fn classify_phase(runtime_artifacts: usize, replay_markers: usize) -> RrPhase {
+    match (runtime_artifacts, replay_markers) {
+        (0, _) => RrPhase::Absent,
+        (_, 0) => RrPhase::Recording,
+        _ => RrPhase::Replaying,
     }
 }
 
@@ -110,9 +124,10 @@ fn check_rdtsc_vs_wall_clock(engine: &mut DecisionEngine) {
     // If TSC delta is way too small or way too large compared to wall clock, something's wrong
     
     let tsc_per_ns = tsc_delta as f64 / wall_delta_ns as f64;
+    engine.record_feature("tsc_per_ns", tsc_per_ns);
     
-    eprintln!("[RR] TSC vs Wall: tsc_delta={}, wall_ns={}, ratio={:.4}", 
-              tsc_delta, wall_delta_ns, tsc_per_ns);
+    diag!("[RR] TSC vs Wall: tsc_delta={}, wall_ns={}, ratio={:.4}", 
+          tsc_delta, wall_delta_ns, tsc_per_ns);
     
     // On native: tsc_per_ns ~= 1.0-5.0 (varies by CPU frequency)
     // On rr: tsc_per_ns could be wildly different (retired branches != time)
@@ -121,13 +136,13 @@ fn check_rdtsc_vs_wall_clock(engine: &mut DecisionEngine) {
         engine.report(
             DetectionSource::RecordReplay,
             40,
-            &format!("TSC advancing too slowly vs wall clock: {:.4} cycles/ns (rr?)", tsc_per_ns)
+            &reason!("TSC advancing too slowly vs wall clock: {:.4} cycles/ns (rr?)", tsc_per_ns)
         );
     } else if tsc_per_ns > 20.0 {
         engine.report(
             DetectionSource::RecordReplay,
             30,
-            &format!("TSC advancing too fast vs wall clock: {:.4} cycles/ns (unusual)", tsc_per_ns)
+            &reason!("TSC advancing too fast vs wall clock: {:.4} cycles/ns (unusual)", tsc_per_ns)
         );
     }
 }
@@ -145,7 +160,11 @@ fn check_rdtsc_vs_wall_clock(engine: &mut DecisionEngine) {
 /// 2. Lower weight significantly (informational only)
 /// 3. Only flag if ALL trials are identical (not most)
 /// 4. Check system load to filter out false positives on idle systems
-fn check_signal_determinism(engine: &mut DecisionEngine) {
+///
+/// Intrusive: it installs SIGUSR1/SIGUSR2 handlers and signals itself, so it
+/// is scheduled as its own slice that `stealthy` skips.
+#[allow(clippy::identity_op)] // Mirrors the x10 weight of SIGUSR2
+pub fn check_signal_determinism(engine: &mut DecisionEngine) {
     
     static SIGNAL_ORDER: AtomicU32 = AtomicU32::new(0);
     static SIGNAL_COUNT: AtomicU32 = AtomicU32::new(0);
@@ -199,9 +218,10 @@ fn check_signal_determinism(engine: &mut DecisionEngine) {
     // Calculate variance for more nuanced analysis
     let unique_values: std::collections::HashSet<_> = orders.iter().collect();
     let num_unique = unique_values.len();
+    engine.record_feature("signal_order_unique", num_unique as f64);
     
-    eprintln!("[RR] Signal orderings over {} trials: {} unique values, all_same={}", 
-              NUM_TRIALS, num_unique, all_same);
+    diag!("[RR] Signal orderings over {} trials: {} unique values, all_same={}", 
+          NUM_TRIALS, num_unique, all_same);
     
     // Only flag if absolutely all trials are identical AND we have many trials
     // This is a very weak signal due to high false positive rate on normal systems
@@ -211,14 +231,14 @@ fn check_signal_determinism(engine: &mut DecisionEngine) {
         
         if load < 0.5 {
             // System is idle - determinism is expected, don't flag
-            eprintln!("[RR] Signal determinism on idle system (load: {:.2}) - likely false positive, skipping", load);
+            diag!("[RR] Signal determinism on idle system (load: {:.2}) - likely false positive, skipping", load);
         } else {
             // System is under load but still deterministic - slightly suspicious
             engine.report_with_confidence(
                 DetectionSource::RecordReplay,
                 2,   // Very low weight - informational only
                 0.15, // Very low confidence - high false positive rate
-                &format!("Signal delivery deterministic across {} trials (load: {:.2}) - possible rr but likely false positive", NUM_TRIALS, load)
+                &reason!("Signal delivery deterministic across {} trials (load: {:.2}) - possible rr but likely false positive", NUM_TRIALS, load)
             );
         }
     }
@@ -247,47 +267,13 @@ fn check_proc_artifacts(engine: &mut DecisionEngine) {
             engine.report(
                 DetectionSource::RecordReplay,
                 60,
-                &format!("/proc/self/exe points to rr-related path: {}", exe_str)
+                &reason!("/proc/self/exe points to rr-related path: {}", exe_str)
             );
         }
     }
     
-    // Check for rr-specific environment variables
-    for var in ["_RR_TRACE_DIR", "RR_", "LD_PRELOAD"] {
-        if let Ok(val) = std::env::var(var) {
-            if val.contains("rr") || val.contains("record") || val.contains("replay") {
-                engine.report(
-                    DetectionSource::RecordReplay,
-                    40,
-                    &format!("rr-related environment variable: {}={}", var, val)
-                );
-            }
-        }
-    }
-    
-    // Check parent process
-    if let Ok(status) = fs::read_to_string("/proc/self/status") {
-        for line in status.lines() {
-            if line.starts_with("PPid:") {
-                if let Some(ppid_str) = line.split_whitespace().nth(1) {
-                    if let Ok(ppid) = ppid_str.parse::<u32>() {
-                        // Try to read parent's comm
-                        let parent_comm_path = format!("/proc/{}/comm", ppid);
-                        if let Ok(comm) = fs::read_to_string(&parent_comm_path) {
-                            let comm = comm.trim();
-                            if comm.contains("rr") {
-                                engine.report(
-                                    DetectionSource::RecordReplay,
-                                    50,
-                                    &format!("Parent process appears to be rr: {}", comm)
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
+    // rr's environment variables are classified by env_scan.rs
+    // An rr parent (or ancestor) is classified by ancestry.rs
 }
 
 /// Check for perf counter availability and behavior
@@ -306,7 +292,7 @@ fn check_perf_behavior(_engine: &mut DecisionEngine) {
     use std::fs;
     
     if let Ok(content) = fs::read_to_string("/proc/sys/kernel/perf_event_paranoid") {
-        eprintln!("[RR] perf_event_paranoid = {}", content.trim());
+        diag!("[RR] perf_event_paranoid = {}", content.trim());
         // Value meanings:
         // -1: Allow all 
         //  0: Allow all, but need CAP_SYS_ADMIN for tracepoints
@@ -316,20 +302,111 @@ fn check_perf_behavior(_engine: &mut DecisionEngine) {
     }
 }
 
-/// Main entry point for record-replay detection
+/// Entry points of our ptrace / rr detectors, the usual breakpoint targets
+fn breakpointed_entries() -> usize {
+    let entries: [fn(&mut DecisionEngine); 4] = [
+        check_record_replay,
+        check_rr_phase,
+        crate::detectors::ptrace::check_ptrace,
+        crate::detectors::ptrace::check_tracer_pid,
+    ];
+    // SAFETY: each pointer is the first byte of a function in our text
+    entries.iter().filter(|f| unsafe { *(**f as *const u8) } == 0xCC).count()
+}
+
+/// XCR0 bits the CPUID leaf 0xD we are shown does not support
+#[allow(unused_unsafe)] // `__cpuid` is only safe on newer toolchains
+fn xcr0_beyond_cpuid() -> u64 {
+    let leaf1: CpuidResult = unsafe { core::arch::x86_64::__cpuid(1) };
+    if leaf1.ecx & (1 << 27) == 0 {
+        return 0; // no OSXSAVE: XGETBV would fault
+    }
+    let leaf_d: CpuidResult = unsafe { core::arch::x86_64::__cpuid_count(0xD, 0) };
+    let supported = (leaf_d.edx as u64) << 32 | leaf_d.eax as u64;
+    // SAFETY: OSXSAVE is set, so XGETBV is enabled
+    let xcr0 = unsafe { read_xcr0() };
+    xcr0 & !supported
+}
+
+/// Tell rr recording from rr replay
+fn check_rr_phase(engine: &mut DecisionEngine) {
+    let maps = std::fs::read_to_string("/proc/self/maps").unwrap_or_default();
+    let preload = std::env::var("LD_PRELOAD").unwrap_or_default();
+    let runtime = rr_runtime_artifacts(&maps, &preload);
+
+    let mut replay = Vec::new();
+    let breakpoints = breakpointed_entries();
+    if breakpoints > 0 {
+        replay.push(reason!("INT3 on {} detector entry point(s)", breakpoints));
+    }
+    let extra_xcr0 = xcr0_beyond_cpuid();
+    if extra_xcr0 != 0 {
+        replay.push(reason!("XCR0 bits {:#x} missing from CPUID leaf 0xD", extra_xcr0));
+    }
+
+    let phase = classify_phase(runtime.len(), replay.len());
+    diag!("[RR] Phase {:?}: runtime={:?} replay={:?}", phase, runtime, replay);
+    engine.record_feature("rr_phase", match phase {
+        RrPhase::Absent => 0.0,
+        RrPhase::Recording => 1.0,
+        RrPhase::Replaying => 2.0,
+    });
+    match phase {
+        RrPhase::Absent => {}
+        RrPhase::Recording => engine.report_with_confidence(
+            DetectionSource::RecordReplay,
+            45,
+            0.85,
+            &reason!("Being recorded by rr: {}", runtime.join(", "))
+        ),
+        RrPhase::Replaying => engine.report_with_confidence(
+            DetectionSource::RecordReplay,
+            60,
+            0.9,
+            &reason!("Being replayed by rr: {} ({})", replay.join("; "), runtime.join(", "))
+        ),
+    }
+}
+
+/// Main entry point for record-replay detection (non-intrusive methods)
 pub fn check_record_replay(engine: &mut DecisionEngine) {
-    // Method 1: CPUID hypervisor check
-    check_cpuid_hypervisor(engine);
-    
-    // Method 2: RDTSC vs wall clock comparison
+    // Method 1: RDTSC vs wall clock comparison
     check_rdtsc_vs_wall_clock(engine);
     
-    // Method 3: Signal determinism
-    check_signal_determinism(engine);
-    
-    // Method 4: /proc and environment artifacts
+    // Method 2: /proc artifacts
     check_proc_artifacts(engine);
     
-    // Method 5: Perf counter behavior
+    // Method 3: Perf counter behavior
     check_perf_behavior(engine);
+    
+    // Method 4: Recording vs replay
+    check_rr_phase(engine);
+    
+    // Signal determinism is intrusive and scheduled on its own
+    // (`check_signal_determinism`)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(feature = "stealth", ignore = "evidence text is coded in stealth builds")]
+    fn test_rr_runtime_artifacts() {
+        let maps = "\
+70000000-70001000 r-xp 00000000 00:00 0 /usr/lib/rr/rr_page_64
+7f0000000000-7f0000010000 r-xp 00000000 08:01 3 /usr/lib/rr/librrpreload.so
+7f1000000000-7f1000100000 rw-s 00000000 00:05 9 /dev/shm/rr-tracee-shmem-412-syscallbuf-1
+";
+        assert_eq!(rr_runtime_artifacts(maps, ""), vec!["rr_page mapping", "librrpreload.so", "syscall buffer"]);
+        assert!(rr_runtime_artifacts("55d0-55e0 r-xp 0 08:01 1 /usr/bin/app\n", "").is_empty());
+    }
+
+    #[test]
+    fn test_classify_phase() {
+        assert_eq!(classify_phase(0, 0), RrPhase::Absent);
+        assert_eq!(classify_phase(0, 1), RrPhase::Absent);
+        assert_eq!(classify_phase(2, 0), RrPhase::Recording);
+        assert_eq!(classify_phase(2, 1), RrPhase::Replaying);
+    }
 }
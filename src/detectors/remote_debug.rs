@@ -0,0 +1,318 @@
+//! Remote Debug Stub Detection (gdbserver / lldb-server / IDA / Ghidra)
+//!
+//! # Overview
+//!
+//! Remote debugging splits the debugger in two: a small stub (`gdbserver`,
+//! `lldb-server`, IDA's `linux_server64`) runs next to the target and
+//! traces it, while the analyst's UI connects over TCP. The stub is
+//! usually our tracer and our parent (`gdbserver :1234 ./app`), and it
+//! always holds a TCP socket. Ghidra's debugger drives a local gdb or lldb
+//! through its Python agents (`ghidragdb`, `ghidralldb`, `ghidratrace`),
+//! so there the tell is in the command line of the debugger or of the
+//! Java process above it.
+//!
+//! We walk the candidates - tracer and the ancestor chain - and, for each
+//! one identified as a stub or a Ghidra bridge, match its `/proc/<pid>/fd`
+//! `socket:[inode]` links against `/proc/net/tcp{,6}` to see whether it is
+//! listening or connected. Separately, a listener on IDA's default
+//! debugger port or Ghidra's JDWP debug port anywhere on the system is weak
+//! evidence on its own.
+//!
+//! When traced, we also recover the tracer's ptrace option set. A tracer
+//! with `PTRACE_O_TRACECLONE` / `PTRACE_O_TRACEFORK` stops us on every
+//! thread creation and fork - visible as extra latency - and attaches to
+//! the new task, so the new thread or child simply reads its own
+//! TracerPid. Debuggers managing our threads set these, a bare
+//! `PTRACE_TRACEME` tracer does not. The profile goes into the evidence.
+//!
+//! # Why This Fails
+//!
+//! - A renamed stub binary only shows up through its sockets, which we do
+//!   not inspect for processes with unremarkable names
+//! - `/proc/<pid>/fd` of a stub running as another user is unreadable
+//! - Stubs can talk over a serial line, a pipe or a UNIX socket
+//! - Network namespaces hide the stub's sockets from our /proc/net view
+//! - `strace -f` sets the same clone/fork options as a debugger
+
+use std::collections::HashMap;
+use std::fs;
+use std::time::Instant;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Process names of debugger stubs
+const STUB_NAMES: &[&str] = &["gdbserver", "lldb-server", "debugserver", "linux_server", "linux_server64"];
+
+/// Command-line fragments of Ghidra's debugger agents and launcher
+const GHIDRA_MARKERS: &[&str] = &["ghidragdb", "ghidralldb", "ghidratrace", "ghidra.ghidra", "/ghidra"];
+
+/// IDA Pro's default remote-debugger port
+const IDA_DEFAULT_PORT: u16 = 23946;
+
+/// JDWP port of Ghidra's `ghidraDebug` launcher
+const GHIDRA_JDWP_PORT: u16 = 18001;
+
+/// Ancestors inspected above our parent
+const MAX_ANCESTORS: usize = 6;
+
+
+/// TCP states from include/net/tcp_states.h
+const TCP_ESTABLISHED: u8 = 0x01;
+const TCP_LISTEN: u8 = 0x0A;
+
+/// One row of /proc/net/tcp or /proc/net/tcp6
+#[derive(Debug, Clone, PartialEq)]
+pub struct TcpSocket {
+    pub local_port: u16,
+    pub remote_port: u16,
+    pub state: u8,
+    pub inode: u64,
+}
+
+/// Parse /proc/net/tcp{,6}
+pub fn parse_proc_net_tcp(text: &str) -> Vec<TcpSocket> {
+    text.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let port = fields.get(1)?.rsplit_once(':')?.1;
+            let remote_port = fields.get(2)?.rsplit_once(':')?.1;
+            Some(TcpSocket {
+                local_port: u16::from_str_radix(port, 16).ok()?,
+                remote_port: u16::from_str_radix(remote_port, 16).ok()?,
+                state: u8::from_str_radix(fields.get(3)?, 16).ok()?,
+                inode: fields.get(9)?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Whether `comm` names a known debugger stub
+pub fn is_debug_stub(comm: &str) -> bool {
+    STUB_NAMES.iter().any(|name| comm.trim() == *name)
+}
+
+/// Whether a command line belongs to one of Ghidra's debugger bridges
+pub fn is_ghidra_bridge(cmdline: &str) -> bool {
+    let cmdline = cmdline.to_ascii_lowercase();
+    GHIDRA_MARKERS.iter().any(|m| cmdline.contains(m))
+}
+
+/// Socket inodes held open by `pid`
+fn socket_inodes(pid: u32) -> Vec<u64> {
+    let Ok(entries) = fs::read_dir(format!("/proc/{}/fd", pid)) else { return Vec::new() };
+    entries.flatten()
+        .filter_map(|entry| fs::read_link(entry.path()).ok())
+        .filter_map(|target| {
+            target.to_str()?.strip_prefix("socket:[")?.strip_suffix(']')?.parse().ok()
+        })
+        .collect()
+}
+
+/// Value of a /proc/<pid>/status field
+fn status_field(pid: &str, field: &str) -> Option<String> {
+    fs::read_to_string(format!("/proc/{}/status", pid)).ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix(field).map(|v| v.trim().to_string()))
+}
+
+/// Tracer and the ancestor chain, with their roles
+fn candidates() -> Vec<(u32, &'static str)> {
+    let mut out = Vec::new();
+    let tracer = status_field("self", "TracerPid:").and_then(|v| v.parse().ok()).unwrap_or(0);
+    if tracer != 0 {
+        out.push((tracer, "tracer"));
+    }
+    let mut pid: u32 = status_field("self", "PPid:").and_then(|v| v.parse().ok()).unwrap_or(0);
+    for depth in 0..=MAX_ANCESTORS {
+        if pid <= 1 {
+            break;
+        }
+        out.push((pid, if depth == 0 { "parent" } else { "ancestor" }));
+        pid = status_field(&pid.to_string(), "PPid:").and_then(|v| v.parse().ok()).unwrap_or(0);
+    }
+    out.dedup_by_key(|(pid, _)| *pid);
+    out
+}
+
+/// Whether a `/proc/.../status` buffer shows a non-zero TracerPid
+pub fn status_traced(status: &[u8]) -> bool {
+    const FIELD: &[u8] = b"TracerPid:";
+    status.windows(FIELD.len())
+        .position(|w| w == FIELD)
+        .and_then(|at| status[at + FIELD.len()..].iter().find(|b| !b.is_ascii_whitespace()))
+        .is_some_and(|b| *b != b'0')
+}
+
+/// Read our own status with raw syscalls (usable in a forked child)
+fn raw_status_traced(path: &std::ffi::CStr) -> bool {
+    let mut buf = [0u8; 4096];
+    // SAFETY: plain open/read/close into a stack buffer
+    let n = unsafe {
+        let fd = libc::open(path.as_ptr(), libc::O_RDONLY);
+        if fd < 0 {
+            return false;
+        }
+        let n = libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len());
+        libc::close(fd);
+        n
+    };
+    n > 0 && status_traced(&buf[..n as usize])
+}
+
+/// Which of a new thread and a forked child start out traced, i.e. whether
+/// the tracer set `PTRACE_O_TRACECLONE` / `PTRACE_O_TRACEFORK`
+fn traced_event_stops() -> (bool, bool) {
+    let start = Instant::now();
+    let clone_traced = std::thread::spawn(|| raw_status_traced(c"/proc/thread-self/status"))
+        .join()
+        .unwrap_or(false);
+    let clone_ns = start.elapsed().as_nanos();
+
+    let start = Instant::now();
+    // SAFETY: the child only makes raw syscalls before `_exit`
+    let fork_traced = unsafe {
+        let child = libc::fork();
+        if child == 0 {
+            libc::_exit(raw_status_traced(c"/proc/self/status") as libc::c_int);
+        }
+        let mut status = 0;
+        child > 0
+            && libc::waitpid(child, &mut status, 0) == child
+            && libc::WIFEXITED(status)
+            && libc::WEXITSTATUS(status) == 1
+    };
+    diag!("[REMOTE] New thread traced={} ({}ns), forked child traced={} ({}ns)",
+          clone_traced, clone_ns, fork_traced, start.elapsed().as_nanos());
+    (clone_traced, fork_traced)
+}
+
+/// Human-readable ptrace option profile
+pub fn option_profile(clone_stops: bool, fork_stops: bool) -> &'static str {
+    match (clone_stops, fork_stops) {
+        (true, true) => described!("PTRACE_O_TRACECLONE | PTRACE_O_TRACEFORK"),
+        (true, false) => "PTRACE_O_TRACECLONE",
+        (false, true) => "PTRACE_O_TRACEFORK",
+        (false, false) => described!("no event options"),
+    }
+}
+
+/// Main entry point for the remote-stub check
+pub fn check_remote_debug_stub(engine: &mut DecisionEngine) {
+    let sockets: HashMap<u64, TcpSocket> = ["/proc/net/tcp", "/proc/net/tcp6"].iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|text| parse_proc_net_tcp(&text))
+        .map(|s| (s.inode, s))
+        .collect();
+
+    let traced = candidates().first().is_some_and(|(_, role)| *role == "tracer");
+    let (clone_stops, fork_stops) = if traced { traced_event_stops() } else { (false, false) };
+    engine.record_feature("tracer_event_stops", (clone_stops as u8 + fork_stops as u8) as f64);
+    let options = option_profile(clone_stops, fork_stops);
+
+    let mut stubs = 0usize;
+    for (pid, role) in candidates() {
+        let comm = fs::read_to_string(format!("/proc/{}/comm", pid)).unwrap_or_default();
+        let cmdline = fs::read(format!("/proc/{}/cmdline", pid))
+            .map(|raw| String::from_utf8_lossy(&raw).replace('\0', " ").trim().to_string())
+            .unwrap_or_default();
+        let kind = if is_debug_stub(&comm) {
+            described!("debug stub")
+        } else if is_ghidra_bridge(&cmdline) {
+            described!("Ghidra debugger bridge")
+        } else {
+            continue;
+        };
+        stubs += 1;
+        let comm = comm.trim();
+        let tcp: Vec<&TcpSocket> = socket_inodes(pid).iter()
+            .filter_map(|inode| sockets.get(inode))
+            .filter(|s| s.state == TCP_LISTEN || s.state == TCP_ESTABLISHED)
+            .collect();
+        diag!("[REMOTE] {} {} ({}: {}) holds {} TCP sockets", role, pid, kind, comm, tcp.len());
+
+        let traced_by = if role == "tracer" && traced { reason!(", tracing with {}", options) } else { String::new() };
+        if tcp.is_empty() {
+            engine.report_with_confidence(
+                DetectionSource::RemoteDebug,
+                20,
+                0.6,
+                &reason!("Our {} (PID {}) is the {} {}{}", role, pid, kind, comm, traced_by)
+            );
+        } else {
+            let ports: Vec<String> = tcp.iter().map(|s| s.local_port.to_string()).collect();
+            engine.report_with_confidence(
+                DetectionSource::RemoteDebug,
+                40,
+                0.9,
+                &reason!("Our {} (PID {}) is the {} {} serving TCP port(s) {}{}", role, pid, kind, comm, ports.join(", "), traced_by)
+            );
+        }
+    }
+    engine.record_feature("remote_debug_stubs", stubs as f64);
+
+    let ida_listening = sockets.values().any(|s| s.state == TCP_LISTEN && s.local_port == IDA_DEFAULT_PORT);
+    engine.record_flag("ida_port_listening", ida_listening);
+    if ida_listening {
+        engine.report_with_confidence(
+            DetectionSource::RemoteDebug,
+            10,
+            0.4,
+            &reason!("A process is listening on IDA's default debugger port {}", IDA_DEFAULT_PORT)
+        );
+    }
+
+    let jdwp_listening = sockets.values().any(|s| s.state == TCP_LISTEN && s.local_port == GHIDRA_JDWP_PORT);
+    engine.record_flag("ghidra_port_listening", jdwp_listening);
+    if jdwp_listening {
+        engine.report_with_confidence(
+            DetectionSource::RemoteDebug,
+            5,
+            0.3,
+            &reason!("A process is listening on Ghidra's JDWP debug port {}", GHIDRA_JDWP_PORT)
+        );
+    }
+
+    if clone_stops || fork_stops {
+        engine.report_with_confidence(
+            DetectionSource::Ptrace,
+            20,
+            0.6,
+            &reason!("Tracer intercepts our thread/process creation ({}): it manages us like a debugger", options)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_net_tcp() {
+        let text = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:5D8A 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 662 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:04D2 0100007F:A1B2 01 00000000:00000000 00:00000000 00000000  1000        0 927 1 0000000000000000 20 4 30 10 -1
+";
+        assert_eq!(parse_proc_net_tcp(text), vec![
+            TcpSocket { local_port: IDA_DEFAULT_PORT, remote_port: 0, state: TCP_LISTEN, inode: 662 },
+            TcpSocket { local_port: 1234, remote_port: 0xA1B2, state: TCP_ESTABLISHED, inode: 927 },
+        ]);
+    }
+
+    #[test]
+    fn test_is_debug_stub() {
+        assert!(is_debug_stub("gdbserver\n"));
+        assert!(is_debug_stub("linux_server64"));
+        assert!(!is_debug_stub("bash"));
+        assert!(!is_debug_stub("gdb"));
+    }
+
+    #[test]
+    fn test_is_ghidra_bridge() {
+        assert!(is_ghidra_bridge("gdb -q -ex python import ghidragdb"));
+        assert!(is_ghidra_bridge("java -cp /opt/ghidra_11.0/Ghidra/Framework ghidra.Ghidra ghidra.GhidraRun"));
+        assert!(!is_ghidra_bridge("gdb -q ./app"));
+        assert_eq!(option_profile(true, false), "PTRACE_O_TRACECLONE");
+        assert!(status_traced(b"Name:\tapp\nTracerPid:\t4242\n"));
+        assert!(!status_traced(b"Name:\tapp\nTracerPid:\t0\n"));
+    }
+}
@@ -0,0 +1,186 @@
+//! Return-Address Range Probes
+//!
+//! # Overview
+//!
+//! `return_probe!()` expands to an inlined probe at the top of a detector:
+//! it unwinds a few frames from the detector itself (glibc `backtrace`,
+//! which follows the unwind tables, so no frame pointers are needed) and
+//! checks that the probe site, the detector's return address and the
+//! callers above it all lie in the executable mappings of our binary or
+//! libc. Code that runs us from somewhere else leaves its address on the
+//! stack:
+//!
+//! | Frame outside our binary and libc            | Meaning                                 | Weight |
+//! |----------------------------------------------|-----------------------------------------|--------|
+//! | Anonymous executable memory                  | DBI code cache or injected trampoline   | 40     |
+//! | Another file's mapping                       | Hook library calling into the detector  | 35     |
+//! | Nothing mapped                               | Forged return address                   | 35     |
+//!
+//! Probes only record; [`check_ret_probe`] reports everything the probes
+//! saw so far, so it runs late. Evidence goes to `Integrity`.
+//!
+//! # Why This Fails
+//!
+//! - Pin and DynamoRIO keep original return addresses on the application
+//!   stack by design; only frameworks or hooks that do not show up
+//! - The unwinder stops at the first frame without unwind information, so
+//!   a trampoline hides everything above it (its own address is recorded)
+//! - Inline hooks that jump back into the original function without a
+//!   CALL leave no frame
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Range;
+use std::sync::{Mutex, OnceLock};
+use crate::detectors::maps_anomaly::{parse_regions, Region};
+use crate::detectors::libc_hooks::is_libc;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+extern "C" {
+    fn backtrace(buffer: *mut *mut libc::c_void, size: libc::c_int) -> libc::c_int;
+}
+
+/// Frames checked per probe: the site, its return address and a few callers
+pub const DEPTH: usize = 6;
+
+/// Foreign frames kept for the report
+const MAX_KEPT: usize = 16;
+
+/// Record where the enclosing function runs from and who called it
+macro_rules! return_probe {
+    () => {
+        $crate::detectors::ret_probe::probe(concat!(module_path!(), ":", line!()))
+    };
+}
+
+/// Executable ranges of `exe` and libc among `regions`
+pub fn code_ranges(regions: &[Region], exe: &str) -> Vec<Range<u64>> {
+    regions.iter()
+        .filter(|r| r.perms.contains('x') && (r.path == exe || is_libc(&r.path)))
+        .map(|r| r.start..r.end)
+        .collect()
+}
+
+/// Frames outside `ranges`, as (depth, address)
+pub fn foreign_frames(frames: &[u64], ranges: &[Range<u64>]) -> Vec<(usize, u64)> {
+    frames.iter().copied()
+        .enumerate()
+        .filter(|(_, addr)| !ranges.iter().any(|r| r.contains(addr)))
+        .collect()
+}
+
+#[derive(Debug, Default)]
+struct Probes {
+    /// Times each site ran
+    sites: BTreeMap<&'static str, u64>,
+    /// (site, depth, address) of foreign frames
+    foreign: Vec<(&'static str, usize, u64)>,
+}
+
+static PROBES: Mutex<Probes> = Mutex::new(Probes { sites: BTreeMap::new(), foreign: Vec::new() });
+
+/// Our code ranges, read from maps at the first probe
+static RANGES: OnceLock<Vec<Range<u64>>> = OnceLock::new();
+
+fn ranges() -> &'static [Range<u64>] {
+    RANGES.get_or_init(|| {
+        let exe = std::env::current_exe().map(|p| p.to_string_lossy().into_owned()).unwrap_or_default();
+        fs::read_to_string("/proc/self/maps").map_or(Vec::new(), |maps| code_ranges(&parse_regions(&maps), &exe))
+    })
+}
+
+/// Body of [`return_probe!`]; inlined so the first frame is the probe site
+#[inline(always)]
+pub fn probe(site: &'static str) {
+    let mut frames = [std::ptr::null_mut(); DEPTH];
+    // SAFETY: `frames` holds DEPTH pointers
+    let n = unsafe { backtrace(frames.as_mut_ptr(), DEPTH as libc::c_int) }.max(0) as usize;
+    record(site, &frames[..n]);
+}
+
+fn record(site: &'static str, frames: &[*mut libc::c_void]) {
+    let frames: Vec<u64> = frames.iter().map(|f| *f as u64).collect();
+    let ranges = ranges();
+    let foreign = if ranges.is_empty() { Vec::new() } else { foreign_frames(&frames, ranges) };
+    let mut probes = PROBES.lock().unwrap_or_else(|e| e.into_inner());
+    *probes.sites.entry(site).or_default() += 1;
+    for (depth, addr) in foreign {
+        diag!("[RET_PROBE] {}: frame {} at {:#x} outside our binary and libc", site, depth, addr);
+        if probes.foreign.len() < MAX_KEPT {
+            probes.foreign.push((site, depth, addr));
+        }
+    }
+}
+
+/// What lies at `addr`, for the report
+fn describe(regions: &[Region], addr: u64) -> (String, u32) {
+    match regions.iter().find(|r| (r.start..r.end).contains(&addr)) {
+        Some(r) if r.path.is_empty() => (reason!("anonymous {} memory", r.perms), 40),
+        Some(r) => (r.path.clone(), 35),
+        None => ("unmapped".to_string(), 35),
+    }
+}
+
+/// Main entry point for the return-address probes (reports every probe so far)
+pub fn check_ret_probe(engine: &mut DecisionEngine) {
+    return_probe!();
+    if ranges().is_empty() {
+        engine.record_diagnostic("ret_probe", &reason!("no executable mappings of our binary or libc found in /proc/self/maps"));
+        return;
+    }
+    let probes = PROBES.lock().unwrap_or_else(|e| e.into_inner());
+    diag!("[RET_PROBE] {} site(s), {} probe(s), {} foreign frame(s)",
+          probes.sites.len(), probes.sites.values().sum::<u64>(), probes.foreign.len());
+    engine.record_feature("ret_probe_sites", probes.sites.len() as f64);
+    engine.record_feature("ret_probe_foreign", probes.foreign.len() as f64);
+    if probes.foreign.is_empty() {
+        return;
+    }
+
+    let regions = fs::read_to_string("/proc/self/maps").map_or(Vec::new(), |maps| parse_regions(&maps));
+    let mut weight = 0;
+    let listed: Vec<String> = probes.foreign.iter()
+        .map(|(site, depth, addr)| {
+            let (what, w) = describe(&regions, *addr);
+            weight = weight.max(w);
+            reason!("{} frame {} at {:#x} ({})", site, depth, addr, what)
+        })
+        .collect();
+    engine.report_with_confidence(
+        DetectionSource::Integrity,
+        weight,
+        0.85,
+        &reason!("Detectors run from or called by code outside our binary and libc: {}", listed.join(", "))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(feature = "stealth", ignore = "evidence text is coded in stealth builds")]
+    fn test_foreign_frames() {
+        let regions = parse_regions("\
+555555554000-555555556000 r--p 00000000 fe:00 10 /opt/app
+555555556000-555555560000 r-xp 00002000 fe:00 10 /opt/app
+7f0000028000-7f00001bd000 r-xp 00028000 fe:00 7 /usr/lib/x86_64-linux-gnu/libc.so.6
+7f0000400000-7f0000500000 rwxp 00000000 00:00 0
+7f0000600000-7f0000610000 r-xp 00000000 fe:00 9 /tmp/hook.so
+");
+        let ranges = code_ranges(&regions, "/opt/app");
+        assert_eq!(ranges, vec![0x555555556000..0x555555560000, 0x7f0000028000..0x7f00001bd000]);
+        let frames = [0x555555556100, 0x7f0000400010, 0x555555557000, 0x7f0000600040, 0x7f0000030000];
+        assert_eq!(foreign_frames(&frames, &ranges), vec![(1, 0x7f0000400010), (3, 0x7f0000600040)]);
+        assert_eq!(describe(&regions, 0x7f0000400010), ("anonymous rwxp memory".to_string(), 40));
+        assert_eq!(describe(&regions, 0x1000).1, 35);
+    }
+
+    #[test]
+    fn test_native_probe_is_clean() {
+        return_probe!();
+        let probes = PROBES.lock().unwrap();
+        assert!(probes.sites.keys().any(|site| site.contains("ret_probe::tests:")));
+        assert!(probes.foreign.iter().all(|(site, ..)| !site.contains("ret_probe::tests:")), "{:?}", probes.foreign);
+    }
+}
@@ -0,0 +1,166 @@
+//! LD_AUDIT / rtld-audit Interface Detection
+//!
+//! # Overview
+//!
+//! An audit library (`LD_AUDIT`) is loaded by ld.so before anything else
+//! and is told about every object load and every symbol binding; through
+//! `la_symbind64` it can redirect any PLT call we make. Unlike a preload it
+//! never shows up in the symbol lookup order, and it lives in a link-map
+//! namespace of its own. We look for:
+//!
+//! - **Environment**: `LD_AUDIT` in the initial environment
+//!   (`/proc/self/environ`, which a later `unsetenv` does not change),
+//!   weighed by `AT_SECURE` - in secure mode ld.so ignores most auditors
+//! - **Namespaces**: glibc raises `_r_debug.r_version` to 2 once a second
+//!   link-map namespace exists, which an auditor always creates
+//! - **Hidden objects**: `dl_iterate_phdr` only walks our own namespace, so
+//!   shared objects mapped in /proc/self/maps but missing from its list
+//!   belong to an auditor (or were mapped by hand)
+//!
+//! # Why This Fails
+//!
+//! - `dlmopen` users create namespaces too (rare in practice)
+//! - Old glibc (before 2.35) keeps `r_version` at 1
+//! - An auditor can scrub the environment block and rename its file; the
+//!   extra namespace and its mappings remain
+
+use std::collections::HashSet;
+use std::ffi::CStr;
+use std::fs;
+use std::path::Path;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// `_r_debug.r_version` once more than one namespace exists
+const MULTI_NAMESPACE_VERSION: i32 = 2;
+
+/// Value of `LD_AUDIT` in a NUL-separated environment block, if set
+pub fn ld_audit_value(environ: &[u8]) -> Option<String> {
+    environ.split(|&b| b == 0)
+        .filter_map(|entry| entry.strip_prefix(b"LD_AUDIT="))
+        .find(|value| !value.is_empty())
+        .map(|value| String::from_utf8_lossy(value).into_owned())
+}
+
+/// Shared objects mapped in `maps` whose path is not in `listed`
+pub fn unlisted_objects(maps: &str, listed: &HashSet<String>) -> Vec<String> {
+    let mut unlisted: Vec<String> = maps.lines()
+        .filter_map(|line| line.split_whitespace().nth(5))
+        .filter(|path| path.starts_with('/') && path.rsplit('/').next().is_some_and(|file| file.contains(".so")))
+        .filter(|path| !listed.contains(*path))
+        .map(str::to_string)
+        .collect();
+    unlisted.dedup();
+    unlisted
+}
+
+/// Canonical paths of every object in our namespace's loader list
+fn listed_objects() -> HashSet<String> {
+    unsafe extern "C" fn collect(info: *mut libc::dl_phdr_info, _size: libc::size_t, data: *mut libc::c_void) -> libc::c_int {
+        let names = &mut *(data as *mut Vec<String>);
+        if !(*info).dlpi_name.is_null() {
+            names.push(CStr::from_ptr((*info).dlpi_name).to_string_lossy().into_owned());
+        }
+        0
+    }
+    let mut names: Vec<String> = Vec::new();
+    // SAFETY: `collect` only reads what the loader hands it, and `names`
+    // outlives the call
+    unsafe {
+        libc::dl_iterate_phdr(Some(collect), &mut names as *mut Vec<String> as *mut libc::c_void);
+    }
+    names.iter()
+        .filter(|name| !name.is_empty())
+        .flat_map(|name| {
+            let canonical = fs::canonicalize(Path::new(name)).map(|p| p.to_string_lossy().into_owned());
+            [Some(name.clone()), canonical.ok()]
+        })
+        .flatten()
+        .collect()
+}
+
+/// `_r_debug.r_version`, if ld.so exports it
+fn r_debug_version() -> Option<i32> {
+    // SAFETY: `_r_debug` starts with the `int r_version` field
+    unsafe {
+        let r_debug = libc::dlsym(libc::RTLD_DEFAULT, c"_r_debug".as_ptr());
+        (!r_debug.is_null()).then(|| *(r_debug as *const i32))
+    }
+}
+
+/// Main entry point for the rtld-audit check
+pub fn check_rtld_audit(engine: &mut DecisionEngine) {
+    let mut findings: Vec<(String, u32)> = Vec::new();
+
+    // SAFETY: getauxval has no preconditions
+    let secure = unsafe { libc::getauxval(libc::AT_SECURE) } != 0;
+    let audit = fs::read("/proc/self/environ").ok().and_then(|env| ld_audit_value(&env));
+    if let Some(value) = &audit {
+        let weight = if secure { 10 } else { 30 };
+        findings.push((reason!("LD_AUDIT={} in the initial environment (AT_SECURE={})", value, secure as u8), weight));
+    }
+
+    let version = r_debug_version();
+    if version.is_some_and(|v| v >= MULTI_NAMESPACE_VERSION) {
+        findings.push((reason!("_r_debug.r_version = {}: a second link-map namespace exists", version.unwrap_or(0)), 25));
+    }
+
+    let listed = listed_objects();
+    let unlisted = match fs::read_to_string("/proc/self/maps") {
+        Ok(maps) => unlisted_objects(&maps, &listed),
+        Err(e) => {
+            engine.record_diagnostic("rtld_audit", &reason!("/proc/self/maps unreadable: {}", e));
+            Vec::new()
+        }
+    };
+    if !unlisted.is_empty() {
+        findings.push((reason!("mapped but outside our loader namespace: {}", unlisted.join(", ")), 40));
+    }
+    diag!("[RTLD_AUDIT] ld_audit={:?} secure={} r_version={:?} listed={} unlisted={:?}", audit, secure, version, listed.len(), unlisted);
+
+    engine.record_feature("rtld_audit_findings", findings.len() as f64);
+    if findings.is_empty() {
+        return;
+    }
+    let weight = findings.iter().map(|(_, w)| w).sum::<u32>().min(70);
+    let confidence = match findings.len() {
+        1 => 0.6,
+        2 => 0.8,
+        _ => 0.95,
+    };
+    let details: Vec<&str> = findings.iter().map(|(d, _)| d.as_str()).collect();
+    engine.report_with_confidence(
+        DetectionSource::Instrumentation,
+        weight,
+        confidence,
+        &reason!("rtld-audit interface in use (PLT calls can be observed and redirected): {}", details.join("; "))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ld_audit_value() {
+        assert_eq!(ld_audit_value(b"PATH=/bin\0LD_AUDIT=/tmp/a.so\0"), Some("/tmp/a.so".to_string()));
+        assert_eq!(ld_audit_value(b"LD_AUDIT=\0HOME=/root\0"), None);
+    }
+
+    #[test]
+    fn test_unlisted_objects() {
+        let maps = "\
+7f0000000000-7f0000001000 r-xp 00001000 fe:00 1 /usr/lib/x86_64-linux-gnu/libc.so.6
+7f0000002000-7f0000003000 r-xp 00001000 fe:00 2 /tmp/libaudit.so
+7f0000003000-7f0000004000 rw-p 00003000 fe:00 2 /tmp/libaudit.so
+7f0000005000-7f0000006000 r-xp 00001000 fe:00 3 /usr/bin/app
+";
+        let listed: HashSet<String> = ["/usr/lib/x86_64-linux-gnu/libc.so.6".to_string()].into_iter().collect();
+        assert_eq!(unlisted_objects(maps, &listed), vec!["/tmp/libaudit.so"]);
+    }
+
+    #[test]
+    fn test_native_process_has_no_hidden_objects() {
+        let maps = fs::read_to_string("/proc/self/maps").unwrap();
+        assert!(unlisted_objects(&maps, &listed_objects()).is_empty());
+    }
+}
@@ -0,0 +1,235 @@
+//! TSX/RTM Transactional Trap Detection
+//!
+//! # Overview
+//!
+//! Restricted Transactional Memory turns any exception inside a
+//! transaction into a silent abort. That gives a hardware-assisted channel
+//! for noticing a debugger without ever raising a signal it could see:
+//!
+//! | Probe                      | Hardware                         | Under debugger / emulator        |
+//! |----------------------------|----------------------------------|----------------------------------|
+//! | Trivial transaction        | Commits (after a few retries)    | Single-step aborts with DEBUG bit|
+//! | Read critical code bytes   | Commits                          | Breakpoint aborts with DEBUG bit |
+//! | INT3 inside transaction    | Aborts, status 0, no SIGTRAP     | SIGTRAP delivered (RTM emulated) |
+//! | XABORT 0x5a                | EXPLICIT bit + code 0x5a         | Status 0 ("always abort" stub)   |
+//!
+//! # Why This Fails
+//!
+//! - Most current CPUs ship with TSX disabled by microcode, so the probe
+//!   usually finds nothing to run
+//! - Interrupts and context switches also abort transactions, so a
+//!   transaction that never commits is only weak evidence
+//! - A debugger that does not set breakpoints in the probed range stays
+//!   invisible
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use core::arch::x86_64::CpuidResult;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+extern "C" {
+    fn rtm_commit_probe() -> u32;
+    fn rtm_int3_probe() -> u32;
+    fn rtm_xabort_probe() -> u32;
+    fn rtm_touch_probe(ptr: *const u8, len: usize) -> u32;
+}
+
+/// Returned by every probe when the transaction committed
+pub const XBEGIN_STARTED: u32 = 0xffff_ffff;
+pub const XABORT_EXPLICIT: u32 = 1 << 0;
+pub const XABORT_DEBUG: u32 = 1 << 4;
+
+/// Code passed to XABORT by `rtm_xabort_probe`
+const XABORT_CODE: u32 = 0x5a;
+
+/// Attempts per transaction; interrupts legitimately abort a few
+const ATTEMPTS: usize = 16;
+
+/// Bytes of each critical function read inside a transaction
+const TOUCH_LEN: usize = 64;
+
+/// Collected probe results
+#[derive(Debug, Clone, Default)]
+pub struct RtmObservation {
+    /// Trivial/touch transactions that committed
+    pub commits: usize,
+    pub attempts: usize,
+    /// Aborts carrying the DEBUG status bit
+    pub debug_aborts: usize,
+    pub int3_status: u32,
+    /// SIGTRAPs that escaped the INT3 transaction
+    pub int3_traps_leaked: u32,
+    pub xabort_status: u32,
+}
+
+/// A conclusion drawn from the probes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RtmFinding {
+    /// A #DB fired inside a transaction (single-step or hardware breakpoint)
+    DebugAbort(usize),
+    /// No transaction ever committed
+    NeverCommits,
+    /// INT3 inside a transaction did not behave like hardware
+    Int3NotSuppressed,
+    /// XABORT did not report its code
+    XabortMismatch(u32),
+}
+
+/// Interpret the probe results
+pub fn assess(obs: &RtmObservation) -> Vec<RtmFinding> {
+    let mut out = Vec::new();
+
+    if obs.debug_aborts > 0 {
+        out.push(RtmFinding::DebugAbort(obs.debug_aborts));
+    }
+    if obs.attempts > 0 && obs.commits == 0 {
+        out.push(RtmFinding::NeverCommits);
+    }
+    if obs.int3_traps_leaked > 0 || obs.int3_status == XBEGIN_STARTED {
+        out.push(RtmFinding::Int3NotSuppressed);
+    }
+    let expected_xabort = XABORT_EXPLICIT | (XABORT_CODE << 24);
+    if obs.xabort_status & (XABORT_EXPLICIT | 0xff00_0000) != expected_xabort {
+        out.push(RtmFinding::XabortMismatch(obs.xabort_status));
+    }
+
+    out
+}
+
+static TRAPS_LEAKED: AtomicU32 = AtomicU32::new(0);
+
+extern "C" fn leak_handler(_signum: libc::c_int, _info: *mut libc::siginfo_t, _ctx: *mut libc::c_void) {
+    // INT3 is a trap, so the saved RIP is already past it; just count
+    TRAPS_LEAKED.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Run a transaction until it commits or attempts run out
+fn run_transaction(obs: &mut RtmObservation, mut probe: impl FnMut() -> u32) {
+    for _ in 0..ATTEMPTS {
+        obs.attempts += 1;
+        let status = probe();
+        if status == XBEGIN_STARTED {
+            obs.commits += 1;
+            return;
+        }
+        if status & XABORT_DEBUG != 0 {
+            obs.debug_aborts += 1;
+        }
+    }
+}
+
+/// Code whose bytes a debugger is likely to breakpoint
+fn critical_functions() -> [*const u8; 3] {
+    [
+        crate::detectors::ptrace::check_tracer_pid as *const () as *const u8,
+        crate::detectors::trap_flag::check_trap_flag as *const () as *const u8,
+        check_rtm_transactions as *const () as *const u8,
+    ]
+}
+
+#[allow(unused_unsafe)] // `__cpuid` is only safe on newer toolchains
+fn rtm_supported() -> bool {
+    if unsafe { core::arch::x86_64::__cpuid(0) }.eax < 7 {
+        return false;
+    }
+    let leaf7: CpuidResult = unsafe { core::arch::x86_64::__cpuid_count(7, 0) };
+    leaf7.ebx & (1 << 11) != 0
+}
+
+/// Main entry point for the RTM transaction probes
+pub fn check_rtm_transactions(engine: &mut DecisionEngine) {
+    let available = rtm_supported();
+    engine.record_flag("rtm_available", available);
+    if !available {
+        diag!("[RTM] CPUID does not advertise RTM, skipping");
+        return;
+    }
+
+    let mut obs = RtmObservation::default();
+
+    // SAFETY: RTM is advertised, so XBEGIN/XEND/XABORT are valid
+    run_transaction(&mut obs, || unsafe { rtm_commit_probe() });
+    for func in critical_functions() {
+        run_transaction(&mut obs, || unsafe { rtm_touch_probe(func, TOUCH_LEN) });
+    }
+    obs.xabort_status = unsafe { rtm_xabort_probe() };
+
+    // A real INT3 never escapes the transaction. If it does, catch it
+    // ourselves rather than dying - the leak is the signal.
+    TRAPS_LEAKED.store(0, Ordering::SeqCst);
+    let mut old_trap: libc::sigaction = unsafe { std::mem::zeroed() };
+    unsafe {
+        let mut sa: libc::sigaction = std::mem::zeroed();
+        sa.sa_sigaction = leak_handler as *const () as usize;
+        libc::sigemptyset(&mut sa.sa_mask);
+        sa.sa_flags = libc::SA_SIGINFO;
+        if libc::sigaction(libc::SIGTRAP, &sa, &mut old_trap) == 0 {
+            obs.int3_status = rtm_int3_probe();
+            libc::sigaction(libc::SIGTRAP, &old_trap, std::ptr::null_mut());
+        } else {
+            diag!("[RTM] Failed to install SIGTRAP handler, skipping INT3 probe");
+            obs.int3_status = 0;
+        }
+    }
+    obs.int3_traps_leaked = TRAPS_LEAKED.load(Ordering::SeqCst);
+
+    diag!("[RTM] commits={}/{} debug_aborts={} int3_status={:#x} leaked={} xabort_status={:#x}",
+          obs.commits, obs.attempts, obs.debug_aborts, obs.int3_status,
+          obs.int3_traps_leaked, obs.xabort_status);
+    engine.record_feature("rtm_debug_aborts", obs.debug_aborts as f64);
+
+    for finding in assess(&obs) {
+        match finding {
+            RtmFinding::DebugAbort(count) => engine.report_with_confidence(
+                DetectionSource::HardwareBreakpoint,
+                60,
+                0.9,
+                &reason!("{} RTM transactions aborted by a debug exception (single-step or breakpoint)", count)
+            ),
+            RtmFinding::NeverCommits => engine.report_with_confidence(
+                DetectionSource::Emulation,
+                15,
+                0.4,
+                &reason!("No RTM transaction committed in {} attempts", obs.attempts)
+            ),
+            RtmFinding::Int3NotSuppressed => engine.report_with_confidence(
+                DetectionSource::Emulation,
+                40,
+                0.8,
+                &reason!("INT3 inside an RTM transaction was not suppressed (RTM emulated)")
+            ),
+            RtmFinding::XabortMismatch(status) => engine.report_with_confidence(
+                DetectionSource::Emulation,
+                30,
+                0.7,
+                &reason!("XABORT 0x5a reported status {:#x} (RTM stubbed to always abort)", status)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hardware() -> RtmObservation {
+        RtmObservation {
+            commits: 4,
+            attempts: 5,
+            debug_aborts: 0,
+            int3_status: 0,
+            int3_traps_leaked: 0,
+            xabort_status: XABORT_EXPLICIT | (XABORT_CODE << 24),
+        }
+    }
+
+    #[test]
+    fn test_hardware_has_no_findings() {
+        assert!(assess(&hardware()).is_empty());
+    }
+
+    #[test]
+    fn test_debug_abort_and_stub() {
+        let obs = RtmObservation { debug_aborts: 2, xabort_status: 0, ..hardware() };
+        assert_eq!(assess(&obs), vec![RtmFinding::DebugAbort(2), RtmFinding::XabortMismatch(0)]);
+    }
+}
@@ -0,0 +1,291 @@
+//! Malware-Sandbox Artifact Detection (Cuckoo / CAPE class)
+//!
+//! # Overview
+//!
+//! Automated sandboxes detonate a sample in a freshly restored, minimal VM
+//! with an analysis agent running next to it. None of that is a debugger,
+//! so this module reports to its own `Sandbox` source and only runs when
+//! enabled (`--sandbox-checks` or `ANTIDEBUG_SANDBOX_CHECKS=1`).
+//!
+//! | Artifact        | What we look for                                        | Weight |
+//! |-----------------|---------------------------------------------------------|--------|
+//! | Agent process   | `agent.py`, `analyzer.py`, `cuckoo`, `capemon`, INetSim | 40     |
+//! | Identity        | Host or user names like `sandbox`, `malware`, `cuckoo`  | 20     |
+//! | NIC vendor      | VirtualBox / VMware / Parallels MAC prefixes            | 15     |
+//!
+//! Nothing is reported below a combined weight of 30.
+//!
+//! The machine itself is scored separately as a [`SandboxProfile`], since
+//! servers, containers and CI runners look the same: it is off unless
+//! enabled (`--sandbox-profile`, `ANTIDEBUG_SANDBOX_PROFILE=1`, or implied
+//! by `--sandbox-checks`) and reports one low-confidence composite:
+//!
+//! | Tell            | What we look for                                        | Score  |
+//! |-----------------|---------------------------------------------------------|--------|
+//! | Few CPUs        | One or two online CPUs                                  | 10     |
+//! | Little RAM      | Under 2 GiB                                             | 15     |
+//! | Tiny disk       | Root filesystem under 40 GiB                            | 15     |
+//! | Fresh boot      | Uptime under 5 minutes                                  | 15     |
+//! | No input        | Nothing in `/dev/input`, no keyboard or mouse in        | 10     |
+//! |                 | `/proc/bus/input/devices`                               |        |
+//!
+//! A single tell is never reported.
+//!
+//! # Why This Fails
+//!
+//! - Hardened sandboxes rename the agent, randomise names and MACs, and
+//!   age the VM before detonation
+//! - Bare-metal sandboxes have real hardware and input devices
+//! - Containers report the host's RAM and uptime
+
+use std::fs;
+use std::time::Duration;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Command-line fragments of sandbox agents and fake-network services
+const AGENT_MARKERS: &[&str] = &["agent.py", "analyzer.py", "cuckoo", "capemon", "inetsim", "fakenet", "drakrun"];
+
+/// Host and user name fragments used by sandbox images
+const SANDBOX_NAMES: &[&str] = &["sandbox", "cuckoo", "malware", "virus", "sample", "analysis", "maltest"];
+
+/// MAC prefixes of desktop hypervisors sandboxes are built on
+const SANDBOX_OUIS: &[&str] = &["08:00:27", "0a:00:27", "00:0c:29", "00:50:56", "00:05:69", "00:1c:14", "00:1c:42"];
+
+const MIN_RAM_BYTES: u64 = 2 << 30;
+const MIN_DISK_BYTES: u64 = 40 << 30;
+const MIN_UPTIME: Duration = Duration::from_secs(300);
+const MAX_SANDBOX_CPUS: usize = 2;
+
+/// Profile tells needed before the composite is reported
+const MIN_PROFILE_TELLS: usize = 2;
+
+/// Combined weight below which artifacts are only recorded
+const REPORT_THRESHOLD: u32 = 30;
+
+/// Agent marker in a NUL-separated command line
+pub fn agent_marker(cmdline: &[u8]) -> Option<&'static str> {
+    let text = String::from_utf8_lossy(cmdline).to_ascii_lowercase();
+    AGENT_MARKERS.iter().copied().find(|marker| text.split('\0').any(|arg| arg.rsplit('/').next().unwrap_or(arg).contains(marker)))
+}
+
+/// Sandbox-style fragment in a host or user name
+pub fn sandbox_name(name: &str) -> Option<&'static str> {
+    let name = name.trim().to_ascii_lowercase();
+    SANDBOX_NAMES.iter().copied().find(|fragment| name.contains(fragment))
+}
+
+/// Whether a MAC address carries a sandbox-hypervisor prefix
+pub fn sandbox_oui(mac: &str) -> bool {
+    let mac = mac.trim().to_ascii_lowercase();
+    SANDBOX_OUIS.iter().any(|oui| mac.starts_with(oui))
+}
+
+/// Whether `/proc/bus/input/devices` lists a keyboard or a mouse
+pub fn has_input_devices(devices: &str) -> bool {
+    devices.lines()
+        .filter_map(|line| line.strip_prefix("H: Handlers="))
+        .any(|handlers| handlers.split_whitespace().any(|h| h == "kbd" || h.starts_with("mouse")))
+}
+
+/// Whether a `/dev/input` listing has an event, mouse or keyboard node
+pub fn has_input_nodes<'a>(names: impl IntoIterator<Item = &'a str>) -> bool {
+    names.into_iter().any(|name| name.starts_with("event") || name.starts_with("mouse") || name.starts_with("kbd"))
+}
+
+/// Hardware view of the machine, scored for sandbox tells
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SandboxProfile {
+    pub cpus: Option<usize>,
+    pub ram: Option<u64>,
+    pub disk: Option<u64>,
+    pub uptime: Option<Duration>,
+    /// Input devices in `/dev/input` or `/proc/bus/input/devices`
+    pub input: bool,
+}
+
+impl SandboxProfile {
+    pub fn detect() -> Self {
+        let dev_input: Vec<String> = fs::read_dir("/dev/input")
+            .map(|entries| entries.flatten().map(|e| e.file_name().to_string_lossy().into_owned()).collect())
+            .unwrap_or_default();
+        let bus_input = fs::read_to_string("/proc/bus/input/devices").is_ok_and(|d| has_input_devices(&d));
+        // SAFETY: sysconf has no preconditions
+        let cpus = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) };
+        Self {
+            cpus: (cpus > 0).then_some(cpus as usize),
+            ram: total_ram(),
+            disk: root_disk(),
+            uptime: uptime(),
+            input: bus_input || has_input_nodes(dev_input.iter().map(String::as_str)),
+        }
+    }
+
+    /// Sandbox tells, with their scores
+    pub fn tells(&self) -> Vec<(String, u32)> {
+        let mut tells = Vec::new();
+        if let Some(cpus) = self.cpus.filter(|c| *c <= MAX_SANDBOX_CPUS) {
+            tells.push((format!("{} CPU(s)", cpus), 10));
+        }
+        if let Some(ram) = self.ram.filter(|r| *r < MIN_RAM_BYTES) {
+            tells.push((reason!("only {} MiB of RAM", ram >> 20), 15));
+        }
+        if let Some(disk) = self.disk.filter(|d| *d < MIN_DISK_BYTES) {
+            tells.push((reason!("root filesystem only {} GiB", disk >> 30), 15));
+        }
+        if let Some(up) = self.uptime.filter(|u| *u < MIN_UPTIME) {
+            tells.push((reason!("booted {} s ago", up.as_secs()), 15));
+        }
+        if !self.input {
+            tells.push((reason!("no input devices"), 10));
+        }
+        tells
+    }
+}
+
+fn agent_processes() -> Vec<String> {
+    let me = std::process::id();
+    let Ok(entries) = fs::read_dir("/proc") else { return Vec::new() };
+    entries.flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|pid| *pid != me)
+        .filter_map(|pid| {
+            let marker = agent_marker(&fs::read(format!("/proc/{}/cmdline", pid)).ok()?)?;
+            Some(format!("{} ({})", pid, marker))
+        })
+        .collect()
+}
+
+fn user_name() -> String {
+    // SAFETY: getpwuid returns a pointer into static storage or null
+    unsafe {
+        let pw = libc::getpwuid(libc::getuid());
+        if pw.is_null() {
+            return std::env::var("USER").unwrap_or_default();
+        }
+        std::ffi::CStr::from_ptr((*pw).pw_name).to_string_lossy().into_owned()
+    }
+}
+
+fn total_ram() -> Option<u64> {
+    // SAFETY: sysinfo fills a zeroed struct
+    let mut info: libc::sysinfo = unsafe { std::mem::zeroed() };
+    (unsafe { libc::sysinfo(&mut info) } == 0).then(|| info.totalram as u64 * info.mem_unit as u64)
+}
+
+fn root_disk() -> Option<u64> {
+    // SAFETY: statvfs fills a zeroed struct from a NUL-terminated path
+    let mut fs: libc::statvfs = unsafe { std::mem::zeroed() };
+    (unsafe { libc::statvfs(c"/".as_ptr(), &mut fs) } == 0).then(|| fs.f_blocks as u64 * fs.f_frsize as u64)
+}
+
+fn uptime() -> Option<Duration> {
+    let text = fs::read_to_string("/proc/uptime").ok()?;
+    Some(Duration::from_secs_f64(text.split_whitespace().next()?.parse().ok()?))
+}
+
+fn sandbox_macs() -> Vec<String> {
+    let Ok(entries) = fs::read_dir("/sys/class/net") else { return Vec::new() };
+    entries.flatten()
+        .filter_map(|entry| {
+            let mac = fs::read_to_string(entry.path().join("address")).ok()?;
+            sandbox_oui(&mac).then(|| format!("{} {}", entry.file_name().to_string_lossy(), mac.trim()))
+        })
+        .collect()
+}
+
+/// Main entry point for the sandbox-artifact check
+pub fn check_sandbox_artifacts(engine: &mut DecisionEngine) {
+    let mut artifacts: Vec<(String, u32)> = Vec::new();
+
+    let agents = agent_processes();
+    if !agents.is_empty() {
+        artifacts.push((reason!("analysis agent process(es): {}", agents.join(", ")), 40));
+    }
+
+    let hostname = fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default();
+    let user = user_name();
+    for (kind, name) in [("hostname", hostname.trim()), ("user", user.as_str())] {
+        if let Some(fragment) = sandbox_name(name) {
+            artifacts.push((format!("{} {:?} contains {:?}", kind, name, fragment), 20));
+        }
+    }
+
+    let macs = sandbox_macs();
+    if !macs.is_empty() {
+        artifacts.push((reason!("desktop-hypervisor NIC: {}", macs.join(", ")), 15));
+    }
+
+    let weight: u32 = artifacts.iter().map(|(_, w)| w).sum();
+    diag!("[SANDBOX] agents={} macs={} weight={}", agents.len(), macs.len(), weight);
+    engine.record_feature("sandbox_artifacts", artifacts.len() as f64);
+    if weight < REPORT_THRESHOLD {
+        return;
+    }
+    let details: Vec<&str> = artifacts.iter().map(|(d, _)| d.as_str()).collect();
+    engine.report_with_confidence(
+        DetectionSource::Sandbox,
+        weight.min(60),
+        (0.3 + 0.12 * artifacts.len() as f64).min(0.9),
+        &reason!("Malware-sandbox artifacts: {}", details.join("; "))
+    );
+}
+
+/// Entry point for the hardware-profile score (`--sandbox-profile`)
+pub fn check_sandbox_profile(engine: &mut DecisionEngine) {
+    let profile = SandboxProfile::detect();
+    let tells = profile.tells();
+    let score: u32 = tells.iter().map(|(_, s)| s).sum();
+    diag!("[SANDBOX] profile {:?}: {} tell(s), score {}", profile, tells.len(), score);
+    engine.record_feature("sandbox_profile_score", score as f64);
+    if tells.len() < MIN_PROFILE_TELLS {
+        return;
+    }
+    let details: Vec<&str> = tells.iter().map(|(d, _)| d.as_str()).collect();
+    engine.report_with_confidence(
+        DetectionSource::Sandbox,
+        score.min(40),
+        (0.15 + 0.07 * tells.len() as f64).min(0.5),
+        &reason!("Machine profile typical of a sandbox VM: {}", details.join("; "))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_and_process_markers() {
+        assert_eq!(agent_marker(b"/usr/bin/python3\0/tmp/agent.py\0"), Some("agent.py"));
+        assert_eq!(agent_marker(b"/usr/sbin/sshd\0-D\0"), None);
+        assert_eq!(sandbox_name("CUCKOO-PC\n"), Some("cuckoo"));
+        assert_eq!(sandbox_name("build-42"), None);
+        assert!(sandbox_oui("08:00:27:ab:cd:ef\n"));
+        assert!(!sandbox_oui("02:fc:00:00:00:01"));
+    }
+
+    #[test]
+    fn test_has_input_devices() {
+        let devices = "I: Bus=0011 Vendor=0001 Product=0001 Version=ab41\nN: Name=\"AT Translated Set 2 keyboard\"\nH: Handlers=sysrq kbd event0 leds\n";
+        assert!(has_input_devices(devices));
+        assert!(!has_input_devices("N: Name=\"Power Button\"\nH: Handlers=event1\n"));
+        assert!(has_input_nodes(["by-path", "event0", "mice"]));
+        assert!(!has_input_nodes(["by-id"]));
+    }
+
+    #[test]
+    fn test_profile_tells() {
+        let desktop = SandboxProfile {
+            cpus: Some(8),
+            ram: Some(16 << 30),
+            disk: Some(500 << 30),
+            uptime: Some(Duration::from_secs(86_400)),
+            input: true,
+        };
+        assert!(desktop.tells().is_empty());
+        let fresh_vm = SandboxProfile { cpus: Some(1), ram: Some(1 << 30), uptime: Some(Duration::from_secs(90)), input: false, ..desktop };
+        let scores: Vec<u32> = fresh_vm.tells().iter().map(|(_, s)| *s).collect();
+        assert_eq!(scores, vec![10, 15, 15, 10]);
+        // Unknown values are not tells
+        assert_eq!(SandboxProfile { input: true, ..Default::default() }.tells(), Vec::new());
+    }
+}
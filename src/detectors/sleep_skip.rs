@@ -0,0 +1,175 @@
+//! Sleep-Skip / Time-Acceleration Detection
+//!
+//! # Overview
+//!
+//! Automated sandboxes run each sample for a few minutes, so samples sleep
+//! to outwait them and sandboxes answer by fast-forwarding sleeps: the call
+//! returns at once, and the sandbox either leaves the clocks alone or
+//! advances its view of time by the skipped amount. We sleep for several
+//! lengths through `nanosleep` and `select` and check each sleep against
+//! two clocks, `CLOCK_MONOTONIC` and the TSC (rate learned in a busy
+//! window, where nothing can be skipped):
+//!
+//! | Monotonic elapsed | TSC elapsed  | Meaning                                  | Weight |
+//! |-------------------|--------------|------------------------------------------|--------|
+//! | >= requested      | >= requested | Native                                   | -      |
+//! | short             | short        | Sleep skipped, clocks left alone         | 40     |
+//! | >= requested      | short        | Sleep skipped, clock advanced to match   | 45     |
+//!
+//! The kernel never ends a `nanosleep` or `select` timeout early (only
+//! late), so a short sleep that was not interrupted by a signal has no
+//! innocent explanation.
+//!
+//! # Why This Fails
+//!
+//! - A sandbox that really waits (or accelerates the whole VM, TSC
+//!   included) passes
+//! - Sandboxes that only skip long sleeps (minutes) are not reached by
+//!   these short ones
+//! - A TSC rate learned from an already-accelerated clock is scaled too;
+//!   only skips (jumps), not uniform acceleration, show up in the TSC column
+
+use std::time::Duration;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::ffi::get_rdtsc;
+
+/// Sleep lengths tried with each call
+const LENGTHS_MS: [u64; 4] = [1, 3, 10, 30];
+
+/// Fraction of the requested time a sleep must last
+const MIN_FRACTION: f64 = 0.9;
+
+/// Busy window the TSC rate is learned over
+const CALIBRATION_NS: u64 = 10_000_000;
+
+/// A sleeping call, returning false if a signal interrupted it
+type SleepCall = fn(Duration) -> bool;
+
+/// How one sleep went wrong
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SleepAnomaly {
+    /// Returned early by every clock
+    Skipped,
+    /// Wall clock advanced by the full time, the TSC did not
+    Accelerated,
+}
+
+/// Judge one sleep of `requested_ns` from the monotonic and TSC-derived
+/// elapsed times
+pub fn classify_sleep(requested_ns: u64, wall_ns: u64, tsc_ns: f64) -> Option<SleepAnomaly> {
+    let min = requested_ns as f64 * MIN_FRACTION;
+    match ((wall_ns as f64) < min, tsc_ns < min) {
+        (_, false) => None,
+        (true, true) => Some(SleepAnomaly::Skipped),
+        (false, true) => Some(SleepAnomaly::Accelerated),
+    }
+}
+
+fn clock_ns(clock: libc::clockid_t) -> u64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` is a valid out-pointer
+    unsafe { libc::clock_gettime(clock, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+/// TSC ticks per monotonic ns over a busy window
+fn calibrate_tsc_per_ns() -> f64 {
+    let start = clock_ns(libc::CLOCK_MONOTONIC);
+    // SAFETY: RDTSC has no preconditions
+    let tsc_start = unsafe { get_rdtsc() };
+    let mut acc: u64 = 0;
+    while clock_ns(libc::CLOCK_MONOTONIC).saturating_sub(start) < CALIBRATION_NS {
+        for i in 0..1000u64 {
+            acc = std::hint::black_box(acc.wrapping_add(i));
+        }
+    }
+    std::hint::black_box(acc);
+    // SAFETY: as above
+    let tsc = unsafe { get_rdtsc() }.wrapping_sub(tsc_start);
+    tsc as f64 / clock_ns(libc::CLOCK_MONOTONIC).saturating_sub(start).max(1) as f64
+}
+
+fn timespec(length: Duration) -> libc::timespec {
+    libc::timespec { tv_sec: length.as_secs() as libc::time_t, tv_nsec: length.subsec_nanos() as libc::c_long }
+}
+
+/// Sleep through `nanosleep`; false if interrupted
+fn nanosleep(length: Duration) -> bool {
+    let request = timespec(length);
+    // SAFETY: `request` is valid; a null remainder is allowed
+    unsafe { libc::nanosleep(&request, std::ptr::null_mut()) == 0 }
+}
+
+/// Sleep through an fd-less `select` timeout; false if interrupted
+fn select(length: Duration) -> bool {
+    let mut timeout = libc::timeval { tv_sec: length.as_secs() as libc::time_t, tv_usec: length.subsec_micros() as libc::suseconds_t };
+    // SAFETY: no fd sets, `timeout` is a valid in/out pointer
+    unsafe { libc::select(0, std::ptr::null_mut(), std::ptr::null_mut(), std::ptr::null_mut(), &mut timeout) == 0 }
+}
+
+/// Main entry point for the sleep-skip check
+pub fn check_sleep_skip(engine: &mut DecisionEngine) {
+    let tsc_per_ns = calibrate_tsc_per_ns();
+    if tsc_per_ns <= 0.0 {
+        engine.record_diagnostic("sleep_skip", &reason!("TSC did not advance during calibration"));
+        return;
+    }
+    let calls: [(&str, SleepCall); 2] = [("nanosleep", nanosleep), ("select", select)];
+    let mut anomalies: Vec<(SleepAnomaly, String)> = Vec::new();
+    let mut shortest = f64::MAX;
+    for (name, sleep) in calls {
+        for ms in LENGTHS_MS {
+            let requested = Duration::from_millis(ms);
+            let wall_start = clock_ns(libc::CLOCK_MONOTONIC);
+            // SAFETY: RDTSC has no preconditions
+            let tsc_start = unsafe { get_rdtsc() };
+            if !sleep(requested) {
+                continue;
+            }
+            // SAFETY: as above
+            let tsc_ns = unsafe { get_rdtsc() }.wrapping_sub(tsc_start) as f64 / tsc_per_ns;
+            let wall_ns = clock_ns(libc::CLOCK_MONOTONIC).saturating_sub(wall_start);
+            let requested_ns = requested.as_nanos() as u64;
+            shortest = shortest.min((wall_ns as f64).min(tsc_ns) / requested_ns as f64);
+            if let Some(anomaly) = classify_sleep(requested_ns, wall_ns, tsc_ns) {
+                anomalies.push((anomaly, reason!("{}({} ms) took {:.2} ms monotonic, {:.2} ms TSC",
+                    name, ms, wall_ns as f64 / 1e6, tsc_ns / 1e6)));
+            }
+        }
+    }
+    diag!("[SLEEP_SKIP] {:.3} TSC ticks/ns, shortest sleep {:.2}x requested, {} anomalies", tsc_per_ns, shortest, anomalies.len());
+    engine.record_feature("sleep_min_ratio", if shortest == f64::MAX { 0.0 } else { shortest });
+    engine.record_feature("sleep_skips", anomalies.len() as f64);
+
+    for kind in [SleepAnomaly::Skipped, SleepAnomaly::Accelerated] {
+        let details: Vec<&str> = anomalies.iter().filter(|(a, _)| *a == kind).map(|(_, d)| d.as_str()).collect();
+        if details.is_empty() {
+            continue;
+        }
+        let (weight, summary) = match kind {
+            SleepAnomaly::Skipped => (40, described!("Sleeps return early")),
+            SleepAnomaly::Accelerated => (45, described!("Sleeps are fast-forwarded (clock advanced, TSC did not)")),
+        };
+        engine.report_with_confidence(
+            DetectionSource::Sandbox,
+            weight,
+            0.85,
+            &format!("{}: {}", summary, details.join("; "))
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_sleep() {
+        let ms = 1_000_000;
+        assert_eq!(classify_sleep(10 * ms, 10 * ms + 60_000, 10.05e6), None);
+        assert_eq!(classify_sleep(10 * ms, 20_000, 20e3), Some(SleepAnomaly::Skipped));
+        assert_eq!(classify_sleep(10 * ms, 10 * ms, 15e3), Some(SleepAnomaly::Accelerated));
+        // A late wake-up is never an anomaly
+        assert_eq!(classify_sleep(10 * ms, 50 * ms, 50e6), None);
+    }
+}
@@ -0,0 +1,234 @@
+//! Stop/Continue History (/proc stat and schedstat)
+//!
+//! # Overview
+//!
+//! Our explicit checks only see a debugger while they run. A background
+//! thread samples the kernel's scheduling view of the process for the whole
+//! scan, so stops that happen between checks still leave a trace:
+//!
+//! | Observation                                          | Meaning                            | Weight |
+//! |------------------------------------------------------|------------------------------------|--------|
+//! | Another of our threads in state `t` (tracing stop)   | Held by a tracer (breakpoint,      | 45     |
+//! |                                                      | syscall stop, single-step)         |        |
+//! | Another of our threads in state `T` (stopped)        | SIGSTOP / job-control stop         | 35     |
+//! | Sampler overslept, and `schedstat` run delay does    | Whole process stopped (all-stop    | 35     |
+//! | not account for it                                   | debugger, SIGSTOP) between samples |        |
+//!
+//! The third row is what sets this apart from the timer watchdog: a late
+//! wake-up is either time spent waiting for a CPU, which the scheduler
+//! records as run delay in `/proc/thread-self/schedstat`, or time the
+//! thread was not runnable at all. A stopped thread is not runnable, so
+//! only a stop (or a paused VM) leaves oversleep the run delay does not
+//! explain. CPU starvation, which the watchdog cannot tell from a stop,
+//! shows up here as run delay and is ignored.
+//!
+//! # Why This Fails
+//!
+//! - A debugger in all-stop mode also stops this thread, so `t` is only
+//!   seen for non-stop debugging or tracers that stop single threads
+//!   (strace, syscall-stop supervisors); the oversleep row covers the rest
+//! - Kernels without `CONFIG_SCHED_INFO` have no `schedstat`; the
+//!   oversleep check is then skipped
+//! - A paused VM looks like a stopped process
+//! - Stops shorter than the oversleep threshold go unnoticed
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::salt::jitter;
+
+/// Sampling period
+pub const INTERVAL: Duration = Duration::from_millis(20);
+
+/// Oversleep not covered by run delay that counts as a stop (salted +-20%)
+const STOP_NS: u64 = jitter(150_000_000, 20, "stop_history.stop_ns");
+
+/// Scheduler state letter of a `/proc/<pid>/stat` line (the field after
+/// the parenthesised command name, which may itself contain `)`)
+pub fn parse_state(stat: &str) -> Option<char> {
+    stat.get(stat.rfind(')')? + 1..)?.split_whitespace().next()?.chars().next()
+}
+
+/// Run delay (ns spent runnable but waiting for a CPU) from a `schedstat`
+/// line: `<run ns> <run delay ns> <timeslices>`
+pub fn parse_run_delay(schedstat: &str) -> Option<u64> {
+    schedstat.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Oversleep of one sample that neither the requested sleep nor run
+/// delay explains
+pub fn unexplained_ns(elapsed_ns: u64, interval_ns: u64, run_delay_ns: u64) -> u64 {
+    elapsed_ns.saturating_sub(interval_ns).saturating_sub(run_delay_ns)
+}
+
+fn clock_ns() -> u64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` is a valid out-pointer
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+fn run_delay() -> Option<u64> {
+    parse_run_delay(&fs::read_to_string("/proc/thread-self/schedstat").ok()?)
+}
+
+#[derive(Debug, Default)]
+struct History {
+    samples: u64,
+    /// Samples in which each (tid, state) was seen
+    stopped_threads: BTreeMap<(i32, char), u64>,
+    /// Unexplained oversleep of each sample above [`STOP_NS`]
+    gaps_ns: Vec<u64>,
+    max_unexplained_ns: u64,
+    schedstat: bool,
+}
+
+/// Our threads other than `own`, in a stopped state, as (tid, state)
+fn stopped_threads(own: i32) -> Vec<(i32, char)> {
+    let Ok(tasks) = fs::read_dir("/proc/self/task") else { return Vec::new() };
+    tasks.flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<i32>().ok())
+        .filter(|tid| *tid != own)
+        .filter_map(|tid| {
+            let state = parse_state(&fs::read_to_string(format!("/proc/self/task/{}/stat", tid)).ok()?)?;
+            matches!(state, 't' | 'T').then_some((tid, state))
+        })
+        .collect()
+}
+
+fn run(interval: Duration, stop: &AtomicBool, history: &Mutex<History>) {
+    // SAFETY: gettid has no preconditions
+    let own = unsafe { libc::gettid() };
+    let interval_ns = interval.as_nanos() as u64;
+    let (mut last_ns, mut last_delay) = (clock_ns(), run_delay());
+    history.lock().unwrap_or_else(|e| e.into_inner()).schedstat = last_delay.is_some();
+
+    while !stop.load(Ordering::Relaxed) {
+        std::thread::sleep(interval);
+        let (now_ns, delay) = (clock_ns(), run_delay());
+        let stopped = stopped_threads(own);
+
+        let mut history = history.lock().unwrap_or_else(|e| e.into_inner());
+        history.samples += 1;
+        for (tid, state) in stopped {
+            diag!("[STOP_HISTORY] thread {} in state {}", tid, state);
+            *history.stopped_threads.entry((tid, state)).or_default() += 1;
+        }
+        if let (Some(before), Some(after)) = (last_delay, delay) {
+            let unexplained = unexplained_ns(now_ns.saturating_sub(last_ns), interval_ns, after.saturating_sub(before));
+            history.max_unexplained_ns = history.max_unexplained_ns.max(unexplained);
+            if unexplained >= STOP_NS {
+                diag!("[STOP_HISTORY] overslept {:.1} ms without run delay to show for it", unexplained as f64 / 1e6);
+                history.gaps_ns.push(unexplained);
+            }
+        }
+        (last_ns, last_delay) = (now_ns, delay);
+    }
+}
+
+/// Running sampler; report with [`StopMonitor::finish`]
+pub struct StopMonitor {
+    stop: Arc<AtomicBool>,
+    history: Arc<Mutex<History>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StopMonitor {
+    /// Start sampling every `interval`. `None` if the thread cannot start.
+    pub fn start(interval: Duration) -> Option<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let history = Arc::new(Mutex::new(History::default()));
+        let (thread_stop, thread_history) = (stop.clone(), history.clone());
+        let thread = crate::engine::threads::spawn_masked("stop-history", move || run(interval, &thread_stop, &thread_history))
+            .ok()?;
+        diag!("[STOP_HISTORY] Sampling thread states and schedstat every {} ms", interval.as_millis());
+        Some(Self { stop, history, thread: Some(thread) })
+    }
+
+    /// Stop sampling and report every stop seen during the scan
+    pub fn finish(mut self, engine: &mut DecisionEngine) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        let history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        diag!("[STOP_HISTORY] {} samples, stopped threads {:?}, {} unexplained gaps (max {:.1} ms)",
+              history.samples, history.stopped_threads, history.gaps_ns.len(), history.max_unexplained_ns as f64 / 1e6);
+        if !history.schedstat {
+            engine.record_diagnostic("stop_history", &reason!("/proc/thread-self/schedstat unavailable; oversleep not attributed"));
+        }
+        let traced: u64 = history.stopped_threads.iter().filter(|((_, s), _)| *s == 't').map(|(_, n)| n).sum();
+        engine.record_feature("stop_traced_samples", traced as f64);
+        engine.record_feature("stop_max_unexplained_ms", history.max_unexplained_ns as f64 / 1e6);
+
+        let describe = |state: char| -> Vec<String> {
+            history.stopped_threads.iter()
+                .filter(|((_, s), _)| *s == state)
+                .map(|((tid, _), n)| reason!("thread {} in {} sample(s)", tid, n))
+                .collect()
+        };
+        let in_t = describe('t');
+        if !in_t.is_empty() {
+            engine.report_with_confidence(
+                DetectionSource::Ptrace,
+                45,
+                0.9,
+                &reason!("Threads seen in tracing stop (t) between checks: {}", in_t.join(", "))
+            );
+        }
+        let in_stop = describe('T');
+        if !in_stop.is_empty() {
+            engine.report_with_confidence(
+                DetectionSource::ExecutionGap,
+                35,
+                0.8,
+                &reason!("Threads seen stopped (T) between checks: {}", in_stop.join(", "))
+            );
+        }
+        if let Some(longest) = history.gaps_ns.iter().max() {
+            engine.report_with_confidence(
+                DetectionSource::ExecutionGap,
+                35,
+                0.75,
+                &reason!("Process was not runnable for up to {} ms ({} time(s)) with no run delay to explain it: stopped between checks",
+                         longest / 1_000_000, history.gaps_ns.len())
+            );
+        }
+    }
+}
+
+impl Drop for StopMonitor {
+    fn drop(&mut self) {
+        // Unreported monitors just stop; the thread exits after its next sample
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_state() {
+        assert_eq!(parse_state("1234 (worker) t 1 1234 1234 0"), Some('t'));
+        assert_eq!(parse_state("1234 (a) b) (c) T 1 1234"), Some('T'));
+        assert_eq!(parse_state("1234 (cat)"), None);
+        assert_eq!(parse_state(&fs::read_to_string("/proc/thread-self/stat").unwrap()), Some('R'));
+    }
+
+    #[test]
+    fn test_unexplained() {
+        let ms = 1_000_000;
+        assert_eq!(parse_run_delay("339015095 24188064 50\n"), Some(24_188_064));
+        assert_eq!(parse_run_delay("0"), None);
+        // Late by 480 ms, of which 470 ms waiting for a CPU: starvation
+        assert_eq!(unexplained_ns(500 * ms, 20 * ms, 470 * ms), 10 * ms);
+        // Late by 980 ms with no run delay: stopped
+        assert_eq!(unexplained_ns(1000 * ms, 20 * ms, 0), 980 * ms);
+        assert_eq!(unexplained_ns(15 * ms, 20 * ms, 0), 0);
+    }
+}
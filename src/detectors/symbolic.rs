@@ -0,0 +1,167 @@
+//! Symbolic / Concolic Execution Detection (angr, SymCC)
+//!
+//! # Overview
+//!
+//! Symbolic and concolic engines track where values come from. Code that
+//! only touches constants runs concretely and fast; code whose branches
+//! depend on an input they made symbolic (the environment, the clock, our
+//! PID) forks a path or asks the solver at every branch. We run the same
+//! function twice:
+//!
+//! - **Constant seed**: a cascade of hash rounds, each followed by a
+//!   three-way branch on the hash bits
+//! - **Environment seed**: the identical cascade seeded from the TSC, our
+//!   PID and a stack address
+//!
+//! On hardware, and under plain emulators or DBI, both take the same time.
+//! Under a symbolic engine the second one blows up by orders of magnitude.
+//!
+//! Floating point is the other weak spot: solvers reason about IEEE
+//! arithmetic through approximate theories, or concretize it with the
+//! wrong rounding. A few results are fixed by IEEE 754 to the last bit:
+//!
+//! | Operation                      | Correct result            |
+//! |--------------------------------|---------------------------|
+//! | `fma(x, x, -(x*x))`, x=1+2^-30 | 2^-60 (product rounding)  |
+//! | `(2^53 + 1) as f64`            | 2^53 (ties to even)       |
+//! | `0.1 + 0.2`                    | `0x3fd3333333333334`      |
+//! | `1 / 3`, `sqrt(2)`             | correctly rounded         |
+//! | `1 / -0.0`                     | negative infinity         |
+//! | `min_subnormal * 0.5`          | 0 (ties to even)          |
+//!
+//! # Why This Fails
+//!
+//! - An engine that does not treat the TSC, PID or stack as symbolic runs
+//!   both cascades concretely
+//! - Engines that execute floating point natively (SymCC concretizes it)
+//!   get every FP result right
+//! - A heavily loaded host can stretch one run; the fastest of several is
+//!   kept to shed that
+
+use std::hint::black_box;
+use std::time::Instant;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Hash rounds per cascade
+const CASCADE_ROUNDS: u32 = 4096;
+
+/// Runs per seed; the fastest is kept
+const CASCADE_RUNS: usize = 3;
+
+/// Environment-seeded / constant-seeded time ratio that counts as blow-up
+const BLOWUP_RATIO: f64 = 20.0;
+
+/// Seed of the constant cascade
+const CONSTANT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// One hash round (xorshift-multiply)
+fn hash_round(mut x: u64) -> u64 {
+    x ^= x >> 31;
+    x = x.wrapping_mul(0x7fb5_d329_728e_a185);
+    x ^= x >> 27;
+    x = x.wrapping_mul(0x81da_def4_bc2d_d44d);
+    x ^ (x >> 33)
+}
+
+/// Hash rounds with a data-dependent branch after each
+#[inline(never)]
+pub fn cascade(seed: u64, rounds: u32) -> u64 {
+    let mut x = seed;
+    let mut acc = 0u64;
+    for i in 0..rounds {
+        x = hash_round(x ^ i as u64);
+        if x & 1 == 0 {
+            acc = acc.wrapping_add(x.rotate_left(7));
+        } else if x & 6 == 2 {
+            acc ^= x >> 3;
+        } else {
+            acc = acc.wrapping_mul(x | 1);
+        }
+    }
+    acc
+}
+
+/// Fastest of [`CASCADE_RUNS`] cascades from `seed`, in nanoseconds
+fn time_cascade(seed: impl Fn() -> u64) -> u64 {
+    (0..CASCADE_RUNS)
+        .map(|_| {
+            let seed = seed();
+            let start = Instant::now();
+            black_box(cascade(black_box(seed), CASCADE_ROUNDS));
+            start.elapsed().as_nanos().max(1) as u64
+        })
+        .min()
+        .unwrap_or(1)
+}
+
+/// Seed from inputs a symbolic engine is likely to make symbolic
+fn environment_seed() -> u64 {
+    let local = 0u8;
+    // SAFETY: RDTSC and getpid have no side effects
+    let (tsc, pid) = unsafe { (crate::ffi::get_rdtsc(), libc::getpid() as u64) };
+    tsc ^ pid.rotate_left(32) ^ (&local as *const u8 as u64)
+}
+
+/// IEEE 754 results computed here that differ from the exact bits
+pub fn fp_deviations() -> Vec<String> {
+    let x = black_box(1.0 + 2f64.powi(-30));
+    let one = black_box(1.0f64);
+    let cases: [(&str, f64, u64); 7] = [
+        (described!("fma(x, x, -(x*x))"), x.mul_add(x, -(x * x)), 2f64.powi(-60).to_bits()),
+        ("(2^53 + 1) as f64", black_box((1u64 << 53) + 1) as f64, 0x4340_0000_0000_0000),
+        ("0.1 + 0.2", black_box(0.1f64) + black_box(0.2f64), 0x3fd3_3333_3333_3334),
+        ("1 / 3", one / black_box(3.0), 0x3fd5_5555_5555_5555),
+        ("sqrt(2)", black_box(2.0f64).sqrt(), 0x3ff6_a09e_667f_3bcd),
+        ("1 / -0.0", one / black_box(-0.0f64), f64::NEG_INFINITY.to_bits()),
+        (described!("min_subnormal * 0.5"), black_box(f64::from_bits(1)) * black_box(0.5), 0),
+    ];
+    cases.iter()
+        .filter(|(_, value, expected)| value.to_bits() != *expected)
+        .map(|(name, value, expected)| reason!("{} = {:#018x} (expected {:#018x})", name, value.to_bits(), expected))
+        .collect()
+}
+
+/// Main entry point for the symbolic-execution check
+pub fn check_symbolic_execution(engine: &mut DecisionEngine) {
+    let constant_ns = time_cascade(|| CONSTANT_SEED);
+    let environment_ns = time_cascade(environment_seed);
+    let ratio = environment_ns as f64 / constant_ns as f64;
+    engine.record_feature("symbolic_cascade_ratio", ratio);
+    diag!("[SYMBOLIC] cascade constant={}ns environment={}ns ratio={:.2}", constant_ns, environment_ns, ratio);
+
+    if ratio > BLOWUP_RATIO {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            40,
+            0.7,
+            &reason!("Input-dependent branch cascade ran {:.0}x slower than the same code on a constant (symbolic execution?)", ratio)
+        );
+    }
+
+    let fp = fp_deviations();
+    engine.record_feature("symbolic_fp_deviations", fp.len() as f64);
+    if !fp.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            35,
+            0.8,
+            &reason!("Floating point does not round like IEEE 754 hardware: {}", fp.join("; "))
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cascade_is_deterministic() {
+        assert_eq!(cascade(CONSTANT_SEED, 64), cascade(CONSTANT_SEED, 64));
+        assert_ne!(cascade(CONSTANT_SEED, 64), cascade(CONSTANT_SEED ^ 1, 64));
+    }
+
+    #[test]
+    fn test_hardware_fp_is_exact() {
+        assert!(fp_deviations().is_empty(), "{:?}", fp_deviations());
+    }
+}
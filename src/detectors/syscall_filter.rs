@@ -0,0 +1,286 @@
+//! Syscall-Filter Sandbox Detection (seccomp / Supervisor Quirks)
+//!
+//! # Overview
+//!
+//! Sandboxes built on seccomp-bpf (container runtimes, Chrome-style
+//! sandboxes, gVisor's systrap, `SystemCallFilter=`) or on a ptrace
+//! supervisor decide for each syscall whether the kernel gets to see it.
+//! Syscalls nobody expects a program to make are the ones they refuse, and
+//! the refusal looks different from what a vanilla kernel answers:
+//!
+//! | Outcome           | What it means                                        | Weight  |
+//! |-------------------|------------------------------------------------------|---------|
+//! | Wrong errno       | `EPERM` / `ENOSYS` where the kernel reports `EFAULT` | 10 each |
+//! | `SIGSYS` caught   | `SECCOMP_RET_TRAP`: a supervisor emulates the call   | 35      |
+//! | Killed by SIGSYS  | `SECCOMP_RET_KILL` on an unexpected syscall          | 40      |
+//! | `Seccomp: 2`      | A filter is installed (containers do this too)       | 15      |
+//!
+//! Every probe is harmless on a real kernel: it either only reads state or
+//! fails argument validation with a well-known errno. The probes run in a
+//! forked child, so a filter that kills on sight takes the child with it
+//! rather than us, and a `SIGSYS` handler there only affects the child.
+//! Filters are inherited across `fork`, so the child sees what we would.
+//!
+//! # Why This Fails
+//!
+//! - Filters that only block dangerous syscalls (most container profiles
+//!   allow everything probed here) look vanilla
+//! - A supervisor that forwards unknown syscalls to the kernel answers
+//!   correctly
+//! - Kernels built without `CONFIG_KCMP`, `CONFIG_KEYS` or
+//!   `CONFIG_MEMBARRIER` answer `ENOSYS` themselves, so that answer is
+//!   accepted as vanilla for those probes
+
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::ffi::raw_syscall4;
+
+/// A syscall that a vanilla kernel answers in a known way
+pub struct Probe {
+    pub name: &'static str,
+    pub nr: i64,
+    pub args: [i64; 4],
+    /// Whether any non-negative result is vanilla
+    pub success: bool,
+    /// Errnos a vanilla kernel may return
+    pub errnos: &'static [i32],
+}
+
+/// Benign, unusual syscalls and their vanilla answers
+pub const PROBES: &[Probe] = &[
+    Probe { name: described!("unassigned syscall 1000"), nr: 1000, args: [0; 4], success: false, errnos: &[libc::ENOSYS] },
+    Probe { name: described!("get_robust_list(0, NULL, NULL)"), nr: libc::SYS_get_robust_list, args: [0; 4], success: false, errnos: &[libc::EFAULT] },
+    Probe { name: described!("clock_adjtime(CLOCK_REALTIME, NULL)"), nr: libc::SYS_clock_adjtime, args: [0; 4], success: false, errnos: &[libc::EFAULT] },
+    Probe { name: described!("setns(-1, 0)"), nr: libc::SYS_setns, args: [-1, 0, 0, 0], success: false, errnos: &[libc::EBADF] },
+    Probe { name: "unshare(0)", nr: libc::SYS_unshare, args: [0; 4], success: true, errnos: &[] },
+    Probe { name: described!("ptrace(PTRACE_CONT, 0)"), nr: libc::SYS_ptrace, args: [libc::PTRACE_CONT as i64, 0, 0, 0], success: false, errnos: &[libc::ESRCH] },
+    Probe { name: described!("kcmp(0, 0, KCMP_VM)"), nr: libc::SYS_kcmp, args: [0, 0, 1, 0], success: false, errnos: &[libc::ESRCH, libc::ENOSYS] },
+    Probe { name: described!("keyctl(GET_KEYRING_ID, thread, 0)"), nr: libc::SYS_keyctl, args: [0, -1, 0, 0], success: true, errnos: &[libc::ENOKEY, libc::ENOSYS] },
+    Probe { name: "membarrier(QUERY)", nr: libc::SYS_membarrier, args: [0; 4], success: true, errnos: &[libc::ENOSYS] },
+    Probe { name: "personality(0xffffffff)", nr: libc::SYS_personality, args: [0xffff_ffff, 0, 0, 0], success: true, errnos: &[] },
+    Probe { name: described!("ioprio_get(PROCESS, self)"), nr: libc::SYS_ioprio_get, args: [1, 0, 0, 0], success: true, errnos: &[] },
+];
+
+/// Result slot the child writes when the probe raised `SIGSYS`
+const TRAPPED: i64 = i64::MIN;
+
+/// How long the probe child may take before it is killed
+const CHILD_TIMEOUT: Duration = Duration::from_secs(2);
+
+static SIGSYS_SEEN: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn sigsys_handler(_sig: libc::c_int, _info: *mut libc::siginfo_t, _ctx: *mut libc::c_void) {
+    SIGSYS_SEEN.store(true, Ordering::SeqCst);
+}
+
+/// How a probe's answer differs from a vanilla kernel's, if it does
+pub fn deviation(probe: &Probe, result: i64) -> Option<String> {
+    if result == TRAPPED {
+        return Some(reason!("{} raised SIGSYS", probe.name));
+    }
+    let vanilla = if result >= 0 { probe.success } else { probe.errnos.contains(&(-result as i32)) };
+    if vanilla {
+        return None;
+    }
+    let answer = if result >= 0 {
+        reason!("succeeded ({})", result)
+    } else {
+        reason!("failed with {}", errno_name(-result as i32))
+    };
+    Some(format!("{} {}", probe.name, answer))
+}
+
+fn errno_name(errno: i32) -> String {
+    match errno {
+        libc::EPERM => "EPERM".to_string(),
+        libc::EACCES => "EACCES".to_string(),
+        libc::ENOSYS => "ENOSYS".to_string(),
+        libc::EFAULT => "EFAULT".to_string(),
+        libc::EINVAL => "EINVAL".to_string(),
+        other => format!("errno {}", other),
+    }
+}
+
+/// (mode, filter count) from the `Seccomp` fields of /proc/self/status
+pub fn seccomp_state(status: &str) -> Option<(u32, u32)> {
+    let field = |name: &str| status.lines().find_map(|l| l.strip_prefix(name)).and_then(|v| v.trim().parse().ok());
+    Some((field("Seccomp:")?, field("Seccomp_filters:").unwrap_or(0)))
+}
+
+/// Run [`PROBES`] in the child; one result per probe goes down `fd` as soon
+/// as it is known, so the parent can tell which probe a fatal filter hit
+///
+/// # Safety
+///
+/// Only to be called in a freshly forked child: it installs a `SIGSYS`
+/// handler and only makes async-signal-safe calls
+unsafe fn run_probes_in_child(fd: libc::c_int) -> ! {
+    let mut sa: libc::sigaction = std::mem::zeroed();
+    sa.sa_sigaction = sigsys_handler as *const () as usize;
+    libc::sigemptyset(&mut sa.sa_mask);
+    sa.sa_flags = libc::SA_SIGINFO;
+    libc::sigaction(libc::SIGSYS, &sa, std::ptr::null_mut());
+
+    for probe in PROBES {
+        SIGSYS_SEEN.store(false, Ordering::SeqCst);
+        let [a1, a2, a3, a4] = probe.args;
+        let mut result = raw_syscall4(probe.nr, a1, a2, a3, a4);
+        if SIGSYS_SEEN.load(Ordering::SeqCst) {
+            result = TRAPPED;
+        }
+        let bytes = result.to_ne_bytes();
+        libc::write(fd, bytes.as_ptr() as *const libc::c_void, bytes.len());
+    }
+    libc::_exit(0)
+}
+
+/// Probe results from a forked child, and the signal that killed it (if any)
+fn probe_in_child() -> Result<(Vec<i64>, Option<i32>), String> {
+    let mut fds = [0; 2];
+    // SAFETY: pipe2 fills the two-element array
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(reason!("pipe2 failed: {}", std::io::Error::last_os_error()));
+    }
+    let [read_fd, write_fd] = fds;
+    // SAFETY: the child only makes raw syscalls, sigaction and write
+    // before `_exit`
+    let child = unsafe { libc::fork() };
+    if child == 0 {
+        unsafe {
+            libc::close(read_fd);
+            run_probes_in_child(write_fd);
+        }
+    }
+    // SAFETY: closing our copy of the write end
+    unsafe { libc::close(write_fd) };
+    if child < 0 {
+        unsafe { libc::close(read_fd) };
+        return Err(reason!("fork failed: {}", std::io::Error::last_os_error()));
+    }
+
+    let mut bytes = Vec::new();
+    let deadline = Instant::now() + CHILD_TIMEOUT;
+    let mut timed_out = false;
+    loop {
+        let mut pfd = libc::pollfd { fd: read_fd, events: libc::POLLIN, revents: 0 };
+        let remaining = deadline.saturating_duration_since(Instant::now()).as_millis() as libc::c_int;
+        // SAFETY: poll/read on our own pipe into a stack buffer
+        if remaining == 0 || unsafe { libc::poll(&mut pfd, 1, remaining) } <= 0 {
+            timed_out = true;
+            break;
+        }
+        let mut buf = [0u8; 128];
+        let n = unsafe { libc::read(read_fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if n <= 0 {
+            break;
+        }
+        bytes.extend_from_slice(&buf[..n as usize]);
+    }
+    let mut status = 0;
+    // SAFETY: reaping the child we forked
+    unsafe {
+        libc::close(read_fd);
+        if timed_out {
+            libc::kill(child, libc::SIGKILL);
+        }
+        libc::waitpid(child, &mut status, 0);
+    }
+    if timed_out {
+        return Err(reason!("probe child did not finish within {:?}", CHILD_TIMEOUT));
+    }
+
+    let results = bytes.chunks_exact(8)
+        .map(|chunk| i64::from_ne_bytes(chunk.try_into().unwrap_or([0; 8])))
+        .collect();
+    let signal = libc::WIFSIGNALED(status).then(|| libc::WTERMSIG(status));
+    Ok((results, signal))
+}
+
+/// Main entry point for the syscall-filter check
+pub fn check_syscall_filter(engine: &mut DecisionEngine) {
+    let mut findings: Vec<(String, u32, f64)> = Vec::new();
+
+    let seccomp = fs::read_to_string("/proc/self/status").ok().and_then(|s| seccomp_state(&s));
+    if let Some((mode, filters)) = seccomp.filter(|(mode, _)| *mode != 0) {
+        findings.push((reason!("Seccomp mode {} with {} filter(s) installed", mode, filters), 15, 0.5));
+    }
+
+    let (results, signal) = match probe_in_child() {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            engine.record_diagnostic("syscall_filter", &e);
+            return;
+        }
+    };
+
+    let deviations: Vec<String> = PROBES.iter().zip(&results)
+        .filter_map(|(probe, result)| deviation(probe, *result))
+        .collect();
+    let trapped = results.contains(&TRAPPED);
+    let errno_deviations = deviations.len() - results.iter().filter(|r| **r == TRAPPED).count();
+    if trapped {
+        findings.push((reason!("SIGSYS delivered for a benign syscall (SECCOMP_RET_TRAP supervisor)"), 35, 0.85));
+    }
+    if errno_deviations > 0 {
+        findings.push((reason!("{} probe(s) answered unlike a vanilla kernel", errno_deviations), (10 * errno_deviations as u32).min(30), 0.7));
+    }
+    if let Some(signal) = signal {
+        let probe = PROBES.get(results.len()).map(|p| p.name).unwrap_or("exit");
+        let confidence = if signal == libc::SIGSYS { 0.9 } else { 0.6 };
+        findings.push((reason!("probe child killed by signal {} at {}", signal, probe), 40, confidence));
+    }
+    diag!("[SYSCALL_FILTER] seccomp={:?} results={:?} signal={:?} deviations={:?}", seccomp, results, signal, deviations);
+
+    engine.record_feature("syscall_filter_deviations", (deviations.len() + signal.is_some() as usize) as f64);
+    if findings.is_empty() {
+        return;
+    }
+    let weight = findings.iter().map(|(_, w, _)| w).sum::<u32>().min(60);
+    let confidence = findings.iter().map(|(_, _, c)| *c).fold(0.0, f64::max);
+    let mut details: Vec<String> = findings.into_iter().map(|(d, _, _)| d).collect();
+    details.extend(deviations);
+    engine.report_with_confidence(
+        DetectionSource::SyscallFilter,
+        weight,
+        confidence,
+        &reason!("Syscalls are filtered by a sandbox: {}", details.join("; "))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(feature = "stealth", ignore = "evidence text is coded in stealth builds")]
+    fn test_deviation() {
+        let robust = &PROBES[1];
+        assert_eq!(deviation(robust, -(libc::EFAULT as i64)), None);
+        assert_eq!(deviation(robust, -(libc::EPERM as i64)).as_deref(), Some("get_robust_list(0, NULL, NULL) failed with EPERM"));
+        assert_eq!(deviation(robust, TRAPPED).as_deref(), Some("get_robust_list(0, NULL, NULL) raised SIGSYS"));
+        let unshare = &PROBES[4];
+        assert_eq!(deviation(unshare, 0), None);
+        assert!(deviation(unshare, -(libc::ENOSYS as i64)).is_some());
+    }
+
+    #[test]
+    fn test_seccomp_state() {
+        assert_eq!(seccomp_state("Name:\tapp\nSeccomp:\t2\nSeccomp_filters:\t3\n"), Some((2, 3)));
+        assert_eq!(seccomp_state("Seccomp:\t0\n"), Some((0, 0)));
+        assert_eq!(seccomp_state("Name:\tapp\n"), None);
+    }
+
+    #[test]
+    fn test_unfiltered_process_answers_like_vanilla() {
+        if seccomp_state(&fs::read_to_string("/proc/self/status").unwrap()).is_some_and(|(mode, _)| mode != 0) {
+            return;
+        }
+        let (results, signal) = probe_in_child().unwrap();
+        assert_eq!(signal, None);
+        assert_eq!(results.len(), PROBES.len());
+        let deviations: Vec<String> = PROBES.iter().zip(&results).filter_map(|(p, r)| deviation(p, *r)).collect();
+        assert!(deviations.is_empty(), "{:?}", deviations);
+    }
+}
@@ -0,0 +1,217 @@
+//! SYSCALL vs INT 0x80 Latency Asymmetry
+//!
+//! # Overview
+//!
+//! x86_64 Linux has two user-to-kernel entry paths for the same syscalls:
+//! the 64-bit `syscall` instruction and the legacy `int 0x80` gate into
+//! the i386 table. On bare metal and under hardware virtualization the two
+//! cost within a small factor of each other and return identical results.
+//!
+//! Interposition layers break that symmetry:
+//!
+//! | Layer                | Typical symptom                                 |
+//! |----------------------|-------------------------------------------------|
+//! | ptrace syscall-stops | Both paths 10-100x slower than native           |
+//! | seccomp user-notify  | Filtered path slow, the other native            |
+//! | gVisor / user kernel | `int 0x80` faults, returns ENOSYS or is slow    |
+//!
+//! We time `getppid` through both paths, compare the ratio with the native
+//! band, and check both paths agree on the answer.
+//!
+//! # Why This Fails
+//!
+//! - Kernels built without IA32 emulation legitimately reject `int 0x80`
+//! - Heavy host load inflates both paths, so only the ratio is trusted
+//!   unless the absolute cost is far beyond any native kernel
+//! - An interposer that handles both paths with equal overhead keeps the
+//!   ratio native and is only caught by the absolute ceiling
+//! - The ceiling is scaled by the engine's syscall tolerance, so a KPTI or
+//!   PREEMPT_RT kernel gives an interposer more room to hide; a calibrated
+//!   baseline replaces it with a multiple of this machine's own cost
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::salt::jitter;
+use crate::ffi::get_rdtsc;
+
+extern "C" {
+    fn syscall64_getppid() -> i64;
+    fn int80_getppid() -> i64;
+    fn syscall_path_landing();
+}
+
+/// Calls per timing batch
+const TIMING_CALLS: u64 = 100;
+
+/// Timing batches; the fastest one is kept
+const TIMING_BATCHES: usize = 7;
+
+/// Native `int 0x80` / `syscall` cost ratio band (int 0x80 skips the
+/// SYSCALL fast path, so 3-4x is normal with mitigations enabled)
+const NATIVE_RATIO_MIN: f64 = 0.5;
+const NATIVE_RATIO_MAX: f64 = 8.0;
+
+/// Per-call cost no native kernel reaches for getppid (cycles, salted +-10%)
+const INTERPOSED_CYCLES: u64 = jitter(20_000, 10, "syscall_paths.interposed_cycles");
+
+/// Measurements from both entry paths
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PathTiming {
+    pub syscall_cycles: u64,
+    /// `None` if `int 0x80` faulted
+    pub int80_cycles: Option<u64>,
+    pub syscall_result: i64,
+    pub int80_result: i64,
+}
+
+impl PathTiming {
+    pub fn ratio(&self) -> Option<f64> {
+        self.int80_cycles.map(|c| c as f64 / self.syscall_cycles.max(1) as f64)
+    }
+}
+
+/// Asymmetries between the paths, given the absolute per-call `ceiling`
+/// in cycles. Returns descriptions.
+pub fn path_asymmetries(timing: &PathTiming, ceiling: f64) -> Vec<String> {
+    let mut out = Vec::new();
+    let ceiling = ceiling as u64;
+
+    if timing.syscall_cycles > ceiling {
+        out.push(reason!("syscall path costs {} cycles per getppid", timing.syscall_cycles));
+    }
+
+    let Some(int80_cycles) = timing.int80_cycles else {
+        return out;
+    };
+    if timing.int80_result != timing.syscall_result {
+        out.push(reason!("int 0x80 getppid returned {} but syscall returned {}",
+                         timing.int80_result, timing.syscall_result));
+    }
+    if int80_cycles > ceiling {
+        out.push(reason!("int 0x80 path costs {} cycles per getppid", int80_cycles));
+    }
+    if let Some(ratio) = timing.ratio() {
+        if !(NATIVE_RATIO_MIN..=NATIVE_RATIO_MAX).contains(&ratio) {
+            out.push(reason!("int 0x80 / syscall cost ratio {:.2} outside native {:.1}-{:.1}",
+                             ratio, NATIVE_RATIO_MIN, NATIVE_RATIO_MAX));
+        }
+    }
+
+    out
+}
+
+static INT80_FAULTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn segv_handler(_signum: libc::c_int, _info: *mut libc::siginfo_t, ctx: *mut libc::c_void) {
+    INT80_FAULTED.store(true, Ordering::SeqCst);
+    // SAFETY: `int80_getppid` is a leaf function, so the landing pad can
+    // return straight to its caller
+    unsafe {
+        let ucontext = ctx as *mut libc::ucontext_t;
+        (*ucontext).uc_mcontext.gregs[libc::REG_RIP as usize] = syscall_path_landing as *const () as usize as i64;
+    }
+}
+
+/// Fastest per-call cost of `f` over several batches, in TSC cycles
+fn min_cycles_per_call(f: unsafe extern "C" fn() -> i64) -> u64 {
+    let mut best = u64::MAX;
+    for _ in 0..TIMING_BATCHES {
+        let start = unsafe { get_rdtsc() };
+        for _ in 0..TIMING_CALLS {
+            std::hint::black_box(unsafe { f() });
+        }
+        let end = unsafe { get_rdtsc() };
+        best = best.min(end.wrapping_sub(start) / TIMING_CALLS);
+    }
+    best
+}
+
+fn measure() -> PathTiming {
+    let syscall_result = unsafe { syscall64_getppid() };
+    let syscall_cycles = min_cycles_per_call(syscall64_getppid);
+
+    let mut old_segv: libc::sigaction = unsafe { std::mem::zeroed() };
+    unsafe {
+        let mut sa: libc::sigaction = std::mem::zeroed();
+        sa.sa_sigaction = segv_handler as *const () as usize;
+        libc::sigemptyset(&mut sa.sa_mask);
+        sa.sa_flags = libc::SA_SIGINFO;
+        if libc::sigaction(libc::SIGSEGV, &sa, &mut old_segv) != 0 {
+            diag!("[SYSCALL] Failed to install SIGSEGV handler, skipping int 0x80");
+            return PathTiming { syscall_cycles, syscall_result, ..Default::default() };
+        }
+    }
+
+    INT80_FAULTED.store(false, Ordering::SeqCst);
+    let int80_result = unsafe { int80_getppid() };
+    let int80_cycles = if INT80_FAULTED.load(Ordering::SeqCst) {
+        None
+    } else {
+        Some(min_cycles_per_call(int80_getppid))
+    };
+
+    unsafe {
+        libc::sigaction(libc::SIGSEGV, &old_segv, std::ptr::null_mut());
+    }
+
+    PathTiming { syscall_cycles, int80_cycles, syscall_result, int80_result }
+}
+
+/// Main entry point for the syscall entry-path asymmetry probe
+pub fn check_syscall_paths(engine: &mut DecisionEngine) {
+    let timing = measure();
+    diag!("[SYSCALL] syscall={} cycles int80={:?} cycles ratio={:?} results={}/{}",
+          timing.syscall_cycles, timing.int80_cycles, timing.ratio(),
+          timing.syscall_result, timing.int80_result);
+
+    engine.record_feature("syscall_cycles", timing.syscall_cycles as f64);
+    if let Some(ratio) = timing.ratio() {
+        engine.record_feature("int80_syscall_ratio", ratio);
+    } else {
+        diag!("[SYSCALL] int 0x80 faulted (no IA32 emulation?), comparing syscall path only");
+    }
+
+    // This machine's calibrated syscall cost, else the universal ceiling
+    let ceiling = engine.baseline_limit("syscall_cycles", 10.0)
+        .unwrap_or(INTERPOSED_CYCLES as f64 * engine.syscall_tolerance());
+    let asymmetries = path_asymmetries(&timing, ceiling);
+    if !asymmetries.is_empty() {
+        // A result mismatch or absurd cost is unambiguous; a skewed ratio alone is noisier
+        let hard = timing.int80_cycles.is_some() && timing.int80_result != timing.syscall_result
+            || timing.syscall_cycles as f64 > ceiling;
+        let (weight, confidence) = if hard { (50, 0.85) } else { (25, 0.6) };
+        engine.report_with_confidence(
+            DetectionSource::SyscallInterposition,
+            weight,
+            confidence,
+            &reason!("Syscall entry paths asymmetric: {}", asymmetries.join("; "))
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_native_paths_are_symmetric() {
+        let native = PathTiming { syscall_cycles: 300, int80_cycles: Some(450), syscall_result: 7, int80_result: 7 };
+        assert!(path_asymmetries(&native, INTERPOSED_CYCLES as f64).is_empty());
+    }
+
+    #[test]
+    fn test_interposed_paths() {
+        let notify = PathTiming { syscall_cycles: 300, int80_cycles: Some(9000), syscall_result: 7, int80_result: 7 };
+        assert_eq!(path_asymmetries(&notify, INTERPOSED_CYCLES as f64).len(), 1);
+
+        let sandbox = PathTiming { syscall_cycles: 300, int80_cycles: Some(300), syscall_result: 7, int80_result: -38 };
+        assert_eq!(path_asymmetries(&sandbox, INTERPOSED_CYCLES as f64).len(), 1);
+
+        let no_ia32 = PathTiming { syscall_cycles: 300, int80_cycles: None, syscall_result: 7, int80_result: -1 };
+        assert!(path_asymmetries(&no_ia32, INTERPOSED_CYCLES as f64).is_empty());
+
+        let kpti = PathTiming { syscall_cycles: 30_000, int80_cycles: Some(90_000), syscall_result: 7, int80_result: 7 };
+        assert_eq!(path_asymmetries(&kpti, INTERPOSED_CYCLES as f64).len(), 2);
+        assert!(path_asymmetries(&kpti, 5.0 * INTERPOSED_CYCLES as f64).is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+//! Per-Thread Wait Channel and Syscall Inspection
+//!
+//! # Overview
+//!
+//! A thread cannot see itself stopped. We start a short-lived sibling
+//! thread that looks at every other thread of ours through
+//! `/proc/self/task/<tid>/wchan` (the kernel function it sleeps in) and
+//! `/proc/self/task/<tid>/syscall` (the syscall it is blocked in, `-1` if
+//! none):
+//!
+//! | Wait channel                     | Meaning                                   | Source               | Weight |
+//! |----------------------------------|-------------------------------------------|----------------------|--------|
+//! | `ptrace_stop`                    | Held by a tracer (breakpoint, syscall     | Ptrace               | 45     |
+//! |                                  | stop, single-step)                        |                      |        |
+//! | `seccomp_do_user_notification`   | Syscall parked for a seccomp supervisor   | SyscallInterposition | 35     |
+//! | `do_signal_stop`                 | Stopped by SIGSTOP (or tgkill to one      | ExecutionGap         | 30     |
+//! |                                  | thread)                                   |                      |        |
+//!
+//! The syscall file refines `ptrace_stop`: blocked in a syscall is a
+//! syscall stop (strace-style tracers), `-1` is a stop outside any syscall
+//! (a breakpoint, single-step or signal). The main thread waits for the
+//! sibling in `futex`, which is expected.
+//!
+//! # Why This Fails
+//!
+//! - A debugger in all-stop mode also stops the sibling before it can look
+//! - `wchan` reads `0` where the kernel hides symbol names (or for a
+//!   running thread); such threads are skipped
+//! - Stops that begin and end between two reads are missed; the
+//!   stop-history sampler covers the rest of the run
+
+use std::fs;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Wait channels associated with tracing, and what they mean
+const TRACING_WCHANS: &[(&str, DetectionSource, u32, f64, &str)] = &[
+    ("ptrace_stop", DetectionSource::Ptrace, 45, 0.9, described!("held in a tracing stop")),
+    ("seccomp_do_user_notification", DetectionSource::SyscallInterposition, 35, 0.8, described!("parked for a seccomp user-notification supervisor")),
+    ("do_signal_stop", DetectionSource::ExecutionGap, 30, 0.7, described!("stopped by a signal")),
+];
+
+/// What `/proc/<tid>/syscall` says the thread is doing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskSyscall {
+    Running,
+    /// Blocked in syscall `nr`
+    InSyscall(i64),
+    /// Blocked in the kernel outside any syscall
+    OutsideSyscall,
+}
+
+/// Parse a `/proc/<pid>/task/<tid>/syscall` line
+pub fn parse_syscall(line: &str) -> Option<TaskSyscall> {
+    let first = line.split_whitespace().next()?;
+    if first == "running" {
+        return Some(TaskSyscall::Running);
+    }
+    match first.parse::<i64>().ok()? {
+        nr if nr < 0 => Some(TaskSyscall::OutsideSyscall),
+        nr => Some(TaskSyscall::InSyscall(nr)),
+    }
+}
+
+/// Tracing-related meaning of a wait channel
+pub fn classify_wchan(wchan: &str) -> Option<(DetectionSource, u32, f64, &'static str)> {
+    TRACING_WCHANS.iter()
+        .find(|(name, ..)| *name == wchan.trim())
+        .map(|&(_, source, weight, confidence, meaning)| (source, weight, confidence, meaning))
+}
+
+/// One thread as seen from the sibling
+#[derive(Debug, Clone)]
+struct TaskView {
+    tid: i32,
+    comm: String,
+    wchan: String,
+    syscall: Option<TaskSyscall>,
+}
+
+/// Every thread but the caller's
+fn inspect_tasks() -> Vec<TaskView> {
+    // SAFETY: gettid has no preconditions
+    let me = unsafe { libc::gettid() };
+    let Ok(entries) = fs::read_dir("/proc/self/task") else { return Vec::new() };
+    entries.flatten()
+        .filter_map(|e| e.file_name().to_str()?.parse::<i32>().ok())
+        .filter(|tid| *tid != me)
+        .map(|tid| {
+            let read = |file: &str| fs::read_to_string(format!("/proc/self/task/{}/{}", tid, file)).unwrap_or_default();
+            TaskView {
+                tid,
+                comm: read("comm").trim().to_string(),
+                wchan: read("wchan").trim().to_string(),
+                syscall: parse_syscall(&read("syscall")),
+            }
+        })
+        .collect()
+}
+
+/// Main entry point for the per-thread wchan / syscall check
+pub fn check_task_wchan(engine: &mut DecisionEngine) {
+    let sibling = std::thread::Builder::new()
+        .name("task-wchan".to_string())
+        .spawn(|| {
+            let _own = crate::engine::threads::own_thread();
+            inspect_tasks()
+        });
+    let Ok(Ok(tasks)) = sibling.map(|handle| handle.join()) else {
+        engine.record_diagnostic("task_wchan", &reason!("sibling thread could not be started"));
+        return;
+    };
+    let named = tasks.iter().filter(|t| !t.wchan.is_empty() && t.wchan != "0").count();
+    diag!("[TASK_WCHAN] {} thread(s), {} with a named wait channel: {:?}", tasks.len(), named,
+          tasks.iter().map(|t| (t.tid, t.wchan.as_str(), t.syscall)).collect::<Vec<_>>());
+    if named == 0 && !tasks.is_empty() {
+        engine.record_diagnostic("task_wchan", &reason!("no wait channel names readable (hidden by the kernel)"));
+    }
+
+    let mut hits = 0;
+    for task in &tasks {
+        let Some((source, weight, confidence, meaning)) = classify_wchan(&task.wchan) else { continue };
+        hits += 1;
+        let context = match task.syscall {
+            Some(TaskSyscall::InSyscall(nr)) if task.wchan == "ptrace_stop" => reason!(" at syscall {} (syscall-stop tracer)", nr),
+            Some(TaskSyscall::OutsideSyscall) if task.wchan == "ptrace_stop" => reason!(" outside any syscall (breakpoint, single-step or signal)"),
+            Some(TaskSyscall::InSyscall(nr)) => reason!(" in syscall {}", nr),
+            _ => String::new(),
+        };
+        engine.report_with_confidence(
+            source,
+            weight,
+            confidence,
+            &reason!("Thread {} ({}) waits in {}: {}{}", task.tid, task.comm, task.wchan, meaning, context)
+        );
+    }
+    engine.record_feature("task_wchan_hits", hits as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_syscall() {
+        assert_eq!(parse_syscall("running\n"), Some(TaskSyscall::Running));
+        assert_eq!(parse_syscall("230 0x0 0x0 0x7ffc 0x7ffc 0x0 0x0 0x7ffc 0x7ff3"), Some(TaskSyscall::InSyscall(230)));
+        assert_eq!(parse_syscall("-1 0x7ffc3833358 0x55d0c1a2b3c4\n"), Some(TaskSyscall::OutsideSyscall));
+        assert_eq!(parse_syscall(""), None);
+    }
+
+    #[test]
+    fn test_classify_wchan() {
+        assert_eq!(classify_wchan("ptrace_stop").map(|c| c.0), Some(DetectionSource::Ptrace));
+        assert_eq!(classify_wchan("do_signal_stop\n").map(|c| c.0), Some(DetectionSource::ExecutionGap));
+        assert_eq!(classify_wchan("hrtimer_nanosleep"), None);
+        assert_eq!(classify_wchan("0"), None);
+    }
+}
@@ -0,0 +1,224 @@
+//! On-Disk vs In-Memory `.text` Diffing
+//!
+//! # Overview
+//!
+//! `int3.rs` counts `0xCC` bytes and has to guess which of them are
+//! padding. This check proves modification instead: it maps our executable
+//! from disk (`/proc/self/exe`, which still names the file we were started
+//! from if it has been replaced since), locates `.text`, and compares the
+//! loaded code with the file image byte for byte.
+//!
+//! - **Relocations**: a PIE's `.text` normally has none, but text
+//!   relocations are possible; `R_X86_64_RELATIVE` entries that fall in
+//!   `.text` are applied to the file image with our load base, any other
+//!   relocated bytes are excluded from the comparison
+//! - **Load base**: `AT_PHDR` minus the link-time address of `PT_PHDR`
+//! - **Findings**: every patched range with its `.text` offset, its runtime
+//!   address and the bytes before and after; patches that write `0xCC` are
+//!   software breakpoints
+//!
+//! # Why This Fails
+//!
+//! - Patches outside `.text` (other code sections, libraries - see
+//!   `inline_hooks.rs` for libc) are not compared
+//! - A debugger that removes its breakpoints while we run (or hides them
+//!   from data reads, see `mem_crossview.rs`) leaves an identical image
+//! - A binary patched on disk before launch matches its own modified file
+
+use std::fs;
+use std::ops::Range;
+use std::os::unix::io::AsRawFd;
+use crate::detectors::coverage::section_named;
+use crate::detectors::mem_crossview::diverging_ranges;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// `SHT_RELA`
+const SHT_RELA: u32 = 4;
+
+/// `Elf64_Rela` size
+const RELA_SIZE: usize = 24;
+
+/// `R_X86_64_RELATIVE`
+const R_X86_64_RELATIVE: u32 = 8;
+
+/// `PT_PHDR`
+const PT_PHDR: u32 = 6;
+
+/// Patched ranges listed per report; the rest are counted
+const MAX_LISTED: usize = 8;
+
+/// One `Elf64_Rela` entry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rela {
+    pub offset: u64,
+    pub kind: u32,
+    pub addend: i64,
+}
+
+fn u64_at(elf: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(elf.get(at..at + 8)?.try_into().ok()?))
+}
+
+/// All entries of the image's `SHT_RELA` sections
+pub fn relocations(elf: &[u8]) -> Vec<Rela> {
+    let Some(sections) = crate::detectors::coverage::sections(elf) else { return Vec::new() };
+    sections.iter()
+        .filter(|s| s.kind == SHT_RELA)
+        .flat_map(|s| (0..s.size / RELA_SIZE).map(move |i| s.offset + i * RELA_SIZE))
+        .filter_map(|at| Some(Rela {
+            offset: u64_at(elf, at)?,
+            kind: u64_at(elf, at + 8)? as u32,
+            addend: u64_at(elf, at + 16)? as i64,
+        }))
+        .collect()
+}
+
+/// Link-time address of the program headers (`PT_PHDR`), or their file
+/// offset if the image has no `PT_PHDR`
+pub fn phdr_vaddr(elf: &[u8]) -> Option<u64> {
+    let phoff = u64_at(elf, 0x20)? as usize;
+    let entsize = u16::from_le_bytes(elf.get(0x36..0x38)?.try_into().ok()?) as usize;
+    let count = u16::from_le_bytes(elf.get(0x38..0x3a)?.try_into().ok()?) as usize;
+    let from_phdr = (0..count)
+        .map(|i| phoff + i * entsize)
+        .find(|&at| elf.get(at..at + 4) == Some(&PT_PHDR.to_le_bytes()[..]))
+        .and_then(|at| u64_at(elf, at + 0x10));
+    Some(from_phdr.unwrap_or(phoff as u64))
+}
+
+/// Apply the relocations that land in `text` (linked at `text_addr`) for a
+/// load at `base`; returns the `text` ranges we cannot reproduce
+pub fn relocate(text: &mut [u8], text_addr: u64, relocs: &[Rela], base: u64) -> Vec<Range<usize>> {
+    let mut unverifiable = Vec::new();
+    let linked = text_addr..text_addr + text.len() as u64;
+    for r in relocs.iter().filter(|r| linked.contains(&r.offset)) {
+        let at = (r.offset - text_addr) as usize;
+        let end = (at + 8).min(text.len());
+        if r.kind == R_X86_64_RELATIVE && end == at + 8 {
+            text[at..end].copy_from_slice(&base.wrapping_add(r.addend as u64).to_le_bytes());
+        } else {
+            unverifiable.push(at..end);
+        }
+    }
+    unverifiable
+}
+
+/// Our executable, mapped read-only from disk
+struct FileImage {
+    base: *const u8,
+    len: usize,
+}
+
+impl FileImage {
+    fn map(file: &fs::File) -> Option<Self> {
+        let len = file.metadata().ok()?.len() as usize;
+        // SAFETY: private read-only mapping of a file we hold open
+        let base = unsafe { libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, file.as_raw_fd(), 0) };
+        (base != libc::MAP_FAILED).then_some(FileImage { base: base as *const u8, len })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: the mapping is `len` bytes long and lives as long as self
+        unsafe { std::slice::from_raw_parts(self.base, self.len) }
+    }
+}
+
+impl Drop for FileImage {
+    fn drop(&mut self) {
+        // SAFETY: unmapping our own mapping
+        unsafe { libc::munmap(self.base as *mut libc::c_void, self.len) };
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Main entry point for the `.text` diff
+pub fn check_text_diff(engine: &mut DecisionEngine) {
+    let image = match fs::File::open("/proc/self/exe").map(|f| FileImage::map(&f)) {
+        Ok(Some(image)) => image,
+        Ok(None) => {
+            engine.record_diagnostic("text_diff", &reason!("mmap of /proc/self/exe failed"));
+            return;
+        }
+        Err(e) => {
+            engine.record_diagnostic("text_diff", &reason!("/proc/self/exe unreadable: {}", e));
+            return;
+        }
+    };
+    let elf = image.bytes();
+    let (Some(text), Some(phdr)) = (section_named(elf, ".text"), phdr_vaddr(elf)) else {
+        engine.record_diagnostic("text_diff", &reason!("no .text or program headers in our image"));
+        return;
+    };
+    let Some(disk) = elf.get(text.offset..text.offset + text.size) else {
+        engine.record_diagnostic("text_diff", &reason!(".text extends past the end of the file"));
+        return;
+    };
+    // SAFETY: getauxval has no preconditions
+    let base = (unsafe { libc::getauxval(libc::AT_PHDR) }).wrapping_sub(phdr);
+    let runtime = base.wrapping_add(text.addr as u64);
+
+    let mut expected = disk.to_vec();
+    let unverifiable = relocate(&mut expected, text.addr as u64, &relocations(elf), base);
+    // SAFETY: .text of our own executable is mapped at `runtime` for the
+    // life of the process
+    let memory = unsafe { std::slice::from_raw_parts(runtime as *const u8, text.size) };
+    for range in &unverifiable {
+        expected[range.clone()].copy_from_slice(&memory[range.clone()]);
+    }
+
+    let patches = diverging_ranges(memory, &expected, runtime);
+    diag!("[TEXT_DIFF] .text at {:#x} ({} bytes, base {:#x}): {} patched range(s), {} unverifiable relocation(s)",
+          runtime, text.size, base, patches.len(), unverifiable.len());
+    engine.record_feature("text_patched_ranges", patches.len() as f64);
+    if patches.is_empty() {
+        return;
+    }
+
+    let at = |addr: u64| (addr - runtime) as usize;
+    let breakpoints = patches.iter().any(|(addr, len)| memory[at(*addr)..at(*addr) + len].contains(&0xcc));
+    let listed: Vec<String> = patches.iter().take(MAX_LISTED)
+        .map(|(addr, len)| reason!(".text+{:#x} ({:#x}): {} -> {}", at(*addr), addr,
+             hex(&expected[at(*addr)..at(*addr) + len]), hex(&memory[at(*addr)..at(*addr) + len])))
+        .collect();
+    let more = patches.len().saturating_sub(MAX_LISTED);
+    engine.report_with_confidence(
+        DetectionSource::Integrity,
+        if breakpoints { 60 } else { 50 },
+        if breakpoints { 0.95 } else { 0.9 },
+        &reason!("Loaded .text differs from the executable on disk{}: {}{}",
+            if breakpoints { described!(" (software breakpoints)") } else { "" },
+            listed.join(", "),
+            if more > 0 { reason!(" and {} more", more) } else { String::new() })
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relocate() {
+        let mut text = vec![0u8; 32];
+        let relocs = [
+            Rela { offset: 0x1008, kind: R_X86_64_RELATIVE, addend: 0x40 },
+            Rela { offset: 0x1010, kind: 1, addend: 0 },
+            Rela { offset: 0x2000, kind: R_X86_64_RELATIVE, addend: 0 },
+        ];
+        assert_eq!(relocate(&mut text, 0x1000, &relocs, 0x5500_0000_0000), vec![0x10..0x18]);
+        assert_eq!(&text[8..16], &0x5500_0000_0040u64.to_le_bytes());
+        assert!(text[..8].iter().chain(&text[16..]).all(|b| *b == 0));
+    }
+
+    #[test]
+    fn test_own_text_matches_disk() {
+        let image = FileImage::map(&fs::File::open("/proc/self/exe").unwrap()).unwrap();
+        let elf = image.bytes();
+        let text = section_named(elf, ".text").unwrap();
+        let base = unsafe { libc::getauxval(libc::AT_PHDR) } - phdr_vaddr(elf).unwrap();
+        let memory = unsafe { std::slice::from_raw_parts((base + text.addr as u64) as *const u8, text.size) };
+        assert_eq!(memory, &elf[text.offset..text.offset + text.size]);
+    }
+}
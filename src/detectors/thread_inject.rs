@@ -0,0 +1,198 @@
+//! Injected-Thread Detection
+//!
+//! # Overview
+//!
+//! Agents injected into a running process (Frida's `frida-inject`,
+//! `linjector`, shellcode loaders) almost always bring a thread of their
+//! own: `ptrace` is used only long enough to call `pthread_create` or
+//! `clone` in the target, and the agent then runs next to our code. The
+//! framework knows which threads it started ([`known_tids`]: the main
+//! thread, its own helpers and registered workers), so for every task in
+//! `/proc/self/task` we look at:
+//!
+//! - **Extra threads**: tasks the framework cannot account for
+//! - **Names**: Frida's GLib and Gum threads (`gmain`, `gdbus`,
+//!   `gum-js-loop`, `pool-frida`) keep their names
+//! - **Where the thread runs**: the user PC of a blocked thread
+//!   (`/proc/self/task/<tid>/syscall`) and the code pointers on its stack
+//!   (read through `/proc/self/mem`, which fails safely if the stack goes
+//!   away). A start routine in anonymous memory or in a library from
+//!   outside the system paths leaves its return address there
+//!
+//! Embedders that start threads of their own should register them
+//! (`threads::register_thread`), or they will be counted as extra.
+//!
+//! # Why This Fails
+//!
+//! - An agent that hijacks an existing thread instead of creating one
+//! - Renamed threads and agents loaded as regular system-path libraries
+//! - A running (not blocked) thread has no PC in `syscall`; only its stack
+//!   is scanned
+//! - Language runtimes in the host program start threads we do not know of
+
+use std::fs;
+use std::os::unix::fs::FileExt;
+use crate::detectors::maps_anomaly::{parse_regions, unexpected_library, Region};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::threads::known_tids;
+
+/// Thread names used by Frida's agent and the GLib main loop it brings
+const AGENT_THREAD_NAMES: &[&str] = &["gmain", "gdbus", "gum-js-loop", "gum-dbg", "pool-frida", "frida", "linjector"];
+
+/// Bytes of each thread's stack scanned upwards from its stack pointer
+const STACK_SCAN_BYTES: usize = 64 << 10;
+
+/// Agent-style thread name, if any
+pub fn agent_thread_name(comm: &str) -> Option<&'static str> {
+    let comm = comm.trim();
+    AGENT_THREAD_NAMES.iter().copied().find(|name| comm == *name || comm.starts_with(&format!("{}-", name)))
+}
+
+/// (stack pointer, program counter) of a blocked task from its `syscall`
+/// file; `None` while it runs
+pub fn blocked_sp_pc(syscall: &str) -> Option<(u64, u64)> {
+    let fields: Vec<&str> = syscall.split_whitespace().collect();
+    if fields.len() < 3 {
+        return None;
+    }
+    let hex = |s: &str| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok();
+    Some((hex(fields[fields.len() - 2])?, hex(fields[fields.len() - 1])?))
+}
+
+/// Where an executable address lives, if that is somewhere injected code
+/// lives (anonymous memory, a deleted file, a library outside the system
+/// paths); `None` for ordinary code and for non-code addresses
+pub fn foreign_code(regions: &[Region], addr: u64) -> Option<String> {
+    let region = regions.iter().find(|r| (r.start..r.end).contains(&addr))?;
+    if region.perms.as_bytes().get(2) != Some(&b'x') {
+        return None;
+    }
+    if region.path.is_empty() || region.path.starts_with("[anon") {
+        Some(reason!("anonymous memory {:#x}-{:#x}", region.start, region.end))
+    } else if region.path.ends_with(" (deleted)") || unexpected_library(&region.path) {
+        Some(region.path.clone())
+    } else {
+        None
+    }
+}
+
+/// Foreign code locations among the pointer-sized words of a stack dump
+pub fn foreign_stack_pointers(regions: &[Region], stack: &[u8]) -> Vec<String> {
+    let mut found: Vec<String> = stack.chunks_exact(8)
+        .filter_map(|word| foreign_code(regions, u64::from_ne_bytes(word.try_into().ok()?)))
+        .collect();
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// Up to [`STACK_SCAN_BYTES`] of stack above `sp`, within its mapping
+fn read_stack(mem: &fs::File, regions: &[Region], sp: u64) -> Vec<u8> {
+    let Some(region) = regions.iter().find(|r| (r.start..r.end).contains(&sp)) else { return Vec::new() };
+    let mut stack = vec![0u8; ((region.end - sp) as usize).min(STACK_SCAN_BYTES)];
+    match mem.read_at(&mut stack, sp) {
+        Ok(n) => stack.truncate(n),
+        Err(_) => stack.clear(),
+    }
+    stack
+}
+
+fn task_tids() -> Vec<i32> {
+    let Ok(entries) = fs::read_dir("/proc/self/task") else { return Vec::new() };
+    entries.flatten().filter_map(|e| e.file_name().to_str()?.parse().ok()).collect()
+}
+
+/// Main entry point for the injected-thread check
+pub fn check_injected_threads(engine: &mut DecisionEngine) {
+    let maps = fs::read_to_string("/proc/self/maps").unwrap_or_default();
+    let regions = parse_regions(&maps);
+    let mem = fs::File::open("/proc/self/mem");
+    if let Err(e) = &mem {
+        engine.record_diagnostic("thread_inject", &reason!("/proc/self/mem unreadable, stacks not scanned: {}", e));
+    }
+    let known = known_tids();
+    // SAFETY: gettid takes no arguments and cannot fail
+    let me = unsafe { libc::syscall(libc::SYS_gettid) } as i32;
+
+    let tids = task_tids();
+    let mut extra = 0;
+    for tid in tids.iter().copied().filter(|tid| *tid != me) {
+        let task = format!("/proc/self/task/{}", tid);
+        let comm = fs::read_to_string(format!("{}/comm", task)).unwrap_or_default().trim().to_string();
+        let mut findings: Vec<(String, u32, f64)> = Vec::new();
+
+        if !known.contains(&tid) {
+            extra += 1;
+            findings.push((reason!("not started by the framework"), 15, 0.5));
+        }
+        if let Some(name) = agent_thread_name(&comm) {
+            findings.push((reason!("named like an instrumentation agent thread ({})", name), 40, 0.9));
+        }
+        let sp_pc = fs::read_to_string(format!("{}/syscall", task)).ok().and_then(|s| blocked_sp_pc(&s));
+        if let Some(location) = sp_pc.and_then(|(_, pc)| foreign_code(&regions, pc)) {
+            findings.push((reason!("blocked with its PC in {}", location), 40, 0.85));
+        }
+        if let (Some((sp, _)), Ok(mem)) = (sp_pc, &mem) {
+            let pointers = foreign_stack_pointers(&regions, &read_stack(mem, &regions, sp));
+            if !pointers.is_empty() {
+                findings.push((reason!("return addresses into {}", pointers.join(", ")), 30, 0.7));
+            }
+        }
+        diag!("[THREAD_INJECT] tid {} ({}) known={} sp/pc={:x?} findings={}", tid, comm, known.contains(&tid), sp_pc, findings.len());
+
+        if findings.is_empty() {
+            continue;
+        }
+        let weight = findings.iter().map(|(_, w, _)| w).sum::<u32>().min(70);
+        let confidence = findings.iter().map(|(_, _, c)| *c).fold(0.0, f64::max);
+        let details: Vec<&str> = findings.iter().map(|(d, _, _)| d.as_str()).collect();
+        engine.report_with_confidence(
+            DetectionSource::Instrumentation,
+            weight,
+            confidence,
+            &reason!("Injected thread? tid {} ({}): {}", tid, comm, details.join("; "))
+        );
+    }
+    diag!("[THREAD_INJECT] {} task(s), {} known, {} unexplained", tids.len(), known.len(), extra);
+    engine.record_feature("unexplained_threads", extra as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_thread_name() {
+        assert_eq!(agent_thread_name("gum-js-loop\n"), Some("gum-js-loop"));
+        assert_eq!(agent_thread_name("pool-frida"), Some("pool-frida"));
+        assert_eq!(agent_thread_name("frida-agent-main"), Some("frida"));
+        assert_eq!(agent_thread_name("watchdog"), None);
+        assert_eq!(agent_thread_name("gmainloop"), None);
+    }
+
+    #[test]
+    fn test_blocked_sp_pc() {
+        let syscall = "230 0x0 0x0 0x7ffebb9930c0 0x7ffebb993100 0x0 0x0 0x7ffebb9930a8 0x7f59469f8503\n";
+        assert_eq!(blocked_sp_pc(syscall), Some((0x7ffebb9930a8, 0x7f59469f8503)));
+        assert_eq!(blocked_sp_pc("running\n"), None);
+    }
+
+    #[test]
+    #[cfg_attr(feature = "stealth", ignore = "evidence text is coded in stealth builds")]
+    fn test_foreign_code() {
+        let regions = parse_regions("\
+7f0000000000-7f0000001000 r-xp 00000000 00:00 0
+7f0000100000-7f0000200000 r-xp 00001000 fe:00 7 /usr/lib/x86_64-linux-gnu/libc.so.6
+7f0000300000-7f0000301000 r-xp 00001000 fe:00 9 /tmp/agent.so
+7f0000400000-7f0000500000 rw-p 00000000 00:00 0
+");
+        assert_eq!(foreign_code(&regions, 0x7f0000000010).as_deref(), Some("anonymous memory 0x7f0000000000-0x7f0000001000"));
+        assert_eq!(foreign_code(&regions, 0x7f0000100010), None);
+        assert_eq!(foreign_code(&regions, 0x7f0000400010), None);
+        let mut stack = Vec::new();
+        for word in [0x7f0000300010u64, 0x7f0000100010, 42, 0x7f0000300020] {
+            stack.extend_from_slice(&word.to_ne_bytes());
+        }
+        assert_eq!(foreign_stack_pointers(&regions, &stack), vec!["/tmp/agent.so"]);
+    }
+}
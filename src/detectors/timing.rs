@@ -1,5 +1,6 @@
 use crate::ffi::get_rdtsc;
 use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::placement;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -42,24 +43,6 @@ impl TimingStats {
     }
 }
 
-/// Attempts to pin the current thread to a specific CPU core.
-/// Returns true if successful, false otherwise.
-/// 
-/// Why this matters:
-/// - Core migration introduces timing variability (~100-1000 cycles)
-/// - Different cores may have different TSC offsets (rare on modern CPUs)
-/// - Reduces measurement noise for statistical analysis
-fn try_pin_to_cpu(cpu: usize) -> bool {
-    unsafe {
-        let mut cpuset: libc::cpu_set_t = std::mem::zeroed();
-        libc::CPU_ZERO(&mut cpuset);
-        libc::CPU_SET(cpu, &mut cpuset);
-        
-        let result = libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &cpuset);
-        result == 0
-    }
-}
-
 /// Checks if CPU frequency scaling is enabled.
 /// 
 /// Why this matters:
@@ -89,17 +72,20 @@ fn check_frequency_scaling() -> Option<String> {
 /// - High overhead of RDTSC instruction (Hypervisor/Emulation)
 /// - High latency of code execution (Single-stepping/Instrumentation)
 /// - High variance indicating intermittent instrumentation
+#[allow(clippy::implicit_saturating_sub)] // Explicit wrap-around guard
 pub fn check_rdtsc_timing(engine: &mut DecisionEngine) {
-    // Try to pin to CPU 0 to reduce variability
-    let pinned = try_pin_to_cpu(0);
-    if !pinned {
-        eprintln!("[TIMING] Warning: Could not pin to CPU 0, results may vary");
+    return_probe!();
+    // Pin to the quietest available CPU to reduce variability:
+    // core migration costs ~100-1000 cycles and cores may differ in TSC offset
+    match placement::pin_to_measurement_cpu() {
+        Some(cpu) => diag!("[TIMING] Pinned to CPU {}", cpu),
+        None => diag!("[TIMING] Warning: Could not pin to a measurement CPU, results may vary"),
     }
     
     // Check frequency scaling
     if let Some(governor) = check_frequency_scaling() {
         if governor != "performance" {
-            eprintln!("[TIMING] Warning: CPU governor is '{}', not 'performance'. Consider: cpupower frequency-set -g performance", governor);
+            diag!("[TIMING] Warning: CPU governor is '{}', not 'performance'. Consider: cpupower frequency-set -g performance", governor);
         }
     }
     
@@ -123,6 +109,15 @@ pub fn check_rdtsc_timing(engine: &mut DecisionEngine) {
     }
     
     let overhead_stats = TimingStats::from_samples(&overhead_samples);
+    engine.record_samples("RDTSC overhead", &overhead_samples);
+    
+    // Power-saving hosts run slower and noisier; single-step levels stay fixed.
+    // A calibrated baseline replaces the universal elevated levels.
+    let tolerance = engine.timing_tolerance();
+    let overhead_elevated = engine.baseline_limit("rdtsc_overhead_mean", 10.0).unwrap_or(500.0 * tolerance);
+    let overhead_cv_elevated = engine.baseline_limit("rdtsc_overhead_cv", 5.0).unwrap_or(2.0 * tolerance);
+    let exec_slow = engine.baseline_limit("exec_block_mean", 40.0).unwrap_or(50_000.0 * tolerance);
+    let exec_elevated = engine.baseline_limit("exec_block_mean", 8.0).unwrap_or(10_000.0 * tolerance);
     
     // Detection thresholds (empirically derived):
     // Native: mean ~25-50 cycles, CV < 0.5
@@ -134,24 +129,24 @@ pub fn check_rdtsc_timing(engine: &mut DecisionEngine) {
         engine.report(
             DetectionSource::Timing,
             40,
-            &format!("RDTSC overhead critical (Emulation/DBI?): mean={:.0} cycles, max={}", 
+            &reason!("RDTSC overhead critical (Emulation/DBI?): mean={:.0} cycles, max={}", 
                      overhead_stats.mean, overhead_stats.max)
         );
-    } else if overhead_stats.mean > 500.0 {
+    } else if overhead_stats.mean > overhead_elevated {
         engine.report(
             DetectionSource::Timing,
             15,
-            &format!("RDTSC overhead elevated (VM/Instrumentation?): mean={:.0} cycles", 
+            &reason!("RDTSC overhead elevated (VM/Instrumentation?): mean={:.0} cycles", 
                      overhead_stats.mean)
         );
     }
     
     // High variance with moderate mean suggests intermittent instrumentation
-    if overhead_stats.cv > 2.0 && overhead_stats.mean < 500.0 {
+    if overhead_stats.cv > overhead_cv_elevated && overhead_stats.mean < overhead_elevated {
         engine.report(
             DetectionSource::Timing,
             20,
-            &format!("RDTSC overhead has high jitter (intermittent instrumentation?): CV={:.2}", 
+            &reason!("RDTSC overhead has high jitter (intermittent instrumentation?): CV={:.2}", 
                      overhead_stats.cv)
         );
     }
@@ -179,6 +174,7 @@ pub fn check_rdtsc_timing(engine: &mut DecisionEngine) {
     }
     
     let exec_stats = TimingStats::from_samples(&execution_samples);
+    engine.record_samples("100-add work block", &execution_samples);
     
     // Single-stepping detection:
     // - Each instruction causes a debug exception
@@ -190,21 +186,21 @@ pub fn check_rdtsc_timing(engine: &mut DecisionEngine) {
         engine.report(
             DetectionSource::Timing,
             60,
-            &format!("Code block execution extremely slow (Single-stepping?): mean={:.0} cycles", 
+            &reason!("Code block execution extremely slow (Single-stepping?): mean={:.0} cycles", 
                      exec_stats.mean)
         );
-    } else if exec_stats.mean > 50_000.0 {
+    } else if exec_stats.mean > exec_slow {
         engine.report(
             DetectionSource::Timing,
             30,
-            &format!("Code block execution slow (DBI/Heavy instrumentation?): mean={:.0} cycles", 
+            &reason!("Code block execution slow (DBI/Heavy instrumentation?): mean={:.0} cycles", 
                      exec_stats.mean)
         );
-    } else if exec_stats.mean > 10_000.0 {
+    } else if exec_stats.mean > exec_elevated {
         engine.report(
             DetectionSource::Timing,
             10,
-            &format!("Code block execution elevated (Light instrumentation?): mean={:.0} cycles", 
+            &reason!("Code block execution elevated (Light instrumentation?): mean={:.0} cycles", 
                      exec_stats.mean)
         );
     }
@@ -212,28 +208,34 @@ pub fn check_rdtsc_timing(engine: &mut DecisionEngine) {
     // Bimodal distribution detection:
     // If some samples are very fast and some very slow, instrumentation might be sampling
     // Threshold relaxed from 10x to 50x to reduce false positives from CPU frequency scaling
-    if exec_stats.max > exec_stats.min * 50 && exec_stats.samples > 10 {
+    if exec_stats.max as f64 > exec_stats.min as f64 * 50.0 * tolerance && exec_stats.samples > 10 {
         engine.report_with_confidence(
             DetectionSource::Timing,
             10,  // Reduced from 15
             0.6, // Lower confidence due to high false positive rate
-            &format!("Execution timing bimodal (Sampling instrumentation?): min={}, max={}", 
+            &reason!("Execution timing bimodal (Sampling instrumentation?): min={}, max={}", 
                      exec_stats.min, exec_stats.max)
         );
     }
     
+    engine.record_feature("rdtsc_overhead_mean", overhead_stats.mean);
+    engine.record_feature("rdtsc_overhead_cv", overhead_stats.cv);
+    engine.record_feature("exec_block_mean", exec_stats.mean);
+    engine.record_feature("exec_block_cv", exec_stats.cv);
+    
     // Log summary for debugging
-    eprintln!("[TIMING] RDTSC overhead: mean={:.1}, var={:.1}, cv={:.3}", 
-              overhead_stats.mean, overhead_stats.variance, overhead_stats.cv);
-    eprintln!("[TIMING] Execution timing: mean={:.1}, var={:.1}, cv={:.3}", 
-              exec_stats.mean, exec_stats.variance, exec_stats.cv);
+    diag!("[TIMING] RDTSC overhead: mean={:.1}, var={:.1}, cv={:.3}", 
+          overhead_stats.mean, overhead_stats.variance, overhead_stats.cv);
+    diag!("[TIMING] Execution timing: mean={:.1}, var={:.1}, cv={:.3}", 
+          exec_stats.mean, exec_stats.variance, exec_stats.cv);
 }
 
 /// Returns raw timing statistics for use by correlation engine
 #[allow(dead_code)] // Public API for correlation engine
+#[allow(clippy::implicit_saturating_sub)] // Explicit wrap-around guard
 pub fn get_timing_stats() -> (TimingStats, TimingStats) {
     // Pin CPU
-    let _ = try_pin_to_cpu(0);
+    let _ = placement::pin_to_measurement_cpu();
     
     // Warmup
     for _ in 0..100 {
@@ -0,0 +1,334 @@
+//! tracefs Probe Detection (uprobes / kprobes)
+//!
+//! # Overview
+//!
+//! SystemTap, `perf probe` and hand-written tracing scripts instrument a
+//! target without touching it: they register a uprobe on an offset in its
+//! executable (or a kprobe on a kernel function) and the kernel plants the
+//! breakpoint in the page cache copy. No tracer, no /proc trace of our own.
+//!
+//! Probes created through tracefs are listed in
+//! `/sys/kernel/tracing/uprobe_events` and `kprobe_events`. When those are
+//! readable we flag:
+//!
+//! - uprobes/uretprobes on our own executable (matched by device and inode,
+//!   so `/usr/bin` vs `/bin` spellings do not matter) - aimed at us
+//! - uprobes on the libc we have mapped - we call into it constantly
+//! - kprobes on the kernel paths our checks rely on (ptrace, /proc status)
+//!
+//! For the syscalls we make ourselves (`ptrace`, `getpid`,
+//! `clock_gettime`) we go further:
+//!
+//! - **`kprobe_profile`**: hit counters of each kprobe, read before and
+//!   after we issue those syscalls - a probe whose count rises with our
+//!   calls is firing on us right now
+//! - **`enabled_functions`**: every kernel function with an ftrace hook,
+//!   which also lists the trampolines bpftrace / BCC `kfunc` (fentry)
+//!   programs attach without any tracefs event
+//! - **Latency**: when tracefs is unreadable, the fastest `getpid` and
+//!   `ptrace` against the fastest of two trivial syscalls nobody probes
+//!   (`gettid`, `getuid`); a probe handler shows up as a fixed surcharge
+//!
+//! # Why This Fails
+//!
+//! - tracefs is root-only on most systems; unprivileged we see nothing
+//!   but the latency inference, which is weak evidence
+//! - bpftrace and BCC attach through `perf_event_open` and never appear in
+//!   the tracefs event lists (fentry programs do appear in
+//!   `enabled_functions`)
+//! - Probes can be registered after this check runs
+//! - Probing every syscall (raw tracepoints) shifts the baseline too
+
+use std::fs;
+use std::hint::black_box;
+use std::os::unix::fs::MetadataExt;
+use std::time::Instant;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::ffi::raw_syscall4;
+
+/// tracefs mount points, newest first
+const TRACEFS_ROOTS: &[&str] = &["/sys/kernel/tracing", "/sys/kernel/debug/tracing"];
+
+/// Kernel functions whose kprobes would watch our checks
+const WATCHED_KERNEL_FNS: &[&str] = &["ptrace", "proc_pid_status", "do_task_stat", "proc_pid_cmdline", "task_state"];
+
+/// Syscall entry points we call ourselves (matches `__x64_sys_*`,
+/// `__do_sys_*`, `__se_sys_*`)
+const OUR_SYSCALLS: &[&str] = &["sys_ptrace", "sys_getpid", "sys_clock_gettime"];
+
+/// Rounds of our syscalls issued between two `kprobe_profile` reads
+const EXERCISE_ROUNDS: u64 = 16;
+
+/// Timed calls per syscall for the latency inference
+const LATENCY_SAMPLES: usize = 500;
+
+/// Surcharge over the unprobed baseline (ns) that suggests a probe handler
+const PROBE_SURCHARGE_NS: u128 = 250;
+
+/// One line of `uprobe_events` / `kprobe_events`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeEvent {
+    /// `p` (probe) or `r` (return probe)
+    pub kind: char,
+    pub name: String,
+    /// File path (uprobes) or symbol (kprobes), offset stripped
+    pub target: String,
+}
+
+/// Parse a tracefs probe list (`p:group/name target[:offset|+offset] [args]`)
+pub fn parse_probe_events(text: &str) -> Vec<ProbeEvent> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let head = fields.next()?;
+            let (kind, name) = head.split_once(':').map_or((head, ""), |(k, n)| (k, n));
+            let target = fields.next()?;
+            // uprobes: /path:0xoffset; kprobes: [module:]symbol[+offset]
+            let target = if target.starts_with('/') {
+                target.rsplit_once(':').map_or(target, |(path, _)| path)
+            } else {
+                target.split('+').next().unwrap_or(target)
+            };
+            Some(ProbeEvent {
+                kind: kind.chars().next()?,
+                name: name.to_string(),
+                target: target.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Whether a kprobe symbol is one of the kernel paths we depend on
+pub fn watches_our_checks(symbol: &str) -> bool {
+    WATCHED_KERNEL_FNS.iter().any(|f| symbol.contains(f))
+}
+
+/// Whether a kernel symbol is the entry of a syscall we make
+pub fn is_our_syscall(symbol: &str) -> bool {
+    OUR_SYSCALLS.iter().any(|f| symbol.ends_with(f))
+}
+
+/// Parse `kprobe_profile` into (event name, hits)
+pub fn parse_kprobe_profile(text: &str) -> Vec<(String, u64)> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some((fields.next()?.to_string(), fields.next()?.parse().ok()?))
+        })
+        .collect()
+}
+
+/// Function names in `enabled_functions`
+pub fn parse_enabled_functions(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Issue each of our syscalls `EXERCISE_ROUNDS` times
+fn exercise_our_syscalls() {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: getpid and clock_gettime write only `ts`; PTRACE_CONT on our
+    // parent (not our tracee) fails without side effects
+    unsafe {
+        let parent = raw_syscall4(libc::SYS_getppid, 0, 0, 0, 0);
+        for _ in 0..EXERCISE_ROUNDS {
+            raw_syscall4(libc::SYS_getpid, 0, 0, 0, 0);
+            raw_syscall4(libc::SYS_clock_gettime, libc::CLOCK_MONOTONIC as i64, &mut ts as *mut libc::timespec as i64, 0, 0);
+            raw_syscall4(libc::SYS_ptrace, libc::PTRACE_CONT as i64, parent, 0, 0);
+        }
+    }
+}
+
+/// Fastest of [`LATENCY_SAMPLES`] raw calls of syscall `nr`, in nanoseconds
+fn fastest_syscall(nr: i64, a1: i64) -> u128 {
+    (0..LATENCY_SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            // SAFETY: only side-effect-free syscalls are timed
+            black_box(unsafe { raw_syscall4(nr, a1, 0, 0, 0) });
+            start.elapsed().as_nanos()
+        })
+        .min()
+        .unwrap_or(0)
+}
+
+/// (syscall, surcharge ns) for our syscalls slower than the unprobed
+/// baseline by more than [`PROBE_SURCHARGE_NS`]
+fn latency_surcharges() -> Vec<(&'static str, u128)> {
+    let baseline = fastest_syscall(libc::SYS_gettid, 0).min(fastest_syscall(libc::SYS_getuid, 0));
+    // SAFETY: getppid has no side effects
+    let parent = unsafe { raw_syscall4(libc::SYS_getppid, 0, 0, 0, 0) };
+    let candidates = [
+        ("getpid", fastest_syscall(libc::SYS_getpid, 0)),
+        ("ptrace", (0..LATENCY_SAMPLES).map(|_| {
+            let start = Instant::now();
+            // SAFETY: PTRACE_CONT on a non-tracee fails without side effects
+            black_box(unsafe { raw_syscall4(libc::SYS_ptrace, libc::PTRACE_CONT as i64, parent, 0, 0) });
+            start.elapsed().as_nanos()
+        }).min().unwrap_or(0)),
+    ];
+    diag!("[TRACEFS] syscall latency baseline={}ns {:?}", baseline, candidates);
+    candidates.into_iter()
+        .map(|(name, ns)| (name, ns.saturating_sub(baseline)))
+        .filter(|(_, surcharge)| *surcharge > PROBE_SURCHARGE_NS)
+        .collect()
+}
+
+/// kprobes on our syscalls and how often each fired while we exercised them
+fn firing_kprobes(kprobes: &[ProbeEvent]) -> Vec<(String, u64)> {
+    let ours: Vec<&str> = kprobes.iter()
+        .filter(|p| is_our_syscall(&p.target))
+        .map(|p| p.name.rsplit('/').next().unwrap_or(&p.name))
+        .collect();
+    if ours.is_empty() {
+        return Vec::new();
+    }
+    let Some(before) = read_events("kprobe_profile").map(|t| parse_kprobe_profile(&t)) else { return Vec::new() };
+    exercise_our_syscalls();
+    let Some(after) = read_events("kprobe_profile").map(|t| parse_kprobe_profile(&t)) else { return Vec::new() };
+    after.into_iter()
+        .filter(|(name, _)| ours.contains(&name.as_str()))
+        .filter_map(|(name, hits)| {
+            let prior = before.iter().find(|(n, _)| *n == name).map_or(0, |(_, h)| *h);
+            let delta = hits.saturating_sub(prior);
+            (delta >= EXERCISE_ROUNDS).then_some((name, delta))
+        })
+        .collect()
+}
+
+fn read_events(file: &str) -> Option<String> {
+    TRACEFS_ROOTS.iter().find_map(|root| fs::read_to_string(format!("{}/{}", root, file)).ok())
+}
+
+/// (device, inode) of a path, following symlinks
+fn file_id(path: &str) -> Option<(u64, u64)> {
+    fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+/// Path of the libc we have mapped
+fn mapped_libc() -> Option<String> {
+    fs::read_to_string("/proc/self/maps").ok()?
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(5))
+        .find(|path| path.rsplit('/').next().is_some_and(|f| f.starts_with("libc.so") || f.starts_with("libc-")))
+        .map(str::to_string)
+}
+
+/// Main entry point for the tracefs probe check
+pub fn check_tracefs_probes(engine: &mut DecisionEngine) {
+    let uprobes = read_events("uprobe_events");
+    let kprobes = read_events("kprobe_events");
+    let enabled = read_events("enabled_functions");
+    engine.record_flag("tracefs_readable", uprobes.is_some() || kprobes.is_some() || enabled.is_some());
+    if uprobes.is_none() && kprobes.is_none() && enabled.is_none() {
+        diag!("[TRACEFS] Probe lists unreadable (not root or tracefs not mounted)");
+        let surcharges = latency_surcharges();
+        engine.record_feature("syscall_probe_surcharges", surcharges.len() as f64);
+        if !surcharges.is_empty() {
+            let details: Vec<String> = surcharges.iter().map(|(name, ns)| reason!("{} +{} ns", name, ns)).collect();
+            engine.report_with_confidence(
+                DetectionSource::KernelProbe,
+                10,
+                0.3,
+                &reason!("Syscalls we rely on cost more than trivial ones (kprobe handler?): {}", details.join(", "))
+            );
+        }
+        return;
+    }
+
+    let exe = file_id("/proc/self/exe");
+    let libc = mapped_libc().and_then(|p| file_id(&p));
+    let mut on_us = 0usize;
+    for probe in parse_probe_events(uprobes.as_deref().unwrap_or_default()) {
+        let target = file_id(&probe.target);
+        if target.is_some() && target == exe {
+            on_us += 1;
+            engine.report_with_confidence(
+                DetectionSource::KernelProbe,
+                50,
+                0.9,
+                &reason!("uprobe {} ({}) installed on our executable", probe.name, probe.kind)
+            );
+        } else if target.is_some() && target == libc {
+            on_us += 1;
+            engine.report_with_confidence(
+                DetectionSource::KernelProbe,
+                20,
+                0.5,
+                &reason!("uprobe {} ({}) installed on libc {}", probe.name, probe.kind, probe.target)
+            );
+        }
+    }
+
+    let kprobes = parse_probe_events(kprobes.as_deref().unwrap_or_default());
+    for (name, hits) in firing_kprobes(&kprobes) {
+        on_us += 1;
+        engine.report_with_confidence(
+            DetectionSource::KernelProbe,
+            35,
+            0.8,
+            &reason!("kprobe {} fired {} times while we made {} rounds of our syscalls", name, hits, EXERCISE_ROUNDS)
+        );
+    }
+    for probe in kprobes {
+        if watches_our_checks(&probe.target) {
+            on_us += 1;
+            engine.report_with_confidence(
+                DetectionSource::KernelProbe,
+                15,
+                0.4,
+                &reason!("kprobe {} on {}, a kernel path our checks use", probe.name, probe.target)
+            );
+        }
+    }
+
+    let hooked: Vec<String> = parse_enabled_functions(enabled.as_deref().unwrap_or_default())
+        .into_iter()
+        .filter(|f| is_our_syscall(f))
+        .collect();
+    if !hooked.is_empty() {
+        on_us += hooked.len();
+        engine.report_with_confidence(
+            DetectionSource::KernelProbe,
+            30,
+            0.7,
+            &reason!("ftrace hooks (kprobe, function tracer or BPF trampoline) on syscalls we make: {}", hooked.join(", "))
+        );
+    }
+    engine.record_feature("tracefs_probes_on_us", on_us as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_probe_events() {
+        let uprobes = "p:uprobes/p_app_0x1139 /usr/bin/app:0x0000000000001139\n\
+                       r:uprobes/malloc_ret /lib/x86_64-linux-gnu/libc.so.6:0x00000000000a50e0 ret=$retval\n";
+        assert_eq!(parse_probe_events(uprobes), vec![
+            ProbeEvent { kind: 'p', name: "uprobes/p_app_0x1139".into(), target: "/usr/bin/app".into() },
+            ProbeEvent { kind: 'r', name: "uprobes/malloc_ret".into(), target: "/lib/x86_64-linux-gnu/libc.so.6".into() },
+        ]);
+
+        let kprobes = "p:kprobes/ptrace_probe __x64_sys_ptrace+4 request=%di\n";
+        let parsed = parse_probe_events(kprobes);
+        assert_eq!(parsed[0].target, "__x64_sys_ptrace");
+        assert!(watches_our_checks(&parsed[0].target));
+        assert!(!watches_our_checks("vfs_write"));
+    }
+
+    #[test]
+    fn test_syscall_probe_lists() {
+        let profile = "  ptrace_probe                                 42               0\n  openat_probe 7 0\n";
+        assert_eq!(parse_kprobe_profile(profile), vec![("ptrace_probe".to_string(), 42), ("openat_probe".to_string(), 7)]);
+        let enabled = "__x64_sys_getpid (1)    R   I   tramp: 0xffffffffc0a01000 (bpf_trampoline_6442+0x0/0x4d)\nvfs_read (1)\n";
+        let functions = parse_enabled_functions(enabled);
+        assert_eq!(functions, vec!["__x64_sys_getpid", "vfs_read"]);
+        assert!(is_our_syscall(&functions[0]));
+        assert!(!is_our_syscall(&functions[1]));
+        assert!(is_our_syscall("__do_sys_clock_gettime"));
+    }
+}
@@ -0,0 +1,148 @@
+//! Tracer Fingerprinting (strace/ltrace vs Interactive Debuggers)
+//!
+//! # Overview
+//!
+//! A non-zero TracerPid says someone is tracing us, not who. The difference
+//! matters: strace and ltrace record what we do, gdb and lldb let a human
+//! stop, inspect and change it. We tell them apart two ways:
+//!
+//! - **Identity**: the tracer's `/proc/<pid>/comm` and `cmdline`
+//! - **Syscall-stop latency**: a syscall tracer (`PTRACE_SYSCALL`) stops us
+//!   on entry and exit of every syscall, two round trips through the tracer
+//!   that cost tens of microseconds. A debugger that just `PTRACE_CONT`s
+//!   leaves syscalls at native speed. Two different syscalls are timed, so
+//!   a filtered tracer (`strace --seccomp-bpf -e ...`) shows up as one slow
+//!   and one fast.
+//!
+//! The result goes to the engine as a [`TracerKind`], which scales all
+//! later `Ptrace` evidence: a passive tracer counts for less than a
+//! debugger.
+//!
+//! # Why This Fails
+//!
+//! - Renaming the tracer binary defeats the identity check
+//! - gdb with `catch syscall` looks like a syscall tracer by latency
+//! - ltrace only stops on syscalls with `-S`; without it only its name and
+//!   the breakpoints it plants in the PLT give it away
+
+use std::fs;
+use std::time::Instant;
+use crate::engine::policy::{DecisionEngine, DetectionSource, TracerKind};
+
+/// Tracer names that only record
+const PASSIVE_TRACERS: &[&str] = &["strace", "ltrace"];
+
+/// Tracer names that give a human control
+const INTERACTIVE_DEBUGGERS: &[&str] = &["gdb", "lldb", "lldb-server", "gdbserver", "linux_server", "linux_server64", "edb", "radare2", "r2"];
+
+/// Calls per syscall timing batch
+const SYSCALL_CALLS: u32 = 200;
+
+/// Per-call cost above which the syscall is stopping in a tracer (native
+/// getppid/getuid take well under a microsecond)
+const SYSCALL_STOP_NS: f64 = 5_000.0;
+
+/// Which tracer family a process name belongs to
+pub fn classify_name(comm: &str) -> TracerKind {
+    let comm = comm.trim();
+    if PASSIVE_TRACERS.contains(&comm) {
+        TracerKind::PassiveTracer
+    } else if INTERACTIVE_DEBUGGERS.contains(&comm) {
+        TracerKind::InteractiveDebugger
+    } else {
+        TracerKind::Unknown
+    }
+}
+
+/// Combine the identity with how many of the timed syscalls stopped
+pub fn classify(by_name: TracerKind, stopped: usize, timed: usize) -> TracerKind {
+    match by_name {
+        TracerKind::Unknown if stopped > 0 => TracerKind::PassiveTracer,
+        TracerKind::Unknown if timed > 0 => TracerKind::InteractiveDebugger,
+        kind => kind,
+    }
+}
+
+/// Mean cost of one `syscall(nr)` in nanoseconds
+fn syscall_ns(nr: libc::c_long) -> f64 {
+    let start = Instant::now();
+    for _ in 0..SYSCALL_CALLS {
+        // SAFETY: getppid/getuid take no arguments and cannot fail
+        std::hint::black_box(unsafe { libc::syscall(nr) });
+    }
+    start.elapsed().as_nanos() as f64 / SYSCALL_CALLS as f64
+}
+
+fn tracer_pid() -> u32 {
+    fs::read_to_string("/proc/self/status").ok()
+        .and_then(|s| s.lines().find_map(|l| l.strip_prefix("TracerPid:").and_then(|v| v.trim().parse().ok())))
+        .unwrap_or(0)
+}
+
+/// Main entry point for tracer fingerprinting
+pub fn check_tracer_kind(engine: &mut DecisionEngine) {
+    let tracer = tracer_pid();
+    if tracer == 0 {
+        engine.record_feature("tracer_kind", 0.0);
+        return;
+    }
+
+    let comm = fs::read_to_string(format!("/proc/{}/comm", tracer)).unwrap_or_default();
+    let cmdline = fs::read(format!("/proc/{}/cmdline", tracer))
+        .map(|raw| String::from_utf8_lossy(&raw).replace('\0', " ").trim().to_string())
+        .unwrap_or_default();
+    let by_name = classify_name(&comm);
+
+    let ceiling = SYSCALL_STOP_NS * engine.syscall_tolerance();
+    let costs = [("getppid", syscall_ns(libc::SYS_getppid)), ("getuid", syscall_ns(libc::SYS_getuid))];
+    let stopped: Vec<&str> = costs.iter().filter(|(_, ns)| *ns > ceiling).map(|(name, _)| *name).collect();
+    diag!("[TRACER] PID {} comm={:?} cmdline={:?} syscall ns: {:?}", tracer, comm.trim(), cmdline, costs);
+    engine.record_feature("syscall_stop_ns", costs.iter().map(|(_, ns)| *ns).fold(0.0, f64::max));
+
+    let kind = classify(by_name, stopped.len(), costs.len());
+    engine.record_feature("tracer_kind", match kind {
+        TracerKind::None => 0.0,
+        TracerKind::PassiveTracer => 1.0,
+        TracerKind::InteractiveDebugger => 2.0,
+        TracerKind::Unknown => 3.0,
+    });
+    engine.set_tracer_kind(kind);
+
+    let stops = match stopped.len() {
+        0 => reason!("syscalls run at native speed"),
+        n if n == costs.len() => reason!("every syscall stops in the tracer"),
+        _ => reason!("only {} stops in the tracer (filtered tracing)", stopped.join(", ")),
+    };
+    let details = reason!("Tracer PID {} ({}) is {:?}: {}", tracer, if cmdline.is_empty() { comm.trim() } else { &cmdline }, kind, stops);
+    match kind {
+        TracerKind::PassiveTracer => engine.report_with_confidence(DetectionSource::SyscallInterposition, 30, 0.9, &details),
+        TracerKind::InteractiveDebugger => engine.report_with_confidence(DetectionSource::Ptrace, 60, 0.9, &details),
+        _ => engine.report_with_confidence(DetectionSource::Ptrace, 40, 0.6, &details),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classification() {
+        assert_eq!(classify_name("strace\n"), TracerKind::PassiveTracer);
+        assert_eq!(classify_name("gdb"), TracerKind::InteractiveDebugger);
+        assert_eq!(classify_name("bash"), TracerKind::Unknown);
+
+        // Identity wins; latency decides only for unknown names
+        assert_eq!(classify(TracerKind::InteractiveDebugger, 2, 2), TracerKind::InteractiveDebugger);
+        assert_eq!(classify(TracerKind::Unknown, 1, 2), TracerKind::PassiveTracer);
+        assert_eq!(classify(TracerKind::Unknown, 0, 2), TracerKind::InteractiveDebugger);
+        assert_eq!(classify(TracerKind::Unknown, 0, 0), TracerKind::Unknown);
+    }
+
+    #[test]
+    fn test_passive_tracer_scales_ptrace_evidence() {
+        let mut engine = DecisionEngine::new();
+        engine.set_tracer_kind(TracerKind::PassiveTracer);
+        engine.report(DetectionSource::Ptrace, 80, "TRACEME failed");
+        assert_eq!(engine.get_score(), 40);
+    }
+}
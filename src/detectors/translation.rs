@@ -0,0 +1,110 @@
+//! Translation-Layer Detection (Wine)
+//!
+//! # Overview
+//!
+//! When a payload ships for several platforms, analysts often run whichever
+//! build is convenient inside their usual Wine-based tooling. Wine leaves
+//! three independent marks on a process it hosts:
+//!
+//! - **Exports**: Wine's ntdll exports `wine_get_version`, resolvable with
+//!   `dlsym(RTLD_DEFAULT, ...)` from anything in the global scope
+//! - **Environment**: `WINEPREFIX`, `WINELOADER`, `WINESERVERSOCKET`,
+//!   `WINEDEBUG` set by the launcher or the analyst's shell
+//! - **Mappings**: the Unix side of ntdll (`ntdll.so`), the preloader and
+//!   files under a `wine/` library directory
+//!
+//! One mark alone (a stale `WINEPREFIX` in a developer's shell) is weak;
+//! each additional one raises the weight.
+//!
+//! # Why This Fails
+//!
+//! - Environment variables can be scrubbed by the launcher
+//! - A custom Wine build can rename or drop `wine_get_version`
+//! - Mappings can be hidden from /proc/self/maps like any other
+
+use std::ffi::CStr;
+use std::fs;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Variables set by Wine launchers
+const WINE_ENV_VARS: &[&str] = &["WINEPREFIX", "WINELOADER", "WINESERVERSOCKET", "WINEDEBUG"];
+
+/// Mapping path fragments belonging to Wine
+const WINE_PATHS: &[&str] = &["/ntdll.so", "/wine/", "wine-preloader", "wine64-preloader"];
+
+/// Version reported by an exported `wine_get_version`, if any object exports it
+fn wine_version() -> Option<String> {
+    // SAFETY: dlsym with a NUL-terminated name only looks the symbol up
+    let symbol = unsafe { libc::dlsym(libc::RTLD_DEFAULT, c"wine_get_version".as_ptr()) };
+    if symbol.is_null() {
+        return None;
+    }
+    // SAFETY: Wine declares it as `const char *wine_get_version(void)`
+    let get_version: extern "C" fn() -> *const libc::c_char = unsafe { std::mem::transmute(symbol) };
+    let version = get_version();
+    if version.is_null() {
+        return Some("unknown".to_string());
+    }
+    // SAFETY: Wine returns a static NUL-terminated string
+    Some(unsafe { CStr::from_ptr(version) }.to_string_lossy().into_owned())
+}
+
+/// Distinct mapped files that belong to Wine
+pub fn wine_mappings(maps: &str) -> Vec<String> {
+    let mut paths: Vec<String> = maps.lines()
+        .filter_map(|line| line.split_whitespace().nth(5))
+        .filter(|path| WINE_PATHS.iter().any(|sig| path.contains(sig)))
+        .map(str::to_string)
+        .collect();
+    paths.dedup();
+    paths
+}
+
+/// Main entry point for the Wine check
+pub fn check_wine(engine: &mut DecisionEngine) {
+    let mut indicators = Vec::new();
+
+    if let Some(version) = wine_version() {
+        indicators.push(reason!("wine_get_version exported (Wine {})", version));
+    }
+    let vars: Vec<&str> = WINE_ENV_VARS.iter().copied().filter(|v| std::env::var_os(v).is_some()).collect();
+    if !vars.is_empty() {
+        indicators.push(reason!("environment sets {}", vars.join(", ")));
+    }
+    let mappings = wine_mappings(&fs::read_to_string("/proc/self/maps").unwrap_or_default());
+    if !mappings.is_empty() {
+        indicators.push(reason!("Wine objects mapped: {}", mappings.join(", ")));
+    }
+
+    engine.record_feature("wine_indicators", indicators.len() as f64);
+    if indicators.is_empty() {
+        return;
+    }
+    let (weight, confidence) = match indicators.len() {
+        1 => (10, 0.4),
+        2 => (25, 0.7),
+        _ => (40, 0.9),
+    };
+    engine.report_with_confidence(
+        DetectionSource::Emulation,
+        weight,
+        confidence,
+        &reason!("Running under the Wine translation layer: {}", indicators.join("; "))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wine_mappings() {
+        let maps = "\
+7f0000000000-7f0000100000 r-xp 00000000 08:01 1 /usr/lib/x86_64-linux-gnu/wine/x86_64-unix/ntdll.so
+7f0000100000-7f0000110000 r--p 00100000 08:01 1 /usr/lib/x86_64-linux-gnu/wine/x86_64-unix/ntdll.so
+7f1000000000-7f1000100000 r-xp 00000000 08:01 2 /usr/lib/x86_64-linux-gnu/libc.so.6
+";
+        assert_eq!(wine_mappings(maps), vec!["/usr/lib/x86_64-linux-gnu/wine/x86_64-unix/ntdll.so"]);
+        assert!(wine_version().is_none());
+    }
+}
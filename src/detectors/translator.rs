@@ -0,0 +1,245 @@
+//! Userspace Binary Translator Detection (Box64, FEX, Rosetta, QEMU user)
+//!
+//! # Overview
+//!
+//! A userspace translator runs our x86 code as translated blocks in its
+//! own code cache. x86 promises things translators find expensive or skip:
+//!
+//! - **Self-modifying code**: a store to an instruction is seen by the next
+//!   fetch with no flush at all. A translator must notice the store and
+//!   drop the stale translation - or keeps running the old code.
+//! - **No instruction-cache flush**: because x86 never flushes, translators
+//!   write-protect pages they translated and catch stores with a fault.
+//!   Plain data stores to a page that also holds executed code then cost a
+//!   signal round trip each, against nanoseconds on hardware.
+//! - **W^X enforcement**: once a page is `mprotect`ed non-executable,
+//!   calling into it faults. A translator that kept the translation can
+//!   keep executing it.
+//!
+//! Each probe builds `mov eax, imm32; ret` in a private page at run time.
+//! The results are combined into one `Emulation` report whose weight and
+//! confidence grow with the number of quirks seen.
+//!
+//! # Why This Fails
+//!
+//! - Hardware virtualization runs all of this natively
+//! - Translators that track stores with page granularity and re-check
+//!   protections (QEMU user mode) pass the correctness probes; only the
+//!   timing probes remain
+//! - Kernels that forbid writable+executable mappings (SELinux `execmem`)
+//!   disable the self-modifying probes
+//! - A debugger intercepts SIGSEGV, so the W^X probe is skipped when traced
+//! - The W^X probe installs a SIGSEGV handler and faults on purpose, which
+//!   makes the check intrusive: `stealthy` does not run it
+
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::signal_compat;
+
+/// Patch / call iterations for the timing probes
+const ITERATIONS: u32 = 200;
+
+/// Mean cost of one patch + call above which stores to code are being
+/// caught by a translator (hardware: a pipeline flush, well under 5 µs)
+const SMC_NS: f64 = 5_000.0;
+
+/// Cost ratio of data stores next to executed code vs. elsewhere
+const CODE_PAGE_STORE_RATIO: f64 = 10.0;
+
+/// Offset of the plain data byte inside the code page
+const DATA_OFFSET: usize = 2048;
+
+static FAULTED: AtomicBool = AtomicBool::new(false);
+
+/// `mov eax, imm32; ret`
+pub fn return_imm(value: u32) -> [u8; 6] {
+    let imm = value.to_le_bytes();
+    [0xb8, imm[0], imm[1], imm[2], imm[3], 0xc3]
+}
+
+/// An anonymous page we generate code into
+struct CodePage {
+    base: *mut u8,
+    size: usize,
+}
+
+impl CodePage {
+    fn new(prot: libc::c_int) -> Option<Self> {
+        // SAFETY: sysconf has no preconditions
+        let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        // SAFETY: fresh private anonymous mapping
+        let base = unsafe { libc::mmap(std::ptr::null_mut(), size, prot, libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1, 0) };
+        (base != libc::MAP_FAILED).then(|| CodePage { base: base as *mut u8, size })
+    }
+
+    fn protect(&self, prot: libc::c_int) -> bool {
+        // SAFETY: the range is our own mapping
+        unsafe { libc::mprotect(self.base as *mut libc::c_void, self.size, prot) == 0 }
+    }
+
+    fn write(&self, offset: usize, bytes: &[u8]) {
+        // SAFETY: the caller keeps `offset + bytes.len()` inside the page and
+        // the page writable
+        unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), self.base.add(offset), bytes.len()) };
+    }
+
+    fn call(&self) -> u32 {
+        // SAFETY: the page holds a complete `mov eax, imm32; ret`
+        let f: extern "C" fn() -> u32 = unsafe { std::mem::transmute(self.base) };
+        f()
+    }
+}
+
+impl Drop for CodePage {
+    fn drop(&mut self) {
+        // SAFETY: unmapping our own mapping
+        unsafe { libc::munmap(self.base as *mut libc::c_void, self.size) };
+    }
+}
+
+/// Patch the immediate in place and call; returns (stale results, mean ns)
+fn self_modifying(page: &CodePage) -> (u32, f64) {
+    page.write(0, &return_imm(0));
+    page.call();
+    let mut stale = 0;
+    let start = Instant::now();
+    for i in 1..=ITERATIONS {
+        page.write(1, &i.to_le_bytes());
+        if black_box(page.call()) != i {
+            stale += 1;
+        }
+    }
+    (stale, start.elapsed().as_nanos() as f64 / ITERATIONS as f64)
+}
+
+/// Mean cost of call + data store, storing into `target`
+fn call_and_store(page: &CodePage, target: *mut u8) -> f64 {
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        black_box(page.call());
+        // SAFETY: `target` points into a writable page we own
+        unsafe { target.write_volatile(i as u8) };
+    }
+    start.elapsed().as_nanos().max(1) as f64 / ITERATIONS as f64
+}
+
+extern "C" fn exec_fault_handler(_signum: libc::c_int, _info: *mut libc::siginfo_t, ctx: *mut libc::c_void) {
+    // SAFETY: the kernel passes a valid ucontext; the fault hit the first
+    // instruction of a function we called, so [RSP] is its return address
+    unsafe {
+        let gregs = &mut (*(ctx as *mut libc::ucontext_t)).uc_mcontext.gregs;
+        let rsp = gregs[libc::REG_RSP as usize] as *const i64;
+        gregs[libc::REG_RIP as usize] = *rsp;
+        gregs[libc::REG_RSP as usize] += 8;
+        gregs[libc::REG_RAX as usize] = 0;
+    }
+    FAULTED.store(true, Ordering::SeqCst);
+}
+
+/// Whether calling into a page after `mprotect(PROT_READ | PROT_WRITE)`
+/// still executes (None if the probe could not run). Intrusive: it swaps
+/// the SIGSEGV disposition and faults on purpose.
+fn executes_after_nx(page: &CodePage) -> Option<bool> {
+    page.write(0, &return_imm(0x5a5a));
+    if !page.protect(libc::PROT_READ | libc::PROT_EXEC) || page.call() != 0x5a5a {
+        return None;
+    }
+    if !page.protect(libc::PROT_READ | libc::PROT_WRITE) {
+        return None;
+    }
+    FAULTED.store(false, Ordering::SeqCst);
+    // SAFETY: SIGSEGV is routed to `exec_fault_handler`, which returns to our
+    // caller, for the duration of one call; the old disposition is restored
+    unsafe {
+        let mut sa: libc::sigaction = std::mem::zeroed();
+        sa.sa_sigaction = exec_fault_handler as *const () as usize;
+        libc::sigemptyset(&mut sa.sa_mask);
+        sa.sa_flags = libc::SA_SIGINFO;
+        let mut old_sa: libc::sigaction = std::mem::zeroed();
+        if libc::sigaction(libc::SIGSEGV, &sa, &mut old_sa) != 0 {
+            return None;
+        }
+        black_box(page.call());
+        libc::sigaction(libc::SIGSEGV, &old_sa, std::ptr::null_mut());
+    }
+    Some(!FAULTED.load(Ordering::SeqCst))
+}
+
+/// Weight and confidence for a set of quirk weights
+pub fn combine(weights: &[u32]) -> (u32, f64) {
+    let weight = weights.iter().sum::<u32>().min(60);
+    let confidence = (0.4 + 0.15 * weights.len() as f64).min(0.95);
+    (weight, confidence)
+}
+
+/// Main entry point for the binary-translator check
+pub fn check_binary_translator(engine: &mut DecisionEngine) {
+    let mut quirks: Vec<(String, u32)> = Vec::new();
+
+    match CodePage::new(libc::PROT_READ | libc::PROT_WRITE | libc::PROT_EXEC) {
+        Some(page) => {
+            let (stale, smc_ns) = self_modifying(&page);
+            let other = CodePage::new(libc::PROT_READ | libc::PROT_WRITE);
+            // SAFETY: DATA_OFFSET is inside both pages
+            let near_code = call_and_store(&page, unsafe { page.base.add(DATA_OFFSET) });
+            let elsewhere = other.as_ref().map(|o| call_and_store(&page, unsafe { o.base.add(DATA_OFFSET) }));
+            let store_ratio = elsewhere.map(|e| near_code / e).unwrap_or(1.0);
+            engine.record_feature("smc_patch_ns", smc_ns);
+            engine.record_feature("code_page_store_ratio", store_ratio);
+            diag!("[TRANSLATOR] smc stale={} {:.0}ns/patch, store near code {:.1}ns vs {:?}ns", stale, smc_ns, near_code, elsewhere);
+
+            if stale > 0 {
+                quirks.push((reason!("{}/{} calls ran stale code after patching it", stale, ITERATIONS), 50));
+            }
+            if smc_ns > SMC_NS {
+                quirks.push((reason!("patching code costs {:.1} µs per call", smc_ns / 1000.0), 25));
+            }
+            if store_ratio > CODE_PAGE_STORE_RATIO {
+                quirks.push((reason!("data stores beside executed code are {:.0}x slower (page write-protected for translation)", store_ratio), 25));
+            }
+        }
+        None => engine.record_diagnostic("translator", &reason!("RWX mapping refused, self-modifying probes skipped")),
+    }
+
+    if signal_compat::get_tracer_pid() == 0 {
+        let executed = CodePage::new(libc::PROT_READ | libc::PROT_WRITE).and_then(|page| executes_after_nx(&page));
+        diag!("[TRANSLATOR] executes after NX: {:?}", executed);
+        if executed == Some(true) {
+            quirks.push((reason!("code still executes after mprotect removed PROT_EXEC"), 40));
+        }
+    }
+
+    engine.record_feature("translator_quirks", quirks.len() as f64);
+    if quirks.is_empty() {
+        return;
+    }
+    let (weight, confidence) = combine(&quirks.iter().map(|(_, w)| *w).collect::<Vec<_>>());
+    let details: Vec<&str> = quirks.iter().map(|(d, _)| d.as_str()).collect();
+    engine.report_with_confidence(
+        DetectionSource::Emulation,
+        weight,
+        confidence,
+        &reason!("Userspace binary translator: {}", details.join("; "))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_return_imm() {
+        assert_eq!(return_imm(0x1234_5678), [0xb8, 0x78, 0x56, 0x34, 0x12, 0xc3]);
+        assert_eq!(combine(&[50]), (50, 0.55));
+        assert_eq!(combine(&[50, 25, 40]).0, 60);
+    }
+
+    #[test]
+    fn test_native_self_modifying_code() {
+        if let Some(page) = CodePage::new(libc::PROT_READ | libc::PROT_WRITE | libc::PROT_EXEC) {
+            assert_eq!(self_modifying(&page).0, 0);
+        }
+    }
+}
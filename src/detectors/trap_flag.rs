@@ -30,20 +30,21 @@ extern "C" fn trap_handler(_signum: libc::c_int, _info: *mut libc::siginfo_t, ct
 /// since it will conflict with the debugger's signal handling. Instead, we report
 /// the detection based on tracer presence alone.
 pub fn check_trap_flag(engine: &mut DecisionEngine) {
+    return_probe!();
     // Check if a tracer is already attached
     let tracer_pid = signal_compat::get_tracer_pid();
     
     if tracer_pid > 0 {
         // A tracer is attached - skip the trap flag test to avoid conflicts
         // The tracer will intercept SIGTRAP and may not pass it to our handler
-        eprintln!("[TRAP_FLAG] Tracer detected (PID {}), skipping trap flag test to avoid conflict", tracer_pid);
+        diag!("[TRAP_FLAG] Tracer detected (PID {}), skipping trap flag test to avoid conflict", tracer_pid);
         
         // Report based on tracer presence - lower weight since we're inferring
         engine.report_with_confidence(
             DetectionSource::TrapFlag,
             40,  // Lower than direct detection (60)
             0.8, // High confidence in tracer presence
-            &format!("Trap flag test skipped due to tracer (PID {})", tracer_pid)
+            &reason!("Trap flag test skipped due to tracer (PID {})", tracer_pid)
         );
         return;
     }
@@ -58,7 +59,7 @@ pub fn check_trap_flag(engine: &mut DecisionEngine) {
         sa.sa_flags = libc::SA_SIGINFO; // Use SA_SIGINFO to get context
         
         if libc::sigaction(libc::SIGTRAP, &sa, std::ptr::null_mut()) != 0 {
-            eprintln!("[TRAP_FLAG] Failed to register signal handler");
+            diag!("[TRAP_FLAG] Failed to register signal handler");
             return;
         }
     }
@@ -72,11 +73,13 @@ pub fn check_trap_flag(engine: &mut DecisionEngine) {
     }
 
     // 4. Check result
-    if !TRAP_WAS_HANDLED.load(Ordering::SeqCst) {
+    let handled = TRAP_WAS_HANDLED.load(Ordering::SeqCst);
+    engine.record_flag("trap_flag_missed", !handled);
+    if !handled {
         engine.report(
             DetectionSource::TrapFlag, 
             60, 
-            "Trap Flag exception failed to trigger signal handler (Debugger intercepted?)"
+            &reason!("Trap Flag exception failed to trigger signal handler (Debugger intercepted?)")
         );
     }
 
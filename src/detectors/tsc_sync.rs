@@ -0,0 +1,281 @@
+//! TSC Invariance, TSC_AUX and Cross-Core Skew (RDTSCP)
+//!
+//! # Overview
+//!
+//! Every timing detector trusts the TSC. This check asks whether the TSC
+//! deserves it, using `RDTSCP`, which returns `IA32_TSC_AUX` alongside the
+//! counter. Linux loads `TSC_AUX` with `(node << 12) | cpu` on every CPU, so
+//! each read also says where it was taken:
+//!
+//! | Check                                                | Source       | Weight |
+//! |------------------------------------------------------|--------------|--------|
+//! | TSC reads trap to a supervisor (`PR_SET_TSC`)        | RecordReplay | 40     |
+//! | `TSC_AUX` disagrees with the CPU we are pinned to    | Emulation    | 30     |
+//! | TSC goes backwards (or jumps) when migrating CPUs    | Timing       | 30     |
+//! | No invariant TSC (CPUID 0x80000007 EDX[8]), no VM    | Emulation    | 15     |
+//! | No invariant TSC under a hypervisor                  | Hypervisor   | 5      |
+//!
+//! Skew is measured by reading the TSC on CPU A, migrating to CPU B, reading
+//! it there and migrating back. With synchronised counters the read on B
+//! lies between the two on A; the smallest violation over several rounds
+//! is a lower bound on the skew. Hardware with invariant TSC is synchronised
+//! by firmware and checked by the kernel at boot, so a consistent violation
+//! means a hypervisor gives vCPUs different offsets, or something rewrites
+//! the counter per CPU.
+//!
+//! # Why This Fails
+//!
+//! - Skew smaller than the migration round trip in the forward direction
+//!   is invisible; only backwards steps are caught at any size
+//! - A hypervisor that virtualises `TSC_AUX` and offsets every vCPU
+//!   identically passes everything
+//! - On a single allowed CPU (or a cpuset of one) there is nothing to
+//!   compare
+
+use std::fs;
+use core::arch::x86_64::{CpuidResult, __cpuid, __rdtscp};
+use crate::detectors::cpu_errata::is_hypervisor_leaf;
+use crate::engine::placement::{allowed_cpus, parse_cpu_list};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Migration round trips per CPU pair
+const ROUNDS: usize = 16;
+
+/// CPUs compared against the first one (large machines: a sample)
+const MAX_CPUS: usize = 16;
+
+/// Skew (cycles) below which a violation is not reported
+const SKEW_MIN_CYCLES: u64 = 1_000;
+
+/// `PR_GET_TSC` / `PR_TSC_SIGSEGV`
+const PR_GET_TSC: libc::c_int = 25;
+const PR_TSC_SIGSEGV: libc::c_int = 2;
+
+/// `TSC_AUX` value Linux programs for `cpu` on `node`
+pub fn expected_aux(cpu: usize, node: usize) -> u32 {
+    ((node as u32) << 12) | (cpu as u32 & 0xfff)
+}
+
+/// Whether `aux` is what the kernel would have loaded on `cpu` (the node
+/// is only compared when known)
+pub fn aux_matches(aux: u32, cpu: usize, node: Option<usize>) -> bool {
+    match node {
+        Some(node) => aux == expected_aux(cpu, node),
+        None => aux & 0xfff == cpu as u32 & 0xfff,
+    }
+}
+
+/// Consistent skew of CPU B against CPU A from `(a_before, b, a_after)`
+/// round trips: positive if B is ahead, negative if behind, 0 if any round
+/// was ordered correctly
+pub fn skew_bound(rounds: &[(u64, u64, u64)]) -> i64 {
+    let behind = rounds.iter().map(|&(a1, b, _)| a1.saturating_sub(b)).min().unwrap_or(0);
+    let ahead = rounds.iter().map(|&(_, b, a2)| b.saturating_sub(a2)).min().unwrap_or(0);
+    if behind > 0 {
+        -(behind as i64)
+    } else {
+        ahead as i64
+    }
+}
+
+#[allow(unused_unsafe)] // `__cpuid` is only safe on newer toolchains
+fn cpuid(leaf: u32) -> CpuidResult {
+    unsafe { __cpuid(leaf) }
+}
+
+/// (RDTSCP supported, invariant TSC advertised, hypervisor bit)
+fn tsc_features() -> (bool, bool, bool) {
+    let max_ext = cpuid(0x8000_0000).eax;
+    let rdtscp = max_ext >= 0x8000_0001 && cpuid(0x8000_0001).edx & (1 << 27) != 0;
+    let invariant = max_ext >= 0x8000_0007 && cpuid(0x8000_0007).edx & (1 << 8) != 0;
+    let hypervisor = cpuid(1).ecx & (1 << 31) != 0 || is_hypervisor_leaf(cpuid(0x4000_0000));
+    (rdtscp, invariant, hypervisor)
+}
+
+/// Whether RDTSC/RDTSCP fault with SIGSEGV for this process
+fn tsc_trapped() -> bool {
+    let mut mode: libc::c_int = 0;
+    // SAFETY: PR_GET_TSC writes one int through the pointer
+    let rc = unsafe { libc::prctl(PR_GET_TSC, &mut mode as *mut libc::c_int) };
+    rc == 0 && mode == PR_TSC_SIGSEGV
+}
+
+fn rdtscp() -> (u64, u32) {
+    let mut aux = 0u32;
+    // SAFETY: only called once CPUID advertises RDTSCP
+    let tsc = unsafe { __rdtscp(&mut aux) };
+    (tsc, aux)
+}
+
+fn numa_node(cpu: usize) -> Option<usize> {
+    fs::read_dir(format!("/sys/devices/system/cpu/cpu{}", cpu)).ok()?
+        .filter_map(|e| e.ok())
+        .find_map(|e| e.file_name().to_str()?.strip_prefix("node")?.parse().ok())
+}
+
+fn pin(cpu: usize) -> bool {
+    // SAFETY: plain libc call on a stack-owned cpu_set_t
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    }
+}
+
+/// CPUs we could run on, `TSC_AUX` mismatches and per-CPU skew against
+/// the first of them
+fn measure(candidates: &[usize]) -> (Vec<usize>, Vec<String>, Vec<(usize, i64)>) {
+    let mut cpus = Vec::new();
+    let mut mismatches = Vec::new();
+    for &cpu in candidates {
+        // Outside our cpuset, or offline
+        if !pin(cpu) {
+            continue;
+        }
+        cpus.push(cpu);
+        let (_, aux) = rdtscp();
+        let node = numa_node(cpu);
+        if !aux_matches(aux, cpu, node) {
+            mismatches.push(reason!("CPU {} reads TSC_AUX {:#x} (expected {:#x})", cpu, aux, expected_aux(cpu, node.unwrap_or(0))));
+        }
+    }
+
+    let mut skews = Vec::new();
+    let Some((&first, others)) = cpus.split_first() else { return (cpus, mismatches, skews) };
+    for &cpu in others {
+        let mut rounds = Vec::with_capacity(ROUNDS);
+        for _ in 0..ROUNDS {
+            if !pin(first) {
+                break;
+            }
+            let (a1, _) = rdtscp();
+            if !pin(cpu) {
+                break;
+            }
+            let (b, aux) = rdtscp();
+            if !pin(first) {
+                break;
+            }
+            let (a2, _) = rdtscp();
+            // Only rounds that really ran on `cpu` count
+            if aux & 0xfff == cpu as u32 & 0xfff {
+                rounds.push((a1, b, a2));
+            }
+        }
+        if !rounds.is_empty() {
+            skews.push((cpu, skew_bound(&rounds)));
+        }
+    }
+    (cpus, mismatches, skews)
+}
+
+/// Main entry point for the TSC invariance and RDTSCP check
+pub fn check_tsc_sync(engine: &mut DecisionEngine) {
+    if tsc_trapped() {
+        // Our own RDTSC would fault into whoever set this; do not execute it
+        engine.record_flag("tsc_trapped", true);
+        engine.report_with_confidence(
+            DetectionSource::RecordReplay,
+            40,
+            0.85,
+            &reason!("TSC reads trap to a supervisor (PR_SET_TSC = SIGSEGV): time is being recorded or rewritten")
+        );
+        return;
+    }
+    engine.record_flag("tsc_trapped", false);
+
+    let (has_rdtscp, invariant, hypervisor) = tsc_features();
+    engine.record_flag("tsc_invariant", invariant);
+    if !invariant {
+        let (source, weight, confidence) = if hypervisor {
+            (DetectionSource::Hypervisor, 5, 0.3)
+        } else {
+            (DetectionSource::Emulation, 15, 0.5)
+        };
+        engine.report_with_confidence(
+            source,
+            weight,
+            confidence,
+            &reason!("CPUID 0x80000007 does not advertise an invariant TSC (every x86-64 CPU since ~2008 does)")
+        );
+    }
+    if !has_rdtscp {
+        engine.record_diagnostic("tsc_sync", &reason!("CPUID does not advertise RDTSCP"));
+        return;
+    }
+
+    // The timing detectors have pinned us to one CPU by now; try them all
+    let restore = allowed_cpus();
+    let Some(mut candidates) = fs::read_to_string("/sys/devices/system/cpu/online").ok().map(|s| parse_cpu_list(&s)) else {
+        engine.record_diagnostic("tsc_sync", &reason!("/sys/devices/system/cpu/online unreadable"));
+        return;
+    };
+    candidates.truncate(MAX_CPUS);
+    let (cpus, mismatches, skews) = measure(&candidates);
+    // SAFETY: plain libc calls on a stack-owned cpu_set_t
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for &cpu in &restore {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set);
+    }
+
+    if cpus.is_empty() {
+        engine.record_diagnostic("tsc_sync", &reason!("could not run on any online CPU"));
+        return;
+    }
+    let max_skew = skews.iter().map(|(_, s)| s.unsigned_abs()).max().unwrap_or(0);
+    diag!("[TSC] rdtscp={} invariant={} hypervisor={} cpus={} aux_mismatches={} skews={:?}",
+          has_rdtscp, invariant, hypervisor, cpus.len(), mismatches.len(), skews);
+    engine.record_feature("tsc_aux_mismatches", mismatches.len() as f64);
+    engine.record_feature("tsc_max_skew_cycles", max_skew as f64);
+
+    if !mismatches.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            30,
+            0.75,
+            &reason!("RDTSCP reports a different CPU than the one we run on: {}", mismatches.join(", "))
+        );
+    }
+    let skewed: Vec<String> = skews.iter()
+        .filter(|(_, s)| s.unsigned_abs() >= SKEW_MIN_CYCLES)
+        .map(|(cpu, s)| reason!("CPU {} {} by >= {} cycles", cpu, if *s < 0 { "behind" } else { "ahead" }, s.unsigned_abs()))
+        .collect();
+    if !skewed.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::Timing,
+            30,
+            0.7,
+            &reason!("TSC is not synchronised across CPUs (relative to CPU {}): {}", cpus[0], skewed.join(", "))
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aux_matches() {
+        assert_eq!(expected_aux(5, 1), 0x1005);
+        assert!(aux_matches(0x1005, 5, Some(1)));
+        assert!(!aux_matches(0x0005, 5, Some(1)));
+        assert!(aux_matches(0x1005, 5, None));
+        assert!(!aux_matches(0, 3, None));
+    }
+
+    #[test]
+    fn test_skew_bound() {
+        // Synchronised: every B read between the A reads
+        assert_eq!(skew_bound(&[(100, 150, 200), (300, 320, 400)]), 0);
+        // B behind by at least 500 in every round
+        assert_eq!(skew_bound(&[(1000, 400, 1100), (2000, 1450, 2100)]), -550);
+        // B ahead by at least 5000
+        assert_eq!(skew_bound(&[(100, 5200, 200), (300, 9000, 400)]), 5000);
+        // One ordered round clears it
+        assert_eq!(skew_bound(&[(1000, 400, 1100), (2000, 2050, 2100)]), 0);
+        assert_eq!(skew_bound(&[]), 0);
+    }
+}
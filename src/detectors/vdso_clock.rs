@@ -0,0 +1,303 @@
+//! vDSO vs Raw-Syscall Clock Divergence
+//!
+//! # Overview
+//!
+//! A process can read the time three ways, and a time faker usually hooks
+//! only one of them:
+//!
+//! | Path                                   | Hooked by                                        |
+//! |----------------------------------------|--------------------------------------------------|
+//! | libc `clock_gettime`                   | `LD_PRELOAD` fakers (libfaketime), inline hooks  |
+//! | `__vdso_clock_gettime`, called direct  | vDSO patching (rr), rewritten vvar data          |
+//! | `syscall(SYS_clock_gettime)`           | ptrace / seccomp supervisors, sandbox kernels    |
+//!
+//! We find the vDSO's own entry point from `AT_SYSINFO_EHDR` and read
+//! `CLOCK_REALTIME` and `CLOCK_MONOTONIC` through each path in turn,
+//! bracketing every libc and syscall read between two vDSO reads. All three
+//! paths read the same kernel timekeeper, so natively each value falls
+//! inside its bracket. We also compare their costs: the vDSO reads the
+//! clocksource in user mode and is many times cheaper than the syscall.
+//!
+//! | Observation                                        | Source       | Weight |
+//! |----------------------------------------------------|--------------|--------|
+//! | libc value outside its vDSO bracket                | Sandbox      | 40     |
+//! | Syscall value outside its vDSO bracket             | Sandbox      | 40     |
+//! | vDSO costs as much as the syscall                  | RecordReplay | 30     |
+//! | libc costs several times the direct vDSO call      | Sandbox      | 15     |
+//!
+//! rr replaces the vDSO's functions with syscall stubs so that it can
+//! record the results, which is what makes the vDSO as slow as a syscall.
+//!
+//! # Why This Fails
+//!
+//! - A faker that rewrites the vvar page (or the hypervisor's clock) moves
+//!   all three paths together
+//! - Where the clocksource has no vDSO support (`hpet`, `acpi_pm`) the
+//!   kernel's own vDSO falls back to the syscall; the cost check is skipped
+//! - An inline hook inside the vDSO itself is seen by both vDSO brackets
+
+use std::ffi::CStr;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::ffi::get_rdtsc;
+
+/// `clock_gettime` as exported by the vDSO
+type ClockGettime = unsafe extern "C" fn(libc::clockid_t, *mut libc::timespec) -> libc::c_int;
+
+/// ELF section type of `.dynsym`
+const SHT_DYNSYM: u32 = 11;
+
+/// Bracketed reads per clock
+const ROUNDS: usize = 32;
+
+/// Distance outside the bracket tolerated (a realtime step between reads)
+const SLACK_NS: i64 = 1_000_000;
+
+/// Calls per timed batch, and batches per path (minimum taken)
+const BATCH: usize = 32;
+const BATCHES: usize = 16;
+
+/// Syscall-to-vDSO cost ratio below which the vDSO is not a fast path
+const MIN_SYSCALL_RATIO: f64 = 2.0;
+
+/// libc-to-vDSO cost ratio above which libc is wrapped
+const MAX_LIBC_RATIO: f64 = 3.0;
+
+/// Clocksources the vDSO reads in user mode
+const VDSO_CLOCKSOURCES: &[&str] = &["tsc", "kvm-clock", "hyperv_clocksource_tsc_page"];
+
+/// How far `value` lies outside `[before, after]` widened by `slack`
+/// (0 if inside, negative if before it)
+pub fn out_of_bracket(before: i64, value: i64, after: i64, slack: i64) -> i64 {
+    if value < before - slack {
+        value - before
+    } else if value > after + slack {
+        value - after
+    } else {
+        0
+    }
+}
+
+/// Per-call cost of each path, in TSC cycles
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathCosts {
+    pub vdso: f64,
+    pub libc: f64,
+    pub syscall: f64,
+}
+
+/// A path whose cost does not fit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CostFinding {
+    /// The vDSO is about as slow as the syscall
+    VdsoTrapped(f64),
+    /// libc is much slower than the vDSO it calls
+    LibcWrapped(f64),
+}
+
+/// Judge the costs; `vdso_capable` says the clocksource has a user-mode path
+pub fn assess_costs(costs: &PathCosts, vdso_capable: bool) -> Vec<CostFinding> {
+    let mut findings = Vec::new();
+    if costs.vdso <= 0.0 {
+        return findings;
+    }
+    let syscall_ratio = costs.syscall / costs.vdso;
+    if vdso_capable && syscall_ratio < MIN_SYSCALL_RATIO {
+        findings.push(CostFinding::VdsoTrapped(syscall_ratio));
+    }
+    let libc_ratio = costs.libc / costs.vdso;
+    if libc_ratio > MAX_LIBC_RATIO {
+        findings.push(CostFinding::LibcWrapped(libc_ratio));
+    }
+    findings
+}
+
+/// `__vdso_clock_gettime`, found through the vDSO's dynamic symbol table
+fn vdso_clock_gettime() -> Option<ClockGettime> {
+    // SAFETY: getauxval has no preconditions
+    let base = unsafe { libc::getauxval(libc::AT_SYSINFO_EHDR) } as usize;
+    if base == 0 {
+        return None;
+    }
+    // SAFETY: the kernel maps the complete vDSO image (section headers
+    // included) at `base`; every offset below comes from that image
+    unsafe {
+        let ehdr = &*(base as *const libc::Elf64_Ehdr);
+        let phdrs = std::slice::from_raw_parts((base + ehdr.e_phoff as usize) as *const libc::Elf64_Phdr, ehdr.e_phnum as usize);
+        let load = phdrs.iter().find(|p| p.p_type == libc::PT_LOAD)?;
+        let bias = load.p_vaddr.wrapping_sub(load.p_offset) as usize;
+        let shdrs = std::slice::from_raw_parts((base + ehdr.e_shoff as usize) as *const libc::Elf64_Shdr, ehdr.e_shnum as usize);
+        let dynsym = shdrs.iter().find(|s| s.sh_type == SHT_DYNSYM && s.sh_entsize > 0)?;
+        let strtab = shdrs.get(dynsym.sh_link as usize)?;
+        let syms = std::slice::from_raw_parts((base + dynsym.sh_offset as usize) as *const libc::Elf64_Sym, (dynsym.sh_size / dynsym.sh_entsize) as usize);
+        let sym = syms.iter().find(|sym| {
+            sym.st_name != 0
+                && sym.st_value != 0
+                && CStr::from_ptr((base + strtab.sh_offset as usize + sym.st_name as usize) as *const libc::c_char).to_bytes() == b"__vdso_clock_gettime"
+        })?;
+        Some(std::mem::transmute::<usize, ClockGettime>(base + (sym.st_value as usize).wrapping_sub(bias)))
+    }
+}
+
+fn ns(ts: &libc::timespec) -> i64 {
+    ts.tv_sec * 1_000_000_000 + ts.tv_nsec
+}
+
+/// The three ways of reading `clock`
+struct Paths {
+    vdso: ClockGettime,
+}
+
+impl Paths {
+    fn vdso(&self, clock: libc::clockid_t) -> i64 {
+        let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        // SAFETY: the vDSO's clock_gettime, with a valid out-pointer
+        unsafe { (self.vdso)(clock, &mut ts) };
+        ns(&ts)
+    }
+
+    fn libc(&self, clock: libc::clockid_t) -> i64 {
+        let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        // SAFETY: `ts` is a valid out-pointer
+        unsafe { libc::clock_gettime(clock, &mut ts) };
+        ns(&ts)
+    }
+
+    fn syscall(&self, clock: libc::clockid_t) -> i64 {
+        let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        // SAFETY: raw clock_gettime with a valid out-pointer
+        unsafe { libc::syscall(libc::SYS_clock_gettime, clock, &mut ts as *mut libc::timespec) };
+        ns(&ts)
+    }
+
+    /// Largest deviation of the libc and syscall reads from their vDSO
+    /// brackets, and the rounds in which each deviated
+    fn bracket(&self, clock: libc::clockid_t) -> ((i64, usize), (i64, usize)) {
+        let (mut libc_dev, mut syscall_dev) = ((0i64, 0usize), (0i64, 0usize));
+        let note = |dev: &mut (i64, usize), d: i64| {
+            if d != 0 {
+                dev.1 += 1;
+                if d.abs() > dev.0.abs() {
+                    dev.0 = d;
+                }
+            }
+        };
+        for _ in 0..ROUNDS {
+            let v1 = self.vdso(clock);
+            let s = self.syscall(clock);
+            let v2 = self.vdso(clock);
+            let l = self.libc(clock);
+            let v3 = self.vdso(clock);
+            note(&mut syscall_dev, out_of_bracket(v1, s, v2, SLACK_NS));
+            note(&mut libc_dev, out_of_bracket(v2, l, v3, SLACK_NS));
+        }
+        (libc_dev, syscall_dev)
+    }
+
+    /// Cheapest per-call cost of one path over several batches
+    fn cost(&self, read: fn(&Self, libc::clockid_t) -> i64) -> f64 {
+        (0..BATCHES).map(|_| {
+            // SAFETY: RDTSC has no preconditions
+            let start = unsafe { get_rdtsc() };
+            for _ in 0..BATCH {
+                std::hint::black_box(read(self, libc::CLOCK_MONOTONIC));
+            }
+            // SAFETY: as above
+            unsafe { get_rdtsc() }.wrapping_sub(start) as f64 / BATCH as f64
+        }).fold(f64::MAX, f64::min)
+    }
+}
+
+fn clocksource() -> Option<String> {
+    std::fs::read_to_string("/sys/devices/system/clocksource/clocksource0/current_clocksource").ok().map(|s| s.trim().to_string())
+}
+
+/// Main entry point for the vDSO vs syscall clock check
+pub fn check_vdso_clock(engine: &mut DecisionEngine) {
+    let Some(vdso) = vdso_clock_gettime() else {
+        engine.record_diagnostic("vdso_clock", &reason!("no vDSO clock_gettime (AT_SYSINFO_EHDR missing or unparsable)"));
+        return;
+    };
+    let paths = Paths { vdso };
+
+    for (clock, name) in [(libc::CLOCK_REALTIME, "CLOCK_REALTIME"), (libc::CLOCK_MONOTONIC, "CLOCK_MONOTONIC")] {
+        let ((libc_dev, libc_rounds), (syscall_dev, syscall_rounds)) = paths.bracket(clock);
+        diag!("[VDSO_CLOCK] {}: libc off by up to {} ns in {}/{} rounds, syscall by {} ns in {}/{}",
+              name, libc_dev, libc_rounds, ROUNDS, syscall_dev, syscall_rounds, ROUNDS);
+        if libc_rounds > ROUNDS / 2 {
+            engine.report_with_confidence(
+                DetectionSource::Sandbox,
+                40,
+                0.85,
+                &reason!("libc clock_gettime({}) is {:+.3} ms off the vDSO's: the libc path is hooked (LD_PRELOAD time faker?)", name, libc_dev as f64 / 1e6)
+            );
+        }
+        if syscall_rounds > ROUNDS / 2 {
+            engine.report_with_confidence(
+                DetectionSource::Sandbox,
+                40,
+                0.8,
+                &reason!("SYS_clock_gettime({}) is {:+.3} ms off the vDSO's: one path is rewritten (supervisor or patched vDSO)", name, syscall_dev as f64 / 1e6)
+            );
+        }
+    }
+
+    let costs = PathCosts { vdso: paths.cost(Paths::vdso), libc: paths.cost(Paths::libc), syscall: paths.cost(Paths::syscall) };
+    let source = clocksource();
+    let vdso_capable = source.as_deref().is_some_and(|s| VDSO_CLOCKSOURCES.contains(&s));
+    let findings = assess_costs(&costs, vdso_capable);
+    diag!("[VDSO_CLOCK] clocksource={:?} cycles/call: vdso={:.0} libc={:.0} syscall={:.0} -> {:?}",
+          source, costs.vdso, costs.libc, costs.syscall, findings);
+    if costs.vdso > 0.0 {
+        engine.record_feature("vdso_syscall_ratio", costs.syscall / costs.vdso);
+    }
+    for finding in findings {
+        match finding {
+            CostFinding::VdsoTrapped(ratio) => engine.report_with_confidence(
+                DetectionSource::RecordReplay,
+                30,
+                0.7,
+                &reason!("vDSO clock_gettime costs as much as the syscall ({:.1}x) on a {} clocksource: the vDSO is patched into syscalls (rr?)",
+                         ratio, source.as_deref().unwrap_or("?"))
+            ),
+            CostFinding::LibcWrapped(ratio) => engine.report_with_confidence(
+                DetectionSource::Sandbox,
+                15,
+                0.5,
+                &reason!("libc clock_gettime costs {:.1}x the vDSO call it wraps: something sits in between", ratio)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_out_of_bracket() {
+        assert_eq!(out_of_bracket(100, 150, 200, 0), 0);
+        assert_eq!(out_of_bracket(100, 5_000_000, 200, SLACK_NS), 4_999_800);
+        assert_eq!(out_of_bracket(10_000_000, 0, 10_000_100, SLACK_NS), -10_000_000);
+        assert_eq!(out_of_bracket(100, 90, 200, 50), 0);
+    }
+
+    #[test]
+    fn test_assess_costs() {
+        let native = PathCosts { vdso: 50.0, libc: 55.0, syscall: 600.0 };
+        assert!(assess_costs(&native, true).is_empty());
+        let rr = PathCosts { vdso: 700.0, libc: 710.0, syscall: 650.0 };
+        assert!(matches!(assess_costs(&rr, true)[..], [CostFinding::VdsoTrapped(_)]));
+        // No user-mode clocksource: the vDSO falls back to the syscall natively
+        assert!(assess_costs(&rr, false).is_empty());
+        let wrapped = PathCosts { vdso: 50.0, libc: 400.0, syscall: 600.0 };
+        assert_eq!(assess_costs(&wrapped, true), vec![CostFinding::LibcWrapped(8.0)]);
+    }
+
+    #[test]
+    fn test_vdso_symbol() {
+        let vdso = vdso_clock_gettime().expect("vDSO exports clock_gettime");
+        let paths = Paths { vdso };
+        let ((_, libc_rounds), (_, syscall_rounds)) = paths.bracket(libc::CLOCK_MONOTONIC);
+        assert_eq!((libc_rounds, syscall_rounds), (0, 0));
+    }
+}
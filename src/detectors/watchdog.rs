@@ -0,0 +1,250 @@
+//! timerfd vs TSC Watchdog
+//!
+//! # Overview
+//!
+//! A background thread arms a periodic `timerfd` and blocks in `read()`;
+//! each read returns how many expirations happened since the last one. On
+//! every wake-up we take `CLOCK_MONOTONIC` and TSC deltas since the previous
+//! wake-up and check them against each other and against the schedule:
+//!
+//! | Observation                                | Meaning                                  |
+//! |--------------------------------------------|------------------------------------------|
+//! | One expiration, deltas ~= interval         | Normal                                   |
+//! | Many expirations at once, long gap         | Process stopped (breakpoint, SIGSTOP)    |
+//! | Monotonic gap long, TSC gap short (or vice versa) | One clock compensated, the other not |
+//!
+//! A debugger in all-stop mode halts every thread, including this one, so a
+//! breakpoint anywhere in the process shows up as bunched expirations on
+//! resume. Between ticks the thread sleeps in the kernel, so the steady-state
+//! cost is one wake-up per interval.
+//!
+//! Findings accumulate on the watchdog thread and are reported when the
+//! owner calls [`Watchdog::poll`] or [`Watchdog::finish`]. A stall's report
+//! names the detectors that were running during the gap (see
+//! `engine/timeline.rs`), i.e. where the process was stopped.
+//!
+//! # Why This Fails
+//!
+//! - Heavy CPU starvation of the whole process also delays wake-ups; the
+//!   stall threshold is far above normal scheduling latency
+//! - A non-stop-mode debugger that halts only one thread leaves this thread
+//!   running (see `engine/threads.rs` for per-thread checks)
+//! - Stops shorter than the stall threshold go unnoticed
+//! - A hypervisor that pauses the whole VM and compensates both clocks is
+//!   invisible from inside
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::salt::jitter;
+use crate::ffi::get_rdtsc;
+
+/// Timer period
+pub const INTERVAL: Duration = Duration::from_millis(20);
+
+/// Gap between wake-ups that counts as a stop (far above scheduling
+/// latency; salted +-20%)
+const STALL_NS: u64 = jitter(250_000_000, 20, "watchdog.stall_ns");
+
+/// Ticks used to learn the TSC rate before clock deltas are compared
+const CALIBRATION_TICKS: u64 = 10;
+
+/// Relative and absolute disagreement between TSC and monotonic deltas that
+/// counts as a mismatch
+const MISMATCH_RATIO: f64 = 0.25;
+const MISMATCH_MIN_NS: f64 = 10_000_000.0;
+
+/// One anomalous wake-up
+#[derive(Debug, Clone, PartialEq)]
+pub enum TickAnomaly {
+    /// Wake-up came `gap_ns` after the previous one, with `expirations` bunched
+    Stall { gap_ns: u64, expirations: u64 },
+    /// TSC-derived and monotonic deltas disagree
+    ClockMismatch { mono_ns: u64, tsc_ns: u64 },
+}
+
+/// Classify one wake-up. `tsc_per_ns` is `None` until calibrated.
+pub fn classify_tick(expirations: u64, mono_ns: u64, tsc_ticks: u64, tsc_per_ns: Option<f64>) -> Option<TickAnomaly> {
+    if let Some(rate) = tsc_per_ns.filter(|r| *r > 0.0) {
+        let tsc_ns = tsc_ticks as f64 / rate;
+        let diff = (tsc_ns - mono_ns as f64).abs();
+        if diff > MISMATCH_MIN_NS && diff > MISMATCH_RATIO * mono_ns.max(tsc_ns as u64) as f64 {
+            return Some(TickAnomaly::ClockMismatch { mono_ns, tsc_ns: tsc_ns as u64 });
+        }
+    }
+    if mono_ns > STALL_NS && expirations > 1 {
+        return Some(TickAnomaly::Stall { gap_ns: mono_ns, expirations });
+    }
+    None
+}
+
+#[derive(Debug, Default)]
+struct WatchdogState {
+    ticks: u64,
+    max_gap_ns: u64,
+    /// Anomalies with the wake-up they were seen at
+    anomalies: Vec<(Instant, TickAnomaly)>,
+}
+
+/// Running watchdog; stop it with [`Watchdog::finish`]
+pub struct Watchdog {
+    stop: Arc<AtomicBool>,
+    state: Arc<Mutex<WatchdogState>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+fn clock_ns(clock: libc::clockid_t) -> u64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` is a valid out-pointer
+    unsafe { libc::clock_gettime(clock, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+/// Periodic timerfd on CLOCK_MONOTONIC, or -1
+fn arm_timerfd(interval: Duration) -> libc::c_int {
+    let period = libc::timespec { tv_sec: interval.as_secs() as libc::time_t, tv_nsec: interval.subsec_nanos() as libc::c_long };
+    let spec = libc::itimerspec { it_interval: period, it_value: period };
+    // SAFETY: plain syscalls; `spec` outlives the call and a null old-value pointer is allowed
+    unsafe {
+        let fd = libc::timerfd_create(libc::CLOCK_MONOTONIC, libc::TFD_CLOEXEC);
+        if fd >= 0 && libc::timerfd_settime(fd, 0, &spec, std::ptr::null_mut()) != 0 {
+            libc::close(fd);
+            return -1;
+        }
+        fd
+    }
+}
+
+fn run(fd: libc::c_int, stop: &AtomicBool, state: &Mutex<WatchdogState>) {
+    let mut last_mono = clock_ns(libc::CLOCK_MONOTONIC);
+    // SAFETY: RDTSC has no preconditions
+    let mut last_tsc = unsafe { get_rdtsc() };
+    let (mut cal_ticks, mut cal_tsc, mut cal_ns) = (0u64, 0u64, 0u64);
+
+    while !stop.load(Ordering::Relaxed) {
+        let mut expirations: u64 = 0;
+        // SAFETY: reading 8 bytes into a u64 we own
+        let n = unsafe { libc::read(fd, &mut expirations as *mut u64 as *mut libc::c_void, 8) };
+        if n != 8 {
+            // EINTR from a signal handler elsewhere in the process: try again
+            if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            break;
+        }
+        let mono = clock_ns(libc::CLOCK_MONOTONIC);
+        // SAFETY: as above
+        let tsc = unsafe { get_rdtsc() };
+        let mono_ns = mono.saturating_sub(last_mono);
+        let tsc_ticks = tsc.wrapping_sub(last_tsc);
+        (last_mono, last_tsc) = (mono, tsc);
+
+        let rate = (cal_ticks >= CALIBRATION_TICKS && cal_ns > 0).then(|| cal_tsc as f64 / cal_ns as f64);
+        let anomaly = classify_tick(expirations, mono_ns, tsc_ticks, rate);
+        if anomaly.is_none() && cal_ticks < CALIBRATION_TICKS && expirations == 1 {
+            cal_ticks += 1;
+            cal_tsc += tsc_ticks;
+            cal_ns += mono_ns;
+        }
+
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        state.ticks += expirations;
+        state.max_gap_ns = state.max_gap_ns.max(mono_ns);
+        if let Some(anomaly) = anomaly {
+            diag!("[WATCHDOG] {:?}", anomaly);
+            state.anomalies.push((Instant::now(), anomaly));
+        }
+    }
+    // SAFETY: we own the descriptor
+    unsafe { libc::close(fd) };
+}
+
+impl Watchdog {
+    /// Start the watchdog thread. `None` if timerfd is unavailable.
+    pub fn start(interval: Duration) -> Option<Self> {
+        let fd = arm_timerfd(interval);
+        if fd < 0 {
+            diag!("[WATCHDOG] timerfd unavailable, watchdog disabled");
+            return None;
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let state = Arc::new(Mutex::new(WatchdogState::default()));
+        let (thread_stop, thread_state) = (stop.clone(), state.clone());
+        let thread = crate::engine::threads::spawn_masked("watchdog", move || run(fd, &thread_stop, &thread_state))
+            .ok()?;
+        diag!("[WATCHDOG] Armed at {} ms", interval.as_millis());
+        Some(Self { stop, state, thread: Some(thread) })
+    }
+
+    /// Report anomalies seen since the last poll; the watchdog keeps running
+    pub fn poll(&self, engine: &mut DecisionEngine) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        for (woke, anomaly) in state.anomalies.drain(..) {
+            match anomaly {
+                TickAnomaly::Stall { gap_ns, expirations } => {
+                    let gap_start = woke.checked_sub(Duration::from_nanos(gap_ns)).unwrap_or(woke);
+                    let during: Vec<&str> = engine.detectors_during(gap_start, woke).map(|r| r.name.as_str()).collect();
+                    let during = if during.is_empty() { String::new() } else { format!(" during {}", during.join(", ")) };
+                    engine.report_with_confidence(
+                        DetectionSource::Timing,
+                        30,
+                        0.7,
+                        &reason!("Watchdog timer fired {} times at once after a {} ms gap: process was stopped{}",
+                                 expirations, gap_ns / 1_000_000, during),
+                    )
+                }
+                TickAnomaly::ClockMismatch { mono_ns, tsc_ns } => engine.report_with_confidence(
+                    DetectionSource::Timing,
+                    25,
+                    0.7,
+                    &reason!("Watchdog tick: CLOCK_MONOTONIC advanced {} ms but TSC {} ms: one clock is compensated",
+                             mono_ns / 1_000_000, tsc_ns / 1_000_000),
+                ),
+            }
+        }
+        engine.record_feature("watchdog_ticks", state.ticks as f64);
+        engine.record_feature("watchdog_max_gap_ms", state.max_gap_ns as f64 / 1e6);
+    }
+
+    /// Stop the thread and report everything it saw
+    pub fn finish(mut self, engine: &mut DecisionEngine) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        diag!("[WATCHDOG] {} ticks, longest gap {:.1} ms, {} anomalies",
+              state.ticks, state.max_gap_ns as f64 / 1e6, state.anomalies.len());
+        drop(state);
+        self.poll(engine);
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        // Unreported watchdogs just stop; the thread exits on its next tick
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_tick() {
+        let ms = 1_000_000;
+        // Normal tick, 3 GHz TSC
+        assert_eq!(classify_tick(1, 20 * ms, 60_000_000, Some(3.0)), None);
+        // 2 s stop, both clocks agree
+        assert_eq!(classify_tick(100, 2000 * ms, 6_000_000_000, Some(3.0)),
+                   Some(TickAnomaly::Stall { gap_ns: 2000 * ms, expirations: 100 }));
+        // Same stop with the TSC held back
+        assert_eq!(classify_tick(100, 2000 * ms, 60_000_000, Some(3.0)),
+                   Some(TickAnomaly::ClockMismatch { mono_ns: 2000 * ms, tsc_ns: 20 * ms }));
+        // Uncalibrated: no clock comparison
+        assert_eq!(classify_tick(1, 20 * ms, 1, None), None);
+    }
+}
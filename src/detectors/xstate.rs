@@ -0,0 +1,281 @@
+//! XGETBV / XSAVE State Consistency Check
+//!
+//! # Overview
+//!
+//! Three views of the CPU's extended register state should always agree:
+//! 1. **CPUID**: which state components the CPU supports (leaf 0xD) and
+//!    which features use them (AVX, AVX-512)
+//! 2. **XCR0**: which components the OS has actually enabled (XGETBV)
+//! 3. **Behavior**: enabled state is saved into the signal frame and
+//!    restored on sigreturn, even if the handler clobbers the registers
+//!
+//! # Architectural Invariants Checked
+//!
+//! - XCR0 bit 0 (x87) is always set
+//! - XCR0 is a subset of CPUID.(0xD,0):EDX:EAX
+//! - YMM (bit 2) requires SSE (bit 1) and CPUID.1:ECX.AVX
+//! - AVX-512 bits 5-7 are all-or-nothing and require CPUID.7:EBX.AVX512F
+//! - A signal frame carries the FP_XSTATE_MAGIC1 marker, the saved YMM15
+//!   upper lane matches what we loaded, and YMM15 survives the round-trip
+//!
+//! # Why This Fails
+//!
+//! - Hardware virtualization runs XSAVE natively - only emulators are caught
+//! - A careful emulator (QEMU TCG with full xsave support) passes all checks
+//! - Kernels booted with `noxsave` legitimately report a minimal XCR0
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use core::arch::x86_64::CpuidResult;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+use crate::engine::signal_compat;
+use crate::ffi::{clobber_ymm15, read_xcr0, ymm_signal_roundtrip};
+
+const XFEATURE_X87: u64 = 1 << 0;
+const XFEATURE_SSE: u64 = 1 << 1;
+const XFEATURE_YMM: u64 = 1 << 2;
+const XFEATURE_AVX512: u64 = 0b111 << 5;
+
+/// `sw_reserved.magic1` in the fxsave area of an XSAVE signal frame
+const FP_XSTATE_MAGIC1: u32 = 0x4650_5853;
+
+/// Offset of `sw_reserved` within the 512-byte legacy fxsave area
+const SW_RESERVED_OFFSET: usize = 464;
+
+/// Offset of the XSAVE header (xstate_bv) after the legacy area
+const XSAVE_HEADER_OFFSET: usize = 512;
+
+/// Pattern loaded into YMM15 (upper lane is what XSAVE must preserve)
+const YMM_PATTERN: [u64; 4] = [
+    0x1122_3344_5566_7788,
+    0x99aa_bbcc_ddee_ff00,
+    0x0f1e_2d3c_4b5a_6978,
+    0x8796_a5b4_c3d2_e1f0,
+];
+
+/// Offset of the AVX (YMM upper-lane) component in a standard-format XSAVE area
+static AVX_OFFSET: AtomicU64 = AtomicU64::new(0);
+
+static HANDLER_RAN: AtomicBool = AtomicBool::new(false);
+static FRAME_MAGIC_OK: AtomicBool = AtomicBool::new(false);
+static FRAME_XSTATE_BV: AtomicU64 = AtomicU64::new(0);
+static FRAME_YMM15_HI: AtomicU64 = AtomicU64::new(0);
+
+extern "C" fn roundtrip_handler(_signum: libc::c_int, _info: *mut libc::siginfo_t, ctx: *mut libc::c_void) {
+    HANDLER_RAN.store(true, Ordering::SeqCst);
+
+    // SAFETY: the kernel passes a valid ucontext_t; `fpregs` points at the
+    // XSAVE area it wrote on our signal stack, which is at least 512 bytes
+    // plus the XSAVE header whenever magic1 is present.
+    unsafe {
+        let ucontext = ctx as *const libc::ucontext_t;
+        let fpregs = (*ucontext).uc_mcontext.fpregs as *const u8;
+        if !fpregs.is_null() {
+            let magic = (fpregs.add(SW_RESERVED_OFFSET) as *const u32).read_unaligned();
+            if magic == FP_XSTATE_MAGIC1 {
+                FRAME_MAGIC_OK.store(true, Ordering::SeqCst);
+                let bv = (fpregs.add(XSAVE_HEADER_OFFSET) as *const u64).read_unaligned();
+                FRAME_XSTATE_BV.store(bv, Ordering::SeqCst);
+
+                let avx_offset = AVX_OFFSET.load(Ordering::SeqCst) as usize;
+                if avx_offset != 0 {
+                    // Upper 128 bits of YMM15 = 16th 16-byte slot of the AVX component
+                    let hi = (fpregs.add(avx_offset + 15 * 16) as *const u64).read_unaligned();
+                    FRAME_YMM15_HI.store(hi, Ordering::SeqCst);
+                }
+            }
+        }
+
+        // The kernel must restore YMM15 from the frame on sigreturn
+        clobber_ymm15();
+    }
+}
+
+/// Check XCR0 against CPUID. Returns descriptions of violated invariants.
+pub fn xcr0_violations(xcr0: u64, supported: u64, cpuid_avx: bool, cpuid_avx512f: bool) -> Vec<String> {
+    let mut violations = Vec::new();
+
+    if xcr0 & XFEATURE_X87 == 0 {
+        violations.push(reason!("x87 state disabled (architecturally impossible)"));
+    }
+    let unsupported = xcr0 & !supported;
+    if unsupported != 0 {
+        violations.push(reason!("enables components CPUID does not support: {:#x}", unsupported));
+    }
+    if xcr0 & XFEATURE_YMM != 0 {
+        if xcr0 & XFEATURE_SSE == 0 {
+            violations.push(reason!("YMM enabled without SSE"));
+        }
+        if !cpuid_avx {
+            violations.push(reason!("YMM enabled but CPUID.1:ECX.AVX clear"));
+        }
+    }
+    let avx512 = xcr0 & XFEATURE_AVX512;
+    if avx512 != 0 && avx512 != XFEATURE_AVX512 {
+        violations.push(reason!("partial AVX-512 state enabled: {:#x}", avx512));
+    }
+    if avx512 == XFEATURE_AVX512 && !cpuid_avx512f {
+        violations.push(reason!("AVX-512 state enabled but CPUID.7:EBX.AVX512F clear"));
+    }
+
+    violations
+}
+
+/// Send ourselves a signal with YMM15 loaded and verify the kernel's save/restore
+fn check_signal_roundtrip(engine: &mut DecisionEngine) {
+    let tracer_pid = signal_compat::get_tracer_pid();
+    if tracer_pid > 0 {
+        diag!("[XSTATE] Tracer detected (PID {}), skipping signal round-trip to avoid conflict", tracer_pid);
+        return;
+    }
+
+    HANDLER_RAN.store(false, Ordering::SeqCst);
+    FRAME_MAGIC_OK.store(false, Ordering::SeqCst);
+    FRAME_XSTATE_BV.store(0, Ordering::SeqCst);
+    FRAME_YMM15_HI.store(0, Ordering::SeqCst);
+
+    let mut out = [0u64; 4];
+    unsafe {
+        let mut sa: libc::sigaction = std::mem::zeroed();
+        sa.sa_sigaction = roundtrip_handler as *const () as usize;
+        libc::sigemptyset(&mut sa.sa_mask);
+        sa.sa_flags = libc::SA_SIGINFO;
+
+        let mut old_sa: libc::sigaction = std::mem::zeroed();
+        if libc::sigaction(libc::SIGUSR1, &sa, &mut old_sa) != 0 {
+            diag!("[XSTATE] Failed to install SIGUSR1 handler");
+            return;
+        }
+
+        ymm_signal_roundtrip(
+            YMM_PATTERN.as_ptr(),
+            out.as_mut_ptr(),
+            libc::getpid(),
+            libc::gettid(),
+            libc::SIGUSR1,
+        );
+
+        libc::sigaction(libc::SIGUSR1, &old_sa, std::ptr::null_mut());
+    }
+
+    if !HANDLER_RAN.load(Ordering::SeqCst) {
+        diag!("[XSTATE] Round-trip signal was not delivered, skipping");
+        return;
+    }
+
+    let preserved = out == YMM_PATTERN;
+    let magic_ok = FRAME_MAGIC_OK.load(Ordering::SeqCst);
+    let xstate_bv = FRAME_XSTATE_BV.load(Ordering::SeqCst);
+    let frame_hi = FRAME_YMM15_HI.load(Ordering::SeqCst);
+
+    engine.record_flag("ymm_signal_preserved", preserved);
+    diag!("[XSTATE] Signal round-trip: preserved={}, frame magic={}, xstate_bv={:#x}, frame ymm15.hi={:#x}",
+          preserved, magic_ok, xstate_bv, frame_hi);
+
+    if !preserved {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            50,
+            0.85,
+            &reason!("YMM15 not restored after signal (got {:x?}) - extended state not saved/restored", out)
+        );
+    }
+
+    if !magic_ok {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            35,
+            0.7,
+            &reason!("Signal frame lacks FP_XSTATE_MAGIC1 although XCR0 enables YMM")
+        );
+    } else if xstate_bv & XFEATURE_YMM != 0
+        && AVX_OFFSET.load(Ordering::SeqCst) != 0
+        && frame_hi != YMM_PATTERN[2]
+    {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            40,
+            0.8,
+            &reason!("Signal frame YMM15 upper lane {:#x} does not match loaded value", frame_hi)
+        );
+    }
+}
+
+/// Main entry point for the extended-state consistency check
+#[allow(unused_unsafe)] // `__cpuid` is only safe on newer toolchains
+pub fn check_xstate_consistency(engine: &mut DecisionEngine) {
+    let leaf1: CpuidResult = unsafe { core::arch::x86_64::__cpuid(1) };
+    let osxsave = leaf1.ecx & (1 << 27) != 0;
+    let cpuid_avx = leaf1.ecx & (1 << 28) != 0;
+
+    if !osxsave {
+        diag!("[XSTATE] OSXSAVE not set, XGETBV unavailable");
+        if cpuid_avx {
+            engine.report_with_confidence(
+                DetectionSource::Emulation,
+                10,
+                0.3,
+                &reason!("CPUID advertises AVX but OS has not enabled XSAVE")
+            );
+        }
+        return;
+    }
+
+    let max_leaf = unsafe { core::arch::x86_64::__cpuid(0) }.eax;
+    if max_leaf < 0xD {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            30,
+            0.7,
+            &reason!("OSXSAVE set but CPUID max leaf {:#x} lacks leaf 0xD", max_leaf)
+        );
+        return;
+    }
+
+    let leaf_d: CpuidResult = unsafe { core::arch::x86_64::__cpuid_count(0xD, 0) };
+    let supported = leaf_d.eax as u64 | ((leaf_d.edx as u64) << 32);
+    let avx_component: CpuidResult = unsafe { core::arch::x86_64::__cpuid_count(0xD, 2) };
+    AVX_OFFSET.store(avx_component.ebx as u64, Ordering::SeqCst);
+    let cpuid_avx512f = if max_leaf >= 7 {
+        unsafe { core::arch::x86_64::__cpuid_count(7, 0) }.ebx & (1 << 16) != 0
+    } else {
+        false
+    };
+
+    // SAFETY: OSXSAVE is set, so XGETBV is available
+    let xcr0 = unsafe { read_xcr0() };
+    engine.record_feature("xcr0", xcr0 as f64);
+    diag!("[XSTATE] XCR0={:#x}, CPUID supported={:#x}, AVX={}, AVX512F={}",
+          xcr0, supported, cpuid_avx, cpuid_avx512f);
+
+    let violations = xcr0_violations(xcr0, supported, cpuid_avx, cpuid_avx512f);
+    engine.record_feature("xcr0_violations", violations.len() as f64);
+    if !violations.is_empty() {
+        engine.report_with_confidence(
+            DetectionSource::Emulation,
+            45,
+            0.8,
+            &reason!("XCR0 inconsistent with CPUID: {}", violations.join("; "))
+        );
+    }
+
+    if xcr0 & XFEATURE_YMM != 0 && cpuid_avx {
+        check_signal_roundtrip(engine);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xcr0_invariants() {
+        // Typical AVX2 desktop: x87 | SSE | YMM
+        assert!(xcr0_violations(0x7, 0x7, true, false).is_empty());
+        // YMM enabled on a CPU that does not advertise AVX
+        assert_eq!(xcr0_violations(0x7, 0x7, false, false).len(), 1);
+        // Partial AVX-512 state
+        assert!(!xcr0_violations(0x27, 0xe7, true, true).is_empty());
+        // Component outside the supported mask
+        assert!(!xcr0_violations(0x7, 0x3, true, false).is_empty());
+    }
+}
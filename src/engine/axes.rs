@@ -0,0 +1,179 @@
+//! Per-Axis Verdicts
+//!
+//! One scalar verdict blends unrelated findings: a production KVM guest
+//! with a little timing noise comes out `Suspicious`, the same verdict as
+//! a half-hidden debugger. The verdict report classifies each threat axis
+//! on its own:
+//!
+//! | Axis        | Weight from                                                   |
+//! |-------------|---------------------------------------------------------------|
+//! | `debugger`  | Evidence scaled by its source's debugger affinity; at least   |
+//! |             | `likely` once a tracer was identified                         |
+//! | `vm`        | Evidence scaled by VM affinity; at least `likely` once the    |
+//! |             | hypervisor was classified (production or analysis)            |
+//! | `emulator`  | Evidence scaled by emulator affinity                          |
+//! | `dbi`       | Evidence scaled by DBI affinity                               |
+//! | `deception` | Contradictions and `Correlation` evidence                     |
+//!
+//! The affinities are the Bayesian mode's (`bayes.rs`), so both modes
+//! agree on what a source says. Each axis' weight maps to a level:
+//!
+//! | Weight  | Level      |
+//! |---------|------------|
+//! | < 10    | `none`     |
+//! | 10-29   | `possible` |
+//! | 30-59   | `likely`   |
+//! | 60+     | `certain`  |
+//!
+//! The overall verdict is still the classifier's.
+
+use std::fmt;
+use crate::engine::bayes::affinity;
+use crate::engine::policy::{Contradiction, DetectionSource, Evidence, Verdict};
+
+/// A threat axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    Debugger,
+    Vm,
+    Emulator,
+    Dbi,
+    Deception,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 5] = [Axis::Debugger, Axis::Vm, Axis::Emulator, Axis::Dbi, Axis::Deception];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Axis::Debugger => "debugger",
+            Axis::Vm => "vm",
+            Axis::Emulator => "emulator",
+            Axis::Dbi => "dbi",
+            Axis::Deception => "deception",
+        }
+    }
+}
+
+/// How sure the engine is about one axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AxisLevel {
+    None,
+    Possible,
+    Likely,
+    Certain,
+}
+
+impl AxisLevel {
+    pub fn from_weight(weight: u32) -> Self {
+        match weight {
+            0..=9 => AxisLevel::None,
+            10..=29 => AxisLevel::Possible,
+            30..=59 => AxisLevel::Likely,
+            _ => AxisLevel::Certain,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AxisLevel::None => "none",
+            AxisLevel::Possible => "possible",
+            AxisLevel::Likely => "likely",
+            AxisLevel::Certain => "certain",
+        }
+    }
+}
+
+/// One axis' classification
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisVerdict {
+    pub axis: Axis,
+    pub level: AxisLevel,
+    /// Weight attributed to the axis
+    pub weight: u32,
+}
+
+/// Overall verdict plus one classification per axis
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerdictReport {
+    pub overall: Verdict,
+    pub axes: Vec<AxisVerdict>,
+}
+
+impl VerdictReport {
+    /// Classify each axis from `evidence` and `contradictions`. `tracer`
+    /// and `vm` say whether a tracer was identified and a hypervisor
+    /// classified; each lifts its axis to at least `likely`.
+    pub fn build(overall: Verdict, evidence: &[Evidence], contradictions: &[Contradiction], tracer: bool, vm: bool) -> Self {
+        let axes = Axis::ALL.into_iter()
+            .map(|axis| {
+                let weight = match axis {
+                    Axis::Deception => {
+                        let correlation: u32 = evidence.iter().filter(|e| e.source == DetectionSource::Correlation).map(|e| e.weight).sum();
+                        correlation + contradictions.iter().map(|c| c.weight).sum::<u32>()
+                    }
+                    _ => evidence.iter().map(|e| e.weight as f64 * affinity(e.source)[axis as usize]).sum::<f64>().round() as u32,
+                };
+                let floor = match axis {
+                    Axis::Debugger if tracer => AxisLevel::Likely,
+                    Axis::Vm if vm => AxisLevel::Likely,
+                    _ => AxisLevel::None,
+                };
+                AxisVerdict { axis, level: AxisLevel::from_weight(weight).max(floor), weight }
+            })
+            .collect();
+        Self { overall, axes }
+    }
+
+    #[allow(dead_code)] // Public API for external callers
+    pub fn level(&self, axis: Axis) -> AxisLevel {
+        self.axes.iter().find(|a| a.axis == axis).map_or(AxisLevel::None, |a| a.level)
+    }
+}
+
+impl fmt::Display for VerdictReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let axes: Vec<String> = self.axes.iter().map(|a| format!("{}: {}", a.axis.name(), a.level.name())).collect();
+        write!(f, "{:?} ({})", self.overall, axes.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::policy::{DecisionEngine, VmClass};
+
+    #[test]
+    fn test_levels() {
+        assert_eq!(AxisLevel::from_weight(9), AxisLevel::None);
+        assert_eq!(AxisLevel::from_weight(10), AxisLevel::Possible);
+        assert_eq!(AxisLevel::from_weight(59), AxisLevel::Likely);
+        assert_eq!(AxisLevel::from_weight(60), AxisLevel::Certain);
+    }
+
+    #[test]
+    fn test_production_vm_is_not_a_debugger() {
+        let mut engine = DecisionEngine::new();
+        engine.set_vm_class(VmClass::Production);
+        engine.report(DetectionSource::Hypervisor, 60, "KVM signature");
+        engine.report(DetectionSource::Jitter, 10, "some jitter");
+        let report = engine.verdict_report();
+        assert_eq!(report.level(Axis::Vm), AxisLevel::Likely);
+        assert_eq!(report.level(Axis::Debugger), AxisLevel::None);
+        assert_eq!(report.level(Axis::Deception), AxisLevel::None);
+        assert_eq!(report.to_string(), format!("{:?} (debugger: none, vm: likely, emulator: none, dbi: none, deception: none)", report.overall));
+    }
+
+    #[test]
+    fn test_axes_separate() {
+        let mut engine = DecisionEngine::new();
+        engine.report(DetectionSource::Ptrace, 60, "TracerPid: 1234");
+        engine.report(DetectionSource::Dbi, 40, "code cache");
+        engine.record_contradiction(DetectionSource::Timing, DetectionSource::Ptrace, "hidden");
+        let report = engine.verdict_report();
+        assert_eq!(report.level(Axis::Debugger), AxisLevel::Certain);
+        assert_eq!(report.level(Axis::Dbi), AxisLevel::Likely);
+        assert_eq!(report.level(Axis::Vm), AxisLevel::None);
+        assert_eq!(report.level(Axis::Deception), AxisLevel::Likely);
+    }
+}
@@ -0,0 +1,373 @@
+//! Per-Machine Timing Baselines
+//!
+//! The timing detectors' "elevated" thresholds are universal: 500 cycles
+//! of RDTSC overhead, 1000 cycles for 100 NOPs. Atypical hardware (an old
+//! Atom, a nested guest, a big.LITTLE efficiency core) sits above them
+//! when perfectly clean, and a fast server leaves an instrumentation layer
+//! room to hide below them.
+//!
+//! `--calibrate <path>` runs the timing-based detectors several times on a
+//! host the operator knows is clean and writes each measured metric's mean
+//! and standard deviation to a baseline file:
+//!
+//! ```text
+//! # metric = mean +- stddev
+//! runs = 5
+//! rdtsc_overhead_mean = 31.4 +- 2.2
+//! nop_mean = 58.0 +- 4.1
+//! syscall_cycles = 412 +- 35
+//! ```
+//!
+//! With `--baseline <path>` (or `ANTIDEBUG_BASELINE`) loaded, a detector's
+//! elevated threshold becomes a multiple of this machine's upper band
+//! (`mean + 3 * stddev`) instead of the universal figure scaled by the
+//! environment's tolerance. Thresholds for single-stepping and other
+//! effects no hardware explains stay fixed. Metrics missing from the file
+//! keep their universal thresholds.
+//!
+//! # Adaptation
+//!
+//! A one-off calibration ages: firmware updates, a new governor or a
+//! noisier neighbour move the machine's normal. With `--adapt-baseline`
+//! (or `ANTIDEBUG_ADAPT_BASELINE=1`) every `Clean` run folds its metrics
+//! into the file as exponentially weighted moving averages:
+//!
+//! ```text
+//! mean' = mean + ALPHA * (x - mean)
+//! var'  = (1 - ALPHA) * (var + ALPHA * (x - mean)^2)
+//! ```
+//!
+//! so thresholds tighten on a machine that turns out quieter than
+//! calibrated and loosen on one that is noisier. A sample above the
+//! metric's current upper band is not folded in, and the adapted upper
+//! band never exceeds [`MAX_ADAPT_RATIO`] times the calibrated one: runs
+//! that stay just below the verdict thresholds cannot ratchet the baseline
+//! up without limit. The calibrated values are kept in the file after the
+//! adapted ones:
+//!
+//! ```text
+//! nop_mean = 61.2 +- 5.0 (calibrated 58.0 +- 4.1)
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use crate::engine::features::{FeatureVector, FEATURES};
+
+/// Environment variable naming a baseline file when `--baseline` is absent
+pub const BASELINE_ENV_VAR: &str = "ANTIDEBUG_BASELINE";
+
+/// Environment variable; `1` adapts the baseline file after clean runs
+/// (same as `--adapt-baseline`)
+pub const ADAPT_ENV_VAR: &str = "ANTIDEBUG_ADAPT_BASELINE";
+
+/// Detector runs averaged by `--calibrate`
+pub const CALIBRATION_RUNS: usize = 5;
+
+/// Feature columns `--calibrate` records
+pub const CALIBRATED_METRICS: &[&str] = &[
+    "rdtsc_overhead_mean",
+    "rdtsc_overhead_cv",
+    "exec_block_mean",
+    "nop_mean",
+    "nop_cv",
+    "amp_mean",
+    "dr_nop_loop_mean",
+    "syscall_cycles",
+];
+
+/// Weight of each new clean run in the moving averages
+pub const ADAPT_ALPHA: f64 = 0.1;
+
+/// Adapted upper bands stay below this multiple of the calibrated one
+pub const MAX_ADAPT_RATIO: f64 = 2.0;
+
+/// Standard deviations above the mean still counted as this machine's
+/// normal
+const SPREAD_SDS: f64 = 3.0;
+
+/// One calibrated metric
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetricBaseline {
+    pub mean: f64,
+    pub stddev: f64,
+}
+
+impl MetricBaseline {
+    /// Highest value this machine produces when clean
+    pub fn upper(&self) -> f64 {
+        self.mean + SPREAD_SDS * self.stddev
+    }
+
+    /// The same metric with its upper band lowered to at most `cap`
+    fn capped(self, cap: f64) -> Self {
+        let mean = self.mean.min(cap);
+        Self { mean, stddev: self.stddev.min((cap - mean) / SPREAD_SDS) }
+    }
+}
+
+/// Calibrated metrics of one machine
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Baseline {
+    /// Clean runs the statistics were taken over
+    pub runs: u32,
+    metrics: BTreeMap<String, MetricBaseline>,
+    /// Metrics as calibrated, before any adaptation
+    calibrated: BTreeMap<String, MetricBaseline>,
+}
+
+impl Baseline {
+    /// Statistics of [`CALIBRATED_METRICS`] over the feature vectors of
+    /// several clean runs. Metrics no run observed are left out.
+    pub fn from_runs(runs: &[FeatureVector]) -> Self {
+        let mut metrics = BTreeMap::new();
+        for name in CALIBRATED_METRICS {
+            let values: Vec<f64> = runs.iter().filter_map(|fv| fv.get(name)).filter(|v| v.is_finite()).collect();
+            if values.is_empty() {
+                continue;
+            }
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / n;
+            metrics.insert(name.to_string(), MetricBaseline { mean, stddev: variance.sqrt() });
+        }
+        Self { runs: runs.len() as u32, calibrated: metrics.clone(), metrics }
+    }
+
+    pub fn get(&self, name: &str) -> Option<MetricBaseline> {
+        self.metrics.get(name).copied()
+    }
+
+    /// The metric as calibrated, before adaptation
+    #[allow(dead_code)] // Public API for external callers
+    pub fn calibrated(&self, name: &str) -> Option<MetricBaseline> {
+        self.calibrated.get(name).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.metrics.len()
+    }
+
+    #[allow(dead_code)] // Public API for external callers
+    pub fn is_empty(&self) -> bool {
+        self.metrics.is_empty()
+    }
+
+    /// Threshold `multiple` times the metric's upper band, if calibrated
+    pub fn limit(&self, name: &str, multiple: f64) -> Option<f64> {
+        self.get(name).map(|m| m.upper() * multiple)
+    }
+
+    /// Fold one clean run's `features` into the calibrated metrics with
+    /// weight `alpha`. Unobserved metrics and samples above the upper band
+    /// are skipped, and no upper band grows past [`MAX_ADAPT_RATIO`] times
+    /// the calibrated one. Returns how many metrics moved.
+    pub fn adapt(&mut self, features: &FeatureVector, alpha: f64) -> usize {
+        let mut updated = 0;
+        for (name, metric) in self.metrics.iter_mut() {
+            let Some(x) = features.get(name).filter(|v| v.is_finite()) else {
+                continue;
+            };
+            if x > metric.upper() {
+                continue;
+            }
+            let delta = x - metric.mean;
+            let variance = (1.0 - alpha) * (metric.stddev * metric.stddev + alpha * delta * delta);
+            let adapted = MetricBaseline { mean: metric.mean + alpha * delta, stddev: variance.sqrt() };
+            *metric = match self.calibrated.get(name) {
+                Some(calibrated) => adapted.capped(calibrated.upper() * MAX_ADAPT_RATIO),
+                None => adapted,
+            };
+            updated += 1;
+        }
+        if updated > 0 {
+            self.runs += 1;
+        }
+        updated
+    }
+}
+
+impl fmt::Display for Baseline {
+    /// The baseline file format read by [`parse_baseline`]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# Per-machine timing baseline: metric = mean +- stddev")?;
+        writeln!(f, "runs = {}", self.runs)?;
+        for (name, m) in &self.metrics {
+            write!(f, "{} = {:.3} +- {:.3}", name, m.mean, m.stddev)?;
+            match self.calibrated.get(name) {
+                Some(c) if c != m => writeln!(f, " (calibrated {:.3} +- {:.3})", c.mean, c.stddev)?,
+                _ => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_number(text: &str) -> Result<f64, String> {
+    let text = text.trim();
+    match text.parse::<f64>() {
+        Ok(v) if v.is_finite() && v >= 0.0 => Ok(v),
+        _ => Err(format!("'{}' is not a non-negative number", text)),
+    }
+}
+
+/// `mean +- stddev`, or just `mean` for no spread
+fn parse_metric(text: &str) -> Result<MetricBaseline, String> {
+    let (mean, stddev) = text.split_once("+-").unwrap_or((text, "0"));
+    Ok(MetricBaseline { mean: parse_number(mean)?, stddev: parse_number(stddev)? })
+}
+
+/// Parse a baseline file: `runs = N` and `column = mean +- stddev` lines,
+/// optionally followed by `(calibrated mean +- stddev)`, `#` comments.
+/// Without calibrated values the metric counts as freshly calibrated.
+pub fn parse_baseline(text: &str) -> Result<Baseline, String> {
+    let mut baseline = Baseline::default();
+    for (lineno, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let at = |e: String| format!("line {}: {}", lineno + 1, e);
+        let (key, value) = line.split_once('=').ok_or_else(|| at("expected key = value".to_string()))?;
+        let key = key.trim();
+        if key == "runs" {
+            baseline.runs = value.trim().parse().map_err(|_| at(format!("'{}' is not a run count", value.trim())))?;
+            continue;
+        }
+        if !FEATURES.iter().any(|(n, _)| *n == key) {
+            return Err(at(format!("unknown feature column '{}'", key)));
+        }
+        let (value, calibrated) = match value.split_once('(') {
+            Some((value, rest)) => {
+                let inner = rest.trim().strip_suffix(')').and_then(|r| r.trim().strip_prefix("calibrated"))
+                    .ok_or_else(|| at("expected (calibrated mean +- stddev)".to_string()))?;
+                (value, Some(parse_metric(inner).map_err(at)?))
+            }
+            None => (value, None),
+        };
+        let metric = parse_metric(value).map_err(at)?;
+        baseline.metrics.insert(key.to_string(), metric);
+        baseline.calibrated.insert(key.to_string(), calibrated.unwrap_or(metric));
+    }
+    Ok(baseline)
+}
+
+/// Load a baseline file. Returns `None` (and logs why) if the file is
+/// missing or malformed, so the detectors keep their universal thresholds.
+pub fn load_baseline(path: &Path) -> Option<Baseline> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(e) => {
+            diag!("[BASELINE] Cannot read {}: {} - using universal thresholds", path.display(), e);
+            return None;
+        }
+    };
+    match parse_baseline(&text) {
+        Ok(baseline) => {
+            diag!("[BASELINE] Loaded {} metric(s) over {} run(s) from {}", baseline.len(), baseline.runs, path.display());
+            Some(baseline)
+        }
+        Err(e) => {
+            diag!("[BASELINE] Invalid baseline {}: {} - using universal thresholds", path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+    #[test]
+    fn test_from_runs_round_trips() {
+        let runs: Vec<FeatureVector> = [28.0, 32.0].iter().map(|&mean| {
+            let mut fv = FeatureVector::new();
+            fv.set("rdtsc_overhead_mean", mean);
+            fv
+        }).collect();
+        let baseline = Baseline::from_runs(&runs);
+        assert_eq!(baseline.runs, 2);
+        assert_eq!(baseline.len(), 1);
+        assert_eq!(baseline.get("rdtsc_overhead_mean"), Some(MetricBaseline { mean: 30.0, stddev: 2.0 }));
+        assert_eq!(baseline.limit("rdtsc_overhead_mean", 10.0), Some(360.0));
+        assert_eq!(baseline.limit("nop_mean", 10.0), None);
+        assert_eq!(parse_baseline(&baseline.to_string()).unwrap(), baseline);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse_baseline("nop_mean = 60 # no spread").unwrap().get("nop_mean"), Some(MetricBaseline { mean: 60.0, stddev: 0.0 }));
+        assert!(parse_baseline("bogus = 1 +- 0").unwrap_err().starts_with("line 1:"));
+        assert!(parse_baseline("nop_mean = fast").is_err());
+        assert!(parse_baseline("nop_mean = -1 +- 0").is_err());
+        assert!(parse_baseline("runs = many").is_err());
+        assert!(parse_baseline("nop_mean").is_err());
+        assert!(parse_baseline("nop_mean = 60 +- 2 (measured 50)").is_err());
+    }
+
+    #[test]
+    fn test_adapt() {
+        let mut baseline = parse_baseline("runs = 5\nnop_mean = 100 +- 40\nsyscall_cycles = 300 +- 10").unwrap();
+        let mut fv = FeatureVector::new();
+        fv.set("nop_mean", 200.0);
+        assert_eq!(baseline.adapt(&fv, 0.1), 1);
+        assert_eq!(baseline.runs, 6);
+        let nop = baseline.get("nop_mean").unwrap();
+        assert!((nop.mean - 110.0).abs() < 1e-9);
+        assert!((nop.stddev - (0.9f64 * (40.0 * 40.0 + 0.1 * 100.0 * 100.0)).sqrt()).abs() < 1e-9);
+        assert_eq!(baseline.get("syscall_cycles").unwrap().mean, 300.0);
+        assert_eq!(baseline.calibrated("nop_mean"), Some(MetricBaseline { mean: 100.0, stddev: 40.0 }));
+
+        // Quieter runs tighten the band
+        let before = baseline.limit("nop_mean", 1.0).unwrap();
+        fv.set("nop_mean", 110.0);
+        baseline.adapt(&fv, 0.1);
+        assert!(baseline.limit("nop_mean", 1.0).unwrap() < before);
+
+        // A sample above the band is not folded in
+        let mut slow = FeatureVector::new();
+        slow.set("syscall_cycles", 331.0);
+        assert_eq!(baseline.adapt(&slow, 0.1), 0);
+        assert_eq!(baseline.runs, 7);
+
+        // Adapted values survive a round trip with their calibration
+        assert_eq!(parse_baseline(&baseline.to_string()).unwrap().calibrated("nop_mean"), baseline.calibrated("nop_mean"));
+        assert!(baseline.to_string().contains("(calibrated 100.000 +- 40.000)"));
+    }
+
+    #[test]
+    fn test_adapt_cannot_ratchet_up() {
+        let mut baseline = parse_baseline("syscall_cycles = 300 +- 10").unwrap();
+        let mut fv = FeatureVector::new();
+        for _ in 0..200 {
+            // Always just inside the current band
+            fv.set("syscall_cycles", baseline.get("syscall_cycles").unwrap().upper());
+            baseline.adapt(&fv, 0.1);
+        }
+        let cap = 330.0 * MAX_ADAPT_RATIO;
+        assert!(baseline.limit("syscall_cycles", 1.0).unwrap() <= cap + 1e-9);
+        assert!(baseline.get("syscall_cycles").unwrap().mean <= cap);
+    }
+
+    #[test]
+    fn test_engine_adapts_only_clean_runs() {
+        let mut engine = DecisionEngine::new();
+        engine.record_feature("nop_mean", 110.0);
+        assert_eq!(engine.adapted_baseline(ADAPT_ALPHA), None);
+        engine.set_baseline(parse_baseline("nop_mean = 100 +- 5").unwrap());
+        assert_eq!(engine.adapted_baseline(ADAPT_ALPHA).unwrap().get("nop_mean").unwrap().mean, 101.0);
+        engine.report(DetectionSource::Ptrace, 60, "TracerPid: 1234");
+        assert_eq!(engine.adapted_baseline(ADAPT_ALPHA), None);
+    }
+
+    #[test]
+    fn test_engine_thresholds() {
+        let mut engine = DecisionEngine::new();
+        engine.set_timing_tolerance(2.0);
+        assert_eq!(engine.baseline_limit("nop_mean", 10.0), None);
+        engine.set_baseline(parse_baseline("nop_mean = 150 +- 10").unwrap());
+        assert_eq!(engine.baseline_limit("nop_mean", 10.0), Some(1800.0));
+        assert_eq!(engine.baseline_limit("amp_mean", 50.0), None);
+    }
+}
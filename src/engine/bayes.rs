@@ -0,0 +1,291 @@
+//! Bayesian Evidence Combination
+//!
+//! The default engine adds weights: two unrelated weak hits count as much
+//! as one strong one, and nothing says *what* the environment is. In
+//! Bayesian mode every piece of evidence carries a likelihood ratio for
+//! each hypothesis about the environment, relative to a clean one:
+//!
+//! | Hypothesis  | Meaning                                              |
+//! |-------------|------------------------------------------------------|
+//! | `Clean`     | Bare metal or production VM, nobody watching         |
+//! | `Debugger`  | A debugger or tracer (ptrace, breakpoints, rr)       |
+//! | `Vm`        | An analysis VM or sandbox                            |
+//! | `Emulator`  | An emulator or binary translator                     |
+//! | `Dbi`       | Dynamic instrumentation (Pin, DynamoRIO, Frida)      |
+//!
+//! A source's affinity `a` for a hypothesis (0 = says nothing about it,
+//! 1 = points straight at it) turns the evidence's engine weight `w` into
+//! the ratio `exp(a * w / SCALE)`. The posterior is the prior times the
+//! product of all ratios, normalised; the environmental adjustment
+//! factor tempers every ratio the way it scales the additive score.
+//!
+//! [`BayesianClassifier`] maps the posterior to a verdict by the
+//! probability that the environment is not clean. Any contradiction is
+//! still `Deceptive`. The additive [`ThresholdClassifier`] stays the
+//! default; `--scoring bayesian` (or `ANTIDEBUG_SCORING=bayesian`) selects
+//! this one.
+//!
+//! [`ThresholdClassifier`]: crate::engine::classifier::ThresholdClassifier
+
+use crate::engine::classifier::{Classifier, ThresholdCheck};
+use crate::engine::features::FeatureVector;
+use crate::engine::policy::{DetectionSource, Evidence, Verdict};
+
+/// Weight that multiplies a fully affine hypothesis' odds by `e`
+const SCALE: f64 = 12.0;
+
+/// Hypotheses about the environment, in [`Likelihoods`] order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hypothesis {
+    Clean,
+    Debugger,
+    Vm,
+    Emulator,
+    Dbi,
+}
+
+impl Hypothesis {
+    pub const ALL: [Hypothesis; 5] = [Hypothesis::Clean, Hypothesis::Debugger, Hypothesis::Vm, Hypothesis::Emulator, Hypothesis::Dbi];
+}
+
+/// One value per [`Hypothesis`], in [`Hypothesis::ALL`] order
+pub type Likelihoods = [f64; 5];
+
+/// How the engine turns evidence into a verdict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScoringMode {
+    /// Sum of weights against thresholds (historical behavior)
+    #[default]
+    Additive,
+    /// Posterior over [`Hypothesis`] from per-evidence likelihood ratios
+    Bayesian,
+}
+
+impl ScoringMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "additive" => Some(ScoringMode::Additive),
+            "bayesian" => Some(ScoringMode::Bayesian),
+            _ => None,
+        }
+    }
+}
+
+/// Affinity of a source for (Debugger, Vm, Emulator, Dbi), from 0 (says
+/// nothing about it) to 1 (points straight at it)
+pub fn affinity(source: DetectionSource) -> [f64; 4] {
+    use DetectionSource::*;
+    match source {
+        Timing => [0.6, 0.4, 0.5, 0.4],
+        Int3 => [1.0, 0.0, 0.0, 0.2],
+        TrapFlag => [1.0, 0.0, 0.3, 0.3],
+        Ptrace => [1.0, 0.0, 0.0, 0.2],
+        HardwareBreakpoint => [1.0, 0.0, 0.0, 0.0],
+        Jitter => [0.4, 0.5, 0.4, 0.3],
+        RecordReplay => [0.9, 0.0, 0.0, 0.3],
+        EbpfComparison => [0.6, 0.0, 0.0, 0.4],
+        Correlation => [0.5, 0.2, 0.2, 0.2],
+        CrossView => [0.5, 0.0, 0.0, 0.7],
+        OutputCapture => [0.5, 0.0, 0.0, 0.2],
+        Instrumentation => [0.3, 0.0, 0.0, 1.0],
+        Dbi => [0.2, 0.0, 0.3, 1.0],
+        CpuAccounting => [0.6, 0.4, 0.3, 0.3],
+        Emulation => [0.1, 0.3, 1.0, 0.4],
+        SyscallInterposition => [0.8, 0.0, 0.2, 0.3],
+        SyscallFilter => [0.5, 0.0, 0.2, 0.2],
+        SamplingProfiler => [0.6, 0.0, 0.0, 0.3],
+        KernelProbe => [0.6, 0.0, 0.0, 0.3],
+        ExecutionGap => [0.9, 0.4, 0.0, 0.0],
+        RemoteTime => [0.5, 0.4, 0.4, 0.2],
+        RemoteDebug => [1.0, 0.0, 0.0, 0.0],
+        Sandbox => [0.3, 0.7, 0.3, 0.3],
+        Hypervisor => [0.0, 1.0, 0.3, 0.0],
+        Integrity => [0.6, 0.0, 0.0, 0.7],
+        DetectorFault => [0.3, 0.0, 0.3, 0.3],
+    }
+}
+
+/// Likelihood ratios (against `Clean`) of evidence from `source` that
+/// carried `weight` after confidence scaling
+pub fn likelihood_ratios(source: DetectionSource, weight: u32) -> Likelihoods {
+    let a = affinity(source);
+    let ratio = |a: f64| (a * weight as f64 / SCALE).exp();
+    [1.0, ratio(a[0]), ratio(a[1]), ratio(a[2]), ratio(a[3])]
+}
+
+/// Posterior probabilities over [`Hypothesis::ALL`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Posterior(pub Likelihoods);
+
+impl Posterior {
+    /// Combine `prior` with every evidence's ratios, each tempered by
+    /// raising it to `temper` (the environmental adjustment factor)
+    pub fn compute(prior: &Likelihoods, evidence: &[Evidence], temper: f64) -> Self {
+        // Log space: dozens of strong ratios overflow a product
+        let mut log: Vec<f64> = prior.iter().map(|p| p.max(f64::MIN_POSITIVE).ln()).collect();
+        for e in evidence {
+            // Late evidence was already scaled by the factor when reported
+            let temper = if e.late { 1.0 } else { temper };
+            for (l, ratio) in log.iter_mut().zip(e.likelihoods) {
+                *l += ratio.max(f64::MIN_POSITIVE).ln() * temper;
+            }
+        }
+        let max = log.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let unnormalised: Vec<f64> = log.iter().map(|l| (l - max).exp()).collect();
+        let total: f64 = unnormalised.iter().sum();
+        let mut p = [0.0; 5];
+        for (p, u) in p.iter_mut().zip(&unnormalised) {
+            *p = u / total;
+        }
+        Posterior(p)
+    }
+
+    pub fn probability(&self, h: Hypothesis) -> f64 {
+        self.0[h as usize]
+    }
+
+    /// Most probable hypothesis other than `Clean`
+    pub fn leading(&self) -> (Hypothesis, f64) {
+        Hypothesis::ALL[1..].iter()
+            .map(|h| (*h, self.probability(*h)))
+            .fold((Hypothesis::Debugger, f64::NEG_INFINITY), |best, h| if h.1 > best.1 { h } else { best })
+    }
+}
+
+impl std::fmt::Display for Posterior {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts: Vec<String> = Hypothesis::ALL.iter()
+            .map(|h| format!("{:?} {:.1}%", h, self.probability(*h) * 100.0))
+            .collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Verdict from the posterior probability that the environment is not clean
+#[derive(Debug, Clone, Copy)]
+pub struct BayesianClassifier {
+    pub prior: Likelihoods,
+    pub suspicious: f64,
+    pub instrumented: f64,
+    pub deceptive: f64,
+}
+
+impl Default for BayesianClassifier {
+    fn default() -> Self {
+        Self {
+            prior: [0.9, 0.04, 0.03, 0.015, 0.015],
+            suspicious: 0.5,
+            instrumented: 0.9,
+            deceptive: 0.999,
+        }
+    }
+}
+
+impl Classifier for BayesianClassifier {
+    fn name(&self) -> &str {
+        "bayesian"
+    }
+
+    fn classify(&self, features: &FeatureVector, evidence: &[Evidence]) -> Verdict {
+        if features.get("contradictions").unwrap_or(0.0) > 0.0 {
+            return Verdict::Deceptive;
+        }
+        let Some(posterior) = self.posterior(features, evidence) else { return Verdict::Clean };
+        let not_clean = 1.0 - posterior.probability(Hypothesis::Clean);
+        if not_clean >= self.deceptive {
+            Verdict::Deceptive
+        } else if not_clean >= self.instrumented {
+            Verdict::Instrumented
+        } else if not_clean >= self.suspicious {
+            Verdict::Suspicious
+        } else {
+            Verdict::Clean
+        }
+    }
+
+    fn posterior(&self, features: &FeatureVector, evidence: &[Evidence]) -> Option<Posterior> {
+        let temper = features.get("env_adjustment_factor").filter(|f| f.is_finite()).unwrap_or(1.0);
+        Some(Posterior::compute(&self.prior, evidence, temper))
+    }
+
+    fn thresholds(&self, features: &FeatureVector, evidence: &[Evidence]) -> Vec<ThresholdCheck> {
+        let not_clean = self.posterior(features, evidence).map_or(0.0, |p| 1.0 - p.probability(Hypothesis::Clean));
+        vec![
+            ThresholdCheck::contradictions(features),
+            ThresholdCheck::at_least("P(not clean) >= suspicious", not_clean, self.suspicious),
+            ThresholdCheck::at_least("P(not clean) >= instrumented", not_clean, self.instrumented),
+            ThresholdCheck::at_least("P(not clean) >= deceptive", not_clean, self.deceptive),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evidence(source: DetectionSource, weight: u32) -> Evidence {
+        Evidence {
+            source,
+            weight,
+            reported: weight,
+            confidence: 1.0,
+            details: String::new(),
+            thread: None,
+            late: false,
+            likelihoods: likelihood_ratios(source, weight),
+            repeats: 0,
+            at: std::time::Instant::now(),
+            seen: std::time::Instant::now(),
+            seq: 0,
+            detector: None,
+        }
+    }
+
+    fn features(contradictions: f64) -> FeatureVector {
+        let mut fv = FeatureVector::new();
+        fv.set("contradictions", contradictions);
+        fv.set("env_adjustment_factor", 1.0);
+        fv
+    }
+
+    #[test]
+    fn test_likelihood_ratios() {
+        let lr = likelihood_ratios(DetectionSource::Hypervisor, 24);
+        assert_eq!(lr[Hypothesis::Clean as usize], 1.0);
+        assert_eq!(lr[Hypothesis::Debugger as usize], 1.0);
+        assert!((lr[Hypothesis::Vm as usize] - 2f64.exp()).abs() < 1e-9);
+        assert_eq!(likelihood_ratios(DetectionSource::Ptrace, 0), [1.0; 5]);
+    }
+
+    #[test]
+    fn test_posterior_and_verdicts() {
+        let c = BayesianClassifier::default();
+        assert_eq!(c.classify(&features(0.0), &[]), Verdict::Clean);
+        let none = c.posterior(&features(0.0), &[]).unwrap();
+        assert!((none.0.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(none.0.iter().zip(c.prior).all(|(p, q)| (p - q).abs() < 1e-9));
+
+        // One weak timing hit stays clean; a TracerPid-strength hit is suspicious
+        assert_eq!(c.classify(&features(0.0), &[evidence(DetectionSource::Jitter, 15)]), Verdict::Clean);
+        let traced = [evidence(DetectionSource::Ptrace, 40)];
+        assert_eq!(c.classify(&features(0.0), &traced), Verdict::Suspicious);
+        assert_eq!(c.posterior(&features(0.0), &traced).unwrap().leading().0, Hypothesis::Debugger);
+
+        let both = [evidence(DetectionSource::Ptrace, 40), evidence(DetectionSource::Int3, 40)];
+        assert_eq!(c.classify(&features(0.0), &both), Verdict::Instrumented);
+        let vm = [evidence(DetectionSource::Hypervisor, 40), evidence(DetectionSource::Sandbox, 30)];
+        assert_eq!(c.posterior(&features(0.0), &vm).unwrap().leading().0, Hypothesis::Vm);
+
+        // Hundreds of strong hits must not overflow
+        let flood: Vec<Evidence> = (0..500).map(|_| evidence(DetectionSource::Ptrace, 100)).collect();
+        assert_eq!(c.classify(&features(0.0), &flood), Verdict::Deceptive);
+        assert_eq!(c.classify(&features(1.0), &[]), Verdict::Deceptive);
+    }
+
+    #[test]
+    fn test_scoring_mode_names() {
+        assert_eq!(ScoringMode::from_name("Bayesian"), Some(ScoringMode::Bayesian));
+        assert_eq!(ScoringMode::from_name("additive"), Some(ScoringMode::Additive));
+        assert_eq!(ScoringMode::from_name("fuzzy"), None);
+    }
+}
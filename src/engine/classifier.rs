@@ -0,0 +1,140 @@
+//! Verdict Classifiers
+//!
+//! The decision engine accumulates evidence; a [`Classifier`] turns that
+//! evidence into a [`Verdict`]. Splitting the two lets embedders swap in a
+//! different decision rule (quorum voting, Bayesian combination, a learned
+//! model) without forking `policy.rs`.
+//!
+//! A classifier sees the run's [`FeatureVector`] - which includes the
+//! engine-level `score`, `contradictions` and `env_adjustment_factor`
+//! columns - and the full evidence history.
+
+use crate::engine::bayes::Posterior;
+use crate::engine::features::FeatureVector;
+use crate::engine::policy::{Evidence, Verdict};
+
+/// Decision rule mapping accumulated evidence to a verdict
+pub trait Classifier {
+    /// Short identifier for logs and summaries
+    fn name(&self) -> &str;
+
+    fn classify(&self, features: &FeatureVector, evidence: &[Evidence]) -> Verdict;
+
+    /// Probabilities of the environment hypotheses, for classifiers that
+    /// compute them (see `bayes.rs`)
+    fn posterior(&self, _features: &FeatureVector, _evidence: &[Evidence]) -> Option<Posterior> {
+        None
+    }
+
+    /// The cut-offs the verdict was decided by and where the run stands
+    /// against each, for classifiers with explicit thresholds (see
+    /// `explain.rs`)
+    fn thresholds(&self, _features: &FeatureVector, _evidence: &[Evidence]) -> Vec<ThresholdCheck> {
+        Vec::new()
+    }
+}
+
+/// One classifier cut-off against the run's value
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThresholdCheck {
+    /// What is compared, e.g. `score >= instrumented`
+    pub name: String,
+    pub value: f64,
+    pub threshold: f64,
+    pub crossed: bool,
+}
+
+impl ThresholdCheck {
+    /// `value >= threshold`
+    pub fn at_least(name: &str, value: f64, threshold: f64) -> Self {
+        Self { name: name.to_string(), value, threshold, crossed: value >= threshold }
+    }
+
+    /// The contradiction rule every built-in classifier shares
+    pub fn contradictions(features: &FeatureVector) -> Self {
+        let count = features.get("contradictions").filter(|v| !v.is_nan()).unwrap_or(0.0);
+        Self { name: "contradictions > 0".to_string(), value: count, threshold: 0.0, crossed: count > 0.0 }
+    }
+}
+
+/// Default classifier: fixed thresholds on the cumulative score.
+///
+/// - any contradiction: Deceptive
+/// - `score >= deceptive`: Deceptive
+/// - `score >= instrumented`: Instrumented
+/// - `score >= suspicious`: Suspicious
+/// - otherwise: Clean
+#[derive(Debug, Clone, Copy)]
+pub struct ThresholdClassifier {
+    pub suspicious: u32,
+    pub instrumented: u32,
+    pub deceptive: u32,
+}
+
+impl Default for ThresholdClassifier {
+    fn default() -> Self {
+        Self { suspicious: 20, instrumented: 50, deceptive: 90 }
+    }
+}
+
+impl Classifier for ThresholdClassifier {
+    fn name(&self) -> &str {
+        "threshold"
+    }
+
+    fn classify(&self, features: &FeatureVector, _evidence: &[Evidence]) -> Verdict {
+        // Contradictions indicate active deception
+        if features.get("contradictions").unwrap_or(0.0) > 0.0 {
+            return Verdict::Deceptive;
+        }
+
+        let score = features.get("score").unwrap_or(0.0) as u32;
+        if score >= self.deceptive {
+            // Overwhelming evidence OR multiple strong techniques
+            Verdict::Deceptive
+        } else if score >= self.instrumented {
+            Verdict::Instrumented
+        } else if score >= self.suspicious {
+            Verdict::Suspicious
+        } else {
+            Verdict::Clean
+        }
+    }
+
+    fn thresholds(&self, features: &FeatureVector, _evidence: &[Evidence]) -> Vec<ThresholdCheck> {
+        let score = features.get("score").unwrap_or(0.0);
+        vec![
+            ThresholdCheck::contradictions(features),
+            ThresholdCheck::at_least("score >= suspicious", score, self.suspicious as f64),
+            ThresholdCheck::at_least("score >= instrumented", score, self.instrumented as f64),
+            ThresholdCheck::at_least("score >= deceptive", score, self.deceptive as f64),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(score: f64, contradictions: f64) -> FeatureVector {
+        let mut fv = FeatureVector::new();
+        fv.set("score", score);
+        fv.set("contradictions", contradictions);
+        fv
+    }
+
+    #[test]
+    fn test_threshold_boundaries() {
+        let c = ThresholdClassifier::default();
+        assert_eq!(c.classify(&features(19.0, 0.0), &[]), Verdict::Clean);
+        assert_eq!(c.classify(&features(20.0, 0.0), &[]), Verdict::Suspicious);
+        assert_eq!(c.classify(&features(50.0, 0.0), &[]), Verdict::Instrumented);
+        assert_eq!(c.classify(&features(90.0, 0.0), &[]), Verdict::Deceptive);
+    }
+
+    #[test]
+    fn test_contradiction_forces_deceptive() {
+        let c = ThresholdClassifier::default();
+        assert_eq!(c.classify(&features(0.0, 1.0), &[]), Verdict::Deceptive);
+    }
+}
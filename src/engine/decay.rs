@@ -0,0 +1,124 @@
+//! Evidence Time-Decay
+//!
+//! A long-lived process keeps its evidence forever: one jitter burst in
+//! the first second still counts a week later, and every monitor report
+//! adds to a score that only grows. With a decay policy, once the scan is
+//! closed (monitoring mode) each evidence's weight halves every
+//! `half_life` since it was last reported:
+//!
+//! ```text
+//! weight(t) = weight * 0.5 ^ ((t - last_seen) / half_life)
+//! ```
+//!
+//! `decide()` classifies the decayed score and evidence; `get_score()`
+//! keeps the lifetime sum. Evidence that is reported again (a collapsed
+//! duplicate, see `limits.rs`) is fresh again, so a tracer that stays
+//! attached keeps its weight. Contradictions decay with the same half-life
+//! from when they were recorded, and one decayed to nothing no longer
+//! counts as a contradiction. Decay is off unless a half-life is given
+//! with `--decay-half-life <seconds>` (or `ANTIDEBUG_DECAY_HALF_LIFE`).
+
+use std::time::Duration;
+
+/// Environment variable with the half-life in seconds when
+/// `--decay-half-life` is absent
+pub const DECAY_ENV_VAR: &str = "ANTIDEBUG_DECAY_HALF_LIFE";
+
+/// How evidence loses weight with age
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DecayPolicy {
+    /// Age at which evidence counts half; `None` = no decay
+    pub half_life: Option<Duration>,
+}
+
+impl DecayPolicy {
+    pub fn half_life(half_life: Duration) -> Self {
+        Self { half_life: (!half_life.is_zero()).then_some(half_life) }
+    }
+
+    /// Parse a half-life in (possibly fractional) seconds; `0` or `off`
+    /// disables decay
+    pub fn parse(secs: &str) -> Result<Self, String> {
+        let secs = secs.trim();
+        if secs.eq_ignore_ascii_case("off") {
+            return Ok(Self::default());
+        }
+        let value: f64 = secs.parse().map_err(|_| format!("'{}' is not a number of seconds", secs))?;
+        Duration::try_from_secs_f64(value).map(Self::half_life).map_err(|_| format!("'{}' is not a valid half-life", secs))
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.half_life.is_some()
+    }
+
+    /// Share of its weight that evidence last reported `age` ago keeps
+    pub fn factor(&self, age: Duration) -> f64 {
+        match self.half_life {
+            Some(half_life) => 0.5f64.powf(age.as_secs_f64() / half_life.as_secs_f64()),
+            None => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use crate::engine::limits::SourceLimits;
+    use crate::engine::policy::{DecisionEngine, DetectionSource, Verdict};
+
+    #[test]
+    fn test_factor() {
+        let policy = DecayPolicy::half_life(Duration::from_secs(60));
+        assert_eq!(policy.factor(Duration::ZERO), 1.0);
+        assert!((policy.factor(Duration::from_secs(60)) - 0.5).abs() < 1e-9);
+        assert!((policy.factor(Duration::from_secs(180)) - 0.125).abs() < 1e-9);
+        assert_eq!(DecayPolicy::default().factor(Duration::from_secs(1 << 30)), 1.0);
+        assert_eq!(DecayPolicy::parse("1.5").unwrap().half_life, Some(Duration::from_millis(1500)));
+        assert!(!DecayPolicy::parse("0").unwrap().is_active());
+        assert!(!DecayPolicy::parse("off").unwrap().is_active());
+        assert!(DecayPolicy::parse("-3").is_err());
+        assert!(DecayPolicy::parse("soon").is_err());
+    }
+
+    #[test]
+    fn test_verdict_over_decayed_score() {
+        let mut engine = DecisionEngine::new();
+        engine.set_source_limits(SourceLimits::default().collapsing_duplicates());
+        engine.set_decay_policy(DecayPolicy::half_life(Duration::from_secs(60)));
+        engine.report(DetectionSource::Ptrace, 60, "TracerPid: 1234");
+        let later = Instant::now() + Duration::from_secs(120);
+        // No decay while the scan is open
+        assert_eq!(engine.decide_at(later), Verdict::Instrumented);
+
+        engine.close_scan();
+        assert_eq!(engine.decide_at(Instant::now()), Verdict::Instrumented);
+        assert_eq!(engine.score_at(later), 15);
+        assert_eq!(engine.decide_at(later), Verdict::Clean);
+        assert_eq!(engine.get_score(), 60);
+
+        // Reported again: fresh
+        engine.report(DetectionSource::Ptrace, 60, "TracerPid: 1234");
+        assert_eq!(engine.score_at(Instant::now()), 60);
+    }
+
+    #[test]
+    fn test_old_contradiction_stops_driving_the_verdict() {
+        let mut engine = DecisionEngine::new();
+        engine.set_decay_policy(DecayPolicy::half_life(Duration::from_secs(60)));
+        engine.report(DetectionSource::Timing, 10, "slow block");
+        engine.record_contradiction(DetectionSource::Timing, DetectionSource::Ptrace, "hidden tracer");
+        engine.close_scan();
+        assert_eq!(engine.decide_at(Instant::now()), Verdict::Deceptive);
+
+        // Halved, but still a contradiction
+        let one_half_life = Instant::now() + Duration::from_secs(60);
+        assert_eq!(engine.decide_at(one_half_life), Verdict::Deceptive);
+        assert_eq!(engine.score_at(one_half_life), 20);
+
+        // Decayed to nothing: no longer counted
+        let much_later = Instant::now() + Duration::from_secs(600);
+        assert_eq!(engine.decide_at(much_later), Verdict::Clean);
+        assert_eq!(engine.score_at(much_later), 0);
+    }
+}
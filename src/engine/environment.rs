@@ -8,9 +8,134 @@
 //! - **CPU Governor**: `performance` is most stable; `schedutil`/`ondemand` add variance
 //! - **SMT (Hyper-Threading)**: Sibling thread activity introduces timing noise
 //! - **CPU Frequency**: Variable frequency causes TSC-to-wallclock drift
+//! - **cgroup CPU quota**: A throttled container is descheduled for the
+//!   rest of each CFS period once its quota runs out, producing exactly the
+//!   bimodal, high-CV timing the jitter and timing detectors flag
+//! - **Power source / EPP**: On battery, or with a power-leaning
+//!   energy-performance preference, the CPU caps boost and parks idle
+//!   cores deeper even under the `performance` governor. Timing detectors
+//!   widen their thresholds by [`EnvironmentState::timing_tolerance`]
+//! - **Isolated CPUs**: Reported here; timing detectors pin to one via
+//!   [`crate::engine::placement`] instead of being adjusted afterwards
+//! - **Kernel configuration**: PREEMPT_RT, `nohz_full` and entry-path
+//!   mitigations raise syscall cost; see [`crate::engine::kernel`]
+//! - **Hardening posture**: Yama, lockdown, `kptr_restrict` and
+//!   `perf_event_paranoid` decide which observers are possible at all; see
+//!   [`crate::engine::posture`]
+//! - **Core dumps**: where a crash of ours would end up (`core_pattern`,
+//!   `RLIMIT_CORE`, the dumpable flag); `core_dump.rs` judges the helper
+//! - **Hardware tracing**: which branch-tracing PMUs (Intel PT, BTS, LBR)
+//!   the kernel exposes under `/sys/bus/event_source`; `hw_trace.rs` probes
+//!   whether someone already holds them
 
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use crate::engine::kernel::KernelProfile;
+use crate::engine::placement;
+use crate::engine::policy::DecisionEngine;
+use crate::engine::posture::KernelPosture;
+
+/// Fraction of CFS periods throttled above which timing is considered unreliable
+const THROTTLED_PERIOD_RATIO: f64 = 0.01;
+
+/// CFS bandwidth limit applied to our cgroup (or an ancestor)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CgroupThrottle {
+    /// cgroup hierarchy version the limit was read from (1 or 2)
+    pub version: u8,
+    /// Most restrictive quota along the path, in CPUs (`None` = unlimited)
+    pub quota_cpus: Option<f64>,
+    /// Periods elapsed / periods throttled, from cpu.stat of our own cgroup
+    pub nr_periods: u64,
+    pub nr_throttled: u64,
+}
+
+impl CgroupThrottle {
+    /// Share of periods in which the cgroup ran out of quota
+    pub fn throttled_ratio(&self) -> f64 {
+        if self.nr_periods == 0 { 0.0 } else { self.nr_throttled as f64 / self.nr_periods as f64 }
+    }
+}
+
+/// Where a crash of this process would be captured
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoreDumpConfig {
+    /// `kernel.core_pattern` ("|/path args" pipes the dump to a helper)
+    pub core_pattern: Option<String>,
+    /// Soft `RLIMIT_CORE` in bytes (`u64::MAX` = unlimited)
+    pub core_limit: Option<u64>,
+    /// `PR_GET_DUMPABLE` (0 = no dumps, no /proc access for others)
+    pub dumpable: Option<i32>,
+}
+
+impl CoreDumpConfig {
+    pub fn detect() -> Self {
+        let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+        // SAFETY: getrlimit fills the struct we own
+        let core_limit = (unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut limit) } == 0).then_some(limit.rlim_cur);
+        // SAFETY: PR_GET_DUMPABLE only reads the flag
+        let dumpable = unsafe { libc::prctl(libc::PR_GET_DUMPABLE) };
+        Self {
+            core_pattern: read_trimmed(Path::new("/proc/sys/kernel/core_pattern")),
+            core_limit,
+            dumpable: (dumpable >= 0).then_some(dumpable),
+        }
+    }
+
+    /// Helper command line if dumps are piped to a program
+    pub fn pipe_helper(&self) -> Option<&str> {
+        self.core_pattern.as_deref()?.strip_prefix('|').map(str::trim)
+    }
+
+    /// Whether a crash would leave a dump anywhere (pipes ignore
+    /// `RLIMIT_CORE` unless it is 1)
+    pub fn captures_crash(&self) -> bool {
+        if self.dumpable == Some(0) {
+            return false;
+        }
+        match self.pipe_helper() {
+            Some(_) => self.core_limit != Some(1),
+            None => self.core_limit.is_none_or(|limit| limit > 0),
+        }
+    }
+}
+
+const EVENT_SOURCE_DIR: &str = "/sys/bus/event_source/devices";
+
+/// Branch-tracing hardware the kernel exposes through perf
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HwTracing {
+    /// perf type of the `intel_pt` PMU
+    pub intel_pt_type: Option<u32>,
+    /// perf type of the `intel_bts` PMU
+    pub intel_bts_type: Option<u32>,
+    /// LBR depth advertised by the core PMU (`caps/branches`)
+    pub lbr_entries: Option<u32>,
+}
+
+impl HwTracing {
+    pub fn detect() -> Self {
+        Self::detect_in(Path::new(EVENT_SOURCE_DIR))
+    }
+
+    /// Read the PMU devices under an event_source `devices` directory
+    pub fn detect_in(dir: &Path) -> Self {
+        let number = |path: PathBuf| read_trimmed(&path).and_then(|s| s.parse().ok());
+        Self {
+            intel_pt_type: number(dir.join("intel_pt/type")),
+            intel_bts_type: number(dir.join("intel_bts/type")),
+            // Hybrid parts name the core PMUs cpu_core / cpu_atom
+            lbr_entries: ["cpu", "cpu_core"].iter()
+                .find_map(|pmu| number(dir.join(pmu).join("caps/branches")))
+                .filter(|&n| n > 0),
+        }
+    }
+
+    pub fn any(&self) -> bool {
+        self.intel_pt_type.is_some() || self.intel_bts_type.is_some() || self.lbr_entries.is_some()
+    }
+}
 
 /// Environment state that affects detection reliability
 #[derive(Debug, Clone)]
@@ -19,8 +144,28 @@ pub struct EnvironmentState {
     pub cpu_governor: Option<String>,
     /// Whether SMT (Hyper-Threading) is active
     pub smt_active: Option<bool>,
+    /// cgroup CPU bandwidth limit, `None` if no cpu controller was found
+    pub cpu_throttle: Option<CgroupThrottle>,
+    /// Running on battery (`None` if the machine reports no power supplies)
+    pub on_battery: Option<bool>,
+    /// cpufreq energy_performance_preference of CPU 0 (intel_pstate/amd-pstate)
+    pub energy_preference: Option<String>,
+    /// Kernel build and boot settings affecting latency baselines
+    pub kernel: KernelProfile,
+    /// Hardening sysctls and lockdown state
+    pub posture: KernelPosture,
+    /// Core-dump capture configuration
+    pub core_dump: CoreDumpConfig,
+    /// Branch-tracing PMUs available to perf
+    pub hw_tracing: HwTracing,
     /// Score adjustment factor (1.0 = no adjustment, <1.0 = reduce scores)
     pub adjustment_factor: f64,
+    /// Confidence multiplier for timing-derived evidence (1.0 = trusted)
+    pub timing_confidence: f64,
+    /// Multiplier (>= 1.0) timing detectors apply to their latency/CV thresholds
+    pub timing_tolerance: f64,
+    /// Multiplier (>= 1.0) for syscall-latency baselines
+    pub syscall_tolerance: f64,
     /// Human-readable warnings about environment
     pub warnings: Vec<String>,
 }
@@ -31,7 +176,17 @@ impl EnvironmentState {
         let mut state = Self {
             cpu_governor: None,
             smt_active: None,
+            cpu_throttle: None,
+            on_battery: None,
+            energy_preference: None,
+            kernel: KernelProfile::default(),
+            posture: KernelPosture::default(),
+            core_dump: CoreDumpConfig::default(),
+            hw_tracing: HwTracing::default(),
             adjustment_factor: 1.0,
+            timing_confidence: 1.0,
+            timing_tolerance: 1.0,
+            syscall_tolerance: 1.0,
             warnings: Vec::new(),
         };
 
@@ -41,6 +196,25 @@ impl EnvironmentState {
         // Detect SMT status
         state.smt_active = detect_smt_status();
         
+        // Detect cgroup CPU bandwidth limits (containers)
+        state.cpu_throttle = detect_cgroup_throttle();
+        
+        // Detect battery power and energy-performance preference
+        state.on_battery = detect_on_battery(Path::new(POWER_SUPPLY_DIR));
+        state.energy_preference = read_trimmed(Path::new(EPP_PATH));
+        
+        // Detect kernel settings that move syscall/scheduling baselines
+        state.kernel = KernelProfile::detect();
+        
+        // Detect which observers the kernel's hardening allows
+        state.posture = KernelPosture::detect();
+        
+        // Detect where a crash would be captured
+        state.core_dump = CoreDumpConfig::detect();
+        
+        // Detect branch-tracing hardware (Intel PT, BTS, LBR)
+        state.hw_tracing = HwTracing::detect();
+        
         // Calculate adjustment factor based on environment
         state.calculate_adjustment();
         
@@ -86,19 +260,136 @@ impl EnvironmentState {
             );
         }
         
+        // A CPU quota deschedules us mid-measurement once it runs out
+        if let Some(ref throttle) = self.cpu_throttle {
+            let ratio = throttle.throttled_ratio();
+            if ratio > THROTTLED_PERIOD_RATIO {
+                factor *= 0.6;
+                self.timing_confidence *= 0.5;
+                self.warnings.push(format!(
+                    "cgroup v{} CPU quota throttled {:.1}% of periods - timing outliers expected",
+                    throttle.version, ratio * 100.0
+                ));
+            } else if throttle.quota_cpus.is_some_and(|cpus| cpus < 1.0) {
+                factor *= 0.8;
+                self.timing_confidence *= 0.7;
+                self.warnings.push(format!(
+                    "cgroup v{} CPU quota below one CPU ({:.2}) - throttling likely under load",
+                    throttle.version, throttle.quota_cpus.unwrap_or(0.0)
+                ));
+            }
+        }
+        
+        // Battery and power-leaning EPP cap boost regardless of governor
+        let mut tolerance: f64 = 1.0;
+        if self.on_battery == Some(true) {
+            tolerance *= 1.5;
+            factor *= 0.8;
+            self.warnings.push("Running on battery - boost is limited and timing varies".to_string());
+        }
+        match self.energy_preference.as_deref() {
+            Some("power") => {
+                tolerance *= 1.5;
+                factor *= 0.8;
+                self.warnings.push("Energy-performance preference 'power' - expect slow, variable timing".to_string());
+            }
+            Some("balance_power") => {
+                tolerance *= 1.25;
+                factor *= 0.9;
+                self.warnings.push("Energy-performance preference 'balance_power' widens timing variance".to_string());
+            }
+            _ => {}
+        }
+        // Kernel settings shift baselines rather than add noise: widen the
+        // affected thresholds instead of discounting the whole score
+        if self.kernel.preempt_rt {
+            tolerance *= 1.25;
+            self.warnings.push("PREEMPT_RT kernel - threaded IRQs add scheduling jitter".to_string());
+        }
+        if let Some(ref cpus) = self.kernel.nohz_full {
+            self.warnings.push(format!("nohz_full={} - kernel entry carries context-tracking overhead", cpus));
+        }
+        if self.kernel.kpti {
+            self.warnings.push("KPTI active - syscalls pay a page-table switch".to_string());
+        }
+        if !self.kernel.entry_mitigations.is_empty() {
+            self.warnings.push(format!("Entry-path mitigations active: {}", self.kernel.entry_mitigations.join(", ")));
+        }
+        self.syscall_tolerance = self.kernel.syscall_cost_factor();
+        self.timing_tolerance = tolerance;
+        
         self.adjustment_factor = factor;
     }
 
+    /// Record environment metrics into the engine's feature vector
+    pub fn record_features(&self, engine: &mut DecisionEngine) {
+        if let Some(ref throttle) = self.cpu_throttle {
+            engine.record_feature("cgroup_cpu_quota", throttle.quota_cpus.unwrap_or(f64::INFINITY));
+            engine.record_feature("cgroup_throttled_ratio", throttle.throttled_ratio());
+        }
+        if let Some(on_battery) = self.on_battery {
+            engine.record_flag("on_battery", on_battery);
+        }
+        engine.record_feature("timing_tolerance", self.timing_tolerance);
+        engine.record_flag("kernel_preempt_rt", self.kernel.preempt_rt);
+        engine.record_flag("kernel_nohz_full", self.kernel.nohz_full.is_some());
+        engine.record_flag("kernel_kpti", self.kernel.kpti);
+        engine.record_feature("syscall_tolerance", self.syscall_tolerance);
+        if let Some(scope) = self.posture.ptrace_scope {
+            engine.record_feature("ptrace_scope", scope as f64);
+        }
+        if let Some(kptr) = self.posture.kptr_restrict {
+            engine.record_feature("kptr_restrict", kptr as f64);
+        }
+        if let Some(lockdown) = self.posture.lockdown {
+            engine.record_feature("kernel_lockdown", lockdown as u8 as f64);
+        }
+        engine.record_flag("core_dump_piped", self.core_dump.pipe_helper().is_some());
+        engine.record_flag("core_dump_captured", self.core_dump.captures_crash());
+        engine.record_flag("intel_pt_available", self.hw_tracing.intel_pt_type.is_some());
+        engine.record_feature("lbr_entries", self.hw_tracing.lbr_entries.unwrap_or(0) as f64);
+        let isolated = placement::isolated_cpus();
+        engine.record_feature("isolated_cpu_count", isolated.len() as f64);
+        if let Some(cpu) = placement::measurement_cpu() {
+            engine.record_flag("measurement_cpu_isolated", isolated.contains(&cpu));
+        }
+    }
+
     /// Print environment summary
     pub fn print_summary(&self) {
-        eprintln!("[ENV] CPU Governor: {}", 
+        diag!("[ENV] CPU Governor: {}", 
             self.cpu_governor.as_deref().unwrap_or("unknown"));
-        eprintln!("[ENV] SMT Active: {}", 
+        diag!("[ENV] SMT Active: {}", 
             self.smt_active.map_or("unknown".to_string(), |v| v.to_string()));
-        eprintln!("[ENV] Score Adjustment Factor: {:.2}", self.adjustment_factor);
+        if let Some(ref throttle) = self.cpu_throttle {
+            diag!("[ENV] cgroup v{} CPU quota: {} (throttled {}/{} periods)",
+                throttle.version,
+                throttle.quota_cpus.map_or("unlimited".to_string(), |c| format!("{:.2} CPUs", c)),
+                throttle.nr_throttled, throttle.nr_periods);
+        }
+        diag!("[ENV] Power: {} | EPP: {}",
+            self.on_battery.map_or("unknown", |b| if b { "battery" } else { "AC" }),
+            self.energy_preference.as_deref().unwrap_or("unknown"));
+        diag!("[ENV] Kernel: config={} preempt_rt={} nohz_full={} kpti={} mitigations_off={}",
+            self.kernel.config_source.as_deref().unwrap_or("unreadable"),
+            self.kernel.preempt_rt,
+            self.kernel.nohz_full.as_deref().unwrap_or("none"),
+            self.kernel.kpti,
+            self.kernel.mitigations_off);
+        diag!("[ENV] Posture: ptrace_scope={:?} lockdown={:?} kptr_restrict={:?} perf_event_paranoid={:?}",
+            self.posture.ptrace_scope, self.posture.lockdown, self.posture.kptr_restrict, self.posture.perf_event_paranoid);
+        diag!("[ENV] Core dumps: pattern={:?} RLIMIT_CORE={:?} dumpable={:?} captured={}",
+            self.core_dump.core_pattern, self.core_dump.core_limit, self.core_dump.dumpable, self.core_dump.captures_crash());
+        diag!("[ENV] Hardware tracing: intel_pt={:?} intel_bts={:?} lbr_entries={:?}",
+            self.hw_tracing.intel_pt_type, self.hw_tracing.intel_bts_type, self.hw_tracing.lbr_entries);
+        diag!("[ENV] Isolated CPUs: {:?} | Measurement CPU: {:?}",
+            placement::isolated_cpus(), placement::measurement_cpu());
+        diag!("[ENV] Score Adjustment Factor: {:.2}", self.adjustment_factor);
+        diag!("[ENV] Timing Confidence: {:.2} | Tolerance: {:.2}x | Syscall Tolerance: {:.2}x",
+            self.timing_confidence, self.timing_tolerance, self.syscall_tolerance);
         
         for warning in &self.warnings {
-            eprintln!("[ENV] WARNING: {}", warning);
+            diag!("[ENV] WARNING: {}", warning);
         }
     }
 }
@@ -127,6 +418,152 @@ fn detect_smt_status() -> Option<bool> {
     None
 }
 
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+const EPP_PATH: &str = "/sys/devices/system/cpu/cpu0/cpufreq/energy_performance_preference";
+
+/// Whether the machine runs on battery: a battery is discharging and no
+/// mains adapter is online. `None` if there are no power supplies (desktop
+/// without ACPI supply reporting, VM, container without /sys).
+pub fn detect_on_battery(dir: &Path) -> Option<bool> {
+    let mut seen = false;
+    let mut mains_online = false;
+    let mut discharging = false;
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        match read_trimmed(&path.join("type")).as_deref() {
+            Some("Mains") => {
+                seen = true;
+                mains_online |= read_trimmed(&path.join("online")).as_deref() == Some("1");
+            }
+            Some("Battery") => {
+                seen = true;
+                discharging |= read_trimmed(&path.join("status")).as_deref() == Some("Discharging");
+            }
+            _ => {}
+        }
+    }
+    if seen { Some(discharging && !mains_online) } else { None }
+}
+
+/// cgroup mount points from /proc/self/mountinfo: (v2 root, v1 cpu controller root)
+pub fn parse_cgroup_mounts(mountinfo: &str) -> (Option<PathBuf>, Option<PathBuf>) {
+    let mut v2 = None;
+    let mut v1_cpu = None;
+    for line in mountinfo.lines() {
+        let Some((left, right)) = line.split_once(" - ") else { continue };
+        let Some(mount_point) = left.split_whitespace().nth(4) else { continue };
+        let mut fields = right.split_whitespace();
+        let fstype = fields.next().unwrap_or("");
+        let super_opts = fields.nth(1).unwrap_or("");
+        match fstype {
+            "cgroup2" => v2 = v2.or_else(|| Some(PathBuf::from(mount_point))),
+            "cgroup" if super_opts.split(',').any(|o| o == "cpu") => {
+                v1_cpu = v1_cpu.or_else(|| Some(PathBuf::from(mount_point)));
+            }
+            _ => {}
+        }
+    }
+    (v2, v1_cpu)
+}
+
+/// Our cgroup paths from /proc/self/cgroup: (v2 path, v1 cpu-controller path)
+pub fn parse_proc_cgroup(content: &str) -> (Option<String>, Option<String>) {
+    let mut v2 = None;
+    let mut v1_cpu = None;
+    for line in content.lines() {
+        let mut parts = line.splitn(3, ':');
+        let (Some(id), Some(controllers), Some(path)) = (parts.next(), parts.next(), parts.next()) else { continue };
+        if id == "0" && controllers.is_empty() {
+            v2 = Some(path.to_string());
+        } else if controllers.split(',').any(|c| c == "cpu") {
+            v1_cpu = Some(path.to_string());
+        }
+    }
+    (v2, v1_cpu)
+}
+
+/// Parse cgroup v2 `cpu.max` ("max 100000" or "50000 100000") into CPUs
+pub fn parse_cpu_max(content: &str) -> Option<Option<f64>> {
+    let mut fields = content.split_whitespace();
+    let quota = fields.next()?;
+    let period: f64 = fields.next()?.parse().ok()?;
+    if quota == "max" {
+        return Some(None);
+    }
+    let quota: f64 = quota.parse().ok()?;
+    if period <= 0.0 { None } else { Some(Some(quota / period)) }
+}
+
+/// `nr_periods` and `nr_throttled` from a v1 or v2 `cpu.stat`
+pub fn parse_cpu_stat(content: &str) -> (u64, u64) {
+    let field = |name: &str| content.lines()
+        .find_map(|l| l.strip_prefix(name).and_then(|v| v.trim().parse().ok()))
+        .unwrap_or(0);
+    (field("nr_periods "), field("nr_throttled "))
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// Directories from `mount/path` up to the mount root, leaf first
+fn cgroup_chain(mount: &Path, path: &str) -> Vec<PathBuf> {
+    let mut dir = mount.join(path.trim_start_matches('/'));
+    let mut out = Vec::new();
+    while dir.starts_with(mount) {
+        out.push(dir.clone());
+        if !dir.pop() {
+            break;
+        }
+    }
+    out
+}
+
+/// Minimum over optional quotas, treating `None` as unlimited
+fn tighter(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (Some(x), Some(y)) => Some(x.min(y)),
+        (x, None) => x,
+        (None, y) => y,
+    }
+}
+
+/// Detect CFS bandwidth limits on our cgroup and its ancestors
+fn detect_cgroup_throttle() -> Option<CgroupThrottle> {
+    let (mount_v2, mount_v1) = parse_cgroup_mounts(&fs::read_to_string("/proc/self/mountinfo").ok()?);
+    let (path_v2, path_v1) = parse_proc_cgroup(&fs::read_to_string("/proc/self/cgroup").ok()?);
+
+    // v1 cpu controller first: on hybrid hosts the v2 tree has no cpu controller
+    if let (Some(mount), Some(path)) = (mount_v1, path_v1) {
+        let chain = cgroup_chain(&mount, &path);
+        let leaf = chain.first()?;
+        let mut quota = None;
+        for dir in &chain {
+            let q: Option<i64> = read_trimmed(&dir.join("cpu.cfs_quota_us")).and_then(|s| s.parse().ok());
+            let p: Option<i64> = read_trimmed(&dir.join("cpu.cfs_period_us")).and_then(|s| s.parse().ok());
+            if let (Some(q), Some(p)) = (q, p) {
+                if q > 0 && p > 0 {
+                    quota = tighter(quota, Some(q as f64 / p as f64));
+                }
+            }
+        }
+        let (nr_periods, nr_throttled) = parse_cpu_stat(&fs::read_to_string(leaf.join("cpu.stat")).unwrap_or_default());
+        return Some(CgroupThrottle { version: 1, quota_cpus: quota, nr_periods, nr_throttled });
+    }
+
+    let (mount, path) = (mount_v2?, path_v2?);
+    let chain = cgroup_chain(&mount, &path);
+    let leaf = chain.first()?;
+    if !leaf.join("cpu.stat").exists() {
+        return None;
+    }
+    let quota = chain.iter()
+        .filter_map(|dir| read_trimmed(&dir.join("cpu.max")).and_then(|s| parse_cpu_max(&s)))
+        .fold(None, tighter);
+    let (nr_periods, nr_throttled) = parse_cpu_stat(&fs::read_to_string(leaf.join("cpu.stat")).unwrap_or_default());
+    Some(CgroupThrottle { version: 2, quota_cpus: quota, nr_periods, nr_throttled })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -138,4 +575,112 @@ mod tests {
         assert!(state.adjustment_factor > 0.0);
         assert!(state.adjustment_factor <= 1.0);
     }
+
+    #[test]
+    fn test_cgroup_parsing() {
+        let mountinfo = "30 25 0:26 / /sys/fs/cgroup rw,nosuid - cgroup2 cgroup2 rw,nsdelegate\n\
+                         31 25 0:27 / /sys/fs/cgroup/cpu,cpuacct rw - cgroup cgroup rw,cpu,cpuacct\n";
+        assert_eq!(parse_cgroup_mounts(mountinfo),
+                   (Some(PathBuf::from("/sys/fs/cgroup")), Some(PathBuf::from("/sys/fs/cgroup/cpu,cpuacct"))));
+
+        let cgroup = "4:memory:/docker/abc\n2:cpu,cpuacct:/docker/abc\n0::/system.slice/x.service\n";
+        assert_eq!(parse_proc_cgroup(cgroup),
+                   (Some("/system.slice/x.service".to_string()), Some("/docker/abc".to_string())));
+    }
+
+    #[test]
+    fn test_cpu_max_and_stat() {
+        assert_eq!(parse_cpu_max("max 100000"), Some(None));
+        assert_eq!(parse_cpu_max("50000 100000"), Some(Some(0.5)));
+        assert_eq!(parse_cpu_max("garbage"), None);
+
+        let stat = "usage_usec 100\nnr_periods 200\nnr_throttled 50\nthrottled_usec 9\n";
+        assert_eq!(parse_cpu_stat(stat), (200, 50));
+        let throttle = CgroupThrottle { version: 2, quota_cpus: Some(0.5), nr_periods: 200, nr_throttled: 50 };
+        assert_eq!(throttle.throttled_ratio(), 0.25);
+    }
+
+    #[test]
+    fn test_throttling_lowers_timing_confidence() {
+        let mut state = EnvironmentState {
+            cpu_governor: Some("performance".to_string()),
+            smt_active: Some(false),
+            cpu_throttle: Some(CgroupThrottle { version: 2, quota_cpus: Some(0.5), nr_periods: 100, nr_throttled: 40 }),
+            on_battery: None,
+            energy_preference: None,
+            kernel: KernelProfile::default(),
+            posture: KernelPosture::default(),
+            core_dump: CoreDumpConfig::default(),
+            hw_tracing: HwTracing::default(),
+            adjustment_factor: 1.0,
+            timing_confidence: 1.0,
+            timing_tolerance: 1.0,
+            syscall_tolerance: 1.0,
+            warnings: Vec::new(),
+        };
+        state.calculate_adjustment();
+        assert!(state.adjustment_factor < 1.0);
+        assert!(state.timing_confidence < 1.0);
+    }
+
+    #[test]
+    fn test_core_dump_capture() {
+        let piped = CoreDumpConfig { core_pattern: Some("|/usr/lib/systemd/systemd-coredump %P %u".to_string()), core_limit: Some(0), dumpable: Some(1) };
+        assert_eq!(piped.pipe_helper(), Some("/usr/lib/systemd/systemd-coredump %P %u"));
+        assert!(piped.captures_crash());
+        let file = CoreDumpConfig { core_pattern: Some("core".to_string()), core_limit: Some(0), dumpable: Some(1) };
+        assert!(!file.captures_crash());
+        assert!(!CoreDumpConfig { dumpable: Some(0), ..piped }.captures_crash());
+    }
+
+    #[test]
+    fn test_hw_tracing_detection() {
+        let dir = std::env::temp_dir().join(format!("antidebug_event_source_{}", std::process::id()));
+        fs::create_dir_all(dir.join("intel_pt")).unwrap();
+        fs::create_dir_all(dir.join("cpu/caps")).unwrap();
+        fs::write(dir.join("intel_pt/type"), "9\n").unwrap();
+        fs::write(dir.join("cpu/caps/branches"), "32\n").unwrap();
+        let tracing = HwTracing::detect_in(&dir);
+        assert_eq!(tracing, HwTracing { intel_pt_type: Some(9), intel_bts_type: None, lbr_entries: Some(32) });
+        assert!(tracing.any());
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(!HwTracing::detect_in(&dir).any());
+    }
+
+    #[test]
+    fn test_battery_widens_tolerance() {
+        let dir = std::env::temp_dir().join(format!("antidebug_power_{}", std::process::id()));
+        let write = |name: &str, files: &[(&str, &str)]| {
+            fs::create_dir_all(dir.join(name)).unwrap();
+            for (file, value) in files {
+                fs::write(dir.join(name).join(file), value).unwrap();
+            }
+        };
+        write("AC", &[("type", "Mains\n"), ("online", "0\n")]);
+        write("BAT0", &[("type", "Battery\n"), ("status", "Discharging\n")]);
+        assert_eq!(detect_on_battery(&dir), Some(true));
+        write("AC", &[("online", "1\n")]);
+        assert_eq!(detect_on_battery(&dir), Some(false));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut state = EnvironmentState {
+            cpu_governor: Some("performance".to_string()),
+            smt_active: Some(false),
+            cpu_throttle: None,
+            on_battery: Some(true),
+            energy_preference: Some("balance_power".to_string()),
+            kernel: KernelProfile::default(),
+            posture: KernelPosture::default(),
+            core_dump: CoreDumpConfig::default(),
+            hw_tracing: HwTracing::default(),
+            adjustment_factor: 1.0,
+            timing_confidence: 1.0,
+            timing_tolerance: 1.0,
+            syscall_tolerance: 1.0,
+            warnings: Vec::new(),
+        };
+        state.calculate_adjustment();
+        assert!((state.timing_tolerance - 1.875).abs() < 1e-9);
+        assert_eq!(state.warnings.len(), 2);
+    }
 }
@@ -0,0 +1,196 @@
+//! Verdict Explanations
+//!
+//! A verdict and a score say little to whoever has to act on them: was it
+//! one strong finding or many weak ones, how close was the next verdict,
+//! what if that one finding was a false positive? `DecisionEngine::explain()`
+//! answers with three parts:
+//!
+//! | Part          | Content                                                      |
+//! |---------------|--------------------------------------------------------------|
+//! | Contributions | Each weighted evidence: reported weight, confidence, weight  |
+//! |               | counted after caps, and its share of the score after the     |
+//! |               | environmental adjustment (heaviest first)                    |
+//! | Thresholds    | The classifier's cut-offs and where the run stands against   |
+//! |               | each (threshold and Bayesian classifiers)                    |
+//! | Pivots        | Single evidence or contradictions whose removal alone would  |
+//! |               | change the verdict, with the verdict without them            |
+//!
+//! A pivot is found by classifying again without the item: its counted
+//! weight leaves the score, it leaves the evidence (so the Bayesian
+//! posterior drops it), and a removed contradiction leaves the count and,
+//! scaled by the environmental adjustment, the score.
+//! Other features stay, and removing evidence does not re-run the
+//! contradiction rules it may have fed.
+
+use std::fmt;
+use crate::engine::classifier::{Classifier, ThresholdCheck};
+use crate::engine::features::FeatureVector;
+use crate::engine::policy::{Contradiction, DetectionSource, Evidence, Verdict};
+
+/// How much one evidence added
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contribution {
+    pub seq: u64,
+    pub source: DetectionSource,
+    /// Weight the detector reported
+    pub reported: u32,
+    pub confidence: f64,
+    /// Weight counted after confidence and caps
+    pub weight: u32,
+    /// Share of the final score (after the environmental adjustment for
+    /// scan evidence)
+    pub counted: f64,
+    pub details: String,
+}
+
+/// Something the verdict hinges on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PivotItem {
+    /// Evidence by sequence number
+    Evidence(u64),
+    /// Contradiction by index into `get_contradictions()`
+    Contradiction(usize),
+}
+
+/// An item whose removal alone changes the verdict
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pivot {
+    pub item: PivotItem,
+    pub description: String,
+    /// Verdict without it
+    pub verdict: Verdict,
+}
+
+/// Structured account of a verdict
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub verdict: Verdict,
+    pub score: f64,
+    pub adjustment_factor: f64,
+    pub contributions: Vec<Contribution>,
+    pub thresholds: Vec<ThresholdCheck>,
+    pub pivots: Vec<Pivot>,
+}
+
+impl Explanation {
+    /// Explain `classifier`'s verdict over `features` and `evidence`.
+    /// `adjustment_factor` is the environmental adjustment the scan's
+    /// evidence was scaled by.
+    pub fn build(classifier: &dyn Classifier, features: &FeatureVector, evidence: &[Evidence], contradictions: &[Contradiction], adjustment_factor: f64) -> Self {
+        let verdict = classifier.classify(features, evidence);
+        let score = features.get("score").unwrap_or(0.0);
+        let counted = |e: &Evidence| e.weight as f64 * if e.late { 1.0 } else { adjustment_factor };
+
+        let mut contributions: Vec<Contribution> = evidence.iter()
+            .filter(|e| e.weight > 0)
+            .map(|e| Contribution {
+                seq: e.seq,
+                source: e.source,
+                reported: e.reported,
+                confidence: e.confidence,
+                weight: e.weight,
+                counted: counted(e),
+                details: e.details.clone(),
+            })
+            .collect();
+        contributions.sort_by(|a, b| b.counted.total_cmp(&a.counted).then(a.seq.cmp(&b.seq)));
+
+        let mut pivots = Vec::new();
+        for (i, e) in evidence.iter().enumerate().filter(|(_, e)| e.weight > 0) {
+            let mut fv = features.clone();
+            fv.set("score", (score - counted(e)).round().max(0.0));
+            let rest: Vec<Evidence> = evidence.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, e)| e.clone()).collect();
+            let without = classifier.classify(&fv, &rest);
+            if without != verdict {
+                pivots.push(Pivot { item: PivotItem::Evidence(e.seq), description: format!("#{} {:?} +{}: {}", e.seq, e.source, e.weight, e.details), verdict: without });
+            }
+        }
+        for (i, c) in contradictions.iter().enumerate() {
+            let mut fv = features.clone();
+            fv.set("score", (score - c.weight as f64 * adjustment_factor).round().max(0.0));
+            fv.set("contradictions", (contradictions.len() - 1) as f64);
+            let without = classifier.classify(&fv, evidence);
+            if without != verdict {
+                pivots.push(Pivot { item: PivotItem::Contradiction(i), description: format!("{:?} vs {:?} +{}: {}", c.source_a, c.source_b, c.weight, c.description), verdict: without });
+            }
+        }
+
+        Self { verdict, score, adjustment_factor, contributions, thresholds: classifier.thresholds(features, evidence), pivots }
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Verdict {:?} at score {:.0} (environmental adjustment x{:.2})", self.verdict, self.score, self.adjustment_factor)?;
+        writeln!(f, "Contributions:")?;
+        for c in &self.contributions {
+            writeln!(f, "  #{} {:?}: reported {} x {:.2} conf -> {} -> {:.1} counted | {}", c.seq, c.source, c.reported, c.confidence, c.weight, c.counted, c.details)?;
+        }
+        if !self.thresholds.is_empty() {
+            writeln!(f, "Thresholds:")?;
+            for t in &self.thresholds {
+                writeln!(f, "  {} ({:.3} vs {:.3}): {}", t.name, t.value, t.threshold, if t.crossed { "crossed" } else { "not crossed" })?;
+            }
+        }
+        match self.pivots.len() {
+            0 => writeln!(f, "Pivots: none - no single item changes the verdict"),
+            _ => {
+                writeln!(f, "Pivots:")?;
+                for p in &self.pivots {
+                    writeln!(f, "  without {} -> {:?}", p.description, p.verdict)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::bayes::BayesianClassifier;
+    use crate::engine::policy::DecisionEngine;
+
+    #[test]
+    fn test_single_strong_finding_is_pivotal() {
+        let mut engine = DecisionEngine::new();
+        engine.report(DetectionSource::Ptrace, 60, "TracerPid: 1234");
+        engine.report_with_confidence(DetectionSource::Jitter, 20, 0.5, "NOP jitter");
+        let explanation = engine.explain();
+        assert_eq!(explanation.verdict, Verdict::Instrumented);
+        assert_eq!(explanation.contributions.iter().map(|c| (c.seq, c.weight)).collect::<Vec<_>>(), vec![(1, 60), (2, 10)]);
+        assert_eq!(explanation.contributions[1].reported, 20);
+        assert_eq!(explanation.pivots, vec![Pivot { item: PivotItem::Evidence(1), description: "#1 Ptrace +60: TracerPid: 1234".to_string(), verdict: Verdict::Clean }]);
+        let crossed: Vec<&str> = explanation.thresholds.iter().filter(|t| t.crossed).map(|t| t.name.as_str()).collect();
+        assert_eq!(crossed, vec!["score >= suspicious", "score >= instrumented"]);
+    }
+
+    #[test]
+    fn test_adjustment_and_contradictions() {
+        let mut engine = DecisionEngine::new();
+        engine.report(DetectionSource::Timing, 30, "slow block");
+        engine.report(DetectionSource::Timing, 30, "slower block");
+        engine.record_contradiction(DetectionSource::Timing, DetectionSource::Ptrace, "hidden tracer");
+        engine.apply_environmental_adjustment(0.5);
+        let explanation = engine.explain();
+        assert_eq!(explanation.verdict, Verdict::Deceptive);
+        assert_eq!(explanation.contributions[0].counted, 15.0);
+        // Neither timing report alone matters; the contradiction does
+        assert_eq!(explanation.pivots.len(), 1);
+        assert_eq!(explanation.pivots[0].item, PivotItem::Contradiction(0));
+        assert_eq!(explanation.pivots[0].verdict, Verdict::Suspicious);
+        assert!(explanation.to_string().contains("without Timing vs Ptrace +30: hidden tracer -> Suspicious"));
+    }
+
+    #[test]
+    fn test_bayesian_thresholds() {
+        let mut engine = DecisionEngine::new();
+        engine.set_classifier(Box::new(BayesianClassifier::default()));
+        engine.report(DetectionSource::Ptrace, 60, "TracerPid: 1234");
+        let explanation = engine.explain();
+        assert_eq!(explanation.thresholds.len(), 4);
+        assert!(explanation.thresholds[1].name.starts_with("P(not clean)"));
+        assert_eq!(explanation.pivots.len(), 1);
+        assert_eq!(explanation.pivots[0].verdict, Verdict::Clean);
+    }
+}
@@ -0,0 +1,422 @@
+//! Feature Vector Export
+//!
+//! Flattens the raw metrics each detector measures into a fixed, ordered
+//! numeric vector - one column per metric - so that runs collected across a
+//! fleet of clean and instrumented machines can be fed to an offline
+//! classifier.
+//!
+//! # Schema
+//!
+//! The column set and order is defined by [`FEATURES`] and is stable across
+//! runs regardless of which detectors actually executed. A metric that was
+//! not measured in a given run (detector skipped, /proc unreadable, ...) is
+//! exported as `NaN` rather than 0, so "not observed" is never confused with
+//! "observed and clean". Boolean metrics are exported as 0.0 / 1.0.
+//!
+//! New detectors append columns at the end; existing columns are never
+//! renamed or reordered.
+//!
+//! # Output Format
+//!
+//! CSV with a header row. [`FeatureVector::append_csv`] writes the header only
+//! when the target file is new or empty, so repeated runs can append to the
+//! same file.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Column name and description, in export order
+pub const FEATURES: &[(&str, &str)] = &[
+    // timing.rs
+    ("rdtsc_overhead_mean", described!("Mean cycles between back-to-back RDTSC reads")),
+    ("rdtsc_overhead_cv", described!("Coefficient of variation of RDTSC overhead")),
+    ("exec_block_mean", described!("Mean cycles for the 100-add work block")),
+    ("exec_block_cv", described!("Coefficient of variation of the work block timing")),
+    // int3.rs
+    ("int3_total_count", described!("0xCC bytes found in our executable mappings")),
+    ("int3_largest_cluster", described!("Longest run of consecutive 0xCC bytes")),
+    ("int3_scattered_count", described!("0xCC bytes in regions not classified as alignment padding")),
+    // trap_flag.rs
+    ("trap_flag_missed", described!("1 if our SIGTRAP handler did not run after setting TF")),
+    // hardware_bp.rs
+    ("dr7_access_faulted", described!("1 if reading DR7 raised SIGSEGV (expected natively)")),
+    ("dr_nop_loop_mean", described!("Mean cycles for the DR7-indicator NOP loop")),
+    ("data_access_cycles", described!("Cycles for the data-breakpoint access pattern")),
+    // jitter.rs
+    ("nop_mean", described!("Mean cycles for 100 NOPs")),
+    ("nop_cv", described!("Coefficient of variation of NOP timing")),
+    ("mov_mean", described!("Mean cycles for 100 MOV reg,reg")),
+    ("xor_mean", described!("Mean cycles for 100 XOR reg,reg")),
+    ("amp_mean", described!("Mean cycles for the single-step amplification loop")),
+    ("amp_p95", described!("95th percentile cycles for the amplification loop")),
+    ("nop_bimodal", described!("1 if NOP timing is bimodal")),
+    ("amp_bimodal", described!("1 if amplification loop timing is bimodal")),
+    // record_replay.rs
+    ("hv_bit", described!("CPUID.1:ECX[31] hypervisor present bit")), // recorded by hypervisor.rs
+    ("tsc_per_ns", described!("TSC ticks per wall-clock nanosecond over a 10ms sleep")),
+    ("signal_order_unique", described!("Distinct signal orderings over the race trials")),
+    // ebpf_compare.rs
+    ("observer_discrepancy_trials", described!("Trials where internal and external observation disagreed")),
+    // environ.rs
+    ("environ_removed", described!("Variables in /proc/self/environ missing from the live environ")),
+    ("environ_modified", described!("Variables whose value differs between the two views")),
+    ("environ_added", described!("Variables only present in the live environ")),
+    ("environ_loader_scrubbed", described!("1 if a loader variable (LD_*) was removed or rewritten")),
+    // auxv.rs
+    ("auxv_view_divergent", described!("Entries where getauxval() differs from /proc/self/auxv")),
+    ("hwcap_fabricated_bits", described!("AT_HWCAP bits set that CPUID.1:EDX does not report")),
+    ("auxv_entry_outside_exe", described!("1 if AT_ENTRY is not in an executable mapping of our binary")),
+    // ptrace.rs
+    ("tracerpid_nonzero", described!("1 if /proc/self/status TracerPid is non-zero")),
+    ("ptrace_traceme_failed", described!("1 if PTRACE_TRACEME failed")),
+    // policy.rs / main.rs
+    ("contradictions", described!("Cross-technique contradictions recorded")),
+    ("env_adjustment_factor", described!("Environmental score adjustment factor")),
+    ("score", described!("Final cumulative score after adjustment")),
+    // cpu_time.rs
+    ("thread_cpu_wall_ratio", described!("Best thread-CPU-time / wall-time ratio over busy-loop windows")),
+    ("amp_stops_per_iter", described!("Context switches + signals per amplification-loop iteration")),
+    ("work_cpu_ns_per_iter", described!("Process CPU ns per calibrated work-loop iteration")),
+    ("work_cpu_wall_ratio", described!("Process CPU time / wall time over the work loop")),
+    ("work_tsc_cpu_ratio", described!("TSC-derived time / process CPU time over the work loop")),
+    // xstate.rs
+    ("xcr0", described!("XCR0 value read via XGETBV")),
+    ("xcr0_violations", described!("XCR0-vs-CPUID architectural invariants violated")),
+    ("ymm_signal_preserved", described!("1 if YMM15 survived a signal round-trip")),
+    // illegal_insn.rs
+    ("illegal_insn_deviations", described!("Illegal-instruction probes whose fault semantics differ from hardware")),
+    // fpu.rs
+    ("x87_extended_ok", described!("1 if x87 computed (1 + 2^-60) - 1 in 80-bit precision")),
+    ("fp_denormal_deviations", described!("Denormal/FTZ/DAZ probes that differ from hardware")),
+    ("rcp_exact_fraction", described!("Fraction of RCPSS/RSQRTSS results equal to exact division")),
+    // cpuid_claims.rs
+    ("isa_claim_deviations", described!("CPUID-advertised instructions that fault, miscompute or run too slowly")),
+    ("isa_probe_max_cycles", described!("Slowest per-call cost of a timed CPUID-advertised instruction (cycles)")),
+    // rtm.rs
+    ("rtm_available", described!("1 if CPUID advertises TSX/RTM")),
+    ("rtm_debug_aborts", described!("RTM transactions aborted by a debug exception")),
+    // cet.rs
+    ("cet_shstk_enabled", described!("1 if arch_prctl reports user shadow stacks enabled")),
+    ("cet_inconsistencies", described!("Disagreements between CPUID, arch_prctl, /proc and RDSSP CET state")),
+    // syscall_paths.rs
+    ("syscall_cycles", described!("Fastest per-call getppid cost via SYSCALL (cycles)")),
+    ("int80_syscall_ratio", described!("getppid cost via int 0x80 divided by cost via SYSCALL")),
+    // output_capture.rs
+    ("output_captured", described!("1 if stdout/stderr lead to a known capture tool")),
+    // environment.rs
+    ("cgroup_cpu_quota", described!("cgroup CFS quota in CPUs (inf = unlimited)")),
+    ("cgroup_throttled_ratio", described!("Fraction of CFS periods in which our cgroup was throttled")),
+    ("on_battery", described!("1 if running on battery with no mains adapter online")),
+    ("timing_tolerance", described!("Multiplier applied to timing thresholds for power-saving state")),
+    ("kernel_preempt_rt", described!("1 if the running kernel is PREEMPT_RT")),
+    ("kernel_nohz_full", described!("1 if nohz_full= is set on the kernel command line")),
+    ("kernel_kpti", described!("1 if kernel page-table isolation is active")),
+    ("syscall_tolerance", described!("Multiplier applied to syscall-latency baselines for kernel configuration")),
+    // profiler.rs
+    ("nmi_watchdog", described!("1 if the kernel NMI watchdog is enabled")),
+    ("pmi_rate", described!("Performance-monitoring interrupts per second on our CPU during a busy window")),
+    // placement.rs
+    ("isolated_cpu_count", described!("CPUs isolated via isolcpus or nohz_full")),
+    ("measurement_cpu_isolated", described!("1 if timing detectors pinned to an isolated CPU")),
+    // threads.rs
+    ("registered_threads", described!("Worker threads registered for per-thread checks")),
+    // watchdog.rs
+    ("watchdog_ticks", described!("timerfd expirations seen by the watchdog")),
+    ("watchdog_max_gap_ms", described!("Longest gap between watchdog wake-ups (ms)")),
+    // attestation.rs
+    ("attest_exchanges", described!("Authenticated time-server exchanges")),
+    ("attest_failures", described!("Time-server exchanges that timed out or failed")),
+    ("attest_max_skew_ms", described!("Largest server vs local elapsed-time difference (ms)")),
+    // bait.rs
+    ("bait_breakpoints", described!("Software breakpoints found in bait functions")),
+    ("bait_call_excess_cycles", described!("Fastest bait call minus fastest control call (cycles)")),
+    // hardware_bp.rs (guard)
+    ("dr7_enabled_slots", described!("Breakpoint slots enabled in DR7, read by the guard process")),
+    // premain.rs
+    ("premain_traced", described!("TracerPid was non-zero in the pre-main constructor")),
+    ("premain_preload", described!("LD_PRELOAD or LD_AUDIT set at process entry")),
+    ("premain_aslr_disabled", described!("ADDR_NO_RANDOMIZE personality set at process entry")),
+    // frida.rs
+    ("frida_maps_objects", described!("File mappings in /proc/self/maps named like Frida components")),
+    ("frida_loaded_objects", described!("Loader-list objects matching Frida by name or Gum exports")),
+    ("frida_unmapped_objects", described!("Frida loader-list objects without a matching maps entry")),
+    // dbi.rs
+    ("dbi_rwx_anon_bytes", described!("Bytes of anonymous RWX memory (code-cache candidates)")),
+    ("dbi_rip_mismatches", described!("Instruction-pointer views disagreeing with link-time addresses")),
+    // translation.rs
+    ("wine_indicators", described!("Independent Wine marks found (exports, environment, mappings)")),
+    // remote_debug.rs
+    ("remote_debug_stubs", described!("Tracer/parent/grandparent processes named like debug stubs")),
+    ("ida_port_listening", described!("Something listens on IDA's default debugger port")),
+    // tracer_kind.rs
+    ("tracer_kind", described!("0 none, 1 passive tracer, 2 interactive debugger, 3 unknown")),
+    ("syscall_stop_ns", described!("Slowest per-call cost of the timed syscalls while traced (ns)")),
+    // tracefs.rs
+    ("tracefs_readable", described!("uprobe_events or kprobe_events could be read")),
+    ("tracefs_probes_on_us", described!("tracefs probes on our executable, our libc or kernel paths we use")),
+    // perf_observer.rs
+    ("pmu_running_ratio", described!("Share of enabled time our own cycles counter was scheduled")),
+    ("perf_event_paranoid", described!("Value of kernel.perf_event_paranoid")),
+    ("perf_observer_processes", described!("perf/bpftrace/trace-cmd processes that launched us or name our PID")),
+    // cpu_semantics.rs
+    ("cpu_semantics_mismatches", described!("CPU-semantics probes whose result real silicon never produces")),
+    // cpu_errata.rs
+    ("cpu_errata_deviations", described!("Errata / undocumented-behavior checks that deviate from real silicon")),
+    // qbdi.rs
+    ("qbdi_indicators", described!("QBDI library, ExecBlock-shaped pages, off-stack SP, unmapped signal RIP")),
+    // coverage.rs
+    ("coverage_markers", described!("Coverage sections, symbol families and runtime hooks in our image")),
+    // symbolic.rs
+    ("symbolic_cascade_ratio", described!("Environment-seeded vs constant-seeded branch cascade time")),
+    ("symbolic_fp_deviations", described!("IEEE 754 results that differ from the exact bits")),
+    // translator.rs
+    ("smc_patch_ns", described!("Mean cost of patching and calling generated code (ns)")),
+    ("code_page_store_ratio", described!("Cost of data stores beside executed code vs. elsewhere")),
+    ("translator_quirks", described!("Binary-translator quirks observed")),
+    // analysis_vm.rs
+    ("disk_latency_cv", described!("Coefficient of variation of synced disk write latency")),
+    ("analysis_vm_traits", described!("Whole-system analysis platform traits observed")),
+    // sandbox.rs
+    ("sandbox_artifacts", described!("Malware-sandbox artifacts observed")),
+    // libc_hooks.rs
+    ("getpid_wrapper_ratio", described!("Fastest libc getpid vs fastest raw syscall")),
+    ("libc_hook_findings", described!("Signs of userspace hooks on libc wrappers")),
+    // rtld_audit.rs
+    ("rtld_audit_findings", described!("Signs of an rtld-audit library")),
+    // host_daemons.rs
+    ("host_analysis_daemons", described!("Analysis daemons running anywhere on the host")),
+    // launch_env.rs
+    ("debugger_launch_artifacts", described!("Traces of having been launched by a debugger")),
+    // syscall_filter.rs
+    ("syscall_filter_deviations", described!("Benign syscalls answered unlike a vanilla kernel")),
+    // hypervisor.rs
+    ("hypervisor_leaf_count", described!("Hypervisor CPUID leaves 0x40000000+ enumerated")),
+    ("hypervisor_anomalies", described!("Signs the VM hides or fakes its hypervisor identity")),
+    // inline_hooks.rs
+    ("inline_hooked_functions", described!("libc functions whose entry is patched or a trampoline")),
+    // maps_anomaly.rs
+    ("maps_anomalies", described!("RWX, anonymous-exec, deleted-exec and foreign-library regions")),
+    // thread_inject.rs
+    ("unexplained_threads", described!("Threads in /proc/self/task the framework did not start")),
+    // fd_table.rs
+    ("inherited_fds", described!("Descriptors above stdio open before main() and still open")),
+    // mem_crossview.rs
+    ("mem_view_divergences", described!("Text ranges that read differently directly and via /proc/self/mem")),
+    // text_diff.rs
+    ("text_patched_ranges", described!("Ranges of .text that differ from the executable on disk")),
+    // cmdline_view.rs
+    ("cmdline_view_mismatches", described!("Disagreements between argv/exe and the kernel's record of the exec")),
+    // ancestry.rs
+    ("ancestor_depth", described!("Processes between us and init")),
+    ("ancestor_matches", described!("Ancestors recognised as analysis tools, harnesses or sandboxes")),
+    // posture.rs (via environment.rs)
+    ("ptrace_scope", described!("Value of kernel.yama.ptrace_scope")),
+    ("kptr_restrict", described!("Value of kernel.kptr_restrict")),
+    ("kernel_lockdown", described!("Kernel lockdown level (0 none, 1 integrity, 2 confidentiality)")),
+    // environment.rs (core dumps)
+    ("core_dump_piped", described!("1 if kernel.core_pattern pipes dumps to a helper")),
+    ("core_dump_captured", described!("1 if a crash of ours would leave a core dump")),
+    // core_dump.rs
+    ("core_dump_helper_suspicious", described!("1 if core_pattern pipes to an analysis-looking or unknown helper")),
+    // late_attach.rs
+    ("tracer_changes", described!("TracerPid transitions since startup not caused by us")),
+    // tsc_sync.rs
+    ("tsc_trapped", described!("RDTSC faults to a supervisor (PR_SET_TSC)")),
+    ("tsc_invariant", described!("CPUID advertises an invariant TSC")),
+    ("tsc_aux_mismatches", described!("CPUs whose TSC_AUX disagrees with the pinned CPU")),
+    ("tsc_max_skew_cycles", described!("Largest consistent cross-core TSC skew (cycles)")),
+    // clock_xcheck.rs
+    ("clock_cpu_ratio", described!("Process CPU time per CLOCK_MONOTONIC_RAW second in a busy window")),
+    ("clock_tick_ratio", described!("times() ticks per CLOCK_MONOTONIC_RAW second")),
+    ("clock_snapshot_ns", described!("Median time to read all five clocks (ns)")),
+    // counter_clock.rs
+    ("counter_clock_rate", described!("Counter-thread rate during timed blocks vs its free-running rate")),
+    // sleep_skip.rs
+    ("sleep_min_ratio", described!("Shortest sleep as a fraction of the requested time (monotonic or TSC)")),
+    ("sleep_skips", described!("Sleeps that returned early or were fast-forwarded")),
+    // clock_jump.rs
+    ("clock_jump_events", described!("Realtime steps and suspends seen against monotonic time during the scan")),
+    ("clock_drift_ppm", described!("Realtime rate against monotonic over the scan, steps excluded (ppm)")),
+    // insn_count.rs
+    ("insn_counter", described!("perf_event_open gave us a user-mode instruction counter")),
+    ("insn_per_iteration", described!("Retired instructions per work-loop iteration (4 natively)")),
+    // hw_trace.rs
+    ("hw_trace_busy", described!("Branch-tracing probes (PT, BTS, LBR) refused with EBUSY")),
+    // vdso_clock.rs
+    ("vdso_syscall_ratio", described!("Per-call cost of SYS_clock_gettime over the direct vDSO call")),
+    // stop_history.rs
+    ("stop_traced_samples", described!("Samples in which another of our threads was in tracing stop (t)")),
+    ("stop_max_unexplained_ms", described!("Longest sampler oversleep not explained by schedstat run delay (ms)")),
+    // cpu_beacon.rs
+    ("beacon_cpu_wall_ratio", described!("Process CPU time per wall second over the scanned slices")),
+    ("beacon_frozen_slices", described!("Detector slices with long wall time and almost no CPU time")),
+    // task_wchan.rs
+    ("task_wchan_hits", described!("Threads found waiting in tracing-related wait channels")),
+    // lib_int3.rs
+    ("lib_int3_breakpoints", described!("Patched ranges containing 0xCC in shared library code")),
+    ("lib_patched_ranges", described!("Other patched ranges in shared library code")),
+    // module_xview.rs
+    ("module_xview_mismatches", described!("Modules seen by only one of dl_iterate_phdr and /proc/self/maps")),
+    // ret_probe.rs
+    ("ret_probe_sites", described!("Detector sites that ran a return-address probe")),
+    ("ret_probe_foreign", described!("Probe frames outside our binary and libc")),
+    // env_scan.rs
+    ("env_indicators", described!("Environment variables matching analysis-tool indicators")),
+    // kmod_scan.rs
+    ("kmod_analysis_modules", described!("Loaded kernel modules of guest additions, tracers or rootkits")),
+    ("kmod_out_of_tree", described!("Loaded out-of-tree kernel modules (taint O)")),
+    // tools.rs
+    ("tool_gdb", described!("Confidence (0-1) that the evidence pattern is gdb's")),
+    ("tool_rr", described!("Confidence (0-1) that the evidence pattern is rr's")),
+    ("tool_strace", described!("Confidence (0-1) that the evidence pattern is strace's")),
+    ("tool_frida", described!("Confidence (0-1) that the evidence pattern is Frida's")),
+    ("tool_pin", described!("Confidence (0-1) that the evidence pattern is Pin's")),
+    ("tool_qemu", described!("Confidence (0-1) that the evidence pattern is QEMU's")),
+    ("tool_valgrind", described!("Confidence (0-1) that the evidence pattern is Valgrind's")),
+    // record_replay.rs
+    ("rr_phase", described!("0 no rr, 1 being recorded, 2 being replayed")),
+    // policy.rs (limits.rs)
+    ("capped_weight", described!("Weight dropped by per-source caps")),
+    ("collapsed_duplicates", described!("Repeated evidence reports collapsed into earlier ones")),
+    // remote_debug.rs
+    ("ghidra_port_listening", described!("Something listens on Ghidra's JDWP debug port")),
+    ("tracer_event_stops", described!("Thread creation / fork events our tracer stops us on (0-2)")),
+    // dbi.rs
+    ("dynamorio_indicators", described!("DynamoRIO components, environment variables and private libc loads")),
+    // tracefs.rs
+    ("syscall_probe_surcharges", described!("Our syscalls slower than trivial ones by a probe handler's cost")),
+    // perf_observer.rs
+    ("perf_event_holders", described!("Other processes holding perf_event file descriptors")),
+    // environment.rs (hardware tracing)
+    ("intel_pt_available", described!("1 if the kernel exposes the intel_pt PMU")),
+    ("lbr_entries", described!("LBR depth advertised by the core PMU (0 if none)")),
+    // sandbox.rs
+    ("sandbox_profile_score", described!("Combined score of sandbox hardware tells (CPUs, RAM, disk, uptime, input)")),
+];
+
+/// One run's worth of detector metrics, in [`FEATURES`] order
+#[derive(Debug, Clone)]
+pub struct FeatureVector {
+    values: Vec<f64>,
+}
+
+impl FeatureVector {
+    /// Empty vector with every column unobserved (NaN)
+    pub fn new() -> Self {
+        Self { values: vec![f64::NAN; FEATURES.len()] }
+    }
+
+    fn index_of(name: &str) -> Option<usize> {
+        FEATURES.iter().position(|(n, _)| *n == name)
+    }
+
+    /// Set a metric by column name. Unknown names are ignored (and caught in
+    /// debug builds) so a typo never shifts the columns.
+    pub fn set(&mut self, name: &str, value: f64) {
+        match Self::index_of(name) {
+            Some(idx) => self.values[idx] = value,
+            None => debug_assert!(false, "unknown feature column: {}", name),
+        }
+    }
+
+    /// Set a boolean metric as 0.0 / 1.0
+    pub fn set_flag(&mut self, name: &str, value: bool) {
+        self.set(name, if value { 1.0 } else { 0.0 });
+    }
+
+    /// Value of a metric, `None` if unknown or not observed this run
+    pub fn get(&self, name: &str) -> Option<f64> {
+        Self::index_of(name)
+            .map(|idx| self.values[idx])
+            .filter(|v| !v.is_nan())
+    }
+
+    #[allow(dead_code)] // Public API for external callers
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    pub fn csv_header() -> String {
+        FEATURES.iter().map(|(n, _)| *n).collect::<Vec<_>>().join(",")
+    }
+
+    pub fn to_csv_row(&self) -> String {
+        self.values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+    }
+
+    /// Write the header and this vector's row to `out`
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "{}", Self::csv_header())?;
+        writeln!(out, "{}", self.to_csv_row())
+    }
+
+    /// Append this vector as a CSV row to `path`, writing the header first
+    /// if the file is new or empty.
+    pub fn append_csv(&self, path: &Path) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", Self::csv_header())?;
+        }
+        writeln!(file, "{}", self.to_csv_row())
+    }
+}
+
+impl Default for FeatureVector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unobserved_columns_are_nan() {
+        let mut fv = FeatureVector::new();
+        fv.set("nop_cv", 0.25);
+        fv.set_flag("hv_bit", true);
+
+        assert_eq!(fv.get("nop_cv"), Some(0.25));
+        assert_eq!(fv.get("hv_bit"), Some(1.0));
+        assert_eq!(fv.get("amp_mean"), None);
+        assert_eq!(fv.values().len(), FEATURES.len());
+    }
+
+    #[test]
+    fn test_csv_row_matches_header() {
+        let fv = FeatureVector::new();
+        let header = FeatureVector::csv_header();
+        assert_eq!(header.split(',').count(), fv.to_csv_row().split(',').count());
+        assert!(fv.to_csv_row().starts_with("NaN"));
+    }
+
+    #[test]
+    fn test_written_csv_parses() {
+        let mut fv = FeatureVector::new();
+        fv.set("score", 42.0);
+        fv.set_flag("tracerpid_nonzero", true);
+        let mut out = Vec::new();
+        fv.write_csv(&mut out).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let row: Vec<(&str, f64)> = lines[0].split(',')
+            .zip(lines[1].split(',').map(|v| v.parse().unwrap()))
+            .collect();
+        assert_eq!(row.len(), FEATURES.len());
+        assert!(row.contains(&("score", 42.0)));
+        assert!(row.contains(&("tracerpid_nonzero", 1.0)));
+        assert!(row.iter().find(|(name, _)| *name == "nop_cv").unwrap().1.is_nan());
+    }
+
+    #[test]
+    fn test_feature_names_unique() {
+        let mut names: Vec<&str> = FEATURES.iter().map(|(n, _)| *n).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), FEATURES.len());
+    }
+}
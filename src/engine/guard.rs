@@ -0,0 +1,186 @@
+//! Cooperative Guard Process
+//!
+//! User space cannot read DR0-DR7 (`mov rax, dr7` faults), so the hardware
+//! breakpoint detector otherwise has to infer breakpoints from timing. The
+//! kernel does hand the registers to a tracer through
+//! `PTRACE_PEEKUSER(offsetof(struct user, u_debugreg))`.
+//!
+//! With the guard enabled (`ANTIDEBUG_GUARD=1`) we fork a short-lived guard
+//! process and allow it to trace us (`PR_SET_PTRACER`, so Yama scope 1 is
+//! fine). The guard seizes the calling thread, interrupts it, reads its
+//! eight debug registers, detaches and sends the values back over a pipe.
+//!
+//! DRx state is per thread; the values are those of the thread that called
+//! [`read_debug_registers`]. If a debugger is already attached the seize
+//! fails with EPERM - the ptrace detectors report that case.
+//!
+//! The guard runs between `fork` and `_exit` in a possibly multithreaded
+//! process, so it only makes raw syscalls: no allocation, no locks.
+
+use std::mem::offset_of;
+
+/// Environment variable that enables the guard ("1")
+pub const GUARD_ENV_VAR: &str = "ANTIDEBUG_GUARD";
+
+/// Guard failure stages reported back through the pipe
+const STAGE_SEIZE: u64 = 1;
+const STAGE_STOP: u64 = 2;
+const STAGE_PEEK: u64 = 3;
+
+/// Words the guard writes back: status, then DR0-DR7
+const REPLY_WORDS: usize = 9;
+
+/// Whether the embedder enabled the guard architecture
+pub fn enabled() -> bool {
+    std::env::var(GUARD_ENV_VAR).is_ok_and(|v| v == "1")
+}
+
+/// DR0-DR7 of one thread as read by the guard
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DebugRegisters {
+    pub dr: [u64; 8],
+}
+
+impl DebugRegisters {
+    /// Breakpoint slots (0-3) enabled in DR7 (local or global enable bit)
+    pub fn armed_slots(&self) -> Vec<usize> {
+        (0..4).filter(|i| self.dr[7] >> (2 * i) & 0b11 != 0).collect()
+    }
+
+    /// Linear address in slot `i`
+    pub fn address(&self, i: usize) -> u64 {
+        self.dr[i]
+    }
+}
+
+fn errno() -> u64 {
+    // SAFETY: errno location is always valid for the calling thread
+    unsafe { *libc::__errno_location() as u64 }
+}
+
+/// Guard body. Only raw syscalls: see module docs.
+unsafe fn guard(go: libc::c_int, reply: libc::c_int, tid: libc::pid_t) -> ! {
+    let mut words = [0u64; REPLY_WORDS];
+    let mut byte = 0u8;
+    if libc::read(go, &mut byte as *mut u8 as *mut libc::c_void, 1) != 1 {
+        libc::_exit(1);
+    }
+
+    let fail = |stage: u64| (stage << 32) | errno();
+    if libc::ptrace(libc::PTRACE_SEIZE, tid, 0, 0) != 0 {
+        words[0] = fail(STAGE_SEIZE);
+    } else {
+        let mut status = 0;
+        if libc::ptrace(libc::PTRACE_INTERRUPT, tid, 0, 0) != 0 || libc::waitpid(tid, &mut status, libc::__WALL) != tid {
+            words[0] = fail(STAGE_STOP);
+        } else {
+            let base = offset_of!(libc::user, u_debugreg);
+            for i in 0..8 {
+                *libc::__errno_location() = 0;
+                let value = libc::ptrace(libc::PTRACE_PEEKUSER, tid, base + i * 8, 0);
+                if value == -1 && errno() != 0 {
+                    words[0] = fail(STAGE_PEEK);
+                    break;
+                }
+                words[1 + i] = value as u64;
+            }
+        }
+        libc::ptrace(libc::PTRACE_DETACH, tid, 0, 0);
+    }
+
+    libc::write(reply, words.as_ptr() as *const libc::c_void, std::mem::size_of_val(&words));
+    libc::_exit(0);
+}
+
+/// Read the calling thread's debug registers through a guard process
+pub fn read_debug_registers() -> Result<DebugRegisters, String> {
+    let mut go = [0; 2];
+    let mut reply = [0; 2];
+    // SAFETY: raw fork/pipe/prctl plumbing; every descriptor is closed below and
+    // the child never returns from `guard`
+    unsafe {
+        if libc::pipe2(go.as_mut_ptr(), libc::O_CLOEXEC) != 0 {
+            return Err(format!("pipe: {}", std::io::Error::last_os_error()));
+        }
+        if libc::pipe2(reply.as_mut_ptr(), libc::O_CLOEXEC) != 0 {
+            let e = std::io::Error::last_os_error();
+            libc::close(go[0]);
+            libc::close(go[1]);
+            return Err(format!("pipe: {}", e));
+        }
+        let tid = libc::syscall(libc::SYS_gettid) as libc::pid_t;
+
+        let pid = libc::fork();
+        if pid == 0 {
+            guard(go[0], reply[1], tid);
+        }
+        libc::close(go[0]);
+        libc::close(reply[1]);
+        if pid < 0 {
+            let e = std::io::Error::last_os_error();
+            libc::close(go[1]);
+            libc::close(reply[0]);
+            return Err(format!("fork: {}", e));
+        }
+
+        // Allow the guard (and only it) to trace us under Yama, then release it
+        libc::prctl(libc::PR_SET_PTRACER, pid as libc::c_ulong, 0, 0, 0);
+        libc::write(go[1], b"g".as_ptr() as *const libc::c_void, 1);
+        libc::close(go[1]);
+
+        let mut words = [0u64; REPLY_WORDS];
+        let want = std::mem::size_of_val(&words);
+        let mut got = 0usize;
+        while got < want {
+            let n = libc::read(reply[0], (words.as_mut_ptr() as *mut u8).add(got) as *mut libc::c_void, want - got);
+            if n > 0 {
+                got += n as usize;
+            } else if n == 0 || std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted {
+                break;
+            }
+        }
+        libc::close(reply[0]);
+        libc::prctl(libc::PR_SET_PTRACER, 0 as libc::c_ulong, 0, 0, 0);
+        let mut status = 0;
+        libc::waitpid(pid, &mut status, 0);
+
+        if got < want {
+            return Err("guard exited without replying".to_string());
+        }
+        if words[0] != 0 {
+            let stage = match words[0] >> 32 {
+                STAGE_SEIZE => "PTRACE_SEIZE",
+                STAGE_STOP => "PTRACE_INTERRUPT",
+                _ => "PTRACE_PEEKUSER",
+            };
+            let err = std::io::Error::from_raw_os_error((words[0] & 0xffff_ffff) as i32);
+            return Err(format!("guard {} failed: {}", stage, err));
+        }
+        let mut regs = DebugRegisters::default();
+        regs.dr.copy_from_slice(&words[1..]);
+        Ok(regs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_armed_slots() {
+        let mut regs = DebugRegisters::default();
+        assert!(regs.armed_slots().is_empty());
+        regs.dr[0] = 0x401000;
+        regs.dr[7] = 0b01 | (0b10 << 4); // L0, G2
+        assert_eq!(regs.armed_slots(), vec![0, 2]);
+        assert_eq!(regs.address(0), 0x401000);
+    }
+
+    #[test]
+    fn test_guard_readback() {
+        // Sandboxes may forbid ptrace entirely; only check a successful read
+        if let Ok(regs) = read_debug_registers() {
+            assert!(regs.armed_slots().is_empty());
+        }
+    }
+}
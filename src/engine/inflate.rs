@@ -0,0 +1,271 @@
+//! Minimal DEFLATE / gzip Decoder (RFC 1951 / RFC 1952)
+//!
+//! Just enough to read `/proc/config.gz` without a compression dependency.
+//! Decoding is bit-by-bit over canonical Huffman tables (the approach of
+//! zlib's `puff.c`): slow, but the inputs are a few tens of kilobytes.
+
+const MAX_BITS: usize = 15;
+
+/// Base lengths and extra bits for length codes 257..285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+/// Base offsets and extra bits for distance codes 0..29
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// Order in which code-length code lengths are stored
+const CLEN_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn bits(&mut self, n: u32) -> Result<u32, String> {
+        let mut value = 0;
+        for i in 0..n {
+            let byte = *self.data.get(self.pos).ok_or("unexpected end of deflate stream")?;
+            value |= ((byte as u32 >> self.bit) & 1) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+    }
+}
+
+/// Canonical Huffman table: code counts per length and symbols in code order
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for len in 1..=MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, String> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid Huffman code".to_string())
+    }
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    (Huffman::new(&lengths), Huffman::new(&[5u8; 30]))
+}
+
+fn dynamic_tables(reader: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let nlen = reader.bits(5)? as usize + 257;
+    let ndist = reader.bits(5)? as usize + 1;
+    let ncode = reader.bits(4)? as usize + 4;
+
+    let mut clen = [0u8; 19];
+    for &index in &CLEN_ORDER[..ncode] {
+        clen[index] = reader.bits(3)? as u8;
+    }
+    let clen_table = Huffman::new(&clen);
+
+    let mut lengths = vec![0u8; nlen + ndist];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = clen_table.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths[..i].last().ok_or("length repeat with no previous length")?;
+                (previous, 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        if i + repeat > lengths.len() {
+            return Err("code lengths overflow the table".to_string());
+        }
+        lengths[i..i + repeat].fill(value);
+        i += repeat;
+    }
+
+    Ok((Huffman::new(&lengths[..nlen]), Huffman::new(&lengths[nlen..])))
+}
+
+fn inflate_block(reader: &mut BitReader, out: &mut Vec<u8>, lit: &Huffman, dist: &Huffman) -> Result<(), String> {
+    loop {
+        let symbol = lit.decode(reader)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let length = LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+                let dsym = dist.decode(reader)? as usize;
+                if dsym >= DIST_BASE.len() {
+                    return Err("invalid distance symbol".to_string());
+                }
+                let distance = DIST_BASE[dsym] as usize + reader.bits(DIST_EXTRA[dsym] as u32)? as usize;
+                if distance > out.len() {
+                    return Err("distance reaches before start of output".to_string());
+                }
+                let start = out.len() - distance;
+                for k in 0..length {
+                    out.push(out[start + k]);
+                }
+            }
+            _ => return Err("invalid literal/length symbol".to_string()),
+        }
+    }
+}
+
+/// Decode a raw DEFLATE stream
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut reader = BitReader { data, pos: 0, bit: 0 };
+    let mut out = Vec::new();
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align_to_byte();
+                let header = data.get(reader.pos..reader.pos + 4).ok_or("truncated stored block")?;
+                let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+                reader.pos += 4;
+                let body = data.get(reader.pos..reader.pos + len).ok_or("truncated stored block")?;
+                out.extend_from_slice(body);
+                reader.pos += len;
+            }
+            1 => {
+                let (lit, dist) = fixed_tables();
+                inflate_block(&mut reader, &mut out, &lit, &dist)?;
+            }
+            2 => {
+                let (lit, dist) = dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &mut out, &lit, &dist)?;
+            }
+            _ => return Err("invalid block type".to_string()),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+/// Decode a gzip member (the CRC is not checked)
+pub fn gunzip(data: &[u8]) -> Result<Vec<u8>, String> {
+    const FEXTRA: u8 = 1 << 2;
+    const FNAME: u8 = 1 << 3;
+    const FCOMMENT: u8 = 1 << 4;
+    const FHCRC: u8 = 1 << 1;
+
+    if data.len() < 18 || data[0] != 0x1f || data[1] != 0x8b || data[2] != 8 {
+        return Err("not a gzip stream".to_string());
+    }
+    let flags = data[3];
+    let mut pos = 10;
+    if flags & FEXTRA != 0 {
+        let xlen = u16::from_le_bytes([data[pos], data[pos + 1]]) as usize;
+        pos += 2 + xlen;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let end = data.get(pos..).and_then(|rest| rest.iter().position(|&b| b == 0)).ok_or("truncated gzip header")?;
+            pos += end + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+    inflate(data.get(pos..).ok_or("truncated gzip header")?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gunzip_fixed_huffman() {
+        // gzip of "CONFIG_PREEMPT_RT=y\n"
+        let gz = [
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x73, 0xf6, 0xf7, 0x73, 0xf3,
+            0x74, 0x8f, 0x0f, 0x08, 0x72, 0x75, 0xf5, 0x0d, 0x08, 0x89, 0x0f, 0x0a, 0xb1, 0xad, 0xe4,
+            0x02, 0x00, 0x0d, 0xfc, 0x79, 0x73, 0x14, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(gunzip(&gz).unwrap(), b"CONFIG_PREEMPT_RT=y\n");
+        assert!(gunzip(&gz[..12]).is_err());
+        assert!(gunzip(b"plain text, not gzip").is_err());
+    }
+
+    #[test]
+    fn test_inflate_back_references_and_dynamic() {
+        // Raw deflate of 12 repeated config stanzas (fixed Huffman, long matches)
+        let repeated = [
+            0x73, 0xf6, 0xf7, 0x73, 0xf3, 0x74, 0x8f, 0xf7, 0xf3, 0x8f, 0xf7, 0x88, 0x8a, 0x77, 0x0b,
+            0xf5, 0xf1, 0xb1, 0xad, 0xe4, 0x72, 0x86, 0x88, 0x05, 0x04, 0xb9, 0xba, 0xfa, 0x06, 0x84,
+            0xc4, 0x07, 0x85, 0x00, 0xc5, 0x94, 0x15, 0xa0, 0xa2, 0x11, 0x06, 0x0a, 0x99, 0xc5, 0x0a,
+            0x79, 0xf9, 0x25, 0x0a, 0xc5, 0xa9, 0x25, 0x30, 0xa5, 0x24, 0x68, 0x37, 0xa4, 0x4c, 0xbb,
+            0x11, 0x65, 0xda, 0x8d, 0x29, 0xd3, 0x6e, 0x42, 0x99, 0x76, 0x53, 0xca, 0xb4, 0x9b, 0x51,
+            0xa6, 0xdd, 0x9c, 0x32, 0xed, 0x16, 0x94, 0x69, 0xb7, 0xa4, 0x30, 0xd9, 0x50, 0x9a, 0xec,
+            0x50, 0xd2, 0x1d, 0x00,
+        ];
+        let expected: String = (0..12)
+            .map(|i| format!("CONFIG_NO_HZ_FULL=y\nCONFIG_PREEMPT_RT=y\n# CONFIG_X{} is not set\n", i))
+            .collect();
+        assert_eq!(inflate(&repeated).unwrap(), expected.as_bytes());
+
+        // Raw deflate with a dynamic Huffman block
+        let dynamic = [
+            0x15, 0x88, 0xc1, 0x0d, 0x00, 0x30, 0x10, 0x82, 0xfe, 0x6e, 0x09, 0xba, 0xff, 0x0c, 0xed,
+            0x99, 0x40, 0x82, 0x28, 0xc4, 0x3f, 0x24, 0x3b, 0x53, 0xdd, 0xf5, 0xf8, 0xb5, 0x48, 0xb1,
+            0x77, 0x7f, 0xd8, 0xd0, 0x26, 0xb4, 0x0f,
+        ];
+        assert_eq!(inflate(&dynamic).unwrap(),
+                   b"abbaa\nbbbbaba\ndbabaacbbdbbabdaabad\nbacabcaacbaacaddabbc\n\nacc");
+    }
+}
@@ -0,0 +1,159 @@
+//! Interleaved Detection Scheduling
+//!
+//! Running every detector in one upfront block gives an analyst a single,
+//! recognizable region to skip over or fast-forward through. The same
+//! detectors can instead be queued as slices and run one at a time between
+//! units of the host application's own work:
+//!
+//! ```text
+//! for chunk in work {
+//!     process(chunk);
+//!     detect_slice!(scheduler, &mut engine);
+//! }
+//! scheduler.drain(&mut engine); // whatever is left
+//! ```
+//!
+//! Slices run in the order they were added (the ptrace probes must stay
+//! last), each through `run_isolated`. The upfront scan is the same queue
+//! drained in one go, so both modes run identical detectors.
+//!
+//! A [`SliceHook`] sees every slice start and end, for checks that watch
+//! the scan as a whole rather than run as one of its slices.
+
+use std::collections::VecDeque;
+use crate::engine::isolation::run_isolated;
+use crate::engine::policy::DecisionEngine;
+
+/// Run the next queued detector slice, if any
+macro_rules! detect_slice {
+    ($scheduler:expr, $engine:expr) => {
+        $scheduler.tick($engine)
+    };
+}
+
+type Detector<'a> = Box<dyn FnOnce(&mut DecisionEngine) + 'a>;
+
+/// Which edge of a slice a [`SliceHook`] is called at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceEdge {
+    Start,
+    End,
+}
+
+/// Called with the slice name around every detector that runs
+pub type SliceHook = fn(&'static str, SliceEdge);
+
+/// One queued detector run
+struct Slice<'a> {
+    /// Phase banner printed before the slice in upfront mode
+    banner: Option<&'static str>,
+    name: &'static str,
+    /// Detector to run, or why the policy skips it
+    detector: Result<Detector<'a>, String>,
+}
+
+/// Queue of detector slices
+pub struct Scheduler<'a> {
+    queue: VecDeque<Slice<'a>>,
+    /// Print phase banners as slices run (off when interleaving)
+    banners: bool,
+    hook: Option<SliceHook>,
+}
+
+impl<'a> Scheduler<'a> {
+    pub fn new(banners: bool) -> Self {
+        Self { queue: VecDeque::new(), banners, hook: None }
+    }
+
+    /// Queue `detector`, optionally starting a new banner phase
+    pub fn add(&mut self, banner: Option<&'static str>, name: &'static str, detector: impl FnOnce(&mut DecisionEngine) + 'a) {
+        self.queue.push_back(Slice { banner, name, detector: Ok(Box::new(detector)) });
+    }
+
+    /// Record a phase the policy skips, so upfront output still lists it
+    pub fn skip(&mut self, banner: Option<&'static str>, name: &'static str, reason: String) {
+        self.queue.push_back(Slice { banner, name, detector: Err(reason) });
+    }
+
+    /// Call `hook` around every slice that runs from now on
+    pub fn set_hook(&mut self, hook: SliceHook) {
+        self.hook = Some(hook);
+    }
+
+    /// Slices still queued (skipped ones included)
+    pub fn pending(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Names of the queued slices that will run (skipped ones excluded)
+    #[allow(dead_code)] // Public API for external callers
+    pub fn scheduled(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.queue.iter().filter(|slice| slice.detector.is_ok()).map(|slice| slice.name)
+    }
+
+    /// Run the next slice. Returns `false` once the queue is empty.
+    pub fn tick(&mut self, engine: &mut DecisionEngine) -> bool {
+        let Some(slice) = self.queue.pop_front() else { return false };
+        if let (true, Some(banner)) = (self.banners, slice.banner) {
+            banner!("\n{}", banner);
+        }
+        match slice.detector {
+            Ok(detector) => {
+                if let Some(hook) = self.hook {
+                    hook(slice.name, SliceEdge::Start);
+                }
+                run_isolated(engine, slice.name, detector);
+                if let Some(hook) = self.hook {
+                    hook(slice.name, SliceEdge::End);
+                }
+            }
+            Err(reason) if self.banners => banner!("    Skipped: {}", reason),
+            Err(_) => diag!("[SCHED] Skipped {}", slice.name),
+        }
+        true
+    }
+
+    /// Run every remaining slice
+    pub fn drain(&mut self, engine: &mut DecisionEngine) {
+        while self.tick(engine) {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::policy::DetectionSource;
+
+    #[test]
+    fn test_slices_run_in_order_between_work() {
+        let mut engine = DecisionEngine::new();
+        let mut scheduler = Scheduler::new(false);
+        scheduler.add(Some("[*] A"), "a", |e| e.report(DetectionSource::Timing, 1, "a"));
+        scheduler.skip(None, "b", "test".to_string());
+        scheduler.add(None, "c", |e| e.report(DetectionSource::Ptrace, 1, "c"));
+        assert_eq!(scheduler.scheduled().collect::<Vec<_>>(), vec!["a", "c"]);
+
+        let mut work_units = 0;
+        while scheduler.pending() > 0 {
+            work_units += 1;
+            detect_slice!(scheduler, &mut engine);
+        }
+        assert_eq!(work_units, 3);
+        let order: Vec<&str> = engine.get_history().iter().map(|e| e.details.as_str()).collect();
+        assert_eq!(order, vec!["a", "c"]);
+        assert!(!scheduler.tick(&mut engine));
+    }
+
+    #[test]
+    fn test_hook_sees_every_slice_that_runs() {
+        use std::sync::Mutex;
+        static EDGES: Mutex<Vec<(&str, SliceEdge)>> = Mutex::new(Vec::new());
+        let mut engine = DecisionEngine::new();
+        let mut scheduler = Scheduler::new(false);
+        scheduler.set_hook(|name, edge| EDGES.lock().unwrap().push((name, edge)));
+        scheduler.add(None, "a", |_| {});
+        scheduler.skip(None, "b", "test".to_string());
+        scheduler.drain(&mut engine);
+        assert_eq!(*EDGES.lock().unwrap(), vec![("a", SliceEdge::Start), ("a", SliceEdge::End)]);
+    }
+}
@@ -0,0 +1,123 @@
+//! Per-Detector Panic Isolation
+//!
+//! A protection library must never be the thing that crashes the product.
+//! Every detector runs inside `catch_unwind`; a panic (an index bug in
+//! percentile math on a degenerate sample set, an unexpected /proc format)
+//! is contained, recorded as a diagnostic for operators, and reported as
+//! low-weight evidence - a hostile environment feeding us impossible
+//! values is one way to make a detector panic.
+//!
+//! One panic hook is installed for the whole process, on first use. It
+//! captures the message of a panic on a thread that is inside a detector
+//! (a thread-local flag) for the diagnostic, and hands every other panic -
+//! background threads, the host application - to the hook it replaced.
+//!
+//! Panics inside signal handlers cannot unwind across `extern "C"` and
+//! still abort; handlers are kept trivial for that reason.
+
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+static HOOK: Once = Once::new();
+
+thread_local! {
+    /// Whether this thread is running a detector under `run_isolated`
+    static IN_DETECTOR: Cell<bool> = const { Cell::new(false) };
+    /// Message and location of this thread's most recent contained panic
+    static LAST_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+fn describe(info: &panic::PanicHookInfo) -> String {
+    let message = info.payload().downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string());
+    match info.location() {
+        Some(loc) => format!("{} at {}:{}", message, loc.file(), loc.line()),
+        None => message,
+    }
+}
+
+/// Install the process-wide hook, keeping the previous one for panics
+/// outside detectors
+fn install_hook() {
+    HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if IN_DETECTOR.with(Cell::get) {
+                LAST_PANIC.with(|last| *last.borrow_mut() = Some(describe(info)));
+            } else {
+                previous(info);
+            }
+        }));
+    });
+}
+
+/// Run `detector` against `engine`, containing any panic.
+/// Returns `false` if the detector panicked.
+pub fn run_isolated(engine: &mut DecisionEngine, name: &str, detector: impl FnOnce(&mut DecisionEngine)) -> bool {
+    install_hook();
+
+    // Evidence recorded before the panic stays valid: the engine only appends
+    engine.begin_detector(name);
+    let outer = IN_DETECTOR.with(|flag| flag.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| detector(engine)));
+    IN_DETECTOR.with(|flag| flag.set(outer));
+
+    if result.is_ok() {
+        engine.end_detector();
+        return true;
+    }
+
+    let message = LAST_PANIC.with(|last| last.borrow_mut().take())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    engine.record_diagnostic(name, &format!("panicked: {}", message));
+    engine.report_with_confidence(
+        DetectionSource::DetectorFault,
+        10,
+        0.5,
+        &reason!("Detector '{}' panicked and was skipped", name)
+    );
+    engine.end_detector();
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_is_contained_and_recorded() {
+        let mut engine = DecisionEngine::new();
+        let ok = run_isolated(&mut engine, "broken", |_| {
+            let samples: Vec<u64> = Vec::new();
+            let _ = samples[samples.len() / 2];
+        });
+        assert!(!ok);
+        assert_eq!(engine.get_diagnostics().len(), 1);
+        assert!(engine.get_diagnostics()[0].message.contains("index out of bounds"));
+        assert!(engine.get_history().iter().any(|e| e.source == DetectionSource::DetectorFault));
+    }
+
+    #[test]
+    fn test_concurrent_detectors_keep_their_own_panics() {
+        let workers: Vec<_> = (0..4).map(|i| std::thread::spawn(move || {
+            let mut engine = DecisionEngine::new();
+            for _ in 0..20 {
+                assert!(!run_isolated(&mut engine, "broken", |_| panic!("worker {}", i)));
+            }
+            engine.get_diagnostics().iter().all(|d| d.message.starts_with(&format!("panicked: worker {} at", i)))
+        })).collect();
+        for worker in workers {
+            assert!(worker.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_clean_detector_runs() {
+        let mut engine = DecisionEngine::new();
+        assert!(run_isolated(&mut engine, "noop", |e| e.record_flag("hv_bit", false)));
+        assert!(engine.get_diagnostics().is_empty());
+    }
+}
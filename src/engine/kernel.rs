@@ -0,0 +1,183 @@
+//! Kernel Configuration Awareness
+//!
+//! Some kernel builds and boot options change syscall and context-switch
+//! latency by a large factor. A detector that does not know this blames the
+//! overhead on instrumentation:
+//!
+//! | Setting                     | Effect on the hot paths we time                  |
+//! |-----------------------------|--------------------------------------------------|
+//! | `CONFIG_PREEMPT_RT`         | Threaded IRQs, sleeping locks: slower, jittery   |
+//! | `nohz_full=` CPUs           | Context tracking on every kernel entry/exit      |
+//! | KPTI (`Mitigation: PTI`)    | CR3 switch on every syscall, 2-5x entry cost     |
+//! | IBRS / buffer clearing      | Extra MSR writes or VERW on kernel entry/exit    |
+//! | `mitigations=off`           | Entry path cheaper than the baselines assume     |
+//!
+//! Sources, in order of preference: `/proc/config.gz` (decoded with
+//! [`crate::engine::inflate`]), `/boot/config-<release>`, the running
+//! kernel's `uname` version string, `/proc/cmdline`, and
+//! `/sys/devices/system/cpu/vulnerabilities`.
+
+use std::fs;
+use std::path::Path;
+use crate::engine::inflate;
+
+const VULNERABILITIES_DIR: &str = "/sys/devices/system/cpu/vulnerabilities";
+
+/// Kernel settings that move latency baselines
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KernelProfile {
+    /// Where the build config was read from, `None` if unreadable
+    pub config_source: Option<String>,
+    pub preempt_rt: bool,
+    /// CPU list passed as `nohz_full=`
+    pub nohz_full: Option<String>,
+    /// `mitigations=off` on the command line
+    pub mitigations_off: bool,
+    /// Kernel page-table isolation active
+    pub kpti: bool,
+    /// Other active mitigations that add work to every kernel entry/exit
+    pub entry_mitigations: Vec<String>,
+}
+
+impl KernelProfile {
+    /// Read every available source
+    pub fn detect() -> Self {
+        let mut profile = Self::default();
+
+        if let Some((source, config)) = read_kernel_config() {
+            profile.config_source = Some(source);
+            profile.preempt_rt = config_enabled(&config, "CONFIG_PREEMPT_RT");
+        }
+        // The uname version string names PREEMPT_RT even without a readable config
+        let mut uts: libc::utsname = unsafe { std::mem::zeroed() };
+        // SAFETY: uname fills the struct we own
+        if unsafe { libc::uname(&mut uts) } == 0 {
+            let version = unsafe { std::ffi::CStr::from_ptr(uts.version.as_ptr()) };
+            profile.preempt_rt |= version.to_string_lossy().contains("PREEMPT_RT");
+        }
+        profile.preempt_rt |= fs::read_to_string("/sys/kernel/realtime").is_ok_and(|s| s.trim() == "1");
+
+        if let Ok(cmdline) = fs::read_to_string("/proc/cmdline") {
+            profile.apply_cmdline(&cmdline);
+        }
+
+        for (name, status) in read_vulnerabilities(Path::new(VULNERABILITIES_DIR)) {
+            profile.apply_vulnerability(&name, &status);
+        }
+
+        profile
+    }
+
+    fn apply_cmdline(&mut self, cmdline: &str) {
+        for arg in cmdline.split_whitespace() {
+            // Arguments after "--" belong to init
+            if arg == "--" {
+                break;
+            }
+            match arg.split_once('=') {
+                Some(("nohz_full", cpus)) if !cpus.is_empty() => self.nohz_full = Some(cpus.to_string()),
+                Some(("mitigations", "off")) => self.mitigations_off = true,
+                _ => {}
+            }
+        }
+    }
+
+    fn apply_vulnerability(&mut self, name: &str, status: &str) {
+        if !status.starts_with("Mitigation:") {
+            return;
+        }
+        if name == "meltdown" && status.contains("PTI") {
+            self.kpti = true;
+        } else if is_entry_mitigation(status) {
+            self.entry_mitigations.push(name.to_string());
+        }
+    }
+
+    /// Expected slowdown of syscall round trips relative to the baselines
+    pub fn syscall_cost_factor(&self) -> f64 {
+        let mut factor: f64 = 1.0;
+        if self.kpti {
+            factor *= 2.0;
+        }
+        if !self.entry_mitigations.is_empty() {
+            factor *= 1.5;
+        }
+        if self.nohz_full.is_some() {
+            factor *= 1.5;
+        }
+        if self.preempt_rt {
+            factor *= 1.5;
+        }
+        factor.min(4.0)
+    }
+}
+
+/// Mitigations that run on every kernel entry or exit (as opposed to
+/// context switches or specific syscalls only)
+fn is_entry_mitigation(status: &str) -> bool {
+    let legacy_ibrs = status.contains("IBRS") && !status.contains("Enhanced") && !status.contains("eIBRS");
+    legacy_ibrs || status.contains("Clear CPU buffers") || status.contains("Safe RET")
+}
+
+/// `CONFIG_X=y` (or `=m`) present in a kernel config
+pub fn config_enabled(config: &str, key: &str) -> bool {
+    config.lines().any(|line| {
+        line.strip_prefix(key)
+            .and_then(|rest| rest.strip_prefix('='))
+            .is_some_and(|value| value == "y" || value == "m")
+    })
+}
+
+/// Build config of the running kernel with the path it came from
+fn read_kernel_config() -> Option<(String, String)> {
+    if let Ok(gz) = fs::read("/proc/config.gz") {
+        if let Ok(config) = inflate::gunzip(&gz) {
+            return Some(("/proc/config.gz".to_string(), String::from_utf8_lossy(&config).into_owned()));
+        }
+    }
+    let release = fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+    let path = format!("/boot/config-{}", release.trim());
+    fs::read_to_string(&path).ok().map(|config| (path, config))
+}
+
+fn read_vulnerabilities(dir: &Path) -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir(dir) else { return Vec::new() };
+    let mut out: Vec<(String, String)> = entries.flatten()
+        .filter_map(|e| {
+            let status = fs::read_to_string(e.path()).ok()?;
+            Some((e.file_name().to_string_lossy().into_owned(), status.trim().to_string()))
+        })
+        .collect();
+    out.sort();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_and_cmdline() {
+        let config = "# CONFIG_PREEMPT_RT is not set\nCONFIG_NO_HZ_FULL=y\nCONFIG_PREEMPT_RTX=y\n";
+        assert!(!config_enabled(config, "CONFIG_PREEMPT_RT"));
+        assert!(config_enabled(config, "CONFIG_NO_HZ_FULL"));
+
+        let mut profile = KernelProfile::default();
+        profile.apply_cmdline("quiet nohz_full=2-7 mitigations=off -- nohz_full=0");
+        assert_eq!(profile.nohz_full.as_deref(), Some("2-7"));
+        assert!(profile.mitigations_off);
+    }
+
+    #[test]
+    fn test_vulnerabilities_and_cost() {
+        let mut profile = KernelProfile::default();
+        profile.apply_vulnerability("meltdown", "Mitigation: PTI");
+        profile.apply_vulnerability("spectre_v2", "Mitigation: Enhanced / Automatic IBRS; IBPB: conditional");
+        profile.apply_vulnerability("mds", "Mitigation: Clear CPU buffers; SMT vulnerable");
+        profile.apply_vulnerability("l1tf", "Not affected");
+        assert!(profile.kpti);
+        assert_eq!(profile.entry_mitigations, vec!["mds".to_string()]);
+        assert_eq!(profile.syscall_cost_factor(), 3.0);
+        assert_eq!(KernelProfile::default().syscall_cost_factor(), 1.0);
+    }
+}
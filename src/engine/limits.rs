@@ -0,0 +1,168 @@
+//! Per-Source Score Limits
+//!
+//! The additive score lets any one detector reach a verdict on its own:
+//! a laptop's frequency scaling can make jitter report again and again
+//! until the score passes `Instrumented`. Two limits, applied when
+//! evidence is reported, keep one source from dominating:
+//!
+//! | Limit                 | Effect                                                         |
+//! |-----------------------|----------------------------------------------------------------|
+//! | Per-source cap        | A source's total weight never exceeds its cap; the rest is     |
+//! |                       | recorded with weight 0                                         |
+//! | Duplicate collapsing  | Evidence repeating an earlier report (same source, thread and  |
+//! |                       | details) adds no weight; the earlier entry counts the repeat   |
+//!
+//! Each preset caps the timing-based sources (`Timing`, `Jitter`,
+//! `CpuAccounting`) just below its `Instrumented` threshold, so timing
+//! alone can at most make a run `Suspicious`, and collapses duplicates. A
+//! bare `DecisionEngine` applies neither: repeated evidence adds up as it
+//! always did. `--source-caps` (or
+//! `ANTIDEBUG_SOURCE_CAPS`) overrides single sources:
+//!
+//! ```text
+//! --source-caps "Jitter=30,Ptrace=none,dedup=off"
+//! ```
+
+use crate::engine::policy::{DetectionSource, Evidence};
+
+/// Environment variable with cap overrides when `--source-caps` is absent
+pub const SOURCE_CAPS_ENV_VAR: &str = "ANTIDEBUG_SOURCE_CAPS";
+
+/// Weight limits applied by `DecisionEngine::report`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SourceLimits {
+    /// Maximum total weight per source, indexed by `DetectionSource as usize`
+    caps: [Option<u32>; DetectionSource::ALL.len()],
+    /// Collapse evidence that repeats an earlier report
+    pub collapse_duplicates: bool,
+}
+
+impl Default for SourceLimits {
+    /// No caps, duplicates add up
+    fn default() -> Self {
+        Self { caps: [None; DetectionSource::ALL.len()], collapse_duplicates: false }
+    }
+}
+
+impl SourceLimits {
+    /// Timing-based sources capped at `cap` each
+    pub fn timing_capped(cap: u32) -> Self {
+        let mut limits = Self::default();
+        for source in DetectionSource::ALL.into_iter().filter(|s| s.is_timing_based()) {
+            limits.set_cap(source, Some(cap));
+        }
+        limits
+    }
+
+    /// The same limits with duplicate collapsing on
+    pub fn collapsing_duplicates(self) -> Self {
+        Self { collapse_duplicates: true, ..self }
+    }
+
+    pub fn cap(&self, source: DetectionSource) -> Option<u32> {
+        self.caps[source as usize]
+    }
+
+    pub fn set_cap(&mut self, source: DetectionSource, cap: Option<u32>) {
+        self.caps[source as usize] = cap;
+    }
+
+    /// Apply overrides of the form `Source=N`, `Source=none` and
+    /// `dedup=on|off`, separated by commas
+    pub fn apply_overrides(&mut self, spec: &str) -> Result<(), String> {
+        for item in spec.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let (key, value) = item.split_once('=').ok_or_else(|| format!("'{}' is not key=value", item))?;
+            let (key, value) = (key.trim(), value.trim());
+            if key.eq_ignore_ascii_case("dedup") {
+                self.collapse_duplicates = match value {
+                    "on" | "1" | "true" => true,
+                    "off" | "0" | "false" => false,
+                    _ => return Err(format!("dedup must be on or off, not '{}'", value)),
+                };
+                continue;
+            }
+            let source = DetectionSource::from_name(key).ok_or_else(|| format!("unknown source '{}'", key))?;
+            let cap = if value.eq_ignore_ascii_case("none") {
+                None
+            } else {
+                Some(value.parse::<u32>().map_err(|_| format!("cap for {} must be a number or none, not '{}'", key, value))?)
+            };
+            self.set_cap(source, cap);
+        }
+        Ok(())
+    }
+
+    /// Weight that counts for new evidence of `weight` from a source that
+    /// has `total` so far
+    pub fn admit(&self, source: DetectionSource, total: u32, weight: u32) -> u32 {
+        match self.cap(source) {
+            Some(cap) => weight.min(cap.saturating_sub(total)),
+            None => weight,
+        }
+    }
+
+    /// Index of the earlier evidence that `source`, `thread` and `details`
+    /// repeat, if duplicates are collapsed
+    pub fn duplicate_of(&self, history: &[Evidence], source: DetectionSource, thread: Option<&str>, details: &str) -> Option<usize> {
+        if !self.collapse_duplicates {
+            return None;
+        }
+        history.iter().position(|e| e.source == source && e.thread.as_deref() == thread && e.details == details)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::policy::DecisionEngine;
+
+    #[test]
+    fn test_source_order_matches_discriminants() {
+        assert!(DetectionSource::ALL.iter().enumerate().all(|(i, s)| *s as usize == i));
+        assert_eq!(DetectionSource::from_name("jitter"), Some(DetectionSource::Jitter));
+        assert_eq!(DetectionSource::from_name("Nope"), None);
+    }
+
+    #[test]
+    fn test_default_keeps_duplicates() {
+        assert!(!SourceLimits::default().collapse_duplicates);
+        let mut engine = DecisionEngine::new();
+        engine.report(DetectionSource::Jitter, 20, "jitter burst");
+        engine.report(DetectionSource::Jitter, 20, "jitter burst");
+        assert_eq!(engine.get_score(), 40);
+        assert_eq!(engine.get_history().len(), 2);
+    }
+
+    #[test]
+    fn test_overrides() {
+        let mut limits = SourceLimits::timing_capped(45);
+        assert_eq!(limits.cap(DetectionSource::Jitter), Some(45));
+        assert_eq!(limits.cap(DetectionSource::Ptrace), None);
+        limits.apply_overrides("Jitter=30, timing=none, Ptrace=60, dedup=off").unwrap();
+        assert_eq!(limits.cap(DetectionSource::Jitter), Some(30));
+        assert_eq!(limits.cap(DetectionSource::Timing), None);
+        assert_eq!(limits.cap(DetectionSource::Ptrace), Some(60));
+        assert!(!limits.collapse_duplicates);
+        assert!(limits.apply_overrides("Bogus=1").is_err());
+        assert!(limits.apply_overrides("Jitter=lots").is_err());
+        assert!(limits.apply_overrides("Jitter").is_err());
+    }
+
+    #[test]
+    fn test_noisy_source_stays_below_instrumented() {
+        let mut engine = DecisionEngine::new();
+        engine.set_source_limits(SourceLimits::timing_capped(45).collapsing_duplicates());
+        for i in 0..5 {
+            engine.report(DetectionSource::Jitter, 20, &format!("jitter burst {}", i));
+        }
+        assert_eq!(engine.get_score(), 45);
+        assert_eq!(engine.get_history().iter().map(|e| e.weight).collect::<Vec<_>>(), vec![20, 20, 5, 0, 0]);
+
+        // Repeats add nothing but are counted on the first report
+        engine.report(DetectionSource::Ptrace, 40, "TracerPid: 1234");
+        engine.report(DetectionSource::Ptrace, 40, "TracerPid: 1234");
+        assert_eq!(engine.get_score(), 85);
+        assert_eq!(engine.get_history().len(), 6);
+        assert_eq!(engine.get_history()[5].repeats, 1);
+    }
+}
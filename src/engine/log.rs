@@ -0,0 +1,316 @@
+//! Diagnostic Log Channel
+//!
+//! Every `[TAG]` diagnostic in the framework goes through [`diag!`]. By
+//! default that is plain stderr. When an embedder installs an
+//! [`EncryptedSink`], events are instead encrypted with the embedder's key
+//! and written as opaque blobs, so operators can still debug a deployment
+//! without stderr handing the analyst a narrated walkthrough of every check.
+//!
+//! # Blob Format
+//!
+//! One record per event, appended back to back:
+//!
+//! ```text
+//! [u32 LE ciphertext length][12-byte random nonce][ChaCha20 ciphertext]
+//! ```
+//!
+//! Records carry no authentication tag: the channel hides content, it does
+//! not prove integrity. Use [`decrypt_blobs`] (or `--decrypt-log`) to read
+//! a log back.
+//!
+//! There is no network reporting endpoint in the framework yet; any
+//! `Write + Send` (a socket included) can back the sink.
+//!
+//! # Stealth Builds
+//!
+//! Encryption hides the log, but the format strings themselves still sit in
+//! `.rodata` and map out every check. With the `stealth` cargo feature,
+//! [`diag!`] discards its format string at compile time and emits
+//! `D<code> <args...>` instead, where the code is [`diag_code`] of the call
+//! site keyed by the per-build salt (`salt.rs`). Evidence details built
+//! with [`reason!`] become `R<code> <args...>` the same way, and static
+//! texts such as feature descriptions in [`described!`] become `#<line>`.
+//! `build.rs` writes the code table (`diag_codes.tsv`) next to the build
+//! output. [`banner!`] output is dropped entirely.
+
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use crate::engine::chacha20::{self, KEY_LEN, NONCE_LEN};
+
+/// Hex-encoded 32-byte key enabling the encrypted sink (demo binary only;
+/// a real embedder compiles its key in rather than exposing it in environ)
+pub const LOG_KEY_ENV_VAR: &str = "ANTIDEBUG_LOG_KEY";
+
+/// File the encrypted sink appends to
+pub const LOG_FILE_ENV_VAR: &str = "ANTIDEBUG_LOG_FILE";
+
+/// Emit a diagnostic through the active log channel (stderr by default)
+#[cfg(not(feature = "stealth"))]
+macro_rules! diag {
+    ($($arg:tt)*) => {
+        $crate::engine::log::emit(format_args!($($arg)*))
+    };
+}
+
+/// Emit a diagnostic as a numeric code plus its raw arguments
+#[cfg(feature = "stealth")]
+macro_rules! diag {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::engine::log::emit_code(
+            const { $crate::engine::salt::salt_code($crate::engine::log::diag_code(file!(), line!())) },
+            &[$(&$arg as &dyn ::std::fmt::Debug),*],
+        )
+    };
+}
+
+/// Text that describes a finding (evidence details, contradictions, engine
+/// diagnostics): `format!` in normal builds
+#[cfg(not(feature = "stealth"))]
+macro_rules! reason {
+    ($($arg:tt)*) => {
+        format!($($arg)*)
+    };
+}
+
+/// The finding as `R<code>` plus its raw arguments, coded like [`diag!`]
+#[cfg(feature = "stealth")]
+macro_rules! reason {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::engine::log::code_text(
+            'R',
+            const { $crate::engine::salt::salt_code($crate::engine::log::diag_code(file!(), line!())) },
+            &[$(&$arg as &dyn ::std::fmt::Debug),*],
+        )
+    };
+}
+
+/// Static text without arguments (feature descriptions, signature tables)
+#[cfg(not(feature = "stealth"))]
+macro_rules! described {
+    ($text:literal) => {
+        $text
+    };
+}
+
+/// `#<line>`, listed in the code table under the file it appears in
+#[cfg(feature = "stealth")]
+macro_rules! described {
+    ($text:literal) => {
+        concat!("#", line!())
+    };
+}
+
+/// Progress output for interactive runs (stdout); compiled out in stealth builds
+#[cfg(not(feature = "stealth"))]
+macro_rules! banner {
+    ($($arg:tt)*) => {
+        println!($($arg)*)
+    };
+}
+
+#[cfg(feature = "stealth")]
+macro_rules! banner {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        { $(let _ = &$arg;)* }
+    };
+}
+
+/// Log sink writing each event as an encrypted record
+pub struct EncryptedSink {
+    key: [u8; KEY_LEN],
+    out: Box<dyn Write + Send>,
+}
+
+impl EncryptedSink {
+    pub fn new(key: [u8; KEY_LEN], out: Box<dyn Write + Send>) -> Self {
+        Self { key, out }
+    }
+
+    /// Sink appending to the file at `path`
+    pub fn open(key: [u8; KEY_LEN], path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(key, Box::new(file)))
+    }
+
+    fn write_event(&mut self, message: &str) -> io::Result<()> {
+        let nonce = random_nonce();
+        let mut data = message.as_bytes().to_vec();
+        chacha20::apply_keystream(&self.key, &nonce, 0, &mut data);
+
+        let mut record = Vec::with_capacity(4 + NONCE_LEN + data.len());
+        record.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        record.extend_from_slice(&nonce);
+        record.extend_from_slice(&data);
+        self.out.write_all(&record)?;
+        self.out.flush()
+    }
+}
+
+static SINK: Mutex<Option<EncryptedSink>> = Mutex::new(None);
+
+/// Route all further diagnostics into `sink` instead of stderr
+pub fn install_sink(sink: EncryptedSink) {
+    if let Ok(mut slot) = SINK.lock() {
+        *slot = Some(sink);
+    }
+}
+
+/// Backend of [`diag!`]
+pub fn emit(args: fmt::Arguments) {
+    if let Ok(mut slot) = SINK.lock() {
+        if let Some(sink) = slot.as_mut() {
+            // A broken sink must not resurrect the stderr narration
+            let _ = sink.write_event(&args.to_string());
+            return;
+        }
+    }
+    eprintln!("{}", args);
+}
+
+/// Stealth-build code of the `diag!` at `file:line` (32-bit FNV-1a of the
+/// path bytes followed by the little-endian line). `build.rs` computes the
+/// same hash for the code table.
+#[allow(dead_code)] // Only reached with the `stealth` feature
+pub const fn diag_code(file: &str, line: u32) -> u32 {
+    const PRIME: u32 = 0x0100_0193;
+    let mut hash: u32 = 0x811c_9dc5;
+    let bytes = file.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        hash = (hash ^ bytes[i] as u32).wrapping_mul(PRIME);
+        i += 1;
+    }
+    let line = line.to_le_bytes();
+    let mut j = 0;
+    while j < line.len() {
+        hash = (hash ^ line[j] as u32).wrapping_mul(PRIME);
+        j += 1;
+    }
+    hash
+}
+
+/// Backend of the stealth-build [`diag!`]
+#[allow(dead_code)] // Only reached with the `stealth` feature
+pub fn emit_code(code: u32, args: &[&dyn fmt::Debug]) {
+    emit(format_args!("{}", code_text('D', code, args)));
+}
+
+/// `<kind><code>` followed by the raw arguments (stealth [`diag!`] and
+/// [`reason!`])
+#[allow(dead_code)] // Only reached with the `stealth` feature
+pub fn code_text(kind: char, code: u32, args: &[&dyn fmt::Debug]) -> String {
+    let mut line = format!("{}{:08x}", kind, code);
+    for arg in args {
+        line.push_str(&format!(" {:?}", arg));
+    }
+    line
+}
+
+/// Fresh ChaCha20 nonce (getrandom, with a clock/counter fallback)
+pub fn random_nonce() -> [u8; NONCE_LEN] {
+    let mut nonce = [0u8; NONCE_LEN];
+    // SAFETY: getrandom writes at most `len` bytes into our buffer
+    let n = unsafe { libc::getrandom(nonce.as_mut_ptr() as *mut libc::c_void, NONCE_LEN, 0) };
+    if n != NONCE_LEN as isize {
+        // Never reuse a nonce: fall back to a clock/counter mix
+        static FALLBACK: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+        let ns = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        nonce[..8].copy_from_slice(&ns.to_le_bytes());
+        nonce[8..].copy_from_slice(&FALLBACK.fetch_add(1, std::sync::atomic::Ordering::Relaxed).to_le_bytes());
+    }
+    nonce
+}
+
+/// Parse a 64-character hex key
+pub fn parse_key(hex: &str) -> Result<[u8; KEY_LEN], String> {
+    let hex = hex.trim();
+    if hex.len() != KEY_LEN * 2 {
+        return Err(format!("key must be {} hex characters, got {}", KEY_LEN * 2, hex.len()));
+    }
+    let mut key = [0u8; KEY_LEN];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+            .map_err(|_| format!("invalid hex at offset {}", 2 * i))?;
+    }
+    Ok(key)
+}
+
+/// Decrypt a log written by [`EncryptedSink`] back into messages
+pub fn decrypt_blobs(key: &[u8; KEY_LEN], mut data: &[u8]) -> Result<Vec<String>, String> {
+    let mut messages = Vec::new();
+    while !data.is_empty() {
+        if data.len() < 4 + NONCE_LEN {
+            return Err(format!("truncated record header after {} records", messages.len()));
+        }
+        let len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce.copy_from_slice(&data[4..4 + NONCE_LEN]);
+        let body = &data[4 + NONCE_LEN..];
+        if body.len() < len {
+            return Err(format!("truncated record body after {} records", messages.len()));
+        }
+
+        let mut plain = body[..len].to_vec();
+        chacha20::apply_keystream(key, &nonce, 0, &mut plain);
+        messages.push(String::from_utf8_lossy(&plain).into_owned());
+        data = &body[len..];
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex as StdMutex};
+
+    /// Shared in-memory writer so the test can read back what the sink wrote
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<StdMutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_is_opaque() {
+        let key = [7u8; KEY_LEN];
+        let buffer = Buffer::default();
+        let mut sink = EncryptedSink::new(key, Box::new(buffer.clone()));
+        sink.write_event("[TIMING] overhead=42").unwrap();
+        sink.write_event("[PTRACE] TracerPid: 0").unwrap();
+
+        let raw = buffer.0.lock().unwrap().clone();
+        assert!(!raw.windows(6).any(|w| w == b"TIMING"));
+        assert_eq!(decrypt_blobs(&key, &raw).unwrap(), vec!["[TIMING] overhead=42", "[PTRACE] TracerPid: 0"]);
+        assert!(decrypt_blobs(&key, &raw[..raw.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_diag_code() {
+        // FNV-1a reference value for the empty input
+        assert_eq!(diag_code("", 0), 0x4b95_f515);
+        assert_ne!(diag_code("src/main.rs", 10), diag_code("src/main.rs", 11));
+        const CODE: u32 = diag_code("src/engine/log.rs", 1);
+        assert_eq!(CODE, diag_code("src/engine/log.rs", 1));
+    }
+
+    #[test]
+    fn test_parse_key() {
+        let key = parse_key(&"0f".repeat(KEY_LEN)).unwrap();
+        assert_eq!(key, [0x0f; KEY_LEN]);
+        assert!(parse_key("abcd").is_err());
+        assert!(parse_key(&"zz".repeat(KEY_LEN)).is_err());
+    }
+}
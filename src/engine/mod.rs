@@ -1,4 +1,36 @@
+// Declared first so `diag!` is in scope for every other module
+#[macro_use]
+pub mod log;
+
+pub mod axes;
+pub mod baseline;
+pub mod bayes;
+pub mod chacha20;
+pub mod classifier;
+pub mod decay;
 pub mod environment;
+pub mod explain;
+pub mod features;
+pub mod guard;
+pub mod inflate;
+#[macro_use]
+pub mod interleave;
+pub mod isolation;
+pub mod kernel;
+pub mod limits;
+pub mod model;
+pub mod placement;
 pub mod policy;
+pub mod posture;
+pub mod presets;
+pub mod report;
 pub mod responses;
+pub mod reverify;
+pub mod rules;
+pub mod salt;
+pub mod secret;
+pub mod sha256;
 pub mod signal_compat;
+pub mod threads;
+pub mod timeline;
+pub mod tools;
@@ -0,0 +1,372 @@
+//! Loadable Model Classifiers
+//!
+//! Lets a threat-intel team retune detection centrally by shipping a small
+//! model file instead of a new binary. Two model types are supported:
+//!
+//! - **Logistic regression**: `p = sigmoid(bias + sum(w_i * x_i))` over
+//!   feature-vector columns, mapped to a verdict by probability cut-offs
+//! - **Decision tree**: binary splits on feature-vector columns with a
+//!   verdict at each leaf
+//!
+//! # File Format
+//!
+//! A minimal TOML subset: `key = value` pairs, `[weights]` table,
+//! `[[node]]` array of tables, `#` comments. Values are numbers or
+//! double-quoted strings.
+//!
+//! ```toml
+//! type = "logistic"
+//! bias = -4.0
+//! suspicious = 0.3      # probability cut-offs
+//! instrumented = 0.6
+//! deceptive = 0.9
+//!
+//! [weights]
+//! tracerpid_nonzero = 6.0
+//! amp_mean = 0.00002
+//! ```
+//!
+//! ```toml
+//! type = "tree"
+//!
+//! [[node]]              # node 0 is the root
+//! feature = "tracerpid_nonzero"
+//! threshold = 0.5       # go left if value < threshold
+//! left = 1
+//! right = 2
+//!
+//! [[node]]
+//! verdict = "Clean"
+//!
+//! [[node]]
+//! verdict = "Instrumented"
+//! ```
+//!
+//! Unobserved (NaN) features contribute 0 to a logistic model and take the
+//! left branch in a tree. Column names are validated against the feature
+//! schema at load time.
+
+use std::collections::HashMap;
+use std::path::Path;
+use crate::engine::classifier::Classifier;
+use crate::engine::features::{FeatureVector, FEATURES};
+use crate::engine::policy::{Evidence, Verdict};
+
+/// Environment variable naming a model file to load at startup
+pub const MODEL_ENV_VAR: &str = "ANTIDEBUG_MODEL";
+
+/// Maximum tree depth walked before giving up (guards against cycles)
+const MAX_TREE_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    Text(String),
+}
+
+/// One `[section]` / `[[node]]` / top-level block of key-value pairs
+#[derive(Debug, Default)]
+struct Table {
+    entries: HashMap<String, Value>,
+}
+
+impl Table {
+    fn number(&self, key: &str) -> Result<f64, String> {
+        match self.entries.get(key) {
+            Some(Value::Number(n)) => Ok(*n),
+            Some(_) => Err(format!("'{}' must be a number", key)),
+            None => Err(format!("missing '{}'", key)),
+        }
+    }
+
+    fn text(&self, key: &str) -> Result<&str, String> {
+        match self.entries.get(key) {
+            Some(Value::Text(s)) => Ok(s),
+            Some(_) => Err(format!("'{}' must be a string", key)),
+            None => Err(format!("missing '{}'", key)),
+        }
+    }
+}
+
+/// Parsed document: top-level keys, the `[weights]` table and `[[node]]` tables
+#[derive(Debug, Default)]
+struct Document {
+    root: Table,
+    weights: Table,
+    nodes: Vec<Table>,
+}
+
+fn parse_value(raw: &str) -> Result<Value, String> {
+    if let Some(inner) = raw.strip_prefix('"') {
+        let text = inner.strip_suffix('"').ok_or_else(|| format!("unterminated string: {}", raw))?;
+        return Ok(Value::Text(text.to_string()));
+    }
+    raw.parse::<f64>()
+        .map(Value::Number)
+        .map_err(|_| format!("invalid value: {}", raw))
+}
+
+fn parse_document(text: &str) -> Result<Document, String> {
+    enum Section { Root, Weights, Node }
+
+    let mut doc = Document::default();
+    let mut section = Section::Root;
+
+    for (lineno, line) in text.lines().enumerate() {
+        let line = match line.find('#') {
+            Some(idx) if line[..idx].matches('"').count() % 2 == 0 => &line[..idx],
+            _ => line,
+        }.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match line {
+            "[weights]" => { section = Section::Weights; continue; }
+            "[[node]]" => { doc.nodes.push(Table::default()); section = Section::Node; continue; }
+            _ if line.starts_with('[') => {
+                return Err(format!("line {}: unknown section {}", lineno + 1, line));
+            }
+            _ => {}
+        }
+
+        let (key, value) = line.split_once('=')
+            .ok_or_else(|| format!("line {}: expected key = value", lineno + 1))?;
+        let value = parse_value(value.trim()).map_err(|e| format!("line {}: {}", lineno + 1, e))?;
+        let table = match section {
+            Section::Root => &mut doc.root,
+            Section::Weights => &mut doc.weights,
+            Section::Node => doc.nodes.last_mut().unwrap(),
+        };
+        table.entries.insert(key.trim().to_string(), value);
+    }
+
+    Ok(doc)
+}
+
+fn check_feature(name: &str) -> Result<(), String> {
+    if FEATURES.iter().any(|(n, _)| *n == name) {
+        Ok(())
+    } else {
+        Err(format!("unknown feature column '{}'", name))
+    }
+}
+
+fn parse_verdict(name: &str) -> Result<Verdict, String> {
+    match name {
+        "Clean" => Ok(Verdict::Clean),
+        "Suspicious" => Ok(Verdict::Suspicious),
+        "Instrumented" => Ok(Verdict::Instrumented),
+        "Deceptive" => Ok(Verdict::Deceptive),
+        other => Err(format!("unknown verdict '{}'", other)),
+    }
+}
+
+/// Feature value for model input: unobserved columns read as 0
+fn input(features: &FeatureVector, name: &str) -> f64 {
+    features.get(name).unwrap_or(0.0)
+}
+
+/// Logistic-regression classifier loaded from a model file
+#[derive(Debug, Clone)]
+pub struct LogisticModel {
+    bias: f64,
+    weights: Vec<(String, f64)>,
+    suspicious: f64,
+    instrumented: f64,
+    deceptive: f64,
+}
+
+impl LogisticModel {
+    fn from_document(doc: &Document) -> Result<Self, String> {
+        let mut weights = Vec::new();
+        for (name, value) in &doc.weights.entries {
+            check_feature(name)?;
+            match value {
+                Value::Number(w) => weights.push((name.clone(), *w)),
+                Value::Text(_) => return Err(format!("weight '{}' must be a number", name)),
+            }
+        }
+        weights.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(Self {
+            bias: doc.root.number("bias").unwrap_or(0.0),
+            weights,
+            suspicious: doc.root.number("suspicious").unwrap_or(0.3),
+            instrumented: doc.root.number("instrumented").unwrap_or(0.6),
+            deceptive: doc.root.number("deceptive").unwrap_or(0.9),
+        })
+    }
+
+    /// Probability that the run is instrumented
+    pub fn probability(&self, features: &FeatureVector) -> f64 {
+        let z = self.weights.iter()
+            .fold(self.bias, |acc, (name, w)| acc + w * input(features, name));
+        1.0 / (1.0 + (-z).exp())
+    }
+}
+
+impl Classifier for LogisticModel {
+    fn name(&self) -> &str {
+        "logistic"
+    }
+
+    fn classify(&self, features: &FeatureVector, _evidence: &[Evidence]) -> Verdict {
+        let p = self.probability(features);
+        if p >= self.deceptive {
+            Verdict::Deceptive
+        } else if p >= self.instrumented {
+            Verdict::Instrumented
+        } else if p >= self.suspicious {
+            Verdict::Suspicious
+        } else {
+            Verdict::Clean
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum TreeNode {
+    Split { feature: String, threshold: f64, left: usize, right: usize },
+    Leaf(Verdict),
+}
+
+/// Decision-tree classifier loaded from a model file
+#[derive(Debug, Clone)]
+pub struct TreeModel {
+    nodes: Vec<TreeNode>,
+}
+
+impl TreeModel {
+    fn from_document(doc: &Document) -> Result<Self, String> {
+        if doc.nodes.is_empty() {
+            return Err("tree model has no [[node]] entries".to_string());
+        }
+
+        let mut nodes = Vec::with_capacity(doc.nodes.len());
+        for (idx, table) in doc.nodes.iter().enumerate() {
+            let node = if let Ok(verdict) = table.text("verdict") {
+                TreeNode::Leaf(parse_verdict(verdict)?)
+            } else {
+                let feature = table.text("feature").map_err(|e| format!("node {}: {}", idx, e))?;
+                check_feature(feature)?;
+                let child = |key: &str| -> Result<usize, String> {
+                    let n = table.number(key).map_err(|e| format!("node {}: {}", idx, e))?;
+                    if n < 0.0 || n as usize >= doc.nodes.len() {
+                        return Err(format!("node {}: {} = {} out of range", idx, key, n));
+                    }
+                    Ok(n as usize)
+                };
+                TreeNode::Split {
+                    feature: feature.to_string(),
+                    threshold: table.number("threshold").map_err(|e| format!("node {}: {}", idx, e))?,
+                    left: child("left")?,
+                    right: child("right")?,
+                }
+            };
+            nodes.push(node);
+        }
+
+        Ok(Self { nodes })
+    }
+}
+
+impl Classifier for TreeModel {
+    fn name(&self) -> &str {
+        "tree"
+    }
+
+    fn classify(&self, features: &FeatureVector, _evidence: &[Evidence]) -> Verdict {
+        let mut idx = 0;
+        for _ in 0..MAX_TREE_DEPTH {
+            match &self.nodes[idx] {
+                TreeNode::Leaf(verdict) => return *verdict,
+                TreeNode::Split { feature, threshold, left, right } => {
+                    idx = if input(features, feature) < *threshold { *left } else { *right };
+                }
+            }
+        }
+        // Cyclic tree - refuse to guess
+        diag!("[MODEL] Tree exceeded depth {}, treating as Suspicious", MAX_TREE_DEPTH);
+        Verdict::Suspicious
+    }
+}
+
+/// Parse a model from its file contents
+pub fn parse_model(text: &str) -> Result<Box<dyn Classifier>, String> {
+    let doc = parse_document(text)?;
+    match doc.root.text("type")? {
+        "logistic" => Ok(Box::new(LogisticModel::from_document(&doc)?)),
+        "tree" => Ok(Box::new(TreeModel::from_document(&doc)?)),
+        other => Err(format!("unknown model type '{}'", other)),
+    }
+}
+
+/// Load a model file. Returns `None` (and logs why) if the file is missing
+/// or malformed, so the caller keeps the default threshold classifier.
+pub fn load_model(path: &Path) -> Option<Box<dyn Classifier>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(e) => {
+            diag!("[MODEL] Cannot read {}: {} - using thresholds", path.display(), e);
+            return None;
+        }
+    };
+    match parse_model(&text) {
+        Ok(model) => {
+            diag!("[MODEL] Loaded {} model from {}", model.name(), path.display());
+            Some(model)
+        }
+        Err(e) => {
+            diag!("[MODEL] Invalid model {}: {} - using thresholds", path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logistic_model() {
+        let model = parse_model(r#"
+            type = "logistic"
+            bias = -4.0
+            [weights]
+            tracerpid_nonzero = 8.0  # strong signal
+        "#).unwrap();
+
+        let mut fv = FeatureVector::new();
+        assert_eq!(model.classify(&fv, &[]), Verdict::Clean);
+        fv.set_flag("tracerpid_nonzero", true);
+        assert_eq!(model.classify(&fv, &[]), Verdict::Deceptive);
+    }
+
+    #[test]
+    fn test_tree_model() {
+        let model = parse_model(r#"
+            type = "tree"
+            [[node]]
+            feature = "hv_bit"
+            threshold = 0.5
+            left = 1
+            right = 2
+            [[node]]
+            verdict = "Clean"
+            [[node]]
+            verdict = "Suspicious"
+        "#).unwrap();
+
+        let mut fv = FeatureVector::new();
+        assert_eq!(model.classify(&fv, &[]), Verdict::Clean);
+        fv.set_flag("hv_bit", true);
+        assert_eq!(model.classify(&fv, &[]), Verdict::Suspicious);
+    }
+
+    #[test]
+    fn test_rejects_unknown_feature() {
+        let err = parse_model("type = \"logistic\"\n[weights]\nbogus = 1.0\n").err().unwrap();
+        assert!(err.contains("bogus"));
+    }
+}
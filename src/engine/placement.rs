@@ -0,0 +1,154 @@
+//! Measurement CPU Placement
+//!
+//! Timing detectors pin themselves to one CPU. Which CPU matters more than
+//! any statistical correction applied afterwards: a CPU shared with the rest
+//! of the system adds timer ticks, IRQs and sibling-thread contention to
+//! every sample.
+//!
+//! # Selection Order
+//!
+//! 1. A CPU reserved by the embedder with [`reserve_measurement_cpu`]
+//! 2. An isolated CPU (`isolcpus=` / `/sys/devices/system/cpu/isolated`,
+//!    or `nohz_full`) whose SMT siblings are isolated too
+//! 3. Any other isolated CPU
+//! 4. CPU 0 (the historical default), or the first CPU we may run on
+//!
+//! Only CPUs in our affinity mask are considered, so a container's
+//! `cpuset` is honoured automatically.
+//!
+//! When the chosen CPU has SMT siblings, the pinned thread also asks for a
+//! private core-scheduling cookie (`PR_SCHED_CORE`, Linux 5.14+) so the
+//! kernel does not co-schedule unrelated tasks on the sibling while we
+//! measure. Failure is ignored.
+
+use std::fs;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+const PR_SCHED_CORE: libc::c_int = 62;
+const PR_SCHED_CORE_CREATE: libc::c_ulong = 1;
+const PIDTYPE_PID: libc::c_ulong = 0;
+
+/// CPU reserved by the embedder, -1 if none
+static RESERVED_CPU: AtomicI64 = AtomicI64::new(-1);
+
+/// Parse a kernel CPU list ("0-3,8,10-11")
+pub fn parse_cpu_list(list: &str) -> Vec<usize> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((lo, hi)) => {
+                if let (Ok(lo), Ok(hi)) = (lo.parse::<usize>(), hi.parse::<usize>()) {
+                    cpus.extend(lo..=hi);
+                }
+            }
+            None => cpus.extend(part.parse::<usize>().ok()),
+        }
+    }
+    cpus
+}
+
+fn read_cpu_list(path: &str) -> Vec<usize> {
+    fs::read_to_string(path).map(|s| parse_cpu_list(&s)).unwrap_or_default()
+}
+
+/// CPUs the calling thread may run on
+pub fn allowed_cpus() -> Vec<usize> {
+    // SAFETY: sched_getaffinity fills the stack-owned set
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return Vec::new();
+        }
+        (0..libc::CPU_SETSIZE as usize).filter(|&cpu| libc::CPU_ISSET(cpu, &set)).collect()
+    }
+}
+
+/// CPUs isolated from general scheduling or the tick
+pub fn isolated_cpus() -> Vec<usize> {
+    let mut cpus = read_cpu_list("/sys/devices/system/cpu/isolated");
+    cpus.extend(read_cpu_list("/sys/devices/system/cpu/nohz_full"));
+    cpus.sort_unstable();
+    cpus.dedup();
+    cpus
+}
+
+fn smt_siblings(cpu: usize) -> Vec<usize> {
+    read_cpu_list(&format!("/sys/devices/system/cpu/cpu{}/topology/thread_siblings_list", cpu))
+        .into_iter()
+        .filter(|&c| c != cpu)
+        .collect()
+}
+
+/// Pick the measurement CPU from the candidates (see module docs).
+/// `siblings` maps a CPU to its SMT siblings.
+pub fn choose_cpu(reserved: Option<usize>, allowed: &[usize], isolated: &[usize], siblings: impl Fn(usize) -> Vec<usize>) -> Option<usize> {
+    if let Some(cpu) = reserved.filter(|c| allowed.contains(c)) {
+        return Some(cpu);
+    }
+    let usable: Vec<usize> = isolated.iter().copied().filter(|c| allowed.contains(c)).collect();
+    let whole_core = usable.iter().copied()
+        .find(|&cpu| siblings(cpu).iter().all(|s| isolated.contains(s)));
+    whole_core
+        .or_else(|| usable.first().copied())
+        .or_else(|| allowed.contains(&0).then_some(0))
+        .or_else(|| allowed.first().copied())
+}
+
+/// Reserve `cpu` for all later measurements. Fails if this thread may not
+/// run there.
+#[allow(dead_code)] // Public API for embedders
+pub fn reserve_measurement_cpu(cpu: usize) -> Result<(), String> {
+    if !allowed_cpus().contains(&cpu) {
+        return Err(format!("CPU {} is outside this process's affinity mask", cpu));
+    }
+    RESERVED_CPU.store(cpu as i64, Ordering::SeqCst);
+    Ok(())
+}
+
+/// CPU the timing detectors will pin to
+pub fn measurement_cpu() -> Option<usize> {
+    let reserved = usize::try_from(RESERVED_CPU.load(Ordering::SeqCst)).ok();
+    choose_cpu(reserved, &allowed_cpus(), &isolated_cpus(), smt_siblings)
+}
+
+/// Pin the calling thread to the measurement CPU. Returns the CPU, or
+/// `None` if pinning failed.
+pub fn pin_to_measurement_cpu() -> Option<usize> {
+    let cpu = measurement_cpu()?;
+    // SAFETY: plain libc calls on a stack-owned cpu_set_t
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return None;
+        }
+        if !smt_siblings(cpu).is_empty() {
+            // Keep unrelated tasks off the sibling thread while we measure
+            libc::prctl(PR_SCHED_CORE, PR_SCHED_CORE_CREATE, 0 as libc::c_ulong, PIDTYPE_PID, 0 as libc::c_ulong);
+        }
+    }
+    Some(cpu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(parse_cpu_list("0-3,8,10-11\n"), vec![0, 1, 2, 3, 8, 10, 11]);
+        assert_eq!(parse_cpu_list(""), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_choose_cpu() {
+        let allowed = [0, 1, 2, 3, 4, 5];
+        // 2/3 and 4/5 are sibling pairs; only 3, 4 and 5 are isolated
+        let siblings = |cpu: usize| vec![cpu ^ 1];
+        assert_eq!(choose_cpu(None, &allowed, &[3, 4, 5], siblings), Some(4));
+        assert_eq!(choose_cpu(None, &allowed, &[3], siblings), Some(3));
+        assert_eq!(choose_cpu(Some(1), &allowed, &[3, 4, 5], siblings), Some(1));
+        assert_eq!(choose_cpu(Some(9), &allowed, &[], siblings), Some(0));
+        assert_eq!(choose_cpu(None, &[6, 7], &[3], siblings), Some(6));
+    }
+}
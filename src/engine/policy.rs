@@ -1,3 +1,18 @@
+use std::time::Instant;
+use crate::engine::axes::VerdictReport;
+use crate::engine::baseline::Baseline;
+use crate::engine::bayes::{likelihood_ratios, Likelihoods, Posterior};
+use crate::engine::classifier::{Classifier, ThresholdClassifier};
+use crate::engine::decay::DecayPolicy;
+use crate::engine::explain::Explanation;
+use crate::engine::features::FeatureVector;
+use crate::engine::limits::SourceLimits;
+use crate::engine::rules::{builtin_rules, ContradictionRule};
+use crate::engine::sha256::sha256;
+use crate::engine::timeline::{self, DetectorRun, TimelineEntry};
+use crate::engine::tools::{identify, record_tool_features, ToolMatch};
+use crate::engine::posture::KernelPosture;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Verdict {
     Clean,
@@ -24,6 +39,108 @@ pub enum DetectionSource {
     RecordReplay,        // rr-class detection
     EbpfComparison,      // External vs internal observation mismatch
     Correlation,         // Cross-technique contradiction
+    
+    // Process-introspection sources
+    CrossView,           // In-process view disagrees with the kernel's /proc view
+    OutputCapture,       // stdout/stderr recorded by a capture tool
+    Instrumentation,     // Injected instrumentation agent (Frida/Gum) in our address space
+    Dbi,                 // Code runs from a DBI code cache (Pin, DynamoRIO)
+    
+    // Kernel-accounting sources
+    CpuAccounting,       // Kernel CPU-time accounting disagrees with wall clock
+    Emulation,           // Instruction cost implausible for real hardware (emulator/translator)
+    SyscallInterposition, // Syscalls routed through a tracer, seccomp notifier or sandbox kernel
+    SyscallFilter,       // Unusual syscalls refused by a seccomp filter or ptrace supervisor
+    SamplingProfiler,    // Performance-counter overflow interrupts sampling our CPU
+    KernelProbe,         // uprobes/kprobes placed on our code or the kernel paths we use
+    ExecutionGap,        // Wall clock jumped against monotonic time mid-run (paused, resumed, resynced)
+    
+    // Remote sources
+    RemoteTime,          // Local clocks disagree with an authenticated remote time server
+    RemoteDebug,         // A remote debug stub (gdbserver, lldb-server, IDA) is tracing us
+    
+    // Analysis-environment sources
+    Sandbox,             // Automated malware-analysis sandbox (agent process, fresh minimal VM)
+    Hypervisor,          // Running in a VM (scaled by its production/analysis class)
+    
+    // Code-integrity sources
+    Integrity,           // Control flow or code tampered with (hooks, rewritten return addresses)
+    
+    // Framework self-monitoring
+    DetectorFault,       // A detector panicked (possibly fed impossible values)
+}
+
+impl DetectionSource {
+    pub const ALL: [DetectionSource; 26] = [
+        DetectionSource::Timing, DetectionSource::Int3, DetectionSource::TrapFlag, DetectionSource::Ptrace,
+        DetectionSource::HardwareBreakpoint, DetectionSource::Jitter, DetectionSource::RecordReplay,
+        DetectionSource::EbpfComparison, DetectionSource::Correlation, DetectionSource::CrossView,
+        DetectionSource::OutputCapture, DetectionSource::Instrumentation, DetectionSource::Dbi,
+        DetectionSource::CpuAccounting, DetectionSource::Emulation, DetectionSource::SyscallInterposition,
+        DetectionSource::SyscallFilter, DetectionSource::SamplingProfiler, DetectionSource::KernelProbe,
+        DetectionSource::ExecutionGap, DetectionSource::RemoteTime, DetectionSource::RemoteDebug,
+        DetectionSource::Sandbox, DetectionSource::Hypervisor, DetectionSource::Integrity,
+        DetectionSource::DetectorFault,
+    ];
+
+    /// Source by its variant name, case-insensitively (`jitter`, `Ptrace`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| format!("{:?}", s).eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Sources whose evidence comes from latency measurements, and so
+    /// degrades with a noisy or throttled host
+    pub fn is_timing_based(self) -> bool {
+        matches!(self, DetectionSource::Timing | DetectionSource::Jitter | DetectionSource::CpuAccounting)
+    }
+}
+
+/// What kind of process is tracing us, as identified by `tracer_kind.rs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TracerKind {
+    /// Nothing identified (no tracer, or not checked yet)
+    None,
+    /// strace/ltrace-style: records syscalls or library calls, never stops
+    /// to let a human inspect or change state
+    PassiveTracer,
+    /// gdb/lldb-style: breakpoints, single-stepping, memory edits
+    InteractiveDebugger,
+    /// A tracer we could not classify
+    Unknown,
+}
+
+impl TracerKind {
+    /// Multiplier for later `Ptrace` evidence. A passive tracer is a real
+    /// observer, but it cannot patch checks or step past them.
+    pub fn ptrace_scale(self) -> f64 {
+        match self {
+            TracerKind::PassiveTracer => 0.5,
+            TracerKind::None | TracerKind::InteractiveDebugger | TracerKind::Unknown => 1.0,
+        }
+    }
+}
+
+/// What kind of virtual machine we run in, as classified by `hypervisor.rs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VmClass {
+    /// Bare metal, or not checked yet
+    None,
+    /// A cloud or server hypervisor (KVM, Hyper-V, ESXi, Xen) running as
+    /// advertised
+    Production,
+    /// A desktop or emulated VM, or one that hides or fakes its identity
+    Analysis,
+}
+
+impl VmClass {
+    /// Multiplier for `Hypervisor` evidence. Most production deployments
+    /// are virtualized; that alone says little about an analyst.
+    pub fn hypervisor_scale(self) -> f64 {
+        match self {
+            VmClass::Production => 0.25,
+            VmClass::None | VmClass::Analysis => 1.0,
+        }
+    }
 }
 
 /// Evidence record with confidence level
@@ -31,9 +148,45 @@ pub enum DetectionSource {
 #[allow(dead_code)] // Fields stored for correlation analysis and logging
 pub struct Evidence {
     pub source: DetectionSource,
+    /// Weight counted after confidence scaling and caps
     pub weight: u32,
+    /// Weight the detector reported
+    pub reported: u32,
     pub confidence: f64,  // 0.0 - 1.0
     pub details: String,
+    /// Registered thread the evidence was gathered on, `None` = whole process
+    pub thread: Option<String>,
+    /// Reported after the scan closed (see `DecisionEngine::close_scan`)
+    pub late: bool,
+    /// Likelihood ratio of each hypothesis against a clean environment
+    /// (Bayesian scoring, see `bayes.rs`)
+    pub likelihoods: Likelihoods,
+    /// Later reports collapsed into this one (see `limits.rs`)
+    pub repeats: u32,
+    /// When it was first reported
+    pub at: Instant,
+    /// When it was last reported (itself or a collapsed repeat); decay
+    /// counts from here
+    pub seen: Instant,
+    /// Report order, from 1 (see `timeline.rs`)
+    pub seq: u64,
+    /// Detector that was running when it was reported, `None` outside any
+    pub detector: Option<String>,
+}
+
+/// Internal failure recorded for operators. Unlike evidence, a diagnostic
+/// describes the framework, not the environment.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub component: String,
+    pub message: String,
+}
+
+/// Raw timing samples kept for reports (histograms)
+#[derive(Debug, Clone)]
+pub struct SampleSet {
+    pub name: String,
+    pub values: Vec<u64>,
 }
 
 /// Contradiction type for deception detection
@@ -42,6 +195,10 @@ pub struct Contradiction {
     pub source_a: DetectionSource,
     pub source_b: DetectionSource,
     pub description: String,
+    /// Score it added
+    pub weight: u32,
+    /// When it was recorded; decay counts from here
+    pub at: Instant,
 }
 
 pub struct DecisionEngine {
@@ -50,6 +207,50 @@ pub struct DecisionEngine {
     contradictions: Vec<Contradiction>,
     /// Per-source aggregated weight (for correlation analysis)
     source_weights: std::collections::HashMap<DetectionSource, u32>,
+    /// Raw detector metrics for offline classifier training
+    features: FeatureVector,
+    /// Last environmental adjustment applied (1.0 = none)
+    adjustment_factor: f64,
+    /// Decision rule used by `decide()`
+    classifier: Box<dyn Classifier>,
+    /// Detector failures (panics) for operators
+    diagnostics: Vec<Diagnostic>,
+    /// Confidence multiplier applied to timing-based sources
+    timing_confidence: f64,
+    /// Multiplier timing detectors apply to their thresholds
+    timing_tolerance: f64,
+    /// Multiplier syscall-latency detectors apply to their baselines
+    syscall_tolerance: f64,
+    /// Calibrated per-machine metrics replacing universal thresholds
+    baseline: Option<Baseline>,
+    /// Raw sample distributions for the HTML report
+    samples: Vec<SampleSet>,
+    /// Identified tracer type, scales later `Ptrace` evidence
+    tracer_kind: TracerKind,
+    /// Classified virtual machine, scales `Hypervisor` evidence
+    vm_class: VmClass,
+    /// Kernel hardening, scales evidence of mechanisms it refuses
+    kernel_posture: KernelPosture,
+    /// Set once the upfront scan is over; later evidence is late
+    scan_closed: bool,
+    /// Part of `score` contributed by late evidence
+    late_weight: u32,
+    /// Per-source caps and duplicate collapsing
+    source_limits: SourceLimits,
+    /// Weight reported but dropped by a source cap
+    capped_weight: u32,
+    /// Weight loss of old evidence in monitoring mode
+    decay: DecayPolicy,
+    /// Patterns `analyze_contradictions()` looks for
+    contradiction_rules: Vec<ContradictionRule>,
+    /// Timeline offsets count from here
+    origin: Instant,
+    /// Sequence number of the last evidence
+    last_seq: u64,
+    /// Completed detector runs, in order
+    detector_runs: Vec<DetectorRun>,
+    /// Detector running now and when it started
+    running: Option<(String, Instant)>,
 }
 
 impl DecisionEngine {
@@ -59,6 +260,28 @@ impl DecisionEngine {
             history: Vec::new(),
             contradictions: Vec::new(),
             source_weights: std::collections::HashMap::new(),
+            features: FeatureVector::new(),
+            adjustment_factor: 1.0,
+            classifier: Box::new(ThresholdClassifier::default()),
+            diagnostics: Vec::new(),
+            timing_confidence: 1.0,
+            timing_tolerance: 1.0,
+            syscall_tolerance: 1.0,
+            baseline: None,
+            samples: Vec::new(),
+            tracer_kind: TracerKind::None,
+            vm_class: VmClass::None,
+            kernel_posture: KernelPosture::default(),
+            scan_closed: false,
+            late_weight: 0,
+            source_limits: SourceLimits::default(),
+            capped_weight: 0,
+            decay: DecayPolicy::default(),
+            contradiction_rules: builtin_rules(),
+            origin: Instant::now(),
+            last_seq: 0,
+            detector_runs: Vec::new(),
+            running: None,
         }
     }
 
@@ -72,95 +295,380 @@ impl DecisionEngine {
     /// Report with explicit confidence level.
     /// Confidence: 1.0 = certain, 0.5 = uncertain, 0.0 = noise
     pub fn report_with_confidence(&mut self, source: DetectionSource, weight: u32, confidence: f64, details: &str) {
+        self.push_evidence(source, weight, confidence, details, None);
+    }
+    
+    /// Report evidence gathered on one registered thread (see `threads.rs`)
+    pub fn report_for_thread(&mut self, thread: &str, source: DetectionSource, weight: u32, confidence: f64, details: &str) {
+        self.push_evidence(source, weight, confidence, details, Some(thread));
+    }
+    
+    fn push_evidence(&mut self, source: DetectionSource, weight: u32, confidence: f64, details: &str, thread: Option<&str>) {
+        if let Some(i) = self.source_limits.duplicate_of(&self.history, source, thread, details) {
+            self.history[i].repeats += 1;
+            self.history[i].seen = Instant::now();
+            diag!("[ENGINE] {:?} | repeated ({}x), no weight | {}", source, self.history[i].repeats + 1, details);
+            return;
+        }
+        let confidence = if source.is_timing_based() { confidence * self.timing_confidence } else { confidence };
+        let confidence = if source == DetectionSource::Ptrace { confidence * self.tracer_kind.ptrace_scale() } else { confidence };
+        let confidence = if source == DetectionSource::Hypervisor { confidence * self.vm_class.hypervisor_scale() } else { confidence };
+        let confidence = confidence * self.kernel_posture.confidence_scale(source);
         let adjusted_weight = (weight as f64 * confidence) as u32;
+        // The scan's score has already been adjusted for the environment
+        let adjusted_weight = if self.scan_closed { (adjusted_weight as f64 * self.adjustment_factor) as u32 } else { adjusted_weight };
+        let total = self.source_weights.get(&source).copied().unwrap_or(0);
+        let counted = self.source_limits.admit(source, total, adjusted_weight);
+        if counted < adjusted_weight {
+            diag!("[ENGINE] {:?} capped at {:?}: {} of {} counted", source, self.source_limits.cap(source), counted, adjusted_weight);
+            self.capped_weight = self.capped_weight.saturating_add(adjusted_weight - counted);
+        }
+        let adjusted_weight = counted;
         self.score = self.score.saturating_add(adjusted_weight);
+        if self.scan_closed {
+            self.late_weight = self.late_weight.saturating_add(adjusted_weight);
+        }
         
         // Track per-source totals for correlation
         *self.source_weights.entry(source).or_insert(0) += adjusted_weight;
         
+        self.last_seq += 1;
         self.history.push(Evidence {
             source,
             weight: adjusted_weight,
+            reported: weight,
             confidence,
             details: details.to_string(),
+            thread: thread.map(str::to_string),
+            late: self.scan_closed,
+            likelihoods: likelihood_ratios(source, adjusted_weight),
+            repeats: 0,
+            at: Instant::now(),
+            seen: Instant::now(),
+            seq: self.last_seq,
+            detector: self.running.as_ref().map(|(name, _)| name.clone()),
         });
         
         // In a real scenario, this log might be obfuscated or omitted.
-        eprintln!("[ENGINE] {:?} | Weight: {} (conf: {:.2}) | {}", source, adjusted_weight, confidence, details);
+        diag!("[ENGINE] {:?} | Weight: {} (conf: {:.2}) | {}{}{}", source, adjusted_weight, confidence,
+              if self.scan_closed { "[late] " } else { "" },
+              thread.map_or(String::new(), |t| format!("[{}] ", t)), details);
+    }
+    
+    /// Attribute later evidence to detector `name` until `end_detector()`
+    pub fn begin_detector(&mut self, name: &str) {
+        self.running = Some((name.to_string(), Instant::now()));
+    }
+    
+    /// Record the running detector's runtime
+    pub fn end_detector(&mut self) {
+        if let Some((name, started)) = self.running.take() {
+            self.detector_runs.push(DetectorRun { name, started, runtime: started.elapsed() });
+        }
+    }
+    
+    /// Completed detector runs, in order
+    pub fn detector_runs(&self) -> &[DetectorRun] {
+        &self.detector_runs
+    }
+    
+    /// Detector runs overlapping `from..to`, e.g. an observed execution gap
+    pub fn detectors_during(&self, from: Instant, to: Instant) -> impl Iterator<Item = &DetectorRun> {
+        self.detector_runs.iter().filter(move |r| r.overlaps(from, to))
+    }
+    
+    /// Detector runs and evidence in the order they happened
+    pub fn timeline(&self) -> Vec<TimelineEntry<'_>> {
+        timeline::build(self.origin, &self.detector_runs, &self.history)
+    }
+    
+    /// End the upfront scan. Evidence reported afterwards (background
+    /// monitors, re-verification) is still accepted and counts towards
+    /// `decide()`, but is marked late, scaled by the environmental
+    /// adjustment already applied to the scan, and left out of `digest()`.
+    pub fn close_scan(&mut self) {
+        self.scan_closed = true;
+    }
+    
+    /// Evidence reported after `close_scan()`
+    pub fn late_evidence(&self) -> impl Iterator<Item = &Evidence> {
+        self.history.iter().filter(|e| e.late)
+    }
+    
+    /// Scale the confidence of all later timing-based evidence (e.g. 0.5 on a
+    /// throttled container). Must be set before the detectors run.
+    pub fn set_timing_confidence(&mut self, scale: f64) {
+        self.timing_confidence = scale.clamp(0.0, 1.0);
+    }
+    
+    /// Widen timing detectors' latency/CV thresholds by `factor` (>= 1.0)
+    /// for power-saving hosts. Must be set before the detectors run.
+    pub fn set_timing_tolerance(&mut self, factor: f64) {
+        self.timing_tolerance = factor.max(1.0);
+    }
+    
+    /// Threshold multiplier for timing detectors (1.0 = nominal)
+    pub fn timing_tolerance(&self) -> f64 {
+        self.timing_tolerance
+    }
+    
+    /// Record what kind of tracer is attached. Scales all later `Ptrace`
+    /// evidence by [`TracerKind::ptrace_scale`].
+    pub fn set_tracer_kind(&mut self, kind: TracerKind) {
+        self.tracer_kind = kind;
+    }
+    
+    /// Cap per-source weight totals and collapse repeated evidence for all
+    /// later reports (see `limits.rs`)
+    pub fn set_source_limits(&mut self, limits: SourceLimits) {
+        self.source_limits = limits;
+    }
+    
+    /// Let evidence lose weight with age once the scan is closed (see
+    /// `decay.rs`)
+    pub fn set_decay_policy(&mut self, decay: DecayPolicy) {
+        self.decay = decay;
+    }
+    
+    /// Record what kind of VM we run in. Scales all later `Hypervisor`
+    /// evidence by [`VmClass::hypervisor_scale`].
+    pub fn set_vm_class(&mut self, class: VmClass) {
+        self.vm_class = class;
+    }
+    
+    /// Record the kernel's hardening posture. Scales all later evidence by
+    /// [`KernelPosture::confidence_scale`].
+    pub fn set_kernel_posture(&mut self, posture: KernelPosture) {
+        self.kernel_posture = posture;
+    }
+    
+    /// Hardening posture of the running kernel, for detectors weighing what
+    /// an observer could have done
+    pub fn kernel_posture(&self) -> &KernelPosture {
+        &self.kernel_posture
+    }
+    
+    /// Widen syscall-latency baselines by `factor` (>= 1.0) for kernels whose
+    /// entry path is legitimately slow (KPTI, PREEMPT_RT, nohz_full)
+    pub fn set_syscall_tolerance(&mut self, factor: f64) {
+        self.syscall_tolerance = factor.max(1.0);
+    }
+    
+    /// Baseline multiplier for syscall-latency detectors (1.0 = nominal)
+    pub fn syscall_tolerance(&self) -> f64 {
+        self.syscall_tolerance
+    }
+    
+    /// Compare timing detectors against this machine's calibrated metrics
+    /// (see `baseline.rs`). Must be set before the detectors run.
+    pub fn set_baseline(&mut self, baseline: Baseline) {
+        self.baseline = Some(baseline);
+    }
+    
+    /// Calibrated baseline, if one was loaded
+    pub fn baseline(&self) -> Option<&Baseline> {
+        self.baseline.as_ref()
+    }
+    
+    /// Threshold `multiple` times the calibrated upper band of feature
+    /// `column`; `None` if the metric is uncalibrated and the detector's
+    /// universal threshold applies
+    pub fn baseline_limit(&self, column: &str, multiple: f64) -> Option<f64> {
+        self.baseline.as_ref()?.limit(column, multiple)
+    }
+    
+    /// The baseline with this run's metrics folded in (see `baseline.rs`),
+    /// or `None` if no baseline is loaded, the run is not `Clean`, or no
+    /// calibrated metric was observed
+    pub fn adapted_baseline(&self, alpha: f64) -> Option<Baseline> {
+        let mut baseline = self.baseline.clone()?;
+        if self.decide() != Verdict::Clean || baseline.adapt(&self.feature_vector(), alpha) == 0 {
+            return None;
+        }
+        Some(baseline)
+    }
+    
+    /// Record a raw detector metric for the exported feature vector.
+    /// Metrics carry no weight; they describe what was measured, not a verdict.
+    pub fn record_feature(&mut self, name: &str, value: f64) {
+        self.features.set(name, value);
+    }
+    
+    /// Record a boolean detector metric (exported as 0.0 / 1.0)
+    pub fn record_flag(&mut self, name: &str, value: bool) {
+        self.features.set_flag(name, value);
+    }
+    
+    /// Keep a raw sample distribution (cycles) for reporting
+    pub fn record_samples(&mut self, name: &str, values: &[u64]) {
+        self.samples.push(SampleSet { name: name.to_string(), values: values.to_vec() });
+    }
+    
+    /// Record an internal failure of `component`
+    pub fn record_diagnostic(&mut self, component: &str, message: &str) {
+        diag!("[ENGINE] DIAGNOSTIC: {} - {}", component, message);
+        self.diagnostics.push(Diagnostic {
+            component: component.to_string(),
+            message: message.to_string(),
+        });
+    }
+    
+    /// Feature vector for this run, with engine-level columns filled in
+    pub fn feature_vector(&self) -> FeatureVector {
+        let mut fv = self.features.clone();
+        fv.set("contradictions", self.contradictions.len() as f64);
+        fv.set("env_adjustment_factor", self.adjustment_factor);
+        fv.set("score", self.score as f64);
+        fv.set("capped_weight", self.capped_weight as f64);
+        fv.set("collapsed_duplicates", self.history.iter().map(|e| e.repeats).sum::<u32>() as f64);
+        record_tool_features(&mut fv, &self.history, self.tracer_kind);
+        fv
     }
     
     /// Record a contradiction between two detection sources.
     /// Example: DRx clean but timing shows single-step behavior
     pub fn record_contradiction(&mut self, source_a: DetectionSource, source_b: DetectionSource, description: &str) {
-        eprintln!("[ENGINE] CONTRADICTION: {:?} vs {:?} - {}", source_a, source_b, description);
+        // Contradictions heavily suggest environment deception
+        self.record_weighted_contradiction(source_a, source_b, 30, description);
+    }
+    
+    /// Record a contradiction that adds `weight` to the score
+    pub fn record_weighted_contradiction(&mut self, source_a: DetectionSource, source_b: DetectionSource, weight: u32, description: &str) {
+        diag!("[ENGINE] CONTRADICTION: {:?} vs {:?} (+{}) - {}", source_a, source_b, weight, description);
         self.contradictions.push(Contradiction {
             source_a,
             source_b,
             description: description.to_string(),
+            weight,
+            at: Instant::now(),
         });
-        
-        // Contradictions heavily suggest environment deception
-        self.score = self.score.saturating_add(30);
+        self.score = self.score.saturating_add(weight);
+    }
+    
+    /// Look for contradictions in addition to the built-in rules (see
+    /// `rules.rs`)
+    pub fn add_contradiction_rules(&mut self, rules: Vec<ContradictionRule>) {
+        self.contradiction_rules.extend(rules);
     }
     
     /// Check for contradictions between sources.
     /// Called after all detectors have run.
     pub fn analyze_contradictions(&mut self) {
-        let has_timing = self.has_detection(DetectionSource::Timing) || self.has_detection(DetectionSource::Jitter);
-        let has_hw_bp = self.has_detection(DetectionSource::HardwareBreakpoint);
-        let has_ptrace = self.has_detection(DetectionSource::Ptrace);
-        
-        // Contradiction: Timing shows single-step but no hardware BP detected
-        // This suggests software single-stepping (GDB step command) which should trigger ptrace
-        if has_timing && !has_hw_bp && !has_ptrace {
-            // Only flag if timing weight is significant
-            let timing_weight = self.get_source_weight(DetectionSource::Timing) + 
-                               self.get_source_weight(DetectionSource::Jitter);
-            if timing_weight > 40 {
-                self.record_contradiction(
-                    DetectionSource::Timing,
-                    DetectionSource::Ptrace,
-                    "Heavy timing anomaly but no tracer detected - possible ptrace hiding"
-                );
-            }
+        let fired: Vec<ContradictionRule> = self.contradiction_rules.iter()
+            .filter(|rule| rule.matches(|source| self.get_source_weight(source), &self.features))
+            .cloned()
+            .collect();
+        for rule in fired {
+            self.record_weighted_contradiction(rule.source_a, rule.source_b, rule.weight, &rule.description);
         }
-        
-        // Contradiction: Ptrace detected but timing completely clean
-        // Suggests the tracer is not actually instrumenting (strace without single-step)
-        // This is actually expected for strace, so we don't flag it unless other evidence exists
     }
 
-    fn has_detection(&self, source: DetectionSource) -> bool {
-        self.history.iter().any(|e| e.source == source && e.weight > 0)
-    }
-    
     fn get_source_weight(&self, source: DetectionSource) -> u32 {
         *self.source_weights.get(&source).unwrap_or(&0)
     }
 
+    /// Replace the decision rule used by `decide()`.
+    /// The default is `ThresholdClassifier`.
+    pub fn set_classifier(&mut self, classifier: Box<dyn Classifier>) {
+        self.classifier = classifier;
+    }
+    
+    pub fn classifier_name(&self) -> &str {
+        self.classifier.name()
+    }
+
     /// Calculate the verdict based on accumulated evidence.
     /// 
-    /// Thresholds (updated for Phase 2):
+    /// Delegates to the configured classifier. The default thresholds
+    /// (updated for Phase 2):
     /// - 0-19: Clean
     /// - 20-49: Suspicious (e.g., slight timing jitter, VM detected)
     /// - 50-89: Instrumented (e.g., ptrace detected, significant evidence)
     /// - 90+ OR contradictions: Deceptive (environment is lying)
+    ///
+    /// In monitoring mode with a decay policy the verdict is over the
+    /// decayed score and evidence.
     pub fn decide(&self) -> Verdict {
-        // Contradictions indicate active deception
-        if !self.contradictions.is_empty() {
-            return Verdict::Deceptive;
+        self.decide_at(Instant::now())
+    }
+
+    /// Verdict as of `now` (which only matters when evidence decays)
+    pub fn decide_at(&self, now: Instant) -> Verdict {
+        match self.decayed_at(now) {
+            Some((fv, history, _)) => self.classifier.classify(&fv, &history),
+            None => self.classifier.classify(&self.feature_vector(), &self.history),
         }
-        
-        if self.score >= 90 {
-            // Overwhelming evidence OR multiple strong techniques
-            Verdict::Deceptive
-        } else if self.score >= 50 {
-            Verdict::Instrumented
-        } else if self.score >= 20 {
-            Verdict::Suspicious
-        } else {
-            Verdict::Clean
+    }
+
+    /// Posterior over the environment hypotheses, if the classifier
+    /// computes one (Bayesian scoring)
+    pub fn posterior(&self) -> Option<Posterior> {
+        match self.decayed_at(Instant::now()) {
+            Some((fv, history, _)) => self.classifier.posterior(&fv, &history),
+            None => self.classifier.posterior(&self.feature_vector(), &self.history),
+        }
+    }
+
+    /// Why the verdict is what it is: per-evidence contributions, the
+    /// classifier's thresholds and the items it hinges on (see
+    /// `explain.rs`)
+    pub fn explain(&self) -> Explanation {
+        match self.decayed_at(Instant::now()) {
+            Some((fv, history, contradictions)) => Explanation::build(self.classifier.as_ref(), &fv, &history, &contradictions, self.adjustment_factor),
+            None => Explanation::build(self.classifier.as_ref(), &self.feature_vector(), &self.history, &self.contradictions, self.adjustment_factor),
+        }
+    }
+
+    /// Overall verdict plus a classification per threat axis (debugger,
+    /// VM, emulator, DBI, deception; see `axes.rs`)
+    pub fn verdict_report(&self) -> VerdictReport {
+        let now = Instant::now();
+        let decayed = self.decayed_at(now);
+        let history = decayed.as_ref().map_or(&self.history[..], |(_, history, _)| &history[..]);
+        let contradictions = decayed.as_ref().map_or(&self.contradictions[..], |(_, _, contradictions)| &contradictions[..]);
+        VerdictReport::build(self.decide_at(now), history, contradictions,
+                             self.tracer_kind != TracerKind::None, self.vm_class != VmClass::None)
+    }
+
+    /// Tools whose fingerprint the evidence matches, most likely first
+    /// (see `tools.rs`)
+    pub fn identify_tools(&self) -> Vec<ToolMatch> {
+        identify(&self.history, &self.features, self.tracer_kind)
+    }
+
+    /// Score as of `now`: the lifetime score less what evidence lost to
+    /// decay
+    pub fn score_at(&self, now: Instant) -> u32 {
+        self.decayed_at(now).map_or(self.score, |(fv, _, _)| fv.get("score").unwrap_or(0.0) as u32)
+    }
+
+    /// Feature vector, evidence and contradictions with decayed weights, or
+    /// `None` while nothing decays (no policy, or the scan is still open).
+    /// Contradictions decayed to nothing are left out.
+    fn decayed_at(&self, now: Instant) -> Option<(FeatureVector, Vec<Evidence>, Vec<Contradiction>)> {
+        if !self.decay.is_active() || !self.scan_closed {
+            return None;
         }
+        let mut lost = 0.0;
+        let history: Vec<Evidence> = self.history.iter()
+            .map(|e| {
+                let weight = (e.weight as f64 * self.decay.factor(now.saturating_duration_since(e.seen))).round() as u32;
+                // Scan evidence counts in the score scaled by the adjustment
+                lost += (e.weight - weight) as f64 * if e.late { 1.0 } else { self.adjustment_factor };
+                Evidence { weight, likelihoods: likelihood_ratios(e.source, weight), ..e.clone() }
+            })
+            .collect();
+        let contradictions: Vec<Contradiction> = self.contradictions.iter()
+            .filter_map(|c| {
+                let weight = (c.weight as f64 * self.decay.factor(now.saturating_duration_since(c.at))).round() as u32;
+                lost += (c.weight - weight) as f64 * self.adjustment_factor;
+                (weight > 0).then(|| Contradiction { weight, ..c.clone() })
+            })
+            .collect();
+        let mut fv = self.feature_vector();
+        fv.set("score", (self.score as f64 - lost).round().max(0.0));
+        fv.set("contradictions", contradictions.len() as f64);
+        Some((fv, history, contradictions))
     }
 
     pub fn get_score(&self) -> u32 {
@@ -173,7 +681,8 @@ impl DecisionEngine {
         if factor < 1.0 && factor > 0.0 {
             let original = self.score;
             self.score = (self.score as f64 * factor) as u32;
-            eprintln!("[ENGINE] Environmental adjustment: {} -> {} (factor: {:.2})", 
+            self.adjustment_factor = factor;
+            diag!("[ENGINE] Environmental adjustment: {} -> {} (factor: {:.2})", 
                 original, self.score, factor);
         }
     }
@@ -188,17 +697,98 @@ impl DecisionEngine {
         &self.contradictions
     }
     
+    #[allow(dead_code)] // Public API for external callers
+    pub fn get_diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+    
+    pub fn get_samples(&self) -> &[SampleSet] {
+        &self.samples
+    }
+    
+    /// Digest of everything the scan's verdict depends on (score, evidence,
+    /// contradictions). Used to detect tampering between decision and use;
+    /// late evidence only ever adds to the score and is not covered.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut state = format!("{}|{}|{}", self.score.saturating_sub(self.late_weight), self.adjustment_factor, self.classifier_name());
+        for e in self.history.iter().filter(|e| !e.late) {
+            state.push_str(&format!("|{:?}:{}:{}:{:?}:{}", e.source, e.weight, e.confidence, e.thread, e.details));
+        }
+        for c in &self.contradictions {
+            state.push_str(&format!("|{:?}/{:?}:{}", c.source_a, c.source_b, c.description));
+        }
+        sha256(&[state.as_bytes()])
+    }
+    
+    /// Per-source weight totals, heaviest first
+    pub fn source_weights(&self) -> Vec<(DetectionSource, u32)> {
+        let mut weights: Vec<_> = self.source_weights.iter().map(|(s, w)| (*s, *w)).collect();
+        weights.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| format!("{:?}", a.0).cmp(&format!("{:?}", b.0))));
+        weights
+    }
+    
     /// Returns a summary suitable for logging
     pub fn summary(&self) -> String {
-        let mut s = format!("Score: {} | Verdict: {:?}\n", self.score, self.decide());
+        let mut s = format!("Score: {} | Verdict: {:?} | Classifier: {}\n",
+            self.score, self.decide(), self.classifier_name());
+        if let Some(half_life) = self.decay.half_life.filter(|_| self.scan_closed) {
+            s.push_str(&format!("Decayed score: {} (half-life {:.0?})\n", self.score_at(Instant::now()), half_life));
+        }
+        s.push_str(&format!("Axes: {}\n", self.verdict_report()));
+        if let Some(posterior) = self.posterior() {
+            let (leading, p) = posterior.leading();
+            s.push_str(&format!("Posterior: {} | Most likely non-clean: {:?} ({:.1}%)\n", posterior, leading, p * 100.0));
+        }
+        let tools = self.identify_tools();
+        if !tools.is_empty() {
+            let ranked: Vec<String> = tools.iter().map(|t| format!("{} {:.0}%", t.tool, t.confidence * 100.0)).collect();
+            s.push_str(&format!("Likely tools: {}\n", ranked.join(", ")));
+        }
+        if self.tracer_kind != TracerKind::None {
+            s.push_str(&format!("Tracer: {:?}\n", self.tracer_kind));
+        }
+        if self.vm_class != VmClass::None {
+            s.push_str(&format!("VM: {:?}\n", self.vm_class));
+        }
+        if let Some(slowest) = self.detector_runs().iter().max_by_key(|r| r.runtime) {
+            let total: std::time::Duration = self.detector_runs.iter().map(|r| r.runtime).sum();
+            s.push_str(&format!("Scan: {} detector run(s) in {:.1} ms, slowest {} ({:.1} ms)\n",
+                self.detector_runs.len(), total.as_secs_f64() * 1e3, slowest.name, slowest.runtime.as_secs_f64() * 1e3));
+        }
+        if let Some(baseline) = self.baseline() {
+            s.push_str(&format!("Baseline: {} calibrated metric(s) over {} run(s)\n", baseline.len(), baseline.runs));
+        }
+        if self.capped_weight > 0 {
+            s.push_str(&format!("Capped: {} weight over per-source caps\n", self.capped_weight));
+        }
         s.push_str("Evidence by source:\n");
         for (source, weight) in &self.source_weights {
             s.push_str(&format!("  {:?}: {}\n", source, weight));
         }
+        let threaded: Vec<&Evidence> = self.history.iter().filter(|e| e.thread.is_some()).collect();
+        if !threaded.is_empty() {
+            s.push_str("Evidence by thread:\n");
+            for e in threaded {
+                s.push_str(&format!("  {}: {:?} {} - {}\n", e.thread.as_deref().unwrap_or_default(), e.source, e.weight, e.details));
+            }
+        }
+        let late: Vec<&Evidence> = self.late_evidence().collect();
+        if !late.is_empty() {
+            s.push_str("Late evidence (after the scan):\n");
+            for e in late {
+                s.push_str(&format!("  {:?} {} - {}\n", e.source, e.weight, e.details));
+            }
+        }
         if !self.contradictions.is_empty() {
             s.push_str("Contradictions:\n");
             for c in &self.contradictions {
-                s.push_str(&format!("  {:?} vs {:?}: {}\n", c.source_a, c.source_b, c.description));
+                s.push_str(&format!("  {:?} vs {:?} (+{}): {}\n", c.source_a, c.source_b, c.weight, c.description));
+            }
+        }
+        if !self.diagnostics.is_empty() {
+            s.push_str("Diagnostics:\n");
+            for d in &self.diagnostics {
+                s.push_str(&format!("  {}: {}\n", d.component, d.message));
             }
         }
         s
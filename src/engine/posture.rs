@@ -0,0 +1,122 @@
+//! Kernel Hardening Posture
+//!
+//! The same evidence means different things on differently hardened
+//! kernels. A tracer that attached to us is more telling where Yama only
+//! lets ancestors attach, and kernel-side instrumentation is hard to
+//! explain where lockdown forbids it:
+//!
+//! | Setting                            | What it restricts                                   |
+//! |------------------------------------|-----------------------------------------------------|
+//! | `kernel.yama.ptrace_scope`         | 1: only ancestors attach; 2: admin only; 3: nobody  |
+//! | `/sys/kernel/security/lockdown`    | confidentiality: no kprobes, BPF, perf kernel reads |
+//! | `kernel.kptr_restrict`             | 1-2: kernel addresses hidden in /proc               |
+//! | `kernel.perf_event_paranoid`       | 2: no kernel profiling; 3 (Debian): no perf at all  |
+//!
+//! Detectors read it through [`DecisionEngine::kernel_posture`]; the engine
+//! scales evidence the posture says should be impossible by
+//! [`KernelPosture::confidence_scale`].
+//!
+//! [`DecisionEngine::kernel_posture`]: crate::engine::policy::DecisionEngine::kernel_posture
+
+use std::fs;
+use crate::engine::policy::DetectionSource;
+
+/// Kernel lockdown level (`/sys/kernel/security/lockdown`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lockdown {
+    None,
+    /// Blocks modifying the running kernel (modules, /dev/mem, kexec)
+    Integrity,
+    /// Also blocks reading kernel memory (kprobes, BPF, perf kernel events)
+    Confidentiality,
+}
+
+/// Hardening sysctls and LSM state, `None` where unreadable
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KernelPosture {
+    pub ptrace_scope: Option<u8>,
+    pub lockdown: Option<Lockdown>,
+    pub kptr_restrict: Option<u8>,
+    pub perf_event_paranoid: Option<i32>,
+}
+
+impl KernelPosture {
+    /// Read every available source
+    pub fn detect() -> Self {
+        let read = |path: &str| fs::read_to_string(path).ok();
+        Self {
+            ptrace_scope: read("/proc/sys/kernel/yama/ptrace_scope").and_then(|s| s.trim().parse().ok()),
+            lockdown: read("/sys/kernel/security/lockdown").and_then(|s| parse_lockdown(&s)),
+            kptr_restrict: read("/proc/sys/kernel/kptr_restrict").and_then(|s| s.trim().parse().ok()),
+            perf_event_paranoid: read("/proc/sys/kernel/perf_event_paranoid").and_then(|s| s.trim().parse().ok()),
+        }
+    }
+
+    /// No process may ptrace another, not even root (Yama scope 3)
+    pub fn ptrace_disabled(&self) -> bool {
+        self.ptrace_scope == Some(3)
+    }
+
+    /// Only ancestors (or `CAP_SYS_PTRACE`) may attach
+    pub fn ptrace_ancestors_only(&self) -> bool {
+        self.ptrace_scope.is_some_and(|s| s >= 1)
+    }
+
+    /// kprobes, kernel-memory BPF and perf kernel reads are refused
+    pub fn kernel_tracing_locked(&self) -> bool {
+        self.lockdown == Some(Lockdown::Confidentiality)
+    }
+
+    /// Unprivileged processes may open perf events on themselves
+    pub fn unprivileged_perf(&self) -> bool {
+        self.perf_event_paranoid.is_none_or(|p| p <= 2)
+    }
+
+    /// Confidence multiplier for evidence from `source`: evidence of a
+    /// mechanism the kernel refuses is more likely a false positive
+    pub fn confidence_scale(&self, source: DetectionSource) -> f64 {
+        match source {
+            DetectionSource::Ptrace if self.ptrace_disabled() => 0.5,
+            DetectionSource::KernelProbe if self.kernel_tracing_locked() => 0.5,
+            _ => 1.0,
+        }
+    }
+}
+
+/// The bracketed entry of `/sys/kernel/security/lockdown`
+/// ("none [integrity] confidentiality")
+pub fn parse_lockdown(content: &str) -> Option<Lockdown> {
+    let selected = content.split_whitespace().find(|w| w.starts_with('['))?;
+    match selected.trim_matches(|c| c == '[' || c == ']') {
+        "none" => Some(Lockdown::None),
+        "integrity" => Some(Lockdown::Integrity),
+        "confidentiality" => Some(Lockdown::Confidentiality),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lockdown() {
+        assert_eq!(parse_lockdown("[none] integrity confidentiality\n"), Some(Lockdown::None));
+        assert_eq!(parse_lockdown("none integrity [confidentiality]\n"), Some(Lockdown::Confidentiality));
+        assert_eq!(parse_lockdown("none integrity confidentiality\n"), None);
+    }
+
+    #[test]
+    fn test_confidence_scale() {
+        let locked = KernelPosture { ptrace_scope: Some(3), lockdown: Some(Lockdown::Confidentiality), kptr_restrict: Some(2), perf_event_paranoid: Some(3) };
+        assert_eq!(locked.confidence_scale(DetectionSource::Ptrace), 0.5);
+        assert_eq!(locked.confidence_scale(DetectionSource::KernelProbe), 0.5);
+        assert_eq!(locked.confidence_scale(DetectionSource::Timing), 1.0);
+        assert!(!locked.unprivileged_perf());
+
+        let open = KernelPosture { ptrace_scope: Some(1), ..KernelPosture::default() };
+        assert!(open.ptrace_ancestors_only());
+        assert_eq!(open.confidence_scale(DetectionSource::Ptrace), 1.0);
+        assert!(open.unprivileged_perf());
+    }
+}
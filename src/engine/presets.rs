@@ -0,0 +1,266 @@
+//! Built-in Policy Presets
+//!
+//! A preset bundles every knob that otherwise lives in scattered
+//! constants: which detectors run, the verdict thresholds, how verdicts
+//! map to responses, and how much the framework prints.
+//!
+//! | Preset     | Detectors            | Thresholds (S/I/D) | Response      | Output  |
+//! |------------|----------------------|--------------------|---------------|---------|
+//! | `paranoid` | All                  | 10 / 30 / 60       | Aggressive    | Normal  |
+//! | `balanced` | All                  | 20 / 50 / 90       | Standard      | Normal  |
+//! | `stealthy` | Non-intrusive only   | 15 / 40 / 75       | Callback only | Silent  |
+//!
+//! "Intrusive" detectors install signal handlers, raise signals, or call
+//! PTRACE_TRACEME - anything that changes process state or that a host
+//! application could observe. `stealthy` lowers its thresholds because it
+//! collects less evidence per run.
+//!
+//! `balanced` reproduces the framework's historical behavior and is the
+//! default, except that every preset caps each timing-based source just
+//! below its `Instrumented` threshold (see `limits.rs`). A loaded model file still replaces the preset's thresholds.
+//! Sandbox-artifact checks, the sandbox hardware profile and the host-wide
+//! daemon scan are opt-in under every preset, as is Bayesian scoring
+//! (`--scoring bayesian`, see `bayes.rs`).
+
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use crate::engine::bayes::ScoringMode;
+use crate::engine::classifier::ThresholdClassifier;
+use crate::engine::decay::DecayPolicy;
+use crate::engine::limits::SourceLimits;
+use crate::engine::responses::ResponseMode;
+
+/// Environment variable naming a preset to use when `--preset` is absent
+pub const PRESET_ENV_VAR: &str = "ANTIDEBUG_PRESET";
+
+/// Environment variable that enables the sandbox-artifact checks (`1`)
+pub const SANDBOX_ENV_VAR: &str = "ANTIDEBUG_SANDBOX_CHECKS";
+
+/// Environment variable that enables the sandbox hardware-profile score (`1`)
+pub const SANDBOX_PROFILE_ENV_VAR: &str = "ANTIDEBUG_SANDBOX_PROFILE";
+
+/// Environment variable that enables the host-wide daemon scan (`1`)
+pub const HOST_SCAN_ENV_VAR: &str = "ANTIDEBUG_HOST_SCAN";
+
+/// Environment variable naming a scoring mode when `--scoring` is absent
+pub const SCORING_ENV_VAR: &str = "ANTIDEBUG_SCORING";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Preset {
+    Paranoid,
+    #[default]
+    Balanced,
+    Stealthy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    /// Phase banners, per-detector diagnostics and the evidence summary
+    Normal,
+    /// Nothing on stdout/stderr until the payload runs
+    Silent,
+}
+
+/// Everything a preset decides
+#[derive(Debug, Clone, Copy)]
+pub struct PolicyConfig {
+    pub preset: Preset,
+    /// Run detectors that install signal handlers or change ptrace state
+    pub intrusive_probes: bool,
+    /// Look for malware-sandbox artifacts (a different threat model than
+    /// debuggers, so off in every preset)
+    pub sandbox_checks: bool,
+    /// Score the machine's hardware for sandbox tells (few CPUs, little RAM,
+    /// no input devices), which servers share; off in every preset
+    pub sandbox_profile: bool,
+    /// Scan every process on the host for analysis daemons, not only the
+    /// ones aimed at us (off in every preset)
+    pub host_scan: bool,
+    pub thresholds: ThresholdClassifier,
+    /// Additive score against `thresholds`, or Bayesian posteriors
+    /// (additive in every preset)
+    pub scoring: ScoringMode,
+    /// Per-source weight caps and duplicate collapsing
+    pub limits: SourceLimits,
+    /// Weight loss of old evidence once the scan is over (none in every
+    /// preset)
+    pub decay: DecayPolicy,
+    pub response: ResponseMode,
+    pub verbosity: Verbosity,
+}
+
+impl Preset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "paranoid" => Some(Preset::Paranoid),
+            "balanced" => Some(Preset::Balanced),
+            "stealthy" => Some(Preset::Stealthy),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Paranoid => "paranoid",
+            Preset::Balanced => "balanced",
+            Preset::Stealthy => "stealthy",
+        }
+    }
+
+    pub fn config(self) -> PolicyConfig {
+        match self {
+            Preset::Paranoid => PolicyConfig {
+                preset: self,
+                intrusive_probes: true,
+                sandbox_checks: false,
+                sandbox_profile: false,
+                host_scan: false,
+                thresholds: ThresholdClassifier { suspicious: 10, instrumented: 30, deceptive: 60 },
+                scoring: ScoringMode::Additive,
+                limits: SourceLimits::timing_capped(25).collapsing_duplicates(),
+                decay: DecayPolicy::default(),
+                response: ResponseMode::Aggressive,
+                verbosity: Verbosity::Normal,
+            },
+            Preset::Balanced => PolicyConfig {
+                preset: self,
+                intrusive_probes: true,
+                sandbox_checks: false,
+                sandbox_profile: false,
+                host_scan: false,
+                thresholds: ThresholdClassifier::default(),
+                scoring: ScoringMode::Additive,
+                limits: SourceLimits::timing_capped(45).collapsing_duplicates(),
+                decay: DecayPolicy::default(),
+                response: ResponseMode::Standard,
+                verbosity: Verbosity::Normal,
+            },
+            Preset::Stealthy => PolicyConfig {
+                preset: self,
+                intrusive_probes: false,
+                sandbox_checks: false,
+                sandbox_profile: false,
+                host_scan: false,
+                thresholds: ThresholdClassifier { suspicious: 15, instrumented: 40, deceptive: 75 },
+                scoring: ScoringMode::Additive,
+                limits: SourceLimits::timing_capped(35).collapsing_duplicates(),
+                decay: DecayPolicy::default(),
+                response: ResponseMode::CallbackOnly,
+                verbosity: Verbosity::Silent,
+            },
+        }
+    }
+
+    /// Resolve a preset name, falling back to `balanced` (and saying so)
+    pub fn resolve(name: Option<&str>) -> Self {
+        match name {
+            None => Preset::Balanced,
+            Some(name) => Preset::from_name(name).unwrap_or_else(|| {
+                diag!("[PRESET] Unknown preset '{}', using balanced", name);
+                Preset::Balanced
+            }),
+        }
+    }
+}
+
+/// Redirects stdout and stderr to /dev/null until dropped.
+///
+/// Works at the file-descriptor level so diagnostics from every detector
+/// (and from libc) are suppressed without each call site knowing about it.
+pub struct SilencedOutput {
+    saved: Option<(i32, i32)>,
+}
+
+impl SilencedOutput {
+    pub fn new() -> Self {
+        let _ = std::io::stdout().flush();
+        let _ = std::io::stderr().flush();
+
+        let devnull = match File::options().write(true).open("/dev/null") {
+            Ok(f) => f,
+            Err(_) => return Self { saved: None },
+        };
+        // SAFETY: dup/dup2 on the process's own standard descriptors
+        unsafe {
+            let out = libc::dup(libc::STDOUT_FILENO);
+            let err = libc::dup(libc::STDERR_FILENO);
+            if out < 0 || err < 0 {
+                return Self { saved: None };
+            }
+            libc::dup2(devnull.as_raw_fd(), libc::STDOUT_FILENO);
+            libc::dup2(devnull.as_raw_fd(), libc::STDERR_FILENO);
+            Self { saved: Some((out, err)) }
+        }
+    }
+}
+
+impl SilencedOutput {
+    /// The stdout that was replaced, for output meant to get through
+    /// (`None` if nothing was silenced)
+    pub fn original_stdout(&self) -> Option<File> {
+        let (out, _) = self.saved?;
+        // SAFETY: `out` stays open until drop; the duplicate is owned by the
+        // returned File
+        let fd = unsafe { libc::dup(out) };
+        (fd >= 0).then(|| unsafe { File::from_raw_fd(fd) })
+    }
+}
+
+impl Default for SilencedOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SilencedOutput {
+    fn drop(&mut self) {
+        let _ = std::io::stdout().flush();
+        if let Some((out, err)) = self.saved.take() {
+            // SAFETY: restoring descriptors duplicated in `new`
+            unsafe {
+                libc::dup2(out, libc::STDOUT_FILENO);
+                libc::dup2(err, libc::STDERR_FILENO);
+                libc::close(out);
+                libc::close(err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::policy::DetectionSource;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Preset::from_name("Paranoid"), Some(Preset::Paranoid));
+        assert_eq!(Preset::from_name("bogus"), None);
+        assert_eq!(Preset::resolve(None), Preset::Balanced);
+    }
+
+    #[test]
+    fn test_balanced_matches_historical_defaults() {
+        let config = Preset::Balanced.config();
+        let defaults = ThresholdClassifier::default();
+        assert!(config.intrusive_probes);
+        assert!(!config.sandbox_checks);
+        assert!(!config.sandbox_profile);
+        assert!(!config.host_scan);
+        assert_eq!(config.scoring, ScoringMode::Additive);
+        assert!(!config.decay.is_active());
+        assert!(config.limits.cap(DetectionSource::Jitter).is_some_and(|cap| cap < defaults.instrumented));
+        assert_eq!(config.response, ResponseMode::Standard);
+        assert_eq!(config.thresholds.suspicious, defaults.suspicious);
+        assert_eq!(config.thresholds.deceptive, defaults.deceptive);
+    }
+
+    #[test]
+    fn test_stealthy_is_passive() {
+        let config = Preset::Stealthy.config();
+        assert!(!config.intrusive_probes);
+        assert_eq!(config.response, ResponseMode::CallbackOnly);
+        assert_eq!(config.verbosity, Verbosity::Silent);
+    }
+}
@@ -0,0 +1,245 @@
+//! Self-Contained HTML Report
+//!
+//! Renders one run into a single HTML file with no external assets, for
+//! human triage of Suspicious/Deceptive verdicts:
+//!
+//! - Verdict, score and classifier
+//! - Environment summary (governor, throttling, kernel, tolerances)
+//! - Per-source score bars
+//! - Evidence timeline, in the order it was reported
+//! - Contradictions and detector diagnostics
+//! - Timing histograms for every recorded sample set (inline SVG)
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::engine::bayes::Hypothesis;
+use crate::engine::environment::EnvironmentState;
+use crate::engine::policy::{DecisionEngine, SampleSet};
+use crate::engine::timeline::TimelineEvent;
+
+/// Histogram bins per sample set
+const BINS: usize = 40;
+const SVG_WIDTH: usize = 480;
+const SVG_HEIGHT: usize = 120;
+
+const STYLE: &str = "\
+body{font-family:sans-serif;margin:2em;color:#222;max-width:60em}\
+h1{font-size:1.4em}h2{font-size:1.1em;border-bottom:1px solid #ccc;margin-top:2em}\
+table{border-collapse:collapse;width:100%}td,th{padding:.25em .5em;text-align:left;vertical-align:top;border-bottom:1px solid #eee}\
+.bar{background:#c0392b;height:.9em}.verdict{font-weight:bold;padding:.2em .5em;color:#fff}\
+.Clean{background:#27ae60}.Suspicious{background:#e67e22}.Instrumented{background:#c0392b}.Deceptive{background:#8e44ad}\
+figure{display:inline-block;margin:.5em}figcaption{font-size:.85em}.run td{color:#888;font-size:.85em}";
+
+/// Escape text for HTML element content and attribute values
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Bin `values` between the minimum and the 99th percentile; anything above
+/// lands in the last bin so one outlier cannot flatten the plot.
+/// Returns (counts, lower bound, upper bound).
+pub fn histogram(values: &[u64], bins: usize) -> (Vec<usize>, u64, u64) {
+    let mut counts = vec![0; bins];
+    if values.is_empty() || bins == 0 {
+        return (counts, 0, 0);
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let lo = sorted[0];
+    let hi = sorted[(sorted.len() * 99 / 100).min(sorted.len() - 1)].max(lo + 1);
+    let width = (hi - lo) as f64 / bins as f64;
+    for &v in values {
+        let bin = ((v.saturating_sub(lo)) as f64 / width) as usize;
+        counts[bin.min(bins - 1)] += 1;
+    }
+    (counts, lo, hi)
+}
+
+fn histogram_svg(set: &SampleSet) -> String {
+    let (counts, lo, hi) = histogram(&set.values, BINS);
+    let peak = counts.iter().copied().max().unwrap_or(0).max(1);
+    let bar_width = SVG_WIDTH / BINS;
+    let mut svg = format!(
+        "<figure><svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = SVG_WIDTH, h = SVG_HEIGHT + 14
+    );
+    for (i, &count) in counts.iter().enumerate() {
+        let height = count * SVG_HEIGHT / peak;
+        let _ = write!(svg, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#2c3e50\"><title>{}</title></rect>",
+                       i * bar_width, SVG_HEIGHT - height, bar_width.saturating_sub(1), height, count);
+    }
+    let _ = write!(svg, "<text x=\"0\" y=\"{y}\" font-size=\"11\">{}</text><text x=\"{w}\" y=\"{y}\" font-size=\"11\" text-anchor=\"end\">{}+</text></svg>",
+                   lo, hi, y = SVG_HEIGHT + 12, w = SVG_WIDTH);
+    let _ = write!(svg, "<figcaption>{} ({} samples, cycles)</figcaption></figure>", escape(&set.name), set.values.len());
+    svg
+}
+
+fn row(out: &mut String, key: &str, value: &str) {
+    let _ = write!(out, "<tr><th>{}</th><td>{}</td></tr>", escape(key), escape(value));
+}
+
+fn environment_table(env: &EnvironmentState) -> String {
+    let mut out = String::from("<table>");
+    row(&mut out, "CPU governor", env.cpu_governor.as_deref().unwrap_or("unknown"));
+    row(&mut out, "SMT active", &env.smt_active.map_or("unknown".to_string(), |v| v.to_string()));
+    row(&mut out, "Power", env.on_battery.map_or("unknown", |b| if b { "battery" } else { "AC" }));
+    row(&mut out, "Energy preference", env.energy_preference.as_deref().unwrap_or("unknown"));
+    if let Some(ref throttle) = env.cpu_throttle {
+        row(&mut out, "cgroup CPU quota", &format!("v{}: {} (throttled {}/{} periods)",
+            throttle.version,
+            throttle.quota_cpus.map_or("unlimited".to_string(), |c| format!("{:.2} CPUs", c)),
+            throttle.nr_throttled, throttle.nr_periods));
+    }
+    row(&mut out, "Kernel", &format!("config={} preempt_rt={} nohz_full={} kpti={}",
+        env.kernel.config_source.as_deref().unwrap_or("unreadable"),
+        env.kernel.preempt_rt, env.kernel.nohz_full.as_deref().unwrap_or("none"), env.kernel.kpti));
+    row(&mut out, "Kernel posture", &format!("ptrace_scope={:?} lockdown={:?} kptr_restrict={:?} perf_event_paranoid={:?}",
+        env.posture.ptrace_scope, env.posture.lockdown, env.posture.kptr_restrict, env.posture.perf_event_paranoid));
+    row(&mut out, "Core dumps", &format!("pattern={} captured={}",
+        env.core_dump.core_pattern.as_deref().unwrap_or("unreadable"), env.core_dump.captures_crash()));
+    row(&mut out, "Adjustment factor", &format!("{:.2}", env.adjustment_factor));
+    row(&mut out, "Timing confidence / tolerance", &format!("{:.2} / {:.2}x", env.timing_confidence, env.timing_tolerance));
+    row(&mut out, "Syscall tolerance", &format!("{:.2}x", env.syscall_tolerance));
+    for warning in &env.warnings {
+        row(&mut out, "Warning", warning);
+    }
+    out.push_str("</table>");
+    out
+}
+
+/// Render the full report
+pub fn render_html(engine: &DecisionEngine, env: &EnvironmentState) -> String {
+    let verdict = format!("{:?}", engine.decide());
+    let mut html = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Anti-Debug Report: {v}</title><style>{}</style></head><body>\
+         <h1>Anti-Debug Report <span class=\"verdict {v}\">{v}</span></h1>\
+         <p>Score {} &middot; Classifier {}</p>",
+        STYLE, engine.get_score(), escape(engine.classifier_name()), v = verdict
+    );
+
+    let tools = engine.identify_tools();
+    if !tools.is_empty() {
+        html.push_str("<h2>Likely Tools</h2><table>");
+        for t in &tools {
+            row(&mut html, t.tool, &format!("{:.0}%", t.confidence * 100.0));
+        }
+        html.push_str("</table>");
+    }
+
+    html.push_str("<h2>Threat Axes</h2><table>");
+    for axis in &engine.verdict_report().axes {
+        row(&mut html, axis.axis.name(), &format!("{} (weight {})", axis.level.name(), axis.weight));
+    }
+    html.push_str("</table>");
+
+    if let Some(posterior) = engine.posterior() {
+        html.push_str("<h2>Posterior</h2><table>");
+        for h in Hypothesis::ALL {
+            let _ = write!(html, "<tr><th>{:?}</th><td style=\"width:70%\"><div class=\"bar\" style=\"width:{:.0}%\"></div></td><td>{:.1}%</td></tr>",
+                           h, posterior.probability(h) * 100.0, posterior.probability(h) * 100.0);
+        }
+        html.push_str("</table>");
+    }
+
+    html.push_str("<h2>Environment</h2>");
+    html.push_str(&environment_table(env));
+
+    html.push_str("<h2>Score by Source</h2><table>");
+    let weights = engine.source_weights();
+    let max_weight = weights.first().map_or(1, |w| w.1.max(1));
+    for (source, weight) in &weights {
+        let _ = write!(html, "<tr><th>{:?}</th><td style=\"width:70%\"><div class=\"bar\" style=\"width:{}%\"></div></td><td>{}</td></tr>",
+                       source, weight * 100 / max_weight, weight);
+    }
+    html.push_str("</table>");
+
+    html.push_str("<h2>Scan Timeline</h2><table><tr><th>Offset</th><th>#</th><th>Source</th><th>Weight</th><th>Confidence</th><th>Thread</th><th>Details</th></tr>");
+    for entry in engine.timeline() {
+        let offset = format!("+{:.3} ms", entry.offset.as_secs_f64() * 1e3);
+        match entry.event {
+            TimelineEvent::Detector(run) => {
+                let _ = write!(html, "<tr class=\"run\"><td>{}</td><td colspan=\"6\">{} ran {:.3} ms</td></tr>",
+                               offset, escape(&run.name), run.runtime.as_secs_f64() * 1e3);
+            }
+            TimelineEvent::Evidence(evidence) => {
+                let _ = write!(html, "<tr><td>{}</td><td>{}</td><td>{:?}</td><td>{}</td><td>{:.2}</td><td>{}</td><td>{}</td></tr>",
+                               offset, evidence.seq, evidence.source, evidence.weight, evidence.confidence,
+                               escape(evidence.thread.as_deref().unwrap_or("process")),
+                               escape(&if evidence.repeats > 0 { format!("{} (repeated {}x)", evidence.details, evidence.repeats + 1) } else { evidence.details.clone() }));
+            }
+        }
+    }
+    html.push_str("</table>");
+
+    if !engine.get_contradictions().is_empty() {
+        html.push_str("<h2>Contradictions</h2><table>");
+        for c in engine.get_contradictions() {
+            row(&mut html, &format!("{:?} vs {:?} (+{})", c.source_a, c.source_b, c.weight), &c.description);
+        }
+        html.push_str("</table>");
+    }
+    if !engine.get_diagnostics().is_empty() {
+        html.push_str("<h2>Diagnostics</h2><table>");
+        for d in engine.get_diagnostics() {
+            row(&mut html, &d.component, &d.message);
+        }
+        html.push_str("</table>");
+    }
+
+    if !engine.get_samples().is_empty() {
+        html.push_str("<h2>Timing Histograms</h2>");
+        for set in engine.get_samples() {
+            html.push_str(&histogram_svg(set));
+        }
+    }
+
+    html.push_str("</body></html>\n");
+    html
+}
+
+/// Render and write the report to `path`
+pub fn write_html(path: &Path, engine: &DecisionEngine, env: &EnvironmentState) -> io::Result<()> {
+    fs::write(path, render_html(engine, env))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::policy::DetectionSource;
+
+    #[test]
+    fn test_histogram_clips_outliers() {
+        let mut values: Vec<u64> = (0..200).map(|i| 100 + i % 10).collect();
+        values.push(1_000_000);
+        let (counts, lo, hi) = histogram(&values, 10);
+        assert_eq!(lo, 100);
+        assert!(hi < 1_000);
+        assert_eq!(counts.iter().sum::<usize>(), values.len());
+    }
+
+    #[test]
+    fn test_render_escapes_details() {
+        let mut engine = DecisionEngine::new();
+        engine.report(DetectionSource::Timing, 20, "<script>alert(1)</script>");
+        engine.record_samples("rdtsc_overhead", &[30, 31, 32, 90]);
+        let env = EnvironmentState::detect();
+        let html = render_html(&engine, &env);
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("<svg"));
+        assert!(html.contains("Timing"));
+    }
+}
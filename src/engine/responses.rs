@@ -1,6 +1,44 @@
 use std::thread;
 use std::time::Duration;
 use crate::engine::policy::Verdict;
+use crate::engine::salt::magic;
+
+/// Exit statuses and fake-error values differ per build (`salt.rs`), so a
+/// wrapper cannot recognise a response by a fixed number
+const INSTRUMENTED_EXIT: i32 = 0x40 | (magic("responses.instrumented_exit") & 0x3f) as i32;
+const DECEPTIVE_EXIT: i32 = 0x80 | (magic("responses.deceptive_exit") & 0x3f) as i32;
+const FAKE_FAULT_ADDR: u64 = 0x0040_0000 + (magic("responses.fault_addr") & 0xfff) * 0x1000;
+const FAKE_ASSERT_VALUE: u32 = magic("responses.assert_value") as u32;
+
+/// How verdicts are turned into actions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseMode {
+    /// Suspicious already triggers the Instrumented countermeasures
+    Aggressive,
+    /// `apply_response` as-is
+    Standard,
+    /// Never act directly; only hand the verdict to the embedder's callback
+    CallbackOnly,
+}
+
+/// Embedder hook invoked with the final verdict in `CallbackOnly` mode
+pub type ResponseCallback = fn(Verdict);
+
+/// Apply the response for `verdict` according to `mode`
+pub fn apply_response_mode(mode: ResponseMode, verdict: Verdict, callback: Option<ResponseCallback>) {
+    match mode {
+        ResponseMode::Aggressive => match verdict {
+            Verdict::Suspicious => apply_response(Verdict::Instrumented),
+            other => apply_response(other),
+        },
+        ResponseMode::Standard => apply_response(verdict),
+        ResponseMode::CallbackOnly => {
+            if let Some(callback) = callback {
+                callback(verdict);
+            }
+        }
+    }
+}
 
 /// Executes a defensive response based on the verdict.
 /// This demonstrates "Ethical" defensive strategies:
@@ -18,26 +56,26 @@ pub fn apply_response(verdict: Verdict) {
             // Mild annoyance / degradation
             // Introduce a noticeable but not fatal delay to mess with timing analysis
             // or user patience.
-            eprintln!("[RESPONSE] Suspicious activity detected. Throttling execution...");
+            diag!("[RESPONSE] Suspicious activity detected. Throttling execution...");
             thread::sleep(Duration::from_secs(2));
         }
         Verdict::Instrumented => {
             // Severe response
-            eprintln!("[RESPONSE] Instrumentation detected. Engaging countermeasures.");
+            diag!("[RESPONSE] Instrumentation detected. Engaging countermeasures.");
             
             // 1. Logic Misdirection: Pretend to be doing work
             fake_computation();
             
             // 2. Fake Error
-            eprintln!("Fatal Error: Core library corruption detected at 0x00400000.");
+            eprintln!("Fatal Error: Core library corruption detected at {:#010x}.", FAKE_FAULT_ADDR);
             
             // 3. Termination
-            std::process::exit(0xC0DE);
+            std::process::exit(INSTRUMENTED_EXIT);
         }
         Verdict::Deceptive => {
             // Maximum response: Environment is actively lying
-            eprintln!("[RESPONSE] CRITICAL: Environment deception detected!");
-            eprintln!("[RESPONSE] Contradictory evidence suggests advanced analysis.");
+            diag!("[RESPONSE] CRITICAL: Environment deception detected!");
+            diag!("[RESPONSE] Contradictory evidence suggests advanced analysis.");
             
             // 1. Extended misdirection
             for _ in 0..5 {
@@ -45,12 +83,12 @@ pub fn apply_response(verdict: Verdict) {
             }
             
             // 2. Multiple fake errors to poison analysis
-            eprintln!("Assertion failed: integrity_check() == 0xDEADBEEF");
+            eprintln!("Assertion failed: integrity_check() == {:#010X}", FAKE_ASSERT_VALUE);
             eprintln!("Stack smashing detected ***");
             eprintln!("Segmentation fault (core dumped)");
             
             // 3. Non-standard exit code
-            std::process::exit(0xDEAD);
+            std::process::exit(DECEPTIVE_EXIT);
         }
     }
 }
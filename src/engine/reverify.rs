@@ -0,0 +1,122 @@
+//! Re-Verification Before Sensitive Calls (TOCTOU)
+//!
+//! A verdict describes the moment it was computed. An analyst who lets the
+//! checks pass and attaches afterwards - or patches the engine's score in
+//! memory - would otherwise reach the payload with a stale "Clean".
+//!
+//! [`seal`] records the verdict together with a digest of the engine state
+//! and the time. [`guarded_call`] runs immediately before the payload (or
+//! any other sensitive call) and:
+//!
+//! 1. Recomputes the digest: any change means the engine was tampered with
+//! 2. Re-reads `TracerPid` from /proc (uncached) and compares it with the
+//!    value at seal time (our own `PTRACE_TRACEME` probe leaves the parent
+//!    as tracer, which the verdict already accounts for); a change the
+//!    late-attach check has already reported is not reported again
+//! 3. Rescans registered worker threads (`threads.rs`)
+//! 4. Flags a long gap since the seal (stopped in the window)
+//!
+//! The call only runs if the verdict decided afterwards still allows it.
+
+use std::time::{Duration, Instant};
+use crate::engine::policy::{DecisionEngine, DetectionSource, Verdict};
+use crate::engine::salt::jitter;
+use crate::engine::signal_compat::{refresh_tracer_pid, tracer_transition};
+use crate::engine::threads;
+
+/// Gap between seal and call above which the window was probably paused
+/// (salted +-25%)
+const MAX_WINDOW: Duration = Duration::from_millis(jitter(2_000, 25, "reverify.max_window_ms"));
+
+/// Verdict plus the engine state it was computed from
+#[derive(Debug, Clone)]
+pub struct Seal {
+    pub verdict: Verdict,
+    digest: [u8; 32],
+    tracer_pid: u32,
+    sealed_at: Instant,
+}
+
+/// Seal the engine's current verdict
+pub fn seal(engine: &DecisionEngine) -> Seal {
+    Seal { verdict: engine.decide(), digest: engine.digest(), tracer_pid: refresh_tracer_pid(), sealed_at: Instant::now() }
+}
+
+/// Verdicts under which protected code may run
+pub fn allows(verdict: Verdict) -> bool {
+    matches!(verdict, Verdict::Clean | Verdict::Suspicious)
+}
+
+/// Run the quick checks against `seal` and return the fresh verdict
+pub fn reverify(engine: &mut DecisionEngine, seal: &Seal) -> Verdict {
+    if engine.digest() != seal.digest {
+        engine.report(DetectionSource::Integrity, 100,
+            &reason!("Engine state changed between verdict and payload: evidence or score tampered with"));
+    }
+
+    if let Some((_, tracer)) = tracer_transition().filter(|(_, t)| *t != 0 && *t != seal.tracer_pid) {
+        engine.report(DetectionSource::Ptrace, 60,
+            &reason!("Tracer (PID {}) attached after the verdict, before the payload", tracer));
+    }
+
+    threads::scan_threads(engine);
+
+    let window = seal.sealed_at.elapsed();
+    if window > MAX_WINDOW {
+        engine.report_with_confidence(DetectionSource::Timing, 30, 0.7,
+            &reason!("{} ms between verdict and payload: process paused in the window", window.as_millis()));
+    }
+
+    let verdict = engine.decide();
+    diag!("[REVERIFY] Sealed {:?}, now {:?} ({} ms later)", seal.verdict, verdict, window.as_millis());
+    verdict
+}
+
+/// Call `f` only if `seal` still holds after re-verification
+pub fn guarded_call<R>(engine: &mut DecisionEngine, seal: &Seal, name: &str, f: impl FnOnce() -> R) -> Option<R> {
+    if !allows(seal.verdict) {
+        return None;
+    }
+    let verdict = reverify(engine, seal);
+    if allows(verdict) {
+        Some(f())
+    } else {
+        diag!("[REVERIFY] Refusing {}: verdict is now {:?}", name, verdict);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guarded_call_runs_when_unchanged() {
+        let mut engine = DecisionEngine::new();
+        let seal = seal(&engine);
+        assert_eq!(guarded_call(&mut engine, &seal, "test", || 42), Some(42));
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let mut engine = DecisionEngine::new();
+        engine.report(DetectionSource::Ptrace, 10, "before seal");
+        let seal = seal(&engine);
+        // Stand-in for a patched score: any state change after sealing
+        engine.report(DetectionSource::Timing, 0, "after seal");
+        assert_eq!(guarded_call(&mut engine, &seal, "test", || 42), None);
+        assert!(engine.get_history().iter().any(|e| e.source == DetectionSource::Integrity));
+    }
+
+    #[test]
+    fn test_late_evidence_is_not_tampering() {
+        let mut engine = DecisionEngine::new();
+        engine.close_scan();
+        let seal = seal(&engine);
+        // A monitor reporting after the verdict
+        engine.report(DetectionSource::Ptrace, 100, "late attach");
+        assert_eq!(engine.late_evidence().count(), 1);
+        assert_eq!(guarded_call(&mut engine, &seal, "test", || 42), None);
+        assert!(!engine.get_history().iter().any(|e| e.source == DetectionSource::Integrity));
+    }
+}
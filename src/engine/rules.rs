@@ -0,0 +1,253 @@
+//! Declarative Contradiction Rules
+//!
+//! `analyze_contradictions()` looks for evidence patterns that cannot all
+//! be true of an honest environment. The patterns are rules, one per line,
+//! so researchers can add correlations without recompiling:
+//!
+//! ```text
+//! # condition [AND condition ...] => contradiction(SourceA, SourceB, +weight) "description"
+//! Jitter > 40 AND Ptrace == 0 => contradiction(Jitter, Ptrace, +30) "Jitter without a tracer"
+//! feature frida_unmapped_objects > 0 => contradiction(Instrumentation, CrossView, +30) "Maps sanitised"
+//! ```
+//!
+//! | Term                        | Value                                          |
+//! |-----------------------------|------------------------------------------------|
+//! | `Source`                    | Total weight of a `DetectionSource`            |
+//! | `SourceA+SourceB`           | Sum of their weights                           |
+//! | `feature <column>`          | Feature-vector column (unobserved = 0)         |
+//!
+//! Comparisons are `>`, `>=`, `<`, `<=`, `==` and `!=` against a number;
+//! `⇒` may stand for `=>`. The built-in rules ([`BUILTIN_RULES`]) always
+//! apply; `--contradiction-rules <path>` (or `ANTIDEBUG_CONTRADICTION_RULES`)
+//! adds a file's rules to them.
+
+use std::path::Path;
+use crate::engine::features::{FeatureVector, FEATURES};
+use crate::engine::policy::DetectionSource;
+
+/// Environment variable naming a rules file when `--contradiction-rules`
+/// is absent
+pub const RULES_ENV_VAR: &str = "ANTIDEBUG_CONTRADICTION_RULES";
+
+/// Rules every engine starts with. Stealth builds compile in the copy
+/// `build.rs` writes, without comments and with coded descriptions.
+#[cfg(not(feature = "stealth"))]
+pub const BUILTIN_RULES: &str = r#"
+# Timing shows single-step behavior but neither hardware breakpoints nor a
+# tracer were found: software single-stepping (GDB's step) should trigger
+# ptrace, so the tracer is hidden
+Timing+Jitter > 40 AND HardwareBreakpoint == 0 AND Ptrace == 0 => contradiction(Timing, Ptrace, +30) "Heavy timing anomaly but no tracer detected - possible ptrace hiding"
+
+# The loader lists a Frida object that /proc/self/maps does not show - the
+# maps view was sanitised
+feature frida_unmapped_objects > 0 => contradiction(Instrumentation, CrossView, +30) "Loader list shows a Frida object that /proc/self/maps hides - maps sanitised"
+
+# Ptrace with completely clean timing is expected of strace (no single-step)
+# and deliberately not a rule
+"#;
+
+#[cfg(feature = "stealth")]
+pub const BUILTIN_RULES: &str = include_str!(concat!(env!("OUT_DIR"), "/builtin_rules.txt"));
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    fn holds(self, left: f64, right: f64) -> bool {
+        match self {
+            Comparison::Greater => left > right,
+            Comparison::GreaterEq => left >= right,
+            Comparison::Less => left < right,
+            Comparison::LessEq => left <= right,
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    /// Sum of these sources' weights
+    Weight(Vec<DetectionSource>),
+    /// Feature-vector column
+    Feature(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Condition {
+    term: Term,
+    comparison: Comparison,
+    value: f64,
+}
+
+/// One parsed rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContradictionRule {
+    conditions: Vec<Condition>,
+    pub source_a: DetectionSource,
+    pub source_b: DetectionSource,
+    /// Score added when the rule fires
+    pub weight: u32,
+    pub description: String,
+}
+
+impl ContradictionRule {
+    /// Whether every condition holds, given each source's total weight and
+    /// the run's features
+    pub fn matches(&self, source_weight: impl Fn(DetectionSource) -> u32, features: &FeatureVector) -> bool {
+        self.conditions.iter().all(|c| {
+            let left = match &c.term {
+                Term::Weight(sources) => sources.iter().map(|s| source_weight(*s) as f64).sum(),
+                Term::Feature(name) => features.get(name).filter(|v| !v.is_nan()).unwrap_or(0.0),
+            };
+            c.comparison.holds(left, c.value)
+        })
+    }
+}
+
+fn parse_source(name: &str) -> Result<DetectionSource, String> {
+    DetectionSource::from_name(name).ok_or_else(|| format!("unknown source '{}'", name.trim()))
+}
+
+fn parse_condition(text: &str) -> Result<Condition, String> {
+    // Two-character operators first, so `>=` is not read as `>`
+    const OPERATORS: [(&str, Comparison); 6] = [
+        (">=", Comparison::GreaterEq), ("<=", Comparison::LessEq), ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual), (">", Comparison::Greater), ("<", Comparison::Less),
+    ];
+    let (at, op, comparison) = OPERATORS.iter()
+        .find_map(|(op, c)| text.find(op).map(|at| (at, *op, *c)))
+        .ok_or_else(|| format!("no comparison in '{}'", text.trim()))?;
+    let (term, value) = (text[..at].trim(), text[at + op.len()..].trim());
+    let value = value.parse::<f64>().map_err(|_| format!("'{}' is not a number", value))?;
+    let term = match term.strip_prefix("feature ") {
+        Some(column) => {
+            let column = column.trim();
+            if !FEATURES.iter().any(|(n, _)| *n == column) {
+                return Err(format!("unknown feature column '{}'", column));
+            }
+            Term::Feature(column.to_string())
+        }
+        None => Term::Weight(term.split('+').map(parse_source).collect::<Result<_, _>>()?),
+    };
+    Ok(Condition { term, comparison, value })
+}
+
+/// `contradiction(SourceA, SourceB, +weight) "description"`
+fn parse_consequence(text: &str) -> Result<(DetectionSource, DetectionSource, u32, String), String> {
+    let args = text.trim().strip_prefix("contradiction(").ok_or("expected contradiction(...)")?;
+    let (args, rest) = args.split_once(')').ok_or("unterminated contradiction(...)")?;
+    let args: Vec<&str> = args.split(',').map(str::trim).collect();
+    let [a, b, weight] = args[..] else {
+        return Err(format!("contradiction takes 3 arguments, got {}", args.len()));
+    };
+    let weight = weight.strip_prefix('+').unwrap_or(weight);
+    let weight = weight.parse::<u32>().map_err(|_| format!("'{}' is not a weight", weight))?;
+    let description = rest.trim()
+        .strip_prefix('"').and_then(|d| d.strip_suffix('"'))
+        .ok_or("expected a double-quoted description")?;
+    Ok((parse_source(a)?, parse_source(b)?, weight, description.to_string()))
+}
+
+fn parse_rule(line: &str) -> Result<ContradictionRule, String> {
+    let line = line.replace('⇒', "=>");
+    let (conditions, consequence) = line.split_once("=>").ok_or("expected condition => contradiction(...)")?;
+    let conditions = conditions.split(" AND ").map(parse_condition).collect::<Result<Vec<_>, _>>()?;
+    let (source_a, source_b, weight, description) = parse_consequence(consequence)?;
+    Ok(ContradictionRule { conditions, source_a, source_b, weight, description })
+}
+
+/// Parse a rules file: one rule per line, `#` comments
+pub fn parse_rules(text: &str) -> Result<Vec<ContradictionRule>, String> {
+    text.lines()
+        .enumerate()
+        .map(|(lineno, line)| (lineno, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(lineno, line)| parse_rule(line).map_err(|e| format!("line {}: {}", lineno + 1, e)))
+        .collect()
+}
+
+/// The built-in rules
+pub fn builtin_rules() -> Vec<ContradictionRule> {
+    parse_rules(BUILTIN_RULES).expect("built-in contradiction rules parse")
+}
+
+/// Load a rules file. Returns `None` (and logs why) if the file is missing
+/// or malformed, so the caller keeps the built-in rules only.
+pub fn load_rules(path: &Path) -> Option<Vec<ContradictionRule>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(e) => {
+            diag!("[RULES] Cannot read {}: {} - using built-in rules", path.display(), e);
+            return None;
+        }
+    };
+    match parse_rules(&text) {
+        Ok(rules) => {
+            diag!("[RULES] Loaded {} contradiction rule(s) from {}", rules.len(), path.display());
+            Some(rules)
+        }
+        Err(e) => {
+            diag!("[RULES] Invalid rules {}: {} - using built-in rules", path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::policy::DecisionEngine;
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = parse_rules("
+# comment
+Jitter > 40 AND Ptrace == 0 ⇒ contradiction(Jitter, Ptrace, +25) \"jitter, no tracer\"
+feature tracerpid_nonzero >= 1 => contradiction(Ptrace, CrossView, 10) \"x\"
+").unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!((rules[0].source_a, rules[0].source_b, rules[0].weight), (DetectionSource::Jitter, DetectionSource::Ptrace, 25));
+        assert_eq!(rules[0].description, "jitter, no tracer");
+        assert_eq!(rules[1].conditions[0].comparison, Comparison::GreaterEq);
+
+        assert!(parse_rules("Bogus > 1 => contradiction(Timing, Ptrace, +30) \"x\"").unwrap_err().starts_with("line 1:"));
+        assert!(parse_rules("feature nope > 1 => contradiction(Timing, Ptrace, +30) \"x\"").is_err());
+        assert!(parse_rules("Timing > lots => contradiction(Timing, Ptrace, +30) \"x\"").is_err());
+        assert!(parse_rules("Timing > 1 => contradiction(Timing, +30) \"x\"").is_err());
+        assert!(parse_rules("Timing > 1 => contradiction(Timing, Ptrace, +30)").is_err());
+        assert_eq!(builtin_rules().len(), 2);
+    }
+
+    #[test]
+    fn test_matches() {
+        let rule = &parse_rules("Timing+Jitter > 40 AND Ptrace == 0 => contradiction(Timing, Ptrace, +30) \"x\"").unwrap()[0];
+        let features = FeatureVector::new();
+        let weights = |timing, ptrace| move |s| match s {
+            DetectionSource::Timing => timing,
+            DetectionSource::Jitter => 20,
+            DetectionSource::Ptrace => ptrace,
+            _ => 0,
+        };
+        assert!(rule.matches(weights(30, 0), &features));
+        assert!(!rule.matches(weights(20, 0), &features));
+        assert!(!rule.matches(weights(30, 10), &features));
+    }
+
+    #[test]
+    fn test_engine_applies_added_rules() {
+        let mut engine = DecisionEngine::new();
+        engine.add_contradiction_rules(parse_rules("Sandbox >= 10 => contradiction(Sandbox, Hypervisor, +5) \"sandbox on bare metal\"").unwrap());
+        engine.report(DetectionSource::Sandbox, 10, "agent process");
+        engine.analyze_contradictions();
+        assert_eq!(engine.get_contradictions().len(), 1);
+        assert_eq!(engine.get_score(), 15);
+    }
+}
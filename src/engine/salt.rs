@@ -0,0 +1,72 @@
+//! Per-Build Salt
+//!
+//! A bypass script written against one build (patch the byte at offset X,
+//! grep stderr for code Y, expect exit status Z) should not work on the
+//! next one. `build.rs` draws a fresh 64-bit salt for every build
+//! (`ANTIDEBUG_BUILD_SALT=<hex>` pins it for reproducible builds) and
+//! derives from it:
+//!
+//! - **Thresholds**: selected detection thresholds move within a tolerance
+//!   their detectors were calibrated for ([`jitter`])
+//! - **Diagnostic codes**: stealth-build `D<code>` values ([`salt_code`]);
+//!   the code table in `OUT_DIR` matches only its own build
+//! - **Layout**: the assembly detectors are linked in a salted order with
+//!   salted NOP padding in front of each, so their offsets shift
+//! - **Response magic**: exit statuses and the values in fake error
+//!   messages ([`magic`])
+//!
+//! Everything here is `const fn`, so the salted values are baked in at
+//! compile time rather than computed where a debugger could watch.
+
+include!(concat!(env!("OUT_DIR"), "/build_salt.rs"));
+
+/// SplitMix64 finalizer. `build.rs` carries a copy for [`salt_code`].
+const fn mix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// 64-bit FNV-1a of `name`
+const fn tag(name: &str) -> u64 {
+    let bytes = name.as_bytes();
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash = (hash ^ bytes[i] as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
+
+/// Salted pseudo-random value for the use site `name`
+pub const fn magic(name: &str) -> u64 {
+    mix64(BUILD_SALT ^ tag(name))
+}
+
+/// `value` moved by up to `tolerance_pct` percent either way
+pub const fn jitter(value: u64, tolerance_pct: u64, name: &str) -> u64 {
+    let span = value / 100 * tolerance_pct;
+    value - span + magic(name) % (2 * span + 1)
+}
+
+/// Stealth-build diagnostic code: the call site's hash, keyed by the salt
+#[allow(dead_code)] // Only reached with the `stealth` feature
+pub const fn salt_code(code: u32) -> u32 {
+    mix64(BUILD_SALT ^ code as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter_stays_within_tolerance() {
+        for name in ["a", "b", "c", "bait.trap_cycles"] {
+            let v = jitter(20_000, 10, name);
+            assert!((18_000..=22_000).contains(&v), "{} -> {}", name, v);
+        }
+        assert_eq!(jitter(1_000, 0, "a"), 1_000);
+        assert_ne!(magic("a"), magic("b"));
+    }
+}
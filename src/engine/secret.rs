@@ -0,0 +1,190 @@
+//! Just-in-Time Decryption of Sensitive Data
+//!
+//! Detection can fail. A [`SecretCell`] limits what a memory dump or a
+//! debugger's memory view yields even then: the value is held encrypted
+//! (ChaCha20, encrypt-then-MAC with HMAC-SHA256) and only exists in
+//! plaintext inside [`SecretCell::with`]:
+//!
+//! 1. Re-derive the key and verify the tag (memory tampering, or a key
+//!    derived in a different process, fails here)
+//! 2. Decrypt into a temporary buffer and run the caller's closure
+//! 3. Wipe the plaintext and re-encrypt under a fresh nonce, so the
+//!    ciphertext changes on every access
+//!
+//! # Key Material
+//!
+//! The key is never stored. It is derived on every access from a random
+//! per-process secret plus facts about the running process: boot ID, PID,
+//! process start time and the executable's device/inode. A dump analysed
+//! offline, or memory transplanted into another process (emulator,
+//! snapshot restore), cannot re-derive it without reconstructing all of
+//! them.
+//!
+//! Windows longer than [`MAX_WINDOW`] (someone stopped inside the closure)
+//! are counted; embedders can check [`SecretCell::slow_windows`].
+
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use crate::engine::chacha20::{apply_keystream, KEY_LEN, NONCE_LEN};
+use crate::engine::log::random_nonce;
+use crate::engine::sha256::{constant_time_eq, hmac_sha256, sha256, DIGEST_LEN};
+
+/// Plaintext lifetime above which an access window counts as slow
+pub const MAX_WINDOW: Duration = Duration::from_millis(50);
+
+/// Random per-process secret mixed into every key
+static PROCESS_SECRET: OnceLock<[u8; 32]> = OnceLock::new();
+
+/// Values a [`SecretCell`] can hold
+pub trait SecretBytes: Sized {
+    fn to_bytes(&self) -> Vec<u8>;
+    fn from_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+impl SecretBytes for Vec<u8> {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.clone()
+    }
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl SecretBytes for String {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl SecretBytes for u64 {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(u64::from_le_bytes(bytes.try_into().ok()?))
+    }
+}
+
+/// Overwrite `bytes` in a way the optimizer cannot drop
+fn wipe(bytes: &mut [u8]) {
+    for b in bytes.iter_mut() {
+        // SAFETY: `b` is a valid, exclusive reference
+        unsafe { std::ptr::write_volatile(b, 0) };
+    }
+}
+
+fn process_secret() -> &'static [u8; 32] {
+    PROCESS_SECRET.get_or_init(|| {
+        let mut secret = [0u8; 32];
+        secret[..NONCE_LEN].copy_from_slice(&random_nonce());
+        secret[NONCE_LEN..2 * NONCE_LEN].copy_from_slice(&random_nonce());
+        secret
+    })
+}
+
+/// Facts about the running process that the key is bound to
+fn environment_material() -> Vec<u8> {
+    let mut material = fs::read("/proc/sys/kernel/random/boot_id").unwrap_or_default();
+    material.extend_from_slice(&std::process::id().to_le_bytes());
+    // Field 22 of /proc/self/stat: start time in clock ticks since boot
+    if let Ok(stat) = fs::read_to_string("/proc/self/stat") {
+        if let Some(start) = stat.rsplit_once(')').and_then(|(_, rest)| rest.split_whitespace().nth(19)) {
+            material.extend_from_slice(start.as_bytes());
+        }
+    }
+    if let Ok(meta) = fs::metadata("/proc/self/exe") {
+        material.extend_from_slice(&meta.dev().to_le_bytes());
+        material.extend_from_slice(&meta.ino().to_le_bytes());
+    }
+    material
+}
+
+fn derive_key() -> [u8; KEY_LEN] {
+    sha256(&[b"secret-cell", process_secret(), &environment_material()])
+}
+
+/// Encrypted-at-rest value
+pub struct SecretCell<T: SecretBytes> {
+    nonce: [u8; NONCE_LEN],
+    ciphertext: Vec<u8>,
+    tag: [u8; DIGEST_LEN],
+    slow_windows: u32,
+    _value: std::marker::PhantomData<T>,
+}
+
+impl<T: SecretBytes> SecretCell<T> {
+    /// Encrypt `value`; the caller's copy is consumed
+    pub fn new(value: T) -> Self {
+        let mut cell = Self { nonce: [0; NONCE_LEN], ciphertext: Vec::new(), tag: [0; DIGEST_LEN], slow_windows: 0, _value: std::marker::PhantomData };
+        let mut plaintext = value.to_bytes();
+        drop(value);
+        cell.seal(&derive_key(), &mut plaintext);
+        cell
+    }
+
+    /// Encrypt `plaintext` in place into the cell, then wipe it
+    fn seal(&mut self, key: &[u8; KEY_LEN], plaintext: &mut [u8]) {
+        self.nonce = random_nonce();
+        let mut ciphertext = plaintext.to_vec();
+        wipe(plaintext);
+        apply_keystream(key, &self.nonce, 1, &mut ciphertext);
+        self.tag = hmac_sha256(key, &[&self.nonce[..], &ciphertext].concat());
+        self.ciphertext = ciphertext;
+    }
+
+    /// Decrypt, run `f` on the value, then wipe and re-encrypt
+    pub fn with<R>(&mut self, f: impl FnOnce(&T) -> R) -> Result<R, String> {
+        let key = derive_key();
+        let expected = hmac_sha256(&key, &[&self.nonce[..], &self.ciphertext].concat());
+        if !constant_time_eq(&expected, &self.tag) {
+            return Err("secret failed its integrity check (tampered, or key material changed)".to_string());
+        }
+
+        let opened = Instant::now();
+        let mut plaintext = self.ciphertext.clone();
+        apply_keystream(&key, &self.nonce, 1, &mut plaintext);
+        let result = T::from_bytes(&plaintext).map(|value| f(&value));
+        self.seal(&key, &mut plaintext);
+
+        let window = opened.elapsed();
+        if window > MAX_WINDOW {
+            self.slow_windows += 1;
+            diag!("[SECRET] Plaintext window lasted {} ms", window.as_millis());
+        }
+        result.ok_or_else(|| "secret bytes do not decode to the stored type".to_string())
+    }
+
+    /// Access windows that lasted longer than [`MAX_WINDOW`]
+    #[allow(dead_code)] // Public API for embedders
+    pub fn slow_windows(&self) -> u32 {
+        self.slow_windows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_and_reencryption() {
+        let mut cell = SecretCell::new(String::from("The answer is 42."));
+        assert!(!cell.ciphertext.windows(6).any(|w| w == b"answer"));
+        let before = cell.ciphertext.clone();
+        assert_eq!(cell.with(|s| s.len()), Ok(17));
+        assert_ne!(cell.ciphertext, before);
+        assert_eq!(cell.with(|s| s.clone()).as_deref(), Ok("The answer is 42."));
+        assert_eq!(cell.slow_windows(), 0);
+    }
+
+    #[test]
+    fn test_tampering_is_rejected() {
+        let mut cell = SecretCell::new(42u64);
+        cell.ciphertext[0] ^= 1;
+        assert!(cell.with(|v| *v).is_err());
+    }
+}
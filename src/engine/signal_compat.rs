@@ -12,6 +12,8 @@
 //! 1. Detect if a tracer is attached via /proc/self/status TracerPid
 //! 2. Provide configuration for graceful mode
 //! 3. Allow detectors to query tracer status before running destructive tests
+//! 4. Keep the startup value, so a tracer that attaches later can be told
+//!    apart from one that was there all along ([`refresh_tracer_pid`])
 
 use std::fs;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
@@ -22,6 +24,15 @@ static CACHED_TRACER_PID: AtomicU32 = AtomicU32::new(0);
 /// Whether we've checked for a tracer
 static TRACER_CHECKED: AtomicBool = AtomicBool::new(false);
 
+/// TracerPid when `init()` ran
+static STARTUP_TRACER_PID: AtomicU32 = AtomicU32::new(0);
+
+/// TracerPid at the last `tracer_transition()` call
+static LAST_SEEN_TRACER_PID: AtomicU32 = AtomicU32::new(0);
+
+/// Our parent, once our own PTRACE_TRACEME has made it our tracer
+static SELF_TRACER_PID: AtomicU32 = AtomicU32::new(0);
+
 /// Whether to run in GDB-compatible mode (skip destructive tests)
 static GDB_COMPAT_MODE: AtomicBool = AtomicBool::new(false);
 
@@ -59,6 +70,48 @@ fn read_tracer_pid_from_proc() -> u32 {
     0
 }
 
+/// Re-read TracerPid from /proc, bypassing and updating the cache.
+///
+/// Cheap enough to call repeatedly (one small /proc read); detectors that
+/// ask `get_tracer_pid()` afterwards see the fresh value.
+pub fn refresh_tracer_pid() -> u32 {
+    let pid = read_tracer_pid_from_proc();
+    CACHED_TRACER_PID.store(pid, Ordering::Relaxed);
+    TRACER_CHECKED.store(true, Ordering::Relaxed);
+    pid
+}
+
+/// TracerPid as seen by `init()` (0 if no tracer was attached at startup)
+pub fn startup_tracer_pid() -> u32 {
+    STARTUP_TRACER_PID.load(Ordering::Relaxed)
+}
+
+/// Re-read TracerPid and return `(previous, current)` if it changed since
+/// the last call (or since `init()`). Each change is returned to exactly
+/// one caller, so concurrent monitors do not report it twice.
+pub fn tracer_transition() -> Option<(u32, u32)> {
+    let current = refresh_tracer_pid();
+    let previous = LAST_SEEN_TRACER_PID.swap(current, Ordering::Relaxed);
+    (previous != current).then_some((previous, current))
+}
+
+/// Record that we are about to call PTRACE_TRACEME: from then on our
+/// parent may be our tracer, and that is not an attach. Marked before the
+/// call so the late-attach monitor never sees the parent unannounced.
+pub fn expect_traceme() {
+    SELF_TRACER_PID.store(std::os::unix::process::parent_id(), Ordering::Relaxed);
+}
+
+/// PTRACE_TRACEME failed: our parent did not become our tracer
+pub fn cancel_traceme() {
+    SELF_TRACER_PID.store(0, Ordering::Relaxed);
+}
+
+/// Parent PID we made our own tracer with PTRACE_TRACEME, or 0
+pub fn self_tracer_pid() -> u32 {
+    SELF_TRACER_PID.load(Ordering::Relaxed)
+}
+
 /// Returns true if a tracer (debugger/strace/ltrace) is attached.
 #[allow(dead_code)]
 pub fn has_tracer() -> bool {
@@ -88,14 +141,16 @@ pub fn is_gdb_compat_mode() -> bool {
 pub fn init() {
     // Check environment variable for explicit compat mode
     if std::env::var("ANTIDEBUG_GDB_COMPATIBLE").is_ok() {
-        eprintln!("[SIGNAL_COMPAT] GDB compatible mode enabled via environment");
+        diag!("[SIGNAL_COMPAT] GDB compatible mode enabled via environment");
         enable_gdb_compat_mode();
     }
     
-    // Pre-cache tracer status
+    // Pre-cache tracer status; later re-reads are compared against it
     let tracer = get_tracer_pid();
+    STARTUP_TRACER_PID.store(tracer, Ordering::Relaxed);
+    LAST_SEEN_TRACER_PID.store(tracer, Ordering::Relaxed);
     if tracer > 0 {
-        eprintln!("[SIGNAL_COMPAT] Tracer detected: PID {}", tracer);
+        diag!("[SIGNAL_COMPAT] Tracer detected: PID {}", tracer);
     }
 }
 
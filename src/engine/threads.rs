@@ -0,0 +1,302 @@
+//! Per-Thread Protection Hooks
+//!
+//! ptrace works on threads, not processes: `PTRACE_ATTACH` to one TID stops
+//! and traces that thread only. `/proc/self/status` describes the main
+//! thread, so a debugger attached to a single worker and single-stepping it
+//! is invisible to every whole-process, one-shot check.
+//!
+//! Worker threads register with [`register_thread`] and call
+//! [`ThreadGuard::checkpoint`] periodically. A checkpoint is cheap (one
+//! small /proc read and a ~2 ms spin) and looks at the calling thread only:
+//!
+//! - Its own `TracerPid` in `/proc/thread-self/status`
+//! - Thread CPU time accrued against wall time over the spin; a stepped
+//!   thread sits in TASK_TRACED and accrues almost none
+//!
+//! A supervising thread calls [`scan_threads`] to inspect every registered
+//! thread from outside (`/proc/self/task/<tid>/status`: tracer and
+//! `t (tracing stop)` state) and to move checkpoint findings into the
+//! engine. All evidence is attributed to the thread's registered name.
+//!
+//! Threads the framework starts for its own work (watchdog, heartbeat)
+//! hold an [`own_thread`] guard, so that together with the registered
+//! workers [`known_tids`] accounts for every thread we expect to exist.
+//! Background monitors start through [`spawn_masked`], which takes the
+//! guard and blocks every signal in the new thread.
+
+use std::fs;
+use std::io;
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+/// Wall-clock length of a checkpoint's busy spin
+const SPIN_NS: u64 = 2_000_000;
+
+/// CPU/wall ratio below which a checkpoint spin counts as stalled. Far below
+/// what contention alone produces over a few milliseconds.
+const STALLED_RATIO: f64 = 0.2;
+
+/// Registered threads (TID, name)
+static REGISTRY: Mutex<Vec<(i32, String)>> = Mutex::new(Vec::new());
+
+/// Threads the framework started for its own work (TIDs)
+static OWN_THREADS: Mutex<Vec<i32>> = Mutex::new(Vec::new());
+
+/// Checkpoint results waiting for [`scan_threads`]
+static FINDINGS: Mutex<Vec<ThreadFinding>> = Mutex::new(Vec::new());
+
+/// Evidence gathered on a worker thread, reported later by the supervisor
+#[derive(Debug, Clone)]
+pub struct ThreadFinding {
+    pub thread: String,
+    pub source: DetectionSource,
+    pub weight: u32,
+    pub confidence: f64,
+    pub details: String,
+}
+
+/// Fields of a task's `status` file that matter here
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaskStatus {
+    /// State letter ('R', 'S', 't' = tracing stop, 'T' = stopped, ...)
+    pub state: char,
+    pub tracer_pid: u32,
+}
+
+/// Parse `/proc/<pid>/task/<tid>/status`
+pub fn parse_task_status(status: &str) -> Option<TaskStatus> {
+    let mut state = None;
+    let mut tracer_pid = None;
+    for line in status.lines() {
+        if let Some(value) = line.strip_prefix("State:") {
+            state = value.trim().chars().next();
+        } else if let Some(value) = line.strip_prefix("TracerPid:") {
+            tracer_pid = value.trim().parse().ok();
+        }
+    }
+    Some(TaskStatus { state: state?, tracer_pid: tracer_pid? })
+}
+
+fn gettid() -> i32 {
+    // SAFETY: gettid takes no arguments and cannot fail
+    unsafe { libc::syscall(libc::SYS_gettid) as i32 }
+}
+
+fn clock_ns(clock: libc::clockid_t) -> u64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` is a valid out-pointer
+    unsafe { libc::clock_gettime(clock, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    // A panicking worker must not disable checks for everyone else
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Registration of the calling thread; deregisters on drop
+#[derive(Debug)]
+pub struct ThreadGuard {
+    tid: i32,
+    name: String,
+}
+
+/// Register the calling thread under `name` (used to attribute evidence)
+pub fn register_thread(name: &str) -> ThreadGuard {
+    let tid = gettid();
+    lock(&REGISTRY).push((tid, name.to_string()));
+    diag!("[THREADS] Registered '{}' (tid {})", name, tid);
+    ThreadGuard { tid, name: name.to_string() }
+}
+
+impl ThreadGuard {
+    #[allow(dead_code)] // Public API for embedders
+    pub fn tid(&self) -> i32 {
+        self.tid
+    }
+
+    /// Lightweight self-check; call it from the registered thread between
+    /// units of work. Returns `false` if anything was found.
+    pub fn checkpoint(&self) -> bool {
+        let mut findings = Vec::new();
+
+        if let Some(status) = fs::read_to_string("/proc/thread-self/status").ok().as_deref().and_then(parse_task_status) {
+            if status.tracer_pid != 0 {
+                findings.push(self.finding(DetectionSource::Ptrace, 50, 1.0,
+                    reason!("thread traced by PID {} (seen from the thread itself)", status.tracer_pid)));
+            }
+        }
+
+        let wall_start = clock_ns(libc::CLOCK_MONOTONIC);
+        let cpu_start = clock_ns(libc::CLOCK_THREAD_CPUTIME_ID);
+        let mut acc: u64 = 0;
+        while clock_ns(libc::CLOCK_MONOTONIC).saturating_sub(wall_start) < SPIN_NS {
+            for i in 0..100u64 {
+                acc = std::hint::black_box(acc.wrapping_add(i));
+            }
+        }
+        std::hint::black_box(acc);
+        let cpu = clock_ns(libc::CLOCK_THREAD_CPUTIME_ID).saturating_sub(cpu_start);
+        let wall = clock_ns(libc::CLOCK_MONOTONIC).saturating_sub(wall_start);
+        let ratio = cpu as f64 / wall.max(1) as f64;
+        if ratio < STALLED_RATIO {
+            findings.push(self.finding(DetectionSource::CpuAccounting, 25, 0.6,
+                reason!("checkpoint spin accrued {} us CPU in {} us wall (ratio {:.2}): thread stepped or stopped",
+                        cpu / 1000, wall / 1000, ratio)));
+        }
+
+        let clean = findings.is_empty();
+        lock(&FINDINGS).extend(findings);
+        clean
+    }
+
+    fn finding(&self, source: DetectionSource, weight: u32, confidence: f64, details: String) -> ThreadFinding {
+        diag!("[THREADS] '{}' (tid {}): {}", self.name, self.tid, details);
+        ThreadFinding { thread: self.name.clone(), source, weight, confidence, details }
+    }
+}
+
+impl Drop for ThreadGuard {
+    fn drop(&mut self) {
+        lock(&REGISTRY).retain(|(tid, _)| *tid != self.tid);
+    }
+}
+
+/// Marks the calling thread as the framework's own while it lives
+#[derive(Debug)]
+pub struct OwnThread {
+    tid: i32,
+}
+
+/// Mark the calling thread as started by the framework (not registered for
+/// checkpoints, but not a stranger either)
+pub fn own_thread() -> OwnThread {
+    let tid = gettid();
+    lock(&OWN_THREADS).push(tid);
+    OwnThread { tid }
+}
+
+impl Drop for OwnThread {
+    fn drop(&mut self) {
+        lock(&OWN_THREADS).retain(|tid| *tid != self.tid);
+    }
+}
+
+/// Start a named framework thread running `f` under an [`own_thread`]
+/// guard, with all signals blocked so that process-directed ones (the
+/// detectors' own probes) are delivered to other threads
+pub fn spawn_masked<F, T>(name: &str, f: F) -> io::Result<JoinHandle<T>>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    std::thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || {
+            let _own = own_thread();
+            // SAFETY: `set` is a stack-owned sigset_t
+            unsafe {
+                let mut set: libc::sigset_t = std::mem::zeroed();
+                libc::sigfillset(&mut set);
+                libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut());
+            }
+            f()
+        })
+}
+
+/// Every TID the framework accounts for: the main thread, its own threads
+/// and registered workers
+pub fn known_tids() -> Vec<i32> {
+    let mut tids = vec![std::process::id() as i32];
+    tids.extend(lock(&OWN_THREADS).iter());
+    tids.extend(lock(&REGISTRY).iter().map(|(tid, _)| *tid));
+    tids
+}
+
+/// Inspect every registered thread from the calling thread, then report all
+/// pending checkpoint findings. Returns the number of registered threads.
+pub fn scan_threads(engine: &mut DecisionEngine) -> usize {
+    let threads = lock(&REGISTRY).clone();
+    let process_tracer = fs::read_to_string("/proc/self/status").ok().as_deref()
+        .and_then(parse_task_status)
+        .map_or(0, |s| s.tracer_pid);
+
+    for (tid, name) in &threads {
+        let path = format!("/proc/self/task/{}/status", tid);
+        let Some(status) = fs::read_to_string(&path).ok().as_deref().and_then(parse_task_status) else {
+            diag!("[THREADS] '{}' (tid {}) has no readable status, skipping", name, tid);
+            continue;
+        };
+        diag!("[THREADS] '{}' (tid {}): state={} tracer={}", name, tid, status.state, status.tracer_pid);
+        if status.tracer_pid != 0 && process_tracer == 0 {
+            engine.report_for_thread(name, DetectionSource::Ptrace, 60, 1.0,
+                &reason!("thread {} traced by PID {} while the main thread is not: single-thread attach", tid, status.tracer_pid));
+        } else if status.tracer_pid != 0 {
+            engine.report_for_thread(name, DetectionSource::Ptrace, 40, 1.0,
+                &reason!("thread {} traced by PID {}", tid, status.tracer_pid));
+        }
+        if status.state == 't' {
+            engine.report_for_thread(name, DetectionSource::Ptrace, 40, 0.9,
+                &reason!("thread {} is in tracing stop", tid));
+        }
+    }
+
+    for finding in lock(&FINDINGS).drain(..) {
+        engine.report_for_thread(&finding.thread, finding.source, finding.weight, finding.confidence, &finding.details);
+    }
+    engine.record_feature("registered_threads", threads.len() as f64);
+    threads.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_task_status() {
+        let status = "Name:\tworker\nState:\tt (tracing stop)\nTgid:\t100\nPid:\t103\nTracerPid:\t4242\n";
+        assert_eq!(parse_task_status(status), Some(TaskStatus { state: 't', tracer_pid: 4242 }));
+        assert_eq!(parse_task_status("Name:\tworker\n"), None);
+    }
+
+    #[test]
+    fn test_scan_attributes_registered_threads() {
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+        let worker = std::thread::spawn(move || {
+            let guard = register_thread("test-worker");
+            guard.checkpoint();
+            ready_tx.send(guard.tid()).unwrap();
+            let _ = done_rx.recv();
+        });
+        let tid = ready_rx.recv().unwrap();
+
+        let mut engine = DecisionEngine::new();
+        assert!(scan_threads(&mut engine) >= 1);
+        assert!(lock(&REGISTRY).iter().any(|(t, name)| *t == tid && name == "test-worker"));
+        assert!(engine.get_history().iter().all(|e| e.thread.is_some()));
+
+        done_tx.send(()).unwrap();
+        worker.join().unwrap();
+        assert!(!lock(&REGISTRY).iter().any(|(t, _)| *t == tid));
+    }
+
+    #[test]
+    fn test_spawn_masked_blocks_signals() {
+        let (tid, name, usr1_blocked) = spawn_masked("masked-test", || {
+            // SAFETY: `set` is a stack-owned sigset_t; a null new set only queries
+            let blocked = unsafe {
+                let mut set: libc::sigset_t = std::mem::zeroed();
+                libc::pthread_sigmask(libc::SIG_BLOCK, std::ptr::null(), &mut set);
+                libc::sigismember(&set, libc::SIGUSR1) == 1
+            };
+            let tid = gettid();
+            assert!(lock(&OWN_THREADS).contains(&tid));
+            (tid, std::thread::current().name().map(str::to_string), blocked)
+        }).unwrap().join().unwrap();
+        assert_eq!(name.as_deref(), Some("masked-test"));
+        assert!(usr1_blocked);
+        assert!(!lock(&OWN_THREADS).contains(&tid));
+    }
+}
@@ -0,0 +1,128 @@
+//! Scan Timeline
+//!
+//! Evidence alone says what was found, not when. Every evidence carries a
+//! sequence number and the detector that reported it, and the engine
+//! records when each detector ran and for how long (`run_isolated` brackets
+//! every detector). Together they reconstruct the scan:
+//!
+//! ```text
+//! +   0.412 ms  run   premain::ingest_premain (0.031 ms)
+//! +  12.906 ms  run   timing::check_rdtsc_timing (3.882 ms)
+//! +  16.702 ms  #3    Timing +15 - RDTSC overhead elevated ...
+//! ```
+//!
+//! Offsets are monotonic (`Instant`) from the engine's creation. Monitors
+//! that observe an execution gap (the watchdog's stalls) ask which detectors
+//! were running during it, which says where in the scan the process was
+//! stopped.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+use crate::engine::policy::Evidence;
+
+/// One completed detector run
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectorRun {
+    pub name: String,
+    pub started: Instant,
+    pub runtime: Duration,
+}
+
+impl DetectorRun {
+    pub fn ended(&self) -> Instant {
+        self.started + self.runtime
+    }
+
+    /// Whether the run overlaps `from..to`
+    pub fn overlaps(&self, from: Instant, to: Instant) -> bool {
+        self.started <= to && self.ended() >= from
+    }
+}
+
+/// What happened at one point of the scan
+#[derive(Debug, Clone, Copy)]
+pub enum TimelineEvent<'a> {
+    Detector(&'a DetectorRun),
+    Evidence(&'a Evidence),
+}
+
+/// One event with its offset from the engine's creation
+#[derive(Debug, Clone, Copy)]
+pub struct TimelineEntry<'a> {
+    pub offset: Duration,
+    pub event: TimelineEvent<'a>,
+}
+
+/// Detector runs and evidence in the order they happened. A run sorts
+/// before evidence it reported at the same instant.
+pub fn build<'a>(origin: Instant, runs: &'a [DetectorRun], evidence: &'a [Evidence]) -> Vec<TimelineEntry<'a>> {
+    let mut entries: Vec<TimelineEntry> = runs.iter()
+        .map(|r| TimelineEntry { offset: r.started.saturating_duration_since(origin), event: TimelineEvent::Detector(r) })
+        .chain(evidence.iter().map(|e| TimelineEntry { offset: e.at.saturating_duration_since(origin), event: TimelineEvent::Evidence(e) }))
+        .collect();
+    // Stable: runs keep their order, evidence keeps its sequence
+    entries.sort_by_key(|entry| (entry.offset, matches!(entry.event, TimelineEvent::Evidence(_))));
+    entries
+}
+
+/// Milliseconds with microsecond resolution
+fn ms(d: Duration) -> String {
+    format!("{:.3} ms", d.as_secs_f64() * 1e3)
+}
+
+impl fmt::Display for TimelineEntry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let offset = format!("+{:>12}", ms(self.offset));
+        match self.event {
+            TimelineEvent::Detector(run) => write!(f, "{}  run   {} ({})", offset, run.name, ms(run.runtime)),
+            TimelineEvent::Evidence(e) => write!(f, "{}  {:<5} {:?} +{} - {}", offset, format!("#{}", e.seq), e.source, e.weight, e.details),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::isolation::run_isolated;
+    use crate::engine::policy::{DecisionEngine, DetectionSource};
+
+    #[test]
+    fn test_evidence_is_sequenced_and_attributed() {
+        let mut engine = DecisionEngine::new();
+        engine.report(DetectionSource::Ptrace, 40, "before any detector");
+        run_isolated(&mut engine, "slow", |e| {
+            std::thread::sleep(Duration::from_millis(5));
+            e.report(DetectionSource::Timing, 15, "slow block");
+        });
+        run_isolated(&mut engine, "quiet", |_| {});
+
+        let history = engine.get_history();
+        assert_eq!(history.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(history[0].detector, None);
+        assert_eq!(history[1].detector.as_deref(), Some("slow"));
+
+        let runs = engine.detector_runs();
+        assert_eq!(runs.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), vec!["slow", "quiet"]);
+        assert!(runs[0].runtime >= Duration::from_millis(5));
+        assert!(runs[1].started >= runs[0].ended());
+
+        let lines: Vec<String> = engine.timeline().iter().map(|e| e.to_string()).collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].contains("#1    Ptrace +40 - before any detector"));
+        assert!(lines[1].contains("run   slow ("));
+        assert!(lines[2].contains("#2    Timing +15 - slow block"));
+        assert!(lines[3].contains("run   quiet ("));
+    }
+
+    #[test]
+    fn test_detectors_during_gap() {
+        let mut engine = DecisionEngine::new();
+        run_isolated(&mut engine, "first", |_| std::thread::sleep(Duration::from_millis(5)));
+        let gap_start = Instant::now();
+        run_isolated(&mut engine, "second", |_| std::thread::sleep(Duration::from_millis(5)));
+        let gap_end = Instant::now();
+        run_isolated(&mut engine, "third", |_| {});
+        let during: Vec<&str> = engine.detectors_during(gap_start, gap_end).map(|r| r.name.as_str()).collect();
+        assert_eq!(during, vec!["second"]);
+    }
+}
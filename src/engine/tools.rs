@@ -0,0 +1,186 @@
+//! Tool Identification
+//!
+//! The verdict says *that* something is watching; the evidence pattern
+//! often says *what*. Each built-in fingerprint lists clues with weights,
+//! and a tool's confidence is the share of its clue weight the run
+//! matched:
+//!
+//! | Tool       | Strongest clues                                             |
+//! |------------|-------------------------------------------------------------|
+//! | `gdb`      | Named in evidence, interactive tracer, breakpoints          |
+//! | `rr`       | `RecordReplay` evidence, rr phase, named in evidence        |
+//! | `strace`   | Named in evidence, passive tracer, syscall interposition    |
+//! | `Frida`    | Named in evidence, Frida objects in maps or the loader list |
+//! | `Pin`      | Named in evidence, `Dbi` evidence                           |
+//! | `QEMU`     | Named in evidence, `Emulation` evidence                     |
+//! | `Valgrind` | Named in evidence, `Dbi` evidence                           |
+//!
+//! A name counts when it is a whole word of some evidence's details (the
+//! tracer's command line, a mapped path, an environment variable), so
+//! `pinned` is not Pin. Tools below [`MIN_CONFIDENCE`] are not listed.
+//! The ranking is printed in the summary and exported as the `tool_*`
+//! feature columns.
+
+use std::collections::HashMap;
+use crate::engine::features::FeatureVector;
+use crate::engine::policy::{DetectionSource, Evidence, TracerKind};
+
+/// Confidence below which a tool is not listed
+pub const MIN_CONFIDENCE: f64 = 0.2;
+
+/// Something a tool leaves in the evidence
+#[derive(Debug, Clone, Copy)]
+enum Clue {
+    /// One of these words appears in an evidence's details
+    Named(&'static [&'static str]),
+    /// The source carries weight
+    Source(DetectionSource),
+    /// The feature is above zero
+    Feature(&'static str),
+    /// `tracer_kind.rs` classified the tracer so
+    Tracer(TracerKind),
+}
+
+/// A tool's clues with their weights
+struct Fingerprint {
+    tool: &'static str,
+    /// Feature column exporting its confidence
+    column: &'static str,
+    clues: &'static [(Clue, u32)],
+}
+
+const FINGERPRINTS: &[Fingerprint] = &[
+    Fingerprint { tool: "gdb", column: "tool_gdb", clues: &[
+        (Clue::Named(&["gdb", "gdbserver"]), 40),
+        (Clue::Tracer(TracerKind::InteractiveDebugger), 25),
+        (Clue::Source(DetectionSource::Ptrace), 15),
+        (Clue::Source(DetectionSource::Int3), 10),
+        (Clue::Source(DetectionSource::HardwareBreakpoint), 10),
+        (Clue::Source(DetectionSource::TrapFlag), 5),
+        (Clue::Source(DetectionSource::RemoteDebug), 5),
+    ] },
+    Fingerprint { tool: "rr", column: "tool_rr", clues: &[
+        (Clue::Source(DetectionSource::RecordReplay), 40),
+        (Clue::Named(&["rr", "librrpreload"]), 30),
+        (Clue::Feature("rr_phase"), 20),
+        (Clue::Source(DetectionSource::CpuAccounting), 5),
+        (Clue::Source(DetectionSource::Ptrace), 5),
+    ] },
+    Fingerprint { tool: "strace", column: "tool_strace", clues: &[
+        (Clue::Named(&["strace"]), 40),
+        (Clue::Tracer(TracerKind::PassiveTracer), 30),
+        (Clue::Source(DetectionSource::SyscallInterposition), 15),
+        (Clue::Source(DetectionSource::Ptrace), 15),
+    ] },
+    Fingerprint { tool: "Frida", column: "tool_frida", clues: &[
+        (Clue::Named(&["frida", "gum", "gadget"]), 40),
+        (Clue::Feature("frida_maps_objects"), 20),
+        (Clue::Feature("frida_loaded_objects"), 20),
+        (Clue::Source(DetectionSource::Instrumentation), 15),
+        (Clue::Source(DetectionSource::CrossView), 5),
+    ] },
+    Fingerprint { tool: "Pin", column: "tool_pin", clues: &[
+        (Clue::Named(&["pin", "pinbin", "pintool"]), 45),
+        (Clue::Source(DetectionSource::Dbi), 35),
+        (Clue::Source(DetectionSource::Timing), 10),
+        (Clue::Source(DetectionSource::Integrity), 10),
+    ] },
+    Fingerprint { tool: "QEMU", column: "tool_qemu", clues: &[
+        (Clue::Named(&["qemu", "tcg"]), 45),
+        (Clue::Source(DetectionSource::Emulation), 40),
+        (Clue::Source(DetectionSource::Hypervisor), 15),
+    ] },
+    Fingerprint { tool: "Valgrind", column: "tool_valgrind", clues: &[
+        (Clue::Named(&["valgrind", "vgpreload", "memcheck"]), 50),
+        (Clue::Source(DetectionSource::Dbi), 35),
+        (Clue::Source(DetectionSource::Emulation), 15),
+    ] },
+];
+
+/// One identified tool
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolMatch {
+    pub tool: &'static str,
+    /// Share of the fingerprint's clue weight matched (0.0 - 1.0)
+    pub confidence: f64,
+}
+
+/// Lowercase alphanumeric words of `text`
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_ascii_lowercase)
+}
+
+/// Confidence of every fingerprint, in table order
+fn scores(evidence: &[Evidence], features: &FeatureVector, tracer: TracerKind) -> Vec<(&'static str, &'static str, f64)> {
+    let mut weights: HashMap<DetectionSource, u32> = HashMap::new();
+    for e in evidence {
+        *weights.entry(e.source).or_insert(0) += e.weight;
+    }
+    let named: Vec<String> = evidence.iter().flat_map(|e| words(&e.details)).collect();
+    let holds = |clue: &Clue| match clue {
+        Clue::Named(names) => named.iter().any(|w| names.contains(&w.as_str())),
+        Clue::Source(source) => weights.get(source).is_some_and(|w| *w > 0),
+        Clue::Feature(name) => features.get(name).is_some_and(|v| v > 0.0),
+        Clue::Tracer(kind) => tracer == *kind,
+    };
+    FINGERPRINTS.iter()
+        .map(|f| {
+            let total: u32 = f.clues.iter().map(|(_, w)| w).sum();
+            let matched: u32 = f.clues.iter().filter(|(clue, _)| holds(clue)).map(|(_, w)| w).sum();
+            (f.tool, f.column, matched as f64 / total as f64)
+        })
+        .collect()
+}
+
+/// Likely tools, most likely first
+pub fn identify(evidence: &[Evidence], features: &FeatureVector, tracer: TracerKind) -> Vec<ToolMatch> {
+    let mut matches: Vec<ToolMatch> = scores(evidence, features, tracer).into_iter()
+        .filter(|(.., confidence)| *confidence >= MIN_CONFIDENCE)
+        .map(|(tool, _, confidence)| ToolMatch { tool, confidence })
+        .collect();
+    matches.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    matches
+}
+
+/// Set every tool's `tool_*` feature column to its confidence
+pub fn record_tool_features(fv: &mut FeatureVector, evidence: &[Evidence], tracer: TracerKind) {
+    for (_, column, confidence) in scores(evidence, fv, tracer) {
+        fv.set(column, confidence);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::policy::DecisionEngine;
+
+    #[test]
+    fn test_words() {
+        assert_eq!(words("Tracer PID 7 (strace -f ./app)").collect::<Vec<_>>(), vec!["tracer", "pid", "7", "strace", "f", "app"]);
+    }
+
+    #[test]
+    fn test_gdb_outranks_strace() {
+        let mut engine = DecisionEngine::new();
+        engine.set_tracer_kind(TracerKind::InteractiveDebugger);
+        engine.report(DetectionSource::Ptrace, 60, "Tracer PID 7 (gdb -q ./app) is InteractiveDebugger");
+        engine.report(DetectionSource::Int3, 30, "0xCC in .text");
+        let tools = engine.identify_tools();
+        assert_eq!(tools[0].tool, "gdb");
+        assert!((tools[0].confidence - 90.0 / 110.0).abs() < 1e-9);
+        assert!(tools.iter().all(|t| t.tool == "gdb" || t.confidence < tools[0].confidence));
+        assert_eq!(engine.feature_vector().get("tool_gdb"), Some(tools[0].confidence));
+    }
+
+    #[test]
+    fn test_words_not_substrings() {
+        let mut engine = DecisionEngine::new();
+        engine.report(DetectionSource::Timing, 20, "Could not keep the thread pinned");
+        assert!(engine.identify_tools().is_empty());
+        engine.report(DetectionSource::Dbi, 40, "Code cache of /opt/pin/intel64/bin/pinbin mapped");
+        assert_eq!(engine.identify_tools()[0].tool, "Pin");
+        assert!((engine.identify_tools()[0].confidence - 0.9).abs() < 1e-9);
+    }
+}
@@ -36,4 +36,40 @@ extern "C" {
     
     /// Measures timing of conditional branch loop for single-step amplification.
     pub fn measure_single_step_amplification() -> u64;
+    
+    /// Runs a fixed-cost loop (4 instructions, ~2 cycles per iteration natively).
+    /// Returns the accumulator so the loop cannot be elided.
+    pub fn calibrated_work_loop(iterations: u64) -> u64;
+    
+    /// Returns XCR0 via XGETBV. Requires CPUID.1:ECX.OSXSAVE, raises #UD otherwise.
+    pub fn read_xcr0() -> u64;
+    
+    /// Loads YMM15 from `pattern`, sends `sig` to (tgid, tid) via raw tgkill,
+    /// then stores YMM15 to `out` after the handler returns.
+    pub fn ymm_signal_roundtrip(pattern: *const u64, out: *mut u64, tgid: i32, tid: i32, sig: i32);
+    
+    /// Zeroes YMM15. Called from signal handlers to test state restoration.
+    pub fn clobber_ymm15();
+    
+    /// Computes (1 + 2^-60) - 1 on the x87 stack. 2^-60 on real hardware.
+    pub fn x87_extended_residual() -> f64;
+    
+    /// Reads the SSE control/status register.
+    pub fn get_mxcsr() -> u32;
+    
+    /// Writes the SSE control/status register.
+    pub fn set_mxcsr(value: u32);
+    
+    /// Loads `value` into the x87 control word and returns what reads back.
+    pub fn x87_control_word_roundtrip(value: u16) -> u16;
+    
+    /// Runs the CPU-semantics edge cases, one result per slot in `out[15]`.
+    pub fn cpu_semantics_probe(out: *mut u64);
+    
+    /// Arithmetic flags left by POPCNT after all were set (0 on hardware).
+    pub fn popcnt_flags_probe() -> u64;
+    
+    /// Issues syscall `nr` with the SYSCALL instruction, bypassing libc.
+    /// Returns the kernel's result (-errno on failure).
+    pub fn raw_syscall4(nr: i64, a1: i64, a2: i64, a3: i64, a4: i64) -> i64;
 }
@@ -1,16 +1,259 @@
 mod ffi;
+#[macro_use]
 mod engine;
 mod detectors;
 
+use detectors::attestation::{Heartbeat, KEY_ENV_VAR as ATTEST_KEY_ENV_VAR};
+use detectors::clock_jump::ClockJumpMonitor;
+use detectors::stop_history::StopMonitor;
+use detectors::watchdog::Watchdog;
+use engine::baseline::{load_baseline, Baseline, ADAPT_ALPHA, ADAPT_ENV_VAR, BASELINE_ENV_VAR, CALIBRATION_RUNS};
+use engine::bayes::{BayesianClassifier, ScoringMode};
+use engine::decay::{DecayPolicy, DECAY_ENV_VAR};
 use engine::environment::EnvironmentState;
+use engine::interleave::Scheduler;
+use engine::isolation::run_isolated;
+use engine::limits::SOURCE_CAPS_ENV_VAR;
+use engine::log::{EncryptedSink, LOG_FILE_ENV_VAR, LOG_KEY_ENV_VAR};
+use engine::model::{load_model, MODEL_ENV_VAR};
 use engine::policy::{DecisionEngine, Verdict};
-use engine::responses::apply_response;
+use engine::presets::{PolicyConfig, Preset, SilencedOutput, Verbosity, PRESET_ENV_VAR, SANDBOX_ENV_VAR, SANDBOX_PROFILE_ENV_VAR, HOST_SCAN_ENV_VAR, SCORING_ENV_VAR};
+use engine::responses::apply_response_mode;
+use engine::rules::{load_rules, RULES_ENV_VAR};
+use engine::secret::SecretCell;
+
+/// Command-line options
+struct CliOptions {
+    /// `--features <path>`: append the run's feature vector as CSV ("-" = stdout)
+    features_out: Option<String>,
+    /// `--model <path>`: classifier model file (overrides ANTIDEBUG_MODEL)
+    model: Option<String>,
+    /// `--preset <name>`: paranoid / balanced / stealthy (overrides ANTIDEBUG_PRESET)
+    preset: Option<String>,
+    /// `--html <path>`: write a self-contained HTML report of the run
+    html_out: Option<String>,
+    /// `--decrypt-log <path>`: print an encrypted diagnostic log and exit
+    decrypt_log: Option<String>,
+    /// `--serve-attestation <addr>`: run a time-attestation server
+    serve_attestation: Option<String>,
+    /// `--interleaved`: run detectors between units of payload work
+    interleaved: bool,
+    /// `--sandbox-checks`: also look for malware-sandbox artifacts
+    sandbox_checks: bool,
+    /// `--sandbox-profile`: also score the machine for sandbox hardware tells
+    sandbox_profile: bool,
+    /// `--host-scan`: also scan every process on the host for analysis daemons
+    host_scan: bool,
+    /// `--scoring <mode>`: additive / bayesian (overrides ANTIDEBUG_SCORING)
+    scoring: Option<String>,
+    /// `--source-caps <spec>`: per-source cap overrides (overrides ANTIDEBUG_SOURCE_CAPS)
+    source_caps: Option<String>,
+    /// `--decay-half-life <secs>`: let late-run evidence decay (overrides ANTIDEBUG_DECAY_HALF_LIFE)
+    decay_half_life: Option<String>,
+    /// `--contradiction-rules <path>`: extra contradiction rules (overrides ANTIDEBUG_CONTRADICTION_RULES)
+    contradiction_rules: Option<String>,
+    /// `--calibrate <path>`: measure this clean host's timing baseline and exit
+    calibrate: Option<String>,
+    /// `--baseline <path>`: per-machine timing baseline (overrides ANTIDEBUG_BASELINE)
+    baseline: Option<String>,
+    /// `--adapt-baseline`: fold clean runs into the baseline file
+    adapt_baseline: bool,
+    /// `--explain`: print what the verdict rests on after the summary
+    explain: bool,
+}
+
+impl CliOptions {
+    fn parse() -> Self {
+        let mut opts = Self { features_out: None, model: None, preset: None, html_out: None, decrypt_log: None, serve_attestation: None, interleaved: false, sandbox_checks: false, sandbox_profile: false, host_scan: false, scoring: None, source_caps: None, decay_half_life: None, contradiction_rules: None, calibrate: None, baseline: None, adapt_baseline: false, explain: false };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--features" => opts.features_out = args.next(),
+                "--model" => opts.model = args.next(),
+                "--preset" => opts.preset = args.next(),
+                "--html" => opts.html_out = args.next(),
+                "--decrypt-log" => opts.decrypt_log = args.next(),
+                "--serve-attestation" => opts.serve_attestation = args.next(),
+                "--interleaved" => opts.interleaved = true,
+                "--sandbox-checks" => opts.sandbox_checks = true,
+                "--sandbox-profile" => opts.sandbox_profile = true,
+                "--host-scan" => opts.host_scan = true,
+                "--scoring" => opts.scoring = args.next(),
+                "--source-caps" => opts.source_caps = args.next(),
+                "--decay-half-life" => opts.decay_half_life = args.next(),
+                "--contradiction-rules" => opts.contradiction_rules = args.next(),
+                "--calibrate" => opts.calibrate = args.next(),
+                "--baseline" => opts.baseline = args.next(),
+                "--adapt-baseline" => opts.adapt_baseline = true,
+                "--explain" => opts.explain = true,
+                other => diag!("[CLI] Ignoring unknown argument: {}", other),
+            }
+        }
+        opts
+    }
+}
+
+/// Key for the encrypted log channel, if one was supplied
+fn log_key() -> Option<[u8; engine::chacha20::KEY_LEN]> {
+    let hex = std::env::var(LOG_KEY_ENV_VAR).ok()?;
+    match engine::log::parse_key(&hex) {
+        Ok(key) => Some(key),
+        Err(e) => {
+            diag!("[LOG] Ignoring {}: {}", LOG_KEY_ENV_VAR, e);
+            None
+        }
+    }
+}
+
+/// `--decrypt-log`: print every message in an encrypted log
+fn decrypt_log(path: &str) -> i32 {
+    let Some(key) = log_key() else {
+        diag!("[LOG] {} must hold the log's key", LOG_KEY_ENV_VAR);
+        return 2;
+    };
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) => {
+            diag!("[LOG] Cannot read {}: {}", path, e);
+            return 1;
+        }
+    };
+    match engine::log::decrypt_blobs(&key, &data) {
+        Ok(messages) => {
+            for message in messages {
+                println!("{}", message);
+            }
+            0
+        }
+        Err(e) => {
+            diag!("[LOG] {}: {}", path, e);
+            1
+        }
+    }
+}
+
+/// `--serve-attestation`: answer heartbeats keyed with ANTIDEBUG_ATTEST_KEY
+fn serve_attestation(bind: &str) -> i32 {
+    let key = match engine::log::parse_key(&std::env::var(ATTEST_KEY_ENV_VAR).unwrap_or_default()) {
+        Ok(key) => key,
+        Err(e) => {
+            diag!("[ATTEST] {} must hold the shared key: {}", ATTEST_KEY_ENV_VAR, e);
+            return 2;
+        }
+    };
+    match detectors::attestation::serve(bind, &key) {
+        Ok(()) => 0,
+        Err(e) => {
+            diag!("[ATTEST] {}: {}", bind, e);
+            1
+        }
+    }
+}
+
+/// `--calibrate`: run the timing-based detectors on this (clean) host and
+/// write their metrics' statistics as a baseline file
+fn calibrate(path: &str) -> i32 {
+    let mut runs = Vec::with_capacity(CALIBRATION_RUNS);
+    for run in 1..=CALIBRATION_RUNS {
+        let mut engine = DecisionEngine::new();
+        run_isolated(&mut engine, "timing::check_rdtsc_timing", detectors::timing::check_rdtsc_timing);
+        run_isolated(&mut engine, "jitter::check_instruction_jitter", detectors::jitter::check_instruction_jitter);
+        run_isolated(&mut engine, "hardware_bp::check_hardware_breakpoints", detectors::hardware_bp::check_hardware_breakpoints);
+        run_isolated(&mut engine, "syscall_paths::check_syscall_paths", detectors::syscall_paths::check_syscall_paths);
+        if engine.decide() != Verdict::Clean {
+            diag!("[BASELINE] Warning: calibration run {} scored {} - is this host really clean?", run, engine.get_score());
+        }
+        runs.push(engine.feature_vector());
+    }
+    let baseline = Baseline::from_runs(&runs);
+    match std::fs::write(path, baseline.to_string()) {
+        Ok(()) => {
+            diag!("[BASELINE] Wrote {} metric(s) over {} run(s) to {}", baseline.len(), baseline.runs, path);
+            0
+        }
+        Err(e) => {
+            diag!("[BASELINE] Cannot write {}: {}", path, e);
+            1
+        }
+    }
+}
 
 fn main() {
-    println!("==================================================");
-    println!("    Anti-Debug / Anti-Instrumentation Framework   ");
-    println!("         Phase 2: Research-Grade System           ");
-    println!("==================================================");
+    let opts = CliOptions::parse();
+    if let Some(path) = &opts.decrypt_log {
+        std::process::exit(decrypt_log(path));
+    }
+    if let Some(bind) = &opts.serve_attestation {
+        std::process::exit(serve_attestation(bind));
+    }
+    if let Some(path) = &opts.calibrate {
+        std::process::exit(calibrate(path));
+    }
+    
+    // Embedder-keyed log channel: diagnostics become opaque blobs
+    if let (Some(key), Ok(path)) = (log_key(), std::env::var(LOG_FILE_ENV_VAR)) {
+        match EncryptedSink::open(key, std::path::Path::new(&path)) {
+            Ok(sink) => engine::log::install_sink(sink),
+            Err(e) => diag!("[LOG] Cannot open {}: {}", path, e),
+        }
+    }
+    
+    // The payload's secret stays encrypted in memory until it is used
+    let mut secret = SecretCell::new(String::from("The answer is 42."));
+    
+    let preset_name = opts.preset.clone().or_else(|| std::env::var(PRESET_ENV_VAR).ok());
+    let mut policy = Preset::resolve(preset_name.as_deref()).config();
+    policy.sandbox_checks |= opts.sandbox_checks || std::env::var(SANDBOX_ENV_VAR).is_ok_and(|v| v == "1");
+    policy.sandbox_profile |= policy.sandbox_checks || opts.sandbox_profile || std::env::var(SANDBOX_PROFILE_ENV_VAR).is_ok_and(|v| v == "1");
+    policy.host_scan |= opts.host_scan || std::env::var(HOST_SCAN_ENV_VAR).is_ok_and(|v| v == "1");
+    if let Some(name) = opts.scoring.clone().or_else(|| std::env::var(SCORING_ENV_VAR).ok()) {
+        match ScoringMode::from_name(&name) {
+            Some(mode) => policy.scoring = mode,
+            None => diag!("[CLI] Unknown scoring mode '{}', keeping {:?}", name, policy.scoring),
+        }
+    }
+    if let Some(spec) = opts.source_caps.clone().or_else(|| std::env::var(SOURCE_CAPS_ENV_VAR).ok()) {
+        let mut limits = policy.limits;
+        match limits.apply_overrides(&spec) {
+            Ok(()) => policy.limits = limits,
+            Err(e) => diag!("[CLI] Ignoring source caps '{}': {}", spec, e),
+        }
+    }
+    if let Some(secs) = opts.decay_half_life.clone().or_else(|| std::env::var(DECAY_ENV_VAR).ok()) {
+        match DecayPolicy::parse(&secs) {
+            Ok(decay) => policy.decay = decay,
+            Err(e) => diag!("[CLI] Ignoring decay half-life: {}", e),
+        }
+    }
+    
+    // Silent presets keep every diagnostic off stdout/stderr until the payload
+    let mut silence = match policy.verbosity {
+        Verbosity::Silent => Some(SilencedOutput::new()),
+        Verbosity::Normal => None,
+    };
+    
+    let mut engine = DecisionEngine::new();
+    engine.set_source_limits(policy.limits);
+    engine.set_decay_policy(policy.decay);
+    
+    // Snapshot taken by the .init_array constructor before main()
+    run_isolated(&mut engine, "premain::ingest_premain", detectors::premain::ingest_premain);
+    
+    // Stop narrating our checks if the output is being recorded
+    let mut captured = false;
+    run_isolated(&mut engine, "output_capture::check_output_capture", |e| {
+        captured = detectors::output_capture::check_output_capture(e);
+    });
+    // `--features -` keeps stdout for the CSV alone
+    let csv_to_stdout = opts.features_out.as_deref() == Some("-");
+    if (captured || csv_to_stdout) && silence.is_none() {
+        silence = Some(SilencedOutput::new());
+    }
+    
+    banner!("==================================================");
+    banner!("    Anti-Debug / Anti-Instrumentation Framework   ");
+    banner!("         Phase 2: Research-Grade System           ");
+    banner!("==================================================");
     
     // ===================================================================
     // SIGNAL COMPATIBILITY INIT (Run first for GDB coexistence)
@@ -22,72 +265,82 @@ fn main() {
     // ENVIRONMENT DETECTION (Run first to inform adjustments)
     // ===================================================================
     
-    println!("\n[*] Phase 0: Environment Detection");
+    banner!("\n[*] Phase 0: Environment Detection");
     let env_state = EnvironmentState::detect();
     env_state.print_summary();
+    env_state.record_features(&mut engine);
+    engine.set_timing_confidence(env_state.timing_confidence);
+    engine.set_timing_tolerance(env_state.timing_tolerance);
+    engine.set_syscall_tolerance(env_state.syscall_tolerance);
+    // This machine's calibrated timing replaces the universal thresholds
+    let baseline_path = opts.baseline.clone().or_else(|| std::env::var(BASELINE_ENV_VAR).ok());
+    if let Some(baseline) = baseline_path.as_deref().and_then(|path| load_baseline(std::path::Path::new(path))) {
+        engine.set_baseline(baseline);
+    }
+    engine.set_kernel_posture(env_state.posture.clone());
     
-    let mut engine = DecisionEngine::new();
-    
-    // ===================================================================
-    // PHASE 1 DETECTIONS (Original)
-    // ===================================================================
-    
-    // 1. Check Timing (Enhanced with statistical analysis)
-    println!("\n[*] Phase 1.1: Statistical Timing Analysis (RDTSC)");
-    detectors::timing::check_rdtsc_timing(&mut engine);
-    
-    // 2. Check Int3
-    println!("\n[*] Phase 1.2: Memory Integrity (INT3 Scanning)");
-    detectors::int3::check_int3_scanning(&mut engine);
+    banner!("[*] Policy preset: {}", policy.preset.name());
+    match policy.scoring {
+        ScoringMode::Additive => engine.set_classifier(Box::new(policy.thresholds)),
+        ScoringMode::Bayesian => engine.set_classifier(Box::new(BayesianClassifier::default())),
+    }
     
-    // 3. Check Trap Flag
-    // Note: This relies on SIGTRAP. Run before ptrace check.
-    println!("\n[*] Phase 1.3: CPU Exception Handling (Trap Flag)");
-    detectors::trap_flag::check_trap_flag(&mut engine);
+    // Optional trained model; the preset's classifier remains the fallback
+    let model_path = opts.model.clone().or_else(|| std::env::var(MODEL_ENV_VAR).ok());
+    if let Some(path) = model_path {
+        if let Some(model) = load_model(std::path::Path::new(&path)) {
+            engine.set_classifier(model);
+        }
+    }
     
-    // ===================================================================
-    // PHASE 2 DETECTIONS (New Elite Extensions)
-    // ===================================================================
+    // Researchers' correlation rules on top of the built-in ones
+    let rules_path = opts.contradiction_rules.clone().or_else(|| std::env::var(RULES_ENV_VAR).ok());
+    if let Some(rules) = rules_path.and_then(|path| load_rules(std::path::Path::new(&path))) {
+        engine.add_contradiction_rules(rules);
+    }
     
-    // 4. Hardware Breakpoint Detection (DR0-DR7)
-    println!("\n[*] Phase 2.1: Hardware Breakpoint Detection (DR0-DR7)");
-    detectors::hardware_bp::check_hardware_breakpoints(&mut engine);
+    // Timer watchdog runs in the background for the whole analysis
+    let watchdog = Watchdog::start(detectors::watchdog::INTERVAL);
     
-    // 5. Single-Instruction Timing Jitter Analysis
-    println!("\n[*] Phase 2.2: Instruction-Level Jitter Analysis");
-    detectors::jitter::check_instruction_jitter(&mut engine);
+    // TracerPid poller for debuggers that attach after the scan has looked
+    let _tracer_monitor = detectors::late_attach::TracerMonitor::start(detectors::late_attach::INTERVAL);
     
-    // 6. Record & Replay Detection (rr-class)
-    println!("\n[*] Phase 2.3: Record & Replay Detection (rr-class)");
-    detectors::record_replay::check_record_replay(&mut engine);
+    // Realtime vs monotonic sampler (pause-and-resync) for the whole analysis
+    let clock_jump = ClockJumpMonitor::start(detectors::clock_jump::INTERVAL);
     
-    // 7. eBPF Observer Comparison
-    println!("\n[*] Phase 2.4: eBPF Observer Comparison");
-    detectors::ebpf_compare::check_ebpf_availability();
-    detectors::ebpf_compare::check_ebpf_comparison(&mut engine);
+    // Thread-state / schedstat sampler for stops between the checks
+    let stop_history = StopMonitor::start(detectors::stop_history::INTERVAL);
     
-    // ===================================================================
-    // PTRACE DETECTION (Run last - modifies process state)
-    // ===================================================================
+    // Optional remote time attestation (ANTIDEBUG_ATTEST_SERVER / _KEY)
+    let heartbeat = Heartbeat::from_env();
     
-    // 8. Check Ptrace (Baseline) - run last as PTRACE_TRACEME changes state
-    println!("\n[*] Phase 3: Ptrace Detection");
-    detectors::ptrace::check_tracer_pid(&mut engine);
-    detectors::ptrace::check_ptrace(&mut engine);
+    // Detectors are queued as slices: drained here in one scan (the default),
+    // or woven between units of payload work with `--interleaved`
+    let mut scheduler = Scheduler::new(!opts.interleaved);
+    scheduler.set_hook(detectors::cpu_beacon::on_slice);
+    schedule_detectors(&policy, &mut scheduler, watchdog, heartbeat, clock_jump, stop_history);
+    if opts.interleaved {
+        banner!("\n[*] Interleaving {} detector slices with payload work", scheduler.pending());
+        interleaved_work(&mut engine, &mut scheduler);
+    } else {
+        scheduler.drain(&mut engine);
+    }
     
     // ===================================================================
     // CORRELATION ANALYSIS
     // ===================================================================
     
-    println!("\n[*] Phase 4: Cross-Technique Correlation");
+    banner!("\n[*] Phase 4: Cross-Technique Correlation");
     engine.analyze_contradictions();
     
     // ===================================================================
     // ENVIRONMENTAL ADJUSTMENT
     // ===================================================================
     
-    println!("\n[*] Phase 5: Environmental Adjustment");
+    banner!("\n[*] Phase 5: Environmental Adjustment");
     engine.apply_environmental_adjustment(env_state.adjustment_factor);
+    // Anything reported from here on is late evidence
+    engine.close_scan();
     
     // ===================================================================
     // FINAL VERDICT
@@ -95,35 +348,442 @@ fn main() {
     
     let verdict = engine.decide();
     let score = engine.get_score();
+    // Re-checked immediately before the payload runs
+    let seal = engine::reverify::seal(&engine);
     
-    println!("\n==================================================");
-    println!("[*] Analysis complete. Cumulative Score: {}", score);
-    println!("[*] Final Verdict: {:?}", verdict);
-    println!("==================================================");
+    banner!("\n==================================================");
+    banner!("[*] Analysis complete. Cumulative Score: {}", score);
+    banner!("[*] Final Verdict: {:?}", verdict);
+    banner!("==================================================");
     
     // Print detailed summary
-    println!("\n{}", engine.summary());
+    banner!("\n{}", engine.summary());
+    if opts.explain {
+        banner!("{}", engine.explain());
+    }
+    
+    // Export feature vector and report before the response (which may exit)
+    if let Some(ref out) = opts.features_out {
+        export_features(&engine, out, silence.as_ref());
+    }
+    
+    if let Some(ref out) = opts.html_out {
+        match engine::report::write_html(std::path::Path::new(out), &engine, &env_state) {
+            Ok(()) => diag!("[REPORT] Wrote HTML report to {}", out),
+            Err(e) => diag!("[REPORT] Failed to write {}: {}", out, e),
+        }
+    }
+    
+    // Clean runs keep the baseline tracking this machine
+    let adapt = opts.adapt_baseline || std::env::var(ADAPT_ENV_VAR).is_ok_and(|v| v == "1");
+    if let (true, Some(path), Some(baseline)) = (adapt, &baseline_path, engine.adapted_baseline(ADAPT_ALPHA)) {
+        match std::fs::write(path, baseline.to_string()) {
+            Ok(()) => diag!("[BASELINE] Folded this run into {} ({} run(s))", path, baseline.runs),
+            Err(e) => diag!("[BASELINE] Cannot update {}: {}", path, e),
+        }
+    }
     
     // Apply response
-    apply_response(verdict);
+    apply_response_mode(policy.response, verdict, Some(report_verdict));
+    
+    // The payload's own output is never silenced, unless stdout belongs to
+    // the CSV: then everything after it stays muted until exit
+    if !csv_to_stdout {
+        drop(silence);
+    }
     
     // If we survived, run the "payload"
     match verdict {
         Verdict::Clean => {
-            println!("\n[+] System integrity verified. Executing protected payload.");
-            payload();
+            banner!("\n[+] System integrity verified. Executing protected payload.");
+            run_payload(&mut engine, &seal, &mut secret);
         }
         Verdict::Suspicious => {
-            println!("\n[!] Suspicious environment detected. Proceeding with caution.");
-            payload();
+            banner!("\n[!] Suspicious environment detected. Proceeding with caution.");
+            run_payload(&mut engine, &seal, &mut secret);
         }
         _ => {
-            println!("\n[!] Integrity verification failed. Access denied.");
+            banner!("\n[!] Integrity verification failed. Access denied.");
+        }
+    }
+}
+
+/// Queue every detector in run order. Phase banners are printed only when
+/// the queue is drained upfront.
+fn schedule_detectors(policy: &PolicyConfig, scheduler: &mut Scheduler, watchdog: Option<Watchdog>, heartbeat: Option<Heartbeat>, clock_jump: Option<ClockJumpMonitor>, stop_history: Option<StopMonitor>) {
+    // ===================================================================
+    // PHASE 1 DETECTIONS (Original)
+    // ===================================================================
+    
+    // Every slice runs through `run_isolated`: a panic in one is recorded
+    // as a diagnostic and the remaining detectors still run.
+    
+    // 1. Check Timing (Enhanced with statistical analysis)
+    scheduler.add(Some(described!("[*] Phase 1.1: Statistical Timing Analysis (RDTSC)")), "timing::check_rdtsc_timing", detectors::timing::check_rdtsc_timing);
+    
+    // 2. Check Int3
+    scheduler.add(Some(described!("[*] Phase 1.2: Memory Integrity (INT3 Scanning)")), "int3::check_int3_scanning", detectors::int3::check_int3_scanning);
+    
+    // 3. Check Trap Flag
+    // Note: This relies on SIGTRAP. Run before ptrace check.
+    add_intrusive(policy, scheduler, Some(described!("[*] Phase 1.3: CPU Exception Handling (Trap Flag)")), "trap_flag::check_trap_flag", detectors::trap_flag::check_trap_flag);
+    
+    // ===================================================================
+    // PHASE 2 DETECTIONS (New Elite Extensions)
+    // ===================================================================
+    
+    // 4. Hardware Breakpoint Detection (DR0-DR7)
+    add_intrusive(policy, scheduler, Some(described!("[*] Phase 2.1: Hardware Breakpoint Detection (DR0-DR7)")), "hardware_bp::check_hardware_breakpoints", detectors::hardware_bp::check_hardware_breakpoints);
+    
+    // 5. Single-Instruction Timing Jitter Analysis
+    scheduler.add(Some(described!("[*] Phase 2.2: Instruction-Level Jitter Analysis")), "jitter::check_instruction_jitter", detectors::jitter::check_instruction_jitter);
+    
+    // 6. Record & Replay Detection (rr-class)
+    scheduler.add(Some(described!("[*] Phase 2.3: Record & Replay Detection (rr-class)")), "record_replay::check_record_replay", detectors::record_replay::check_record_replay);
+    add_intrusive(policy, scheduler, None, "record_replay::check_signal_determinism", detectors::record_replay::check_signal_determinism);
+    
+    // 7. eBPF Observer Comparison
+    scheduler.add(Some(described!("[*] Phase 2.4: eBPF Observer Comparison")), "ebpf_compare::check_ebpf_comparison", |e| {
+        detectors::ebpf_compare::check_ebpf_availability();
+        detectors::ebpf_compare::check_ebpf_comparison(e);
+    });
+    
+    // 8. Environment cross-view (environ vs /proc/self/environ)
+    scheduler.add(Some(described!("[*] Phase 2.5: Environment Cross-View (environ vs /proc)")), "environ::check_environ_divergence", detectors::environ::check_environ_divergence);
+    
+    // 9. Auxiliary vector consistency (getauxval vs /proc/self/auxv vs CPUID/maps)
+    scheduler.add(Some(described!("[*] Phase 2.6: Auxiliary Vector Consistency")), "auxv::check_auxv_consistency", detectors::auxv::check_auxv_consistency);
+    
+    // 10. Kernel CPU-time accounting (thread CPU vs wall, process CPU drift)
+    scheduler.add(Some(described!("[*] Phase 2.7: Kernel CPU-Time Accounting")), "cpu_time::check_thread_cpu_time", detectors::cpu_time::check_thread_cpu_time);
+    scheduler.add(None, "cpu_time::check_process_cpu_drift", detectors::cpu_time::check_process_cpu_drift);
+    
+    // 11. Extended state consistency (XCR0 vs CPUID vs signal-frame XSAVE)
+    add_intrusive(policy, scheduler, Some(described!("[*] Phase 2.8: XGETBV/XSAVE State Consistency")), "xstate::check_xstate_consistency", detectors::xstate::check_xstate_consistency);
+    
+    // 12. Illegal-instruction fault semantics (SIGILL/SIGSEGV corner cases)
+    add_intrusive(policy, scheduler, Some(described!("[*] Phase 2.9: Illegal-Instruction Semantics")), "illegal_insn::check_illegal_instruction_semantics", detectors::illegal_insn::check_illegal_instruction_semantics);
+    
+    // 13. x87/SSE numeric edge cases (timing-independent emulation signal)
+    scheduler.add(Some(described!("[*] Phase 2.10: x87/SSE Numeric Fingerprint")), "fpu::check_fpu_fingerprint", detectors::fpu::check_fpu_fingerprint);
+    
+    // 14. CPUID-advertised features actually execute natively
+    add_intrusive(policy, scheduler, Some(described!("[*] Phase 2.11: CPUID Claims vs Instruction Behavior")), "cpuid_claims::check_cpuid_claims", detectors::cpuid_claims::check_cpuid_claims);
+    
+    // 15. TSX/RTM: debug exceptions inside a transaction abort silently
+    add_intrusive(policy, scheduler, Some(described!("[*] Phase 2.12: TSX/RTM Transactional Trap Detection")), "rtm::check_rtm_transactions", detectors::rtm::check_rtm_transactions);
+    
+    // 16. CET shadow-stack state and return-address integrity
+    scheduler.add(Some(described!("[*] Phase 2.13: CET Shadow-Stack State")), "cet::check_cet_state", detectors::cet::check_cet_state);
+    
+    // 17. SYSCALL vs int 0x80 entry-path latency asymmetry
+    add_intrusive(policy, scheduler, Some(described!("[*] Phase 2.14: SYSCALL vs int 0x80 Asymmetry")), "syscall_paths::check_syscall_paths", detectors::syscall_paths::check_syscall_paths);
+    
+    // 18. Sampling profiler (PMI rate on our CPU)
+    scheduler.add(Some(described!("[*] Phase 2.15: Sampling-Profiler Interrupts")), "profiler::check_sampling_profiler", detectors::profiler::check_sampling_profiler);
+    
+    // 19. Per-thread checks on a registered worker (single-thread attach)
+    scheduler.add(Some(described!("[*] Phase 2.16: Per-Thread Checks")), "threads::scan_threads", check_worker_threads);
+    
+    // 20. Timer watchdog (stops seen as bunched timerfd expirations)
+    match watchdog {
+        Some(watchdog) => scheduler.add(Some(described!("[*] Phase 2.17: Timer Watchdog")), "watchdog::finish", move |e| watchdog.finish(e)),
+        None => scheduler.skip(Some(described!("[*] Phase 2.17: Timer Watchdog")), "watchdog::finish", "timerfd unavailable".to_string()),
+    }
+    
+    // 21. Remote time attestation (server clock vs local clocks)
+    match heartbeat {
+        Some(heartbeat) => scheduler.add(Some(described!("[*] Phase 2.18: Remote Time Attestation")), "attestation::finish", move |e| heartbeat.finish(e)),
+        None => scheduler.skip(Some(described!("[*] Phase 2.18: Remote Time Attestation")), "attestation::finish", "no time server configured".to_string()),
+    }
+    
+    // 22. Bait symbols (breakpoints on check_license / decrypt_key)
+    scheduler.add(Some(described!("[*] Phase 2.19: Bait Symbols")), "bait::check_bait_functions", detectors::bait::check_bait_functions);
+    
+    // 23. Frida / Gum: /proc/self/maps, then the loader's own list
+    scheduler.add(Some(described!("[*] Phase 2.20: Frida Detection")), "frida::check_frida_maps", detectors::frida::check_frida_maps);
+    scheduler.add(None, "frida::check_frida_loaded_objects", detectors::frida::check_frida_loaded_objects);
+    
+    // 24. Pin / DynamoRIO code caches
+    scheduler.add(Some(described!("[*] Phase 2.21: DBI Code-Cache Detection")), "dbi::check_dbi_code_cache", detectors::dbi::check_dbi_code_cache);
+    
+    // 25. Wine translation layer
+    scheduler.add(Some(described!("[*] Phase 2.22: Translation-Layer Detection")), "translation::check_wine", detectors::translation::check_wine);
+    
+    // 26. Remote debug stubs (gdbserver / lldb-server / IDA)
+    scheduler.add(Some(described!("[*] Phase 2.23: Remote Debug Stub Detection")), "remote_debug::check_remote_debug_stub", detectors::remote_debug::check_remote_debug_stub);
+    
+    // 27. Tracer fingerprint (strace/ltrace vs gdb/lldb) - before the ptrace
+    // detectors, whose evidence it scales
+    scheduler.add(Some(described!("[*] Phase 2.24: Tracer Fingerprinting")), "tracer_kind::check_tracer_kind", detectors::tracer_kind::check_tracer_kind);
+    
+    // 28. tracefs uprobes / kprobes
+    scheduler.add(Some(described!("[*] Phase 2.25: tracefs Probe Detection")), "tracefs::check_tracefs_probes", detectors::tracefs::check_tracefs_probes);
+    
+    // 29. perf / bpftrace observers (PMU contention, observer processes)
+    scheduler.add(Some(described!("[*] Phase 2.26: perf Observer Detection")), "perf_observer::check_perf_observers", detectors::perf_observer::check_perf_observers);
+    scheduler.add(None, "perf_observer::check_perf_fd_holders", detectors::perf_observer::check_perf_fd_holders);
+    
+    // 30. CPU semantics battery (segment prefixes, flag corners, NaN/denormal flags)
+    scheduler.add(Some(described!("[*] Phase 2.27: CPU Semantics Probes")), "cpu_semantics::check_cpu_semantics", detectors::cpu_semantics::check_cpu_semantics);
+    
+    // 31. CPU errata / stable undocumented behavior (Bochs, QEMU TCG)
+    scheduler.add(Some(described!("[*] Phase 2.28: CPU Errata Probes")), "cpu_errata::check_cpu_errata", detectors::cpu_errata::check_cpu_errata);
+    
+    // 32. QBDI (ExecBlock copies, virtual stack, signal-context RIP)
+    // Note: the RIP probe raises SIGUSR1 at ourselves, so this is intrusive.
+    add_intrusive(policy, scheduler, Some(described!("[*] Phase 2.29: QBDI Instrumentation Detection")), "qbdi::check_qbdi", detectors::qbdi::check_qbdi);
+    
+    // 33. coverage-instrumented rebuilds (profile / sancov / gcov / AFL)
+    scheduler.add(Some(described!("[*] Phase 2.30: Coverage Build Detection")), "coverage::check_coverage_build", detectors::coverage::check_coverage_build);
+    
+    // 34. symbolic / concolic execution (angr, SymCC)
+    scheduler.add(Some(described!("[*] Phase 2.31: Symbolic Execution Detection")), "symbolic::check_symbolic_execution", detectors::symbolic::check_symbolic_execution);
+    
+    // 35. userspace binary translators (SMC, W^X, code-page stores)
+    // Note: the W^X probe catches its own SIGSEGV, so this is intrusive.
+    add_intrusive(policy, scheduler, Some(described!("[*] Phase 2.32: Binary Translator Detection")), "translator::check_binary_translator", detectors::translator::check_binary_translator);
+    
+    // 36. whole-system analysis platforms (device jitter, RTC, branding)
+    scheduler.add(Some(described!("[*] Phase 2.33: Analysis Platform Detection")), "analysis_vm::check_analysis_vm", detectors::analysis_vm::check_analysis_vm);
+    
+    // 37. malware-sandbox artifacts (opt-in: --sandbox-checks)
+    add_opt_in(policy.sandbox_checks, "--sandbox-checks", scheduler, Some(described!("[*] Phase 2.34: Sandbox Artifact Detection")), "sandbox::check_sandbox_artifacts", detectors::sandbox::check_sandbox_artifacts);
+    add_opt_in(policy.sandbox_profile, "--sandbox-profile", scheduler, None, "sandbox::check_sandbox_profile", detectors::sandbox::check_sandbox_profile);
+    
+    // 38. LD_PRELOAD hooks on libc wrappers (syscall vs libc divergence)
+    scheduler.add(Some(described!("[*] Phase 2.35: libc Hook Detection")), "libc_hooks::check_libc_hooks", detectors::libc_hooks::check_libc_hooks);
+    
+    // 39. LD_AUDIT auditors (environment, namespaces, hidden objects)
+    scheduler.add(Some(described!("[*] Phase 2.36: rtld-audit Detection")), "rtld_audit::check_rtld_audit", detectors::rtld_audit::check_rtld_audit);
+    
+    // 40. analysis daemons anywhere on the host (opt-in: --host-scan)
+    add_opt_in(policy.host_scan, "--host-scan", scheduler, Some(described!("[*] Phase 2.37: Host-Wide Analysis Daemon Scan")), "host_daemons::check_host_daemons", detectors::host_daemons::check_host_daemons);
+    
+    // 41. started under gdb/lldb (LINES/COLUMNS, $_, terminal owner, signals)
+    scheduler.add(Some(described!("[*] Phase 2.38: Debugger Launch Fingerprinting")), "launch_env::check_launch_environment", detectors::launch_env::check_launch_environment);
+    
+    // 42. Check Syscall Filtering (seccomp sandboxes)
+    scheduler.add(Some(described!("[*] Phase 2.39: Syscall-Filter Sandbox Probes")), "syscall_filter::check_syscall_filter", detectors::syscall_filter::check_syscall_filter);
+    
+    // 43. Classify the Hypervisor (production vs analysis VM)
+    scheduler.add(Some(described!("[*] Phase 2.40: Hypervisor Classification")), "hypervisor::check_hypervisor", detectors::hypervisor::check_hypervisor);
+    
+    // 44. Check libc Prologues for Inline Hooks
+    scheduler.add(Some(described!("[*] Phase 2.41: Inline-Hook Detection")), "inline_hooks::check_inline_hooks", detectors::inline_hooks::check_inline_hooks);
+    
+    // 45. Scan /proc/self/maps for Injection Anomalies
+    scheduler.add(Some(described!("[*] Phase 2.42: Memory-Map Anomaly Scan")), "maps_anomaly::check_maps_anomalies", detectors::maps_anomaly::check_maps_anomalies);
+    
+    // 46. Check for Threads Injected by an Agent
+    scheduler.add(Some(described!("[*] Phase 2.43: Injected-Thread Detection")), "thread_inject::check_injected_threads", detectors::thread_inject::check_injected_threads);
+    
+    // 47. Inspect Descriptors Inherited from the Launcher
+    scheduler.add(Some(described!("[*] Phase 2.44: Inherited Descriptor Inspection")), "fd_table::check_fd_table", detectors::fd_table::check_fd_table);
+    
+    // 48. Compare direct and /proc/self/mem views of our text
+    scheduler.add(Some(described!("[*] Phase 2.45: Text Cross-View (/proc/self/mem)")), "mem_crossview::check_mem_crossview", detectors::mem_crossview::check_mem_crossview);
+    
+    // 49. Diff our loaded .text against the executable on disk
+    scheduler.add(Some(described!("[*] Phase 2.46: On-Disk vs In-Memory .text")), "text_diff::check_text_diff", detectors::text_diff::check_text_diff);
+    
+    // 50. Compare argv, exe and vector addresses with the kernel's view
+    scheduler.add(Some(described!("[*] Phase 2.47: Command-Line Cross-View")), "cmdline_view::check_cmdline_view", detectors::cmdline_view::check_cmdline_view);
+    
+    // 51. Classify every ancestor up to init
+    scheduler.add(Some(described!("[*] Phase 2.48: Ancestor Process Chain")), "ancestry::check_ancestry", detectors::ancestry::check_ancestry);
+    
+    // 52. Judge where a crash of ours would be captured
+    scheduler.add(Some(described!("[*] Phase 2.49: Core-Dump Capture Configuration")), "core_dump::check_core_dump", detectors::core_dump::check_core_dump);
+    
+    // 53. Late tracer attach (TracerPid vs startup snapshot; also re-run before the payload)
+    scheduler.add(Some(described!("[*] Phase 2.50: Mid-Run Tracer Attach")), "late_attach::check_late_attach", detectors::late_attach::check_late_attach);
+    
+    // 54. TSC invariance, RDTSCP TSC_AUX and cross-core skew
+    scheduler.add(Some(described!("[*] Phase 2.51: TSC Invariance & Cross-Core Skew (RDTSCP)")), "tsc_sync::check_tsc_sync", detectors::tsc_sync::check_tsc_sync);
+    
+    // 55. Cross-correlate TSC, vDSO and syscall clocks over a busy window
+    scheduler.add(Some(described!("[*] Phase 2.52: Multi-Clock Cross-Correlation")), "clock_xcheck::check_clock_xcheck", detectors::clock_xcheck::check_clock_xcheck);
+    
+    // 56. Counter thread on a second CPU as a clock RDTSC cannot virtualize
+    scheduler.add(Some(described!("[*] Phase 2.53: Counter-Thread Software Clock")), "counter_clock::check_counter_clock", detectors::counter_clock::check_counter_clock);
+    
+    // 57. Sleeps of several lengths checked against monotonic time and the TSC
+    scheduler.add(Some(described!("[*] Phase 2.54: Sleep-Skip / Time Acceleration")), "sleep_skip::check_sleep_skip", detectors::sleep_skip::check_sleep_skip);
+    
+    // 58. Retired instructions of a fixed loop (DBI expansion, rr holding the PMU)
+    scheduler.add(Some(described!("[*] Phase 2.55: Retired-Instruction Counter (PMU)")), "insn_count::check_insn_count", detectors::insn_count::check_insn_count);
+    
+    // 59. Intel PT / BTS / LBR already claimed by an external tracer
+    scheduler.add(Some(described!("[*] Phase 2.56: Hardware Branch Tracers (PT/BTS/LBR)")), "hw_trace::check_hw_trace", detectors::hw_trace::check_hw_trace);
+    
+    // 60. clock_gettime through libc, the vDSO and the raw syscall (single-path time fakers)
+    scheduler.add(Some(described!("[*] Phase 2.57: vDSO vs Syscall Clock Divergence")), "vdso_clock::check_vdso_clock", detectors::vdso_clock::check_vdso_clock);
+    
+    // 61. Thread states and unexplained oversleep sampled over the whole scan
+    match stop_history {
+        Some(monitor) => scheduler.add(Some(described!("[*] Phase 2.58: Stop/Continue History")), "stop_history::finish", move |e| monitor.finish(e)),
+        None => scheduler.skip(Some(described!("[*] Phase 2.58: Stop/Continue History")), "stop_history::finish", "sampler thread unavailable".to_string()),
+    }
+    
+    // 62. Process CPU time vs wall time around every slice so far (frozen at a breakpoint)
+    scheduler.add(Some(described!("[*] Phase 2.59: CPU-Time vs Wall-Time Beacon")), "cpu_beacon::check_cpu_beacon", detectors::cpu_beacon::check_cpu_beacon);
+    
+    // 63. Every other thread's wchan and syscall, read from a sibling (ptrace_stop, seccomp waits)
+    scheduler.add(Some(described!("[*] Phase 2.60: Per-Thread Wait Channels")), "task_wchan::check_task_wchan", detectors::task_wchan::check_task_wchan);
+    
+    // 64. Executable mappings of libc, ld-linux etc. against their files
+    scheduler.add(Some(described!("[*] Phase 2.61: Shared Library Breakpoints")), "lib_int3::check_lib_int3", detectors::lib_int3::check_lib_int3);
+    
+    // 65. Objects in dl_iterate_phdr against /proc/self/maps, both ways, by address
+    scheduler.add(Some(described!("[*] Phase 2.62: Loader List vs Maps")), "module_xview::check_module_xview", detectors::module_xview::check_module_xview);
+    
+    // 66. Frames recorded by return_probe!() in the detectors, checked against our binary and libc
+    scheduler.add(Some(described!("[*] Phase 2.63: Return-Address Probes")), "ret_probe::check_ret_probe", detectors::ret_probe::check_ret_probe);
+    
+    // 67. Every environment variable against the analysis-tool indicator table
+    scheduler.add(Some(described!("[*] Phase 2.64: Analysis Environment Variables")), "env_scan::check_env_scan", detectors::env_scan::check_env_scan);
+    
+    // 68. Loaded kernel modules by vendor (guest additions, tracing modules, rootkits)
+    scheduler.add(Some(described!("[*] Phase 2.65: Kernel Module Scan")), "kmod_scan::check_kmod_scan", detectors::kmod_scan::check_kmod_scan);
+    
+    // 69. Wall clock against monotonic time over the whole scan (last, to cover it all)
+    match clock_jump {
+        Some(monitor) => scheduler.add(Some(described!("[*] Phase 2.66: Wall-Clock Jump Monitor")), "clock_jump::finish", move |e| monitor.finish(e)),
+        None => scheduler.skip(Some(described!("[*] Phase 2.66: Wall-Clock Jump Monitor")), "clock_jump::finish", "sampler thread unavailable".to_string()),
+    }
+    
+    // ===================================================================
+    // PTRACE DETECTION (Run last - modifies process state)
+    // ===================================================================
+    
+    // 70. Check Ptrace (Baseline) - run last as PTRACE_TRACEME changes state
+    scheduler.add(Some(described!("[*] Phase 3: Ptrace Detection")), "ptrace::check_tracer_pid", detectors::ptrace::check_tracer_pid);
+    add_intrusive(policy, scheduler, None, "ptrace::check_ptrace", detectors::ptrace::check_ptrace);
+}
+
+/// Queue an intrusive detector only if the preset allows it
+fn add_intrusive(policy: &PolicyConfig, scheduler: &mut Scheduler, banner: Option<&'static str>, name: &'static str, detector: impl FnOnce(&mut DecisionEngine) + 'static) {
+    if policy.intrusive_probes {
+        scheduler.add(banner, name, detector);
+    } else {
+        scheduler.skip(banner, name, format!("{} preset runs non-intrusive detectors only", policy.preset.name()));
+    }
+}
+
+/// Queue an opt-in detector only if its flag was given
+fn add_opt_in(enabled: bool, flag: &str, scheduler: &mut Scheduler, banner: Option<&'static str>, name: &'static str, detector: impl FnOnce(&mut DecisionEngine) + 'static) {
+    if enabled {
+        scheduler.add(banner, name, detector);
+    } else {
+        scheduler.skip(banner, name, format!("opt-in check, enable with {}", flag));
+    }
+}
+
+/// `--interleaved`: the payload's own work in small units, one detector
+/// slice after each, so there is no single scan phase to skip
+fn interleaved_work(engine: &mut DecisionEngine, scheduler: &mut Scheduler) {
+    let mut acc: u64 = 0x9e37_79b9_7f4a_7c15;
+    while scheduler.pending() > 0 {
+        for i in 0..100_000u64 {
+            acc = std::hint::black_box(acc.rotate_left(5) ^ i);
+        }
+        detect_slice!(scheduler, engine);
+    }
+    std::hint::black_box(acc);
+}
+
+/// Response callback for `CallbackOnly` presets. An embedder would hand the
+/// verdict to its own logic; the demo just records it.
+fn report_verdict(verdict: Verdict) {
+    diag!("[RESPONSE] Verdict {:?} delivered to callback", verdict);
+}
+
+/// Demonstrate per-thread hooks: a registered worker checkpoints between
+/// units of work while this thread scans it from outside
+fn check_worker_threads(engine: &mut DecisionEngine) {
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+    let worker = std::thread::spawn(move || {
+        let guard = engine::threads::register_thread("payload-worker");
+        for _ in 0..3 {
+            guard.checkpoint();
         }
+        let _ = ready_tx.send(());
+        let _ = done_rx.recv();
+    });
+    let _ = ready_rx.recv();
+    let scanned = engine::threads::scan_threads(engine);
+    banner!("    Scanned {} registered thread(s)", scanned);
+    let _ = done_tx.send(());
+    let _ = worker.join();
+}
+
+/// Write the feature vector to `out` (a CSV file, or "-" for stdout)
+fn export_features(engine: &DecisionEngine, out: &str, silence: Option<&SilencedOutput>) {
+    let fv = engine.feature_vector();
+    if out == "-" {
+        // Past the silence guard, so nothing else is interleaved
+        let written = match silence.and_then(SilencedOutput::original_stdout) {
+            Some(mut stdout) => fv.write_csv(&mut stdout),
+            None => fv.write_csv(&mut std::io::stdout()),
+        };
+        if let Err(e) = written {
+            diag!("[FEATURES] Failed to write to stdout: {}", e);
+        }
+    } else if let Err(e) = fv.append_csv(std::path::Path::new(out)) {
+        diag!("[FEATURES] Failed to write {}: {}", out, e);
+    } else {
+        diag!("[FEATURES] Appended feature vector to {}", out);
     }
 }
 
-fn payload() {
-    println!("[+] SECRET: The answer is 42.");
+/// Run the payload only if the sealed verdict survives re-verification
+fn run_payload(engine: &mut DecisionEngine, seal: &engine::reverify::Seal, secret: &mut SecretCell<String>) {
+    // A debugger attached while the verdict was being reported
+    detectors::late_attach::check_late_attach(engine);
+    if engine::reverify::guarded_call(engine, seal, "payload", || payload(secret)).is_none() {
+        banner!("\n[!] Environment changed since the verdict. Access denied.");
+    }
+}
+
+fn payload(secret: &mut SecretCell<String>) {
+    // Decrypted only for the duration of the print
+    if let Err(e) = secret.with(|s| println!("[+] SECRET: {}", s)) {
+        diag!("[SECRET] {}", e);
+    }
     println!("[+] Phase 2 research framework operational.");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn scheduled(preset: Preset) -> BTreeSet<&'static str> {
+        let mut scheduler = Scheduler::new(false);
+        schedule_detectors(&preset.config(), &mut scheduler, None, None, None, None);
+        scheduler.scheduled().collect()
+    }
+
+    #[test]
+    fn test_stealthy_schedules_no_intrusive_detectors() {
+        let balanced = scheduled(Preset::Balanced);
+        let stealthy = scheduled(Preset::Stealthy);
+        assert!(stealthy.is_subset(&balanced));
+        let skipped: Vec<&str> = balanced.difference(&stealthy).copied().collect();
+        assert_eq!(skipped, vec![
+            "cpuid_claims::check_cpuid_claims",
+            "hardware_bp::check_hardware_breakpoints",
+            "illegal_insn::check_illegal_instruction_semantics",
+            "ptrace::check_ptrace",
+            "qbdi::check_qbdi",
+            "record_replay::check_signal_determinism",
+            "rtm::check_rtm_transactions",
+            "syscall_paths::check_syscall_paths",
+            "translator::check_binary_translator",
+            "trap_flag::check_trap_flag",
+            "xstate::check_xstate_consistency",
+        ]);
+    }
+}
@@ -0,0 +1,16 @@
+//! `--features -` hands stdout to the feature CSV alone
+
+use std::process::Command;
+
+#[test]
+fn test_features_to_stdout_is_only_csv() {
+    let output = Command::new(env!("CARGO_BIN_EXE_anti_debug_framework"))
+        .args(["--features", "-"])
+        .output()
+        .expect("run anti_debug_framework");
+    let stdout = String::from_utf8(output.stdout).expect("stdout is UTF-8");
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "stdout: {:?}", stdout);
+    assert!(lines[0].starts_with("rdtsc_overhead_mean,"), "header: {:?}", lines[0]);
+    assert_eq!(lines[1].split(',').count(), lines[0].split(',').count());
+}
@@ -0,0 +1,58 @@
+#!/bin/bash
+# ============================================================================
+# Stealth Build String Check
+# ============================================================================
+# Builds with --features stealth and fails if the binary still carries
+# detector text: a list of known evidence/description strings, plus every
+# literal run of the reason!/described! texts in the build's code table.
+
+set -e
+
+BINARY="./target/release/anti_debug_framework"
+
+cargo build --release --features stealth
+
+# The table of the build just made (OUT_DIR of the newest build script run)
+TABLE=$(ls -t ./target/release/build/anti_debug_framework-*/out/diag_codes.tsv | head -1)
+if [ ! -f "$TABLE" ]; then
+    echo "Code table not found."
+    exit 1
+fi
+
+WORK=$(mktemp -d)
+trap 'rm -rf "$WORK"' EXIT
+
+strings -n 8 "$BINARY" > "$WORK/strings"
+
+cat > "$WORK/known" <<'EOF'
+TracerPid is non-zero
+Frida component mapped
+listening on frida-server
+Something listens on Ghidra
+QBDI mapped
+Userspace binary translator
+panicked and was skipped
+Likely debugger breakpoints
+Heavy timing anomaly but no tracer
+Hardware breakpoints armed in DR7
+Sleeps are fast-forwarded
+Statistical Timing Analysis
+EOF
+
+# Text between the {} placeholders of R (reason!) and # (described!) rows
+grep -E "^(R|#)" "$TABLE" | cut -f3 | sed 's/{[^}]*}/\n/g' \
+    | awk 'length($0) >= 16 && / [A-Za-z]/' | sort -u > "$WORK/coded"
+
+FOUND=0
+for LIST in known coded; do
+    if grep -o -F -f "$WORK/$LIST" "$WORK/strings" > "$WORK/hits"; then
+        echo "Detector strings left in the stealth binary ($LIST):"
+        sort -u "$WORK/hits" | sed 's/^/  /'
+        FOUND=1
+    fi
+done
+
+if [ "$FOUND" -ne 0 ]; then
+    exit 1
+fi
+echo "No detector strings in $BINARY ($(wc -l < "$WORK/coded") coded texts checked)"